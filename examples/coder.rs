@@ -240,7 +240,7 @@ Otherwise, follow debugging best practices:
 #[instrument(name = "coder_example_main")] // Instrument main if desired
 async fn main() -> Result<()> {
     // --- Setup ---
-    let _otel = telemetry::init_tracing_subscriber();
+    let _otel = telemetry::init_tracing_subscriber("error");
     let config = hal::mcp::config::McpConfig::read_config("mcp.json").await?;
     let mcp_manager = config.create_manager().await?;
     let (toolset, tool_defs) = mcp_manager.get_tool_set_and_defs().await?;
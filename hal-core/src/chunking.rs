@@ -0,0 +1,1442 @@
+//! # Markdown Chunking Module
+//!
+//! This module provides sophisticated text chunking functionality specifically optimized
+//! for Markdown content. It intelligently splits documents into semantically meaningful
+//! segments while preserving important structural elements.
+//!
+//! ## Key Components
+//!
+//! - `ChunkOptions`: Controls the chunking behavior (size and overlap)
+//! - `TextChunk`: Represents a segment of text with metadata and position information
+//! - `chunk_markdown`: Primary function for splitting Markdown into chunks
+//! - `chunk_markdown_iter`: The same chunks produced lazily, for very large documents
+//! - `count_words`: Word count that also works for languages written without spaces
+//! - `render_chunks`: Stable plain-text rendering of chunks for inspection and snapshot tests
+//!
+//! ## Features
+//!
+//! - Structure-aware chunking that respects:
+//!   - Paragraph boundaries
+//!   - Code block integrity
+//!   - Heading hierarchies
+//!   - Document section boundaries
+//! - Configurable chunk sizes with overlap for context continuity
+//! - Undersized chunks merged into their neighbors, so short notes are never dropped
+//! - Metadata preservation (headings, positions) for improved retrieval
+//! - UTF-8 safe text handling, with Unicode word segmentation so chunk sizes are
+//!   meaningful for Chinese and Japanese text
+//!
+//! ## Chunking Strategy
+//!
+//! The chunker uses a sophisticated algorithm that:
+//! 1. Parses Markdown with pulldown_cmark to understand document structure
+//! 2. Attempts to split at natural boundaries (paragraphs, code blocks, etc.)
+//! 3. Maintains content integrity by avoiding splits in the middle of important elements
+//! 4. Associates chunks with their parent headings for context preservation
+//!
+//! This structure-aware chunking is critical for RAG quality as it ensures that
+//! the indexed content maintains semantic coherence and proper context.
+
+use std::collections::VecDeque;
+
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+use serde::Serialize;
+use tracing::{debug, instrument};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Configuration for chunking text
+#[derive(Debug, Clone)]
+pub struct ChunkOptions {
+    /// Target size of each chunk in words
+    pub target_chunk_size: usize,
+
+    /// Size of overlap between chunks in words
+    pub overlap_size: usize,
+
+    /// Overlap between chunks in whole sentences instead of words, so every chunk after
+    /// the first starts at the beginning of a sentence
+    pub overlap_sentences: Option<usize>,
+
+    /// Chunks with fewer characters are merged into a neighboring chunk
+    pub min_chunk_chars: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            target_chunk_size: 500,
+            overlap_size: 50,
+            overlap_sentences: None,
+            min_chunk_chars: 100,
+        }
+    }
+}
+
+/// A chunk of text with metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct TextChunk {
+    /// The text of the chunk
+    pub text: String,
+
+    /// The position of the chunk in the original document
+    pub position: usize,
+
+    /// The heading of the chunk
+    pub heading: Option<String>,
+
+    /// The H1–H3 headings enclosing the chunk, outermost first
+    pub heading_path: Vec<String>,
+}
+
+/// Format a heading path as a breadcrumb, e.g. `Guide > Setup > Install`
+///
+/// Returns `None` when the path is empty.
+pub fn format_breadcrumb(heading_path: &[String]) -> Option<String> {
+    if heading_path.is_empty() {
+        None
+    } else {
+        Some(heading_path.join(" > "))
+    }
+}
+
+/// Collect the heading texts of a tracked heading path
+fn heading_texts(heading_path: &[(usize, String)]) -> Vec<String> {
+    heading_path.iter().map(|(_, text)| text.clone()).collect()
+}
+
+/// Chunk Markdown text into smaller pieces
+/// The chunker tries to preserve paragraph boundaries and code block boundaries.
+/// It builds a chunk out of a list of words. It tries it track important elements by their position in the list of words.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown text to chunk
+/// * `options` - Chunking options
+///
+/// # Returns
+///
+/// A vector of text chunks
+#[instrument(skip(markdown))]
+pub fn chunk_markdown(markdown: &str, options: &ChunkOptions) -> Vec<TextChunk> {
+    debug!("Chunking Markdown text with options: {:?}", options);
+
+    let chunks: Vec<TextChunk> = chunk_markdown_iter(markdown, options).collect();
+    debug!("Created {} chunks", chunks.len());
+    chunks
+}
+
+/// Chunk Markdown text lazily, producing each chunk as soon as it is complete
+///
+/// Produces the same chunks as `chunk_markdown`, but only holds the chunk being built and
+/// the one waiting for a possible merge in memory, so very large documents can be
+/// processed with bounded memory and their chunks pipelined into embedding.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown text to chunk
+/// * `options` - Chunking options
+///
+/// # Returns
+///
+/// An iterator over the text chunks
+pub fn chunk_markdown_iter<'a>(
+    markdown: &'a str,
+    options: &ChunkOptions,
+) -> impl Iterator<Item = TextChunk> + 'a {
+    MergeSmallChunks {
+        chunks: MarkdownChunks::new(markdown, options.clone()),
+        min_chars: options.min_chunk_chars,
+        previous: None,
+        pending: None,
+        position: 0,
+    }
+}
+
+/// Splits Markdown into chunks while its events are parsed
+struct MarkdownChunks<'a> {
+    /// The Markdown parser
+    parser: Parser<'a>,
+
+    /// Chunking options
+    options: ChunkOptions,
+
+    /// Chunks completed but not yet returned
+    ready: VecDeque<TextChunk>,
+
+    /// Whether the parser is exhausted and the final chunk was added
+    finished: bool,
+
+    /// The current heading
+    current_heading: Option<String>,
+
+    /// The enclosing headings as (level, text) pairs
+    heading_path: Vec<(usize, String)>,
+
+    /// The words of the current chunk
+    current_chunk: Vec<String>,
+
+    /// The position of the next chunk
+    position: usize,
+
+    /// Whether we're inside a code block
+    in_code_block: bool,
+
+    /// Paragraph boundaries in the current chunk
+    paragraph_breaks: Vec<usize>,
+
+    /// Code block boundaries in the current chunk
+    code_block_boundaries: Vec<usize>,
+}
+
+impl<'a> MarkdownChunks<'a> {
+    fn new(markdown: &'a str, options: ChunkOptions) -> Self {
+        Self {
+            parser: Parser::new(markdown),
+            options,
+            ready: VecDeque::new(),
+            finished: false,
+            current_heading: None,
+            heading_path: Vec::new(),
+            current_chunk: Vec::new(),
+            position: 0,
+            in_code_block: false,
+            paragraph_breaks: Vec::new(),
+            code_block_boundaries: Vec::new(),
+        }
+    }
+
+    /// Add a parsed event to the current chunk, completing chunks as they fill up
+    fn handle(&mut self, event: Event<'a>) {
+        match &event {
+            Event::Text(text) => {
+                let words = split_words(text);
+                // Add the text to the current chunk
+                self.current_chunk.extend(words);
+
+                // Check if the chunk is large enough
+                if self.current_chunk.len() >= self.options.target_chunk_size {
+                    // Find a good boundary to split at
+                    let split_point = find_split_point(
+                        &self.current_chunk,
+                        self.options.target_chunk_size,
+                        &self.paragraph_breaks,
+                        &self.code_block_boundaries,
+                        self.in_code_block,
+                    );
+
+                    let split_text: String = self
+                        .current_chunk
+                        .iter()
+                        .take(split_point)
+                        .cloned()
+                        .collect();
+
+                    self.ready.push_back(TextChunk {
+                        text: split_text.trim().to_string(),
+                        position: self.position,
+                        heading: self.current_heading.clone(),
+                        heading_path: heading_texts(&self.heading_path),
+                    });
+                    self.position += 1;
+
+                    // Start a new chunk with overlap
+                    let overlap_start = match self.options.overlap_sentences {
+                        Some(sentences) => {
+                            sentence_overlap_start(&self.current_chunk, split_point, sentences)
+                        }
+                        None => split_point.saturating_sub(self.options.overlap_size),
+                    };
+                    self.current_chunk = std::mem::take(&mut self.current_chunk)
+                        .into_iter()
+                        .skip(overlap_start)
+                        .collect();
+
+                    // Adjust the paragraph and code block boundaries
+                    adjust_boundaries(&mut self.paragraph_breaks, overlap_start, split_point);
+                    adjust_boundaries(&mut self.code_block_boundaries, overlap_start, split_point);
+                }
+
+                // If we're capturing a heading and we get text, store it
+                if let Some(heading) = self.current_heading.as_mut().filter(|h| h.is_empty()) {
+                    *heading = text.to_string();
+                }
+                if let Some((_, heading)) =
+                    self.heading_path.last_mut().filter(|(_, h)| h.is_empty())
+                {
+                    *heading = text.to_string();
+                }
+            }
+            Event::Start(tag) => {
+                // Check if this is a heading
+                if let Tag::Heading {
+                    level,
+                    id: _,
+                    classes: _,
+                    attrs: _,
+                } = tag
+                {
+                    // Extract heading text (will be populated in the next events)
+                    if matches!(
+                        level,
+                        HeadingLevel::H1 | HeadingLevel::H2 | HeadingLevel::H3
+                    ) {
+                        // Only track headings up to level 3
+                        // If we have a current chunk, add it to the chunks
+                        if !self.current_chunk.is_empty() {
+                            self.ready.push_back(TextChunk {
+                                text: self.current_chunk.join(""),
+                                position: self.position,
+                                heading: self.current_heading.clone(),
+                                heading_path: heading_texts(&self.heading_path),
+                            });
+                            self.position += 1;
+                            self.current_chunk.clear();
+                            self.paragraph_breaks.clear();
+                            self.code_block_boundaries.clear();
+                        }
+
+                        // We'll capture the heading text in the next Text event
+                        self.current_heading = Some(String::new());
+
+                        // Pop headings at the same or a deeper level before descending
+                        let level = *level as usize;
+                        self.heading_path.retain(|(parent, _)| *parent < level);
+                        self.heading_path.push((level, String::new()));
+                    }
+                } else if let Tag::CodeBlock(_kind) = tag {
+                    // Mark the start of a code block
+                    self.in_code_block = true;
+                    self.code_block_boundaries.push(self.current_chunk.len());
+
+                    // Add a marker for the code block start
+                    if !self.current_chunk.is_empty()
+                        && !self
+                            .current_chunk
+                            .last()
+                            .map(|c| c.ends_with('\n'))
+                            .unwrap_or(false)
+                    {
+                        self.current_chunk.push("\n".to_string());
+                    }
+                } else if let Tag::Paragraph = tag {
+                    // Mark the start of a paragraph
+                    if !self.current_chunk.is_empty()
+                        && !self
+                            .current_chunk
+                            .last()
+                            .map(|c| c.ends_with('\n'))
+                            .unwrap_or(false)
+                    {
+                        self.current_chunk.push("\n".to_string());
+                    }
+                } else if !self.current_chunk.is_empty()
+                    && !self
+                        .current_chunk
+                        .last()
+                        .map(|c| c.ends_with(['\n', ' ']) || c.ends_with(' '))
+                        .unwrap_or(false)
+                {
+                    self.current_chunk.push(" ".to_string());
+                }
+            }
+            Event::End(tag) => {
+                if let TagEnd::CodeBlock = tag {
+                    // Mark the end of a code block
+                    self.in_code_block = false;
+                    self.code_block_boundaries.push(self.current_chunk.len());
+
+                    // Add a marker for the code block end
+                    if !self.current_chunk.is_empty()
+                        && !self
+                            .current_chunk
+                            .last()
+                            .map(|c| c.ends_with('\n'))
+                            .unwrap_or(false)
+                    {
+                        self.current_chunk.push("\n".to_string());
+                    }
+                } else if let TagEnd::Paragraph = tag {
+                    // Mark the end of a paragraph
+                    self.paragraph_breaks.push(self.current_chunk.len());
+
+                    // Add a newline after paragraphs
+                    if !self
+                        .current_chunk
+                        .last()
+                        .map(|c| c.ends_with('\n'))
+                        .unwrap_or(false)
+                    {
+                        self.current_chunk.push("\n".to_string());
+                    }
+                    self.current_chunk.push("\n".to_string());
+                } else if let TagEnd::Heading(level) = tag
+                    && matches!(
+                        level,
+                        HeadingLevel::H1 | HeadingLevel::H2 | HeadingLevel::H3
+                    )
+                    && self.current_heading.is_some()
+                {
+                    // We've captured the heading text in previous Text events
+                    // Add a newline after headings
+                    if !self
+                        .current_chunk
+                        .last()
+                        .map(|c| c.ends_with('\n'))
+                        .unwrap_or(false)
+                    {
+                        self.current_chunk.push("\n".to_string());
+                    }
+                }
+            }
+            Event::Code(code) => {
+                // Inline code
+                let code = code.clone().into_string();
+                self.current_chunk.push("`".to_string());
+                self.current_chunk.push(code);
+                self.current_chunk.push("`".to_string());
+            }
+            Event::SoftBreak => {
+                self.current_chunk.push(" ".to_string());
+            }
+            Event::HardBreak => {
+                self.current_chunk.push("\n".to_string());
+            }
+            _ => {
+                // Add a space to separate elements
+                if !self.current_chunk.is_empty()
+                    && !self
+                        .current_chunk
+                        .last()
+                        .map(|c| c.ends_with('\n') || c.ends_with(' '))
+                        .unwrap_or(false)
+                {
+                    self.current_chunk.push(" ".to_string());
+                }
+            }
+        }
+    }
+
+    /// Add the final chunk if it's not empty
+    fn finish(&mut self) {
+        if !self.current_chunk.is_empty() {
+            self.ready.push_back(TextChunk {
+                text: self.current_chunk.join("").trim().to_string(),
+                position: self.position,
+                heading: self.current_heading.take(),
+                heading_path: heading_texts(&self.heading_path),
+            });
+            self.current_chunk.clear();
+        }
+        self.finished = true;
+    }
+}
+
+impl Iterator for MarkdownChunks<'_> {
+    type Item = TextChunk;
+
+    fn next(&mut self) -> Option<TextChunk> {
+        loop {
+            if let Some(chunk) = self.ready.pop_front() {
+                return Some(chunk);
+            }
+            if self.finished {
+                return None;
+            }
+            match self.parser.next() {
+                Some(event) => self.handle(event),
+                None => self.finish(),
+            }
+        }
+    }
+}
+
+/// Merges chunks shorter than `min_chars` into a neighboring chunk
+///
+/// A short chunk is appended to the previous chunk of the same section, such as a
+/// closing note, or else prepended to the next chunk, such as a heading directly
+/// followed by a subheading. Positions are renumbered as chunks are returned, so no
+/// content is dropped and positions stay consecutive.
+struct MergeSmallChunks<I> {
+    /// The chunks in document order
+    chunks: I,
+
+    /// The fewest characters a chunk may have on its own
+    min_chars: usize,
+
+    /// The latest chunk long enough to stand alone, held back while short chunks may
+    /// still be appended to it
+    previous: Option<TextChunk>,
+
+    /// A short chunk waiting to be prepended to the next chunk
+    pending: Option<TextChunk>,
+
+    /// The position of the next chunk returned
+    position: usize,
+}
+
+impl<I> MergeSmallChunks<I> {
+    /// Number a chunk with the next position
+    fn number(&mut self, mut chunk: TextChunk) -> TextChunk {
+        chunk.position = self.position;
+        self.position += 1;
+        chunk
+    }
+}
+
+/// Join two chunk texts with a paragraph break
+fn join_texts(first: &str, second: &str) -> String {
+    format!("{}\n\n{}", first.trim_end(), second)
+}
+
+impl<I: Iterator<Item = TextChunk>> Iterator for MergeSmallChunks<I> {
+    type Item = TextChunk;
+
+    fn next(&mut self) -> Option<TextChunk> {
+        while let Some(mut chunk) = self.chunks.next() {
+            if chunk.text.is_empty() {
+                continue;
+            }
+            if let Some(short) = self.pending.take() {
+                chunk.text = join_texts(&short.text, &chunk.text);
+            }
+            if chunk.text.chars().count() >= self.min_chars {
+                if let Some(previous) = self.previous.replace(chunk) {
+                    return Some(self.number(previous));
+                }
+                continue;
+            }
+            match &mut self.previous {
+                Some(previous) if previous.heading_path == chunk.heading_path => {
+                    previous.text = join_texts(&previous.text, &chunk.text);
+                }
+                _ => self.pending = Some(chunk),
+            }
+        }
+
+        if let Some(short) = self.pending.take() {
+            match &mut self.previous {
+                Some(previous) => previous.text = join_texts(&previous.text, &short.text),
+                None => self.previous = Some(short),
+            }
+        }
+        let last = self.previous.take()?;
+        Some(self.number(last))
+    }
+}
+
+/// Render chunks into a stable plain-text form
+///
+/// Each chunk is written as a header line with its index, position, word count and
+/// character count, followed by its heading and its full text. The output is
+/// deterministic for a given input and options, which makes it suitable for
+/// snapshot tests and for attaching to chunking bug reports.
+///
+/// # Arguments
+///
+/// * `chunks` - The chunks to render
+///
+/// # Returns
+///
+/// The rendered chunks
+pub fn render_chunks(chunks: &[TextChunk]) -> String {
+    let mut output = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        output.push_str(&format!(
+            "--- chunk {} (position {}, {} words, {} chars) ---\n",
+            i,
+            chunk.position,
+            count_words(&chunk.text),
+            chunk.text.chars().count()
+        ));
+        output.push_str(&format!(
+            "heading: {}\n",
+            chunk.heading.as_deref().unwrap_or("<none>")
+        ));
+        output.push_str(&format!(
+            "path: {}\n",
+            format_breadcrumb(&chunk.heading_path).unwrap_or_else(|| "<none>".to_string())
+        ));
+        output.push_str(&chunk.text);
+        output.push_str("\n\n");
+    }
+    output
+}
+
+/// Find an appropriate split point for a chunk
+///
+/// This function tries to find a natural boundary to split the text at,
+/// respecting code blocks and paragraphs.
+///
+/// # Arguments
+///
+/// * `text` - The text to split
+/// * `target_size` - The target size for the chunk
+/// * `paragraph_breaks` - Positions of paragraph breaks in the text
+/// * `code_block_boundaries` - Positions of code block boundaries in the text
+/// * `in_code_block` - Whether we're currently inside a code block
+///
+/// # Returns
+///
+/// The position to split the text at
+fn find_split_point(
+    text: &[String],
+    target_size: usize,
+    paragraph_breaks: &[usize],
+    code_block_boundaries: &[usize],
+    in_code_block: bool,
+) -> usize {
+    let words: Vec<String> = text.iter().map(|s| s.to_string()).collect();
+    let words_len = words.len();
+
+    // If we're in a code block, try to find the end of it
+    if in_code_block {
+        // Find the next code block boundary after the target size
+        for &pos in code_block_boundaries {
+            if pos > target_size && pos < words_len {
+                return pos;
+            }
+        }
+    }
+
+    // Try to split at a paragraph break
+    for &pos in paragraph_breaks.iter().rev() {
+        // Only use paragraph breaks that are at least 30% of the target size
+        if pos > target_size * 3 / 10 && pos < words_len {
+            return pos;
+        }
+    }
+
+    // Try to split at a code block boundary
+    for &pos in code_block_boundaries.iter().rev() {
+        // Only use code block boundaries that are at least 30% of the target size
+        if pos > target_size * 3 / 10 && pos < words_len {
+            return pos;
+        }
+    }
+
+    let sentence_pos = text.iter().take(target_size + 200).rposition(|word| {
+        word.ends_with(". ")
+            || word.ends_with("! ")
+            || word.ends_with("? ")
+            || word.trim_end().ends_with(CJK_SENTENCE_ENDINGS)
+    });
+
+    // Split after the word ending the sentence, so the chunk ends with it
+    if let Some(pos) = sentence_pos {
+        return pos + 1;
+    }
+
+    // If all else fails, split at the target word count
+    std::cmp::min(words_len, target_size)
+}
+
+/// Sentence-ending punctuation of Chinese and Japanese, which is not followed by a space
+const CJK_SENTENCE_ENDINGS: [char; 3] = ['\u{3002}', '\u{ff01}', '\u{ff1f}'];
+
+/// Split text into words, each keeping the whitespace that follows it
+///
+/// Words follow Unicode word boundaries, with punctuation kept on the word before it.
+/// Chinese and Japanese are written without spaces, so each ideograph, hiragana
+/// character or katakana run counts as a word of its own; otherwise a whole paragraph
+/// would be a single word and target chunk sizes would be meaningless.
+///
+/// # Arguments
+///
+/// * `text` - The text to split
+///
+/// # Returns
+///
+/// The words, which concatenate back to `text`
+fn split_words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for segment in text.split_word_bounds() {
+        let is_word = segment.chars().any(char::is_alphanumeric);
+        match words.last_mut() {
+            Some(word) if segment.trim().is_empty() => word.push_str(segment),
+            Some(word)
+                if !word.ends_with(char::is_whitespace)
+                    && !(is_word && (segment.starts_with(is_cjk) || word.ends_with(is_cjk))) =>
+            {
+                word.push_str(segment)
+            }
+            _ => words.push(segment.to_string()),
+        }
+    }
+    words
+}
+
+/// Whether a character belongs to a script written without spaces between words
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        // Hiragana, Katakana and Katakana phonetic extensions
+        '\u{3040}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' | '\u{ff66}'..='\u{ff9f}'
+        // CJK unified and compatibility ideographs, including the extension planes
+        | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2ffff}'
+    )
+}
+
+/// Count the words in a text
+///
+/// Unlike counting whitespace-separated words, this counts each Chinese or Japanese
+/// word the way the chunker does when measuring `target_chunk_size`.
+///
+/// # Arguments
+///
+/// * `text` - The text to count the words of
+///
+/// # Returns
+///
+/// The number of words
+pub fn count_words(text: &str) -> usize {
+    split_words(text)
+        .iter()
+        .filter(|word| !word.trim().is_empty())
+        .count()
+}
+
+/// Find where the overlap of the next chunk starts when measured in sentences
+///
+/// # Arguments
+///
+/// * `words` - The words of the current chunk
+/// * `split_point` - Where the current chunk ends
+/// * `sentences` - Number of sentences before the split point to repeat
+///
+/// # Returns
+///
+/// The position of the first word of the earliest repeated sentence. When the chunk
+/// holds fewer sentences, every sentence but the first is repeated, and nothing when it
+/// is a single sentence, so the next chunk always moves forward.
+fn sentence_overlap_start(words: &[String], split_point: usize, sentences: usize) -> usize {
+    let split_point = split_point.min(words.len());
+    if sentences == 0 {
+        return split_point;
+    }
+    let starts: Vec<usize> = (1..split_point)
+        .filter(|&i| starts_sentence(words, i))
+        .collect();
+    match starts.len().checked_sub(sentences) {
+        Some(index) => starts[index],
+        None => starts.first().copied().unwrap_or(split_point),
+    }
+}
+
+/// Whether a word starts a sentence, after sentence-ending punctuation or a line break
+///
+/// Whitespace-only words, such as soft breaks and paragraph markers, are skipped when
+/// looking for the previous word.
+fn starts_sentence(words: &[String], i: usize) -> bool {
+    if words[i].trim().is_empty() {
+        return false;
+    }
+    let mut separated = false;
+    for word in words[..i].iter().rev() {
+        let trimmed = word.trim_end();
+        if word.contains('\n') {
+            return true;
+        }
+        separated |= trimmed.len() < word.len();
+        if !trimmed.is_empty() {
+            let trimmed = trimmed.trim_end_matches([
+                '"', '\'', ')', ']', '\u{201d}', '\u{2019}', '\u{300d}', '\u{300f}',
+            ]);
+            return (separated && trimmed.ends_with(['.', '!', '?']))
+                || trimmed.ends_with(CJK_SENTENCE_ENDINGS);
+        }
+    }
+    false
+}
+
+/// Adjust boundary positions after removing text
+///
+/// # Arguments
+///
+/// * `boundaries` - List of boundary positions to adjust
+/// * `start` - Start position of removed text
+/// * `end` - End position of removed text
+fn adjust_boundaries(boundaries: &mut Vec<usize>, start: usize, end: usize) {
+    let shift = end - start;
+
+    // Remove boundaries that were in the removed section
+    boundaries.retain(|&pos| pos < start || pos >= end);
+
+    // Adjust remaining boundaries
+    for pos in boundaries.iter_mut() {
+        if *pos >= end {
+            *pos -= shift;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_character_chunking() {
+        let text = "Hello, 世界! This is a test with UTF-8 characters. 你好，世界！";
+        let options = ChunkOptions {
+            target_chunk_size: 20,
+            overlap_size: 5,
+            overlap_sentences: None,
+            min_chunk_chars: 0,
+        };
+
+        let chunks = chunk_markdown(text, &options);
+
+        // Verify that chunks are created and contain valid UTF-8
+        assert!(!chunks.is_empty());
+        for chunk in &chunks {
+            // Verify each chunk contains valid UTF-8
+            assert!(chunk.text.chars().all(|c| c.len_utf8() > 0));
+
+            // Verify chunk boundaries don't split UTF-8 characters
+            String::from_utf8(chunk.text.as_bytes().to_vec()).unwrap();
+        }
+
+        // Verify that the chunks can be rejoined without losing characters
+        let total_chars = text.chars().count();
+        let chunks_chars: usize = chunks.iter().map(|c| c.text.chars().count()).sum();
+        assert!(
+            chunks_chars >= total_chars,
+            "All characters should be preserved in chunks"
+        );
+    }
+
+    /// This test demonstrates how the chunk_markdown function works with different types of markdown content.
+    /// It shows how the function respects code blocks, paragraphs, and headings when chunking text.
+    #[test]
+    fn test_chunk_markdown_illustrative() {
+        // Arrange: Create a sample markdown document with various elements
+        let markdown = r#"# Main Heading
+
+This is the first paragraph with some text. It contains a few sentences
+that should be kept together when chunking. The chunker should try to respect
+paragraph boundaries.
+
+## Second Level Heading
+
+This is another paragraph with different content. It should be associated
+with the second level heading.
+
+```rust
+// This is a code block
+fn example_function() -> Result<(), Error> {
+    println!("This code block should not be split in the middle");
+    println!("It should be kept intact if possible");
+    Ok(())
+}
+```
+
+### Third Level Heading
+
+- List item 1
+- List item 2
+- List item 3
+
+This is a paragraph after the list. It should be treated as a separate chunk
+from the list above.
+
+#### Fourth Level Heading
+
+This heading is level 4, so it might not be tracked as a main heading.
+
+```python
+# Another code block in a different language
+def another_example():
+    print("This should also be kept intact")
+    return True
+```
+
+Final paragraph with some concluding text."#;
+
+        // Create chunk options with a small target size to force multiple chunks
+        let options = ChunkOptions {
+            target_chunk_size: 200, // Small size to force multiple chunks
+            overlap_size: 50,       // Reasonable overlap
+            overlap_sentences: None,
+            min_chunk_chars: 0,
+        };
+
+        // Act: Chunk the markdown
+        let chunks = chunk_markdown(markdown, &options);
+
+        // Assert: Verify the chunks are created correctly
+        assert!(!chunks.is_empty(), "Should have created at least one chunk");
+
+        // Print the chunks for illustration
+        println!("Created {} chunks:", chunks.len());
+        for (i, chunk) in chunks.iter().enumerate() {
+            println!("\nChunk {}:", i + 1);
+            println!("Heading: {:?}", chunk.heading);
+            println!("Position: {}", chunk.position);
+            println!("Text length: {} characters", chunk.text.len());
+            println!("Text preview: {}", preview_text(&chunk.text, 100));
+
+            // Check for code block boundaries
+            if chunk.text.contains("```") {
+                println!("Contains code block markers");
+
+                // Check if code block is split
+                let open_count = chunk.text.matches("```").count();
+                if open_count % 2 != 0 {
+                    println!("WARNING: Code block might be split across chunks!");
+
+                    // Check if it's the start or end of a code block
+                    if chunk.text.trim_end().ends_with("```") {
+                        println!("  - This chunk contains the end of a code block");
+                    }
+                    if chunk.text.contains("```") && !chunk.text.contains("```\n") {
+                        println!("  - This chunk contains the start of a code block");
+                    }
+                } else {
+                    println!("Code blocks are intact within this chunk");
+                }
+            }
+
+            // Check for paragraph integrity
+            let paragraphs = chunk.text.split("\n\n").collect::<Vec<_>>();
+            println!("Contains {} paragraphs:", paragraphs.len());
+            for (j, para) in paragraphs.iter().enumerate().take(2) {
+                println!("  - Paragraph {}: {}", j + 1, preview_text(para, 50));
+            }
+            if paragraphs.len() > 2 {
+                println!("  - ... and {} more paragraphs", paragraphs.len() - 2);
+            }
+        }
+
+        // Verify some specific expectations
+
+        // The first chunk should contain the main heading
+        assert_eq!(
+            chunks[0].heading,
+            Some("Main Heading".to_string()),
+            "First chunk should have the main heading"
+        );
+
+        // Check that code blocks are not split in the middle of code
+        for (i, chunk) in chunks.iter().enumerate() {
+            if chunk.text.contains("```rust") || chunk.text.contains("```python") {
+                // This chunk starts a code block
+                let code_start = chunk.text.find("```").unwrap();
+                let remaining_text = &chunk.text[code_start..];
+
+                // If the code block doesn't end in this chunk, it should end at a chunk boundary
+                if !remaining_text.contains("\n```") && i < chunks.len() - 1 {
+                    println!(
+                        "\nCode block starts in chunk {} and continues to next chunk",
+                        i + 1
+                    );
+                    // The next chunk should continue the code block
+                    assert!(
+                        chunks[i + 1].text.contains("```"),
+                        "Code block should continue in next chunk"
+                    );
+                }
+            }
+        }
+
+        // Verify that paragraphs are generally kept intact
+        for chunk in &chunks {
+            let paragraphs = chunk.text.split("\n\n").collect::<Vec<_>>();
+            for para in paragraphs {
+                // Check that paragraphs aren't too small (arbitrary threshold)
+                if !para.contains("```") && para.trim().len() > 10 {
+                    assert!(
+                        para.split_whitespace().count() >= 2,
+                        "Paragraphs should generally contain multiple words: {}",
+                        para
+                    );
+                }
+            }
+        }
+
+        // Verify that some chunks have associated headings
+        let chunks_with_headings = chunks
+            .iter()
+            .filter(|chunk| chunk.heading.is_some())
+            .count();
+
+        assert!(chunks_with_headings > 0, "Some chunks should have headings");
+
+        // Verify that heading associations are maintained correctly
+        let mut current_heading = None;
+        for chunk in &chunks {
+            if chunk.heading.is_some() {
+                current_heading = chunk.heading.clone();
+            } else if current_heading.is_some() {
+                // If this chunk doesn't have a heading but we've seen one before,
+                // it should have the same heading as the previous chunk with a heading
+                assert_eq!(
+                    chunk.heading, current_heading,
+                    "Chunks should maintain heading association"
+                );
+            }
+        }
+    }
+
+    /// This test specifically demonstrates how the chunker preserves code blocks and paragraph boundaries.
+    #[test]
+    fn test_chunk_markdown_boundary_preservation() {
+        // Arrange: Create a markdown document with a large code block and paragraphs
+        let markdown = r#"# Boundary Preservation Test
+
+This is a paragraph before a code block. We want to ensure that the chunker
+respects the boundaries between paragraphs and code blocks.
+
+```rust
+// This is a large code block that might exceed the chunk size
+fn example_function() -> Result<(), Error> {
+    // First, we do some initialization
+    let mut data = Vec::new();
+    for i in 0..100 {
+        data.push(i);
+    }
+
+    // Then we process the data
+    let processed = data.iter()
+        .map(|x| x * 2)
+        .filter(|x| x % 3 == 0)
+        .collect::<Vec<_>>();
+
+    // Finally, we return the result
+    println!("Processed {} items", processed.len());
+    Ok(())
+}
+
+// Another function in the same code block
+fn another_function() {
+    println!("This is another function");
+    println!("It should be kept with the previous function");
+    println!("Because they're in the same code block");
+}
+```
+
+This is a paragraph after the code block. It should be in a different chunk
+than the code block if the code block is large enough to be its own chunk.
+
+Here's another paragraph that should be kept together with the previous one
+if possible, rather than being split in the middle.
+
+## A New Section
+
+This section starts with a heading, which should create a new chunk boundary.
+The content under this heading should be associated with this heading."#;
+
+        // Create chunk options with a size that will force the code block to be chunked
+        let options = ChunkOptions {
+            target_chunk_size: 30, // Size that will likely split the code block
+            overlap_size: 5,       // Reasonable overlap
+            overlap_sentences: None,
+            min_chunk_chars: 0,
+        };
+
+        // Act: Chunk the markdown
+        let chunks = chunk_markdown(markdown, &options);
+
+        // Print the chunks for illustration
+        println!("\n=== BOUNDARY PRESERVATION TEST ===");
+        println!("Created {} chunks:", chunks.len());
+
+        // Track code block state across chunks
+        let mut in_code_block = false;
+        let mut code_block_chunks = Vec::new();
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            println!("\nChunk {}:", i + 1);
+            println!("Heading: {:?}", chunk.heading);
+            println!("Position: {}", chunk.position);
+            println!("Text length: {} characters", chunk.text.len());
+
+            // Check for code block markers
+            let starts_code = chunk.text.contains("```rust")
+                || (chunk.text.contains("```") && !chunk.text.contains("```\n"));
+            let ends_code = chunk.text.contains("\n```");
+
+            if starts_code {
+                println!("⬇️ STARTS CODE BLOCK ⬇️");
+                in_code_block = true;
+                code_block_chunks.push(i);
+            }
+
+            if in_code_block {
+                println!("📝 CONTAINS CODE BLOCK 📝");
+            }
+
+            if ends_code {
+                println!("⬆️ ENDS CODE BLOCK ⬆️");
+                in_code_block = false;
+            }
+
+            // Print the first few lines and last few lines
+            let lines: Vec<&str> = chunk.text.lines().collect();
+            println!("First few lines:");
+            for line in lines.iter().take(3) {
+                println!("  {}", line);
+            }
+            if lines.len() > 6 {
+                println!("  ... ({} more lines) ...", lines.len() - 6);
+            }
+            println!("Last few lines:");
+            for line in lines.iter().rev().take(3).rev() {
+                println!("  {}", line);
+            }
+        }
+
+        // Assert: Verify code block handling
+        if !code_block_chunks.is_empty() {
+            println!("\nCode block appears in chunks: {:?}", code_block_chunks);
+
+            // Check if code block is split across chunks
+            if code_block_chunks.len() > 1 {
+                println!("Code block is split across multiple chunks");
+
+                // Verify that the split happens at reasonable boundaries
+                for i in 0..code_block_chunks.len() - 1 {
+                    let current = code_block_chunks[i];
+                    let next = code_block_chunks[i + 1];
+
+                    // Chunks should be consecutive
+                    assert_eq!(next, current + 1, "Code block chunks should be consecutive");
+
+                    // Check the end of the current chunk
+                    let current_chunk = &chunks[current];
+                    let current_lines: Vec<&str> = current_chunk.text.lines().collect();
+                    let last_line = current_lines.last().unwrap_or(&"");
+
+                    // Check the start of the next chunk
+                    let next_chunk = &chunks[next];
+                    let next_lines: Vec<&str> = next_chunk.text.lines().collect();
+                    let first_line = next_lines.first().unwrap_or(&"");
+
+                    println!("Split between chunks {} and {}:", current + 1, next + 1);
+                    println!("  Last line of chunk {}: {}", current + 1, last_line);
+                    println!("  First line of chunk {}: {}", next + 1, first_line);
+
+                    // The split should happen at a reasonable boundary (empty line or function boundary)
+                    assert!(
+                        last_line.trim().is_empty()
+                            || last_line.trim().ends_with("{")
+                            || last_line.trim().ends_with("}")
+                            || first_line.trim().is_empty()
+                            || first_line.trim().starts_with("fn ")
+                            || first_line.trim().starts_with("//"),
+                        "Code block should be split at a reasonable boundary"
+                    );
+                }
+            } else {
+                println!("Code block is contained within a single chunk");
+            }
+        }
+
+        // Verify paragraph handling
+        let mut paragraph_count = 0;
+        for chunk in &chunks {
+            let paragraphs = chunk.text.split("\n\n").collect::<Vec<_>>();
+            paragraph_count += paragraphs.len();
+
+            // Check that paragraphs aren't split in the middle
+            for para in paragraphs {
+                if !para.contains("```") && para.trim().len() > 10 {
+                    // Count sentences in paragraph (rough approximation)
+                    let sentences = para
+                        .split(['.', '!', '?'])
+                        .filter(|s| !s.trim().is_empty())
+                        .count();
+
+                    println!(
+                        "Paragraph with {} sentences: {}",
+                        sentences,
+                        preview_text(para, 50)
+                    );
+
+                    // Most paragraphs should have complete sentences
+                    if sentences > 0 && !para.trim().starts_with("//") && !para.contains("fn ") {
+                        assert!(
+                            para.contains('.')
+                                || para.contains('!')
+                                || para.contains('?')
+                                || para.trim().starts_with('#')
+                                || para.contains("```")
+                                || para.trim().starts_with("fn ")
+                                || para.trim().starts_with("//"),
+                            "Paragraphs should generally contain complete sentences"
+                        );
+                    }
+                }
+            }
+        }
+
+        println!("\nTotal paragraphs across all chunks: {}", paragraph_count);
+
+        // Verify heading boundaries
+        let heading_chunks = chunks
+            .iter()
+            .filter(|chunk| chunk.heading.as_deref() == Some("A New Section"))
+            .collect::<Vec<_>>();
+
+        assert!(
+            !heading_chunks.is_empty(),
+            "Should have a chunk with the section heading"
+        );
+
+        // The heading should be at or near the start of its chunk
+        let heading_chunk = heading_chunks[0];
+        let heading_pos = heading_chunk.text.find("A New Section").unwrap();
+
+        println!(
+            "\nHeading 'A New Section' position in its chunk: {}",
+            heading_pos
+        );
+        assert!(
+            heading_pos < 50,
+            "Heading should be near the start of its chunk"
+        );
+    }
+
+    #[test]
+    fn test_render_chunks() {
+        let chunks = vec![
+            TextChunk {
+                text: "First chunk text".to_string(),
+                position: 0,
+                heading: Some("Intro".to_string()),
+                heading_path: vec!["Guide".to_string(), "Intro".to_string()],
+            },
+            TextChunk {
+                text: "Second".to_string(),
+                position: 1,
+                heading: None,
+                heading_path: Vec::new(),
+            },
+        ];
+
+        let rendered = render_chunks(&chunks);
+
+        assert_eq!(
+            rendered,
+            "--- chunk 0 (position 0, 3 words, 16 chars) ---\nheading: Intro\npath: Guide > Intro\nFirst chunk text\n\n\
+             --- chunk 1 (position 1, 1 words, 6 chars) ---\nheading: <none>\npath: <none>\nSecond\n\n"
+        );
+    }
+
+    #[test]
+    fn test_chunk_snapshot_headings_and_code() {
+        let markdown = r#"# Getting Started
+
+Install the tool with cargo. Then run the setup command to create a config file.
+
+## Configuration
+
+The configuration file lives in your home directory. Every option has a default.
+
+```toml
+[crawler]
+max_depth = 2
+max_pages = 100
+```
+
+## Usage
+
+Run the crawl command followed by the index command. Search once indexing completes."#;
+
+        assert_chunk_snapshot(
+            "headings_and_code",
+            markdown,
+            &ChunkOptions {
+                target_chunk_size: 20,
+                overlap_size: 5,
+                overlap_sentences: None,
+                min_chunk_chars: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn test_sentence_overlap() {
+        let markdown: String = (1..=12)
+            .map(|i| format!("Sentence {} has five words.\n", i))
+            .collect();
+        let options = ChunkOptions {
+            target_chunk_size: 20,
+            overlap_size: 0,
+            overlap_sentences: Some(1),
+            min_chunk_chars: 0,
+        };
+
+        let chunks = chunk_markdown(&markdown, &options);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks[1..] {
+            assert!(chunk.text.starts_with("Sentence"), "{:?}", chunk.text);
+        }
+
+        let words: Vec<String> = "One two. Three four. Five"
+            .split_inclusive(' ')
+            .map(String::from)
+            .collect();
+        assert_eq!(sentence_overlap_start(&words, 5, 1), 4);
+        assert_eq!(sentence_overlap_start(&words, 5, 2), 2);
+        assert_eq!(sentence_overlap_start(&words, 5, 3), 2);
+        assert_eq!(sentence_overlap_start(&words, 2, 1), 2);
+    }
+
+    #[test]
+    fn test_merge_small_chunks() {
+        let markdown = format!(
+            "# Guide\n\n## Setup\n\n{}\n\n> Note: back up first.\n\n## Usage\n\n{}",
+            "Install the binary and configure it. ".repeat(8),
+            "Run the crawl command. ".repeat(6)
+        );
+        let options = ChunkOptions {
+            target_chunk_size: 40,
+            overlap_size: 0,
+            overlap_sentences: None,
+            min_chunk_chars: 100,
+        };
+
+        let chunks = chunk_markdown(&markdown, &options);
+        let text: String = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert!(text.contains("Guide"));
+        assert!(text.contains("back up first"));
+        assert!(chunks.iter().all(|chunk| chunk.text.len() >= 100));
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.position)
+                .collect::<Vec<_>>(),
+            (0..chunks.len()).collect::<Vec<_>>()
+        );
+
+        let short = chunk_markdown("Just a line.", &options);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].text, "Just a line.");
+    }
+
+    #[test]
+    fn test_chunk_markdown_iter_matches_chunk_markdown() {
+        let markdown = format!(
+            "# Guide\n\n{}\n\n```rust\nfn main() {{}}\n```\n\n## Usage\n\n{}\n\nDone.",
+            "Install the binary and configure it. ".repeat(20),
+            "Run the crawl command. ".repeat(15)
+        );
+        let options = ChunkOptions {
+            target_chunk_size: 30,
+            overlap_size: 5,
+            overlap_sentences: None,
+            min_chunk_chars: 100,
+        };
+
+        let eager = chunk_markdown(&markdown, &options);
+        let lazy: Vec<TextChunk> = chunk_markdown_iter(&markdown, &options).collect();
+        assert!(eager.len() > 1);
+        assert_eq!(render_chunks(&lazy), render_chunks(&eager));
+
+        let mut iter = chunk_markdown_iter(&markdown, &options);
+        assert_eq!(iter.next().map(|chunk| chunk.position), Some(0));
+        assert_eq!(chunk_markdown_iter("", &options).count(), 0);
+    }
+
+    #[test]
+    fn test_split_words_cjk() {
+        assert_eq!(
+            split_words("Hello, world. Don't\tpanic!"),
+            vec!["Hello, ", "world. ", "Don't\t", "panic!"]
+        );
+        assert_eq!(
+            split_words("我们使用Rust。カタカナです"),
+            vec!["我", "们", "使", "用", "Rust。", "カタカナ", "で", "す"]
+        );
+        assert_eq!(count_words("这是一个测试。"), 6);
+
+        let markdown = "这是一个很长的句子，用来测试中文分块。\n".repeat(20);
+        let options = ChunkOptions {
+            target_chunk_size: 50,
+            overlap_size: 0,
+            overlap_sentences: None,
+            min_chunk_chars: 0,
+        };
+        let chunks = chunk_markdown(&markdown, &options);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| count_words(&chunk.text) <= 60));
+        assert!(chunks.iter().all(|chunk| chunk.text.ends_with('。')));
+        // Lines are joined into paragraphs, but no character is lost or repeated
+        let without_whitespace =
+            |text: &str| -> String { text.chars().filter(|c| !c.is_whitespace()).collect() };
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| without_whitespace(&chunk.text))
+                .collect::<String>(),
+            without_whitespace(&markdown)
+        );
+    }
+
+    #[test]
+    fn test_chunk_markdown_heading_path() {
+        let markdown = "# Guide\n\nIntro text.\n\n## Setup\n\nSetup text.\n\n### Install\n\nInstall text.\n\n## Usage\n\nUsage text.\n\n# Reference\n\nReference text.";
+        let options = ChunkOptions {
+            target_chunk_size: 500,
+            overlap_size: 0,
+            overlap_sentences: None,
+            min_chunk_chars: 0,
+        };
+
+        let chunks = chunk_markdown(markdown, &options);
+        let paths: Vec<Option<String>> = chunks
+            .iter()
+            .map(|chunk| format_breadcrumb(&chunk.heading_path))
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                Some("Guide".to_string()),
+                Some("Guide > Setup".to_string()),
+                Some("Guide > Setup > Install".to_string()),
+                Some("Guide > Usage".to_string()),
+                Some("Reference".to_string()),
+            ]
+        );
+    }
+
+    /// Compare the rendered chunks of `markdown` against `snapshots/<name>.snap`
+    ///
+    /// Setting `HAL_UPDATE_SNAPSHOTS=1` writes the snapshot instead, to create it or to
+    /// accept an intended change; a missing snapshot fails the test otherwise.
+    fn assert_chunk_snapshot(name: &str, markdown: &str, options: &ChunkOptions) {
+        let rendered = render_chunks(&chunk_markdown(markdown, options));
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(format!("{}.snap", name));
+
+        if std::env::var("HAL_UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1") {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &rendered).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+            panic!(
+                "chunk snapshot {} is missing ({}); create it with HAL_UPDATE_SNAPSHOTS=1",
+                path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            rendered, expected,
+            "chunk snapshot '{}' changed; rerun with HAL_UPDATE_SNAPSHOTS=1 if intended",
+            name
+        );
+    }
+
+    /// Helper function to preview text with a maximum length
+    fn preview_text(text: &str, max_length: usize) -> String {
+        if text.len() <= max_length {
+            text.to_string()
+        } else {
+            format!("{}...", &text[..max_length])
+        }
+    }
+}
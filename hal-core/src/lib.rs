@@ -0,0 +1,25 @@
+//! # HAL Core
+//!
+//! The parts of HAL that need neither an async runtime nor a database: Markdown chunking
+//! and Markdown formatting. This crate compiles to `wasm32-unknown-unknown`, so the
+//! chunker can run in a browser or extension to preprocess content on the client, and
+//! produces the same chunks as the `hal` indexing pipeline.
+//!
+//! ## Key Components
+//!
+//! - `chunking`: Structure-aware splitting of Markdown into chunks with heading paths
+//! - `markdown`: Rendering of Markdown with colors and styling
+//! - `wasm`: JavaScript bindings for the chunker (requires the `wasm` feature)
+//!
+//! ## Building for the Browser
+//!
+//! ```sh
+//! wasm-pack build hal-core --target web --features wasm
+//! ```
+
+pub mod chunking;
+pub mod markdown;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use chunking::{ChunkOptions, TextChunk, chunk_markdown, chunk_markdown_iter, count_words};
@@ -0,0 +1,218 @@
+//! # Markdown Formatting Module
+//!
+//! This module renders Markdown with colors and styling for terminal output. It writes
+//! to any `termcolor::WriteColor`, so the same rendering works for a terminal, an
+//! in-memory ANSI buffer, or plain text.
+//!
+//! ## Key Components
+//!
+//! - `write_markdown`: Renders Markdown to a color-capable writer
+//! - `markdown_to_ansi`: Renders Markdown to a string with ANSI escape codes
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::io::Result;
+use termcolor::{Ansi, Color, ColorSpec, WriteColor};
+
+/// Formats markdown text with colors and styling into a writer
+///
+/// # Arguments
+///
+/// * `out` - The writer, e.g. a `termcolor::StandardStream` for the terminal
+/// * `markdown` - The Markdown text to render
+pub fn write_markdown<W: WriteColor>(out: &mut W, markdown: &str) -> Result<()> {
+    let parser = Parser::new_ext(markdown, Options::all());
+    let mut format_state = FormatState::new();
+
+    for event in parser {
+        format_state.handle_event(out, event)?;
+    }
+
+    Ok(())
+}
+
+/// Formats markdown text into a string with ANSI escape codes
+pub fn markdown_to_ansi(markdown: &str) -> String {
+    let mut out = Ansi::new(Vec::new());
+    write_markdown(&mut out, markdown).expect("writing to a Vec cannot fail");
+    String::from_utf8_lossy(&out.into_inner()).into_owned()
+}
+
+/// Tracks the current formatting state
+struct FormatState {
+    in_code_block: bool,
+    list_level: usize,
+    format_stack: Vec<ColorSpec>,
+    current_list_type: Option<bool>,
+}
+
+impl FormatState {
+    fn new() -> Self {
+        Self {
+            in_code_block: false,
+            list_level: 0,
+            format_stack: Vec::new(),
+            current_list_type: None,
+        }
+    }
+
+    fn handle_event(&mut self, stdout: &mut impl WriteColor, event: Event) -> Result<()> {
+        match event {
+            Event::Start(tag) => self.handle_start(stdout, tag),
+            Event::End(tag_end) => self.handle_end(stdout, tag_end),
+            Event::Text(text) => self.write_text(stdout, &text),
+            Event::Code(code) => self.write_inline_code(stdout, &code),
+            Event::SoftBreak | Event::HardBreak => writeln!(stdout),
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_start(&mut self, stdout: &mut impl WriteColor, tag: Tag) -> Result<()> {
+        match tag {
+            Tag::Heading { level, .. } => {
+                let level_color = match level {
+                    HeadingLevel::H1 => Color::Rgb(255, 99, 71), // Tomato red for h1
+                    HeadingLevel::H2 => Color::Rgb(70, 130, 180), // Steel blue for h2
+                    _ => Color::Cyan,                            // Cyan for other levels
+                };
+                let mut spec = ColorSpec::new();
+                spec.set_fg(Some(level_color)).set_bold(true);
+                self.format_stack.push(spec.clone());
+                stdout.set_color(&spec)?;
+                if level == HeadingLevel::H1 {
+                    writeln!(stdout)?
+                }
+            }
+            Tag::Paragraph => writeln!(stdout)?,
+            Tag::Strong => {
+                let mut spec = ColorSpec::new();
+                spec.set_bold(true);
+                self.format_stack.push(spec.clone());
+                stdout.set_color(&spec)?
+            }
+            Tag::Emphasis => {
+                let mut spec = ColorSpec::new();
+                spec.set_italic(true);
+                self.format_stack.push(spec.clone());
+                stdout.set_color(&spec)?
+            }
+            Tag::BlockQuote(_) => {
+                let mut spec = ColorSpec::new();
+                spec.set_fg(Some(Color::Yellow));
+                self.format_stack.push(spec.clone());
+                stdout.set_color(&spec)?;
+                write!(stdout, "  │ ")?
+            }
+            Tag::CodeBlock(kind) => {
+                self.in_code_block = true;
+                let mut spec = ColorSpec::new();
+                spec.set_fg(Some(Color::Green));
+                self.format_stack.push(spec.clone());
+                stdout.set_color(&spec)?;
+                match kind {
+                    CodeBlockKind::Fenced(lang) => {
+                        writeln!(stdout)?;
+                        let lang = lang.to_string();
+                        if !lang.is_empty() {
+                            let mut lang_spec = ColorSpec::new();
+                            lang_spec.set_fg(Some(Color::Blue)).set_italic(true);
+                            stdout.set_color(&lang_spec)?;
+                            writeln!(stdout, "[{}]", lang)?;
+                            stdout.set_color(&spec)?
+                        }
+                    }
+                    CodeBlockKind::Indented => writeln!(stdout)?,
+                }
+            }
+            Tag::List(start) => {
+                self.list_level += 1;
+                self.current_list_type = Some(start.is_some());
+                if let Some(num) = start {
+                    write!(stdout, "{}{:2}. ", "  ".repeat(self.list_level - 1), num)?
+                }
+            }
+            Tag::Item if self.list_level > 0 => match self.current_list_type {
+                Some(true) => (), // Ordered list items are handled in List(start)
+                Some(false) => write!(stdout, "{}• ", "  ".repeat(self.list_level - 1))?,
+                None => write!(stdout, "{}• ", "  ".repeat(self.list_level - 1))?,
+            },
+            Tag::Link { dest_url, .. } => {
+                let mut spec = ColorSpec::new();
+                spec.set_fg(Some(Color::Blue)).set_underline(true);
+                self.format_stack.push(spec.clone());
+                stdout.set_color(&spec)?;
+                write!(stdout, "{}", dest_url)?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_end(&mut self, stdout: &mut impl WriteColor, tag_end: TagEnd) -> Result<()> {
+        match tag_end {
+            TagEnd::Heading(_) => {
+                self.format_stack.pop();
+                writeln!(stdout)?
+            }
+            TagEnd::Paragraph => writeln!(stdout)?,
+            TagEnd::Strong | TagEnd::Emphasis | TagEnd::Link => {
+                self.format_stack.pop();
+                if let Some(spec) = self.format_stack.last() {
+                    stdout.set_color(spec)?
+                } else {
+                    stdout.reset()?
+                }
+                if matches!(tag_end, TagEnd::Link) {
+                    write!(stdout, " ")?
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.format_stack.pop();
+                if let Some(spec) = self.format_stack.last() {
+                    stdout.set_color(spec)?
+                } else {
+                    stdout.reset()?
+                }
+                writeln!(stdout)?
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.format_stack.pop();
+                if let Some(spec) = self.format_stack.last() {
+                    stdout.set_color(spec)?
+                } else {
+                    stdout.reset()?
+                }
+                writeln!(stdout)?
+            }
+            TagEnd::List(_) => {
+                self.list_level -= 1;
+                if self.list_level == 0 {
+                    self.current_list_type = None;
+                    writeln!(stdout)?
+                }
+            }
+            TagEnd::Item => writeln!(stdout)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn write_text(&self, stdout: &mut impl WriteColor, text: &str) -> Result<()> {
+        write!(stdout, "{}", text)
+    }
+
+    fn write_inline_code(&self, stdout: &mut impl WriteColor, code: &str) -> Result<()> {
+        let current_spec = if let Some(spec) = self.format_stack.last() {
+            spec.clone()
+        } else {
+            ColorSpec::new()
+        };
+
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
+        write!(stdout, "`{}`", code)?;
+
+        // Restore previous color spec
+        stdout.set_color(&current_spec)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+//! JavaScript bindings for the chunker
+
+use wasm_bindgen::prelude::*;
+
+use crate::chunking::{ChunkOptions, chunk_markdown};
+
+/// Chunk Markdown text, returning the chunks as a JSON array
+///
+/// Each chunk has `text`, `position`, `heading` and `heading_path` fields, as produced
+/// by the `hal` indexing pipeline.
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown text to chunk
+/// * `target_chunk_size` - Target size of each chunk in words
+/// * `overlap_size` - Size of overlap between chunks in words
+#[wasm_bindgen(js_name = chunkMarkdown)]
+pub fn chunk_markdown_json(
+    markdown: &str,
+    target_chunk_size: usize,
+    overlap_size: usize,
+) -> Result<String, JsError> {
+    let options = ChunkOptions {
+        target_chunk_size,
+        overlap_size,
+        ..ChunkOptions::default()
+    };
+    Ok(serde_json::to_string(&chunk_markdown(markdown, &options))?)
+}
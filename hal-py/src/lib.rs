@@ -0,0 +1,258 @@
+//! # HAL Python Bindings
+//!
+//! This crate exposes HAL's crawl, chunk, index and search steps as the `hal_py` Python
+//! module, so pipelines can be driven and indexes queried from notebooks.
+//!
+//! ## Key Components
+//!
+//! - `crawl`: Crawls a website and returns its pages
+//! - `chunk`: Splits Markdown into chunks with the indexing pipeline's chunker
+//! - `Index`: An index database that websites or crawled pages can be added to and searched
+//!
+//! ## Usage
+//!
+//! ```python
+//! import hal_py
+//!
+//! index = hal_py.Index("index.db")
+//! index.index("https://docs.example.com", max_pages=20)
+//!
+//! pages = hal_py.crawl("https://blog.example.com", max_pages=50)
+//! index.index_pages([page for page in pages if "/drafts/" not in page["url"]])
+//! for result in index.search("How do I configure the crawler?", limit=5):
+//!     print(result["score"], result["url"])
+//! ```
+//!
+//! Results and pages are returned as plain dicts with the same fields as the CLI's JSON
+//! output. Calls release the GIL while they run, and the LLM steps read `GEMINI_API_KEY`
+//! (indexing) and `GEMINI_FREE_API_KEY` (search), or the keys in `hal.toml`, like the CLI.
+
+use std::sync::LazyLock;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use hal::crawler::{CrawledPage, CrawlerConfig};
+use hal::index::Database;
+use hal::processor::{ChunkOptions, ProcessorConfig};
+use hal::search::SearchOptions;
+
+/// Runtime the async HAL functions are driven on
+static RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start the tokio runtime")
+});
+
+/// Raise a Python `RuntimeError` for a HAL error
+fn runtime_error(e: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// Convert a serializable value to Python objects through JSON
+fn to_python(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(runtime_error)?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// Fail early when the LLM steps cannot run, instead of panicking in the client
+fn require_api_key(name: &str) -> PyResult<()> {
+    if hal::config::api_key(name).is_none() {
+        return Err(PyRuntimeError::new_err(format!(
+            "{} environment variable must be set",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Crawl configuration matching the CLI's defaults
+fn crawler_config(max_depth: u32, max_pages: u32) -> CrawlerConfig {
+    CrawlerConfig::builder()
+        .max_depth(max_depth)
+        .max_pages(max_pages)
+        .rate_limit_ms(500)
+        .respect_robots_txt(true)
+        .user_agent("hal-rag/0.1".to_string())
+        .build()
+}
+
+/// Crawl a website and return its pages as dicts
+#[pyfunction]
+#[pyo3(signature = (url, max_depth = 2, max_pages = 100))]
+fn crawl(py: Python<'_>, url: &str, max_depth: u32, max_pages: u32) -> PyResult<PyObject> {
+    let pages = py
+        .allow_threads(|| {
+            RUNTIME.block_on(hal::crawler::crawl_website(
+                url,
+                crawler_config(max_depth, max_pages),
+            ))
+        })
+        .map_err(runtime_error)?;
+    to_python(py, &pages)
+}
+
+/// Split Markdown into chunks as dicts with `text`, `position`, `heading` and
+/// `heading_path`
+#[pyfunction]
+#[pyo3(signature = (markdown, target_chunk_size = 500, overlap_size = 50))]
+fn chunk(
+    py: Python<'_>,
+    markdown: &str,
+    target_chunk_size: usize,
+    overlap_size: usize,
+) -> PyResult<PyObject> {
+    if target_chunk_size == 0 {
+        return Err(PyValueError::new_err("target_chunk_size must be positive"));
+    }
+    let options = ChunkOptions {
+        target_chunk_size,
+        overlap_size,
+        ..ChunkOptions::default()
+    };
+    let chunks = py.allow_threads(|| hal_core::chunk_markdown(markdown, &options));
+    to_python(py, &chunks)
+}
+
+/// An index database
+#[pyclass]
+struct Index {
+    db: Database,
+}
+
+#[pymethods]
+impl Index {
+    /// Open the index at a path, creating it if needed
+    #[new]
+    fn new(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let db = py
+            .allow_threads(|| RUNTIME.block_on(Database::new_from_path(path)))
+            .map_err(runtime_error)?;
+        Ok(Self { db })
+    }
+
+    /// Crawl a website and index its pages
+    ///
+    /// Returns the number of chunks indexed.
+    #[pyo3(signature = (url, max_depth = 2, max_pages = 100, chunk_size = 500, model = "gemini-2.0-flash"))]
+    fn index(
+        &self,
+        py: Python<'_>,
+        url: &str,
+        max_depth: u32,
+        max_pages: u32,
+        chunk_size: usize,
+        model: &str,
+    ) -> PyResult<usize> {
+        require_api_key("GEMINI_API_KEY")?;
+        let config = ProcessorConfig::builder()
+            .chunk_options(ChunkOptions {
+                target_chunk_size: chunk_size,
+                overlap_size: chunk_size / 10,
+                ..ChunkOptions::default()
+            })
+            .llm_model(model.to_string())
+            .embedding_dimensions(768)
+            .build();
+
+        py.allow_threads(|| {
+            RUNTIME.block_on(async {
+                let pages = hal::crawler::crawl_website(url, crawler_config(max_depth, max_pages))
+                    .await
+                    .map_err(runtime_error)?;
+                self.store_pages(pages, config).await
+            })
+        })
+    }
+
+    /// Index pages previously returned by `crawl`, e.g. after filtering them
+    ///
+    /// Returns the number of chunks indexed.
+    #[pyo3(name = "index_pages", signature = (pages, chunk_size = 500, model = "gemini-2.0-flash"))]
+    fn index_crawled_pages(
+        &self,
+        py: Python<'_>,
+        pages: &Bound<'_, PyAny>,
+        chunk_size: usize,
+        model: &str,
+    ) -> PyResult<usize> {
+        require_api_key("GEMINI_API_KEY")?;
+        let json: String = py
+            .import("json")?
+            .call_method1("dumps", (pages,))?
+            .extract()?;
+        let pages: Vec<CrawledPage> =
+            serde_json::from_str(&json).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let config = ProcessorConfig::builder()
+            .chunk_options(ChunkOptions {
+                target_chunk_size: chunk_size,
+                overlap_size: chunk_size / 10,
+                ..ChunkOptions::default()
+            })
+            .llm_model(model.to_string())
+            .embedding_dimensions(768)
+            .build();
+
+        py.allow_threads(|| RUNTIME.block_on(self.store_pages(pages, config)))
+    }
+
+    /// Search the index and return the results as dicts, best first
+    #[pyo3(signature = (query, limit = 10, source = None))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        query: &str,
+        limit: usize,
+        source: Option<String>,
+    ) -> PyResult<PyObject> {
+        require_api_key("GEMINI_FREE_API_KEY")?;
+        let options = SearchOptions {
+            limit,
+            source_filter: source,
+            ..SearchOptions::default()
+        };
+        let results = py
+            .allow_threads(|| {
+                RUNTIME.block_on(async {
+                    let client = hal::model::Client::new_gemini_free_from_env();
+                    hal::search::search_index(&self.db, &client, query, options).await
+                })
+            })
+            .map_err(runtime_error)?;
+        to_python(py, &results)
+    }
+}
+
+impl Index {
+    /// Process and store pages, returning the number of chunks indexed
+    async fn store_pages(
+        &self,
+        pages: Vec<CrawledPage>,
+        config: ProcessorConfig,
+    ) -> PyResult<usize> {
+        let client = hal::model::Client::new_gemini_from_env();
+        let mut total_chunks = 0;
+        for page in pages {
+            let chunks = hal::processor::process_content(&client, page.clone(), config.clone())
+                .await
+                .map_err(runtime_error)?;
+            total_chunks += chunks.len();
+            self.db
+                .update_website_index(&page.url, chunks)
+                .await
+                .map_err(runtime_error)?;
+        }
+        Ok(total_chunks)
+    }
+}
+
+/// Python module `hal_py`
+#[pymodule]
+fn hal_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(crawl, m)?)?;
+    m.add_function(wrap_pyfunction!(chunk, m)?)?;
+    m.add_class::<Index>()?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+/*
+ * C API for querying a HAL index. Build the library with:
+ *
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * Functions returning pointers return NULL on failure; hal_last_error() then
+ * describes the failure. All strings are UTF-8 and NUL-terminated.
+ */
+
+#ifndef HAL_H
+#define HAL_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An open index. */
+typedef struct HalIndex HalIndex;
+
+/* Open the index database at path, creating it if needed. Release with hal_close(). */
+HalIndex *hal_open(const char *path);
+
+/*
+ * Search an index. source may be NULL to search every website; a limit of 0
+ * returns up to 10 results. Returns a JSON array of results, best first, to be
+ * released with hal_string_free(). Queries are embedded with the embedding
+ * backend of hal.toml, the Gemini model by default. GEMINI_FREE_API_KEY, or the
+ * key stored in hal.toml, must be set.
+ */
+char *hal_search(const HalIndex *index, const char *query, const char *source, size_t limit);
+
+/* Free a string returned by hal_search(). NULL is ignored. */
+void hal_string_free(char *value);
+
+/* Close an index. NULL is ignored. */
+void hal_close(HalIndex *index);
+
+/*
+ * The message of the last error on the calling thread, or NULL. Valid until
+ * the next failing call on the same thread.
+ */
+const char *hal_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HAL_H */
@@ -0,0 +1,237 @@
+//! # Audit Log
+//!
+//! Append-only record of tool invocations, so the work of agents trusted with write and
+//! execute access can be reviewed afterwards. Every call is written as one JSON line to
+//! `.hal/audit.jsonl` in the working directory by:
+//!
+//! - The MCP server, for each tool call it handles
+//! - The `AgentExecutor` of `hal code`, for each tool call with its approval decision
+//! - The `SemanticSearch` rig tool, when given a log
+//!
+//! Arguments are stored as a hash rather than in full, since they may contain file contents
+//! or secrets. `hal audit tail` prints the latest entries.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How a tool call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// The tool returned a result
+    Success,
+    /// The tool failed
+    Error,
+    /// The call was not allowed to run
+    Denied,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Success => "success",
+            Self::Error => "error",
+            Self::Denied => "denied",
+        })
+    }
+}
+
+/// A recorded tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the call finished, RFC 3339
+    pub timestamp: String,
+    /// What made the call: `mcp`, `agent` or `rig`
+    pub source: String,
+    /// Name of the tool
+    pub tool: String,
+    /// Hash of the JSON arguments, see [`args_hash`]
+    pub args_hash: String,
+    /// Size of the result in bytes
+    pub result_bytes: usize,
+    /// Time the call took
+    pub duration_ms: u64,
+    /// How the call ended
+    pub outcome: Outcome,
+    /// Permission decision, e.g. `approved`, `denied` or the access a call granted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<String>,
+    /// Error of a failed or denied call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    /// An entry of a successful call without a result; see [`Self::with_result`]
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - What made the call: `mcp`, `agent` or `rig`
+    /// * `tool` - Name of the tool
+    /// * `args` - JSON arguments of the call, which are hashed
+    /// * `duration` - Time the call took
+    pub fn new(source: &str, tool: &str, args: &str, duration: Duration) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            source: source.to_string(),
+            tool: tool.to_string(),
+            args_hash: args_hash(args),
+            result_bytes: 0,
+            duration_ms: duration.as_millis() as u64,
+            outcome: Outcome::Success,
+            permission: None,
+            error: None,
+        }
+    }
+
+    /// Record the result of the call, or its error
+    pub fn with_result<E: std::fmt::Display>(mut self, result: Result<usize, E>) -> Self {
+        match result {
+            Ok(bytes) => self.result_bytes = bytes,
+            Err(e) => {
+                self.outcome = Outcome::Error;
+                self.error = Some(e.to_string());
+            }
+        }
+        self
+    }
+
+    /// Record a permission decision; denied calls are marked as such
+    pub fn with_permission(mut self, decision: impl Into<String>, denied: bool) -> Self {
+        self.permission = Some(decision.into());
+        if denied {
+            self.outcome = Outcome::Denied;
+        }
+        self
+    }
+}
+
+/// Stable hash of tool arguments, as 16 hex digits (64-bit FNV-1a)
+pub fn args_hash(args: &str) -> String {
+    let hash = args.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Append-only JSONL file of tool calls
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    /// File the entries are appended to
+    path: PathBuf,
+    /// Keeps lines of concurrent calls in one process from interleaving
+    lock: Arc<Mutex<()>>,
+}
+
+impl AuditLog {
+    /// Create a log appending to a file, created with its directory on the first entry
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Default location of the log, in the `.hal` directory of the working directory
+    pub fn default_path() -> PathBuf {
+        Path::new(".hal").join("audit.jsonl")
+    }
+
+    /// File the entries are appended to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry
+    pub fn record(&self, entry: &AuditEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // One write per line, so appends of other processes don't split it
+        file.write_all(line.as_bytes())
+    }
+
+    /// Append an entry, logging instead of failing the tool call if it cannot be written
+    pub fn record_or_warn(&self, entry: &AuditEntry) {
+        if let Err(e) = self.record(entry) {
+            tracing::warn!(error = %e, path = %self.path.display(), "Failed to write audit log");
+        }
+    }
+
+    /// The latest entries, oldest first
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Most entries to return
+    /// * `tool` - Only return calls of this tool
+    pub fn tail(&self, limit: usize, tool: Option<&str>) -> std::io::Result<Vec<AuditEntry>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries: Vec<AuditEntry> = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+            .filter(|entry| tool.is_none_or(|tool| entry.tool == tool))
+            .collect();
+        let start = entries.len().saturating_sub(limit);
+        Ok(entries.split_off(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_and_tail() {
+        let temp_dir = tempdir().unwrap();
+        let log = AuditLog::new(temp_dir.path().join(".hal").join("audit.jsonl"));
+        assert!(log.tail(10, None).unwrap().is_empty());
+
+        let duration = Duration::from_millis(12);
+        let read = AuditEntry::new("mcp", "show_file", r#"{"path":"a"}"#, duration)
+            .with_result(Ok::<_, String>(42));
+        let denied = AuditEntry::new("agent", "write_file", r#"{"path":"b"}"#, duration)
+            .with_permission("denied", true);
+        let failed = AuditEntry::new("mcp", "show_file", r#"{"path":"c"}"#, duration)
+            .with_result(Err::<usize, _>("No read access"));
+        for entry in [&read, &denied, &failed] {
+            log.record(entry).unwrap();
+        }
+
+        let entries = log.tail(2, None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].tool, "write_file");
+        assert_eq!(entries[0].outcome, Outcome::Denied);
+        assert_eq!(entries[1].outcome, Outcome::Error);
+        assert_eq!(entries[1].error.as_deref(), Some("No read access"));
+
+        let entries = log.tail(10, Some("show_file")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].result_bytes, 42);
+        assert_eq!(entries[0].duration_ms, 12);
+        assert_eq!(entries[0].args_hash, args_hash(r#"{"path":"a"}"#));
+        assert_ne!(entries[0].args_hash, entries[1].args_hash);
+    }
+
+    #[test]
+    fn test_args_hash_is_stable() {
+        assert_eq!(args_hash(""), "cbf29ce484222325");
+        assert_eq!(args_hash("a"), "af63dc4c8601ec8c");
+    }
+}
@@ -0,0 +1,118 @@
+//! # Cancellation
+//!
+//! Crawls, indexing and re-embedding run for minutes and must stop cleanly when
+//! interrupted. They take a `CancellationToken`, through `CrawlerConfig::cancel`,
+//! `ProcessorConfig::cancel` and the `cancel` argument of
+//! `Database::reembed_all_chunks`. Once it is cancelled:
+//!
+//! - A crawl stops fetching and returns the pages fetched so far, with its report
+//!   marked as cancelled
+//! - Processing a page fails with `ProcessError::Cancelled` before anything of the page
+//!   is stored, so pages are indexed completely or not at all
+//! - Re-embedding keeps the chunks already updated and skips the others
+//!
+//! The commands then write a `Checkpoint` recording the work completed and skipped.
+//! `shutdown_token` is the token the commands of this process share.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+pub use tokio_util::sync::CancellationToken;
+
+/// The token cancelled when this process is asked to stop
+pub fn shutdown_token() -> CancellationToken {
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+    TOKEN.get_or_init(CancellationToken::new).clone()
+}
+
+/// Record of a cancelled operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The operation, e.g. `crawl`, `index` or `reembed`
+    pub operation: String,
+    /// What it ran on, such as the source URL or domain
+    pub target: String,
+    /// When it was cancelled, RFC 3339
+    pub cancelled_at: String,
+    /// Items completed: page URLs or chunk IDs
+    pub completed: Vec<String>,
+    /// Items skipped because of the cancellation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remaining: Vec<String>,
+}
+
+impl Checkpoint {
+    /// Record an operation cancelled now
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The operation, e.g. `index`
+    /// * `target` - What it ran on
+    /// * `completed` - Items completed
+    /// * `remaining` - Items skipped
+    pub fn new(
+        operation: &str,
+        target: &str,
+        completed: Vec<String>,
+        remaining: Vec<String>,
+    ) -> Self {
+        Self {
+            operation: operation.to_string(),
+            target: target.to_string(),
+            cancelled_at: chrono::Utc::now().to_rfc3339(),
+            completed,
+            remaining,
+        }
+    }
+
+    /// Default location of the checkpoint of an operation, in the `.hal` directory of the
+    /// working directory
+    pub fn default_path(operation: &str) -> PathBuf {
+        Path::new(".hal")
+            .join("checkpoints")
+            .join(format!("{}.json", operation))
+    }
+
+    /// Write the checkpoint, replacing the previous one
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Read a checkpoint, or `None` if there is none
+    pub fn load(path: &Path) -> std::io::Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(Checkpoint::default_path("index"));
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let checkpoint = Checkpoint::new(
+            "index",
+            "https://example.com",
+            vec!["https://example.com/a".to_string()],
+            vec!["https://example.com/b".to_string()],
+        );
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+    }
+}
@@ -2,21 +2,33 @@
 //! and an executing ("Junior") agent with tools to accomplish coding tasks.
 
 // Declare submodules according to Rust 2018+ conventions
+pub mod artifacts;
 pub mod config;
+pub mod context;
 pub mod error;
 pub mod events;
 pub mod executor; // Exported executor module
 mod session; // Private implementation module
+pub mod tool_selection;
 
 // Re-export public types for easier access
+pub use artifacts::ArtifactStore;
 pub use config::CoderConfig;
 pub use error::CoderError;
 pub use events::CoderEvent;
-pub use executor::{AgentExecutor, ExecutionOutcome, ExecutorEvent}; // Re-export executor types
+pub use executor::{AgentExecutor, ExecutionOutcome, ExecutorEvent, ToolApproval}; // Re-export executor types
+pub use tool_selection::ToolIndex;
 
 use futures::stream::Stream;
 use rig::{completion::CompletionModel, message::Message};
 
+/// Instructions of a single agent running a coding task end-to-end with `AgentExecutor`
+pub const TASK_PREAMBLE: &str = r"You are a coding agent working on a task in a project directory.
+Use the tools to read the code, edit files and run commands to complete the task.
+Only change what the task asks for, and keep the project building.
+Request permission before running commands outside the allowlist.
+When the task is done, or you cannot make progress, call the 'finish' tool with a summary of what you changed.";
+
 /// Runs a Pro/Junior coder session based on the user request and prior history.
 ///
 /// This function orchestrates the interaction between a planning agent (Pro)
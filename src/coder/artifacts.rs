@@ -0,0 +1,231 @@
+//! Artifact store for large tool results.
+//!
+//! Tool results such as file reads or command output can be large enough to blow up the
+//! conversation history. With a result budget, the executor keeps only the start of such a
+//! result inline, followed by a note, and writes the full output to an artifact file. The
+//! agent pages through it with the `read_artifact` tool, which the executor answers itself.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rig::completion::ToolDefinition;
+use serde_json::json;
+
+use crate::coder::error::CoderError;
+
+/// Name of the tool reading stored artifacts
+pub const READ_ARTIFACT_TOOL: &str = "read_artifact";
+
+/// Default size of tool results kept inline, in characters
+pub const DEFAULT_RESULT_BUDGET: usize = 8_000;
+
+/// Directory of artifact files of a session
+pub struct ArtifactStore {
+    /// Directory the artifacts are written to
+    dir: PathBuf,
+    /// Most characters of a result kept inline, and of a page read back
+    budget: usize,
+    /// Number of artifacts written, to keep IDs unique within the session
+    count: AtomicUsize,
+}
+
+impl ArtifactStore {
+    /// Create a store writing to a directory, created on the first artifact
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory the artifacts are written to
+    /// * `budget` - Most characters of a result kept inline, and of a page read back
+    pub fn new(dir: impl Into<PathBuf>, budget: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            budget: budget.max(1),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Default location of artifacts, in the `.hal` directory of the working directory
+    pub fn default_dir() -> PathBuf {
+        Path::new(".hal").join("artifacts")
+    }
+
+    /// Definition of the `read_artifact` tool
+    pub fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: READ_ARTIFACT_TOOL.to_string(),
+            description: format!(
+                "Read part of a tool result that was too large to return in full. Results longer than {} characters are truncated and stored as an artifact; the truncation note names the artifact ID. Returns up to `limit` characters starting at `offset`, and the offset of the next page.",
+                self.budget
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "id": {
+                        "type": "string",
+                        "description": "ID of the artifact, from the truncation note"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Character offset to start reading at (default 0)"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Most characters to read (default and maximum {})", self.budget)
+                    }
+                },
+                "required": ["id"]
+            }),
+        }
+    }
+
+    /// Keep a tool result within the budget, storing the full result if it is larger
+    ///
+    /// # Arguments
+    ///
+    /// * `tool_name` - Tool that returned the result, part of the artifact ID
+    /// * `result` - The full result
+    ///
+    /// # Returns
+    ///
+    /// The result if it fits, otherwise its start followed by a note naming the artifact
+    pub fn limit(&self, tool_name: &str, result: String) -> String {
+        let total = result.chars().count();
+        if total <= self.budget {
+            return result;
+        }
+
+        let mut inline: String = result.chars().take(self.budget).collect();
+        let note = match self.save(tool_name, &result) {
+            Ok(id) => format!(
+                "[Truncated: showing {} of {} characters. The full result is stored as artifact '{}'; call {} with this id and offset {} to read the rest.]",
+                self.budget, total, id, READ_ARTIFACT_TOOL, self.budget
+            ),
+            Err(e) => {
+                tracing::warn!(error = %e, tool_name, "Failed to store tool result artifact");
+                format!(
+                    "[Truncated: showing {} of {} characters. The full result could not be stored.]",
+                    self.budget, total
+                )
+            }
+        };
+        inline.push_str("\n\n");
+        inline.push_str(&note);
+        inline
+    }
+
+    /// Write a result to a new artifact
+    fn save(&self, tool_name: &str, result: &str) -> std::io::Result<String> {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let tool: String = tool_name
+            .chars()
+            .map(|c| if is_id_char(c) { c } else { '_' })
+            .collect();
+        let id = format!(
+            "{}-{}-{}",
+            chrono::Local::now().format("%Y%m%d%H%M%S"),
+            count,
+            tool
+        );
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path(&id), result)?;
+        Ok(id)
+    }
+
+    /// Read a page of an artifact, as the JSON result of the `read_artifact` tool
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - JSON arguments of the tool call: `id`, and optionally `offset` and `limit`
+    pub fn read(&self, args: &serde_json::Value) -> Result<String, CoderError> {
+        let id = args
+            .get("id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| CoderError::Artifact("Missing artifact id".to_string()))?;
+        if id.is_empty() || !id.chars().all(is_id_char) {
+            return Err(CoderError::Artifact(format!(
+                "Invalid artifact id '{}'",
+                id
+            )));
+        }
+        let offset = args.get("offset").and_then(|o| o.as_u64()).unwrap_or(0) as usize;
+        let limit = args
+            .get("limit")
+            .and_then(|l| l.as_u64())
+            .map_or(self.budget, |l| (l as usize).clamp(1, self.budget));
+
+        let content = std::fs::read_to_string(self.path(id))
+            .map_err(|e| CoderError::Artifact(format!("Cannot read artifact '{}': {}", id, e)))?;
+        let total = content.chars().count();
+        let page: String = content.chars().skip(offset).take(limit).collect();
+        let end = offset.saturating_add(limit);
+
+        Ok(json!({
+            "id": id,
+            "offset": offset,
+            "total": total,
+            "content": page,
+            "next_offset": (end < total).then_some(end),
+        })
+        .to_string())
+    }
+
+    /// File of an artifact
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", id))
+    }
+}
+
+/// Whether a character may appear in an artifact ID, which keeps IDs inside the store
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_small_results_are_kept() {
+        let temp_dir = tempdir().unwrap();
+        let store = ArtifactStore::new(temp_dir.path().join("artifacts"), 10);
+
+        assert_eq!(store.limit("show_file", "short".to_string()), "short");
+        assert!(!temp_dir.path().join("artifacts").exists());
+    }
+
+    #[test]
+    fn test_large_results_are_stored_and_paged() {
+        let temp_dir = tempdir().unwrap();
+        let store = ArtifactStore::new(temp_dir.path(), 10);
+
+        let limited = store.limit(
+            "execute_shell_command",
+            "0123456789abcdefghij-end".to_string(),
+        );
+        assert!(limited.starts_with("0123456789\n\n[Truncated: showing 10 of 24 characters."));
+        let id = limited.split('\'').nth(1).unwrap();
+
+        let page: serde_json::Value =
+            serde_json::from_str(&store.read(&json!({ "id": id, "offset": 10 })).unwrap()).unwrap();
+        assert_eq!(page["content"], "abcdefghij");
+        assert_eq!(page["next_offset"], 20);
+
+        let page: serde_json::Value =
+            serde_json::from_str(&store.read(&json!({ "id": id, "offset": 20 })).unwrap()).unwrap();
+        assert_eq!(page["content"], "-end");
+        assert!(page["next_offset"].is_null());
+    }
+
+    #[test]
+    fn test_read_rejects_paths() {
+        let temp_dir = tempdir().unwrap();
+        let store = ArtifactStore::new(temp_dir.path(), 10);
+
+        assert!(matches!(
+            store.read(&json!({ "id": "../secret" })),
+            Err(CoderError::Artifact(_))
+        ));
+        assert!(store.read(&json!({ "id": "missing" })).is_err());
+    }
+}
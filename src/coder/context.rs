@@ -0,0 +1,197 @@
+//! Token-budgeted context loading for coding tasks.
+//!
+//! Instead of dumping a whole repository into the conversation, the task prompt is embedded
+//! and matched against a repository index with the search system. The best matching chunks
+//! are grouped by file and preloaded into the agent's context until a token budget is spent,
+//! so the agent starts with the code that matters for the task.
+
+use rig::{completion::CompletionModel, embeddings::EmbeddingModel};
+
+use crate::index::Database;
+use crate::model::Client;
+use crate::search::{SearchError, SearchOptions, SearchResult, search_index_with_client};
+
+/// Default token budget of preloaded files
+pub const DEFAULT_CONTEXT_TOKENS: usize = 8_000;
+
+/// Options for loading the files relevant to a task
+#[derive(Debug, Clone)]
+pub struct ContextOptions {
+    /// Most tokens of preloaded chunks, estimated at four characters per token
+    pub max_tokens: usize,
+    /// Number of chunks retrieved before the budget is applied
+    pub limit: usize,
+    /// Only retrieve chunks of this indexed source, e.g. the repository's domain
+    pub source_filter: Option<String>,
+    /// Match `source_filter` against the whole domain instead of any part of it
+    pub exact_source: bool,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: DEFAULT_CONTEXT_TOKENS,
+            limit: 50,
+            source_filter: None,
+            exact_source: false,
+        }
+    }
+}
+
+/// A file with the chunks that matched the task
+#[derive(Debug, Clone)]
+pub struct RelevantFile {
+    /// Path or URL of the file in the index
+    pub path: String,
+    /// Score of the best matching chunk
+    pub score: f64,
+    /// Matching chunks, best first
+    pub chunks: Vec<SearchResult>,
+}
+
+/// Files preloaded for a task
+#[derive(Debug, Clone, Default)]
+pub struct RelevantContext {
+    /// Files in order of their best matching chunk
+    pub files: Vec<RelevantFile>,
+    /// Estimated tokens of the chunks
+    pub tokens: usize,
+}
+
+impl RelevantContext {
+    /// Whether no chunk matched or fit the budget
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Render the files as a section of the agent's preamble
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = String::from(
+            "These files from the repository index are relevant to the task. They are excerpts; read the files for the full code.\n",
+        );
+        for file in &self.files {
+            prompt.push_str(&format!("\n### {} (score {:.3})\n", file.path, file.score));
+            for chunk in &file.chunks {
+                if let Some(heading_path) = &chunk.heading_path {
+                    prompt.push_str(&format!("{}\n", heading_path));
+                }
+                prompt.push_str(&format!("```\n{}\n```\n", chunk.text.trim_end()));
+            }
+        }
+        prompt
+    }
+}
+
+/// Retrieve the files relevant to a task and keep the best chunks within a token budget
+///
+/// # Arguments
+///
+/// * `db` - Repository index
+/// * `client` - Client embedding the task
+/// * `task` - The task prompt
+/// * `options` - Token budget, retrieval limit and source filter
+pub async fn load_relevant_files<C, E>(
+    db: &Database,
+    client: &Client<C, E>,
+    task: &str,
+    options: &ContextOptions,
+) -> Result<RelevantContext, SearchError>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    let results = search_index_with_client(
+        db,
+        client,
+        task,
+        SearchOptions {
+            limit: options.limit,
+            source_filter: options.source_filter.clone(),
+            exact_source: options.exact_source,
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(pack(results, options.max_tokens))
+}
+
+/// Group chunks by file, best first, keeping those that fit the token budget
+fn pack(mut results: Vec<SearchResult>, max_tokens: usize) -> RelevantContext {
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut context = RelevantContext::default();
+    for result in results {
+        let tokens = crate::search::bench::estimate_tokens(&result.text);
+        if context.tokens + tokens > max_tokens {
+            // A smaller chunk further down may still fit
+            continue;
+        }
+        context.tokens += tokens;
+        match context
+            .files
+            .iter_mut()
+            .find(|file| file.path == result.url)
+        {
+            Some(file) => file.chunks.push(result),
+            None => context.files.push(RelevantFile {
+                path: result.url.clone(),
+                score: result.score,
+                chunks: vec![result],
+            }),
+        }
+    }
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_groups_files_within_budget() {
+        let results = vec![
+            SearchResult::builder("src/config.rs")
+                .text("fn load() {}")
+                .score(0.6)
+                .build(),
+            SearchResult::builder("src/main.rs")
+                .text("fn main() {}")
+                .score(0.9)
+                .build(),
+            SearchResult::builder("src/big.rs")
+                .text("x".repeat(400))
+                .score(0.8)
+                .build(),
+            SearchResult::builder("src/config.rs")
+                .text("struct Config;")
+                .score(0.7)
+                .build(),
+        ];
+
+        let context = pack(results, 20);
+
+        let paths: Vec<_> = context.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/main.rs", "src/config.rs"]);
+        assert_eq!(context.files[1].chunks.len(), 2);
+        assert_eq!(context.files[1].score, 0.7);
+        assert!(context.tokens <= 20);
+
+        let prompt = context.to_prompt();
+        assert!(prompt.contains("### src/main.rs (score 0.900)\n```\nfn main() {}\n```"));
+        assert!(!prompt.contains("src/big.rs"));
+    }
+
+    #[test]
+    fn test_pack_with_no_budget_is_empty() {
+        let context = pack(
+            vec![
+                SearchResult::builder("src/main.rs")
+                    .text("fn main() {}")
+                    .score(0.9)
+                    .build(),
+            ],
+            0,
+        );
+        assert!(context.is_empty());
+    }
+}
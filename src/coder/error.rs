@@ -26,9 +26,15 @@ pub enum CoderError {
     #[error("Tool execution failed: {0}")]
     ToolError(#[from] ToolSetError),
 
+    #[error("Tool call '{0}' was denied by the user")]
+    ToolDenied(String),
+
     #[error("Failed to serialize tool arguments: {0}")]
     ToolArgsSerializationError(#[from] serde_json::Error),
 
+    #[error("Artifact error: {0}")]
+    Artifact(String),
+
     #[error("Agent error: {0}")]
     AgentError(String),
 
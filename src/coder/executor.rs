@@ -5,6 +5,8 @@
 //! the complexity of maintaining conversation history, processing tool calls,
 //! and managing the interaction loop.
 
+use crate::audit::{AuditEntry, AuditLog};
+use crate::coder::artifacts::{ArtifactStore, READ_ARTIFACT_TOOL};
 use crate::coder::error::CoderError;
 use rig::completion::CompletionResponse;
 use rig::one_or_many::OneOrMany; // Corrected import path
@@ -14,7 +16,7 @@ use rig::{
     message::{AssistantContent, Message, ToolCall, ToolResult, ToolResultContent, UserContent},
 };
 use serde_json::json;
-use std::{collections::VecDeque, sync::Arc}; // Removed unused std imports
+use std::{collections::VecDeque, sync::Arc, time::Instant}; // Removed unused std imports
 use tokio::sync::mpsc::Sender; // Removed unused mpsc imports
 use tracing::{debug, error, info, instrument, warn};
 
@@ -44,6 +46,9 @@ pub enum ExecutorEvent {
     Finished { summary: String },
 }
 
+/// Decides whether a tool call may run; denied calls are reported back to the agent as errors.
+pub type ToolApproval = Arc<dyn Fn(&ToolCall) -> bool + Send + Sync>;
+
 /// Contains the final state after executor execution.
 pub struct ExecutionOutcome {
     /// The full conversation history
@@ -121,6 +126,12 @@ where
     history: Vec<Message>,
     /// Queue of pending responses from the agent
     responses: VecDeque<AssistantContent>,
+    /// Approval asked before each tool call, if any
+    approval: Option<ToolApproval>,
+    /// Store of tool results over the result budget, if results are limited
+    artifacts: Option<ArtifactStore>,
+    /// Log tool calls are recorded in, if any
+    audit: Option<AuditLog>,
 }
 
 impl<C> AgentExecutor<C>
@@ -139,6 +150,9 @@ where
             max_iterations,
             history: Vec::new(),
             responses: VecDeque::new(),
+            approval: None,
+            artifacts: None,
+            audit: None,
         }
     }
 
@@ -148,6 +162,35 @@ where
         self
     }
 
+    /// Asks for approval before each tool call; denied calls are not executed
+    pub fn with_approval(mut self, approval: ToolApproval) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    /// Limits tool results to the store's budget, storing larger ones as artifacts
+    ///
+    /// The agent gets a `read_artifact` tool, answered by the executor, to page through them.
+    pub fn with_artifacts(mut self, artifacts: ArtifactStore) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// Records each tool call, with its approval decision, in an audit log
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Definitions of the tools sent to the agent
+    fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions = self.tool_defs.as_ref().clone();
+        if let Some(artifacts) = &self.artifacts {
+            definitions.push(artifacts.tool_definition());
+        }
+        definitions
+    }
+
     async fn request(
         &self,
         initial_prompt: String,
@@ -159,7 +202,7 @@ where
             .completion(initial_prompt.clone(), self.history.clone())
             .await
             .map_err(CoderError::CompletionError)?
-            .tools(self.tool_defs.as_ref().clone())
+            .tools(self.tool_definitions())
             .send()
             .await
             .map_err(CoderError::CompletionError)
@@ -402,8 +445,45 @@ where
         tool_id = %tool_call.id
     ))]
     async fn execute_tool_call(&self, tool_call: &ToolCall) -> Result<String, CoderError> {
+        let started = Instant::now();
+        let result = self.run_tool_call(tool_call).await;
+
+        if let Some(audit) = &self.audit {
+            let args = tool_call.function.arguments.to_string();
+            let entry =
+                AuditEntry::new("agent", &tool_call.function.name, &args, started.elapsed())
+                    .with_result(result.as_ref().map(String::len));
+            let entry = match (&self.approval, &result) {
+                (Some(_), Err(CoderError::ToolDenied(_))) => entry.with_permission("denied", true),
+                (Some(_), _) => entry.with_permission("approved", false),
+                (None, _) => entry,
+            };
+            audit.record_or_warn(&entry);
+        }
+        result
+    }
+
+    /// Runs a tool call, answering artifact reads and asking for approval first
+    async fn run_tool_call(&self, tool_call: &ToolCall) -> Result<String, CoderError> {
         let name = &tool_call.function.name;
 
+        if let Some(artifacts) = self
+            .artifacts
+            .as_ref()
+            .filter(|_| name == READ_ARTIFACT_TOOL)
+        {
+            return artifacts.read(&tool_call.function.arguments);
+        }
+
+        if self
+            .approval
+            .as_ref()
+            .is_some_and(|approve| !approve(tool_call))
+        {
+            info!(tool_name = %name, "Tool call denied");
+            return Err(CoderError::ToolDenied(name.clone()));
+        }
+
         // Convert arguments to JSON string
         let args_json = serde_json::to_string(&tool_call.function.arguments)
             .map_err(|e| CoderError::ToolArgsSerializationError(e))?;
@@ -422,6 +502,8 @@ where
         if result.is_empty() {
             warn!(tool_name = %name, "Tool returned empty result");
             Ok(json!({ "result": "Tool returned no result" }).to_string())
+        } else if let Some(artifacts) = &self.artifacts {
+            Ok(artifacts.limit(name, result))
         } else {
             Ok(result)
         }
@@ -439,7 +521,7 @@ where
             .completion("", self.history.clone())
             .await
             .map_err(CoderError::CompletionError)?
-            .tools(self.tool_defs.as_ref().clone())
+            .tools(self.tool_definitions())
             .send()
             .await
         {
@@ -478,7 +560,7 @@ where
             .completion(continue_prompt, self.history.clone())
             .await
             .map_err(CoderError::CompletionError)?
-            .tools(self.tool_defs.as_ref().clone())
+            .tools(self.tool_definitions())
             .send()
             .await
         {
@@ -529,6 +611,49 @@ mod tests {
         assert_eq!(executor.max_iterations, 10);
     }
 
+    #[tokio::test]
+    async fn test_denied_tool_call_is_not_executed() {
+        let agent = AgentBuilder::new(MockCompletionModel::new()).build();
+        let executor = AgentExecutor::new(Arc::new(agent), Arc::new(vec![]), 10).with_approval(
+            Arc::new(|call: &ToolCall| call.function.name != "write_file"),
+        );
+
+        let call = ToolCall {
+            id: "1".to_string(),
+            function: rig::message::ToolFunction {
+                name: "write_file".to_string(),
+                arguments: json!({ "path": "a.txt", "content": "a" }),
+            },
+        };
+
+        let result = executor.execute_tool_call(&call).await;
+        assert!(matches!(result, Err(CoderError::ToolDenied(name)) if name == "write_file"));
+    }
+
+    #[tokio::test]
+    async fn test_read_artifact_is_answered_by_executor() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let artifacts = ArtifactStore::new(temp_dir.path(), 5);
+        let stored = artifacts.limit("show_file", "0123456789".to_string());
+        let id = stored.split('\'').nth(1).unwrap().to_string();
+
+        let agent = AgentBuilder::new(MockCompletionModel::new()).build();
+        let executor =
+            AgentExecutor::new(Arc::new(agent), Arc::new(vec![]), 10).with_artifacts(artifacts);
+        assert_eq!(executor.tool_definitions()[0].name, READ_ARTIFACT_TOOL);
+
+        let call = ToolCall {
+            id: "1".to_string(),
+            function: rig::message::ToolFunction {
+                name: READ_ARTIFACT_TOOL.to_string(),
+                arguments: json!({ "id": id, "offset": 5 }),
+            },
+        };
+        let page: serde_json::Value =
+            serde_json::from_str(&executor.execute_tool_call(&call).await.unwrap()).unwrap();
+        assert_eq!(page["content"], "56789");
+    }
+
     // More comprehensive tests would mock the agent and tool responses
     // to test the full execution flow, but that requires more complex setup
 }
@@ -0,0 +1,230 @@
+//! Embedding-based tool selection for coding agents.
+//!
+//! Sending every tool definition with every request costs context and makes the agent more
+//! likely to pick a wrong tool as the catalog grows. A `ToolIndex` embeds the documents
+//! describing each tool, in the way rig's `ToolEmbedding::embedding_docs` does for dynamic
+//! tools, and selects only the tools closest to the task prompt. Tools the agent always
+//! needs, such as `finish`, are kept regardless of their score.
+
+use rig::completion::ToolDefinition;
+use rig::embeddings::{EmbeddingError, EmbeddingModel};
+
+use crate::search::bench::cosine_similarity;
+
+/// Tools a coding agent keeps whatever the prompt: it must be able to end the task and to
+/// ask for access
+pub const ESSENTIAL_TOOLS: &[&str] = &["finish", "request_permission"];
+
+/// A tool with the embeddings of its documents
+#[derive(Debug, Clone)]
+struct IndexedTool {
+    definition: ToolDefinition,
+    vectors: Vec<Vec<f64>>,
+}
+
+/// Vector index of tool definitions
+#[derive(Debug, Clone, Default)]
+pub struct ToolIndex {
+    tools: Vec<IndexedTool>,
+}
+
+impl ToolIndex {
+    /// Embed the documents of each tool
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Model embedding the documents and, later, the prompts
+    /// * `definitions` - Definitions of the tools in the catalog
+    pub async fn build<E: EmbeddingModel>(
+        model: &E,
+        definitions: Vec<ToolDefinition>,
+    ) -> Result<Self, EmbeddingError> {
+        let documents: Vec<(usize, String)> = definitions
+            .iter()
+            .enumerate()
+            .flat_map(|(i, definition)| {
+                embedding_docs(definition)
+                    .into_iter()
+                    .map(move |document| (i, document))
+            })
+            .collect();
+
+        let mut tools: Vec<IndexedTool> = definitions
+            .into_iter()
+            .map(|definition| IndexedTool {
+                definition,
+                vectors: Vec::new(),
+            })
+            .collect();
+        for batch in documents.chunks(E::MAX_DOCUMENTS.max(1)) {
+            let texts = batch.iter().map(|(_, document)| document.clone());
+            let embeddings = model.embed_texts(texts).await?;
+            for ((i, _), embedding) in batch.iter().zip(embeddings) {
+                tools[*i].vectors.push(embedding.vec);
+            }
+        }
+        Ok(Self { tools })
+    }
+
+    /// Number of tools in the index
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Whether the index has no tools
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// The tools most relevant to a prompt
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Model the index was built with
+    /// * `prompt` - Task or message the agent works on
+    /// * `limit` - Most tools to select by relevance
+    /// * `always` - Tools selected regardless of their relevance, on top of the limit
+    ///
+    /// # Returns
+    ///
+    /// Definitions of the selected tools, in catalog order
+    pub async fn select<E: EmbeddingModel>(
+        &self,
+        model: &E,
+        prompt: &str,
+        limit: usize,
+        always: &[&str],
+    ) -> Result<Vec<ToolDefinition>, EmbeddingError> {
+        if self.tools.len() <= limit {
+            return Ok(self.definitions());
+        }
+        let query = model.embed_text(prompt).await?;
+        Ok(self.select_by_vector(&query.vec, limit, always))
+    }
+
+    /// Definitions of all tools, in catalog order
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .iter()
+            .map(|tool| tool.definition.clone())
+            .collect()
+    }
+
+    /// The tools closest to an embedded prompt
+    fn select_by_vector(
+        &self,
+        query: &[f64],
+        limit: usize,
+        always: &[&str],
+    ) -> Vec<ToolDefinition> {
+        // A tool is as relevant as its best matching document
+        let mut ranked: Vec<(usize, f64)> = self
+            .tools
+            .iter()
+            .enumerate()
+            .filter(|(_, tool)| !always.contains(&tool.definition.name.as_str()))
+            .map(|(i, tool)| {
+                let score = tool
+                    .vectors
+                    .iter()
+                    .map(|vector| cosine_similarity(query, vector))
+                    .fold(f64::MIN, f64::max);
+                (i, score)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(limit);
+
+        self.tools
+            .iter()
+            .enumerate()
+            .filter(|(i, tool)| {
+                always.contains(&tool.definition.name.as_str())
+                    || ranked.iter().any(|(selected, _)| selected == i)
+            })
+            .map(|(_, tool)| tool.definition.clone())
+            .collect()
+    }
+}
+
+/// Documents a tool is retrieved by: its name with its description, and the descriptions
+/// of its parameters
+pub fn embedding_docs(definition: &ToolDefinition) -> Vec<String> {
+    let mut documents = vec![format!(
+        "{}: {}",
+        definition.name.replace('_', " "),
+        definition.description
+    )];
+    if let Some(properties) = definition.parameters["properties"].as_object() {
+        let parameters: Vec<String> = properties
+            .iter()
+            .filter_map(|(name, schema)| {
+                let description = schema["description"].as_str()?;
+                Some(format!("{}: {}", name.replace('_', " "), description))
+            })
+            .collect();
+        if !parameters.is_empty() {
+            documents.push(parameters.join("\n"));
+        }
+    }
+    documents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock_model::MockEmbeddingModel;
+    use serde_json::json;
+
+    fn definition(name: &str, description: &str) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path of the file" }
+                }
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_relevant_tools() {
+        let model = MockEmbeddingModel::new();
+        let index = ToolIndex::build(
+            &model,
+            vec![
+                definition("show_file", "Show the contents of a file"),
+                definition("execute_shell_command", "Run a shell command"),
+                definition("search", "Search indexed documentation websites"),
+                definition("finish", "Report that the task is done"),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(index.len(), 4);
+
+        let selected = index
+            .select(&model, "run the shell command cargo test", 1, &["finish"])
+            .await
+            .unwrap();
+        let names: Vec<&str> = selected.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["execute_shell_command", "finish"]);
+
+        let all = index.select(&model, "anything", 10, &[]).await.unwrap();
+        assert_eq!(all.len(), 4);
+    }
+
+    #[test]
+    fn test_embedding_docs() {
+        let documents = embedding_docs(&definition("show_file", "Show a file"));
+        assert_eq!(
+            documents,
+            vec![
+                "show file: Show a file".to_string(),
+                "path: Path of the file".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,547 @@
+//! # Configuration Module
+//!
+//! This module reads and writes `hal.toml`, the file `hal init` creates so first-run setup
+//! doesn't require exporting environment variables, and keeps API keys in the OS keyring.
+//! Environment variables still take precedence over both.
+//!
+//! ## Key Components
+//!
+//! - `HalConfig`: The provider, API keys, default database, Vertex AI project, Azure
+//!   OpenAI resource, rate-limit tier, fallback model chain, model concurrency, embedding
+//!   backend, completion cache, sampling parameters, MCP session quotas, index retention
+//!   limits and command plugins
+//! - `Provider` / `RateLimitTier`: The choices `hal init` offers
+//! - `config_path`: Where the configuration is read from
+//! - `api_key`: An API key from the environment, the keyring or the configuration
+//! - `store_api_key` / `remove_api_key`: Manage API keys in the OS keyring
+//! - `require_keyring`: Whether this build has an OS keyring
+//!
+//! ## Location
+//!
+//! `HAL_CONFIG` names the file if set. Otherwise `hal.toml` in the working directory is
+//! used if it exists, then `$XDG_CONFIG_HOME/hal/hal.toml` (`~/.config/hal/hal.toml`).
+//!
+//! ## Keyring
+//!
+//! Keys are stored under the service `hal`, with the environment variable they stand in for
+//! as the user name. The `[api_keys]` table of `hal.toml` is only used where no keyring is
+//! available. The macOS Keychain and the Windows Credential Manager are always built in;
+//! other systems use the Secret Service with the `secret-service` feature, which needs the
+//! D-Bus headers, and have no keyring without it.
+//!
+//! ## Example
+//!
+//! ```toml
+//! provider = "gemini"
+//! database = "http://127.0.0.1:8080"
+//! rate_limit_tier = "free"
+//!
+//! # Requests in flight per model, on top of the rate limits
+//! [concurrency]
+//! completion = 8
+//! embedding = 16
+//!
+//! # Completions fall back to the next model on rate-limit or unavailable errors
+//! [[model_chain.models]]
+//! model = "gemini-2.0-flash"
+//!
+//! [[model_chain.models]]
+//! model = "gemini-2.0-flash-lite"
+//! concurrency = 4
+//!
+//! [[model_chain.models]]
+//! model = "gemini-2.0-flash"
+//! api_key = "GEMINI_FREE_API_KEY"
+//!
+//! # Falls back to Vertex AI, authenticated with Application Default Credentials
+//! [[model_chain.models]]
+//! provider = "vertex"
+//! model = "gemini-2.0-flash"
+//!
+//! [vertex]
+//! project = "docs-project"
+//! location = "europe-west4"
+//!
+//! # Falls back to a deployment of an Azure OpenAI resource
+//! [[model_chain.models]]
+//! provider = "azure"
+//! model = "gpt-4o-mini"
+//!
+//! [azure]
+//! resource = "docs-openai"
+//! api_version = "2024-10-21"
+//!
+//! # Embed offline with a local model (needs the `local-embeddings` feature)
+//! [embedding]
+//! provider = "local"
+//! model = "BAAI/bge-base-en-v1.5"
+//!
+//! # Completions indexing reuses instead of paying for again, see `--no-cache`
+//! [completion_cache]
+//! ttl_hours = 72
+//!
+//! [generation]
+//! temperature = 0.2
+//! max_tokens = 1024
+//!
+//! [mcp_quotas]
+//! max_shell_commands = 200
+//! max_bytes_written = 10485760
+//! max_searches_per_minute = 30
+//!
+//! # Limits `hal gc` enforces by dropping the least recently used chunks
+//! [retention]
+//! max_chunks_per_domain = 50000
+//! max_unused_days = 180
+//! max_database_mb = 2048
+//!
+//! # Command plugins, see `hal::plugin`
+//! [[plugins]]
+//! name = "corp"
+//! command = "hal-corp-plugin"
+//! schemes = ["jira"]
+//!
+//! # Only used where no OS keyring is available
+//! [api_keys]
+//! GEMINI_API_KEY = "..."
+//! GEMINI_FREE_API_KEY = "..."
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::index::retention::RetentionPolicy;
+use crate::mcp::QuotaConfig;
+use crate::model::azure::AzureConfig;
+use crate::model::vertex::VertexConfig;
+use crate::model::{
+    CompletionCacheConfig, EmbeddingConfig, GenerationOptions, ModelChain, ModelConcurrency,
+};
+use crate::plugin::PluginConfig;
+
+/// Name of the configuration file
+pub const CONFIG_FILE: &str = "hal.toml";
+
+/// Environment variable naming the configuration file
+pub const CONFIG_ENV: &str = "HAL_CONFIG";
+
+/// Keyring service API keys are stored under
+pub const KEYRING_SERVICE: &str = "hal";
+
+/// Errors reading or writing the configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// Reading or writing the file failed
+    #[error("Configuration file error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The file is not valid TOML or has unexpected values
+    #[error("Invalid configuration: {0}")]
+    Parse(String),
+
+    /// No location for the configuration file could be determined
+    #[error("No configuration directory found; set HAL_CONFIG or HOME")]
+    NoLocation,
+
+    /// The OS keyring could not be used
+    #[error("Keyring error: {0}")]
+    Keyring(String),
+
+    /// A provider of the model chain could not be set up from its table
+    #[error("Provider configuration error: {0}")]
+    Provider(String),
+}
+
+/// LLM provider used for completions and embeddings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// Google Gemini
+    #[default]
+    Gemini,
+
+    /// Gemini on Google Cloud's Vertex AI, authenticated with Application Default
+    /// Credentials
+    Vertex,
+
+    /// OpenAI models deployed to an Azure OpenAI resource
+    Azure,
+}
+
+impl Provider {
+    /// Environment variables holding the provider's API keys, with their purpose
+    pub fn api_key_vars(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Provider::Gemini => &[
+                ("GEMINI_API_KEY", "indexing"),
+                ("GEMINI_FREE_API_KEY", "search and chat"),
+            ],
+            Provider::Vertex => &[],
+            Provider::Azure => &[(crate::model::azure::API_KEY_VAR, "completions")],
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Gemini => f.write_str("gemini"),
+            Provider::Vertex => f.write_str("vertex"),
+            Provider::Azure => f.write_str("azure"),
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "gemini" => Ok(Provider::Gemini),
+            "vertex" => Ok(Provider::Vertex),
+            "azure" => Ok(Provider::Azure),
+            other => Err(format!(
+                "unknown provider '{}', expected gemini, vertex or azure",
+                other
+            )),
+        }
+    }
+}
+
+/// Request quotas the LLM clients throttle themselves to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitTier {
+    /// Free tier quotas for every client
+    Free,
+
+    /// Paid tier quotas for indexing, free tier quotas for search and chat
+    #[default]
+    Paid,
+}
+
+impl fmt::Display for RateLimitTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitTier::Free => f.write_str("free"),
+            RateLimitTier::Paid => f.write_str("paid"),
+        }
+    }
+}
+
+impl FromStr for RateLimitTier {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "free" => Ok(RateLimitTier::Free),
+            "paid" => Ok(RateLimitTier::Paid),
+            other => Err(format!(
+                "unknown rate-limit tier '{}', expected free or paid",
+                other
+            )),
+        }
+    }
+}
+
+/// Contents of `hal.toml`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HalConfig {
+    /// LLM provider
+    pub provider: Provider,
+
+    /// Default database: a libsql server URL or a local file path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+
+    /// Request quotas of the LLM clients
+    pub rate_limit_tier: RateLimitTier,
+
+    /// Most requests in flight per model
+    #[serde(skip_serializing_if = "is_default")]
+    pub concurrency: ModelConcurrency,
+
+    /// Completion models tried in order by indexing and search
+    #[serde(skip_serializing_if = "ModelChain::is_empty")]
+    pub model_chain: ModelChain,
+
+    /// Vertex AI project and location, for the `vertex` provider
+    #[serde(skip_serializing_if = "is_default")]
+    pub vertex: VertexConfig,
+
+    /// Azure OpenAI resource and API version, for the `azure` provider
+    #[serde(skip_serializing_if = "is_default")]
+    pub azure: AzureConfig,
+
+    /// Model embedding chunks and queries for indexing and search
+    #[serde(skip_serializing_if = "is_default")]
+    pub embedding: EmbeddingConfig,
+
+    /// On-disk cache of the completions indexing makes
+    #[serde(skip_serializing_if = "is_default")]
+    pub completion_cache: CompletionCacheConfig,
+
+    /// Sampling parameters for completions, overridden by command line flags
+    #[serde(skip_serializing_if = "is_default")]
+    pub generation: GenerationOptions,
+
+    /// Per-session limits of the MCP server, overridden by `hal mcp` flags
+    #[serde(skip_serializing_if = "is_default")]
+    pub mcp_quotas: QuotaConfig,
+
+    /// Limits on what indexes keep, overridden by `hal gc` flags
+    #[serde(skip_serializing_if = "is_default")]
+    pub retention: RetentionPolicy,
+
+    /// Plugins run as external commands, registered at startup
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginConfig>,
+
+    /// API keys by the environment variable they stand in for
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub api_keys: BTreeMap<String, String>,
+}
+
+/// Whether a value is its type's default, to leave it out of the file
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+impl HalConfig {
+    /// Read a configuration file
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content)
+            .map_err(|e| ConfigError::Parse(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Read the configuration file from its default location, or the defaults if there is none
+    pub fn load() -> Result<Self, ConfigError> {
+        match config_path() {
+            Some(path) if path.exists() => Self::from_path(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Write the configuration, readable only by the owner since it may hold API keys
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content =
+            toml::to_string_pretty(self).map_err(|e| ConfigError::Parse(e.to_string()))?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(path)?, content.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The per-user configuration file, `$XDG_CONFIG_HOME/hal/hal.toml` or `~/.config/hal/hal.toml`
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("hal").join(CONFIG_FILE))
+}
+
+/// The configuration file in effect: `HAL_CONFIG`, `./hal.toml` or the per-user file
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let local = PathBuf::from(CONFIG_FILE);
+    if local.exists() {
+        return Some(local);
+    }
+    user_config_path()
+}
+
+/// The configuration of this process, read once
+///
+/// An unreadable file is logged and treated as empty so environment variables keep working.
+pub fn current() -> &'static HalConfig {
+    static CONFIG: OnceLock<HalConfig> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        HalConfig::load().unwrap_or_else(|e| {
+            warn!("Ignoring configuration: {}", e);
+            HalConfig::default()
+        })
+    })
+}
+
+/// An API key from its environment variable, the OS keyring or the configuration file
+///
+/// # Arguments
+///
+/// * `name` - The environment variable, e.g. `GEMINI_API_KEY`
+pub fn api_key(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .or_else(|| keyring_api_key(name))
+        .or_else(|| current().api_keys.get(name).cloned())
+}
+
+/// Fail unless this build has an OS keyring to keep API keys in
+pub fn require_keyring() -> Result<(), ConfigError> {
+    if cfg!(any(
+        target_os = "macos",
+        target_os = "windows",
+        feature = "secret-service"
+    )) {
+        Ok(())
+    } else {
+        Err(ConfigError::Keyring(
+            "no OS keyring in this build; rebuild with `--features secret-service` or set \
+             the key in the environment or hal.toml"
+                .to_string(),
+        ))
+    }
+}
+
+/// The keyring entry of an API key
+fn keyring_entry(name: &str) -> Result<keyring::Entry, ConfigError> {
+    require_keyring()?;
+    keyring::Entry::new(KEYRING_SERVICE, name).map_err(|e| ConfigError::Keyring(e.to_string()))
+}
+
+/// An API key from the OS keyring; a missing keyring is treated like a missing key
+fn keyring_api_key(name: &str) -> Option<String> {
+    require_keyring().ok()?;
+    match keyring_entry(name).and_then(|entry| {
+        entry
+            .get_password()
+            .map_err(|e| ConfigError::Keyring(e.to_string()))
+    }) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            debug!("No {} in the keyring: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Store an API key in the OS keyring
+///
+/// # Arguments
+///
+/// * `name` - The environment variable the key stands in for, e.g. `GEMINI_API_KEY`
+/// * `key` - The API key
+pub fn store_api_key(name: &str, key: &str) -> Result<(), ConfigError> {
+    keyring_entry(name)?
+        .set_password(key)
+        .map_err(|e| ConfigError::Keyring(e.to_string()))
+}
+
+/// Remove an API key from the OS keyring
+///
+/// # Returns
+///
+/// Whether a key was stored
+pub fn remove_api_key(name: &str) -> Result<bool, ConfigError> {
+    match keyring_entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(ConfigError::Keyring(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hal").join(CONFIG_FILE);
+
+        let mut config = HalConfig {
+            database: Some("docs.db".to_string()),
+            rate_limit_tier: RateLimitTier::Free,
+            ..HalConfig::default()
+        };
+        config
+            .api_keys
+            .insert("GEMINI_API_KEY".to_string(), "secret".to_string());
+        config.save(&path).unwrap();
+
+        assert_eq!(HalConfig::from_path(&path).unwrap(), config);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("rate_limit_tier = \"free\""));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_defaults_and_invalid_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+
+        std::fs::write(&path, "database = \"http://127.0.0.1:8080\"\n").unwrap();
+        let config = HalConfig::from_path(&path).unwrap();
+        assert_eq!(config.provider, Provider::Gemini);
+        assert_eq!(config.rate_limit_tier, RateLimitTier::Paid);
+        assert!(config.model_chain.is_empty());
+        assert!(config.completion_cache.cache().is_some());
+
+        std::fs::write(
+            &path,
+            "[[model_chain.models]]\nmodel = \"gemini-2.0-flash\"\n\n\
+             [[model_chain.models]]\nmodel = \"gemini-2.0-flash-lite\"\n\
+             api_key = \"GEMINI_FREE_API_KEY\"\n\n\
+             [[model_chain.models]]\nprovider = \"vertex\"\nmodel = \"gemini-2.0-flash\"\n\n\
+             [[model_chain.models]]\nprovider = \"azure\"\nmodel = \"chat-prod\"\n\n\
+             [vertex]\nproject = \"docs-project\"\n\n\
+             [azure]\nresource = \"docs-openai\"\n",
+        )
+        .unwrap();
+        let config = HalConfig::from_path(&path).unwrap();
+        let chain = config.model_chain;
+        assert_eq!(chain.models.len(), 4);
+        assert_eq!(chain.models[1].provider, Provider::Gemini);
+        assert_eq!(chain.models[2].provider, Provider::Vertex);
+        assert_eq!(config.vertex.project.as_deref(), Some("docs-project"));
+        assert_eq!(chain.models[3].provider, Provider::Azure);
+        assert_eq!(config.azure.resource.as_deref(), Some("docs-openai"));
+        assert_eq!(
+            chain.models[1].api_key.as_deref(),
+            Some("GEMINI_FREE_API_KEY")
+        );
+        assert_eq!(
+            chain.label(config.provider),
+            "gemini/gemini-2.0-flash,gemini/gemini-2.0-flash-lite,vertex/gemini-2.0-flash,azure/chat-prod"
+        );
+
+        std::fs::write(&path, "[completion_cache]\nenabled = false\n").unwrap();
+        let config = HalConfig::from_path(&path).unwrap();
+        assert!(config.completion_cache.cache().is_none());
+
+        std::fs::write(&path, "rate_limit_tier = \"unlimited\"\n").unwrap();
+        assert!(matches!(
+            HalConfig::from_path(&path),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!("enterprise".parse::<RateLimitTier>().is_err());
+    }
+}
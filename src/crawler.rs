@@ -9,7 +9,17 @@
 //! - `CrawlerConfig`: Configuration for the crawler, including depth, rate limits, etc.
 //! - `CrawledPage`: Represents a processed web page with content and metadata
 //! - `crawl_website`: Main function to crawl a website with the given configuration
+//! - `page_from_html`: Re-runs content extraction on stored raw HTML
+//! - `report`: Per-URL crawl outcomes (fetched, skipped by robots.txt, failed, redirected, empty)
 //! - Content extraction utilities for converting HTML to clean, processable text
+//! - `link_audit`: Finds vanished pages and dead links of an indexed site with `HEAD` requests
+//! - `documents`: Linked PDF and CSV files downloaded and extracted during a crawl
+//! - `http_cache`: Conditional requests revalidating pages cached by an earlier crawl
+//! - `mail`: mbox and Maildir ingestion for indexing mail archives
+//! - `git`: Files of a repository changed since the commit it was last indexed at
+//! - `transcription`: Audio files and podcast feeds as timestamped transcripts (behind a feature)
+//! - `traps`: Detection of calendars, faceted filters and other endless URL spaces
+//! - `connectors`: API-based sources such as Confluence and Notion (behind features)
 //!
 //! ## Features
 //!
@@ -25,16 +35,27 @@
 //! to the processor module which then chunks it for embedding and indexing.
 
 mod config;
+#[cfg(any(feature = "confluence", feature = "notion"))]
+pub mod connectors;
 mod content_extraction;
+pub mod documents;
 mod error;
+pub mod git;
+pub mod http_cache;
+pub mod link_audit;
+pub mod mail;
+pub mod report;
 mod spider_integration;
 pub mod storage;
+#[cfg(feature = "transcription")]
+pub mod transcription;
+pub mod traps;
 
 // Re-export important types and functions
-pub use config::CrawlerConfig;
+pub use config::{CrawlerConfig, CrawlerConfigBuilder};
 pub use content_extraction::extract_metadata;
 pub use error::CrawlError;
-pub use spider_integration::crawl_website;
+pub use spider_integration::{crawl_website, crawl_website_with_report, page_from_html};
 
 use serde::{Deserialize, Serialize};
 
@@ -49,6 +70,10 @@ pub struct CrawledPage {
 
     /// Metadata extracted from the page
     pub metadata: PageMetadata,
+
+    /// Raw HTML of the page, kept when `CrawlerConfig::keep_raw_html` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html: Option<String>,
 }
 
 /// Metadata for a crawled page
@@ -68,6 +93,14 @@ pub struct PageMetadata {
 
     /// Domain of the page
     pub domain: String,
+
+    /// Tags or keywords of the page
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// schema.org or OpenGraph type of the page, e.g. `BlogPosting` or `article`
+    #[serde(default)]
+    pub content_type: Option<String>,
 }
 
 #[cfg(test)]
@@ -82,11 +115,14 @@ mod tests {
             publication_date: None,
             author: Some("Test Author".to_string()),
             domain: "example.com".to_string(),
+            tags: vec!["test".to_string()],
+            content_type: None,
         };
 
         assert_eq!(metadata.title.as_deref().unwrap(), "Test Page");
         assert_eq!(metadata.description.as_deref().unwrap(), "Test description");
         assert_eq!(metadata.author.as_deref().unwrap(), "Test Author");
         assert_eq!(metadata.domain, "example.com");
+        assert_eq!(metadata.tags, vec!["test"]);
     }
 }
@@ -16,9 +16,21 @@
 //! - Content selection via CSS selectors
 //! - Exclusion patterns for boilerplate content (navigation, headers, footers)
 //! - User-agent customization
+//! - Downloading linked PDF and CSV documents up to a size cap
+//! - Response size limits and binary content guards
+//! - Conditional requests for pages cached by an earlier crawl
+//! - HTTP and SOCKS5 proxies and per-crawl TLS settings
+//! - Crawl trap detection limits
+//! - Cancellation, returning the pages fetched so far
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::cancel::CancellationToken;
+use crate::crawler::error::CrawlError;
+use crate::crawler::http_cache::HttpValidators;
+
 /// Configuration for the crawler
 #[derive(Debug, Clone)]
 pub struct CrawlerConfig {
@@ -45,6 +57,46 @@ pub struct CrawlerConfig {
 
     /// CSS selectors for elements to exclude
     pub exclude_selectors: Vec<String>,
+
+    /// Whether to keep the raw HTML of crawled pages
+    pub keep_raw_html: bool,
+
+    /// Whether to download linked PDF and CSV documents and index their text
+    pub follow_documents: bool,
+
+    /// Largest linked document to download, in bytes
+    pub max_document_bytes: u64,
+
+    /// Largest page to convert to Markdown, in bytes
+    pub max_page_bytes: u64,
+
+    /// Whether to skip binary files, static assets and minified scripts instead of
+    /// converting them to Markdown
+    pub skip_binary_content: bool,
+
+    /// Cache validators of pages from an earlier crawl, by URL, for conditional requests
+    pub http_cache: HashMap<String, HttpValidators>,
+
+    /// Proxy all requests go through, e.g. `http://proxy:3128` or `socks5h://127.0.0.1:9050`
+    pub proxy: Option<String>,
+
+    /// Whether to accept invalid TLS certificates, e.g. of an intercepting corporate proxy
+    pub accept_invalid_certs: bool,
+
+    /// PEM file with an additional CA certificate to trust for hal's own requests
+    pub ca_certificate: Option<PathBuf>,
+
+    /// Whether to stop following URL patterns that look like crawl traps
+    pub detect_traps: bool,
+
+    /// URLs allowed per numbered path pattern or per path with query strings
+    pub max_urls_per_pattern: usize,
+
+    /// Times a path segment may occur in one URL
+    pub max_segment_repeats: usize,
+
+    /// Token stopping the crawl, which then returns the pages fetched so far
+    pub cancel: CancellationToken,
 }
 
 impl Default for CrawlerConfig {
@@ -73,6 +125,19 @@ impl Default for CrawlerConfig {
                 "#sidebar".to_string(),
                 "#comments".to_string(),
             ],
+            keep_raw_html: false,
+            follow_documents: false,
+            max_document_bytes: 10 * 1024 * 1024,
+            max_page_bytes: 2 * 1024 * 1024,
+            skip_binary_content: true,
+            http_cache: HashMap::new(),
+            proxy: None,
+            accept_invalid_certs: false,
+            ca_certificate: None,
+            detect_traps: true,
+            max_urls_per_pattern: 50,
+            max_segment_repeats: 2,
+            cancel: CancellationToken::new(),
         }
     }
 }
@@ -133,6 +198,84 @@ impl CrawlerConfigBuilder {
         self
     }
 
+    /// Set whether to keep the raw HTML of crawled pages
+    pub fn keep_raw_html(mut self, keep_raw_html: bool) -> Self {
+        self.config.keep_raw_html = keep_raw_html;
+        self
+    }
+
+    /// Set whether to download linked PDF and CSV documents
+    pub fn follow_documents(mut self, follow_documents: bool) -> Self {
+        self.config.follow_documents = follow_documents;
+        self
+    }
+
+    /// Set the largest linked document to download, in bytes
+    pub fn max_document_bytes(mut self, max_document_bytes: u64) -> Self {
+        self.config.max_document_bytes = max_document_bytes;
+        self
+    }
+
+    /// Set the largest page to convert to Markdown, in bytes
+    pub fn max_page_bytes(mut self, max_page_bytes: u64) -> Self {
+        self.config.max_page_bytes = max_page_bytes;
+        self
+    }
+
+    /// Set whether to skip binary files, static assets and minified scripts
+    pub fn skip_binary_content(mut self, skip_binary_content: bool) -> Self {
+        self.config.skip_binary_content = skip_binary_content;
+        self
+    }
+
+    /// Set the cache validators of pages from an earlier crawl
+    pub fn http_cache(mut self, http_cache: HashMap<String, HttpValidators>) -> Self {
+        self.config.http_cache = http_cache;
+        self
+    }
+
+    /// Set the proxy all requests go through (`http://`, `https://`, `socks5://` or `socks5h://`)
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.config.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Set whether to accept invalid TLS certificates
+    pub fn accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.config.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Set a PEM file with an additional CA certificate to trust
+    pub fn ca_certificate(mut self, ca_certificate: impl Into<PathBuf>) -> Self {
+        self.config.ca_certificate = Some(ca_certificate.into());
+        self
+    }
+
+    /// Set whether to stop following URL patterns that look like crawl traps
+    pub fn detect_traps(mut self, detect_traps: bool) -> Self {
+        self.config.detect_traps = detect_traps;
+        self
+    }
+
+    /// Set the URLs allowed per numbered path pattern or per path with query strings
+    pub fn max_urls_per_pattern(mut self, max_urls_per_pattern: usize) -> Self {
+        self.config.max_urls_per_pattern = max_urls_per_pattern;
+        self
+    }
+
+    /// Set the times a path segment may occur in one URL
+    pub fn max_segment_repeats(mut self, max_segment_repeats: usize) -> Self {
+        self.config.max_segment_repeats = max_segment_repeats;
+        self
+    }
+
+    /// Set the token stopping the crawl
+    pub fn cancel(mut self, cancel: CancellationToken) -> Self {
+        self.config.cancel = cancel;
+        self
+    }
+
     /// Build the configuration
     pub fn build(self) -> CrawlerConfig {
         self.config
@@ -149,4 +292,52 @@ impl CrawlerConfig {
     pub fn rate_limit(&self) -> Duration {
         Duration::from_millis(self.rate_limit_ms)
     }
+
+    /// Build an HTTP client with the proxy and TLS settings of the crawl
+    ///
+    /// Used for robots.txt, linked documents and conditional requests. The page crawl
+    /// itself applies the proxy and `accept_invalid_certs` through spider, which trusts
+    /// only the system CA certificates.
+    pub fn http_client(&self) -> Result<reqwest::Client, CrawlError> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| CrawlError::Other(format!("Invalid proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &self.ca_certificate {
+            let pem = std::fs::read(path).map_err(|e| {
+                CrawlError::Other(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            let certificate = reqwest::Certificate::from_pem(&pem).map_err(|e| {
+                CrawlError::Other(format!("Invalid CA certificate {}: {}", path.display(), e))
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_client() {
+        let config = CrawlerConfig::builder()
+            .proxy("socks5h://127.0.0.1:9050")
+            .accept_invalid_certs(true)
+            .build();
+        assert!(config.http_client().is_ok());
+
+        let config = CrawlerConfig::builder().proxy("not a proxy").build();
+        assert!(config.http_client().is_err());
+
+        let config = CrawlerConfig::builder()
+            .ca_certificate("/nonexistent/ca.pem")
+            .build();
+        assert!(config.http_client().is_err());
+    }
 }
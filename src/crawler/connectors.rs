@@ -0,0 +1,30 @@
+//! # API Connectors Module
+//!
+//! This module provides API-based content sources that produce `CrawledPage`s without
+//! HTML crawling, enabling RAG over internal knowledge bases.
+//!
+//! ## Key Components
+//!
+//! - `ConfluenceConnector`: Pages through the pages of a Confluence space (feature `confluence`)
+//! - `NotionConnector`: Pages through the pages of a Notion database (feature `notion`)
+//!
+//! ## Features
+//!
+//! - Cursor and offset pagination handled transparently
+//! - Conversion of page bodies to Markdown for the normal chunking pipeline
+//! - Title, author, date and label/tag metadata mapped into `PageMetadata`
+//! - Credentials read from the environment with `from_env` constructors
+
+#[cfg(feature = "confluence")]
+mod confluence;
+#[cfg(feature = "confluence")]
+mod html;
+#[cfg(feature = "notion")]
+mod notion;
+
+#[cfg(feature = "confluence")]
+pub use confluence::ConfluenceConnector;
+#[cfg(feature = "confluence")]
+pub use html::html_to_markdown;
+#[cfg(feature = "notion")]
+pub use notion::NotionConnector;
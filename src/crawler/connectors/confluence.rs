@@ -0,0 +1,282 @@
+//! Confluence Cloud connector using the REST content API.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{debug, info, instrument};
+use url::Url;
+
+use super::html::html_to_markdown;
+use crate::crawler::error::CrawlError;
+use crate::crawler::{CrawledPage, PageMetadata};
+
+/// Number of pages requested per API call
+const PAGE_SIZE: u32 = 25;
+
+/// Connector that reads the pages of a Confluence space
+#[derive(Debug, Clone)]
+pub struct ConfluenceConnector {
+    /// Base URL of the Confluence site, e.g. `https://example.atlassian.net/wiki`
+    base_url: String,
+
+    /// Account email used for basic authentication
+    email: String,
+
+    /// API token used for basic authentication
+    api_token: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentResponse {
+    results: Vec<Content>,
+    #[serde(default)]
+    size: u32,
+    #[serde(rename = "_links", default)]
+    links: Links,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Links {
+    next: Option<String>,
+    webui: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Content {
+    title: String,
+    body: Option<Body>,
+    history: Option<History>,
+    version: Option<Version>,
+    metadata: Option<Metadata>,
+    #[serde(rename = "_links", default)]
+    links: Links,
+}
+
+#[derive(Debug, Deserialize)]
+struct Body {
+    view: Option<Representation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Representation {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct History {
+    created_by: Option<User>,
+    created_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct User {
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Version {
+    when: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    labels: Option<Labels>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Labels {
+    results: Vec<Label>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    name: String,
+}
+
+impl ConfluenceConnector {
+    /// Create a new connector
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the Confluence site, including the `/wiki` path on Cloud
+    /// * `email` - Account email
+    /// * `api_token` - API token for the account
+    pub fn new(base_url: &str, email: &str, api_token: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            email: email.to_string(),
+            api_token: api_token.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a connector from `CONFLUENCE_BASE_URL`, `CONFLUENCE_EMAIL` and `CONFLUENCE_API_TOKEN`
+    pub fn from_env() -> Result<Self, CrawlError> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| {
+                CrawlError::Other(format!("{} environment variable must be set", name))
+            })
+        };
+        Ok(Self::new(
+            &var("CONFLUENCE_BASE_URL")?,
+            &var("CONFLUENCE_EMAIL")?,
+            &var("CONFLUENCE_API_TOKEN")?,
+        ))
+    }
+
+    /// Fetch the pages of a space as Markdown
+    ///
+    /// # Arguments
+    ///
+    /// * `space_key` - Key of the space to read
+    /// * `max_pages` - Maximum number of pages to return
+    ///
+    /// # Returns
+    ///
+    /// A vector of crawled pages
+    #[instrument(skip(self))]
+    pub async fn fetch_space(
+        &self,
+        space_key: &str,
+        max_pages: u32,
+    ) -> Result<Vec<CrawledPage>, CrawlError> {
+        let domain = Url::parse(&self.base_url)?
+            .host_str()
+            .ok_or(url::ParseError::EmptyHost)?
+            .to_string();
+
+        let mut pages: Vec<CrawledPage> = Vec::new();
+        let mut start = 0;
+        while (pages.len() as u32) < max_pages {
+            let limit = PAGE_SIZE.min(max_pages - pages.len() as u32);
+            debug!("Fetching Confluence pages {}..{}", start, start + limit);
+
+            let response: ContentResponse = self
+                .client
+                .get(format!("{}/rest/api/content", self.base_url))
+                .basic_auth(&self.email, Some(&self.api_token))
+                .query(&[
+                    ("spaceKey", space_key.to_string()),
+                    ("type", "page".to_string()),
+                    (
+                        "expand",
+                        "body.view,history,version,metadata.labels".to_string(),
+                    ),
+                    ("start", start.to_string()),
+                    ("limit", limit.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            for content in response.results {
+                pages.push(self.to_page(content, &domain));
+            }
+
+            start += response.size;
+            if response.links.next.is_none() || response.size == 0 {
+                break;
+            }
+        }
+
+        info!(
+            "Fetched {} pages from Confluence space {}",
+            pages.len(),
+            space_key
+        );
+        Ok(pages)
+    }
+
+    /// Convert an API content object into a crawled page
+    fn to_page(&self, content: Content, domain: &str) -> CrawledPage {
+        let markdown = content
+            .body
+            .and_then(|body| body.view)
+            .map(|view| html_to_markdown(&view.value))
+            .unwrap_or_default();
+
+        let url = match content.links.webui {
+            Some(webui) => format!("{}{}", self.base_url, webui),
+            None => self.base_url.clone(),
+        };
+
+        let (author, created_date) = match content.history {
+            Some(history) => (
+                history.created_by.and_then(|user| user.display_name),
+                history.created_date,
+            ),
+            None => (None, None),
+        };
+
+        let tags = content
+            .metadata
+            .and_then(|metadata| metadata.labels)
+            .map(|labels| labels.results.into_iter().map(|label| label.name).collect())
+            .unwrap_or_default();
+
+        CrawledPage {
+            url,
+            content: format!("# {}\n\n{}", content.title, markdown),
+            metadata: PageMetadata {
+                title: Some(content.title),
+                description: None,
+                publication_date: content.version.and_then(|v| v.when).or(created_date),
+                author,
+                domain: domain.to_string(),
+                tags,
+                content_type: None,
+            },
+            raw_html: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_space_paginates() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/wiki/rest/api/content")
+            .match_query(mockito::Matcher::UrlEncoded("start".into(), "0".into()))
+            .with_body(
+                r#"{"results":[{"title":"Runbook","body":{"view":{"value":"<p>Restart it.</p>"}},
+                "history":{"createdBy":{"displayName":"Ada"},"createdDate":"2024-01-02T03:04:05Z"},
+                "metadata":{"labels":{"results":[{"name":"ops"}]}},
+                "_links":{"webui":"/spaces/OPS/pages/1"}}],
+                "size":1,"_links":{"next":"/rest/api/content?start=1"}}"#,
+            )
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/wiki/rest/api/content")
+            .match_query(mockito::Matcher::UrlEncoded("start".into(), "1".into()))
+            .with_body(
+                r#"{"results":[{"title":"FAQ","body":{"view":{"value":"<p>Answers.</p>"}},
+                "_links":{"webui":"/spaces/OPS/pages/2"}}],"size":1,"_links":{}}"#,
+            )
+            .create_async()
+            .await;
+
+        let connector =
+            ConfluenceConnector::new(&format!("{}/wiki", server.url()), "a@example.com", "token");
+        let pages = connector.fetch_space("OPS", 10).await.unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].content, "# Runbook\n\nRestart it.");
+        assert_eq!(pages[0].metadata.author.as_deref(), Some("Ada"));
+        assert_eq!(pages[0].metadata.tags, vec!["ops"]);
+        assert!(pages[0].url.ends_with("/wiki/spaces/OPS/pages/1"));
+        assert_eq!(pages[1].metadata.title.as_deref(), Some("FAQ"));
+    }
+}
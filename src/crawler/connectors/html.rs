@@ -0,0 +1,158 @@
+//! Conversion of API-provided HTML fragments to Markdown.
+//!
+//! Connector APIs return rendered page bodies rather than full documents, so the
+//! readability transform used for crawled pages does not apply. This converter
+//! keeps the structure the chunker relies on: headings, paragraphs, lists and code.
+
+use scraper::{ElementRef, Html, Node};
+
+/// Convert an HTML fragment to Markdown
+///
+/// # Arguments
+///
+/// * `html` - The HTML fragment
+///
+/// # Returns
+///
+/// The Markdown text
+pub fn html_to_markdown(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut output = String::new();
+    render_children(fragment.root_element(), &mut output);
+
+    // Collapse runs of blank lines left by nested blocks
+    let mut markdown = String::new();
+    let mut blank_lines = 0;
+    for line in output.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        markdown.push_str(line);
+        markdown.push('\n');
+    }
+    markdown.trim().to_string()
+}
+
+/// Render the children of an element
+fn render_children(element: ElementRef, output: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => {
+                let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if collapsed.is_empty() {
+                    continue;
+                }
+                if text.starts_with(char::is_whitespace) && !output.ends_with([' ', '\n']) {
+                    output.push(' ');
+                }
+                output.push_str(&collapsed);
+                if text.ends_with(char::is_whitespace) {
+                    output.push(' ');
+                }
+            }
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    render_element(child, output);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Render a single element
+fn render_element(element: ElementRef, output: &mut String) {
+    let name = element.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            let text = element.text().collect::<String>();
+            output.push_str("\n\n");
+            output.push_str(&"#".repeat(level));
+            output.push(' ');
+            output.push_str(
+                text.split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .as_str(),
+            );
+            output.push_str("\n\n");
+        }
+        "p" | "div" | "section" | "blockquote" | "table" => {
+            output.push_str("\n\n");
+            render_children(element, output);
+            output.push_str("\n\n");
+        }
+        "ul" | "ol" => {
+            output.push('\n');
+            render_children(element, output);
+            output.push('\n');
+        }
+        "li" => {
+            output.push_str("\n- ");
+            render_children(element, output);
+        }
+        "tr" => {
+            output.push('\n');
+            render_children(element, output);
+        }
+        "td" | "th" => {
+            render_children(element, output);
+            output.push_str(" | ");
+        }
+        "pre" => {
+            output.push_str("\n\n```\n");
+            output.push_str(element.text().collect::<String>().trim_end());
+            output.push_str("\n```\n\n");
+        }
+        "code" => {
+            output.push('`');
+            output.push_str(&element.text().collect::<String>());
+            output.push('`');
+        }
+        "strong" | "b" => {
+            output.push_str("**");
+            render_children(element, output);
+            output.push_str("**");
+        }
+        "em" | "i" => {
+            output.push('*');
+            render_children(element, output);
+            output.push('*');
+        }
+        "a" => match element.value().attr("href") {
+            Some(href) => {
+                output.push('[');
+                render_children(element, output);
+                output.push_str(&format!("]({})", href));
+            }
+            None => render_children(element, output),
+        },
+        "br" => output.push('\n'),
+        "script" | "style" => {}
+        _ => render_children(element, output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = "<h1>Runbook</h1><p>Restart the <strong>worker</strong> with <code>systemctl</code>.</p>\
+            <ul><li>Check logs</li><li>See <a href=\"https://example.com\">docs</a></li></ul>\
+            <pre>systemctl restart worker</pre>";
+
+        assert_eq!(
+            html_to_markdown(html),
+            "# Runbook\n\nRestart the **worker** with `systemctl`.\n\n- Check logs\n- See [docs](https://example.com)\n\n```\nsystemctl restart worker\n```"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Notion connector using the public REST API.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::{debug, info, instrument};
+
+use crate::crawler::error::CrawlError;
+use crate::crawler::{CrawledPage, PageMetadata};
+
+/// Default base URL of the Notion API
+const NOTION_API_URL: &str = "https://api.notion.com/v1";
+
+/// Notion API version sent with every request
+const NOTION_VERSION: &str = "2022-06-28";
+
+/// Number of results requested per API call, the maximum Notion allows
+const PAGE_SIZE: u32 = 100;
+
+/// Connector that reads the pages of a Notion database
+#[derive(Debug, Clone)]
+pub struct NotionConnector {
+    /// Base URL of the Notion API
+    base_url: String,
+
+    /// Integration token
+    token: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListResponse<T> {
+    results: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotionPage {
+    id: String,
+    url: String,
+    created_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    properties: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Block {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    content: serde_json::Map<String, Value>,
+}
+
+impl NotionConnector {
+    /// Create a new connector
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Integration token with access to the database
+    pub fn new(token: &str) -> Self {
+        Self {
+            base_url: NOTION_API_URL.to_string(),
+            token: token.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a connector from `NOTION_API_TOKEN`
+    pub fn from_env() -> Result<Self, CrawlError> {
+        let token = std::env::var("NOTION_API_TOKEN").map_err(|_| {
+            CrawlError::Other("NOTION_API_TOKEN environment variable must be set".to_string())
+        })?;
+        Ok(Self::new(&token))
+    }
+
+    /// Use a different API base URL
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Fetch the pages of a database as Markdown
+    ///
+    /// # Arguments
+    ///
+    /// * `database_id` - ID of the database to read
+    /// * `max_pages` - Maximum number of pages to return
+    ///
+    /// # Returns
+    ///
+    /// A vector of crawled pages
+    #[instrument(skip(self))]
+    pub async fn fetch_database(
+        &self,
+        database_id: &str,
+        max_pages: u32,
+    ) -> Result<Vec<CrawledPage>, CrawlError> {
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        while (pages.len() as u32) < max_pages {
+            let mut body = json!({ "page_size": PAGE_SIZE.min(max_pages - pages.len() as u32) });
+            if let Some(cursor) = &cursor {
+                body["start_cursor"] = json!(cursor);
+            }
+
+            let response: ListResponse<NotionPage> = self
+                .client
+                .post(format!("{}/databases/{}/query", self.base_url, database_id))
+                .bearer_auth(&self.token)
+                .header("Notion-Version", NOTION_VERSION)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            for page in response.results {
+                let markdown = self.fetch_blocks(&page.id).await?;
+                pages.push(to_crawled_page(page, markdown));
+            }
+
+            match response.next_cursor {
+                Some(next) if response.has_more => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        info!(
+            "Fetched {} pages from Notion database {}",
+            pages.len(),
+            database_id
+        );
+        Ok(pages)
+    }
+
+    /// Fetch the top-level blocks of a page and render them as Markdown
+    async fn fetch_blocks(&self, page_id: &str) -> Result<String, CrawlError> {
+        let mut lines = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            debug!("Fetching blocks for Notion page {}", page_id);
+            let mut request = self
+                .client
+                .get(format!("{}/blocks/{}/children", self.base_url, page_id))
+                .bearer_auth(&self.token)
+                .header("Notion-Version", NOTION_VERSION)
+                .query(&[("page_size", PAGE_SIZE.to_string())]);
+            if let Some(cursor) = &cursor {
+                request = request.query(&[("start_cursor", cursor)]);
+            }
+
+            let response: ListResponse<Block> =
+                request.send().await?.error_for_status()?.json().await?;
+
+            lines.extend(response.results.iter().filter_map(block_to_markdown));
+
+            match response.next_cursor {
+                Some(next) if response.has_more => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(lines.join("\n\n"))
+    }
+}
+
+/// Build a crawled page from a database entry and its rendered blocks
+fn to_crawled_page(page: NotionPage, markdown: String) -> CrawledPage {
+    let mut title = None;
+    let mut tags = Vec::new();
+    for property in page.properties.values() {
+        match property["type"].as_str() {
+            Some("title") => title = Some(rich_text(&property["title"])),
+            Some("multi_select") => tags.extend(
+                property["multi_select"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|option| option["name"].as_str().map(String::from)),
+            ),
+            Some("select") => {
+                if let Some(name) = property["select"]["name"].as_str() {
+                    tags.push(name.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let content = match &title {
+        Some(title) => format!("# {}\n\n{}", title, markdown),
+        None => markdown,
+    };
+
+    CrawledPage {
+        url: page.url,
+        content,
+        metadata: PageMetadata {
+            title,
+            description: None,
+            publication_date: page.created_time,
+            author: None,
+            domain: "notion.so".to_string(),
+            tags,
+            content_type: None,
+        },
+        raw_html: None,
+    }
+}
+
+/// Render a single block as Markdown, skipping unsupported block types
+fn block_to_markdown(block: &Block) -> Option<String> {
+    let value = block.content.get(&block.kind)?;
+    let text = rich_text(&value["rich_text"]);
+    let markdown = match block.kind.as_str() {
+        "paragraph" => text,
+        "heading_1" => format!("# {}", text),
+        "heading_2" => format!("## {}", text),
+        "heading_3" => format!("### {}", text),
+        "bulleted_list_item" => format!("- {}", text),
+        "numbered_list_item" => format!("1. {}", text),
+        "to_do" => {
+            let checked = value["checked"].as_bool().unwrap_or(false);
+            format!("- [{}] {}", if checked { "x" } else { " " }, text)
+        }
+        "quote" | "callout" => format!("> {}", text),
+        "code" => format!(
+            "```{}\n{}\n```",
+            value["language"].as_str().unwrap_or_default(),
+            text
+        ),
+        _ => return None,
+    };
+
+    if markdown.trim().is_empty() {
+        None
+    } else {
+        Some(markdown)
+    }
+}
+
+/// Concatenate the plain text of a rich text array
+fn rich_text(value: &Value) -> String {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part["plain_text"].as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_database() {
+        let mut server = mockito::Server::new_async().await;
+        let query = server
+            .mock("POST", "/databases/db1/query")
+            .with_body(
+                r#"{"results":[{"id":"p1","url":"https://www.notion.so/Onboarding-p1",
+                "created_time":"2024-05-01T10:00:00.000Z",
+                "properties":{
+                    "Name":{"type":"title","title":[{"plain_text":"Onboarding"}]},
+                    "Tags":{"type":"multi_select","multi_select":[{"name":"hr"},{"name":"guide"}]}
+                }}],"has_more":false,"next_cursor":null}"#,
+            )
+            .create_async()
+            .await;
+        let blocks = server
+            .mock("GET", "/blocks/p1/children")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                r#"{"results":[
+                    {"type":"heading_2","heading_2":{"rich_text":[{"plain_text":"First day"}]}},
+                    {"type":"paragraph","paragraph":{"rich_text":[{"plain_text":"Meet the "},{"plain_text":"team."}]}},
+                    {"type":"to_do","to_do":{"rich_text":[{"plain_text":"Get a laptop"}],"checked":true}},
+                    {"type":"image","image":{}}
+                ],"has_more":false,"next_cursor":null}"#,
+            )
+            .create_async()
+            .await;
+
+        let connector = NotionConnector::new("secret").with_base_url(&server.url());
+        let pages = connector.fetch_database("db1", 10).await.unwrap();
+
+        query.assert_async().await;
+        blocks.assert_async().await;
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            pages[0].content,
+            "# Onboarding\n\n## First day\n\nMeet the team.\n\n- [x] Get a laptop"
+        );
+        assert_eq!(pages[0].metadata.title.as_deref(), Some("Onboarding"));
+        assert_eq!(pages[0].metadata.tags, vec!["hr", "guide"]);
+        assert!(pages[0].metadata.publication_date.is_some());
+    }
+}
@@ -7,11 +7,14 @@
 //! ## Key Components
 //!
 //! - `extract_metadata`: Extracts standard metadata from HTML (title, description, etc.)
+//! - `StructuredData`: Metadata declared in schema.org JSON-LD or OpenGraph tags
 //!
 //! ## Features
 //!
 //! - Robust HTML parsing using the scraper library
 //! - Extraction of common metadata fields from web pages
+//! - schema.org JSON-LD and OpenGraph metadata, preferred over plain meta tags since many
+//!   blogs only declare their publication date there
 //! - Domain extraction for source attribution
 //! - Error handling for malformed HTML or missing data
 //!
@@ -20,9 +23,74 @@
 
 use crate::crawler::PageMetadata;
 use crate::crawler::error::CrawlError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use scraper::{Html, Selector};
+use serde_json::Value;
 use url::Url;
 
+/// schema.org types describing the main content of a page, most specific first
+const CONTENT_TYPES: &[&str] = &[
+    "BlogPosting",
+    "NewsArticle",
+    "TechArticle",
+    "ScholarlyArticle",
+    "Report",
+    "Article",
+    "Recipe",
+    "Product",
+    "Event",
+    "Course",
+    "HowTo",
+    "FAQPage",
+    "QAPage",
+    "VideoObject",
+    "PodcastEpisode",
+    "SoftwareApplication",
+    "Book",
+    "ProfilePage",
+    "WebPage",
+];
+
+/// Page metadata declared in schema.org JSON-LD or OpenGraph tags
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StructuredData {
+    /// The schema.org type or OpenGraph type of the page, e.g. `BlogPosting`
+    pub content_type: Option<String>,
+
+    /// The headline or title
+    pub title: Option<String>,
+
+    /// The description
+    pub description: Option<String>,
+
+    /// The author, or several joined with commas
+    pub author: Option<String>,
+
+    /// The publication date
+    pub publication_date: Option<DateTime<Utc>>,
+
+    /// Keywords and article tags
+    pub tags: Vec<String>,
+}
+
+impl StructuredData {
+    /// Fill the fields missing from this data with those of another source
+    fn or(self, other: StructuredData) -> Self {
+        Self {
+            content_type: self.content_type.or(other.content_type),
+            title: self.title.or(other.title),
+            description: self.description.or(other.description),
+            author: self.author.or(other.author),
+            publication_date: self.publication_date.or(other.publication_date),
+            tags: if self.tags.is_empty() {
+                other.tags
+            } else {
+                self.tags
+            },
+        }
+    }
+}
+
 /// Extract metadata from a page
 ///
 /// # Arguments
@@ -35,6 +103,7 @@ use url::Url;
 /// The extracted metadata
 pub fn extract_metadata(url: &str, html: &str) -> Result<PageMetadata, CrawlError> {
     let document = Html::parse_document(html);
+    let structured = extract_json_ld(&document).or(extract_open_graph(&document));
 
     // Parse URL to extract domain
     let parsed_url = Url::parse(url).map_err(CrawlError::UrlParse)?;
@@ -51,31 +120,36 @@ pub fn extract_metadata(url: &str, html: &str) -> Result<PageMetadata, CrawlErro
     let title = document
         .select(&title_selector)
         .next()
-        .map(|element| element.text().collect::<String>());
+        .map(|element| element.text().collect::<String>())
+        .or(structured.title);
 
     // Extract description
     let description_selector = Selector::parse("meta[name='description']").map_err(|e| {
         CrawlError::HtmlParse(format!("Failed to parse description selector: {}", e))
     })?;
 
-    let description = document
-        .select(&description_selector)
-        .next()
-        .and_then(|element| element.value().attr("content"))
-        .map(|s| s.to_string());
+    let description = structured.description.or_else(|| {
+        document
+            .select(&description_selector)
+            .next()
+            .and_then(|element| element.value().attr("content"))
+            .map(|s| s.to_string())
+    });
 
-    // Extract publication date
-    let publication_date = None; // This would require more complex logic to extract reliably
+    // Extract publication date, which pages only declare in structured data
+    let publication_date = structured.publication_date;
 
     // Extract author
     let author_selector = Selector::parse("meta[name='author']")
         .map_err(|e| CrawlError::HtmlParse(format!("Failed to parse author selector: {}", e)))?;
 
-    let author = document
-        .select(&author_selector)
-        .next()
-        .and_then(|element| element.value().attr("content"))
-        .map(|s| s.to_string());
+    let author = structured.author.or_else(|| {
+        document
+            .select(&author_selector)
+            .next()
+            .and_then(|element| element.value().attr("content"))
+            .map(|s| s.to_string())
+    });
 
     Ok(PageMetadata {
         title,
@@ -83,5 +157,246 @@ pub fn extract_metadata(url: &str, html: &str) -> Result<PageMetadata, CrawlErro
         publication_date,
         author,
         domain,
+        tags: structured.tags,
+        content_type: structured.content_type,
     })
 }
+
+/// Extract the metadata of the main entity declared in JSON-LD blocks
+///
+/// Blocks may hold a single object, an array or an `@graph`; the object whose schema.org
+/// type comes first in `CONTENT_TYPES` describes the page. Invalid blocks are ignored.
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML of the page
+///
+/// # Returns
+///
+/// The structured data, empty if the page has no JSON-LD
+pub fn extract_json_ld(document: &Html) -> StructuredData {
+    let Ok(selector) = Selector::parse("script[type='application/ld+json']") else {
+        return StructuredData::default();
+    };
+
+    let mut entities = Vec::new();
+    for element in document.select(&selector) {
+        let text = element.text().collect::<String>();
+        if let Ok(value) = serde_json::from_str::<Value>(text.trim()) {
+            collect_entities(value, &mut entities);
+        }
+    }
+
+    let Some((content_type, entity)) = entities
+        .iter()
+        .filter_map(|entity| {
+            let types = schema_types(entity);
+            CONTENT_TYPES
+                .iter()
+                .position(|known| types.iter().any(|t| t == known))
+                .map(|rank| (rank, CONTENT_TYPES[rank], entity))
+        })
+        .min_by_key(|(rank, _, _)| *rank)
+        .map(|(_, content_type, entity)| (content_type, entity))
+    else {
+        return StructuredData::default();
+    };
+
+    let text = |key: &str| {
+        entity
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let tags = match entity.get("keywords") {
+        Some(Value::String(keywords)) => split_keywords(keywords),
+        Some(Value::Array(keywords)) => keywords
+            .iter()
+            .filter_map(Value::as_str)
+            .flat_map(split_keywords)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    StructuredData {
+        content_type: Some(content_type.to_string()),
+        title: text("headline").or_else(|| text("name")),
+        description: text("description"),
+        author: entity.get("author").and_then(author_names),
+        publication_date: text("datePublished")
+            .or_else(|| text("dateCreated"))
+            .or_else(|| text("uploadDate"))
+            .and_then(|date| parse_date(&date)),
+        tags,
+    }
+}
+
+/// Extract the metadata declared in OpenGraph and `article:` meta tags
+///
+/// # Arguments
+///
+/// * `document` - The parsed HTML of the page
+///
+/// # Returns
+///
+/// The structured data, empty if the page has no OpenGraph tags
+pub fn extract_open_graph(document: &Html) -> StructuredData {
+    let Ok(selector) = Selector::parse("meta[property]") else {
+        return StructuredData::default();
+    };
+
+    let mut data = StructuredData::default();
+    let mut authors = Vec::new();
+    for element in document.select(&selector) {
+        let (Some(property), Some(content)) = (
+            element.value().attr("property"),
+            element.value().attr("content").map(str::trim),
+        ) else {
+            continue;
+        };
+        if content.is_empty() {
+            continue;
+        }
+        match property {
+            "og:type" => data.content_type = data.content_type.or(Some(content.to_string())),
+            "og:title" => data.title = data.title.or(Some(content.to_string())),
+            "og:description" => data.description = data.description.or(Some(content.to_string())),
+            "article:published_time" => {
+                data.publication_date = data.publication_date.or(parse_date(content))
+            }
+            // Profile URLs say little about the author
+            "article:author" if !content.starts_with("http") => authors.push(content.to_string()),
+            "article:tag" => data.tags.push(content.to_string()),
+            _ => {}
+        }
+    }
+    if !authors.is_empty() {
+        data.author = Some(authors.join(", "));
+    }
+    data
+}
+
+/// Flatten JSON-LD arrays and `@graph` containers into their objects
+fn collect_entities(value: Value, entities: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                collect_entities(value, entities);
+            }
+        }
+        Value::Object(mut object) => {
+            if let Some(graph) = object.remove("@graph") {
+                collect_entities(graph, entities);
+            }
+            entities.push(Value::Object(object));
+        }
+        _ => {}
+    }
+}
+
+/// The schema.org types of a JSON-LD object, without any `schema:` prefix
+fn schema_types(entity: &Value) -> Vec<String> {
+    let strip = |t: &str| t.rsplit(['/', ':']).next().unwrap_or(t).to_string();
+    match entity.get("@type") {
+        Some(Value::String(t)) => vec![strip(t)],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).map(strip).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The names of a JSON-LD author, which may be a string, a person or a list of either
+fn author_names(author: &Value) -> Option<String> {
+    let names: Vec<String> = match author {
+        Value::String(name) => vec![name.trim().to_string()],
+        Value::Object(person) => person
+            .get("name")
+            .and_then(Value::as_str)
+            .map(|name| vec![name.trim().to_string()])
+            .unwrap_or_default(),
+        Value::Array(authors) => authors.iter().filter_map(author_names).collect(),
+        _ => Vec::new(),
+    };
+    let names: Vec<String> = names.into_iter().filter(|name| !name.is_empty()).collect();
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+/// Split a comma-separated keyword list
+fn split_keywords(keywords: &str) -> Vec<String> {
+    keywords
+        .split(',')
+        .map(str::trim)
+        .filter(|keyword| !keyword.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse an ISO 8601 date as used by schema.org and OpenGraph, assuming UTC without an offset
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Some(date.and_utc());
+    }
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_json_ld() {
+        let html = r#"<html><head>
+            <title>Post | Blog</title>
+            <meta name="author" content="Site Owner">
+            <script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+                {"@type": "WebSite", "name": "Blog"},
+                {"@type": "BlogPosting", "headline": "Post",
+                 "author": [{"@type": "Person", "name": "Ada"}, "Grace"],
+                 "datePublished": "2024-03-01T09:30:00+02:00",
+                 "keywords": "rust, rag"}
+            ]}
+            </script>
+            <script type="application/ld+json">{ invalid</script>
+        </head><body></body></html>"#;
+
+        let metadata = extract_metadata("https://blog.example.com/post", html).unwrap();
+        assert_eq!(metadata.content_type.as_deref(), Some("BlogPosting"));
+        assert_eq!(metadata.title.as_deref(), Some("Post | Blog"));
+        assert_eq!(metadata.author.as_deref(), Some("Ada, Grace"));
+        assert_eq!(
+            metadata.publication_date.unwrap().to_rfc3339(),
+            "2024-03-01T07:30:00+00:00"
+        );
+        assert_eq!(metadata.tags, vec!["rust", "rag"]);
+    }
+
+    #[test]
+    fn test_extract_open_graph() {
+        let html = r#"<html><head>
+            <meta property="og:type" content="article">
+            <meta property="og:description" content="An article">
+            <meta property="article:published_time" content="2023-11-05">
+            <meta property="article:author" content="https://example.com/ada">
+            <meta property="article:tag" content="search">
+            <meta name="author" content="Ada">
+        </head><body></body></html>"#;
+
+        let metadata = extract_metadata("https://example.com/a", html).unwrap();
+        assert_eq!(metadata.content_type.as_deref(), Some("article"));
+        assert_eq!(metadata.description.as_deref(), Some("An article"));
+        assert_eq!(metadata.author.as_deref(), Some("Ada"));
+        assert_eq!(
+            metadata.publication_date.unwrap().to_rfc3339(),
+            "2023-11-05T00:00:00+00:00"
+        );
+        assert_eq!(metadata.tags, vec!["search"]);
+    }
+}
@@ -0,0 +1,384 @@
+//! # Linked Documents Module
+//!
+//! This module downloads the PDF and CSV files a crawl links to and extracts their text,
+//! so documents published next to a site's pages are indexed with them instead of
+//! being skipped.
+//!
+//! ## Key Components
+//!
+//! - `DocumentKind`: The document types that can be extracted
+//! - `fetch_documents`: Downloads linked documents within a size cap and turns them into pages
+//! - `extract_text`: Extracts the text of a downloaded document
+//! - `csv_to_markdown`: Renders CSV as a Markdown table
+//!
+//! PDF extraction requires the `pdf` feature; without it, PDF links are reported as
+//! skipped. Documents are enabled with `CrawlerConfig::follow_documents` and capped at
+//! `CrawlerConfig::max_document_bytes`.
+
+use std::fmt;
+
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+use crate::crawler::error::CrawlError;
+use crate::crawler::report::{CrawlReport, PageOutcome, PageReport};
+use crate::crawler::{CrawledPage, CrawlerConfig, PageMetadata};
+
+/// Rows of a CSV file rendered into a page
+const MAX_CSV_ROWS: usize = 1000;
+
+/// A document type the crawler can extract text from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    /// Portable Document Format
+    Pdf,
+
+    /// Comma-separated values
+    Csv,
+}
+
+impl DocumentKind {
+    /// The document type of a URL, from the extension of its path
+    pub fn from_url(url: &Url) -> Option<Self> {
+        let extension = url.path().rsplit_once('.')?.1.to_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(DocumentKind::Pdf),
+            "csv" => Some(DocumentKind::Csv),
+            _ => None,
+        }
+    }
+
+    /// The document type of a `Content-Type` header, ignoring parameters
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_lowercase();
+        match mime.as_str() {
+            "application/pdf" => Some(DocumentKind::Pdf),
+            "text/csv" | "application/csv" => Some(DocumentKind::Csv),
+            _ => None,
+        }
+    }
+
+    /// Label of the document type in reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentKind::Pdf => "pdf",
+            DocumentKind::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Extract the text of a document
+///
+/// # Arguments
+///
+/// * `kind` - The document type
+/// * `bytes` - The downloaded document
+///
+/// # Returns
+///
+/// The text as Markdown
+pub fn extract_text(kind: DocumentKind, bytes: &[u8]) -> Result<String, CrawlError> {
+    match kind {
+        DocumentKind::Csv => Ok(csv_to_markdown(&String::from_utf8_lossy(bytes))),
+        #[cfg(feature = "pdf")]
+        DocumentKind::Pdf => pdf_extract::extract_text_from_mem(bytes)
+            .map_err(|e| CrawlError::ContentExtraction(format!("Failed to read PDF: {}", e))),
+        #[cfg(not(feature = "pdf"))]
+        DocumentKind::Pdf => Err(CrawlError::ContentExtraction(
+            "PDF extraction requires the `pdf` feature".to_string(),
+        )),
+    }
+}
+
+/// Render CSV as a Markdown table, with the first row as the header
+///
+/// Quoted fields may contain commas, escaped quotes and line breaks. Tables are cut
+/// off after `MAX_CSV_ROWS` rows.
+pub fn csv_to_markdown(csv: &str) -> String {
+    let rows = parse_csv(csv);
+    let Some(columns) = rows.iter().map(Vec::len).max() else {
+        return String::new();
+    };
+
+    let cell = |row: &[String], i: usize| {
+        row.get(i)
+            .map(|field| field.replace('|', "\\|").replace('\n', " "))
+            .unwrap_or_default()
+    };
+    let line = |row: &[String]| {
+        let cells: Vec<String> = (0..columns).map(|i| cell(row, i)).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().take(MAX_CSV_ROWS).map(|row| line(row)));
+    if rows.len() > MAX_CSV_ROWS + 1 {
+        lines.push(String::new());
+        lines.push(format!(
+            "{} more rows not shown",
+            rows.len() - MAX_CSV_ROWS - 1
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Split CSV into rows of fields, skipping empty lines
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|field| !field.is_empty()) {
+                    rows.push(std::mem::take(&mut row));
+                }
+                row.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Download linked documents and turn them into pages
+///
+/// Every document is recorded in the report: fetched, failed, or skipped with the reason
+/// when it exceeds the size cap or cannot be extracted.
+///
+/// # Arguments
+///
+/// * `links` - Links to documents, in the order they are fetched
+/// * `config` - The crawler configuration, for the user agent, rate limit and size cap
+/// * `limit` - Maximum number of documents to fetch
+/// * `report` - The crawl report to record the documents in
+///
+/// # Returns
+///
+/// The pages of the documents that were extracted, or an error if the proxy or TLS
+/// settings are invalid
+#[instrument(skip(links, config, report))]
+pub async fn fetch_documents(
+    links: Vec<Url>,
+    config: &CrawlerConfig,
+    limit: usize,
+    report: &mut CrawlReport,
+) -> Result<Vec<CrawledPage>, CrawlError> {
+    let client = config.http_client()?;
+    let mut pages = Vec::new();
+
+    for (i, link) in links.into_iter().take(limit).enumerate() {
+        if i > 0 {
+            tokio::time::sleep(config.rate_limit()).await;
+        }
+        match fetch_document(&client, &link, config).await {
+            Ok((page, page_report)) => {
+                report.record(page_report);
+                pages.extend(page);
+            }
+            Err(e) => {
+                warn!("Failed to fetch document {}: {}", link, e);
+                report.record(PageReport {
+                    detail: Some(e.to_string()),
+                    ..PageReport::new(link.as_str(), PageOutcome::Failed)
+                });
+            }
+        }
+    }
+
+    Ok(pages)
+}
+
+/// Download a single document and extract it, reporting why it was not kept
+async fn fetch_document(
+    client: &reqwest::Client,
+    url: &Url,
+    config: &CrawlerConfig,
+) -> Result<(Option<CrawledPage>, PageReport), CrawlError> {
+    let mut response = client.get(url.clone()).send().await?;
+
+    let status = response.status().as_u16();
+    let mut report = PageReport {
+        status_code: Some(status),
+        ..PageReport::new(url.as_str(), PageOutcome::Fetched)
+    };
+    if !response.status().is_success() {
+        report.outcome = PageOutcome::Failed;
+        return Ok((None, report));
+    }
+
+    let kind = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(DocumentKind::from_content_type)
+        .or_else(|| DocumentKind::from_url(url));
+    let Some(kind) = kind else {
+        report.outcome = PageOutcome::Skipped;
+        report.detail = Some("not a supported document type".to_string());
+        return Ok((None, report));
+    };
+
+    let too_large = || {
+        format!(
+            "{} document larger than {} bytes",
+            kind, config.max_document_bytes
+        )
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > config.max_document_bytes)
+    {
+        report.outcome = PageOutcome::Skipped;
+        report.detail = Some(too_large());
+        return Ok((None, report));
+    }
+
+    // Servers may omit or misstate the length, so the cap is enforced while reading
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > config.max_document_bytes {
+            report.outcome = PageOutcome::Skipped;
+            report.detail = Some(too_large());
+            return Ok((None, report));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let content = match extract_text(kind, &bytes) {
+        Ok(content) => content,
+        Err(e) => {
+            report.outcome = PageOutcome::Skipped;
+            report.detail = Some(e.to_string());
+            return Ok((None, report));
+        }
+    };
+    if content.trim().is_empty() {
+        report.outcome = PageOutcome::EmptyContent;
+        report.detail = Some(format!("{} document without text", kind));
+        return Ok((None, report));
+    }
+    debug!("Extracted {} characters from {}", content.len(), url);
+    report.detail = Some(format!("{} document", kind));
+
+    let title = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.').map(|(stem, _)| stem))
+        .map(|stem| {
+            stem.replace("%20", " ")
+                .replace(['-', '_'], " ")
+                .trim()
+                .to_string()
+        })
+        .filter(|title| !title.is_empty());
+    let page = CrawledPage {
+        url: url.to_string(),
+        content,
+        metadata: PageMetadata {
+            title,
+            description: None,
+            publication_date: None,
+            author: None,
+            domain: url.host_str().unwrap_or_default().to_string(),
+            tags: vec![kind.to_string()],
+            content_type: None,
+        },
+        raw_html: None,
+    };
+    Ok((Some(page), report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_kind() {
+        let url = Url::parse("https://example.com/files/Report.PDF?download=1").unwrap();
+        assert_eq!(DocumentKind::from_url(&url), Some(DocumentKind::Pdf));
+        let url = Url::parse("https://example.com/docs/guide").unwrap();
+        assert_eq!(DocumentKind::from_url(&url), None);
+        assert_eq!(
+            DocumentKind::from_content_type("text/csv; charset=utf-8"),
+            Some(DocumentKind::Csv)
+        );
+        assert_eq!(DocumentKind::from_content_type("text/html"), None);
+    }
+
+    #[test]
+    fn test_csv_to_markdown() {
+        let csv = "name,description\r\nhal,\"Crawls, chunks and \"\"indexes\"\"\"\n\ntool,a|b\n";
+        assert_eq!(
+            csv_to_markdown(csv),
+            "| name | description |\n| --- | --- |\n\
+             | hal | Crawls, chunks and \"indexes\" |\n| tool | a\\|b |"
+        );
+        assert_eq!(csv_to_markdown(""), "");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_documents() {
+        let mut server = mockito::Server::new_async().await;
+        let _csv = server
+            .mock("GET", "/data/release_notes.csv")
+            .with_header("content-type", "text/csv")
+            .with_body("version,date\n1.0,2024-01-01\n")
+            .create_async()
+            .await;
+        let _large = server
+            .mock("GET", "/large.csv")
+            .with_header("content-type", "text/csv")
+            .with_body("a,b\n".repeat(100))
+            .create_async()
+            .await;
+
+        let config = CrawlerConfig::builder()
+            .rate_limit_ms(0)
+            .max_document_bytes(64)
+            .build();
+        let links = vec![
+            Url::parse(&format!("{}/data/release_notes.csv", server.url())).unwrap(),
+            Url::parse(&format!("{}/large.csv", server.url())).unwrap(),
+        ];
+        let mut report = CrawlReport::new(server.url());
+        let pages = fetch_documents(links, &config, 10, &mut report)
+            .await
+            .unwrap();
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].metadata.title.as_deref(), Some("release notes"));
+        assert!(pages[0].content.contains("| 1.0 | 2024-01-01 |"));
+        assert_eq!(report.summary.fetched, 1);
+        assert_eq!(report.summary.skipped, 1);
+        assert!(
+            report.pages[1]
+                .detail
+                .as_deref()
+                .unwrap()
+                .contains("64 bytes")
+        );
+    }
+}
@@ -49,6 +49,10 @@ pub enum CrawlError {
     #[error("URL parsing error: {0}")]
     UrlParse(#[from] url::ParseError),
 
+    /// Index database error
+    #[error("Database error: {0}")]
+    Database(#[from] crate::index::DbError),
+
     /// Other errors
     #[error("{0}")]
     Other(String),
@@ -0,0 +1,163 @@
+//! # Git Change Detection Module
+//!
+//! This module asks `git` which files of a repository changed since the commit it was last
+//! indexed at, so re-indexing a documentation repository only reprocesses what changed.
+//!
+//! ## Key Components
+//!
+//! - `head_commit`: The SHA of the commit checked out in a repository
+//! - `changed_files`: Files added, modified or deleted between a commit and `HEAD`
+//! - `GitChanges`: The changed and deleted files of a diff
+//!
+//! Paths are relative to the directory passed in, and only files below it are reported,
+//! so a subdirectory of a repository can be indexed on its own. Renames are reported as a
+//! deletion and an addition.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tracing::{debug, instrument};
+
+use crate::crawler::error::CrawlError;
+
+/// Files changed between two commits
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitChanges {
+    /// Files added or modified, to be reprocessed
+    pub changed: Vec<PathBuf>,
+
+    /// Files deleted, whose chunks are to be removed
+    pub deleted: Vec<PathBuf>,
+}
+
+/// Run git in a directory and return its standard output
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, CrawlError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| CrawlError::Other(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(CrawlError::Other(format!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// The SHA of the commit checked out in a repository
+///
+/// # Arguments
+///
+/// * `dir` - The repository or a directory inside it
+#[instrument]
+pub fn head_commit(dir: &Path) -> Result<String, CrawlError> {
+    let output = git(dir, &["rev-parse", "HEAD"])?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Files below a directory that changed between a commit and `HEAD`
+///
+/// # Arguments
+///
+/// * `dir` - The repository or a directory inside it
+/// * `since` - The commit to compare `HEAD` against
+///
+/// # Returns
+///
+/// The changed and deleted files, relative to `dir`
+#[instrument]
+pub fn changed_files(dir: &Path, since: &str) -> Result<GitChanges, CrawlError> {
+    let output = git(
+        dir,
+        &[
+            "diff",
+            "--name-status",
+            "--no-renames",
+            "--relative",
+            "-z",
+            since,
+            "HEAD",
+        ],
+    )?;
+    let changes = parse_name_status(&output);
+    debug!(
+        "{} files changed and {} deleted since {}",
+        changes.changed.len(),
+        changes.deleted.len(),
+        since
+    );
+    Ok(changes)
+}
+
+/// Parse `git diff --name-status -z` output, which alternates status and path
+fn parse_name_status(output: &[u8]) -> GitChanges {
+    let mut changes = GitChanges::default();
+    let mut fields = output
+        .split(|&byte| byte == 0)
+        .filter(|field| !field.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let path = PathBuf::from(String::from_utf8_lossy(path).into_owned());
+        if status.first() == Some(&b'D') {
+            changes.deleted.push(path);
+        } else {
+            changes.changed.push(path);
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_name_status() {
+        let changes = parse_name_status(b"M\0guide.md\0D\0old notes.md\0A\0api/new.md\0");
+        assert_eq!(
+            changes,
+            GitChanges {
+                changed: vec![PathBuf::from("guide.md"), PathBuf::from("api/new.md")],
+                deleted: vec![PathBuf::from("old notes.md")],
+            }
+        );
+        assert_eq!(parse_name_status(b""), GitChanges::default());
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        let run = |args: &[&str]| git(repo, args).unwrap();
+        if git(repo, &["init", "-q"]).is_err() {
+            // git is not installed
+            return;
+        }
+        run(&["config", "user.email", "hal@example.com"]);
+        run(&["config", "user.name", "hal"]);
+
+        std::fs::create_dir(repo.join("docs")).unwrap();
+        std::fs::write(repo.join("docs/guide.md"), "# Guide").unwrap();
+        std::fs::write(repo.join("docs/old.md"), "# Old").unwrap();
+        std::fs::write(repo.join("README.md"), "# Readme").unwrap();
+        run(&["add", "-A"]);
+        run(&["commit", "-qm", "Initial"]);
+        let first = head_commit(repo).unwrap();
+
+        std::fs::write(repo.join("docs/guide.md"), "# Guide\n\nUpdated").unwrap();
+        std::fs::remove_file(repo.join("docs/old.md")).unwrap();
+        std::fs::write(repo.join("README.md"), "# Readme\n\nUpdated").unwrap();
+        run(&["add", "-A"]);
+        run(&["commit", "-qm", "Update"]);
+        assert_ne!(head_commit(repo).unwrap(), first);
+
+        let changes = changed_files(&repo.join("docs"), &first).unwrap();
+        assert_eq!(changes.changed, vec![PathBuf::from("guide.md")]);
+        assert_eq!(changes.deleted, vec![PathBuf::from("old.md")]);
+    }
+}
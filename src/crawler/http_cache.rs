@@ -0,0 +1,251 @@
+//! # HTTP Cache Module
+//!
+//! This module revalidates previously crawled pages with conditional requests, so a
+//! recrawl only downloads and reprocesses pages that changed. The `ETag` and
+//! `Last-Modified` validators of every fetched page are kept in the crawl report and
+//! stored in the index database between crawls.
+//!
+//! ## Key Components
+//!
+//! - `HttpValidators`: The `ETag` and `Last-Modified` headers of a response
+//! - `revalidate_pages`: Sends conditional requests for known pages before a crawl
+//! - `Revalidation`: The pages that changed and the URLs that did not
+//!
+//! Unchanged pages answer `304 Not Modified` and are reported as `not_modified`; the
+//! crawler does not fetch them again, so pages only reachable through them are found on
+//! the next crawl without the cache.
+
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use reqwest::header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, warn};
+
+use crate::crawler::error::CrawlError;
+use crate::crawler::report::{PageOutcome, PageReport};
+use crate::crawler::{CrawledPage, CrawlerConfig, page_from_html};
+
+/// Conditional requests in flight at once
+const CONCURRENCY: usize = 4;
+
+/// Cache validators of a response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpValidators {
+    /// The `ETag` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    /// The `Last-Modified` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl HttpValidators {
+    /// The validators of a response's headers, or `None` if it has neither
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Self::from_header_values(|name| headers.get(name).and_then(|value| value.to_str().ok()))
+    }
+
+    /// The validators of a page crawled by spider, whose headers come from its own reqwest
+    pub fn from_spider_headers(headers: &spider::reqwest::header::HeaderMap) -> Option<Self> {
+        Self::from_header_values(|name| headers.get(name).and_then(|value| value.to_str().ok()))
+    }
+
+    /// The validators read with a header lookup by name
+    fn from_header_values<'a>(header: impl Fn(&str) -> Option<&'a str>) -> Option<Self> {
+        let validators = Self {
+            etag: header(ETAG.as_str()).map(str::to_string),
+            last_modified: header(LAST_MODIFIED.as_str()).map(str::to_string),
+        };
+        (!validators.is_empty()).then_some(validators)
+    }
+
+    /// Whether neither validator is set
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Add the conditional request headers for these validators
+    fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut request = request;
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// The result of revalidating known pages
+#[derive(Debug, Default)]
+pub struct Revalidation {
+    /// Pages that changed, with their new content
+    pub pages: Vec<CrawledPage>,
+
+    /// The outcome of every revalidated URL
+    pub reports: Vec<PageReport>,
+}
+
+impl Revalidation {
+    /// URLs the server reported as unchanged
+    pub fn not_modified(&self) -> impl Iterator<Item = &str> {
+        self.reports
+            .iter()
+            .filter(|report| report.outcome == PageOutcome::NotModified)
+            .map(|report| report.url.as_str())
+    }
+}
+
+/// Revalidate previously crawled pages with conditional requests
+///
+/// # Arguments
+///
+/// * `cache` - Validators of the pages from their last crawl, by URL
+/// * `config` - The crawler configuration, for the user agent
+///
+/// # Returns
+///
+/// The changed pages and the outcome of every URL, or an error if the proxy or TLS
+/// settings are invalid. Requests that fail are reported as failed so the crawl can
+/// fetch the page again.
+#[instrument(skip(cache, config), fields(pages = cache.len()))]
+pub async fn revalidate_pages(
+    cache: &HashMap<String, HttpValidators>,
+    config: &CrawlerConfig,
+) -> Result<Revalidation, CrawlError> {
+    let client = config.http_client()?;
+    let mut urls: Vec<_> = cache.iter().collect();
+    urls.sort_by_key(|(url, _)| *url);
+
+    let results: Vec<_> = futures::stream::iter(urls)
+        .map(|(url, validators)| {
+            let client = client.clone();
+            async move {
+                let result = revalidate_page(&client, url, validators).await;
+                (url, result)
+            }
+        })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut revalidation = Revalidation::default();
+    for (url, result) in results {
+        match result {
+            Ok((page, report)) => {
+                revalidation.pages.extend(page);
+                revalidation.reports.push(report);
+            }
+            Err(e) => {
+                warn!("Failed to revalidate {}: {}", url, e);
+                revalidation.reports.push(PageReport {
+                    detail: Some(e.to_string()),
+                    ..PageReport::new(url.as_str(), PageOutcome::Failed)
+                });
+            }
+        }
+    }
+    Ok(revalidation)
+}
+
+/// Send a conditional request for a single page
+async fn revalidate_page(
+    client: &reqwest::Client,
+    url: &str,
+    validators: &HttpValidators,
+) -> Result<(Option<CrawledPage>, PageReport), CrawlError> {
+    let response = validators.apply(client.get(url)).send().await?;
+
+    let status = response.status();
+    let mut report = PageReport {
+        status_code: Some(status.as_u16()),
+        validators: HttpValidators::from_headers(response.headers()),
+        ..PageReport::new(url, PageOutcome::Fetched)
+    };
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        debug!("Not modified: {}", url);
+        report.outcome = PageOutcome::NotModified;
+        report.validators = report.validators.or_else(|| Some(validators.clone()));
+        return Ok((None, report));
+    }
+    if !status.is_success() {
+        report.outcome = PageOutcome::Failed;
+        return Ok((None, report));
+    }
+
+    let final_url = response.url().to_string();
+    if final_url != url {
+        report.outcome = PageOutcome::Redirected;
+        report.redirected_to = Some(final_url.clone());
+    }
+    let html = response.text().await?;
+    let page = page_from_html(&final_url, &html);
+    if page.is_none() {
+        report.outcome = PageOutcome::EmptyContent;
+    }
+    Ok((page, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validators_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(HttpValidators::from_headers(&headers), None);
+
+        headers.insert(ETAG, "\"v2\"".parse().unwrap());
+        let validators = HttpValidators::from_headers(&headers).unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(validators.last_modified, None);
+    }
+
+    #[tokio::test]
+    async fn test_revalidate_pages() {
+        let mut server = mockito::Server::new_async().await;
+        let _unchanged = server
+            .mock("GET", "/unchanged")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+        let body = format!(
+            "<html><body><p>{}</p></body></html>",
+            "Updated text. ".repeat(20)
+        );
+        let _changed = server
+            .mock("GET", "/changed")
+            .with_header("etag", "\"v3\"")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let etag = |value: &str| HttpValidators {
+            etag: Some(value.to_string()),
+            last_modified: None,
+        };
+        let cache = HashMap::from([
+            (format!("{}/unchanged", server.url()), etag("\"v1\"")),
+            (format!("{}/changed", server.url()), etag("\"v2\"")),
+        ]);
+        let revalidation = revalidate_pages(&cache, &CrawlerConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            revalidation.not_modified().collect::<Vec<_>>(),
+            vec![format!("{}/unchanged", server.url())]
+        );
+        assert_eq!(revalidation.pages.len(), 1);
+        let changed = revalidation
+            .reports
+            .iter()
+            .find(|report| report.outcome == PageOutcome::Fetched)
+            .unwrap();
+        assert_eq!(changed.validators, Some(etag("\"v3\"")));
+    }
+}
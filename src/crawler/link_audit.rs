@@ -0,0 +1,328 @@
+//! # Link Audit Module
+//!
+//! This module checks an indexed site for pages that disappeared since they were indexed
+//! and for dead links in the indexed content, without recrawling the site. Every stored
+//! page and every link found in the stored Markdown is checked with a lightweight `HEAD`
+//! request, falling back to `GET` for servers that do not support `HEAD`.
+//!
+//! ## Key Components
+//!
+//! - `LinkAuditConfig`: Concurrency, timeout and user agent of the checks
+//! - `LinkAudit`: The vanished pages and dead links of a site
+//! - `audit_links`: Runs the audit for an indexed domain
+//! - `prune_vanished_pages`: Deletes the chunks of pages that vanished
+//!
+//! Pages answering `404 Not Found` or `410 Gone` count as vanished. Other errors may be
+//! transient, so they are reported as dead links but never pruned.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+use url::Url;
+
+use crate::crawler::error::CrawlError;
+use crate::index::{Database, DbError};
+
+/// Configuration of a link audit
+#[derive(Debug, Clone)]
+pub struct LinkAuditConfig {
+    /// Number of concurrent requests
+    pub concurrency: usize,
+
+    /// Timeout of each request in seconds
+    pub timeout_secs: u64,
+
+    /// Whether links found in the content are checked, or only the stored pages
+    pub check_links: bool,
+
+    /// User agent sent with the requests
+    pub user_agent: String,
+}
+
+impl Default for LinkAuditConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            timeout_secs: 10,
+            check_links: true,
+            user_agent: "hal-rag/0.1".to_string(),
+        }
+    }
+}
+
+/// The result of checking a URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkCheck {
+    /// The checked URL
+    pub url: String,
+
+    /// HTTP status code, if the server answered
+    pub status_code: Option<u16>,
+
+    /// The request error, if the server could not be reached
+    pub error: Option<String>,
+}
+
+impl LinkCheck {
+    /// Whether the URL is broken
+    pub fn is_dead(&self) -> bool {
+        self.error.is_some() || self.status_code.is_some_and(|status| status >= 400)
+    }
+
+    /// Whether the server reports the URL as permanently missing
+    pub fn is_gone(&self) -> bool {
+        matches!(self.status_code, Some(404 | 410))
+    }
+}
+
+/// A dead link and the pages linking to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLink {
+    /// The result of checking the link
+    pub check: LinkCheck,
+
+    /// Indexed pages containing the link
+    pub found_on: Vec<String>,
+}
+
+/// The result of a link audit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkAudit {
+    /// The audited domain
+    pub domain: String,
+
+    /// Number of stored pages checked
+    pub pages_checked: usize,
+
+    /// Number of distinct links checked, excluding stored pages
+    pub links_checked: usize,
+
+    /// Stored pages that no longer exist
+    pub vanished_pages: Vec<LinkCheck>,
+
+    /// Stored pages that could not be checked, e.g. because of a server error
+    pub unreachable_pages: Vec<LinkCheck>,
+
+    /// Dead links found in the stored content
+    pub dead_links: Vec<DeadLink>,
+}
+
+/// Audit the pages and links of an indexed domain
+///
+/// # Arguments
+///
+/// * `db` - The database holding the index
+/// * `domain` - Domain of the indexed website
+/// * `config` - Concurrency, timeout and user agent of the checks
+///
+/// # Returns
+///
+/// The vanished pages and dead links of the domain
+#[instrument(skip(db, config))]
+pub async fn audit_links(
+    db: &Database,
+    domain: &str,
+    config: &LinkAuditConfig,
+) -> Result<LinkAudit, CrawlError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT c.url, c.text FROM chunks c
+             JOIN websites w ON c.website_id = w.id
+             WHERE w.domain = ?
+             ORDER BY c.url, c.position",
+            vec![libsql::Value::from(domain.to_string())],
+        )
+        .await?;
+
+    let mut pages = BTreeSet::new();
+    let mut links: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    while let Some(row) = rows.next().await.map_err(DbError::from)? {
+        let url: String = row.get(0).map_err(DbError::from)?;
+        let text = db
+            .decode_text(row.get_value(1).map_err(DbError::from)?)
+            .await?;
+        if config.check_links {
+            for link in extract_links(&url, &text) {
+                links.entry(link).or_default().insert(url.clone());
+            }
+        }
+        pages.insert(url);
+    }
+    links.retain(|link, _| !pages.contains(link));
+
+    if pages.is_empty() {
+        return Err(CrawlError::Other(format!(
+            "No indexed pages found for {}",
+            domain
+        )));
+    }
+    info!(
+        "Checking {} pages and {} links of {}",
+        pages.len(),
+        links.len(),
+        domain
+    );
+
+    let client = reqwest::Client::builder()
+        .user_agent(config.user_agent.clone())
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()?;
+
+    let urls: Vec<String> = pages.iter().chain(links.keys()).cloned().collect();
+    let checks: BTreeMap<String, LinkCheck> = stream::iter(urls)
+        .map(|url| {
+            let client = client.clone();
+            async move { (url.clone(), check_url(&client, &url).await) }
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut audit = LinkAudit {
+        domain: domain.to_string(),
+        pages_checked: pages.len(),
+        links_checked: links.len(),
+        ..LinkAudit::default()
+    };
+    for page in &pages {
+        let check = &checks[page];
+        if check.is_gone() {
+            audit.vanished_pages.push(check.clone());
+        } else if check.is_dead() {
+            audit.unreachable_pages.push(check.clone());
+        }
+    }
+    for (link, found_on) in links {
+        let check = &checks[&link];
+        if check.is_dead() {
+            audit.dead_links.push(DeadLink {
+                check: check.clone(),
+                found_on: found_on.into_iter().collect(),
+            });
+        }
+    }
+
+    Ok(audit)
+}
+
+/// Delete the chunks of the pages that vanished
+///
+/// # Returns
+///
+/// The number of chunks deleted
+pub async fn prune_vanished_pages(db: &Database, audit: &LinkAudit) -> Result<usize, CrawlError> {
+    let mut deleted = 0;
+    for page in &audit.vanished_pages {
+        debug!("Pruning chunks of {}", page.url);
+        deleted += db.delete_chunks_by_page_url(&page.url).await?;
+    }
+    Ok(deleted)
+}
+
+/// Check a URL with `HEAD`, retrying with `GET` if the server rejects `HEAD`
+async fn check_url(client: &reqwest::Client, url: &str) -> LinkCheck {
+    let mut response = client.head(url).send().await;
+    let head_rejected = response.as_ref().is_ok_and(|head| {
+        matches!(
+            head.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED | StatusCode::FORBIDDEN
+        )
+    });
+    if head_rejected {
+        response = client.get(url).send().await;
+    }
+
+    match response {
+        Ok(response) => LinkCheck {
+            url: url.to_string(),
+            status_code: Some(response.status().as_u16()),
+            error: None,
+        },
+        Err(e) => LinkCheck {
+            url: url.to_string(),
+            status_code: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// HTTP(S) links of a Markdown chunk, resolved against the page URL and without fragments
+fn extract_links(page_url: &str, markdown: &str) -> Vec<String> {
+    let Ok(base) = Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let pattern = Regex::new(r"\]\(<?([^)\s>]+)>?(?:\s+[^)]*)?\)").expect("valid regex");
+
+    pattern
+        .captures_iter(markdown)
+        .filter_map(|captures| base.join(&captures[1]).ok())
+        .filter(|link| matches!(link.scheme(), "http" | "https"))
+        .map(|mut link| {
+            link.set_fragment(None);
+            link.to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let markdown = "See [the guide](/docs/guide#install) and [spec](https://other.com/spec \"Spec\").\n\
+                        ![logo](images/logo.png) [mail](mailto:team@example.com) [top](#top)";
+
+        let links = extract_links("https://example.com/en/page", markdown);
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/docs/guide",
+                "https://other.com/spec",
+                "https://example.com/en/images/logo.png",
+                "https://example.com/en/page",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_url() {
+        let mut server = mockito::Server::new_async().await;
+        let _ok = server
+            .mock("HEAD", "/ok")
+            .with_status(200)
+            .create_async()
+            .await;
+        let _gone = server
+            .mock("HEAD", "/gone")
+            .with_status(410)
+            .create_async()
+            .await;
+        let _head = server
+            .mock("HEAD", "/no-head")
+            .with_status(405)
+            .create_async()
+            .await;
+        let _get = server
+            .mock("GET", "/no-head")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let ok = check_url(&client, &format!("{}/ok", server.url())).await;
+        assert!(!ok.is_dead());
+
+        let gone = check_url(&client, &format!("{}/gone", server.url())).await;
+        assert!(gone.is_dead());
+        assert!(gone.is_gone());
+
+        let no_head = check_url(&client, &format!("{}/no-head", server.url())).await;
+        assert_eq!(no_head.status_code, Some(200));
+    }
+}
@@ -0,0 +1,465 @@
+//! # Mail Ingestion Module
+//!
+//! This module turns mbox files and Maildir directories into `CrawledPage`s, so mail
+//! archives can be indexed as a personal knowledge base.
+//!
+//! ## Key Components
+//!
+//! - `MailMessage`: A parsed message with its sender, subject, date and cleaned body
+//! - `parse_mbox`: Splits an mbox file into messages
+//! - `load_maildir`: Reads the messages of a Maildir directory
+//! - `clean_body`: Strips quoted replies and signatures from a message body
+//!
+//! ## Features
+//!
+//! - Header unfolding and RFC 2822 date parsing
+//! - `text/plain` part selection for multipart messages
+//! - Quoted-printable decoding
+//! - Sender and date carried into `PageMetadata` for date-filtered search
+//!
+//! Messages are given `mail://<mailbox>/<message id>` URLs so each mailbox is
+//! indexed as its own website.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use tracing::{debug, instrument};
+
+use crate::crawler::error::CrawlError;
+use crate::crawler::{CrawledPage, PageMetadata};
+
+/// A parsed mail message
+#[derive(Debug, Clone, PartialEq)]
+pub struct MailMessage {
+    /// Message-ID header without angle brackets
+    pub message_id: Option<String>,
+
+    /// From header
+    pub from: Option<String>,
+
+    /// Subject header
+    pub subject: Option<String>,
+
+    /// Date header
+    pub date: Option<DateTime<Utc>>,
+
+    /// Plain text body with quoted replies and signatures removed
+    pub body: String,
+}
+
+impl MailMessage {
+    /// Convert the message into a crawled page
+    ///
+    /// # Arguments
+    ///
+    /// * `mailbox` - Name of the mailbox, used as the host of the page URL
+    /// * `index` - Position of the message, used when it has no Message-ID
+    pub fn into_page(self, mailbox: &str, index: usize) -> CrawledPage {
+        let id = self
+            .message_id
+            .clone()
+            .unwrap_or_else(|| format!("message-{}", index));
+        let id: String = id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-._@".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        let mut content = String::new();
+        if let Some(subject) = &self.subject {
+            content.push_str(&format!("# {}\n\n", subject));
+        }
+        if let Some(from) = &self.from {
+            content.push_str(&format!("From: {}\n", from));
+        }
+        if let Some(date) = &self.date {
+            content.push_str(&format!("Date: {}\n", date.to_rfc2822()));
+        }
+        content.push('\n');
+        content.push_str(&self.body);
+
+        CrawledPage {
+            url: format!("mail://{}/{}", mailbox, id),
+            content,
+            metadata: PageMetadata {
+                title: self.subject,
+                description: None,
+                publication_date: self.date,
+                author: self.from,
+                domain: mailbox.to_string(),
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        }
+    }
+}
+
+/// Split an mbox file into messages
+///
+/// # Arguments
+///
+/// * `mbox` - The contents of the mbox file
+///
+/// # Returns
+///
+/// The parsed messages, skipping any without a body
+pub fn parse_mbox(mbox: &str) -> Vec<MailMessage> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+
+    for line in mbox.split_inclusive('\n') {
+        if line.starts_with("From ") {
+            if let Some(message) = parse_message(&current) {
+                messages.push(message);
+            }
+            current.clear();
+            continue;
+        }
+        // Undo mboxrd escaping of body lines that begin with "From "
+        match line.strip_prefix('>') {
+            Some(rest) if rest.trim_start_matches('>').starts_with("From ") => {
+                current.push_str(rest)
+            }
+            _ => current.push_str(line),
+        }
+    }
+    if let Some(message) = parse_message(&current) {
+        messages.push(message);
+    }
+
+    debug!("Parsed {} messages from mbox", messages.len());
+    messages
+}
+
+/// Read the messages of a Maildir directory from its `cur` and `new` subdirectories
+///
+/// # Arguments
+///
+/// * `path` - Path of the Maildir directory
+///
+/// # Returns
+///
+/// The parsed messages, ordered by file name
+#[instrument]
+pub fn load_maildir(path: &Path) -> Result<Vec<MailMessage>, CrawlError> {
+    let mut files = Vec::new();
+    for subdir in ["cur", "new"] {
+        let dir = path.join(subdir);
+        if !dir.is_dir() {
+            continue;
+        }
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| CrawlError::Other(format!("Failed to read {}: {}", dir.display(), e)))?;
+        for entry in entries {
+            let entry =
+                entry.map_err(|e| CrawlError::Other(format!("Failed to read entry: {}", e)))?;
+            if entry.path().is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+
+    let mut messages = Vec::new();
+    for file in files {
+        let bytes = std::fs::read(&file)
+            .map_err(|e| CrawlError::Other(format!("Failed to read {}: {}", file.display(), e)))?;
+        if let Some(message) = parse_message(&String::from_utf8_lossy(&bytes)) {
+            messages.push(message);
+        }
+    }
+
+    debug!("Parsed {} messages from maildir", messages.len());
+    Ok(messages)
+}
+
+/// Whether a directory looks like a Maildir
+pub fn is_maildir(path: &Path) -> bool {
+    path.join("cur").is_dir() && path.join("new").is_dir()
+}
+
+/// Parse a single RFC 2822 message
+///
+/// # Arguments
+///
+/// * `raw` - The raw message including headers
+///
+/// # Returns
+///
+/// The parsed message, or `None` when it has no text body
+pub fn parse_message(raw: &str) -> Option<MailMessage> {
+    let raw = raw.replace("\r\n", "\n");
+    let (header_block, body) = raw.split_once("\n\n").unwrap_or((raw.as_str(), ""));
+    let headers = parse_headers(header_block);
+
+    let content_type = header(&headers, "content-type").unwrap_or_default();
+    let encoding = header(&headers, "content-transfer-encoding").unwrap_or_default();
+    let text = extract_text(&content_type, &encoding, body)?;
+
+    let body = clean_body(&text);
+    if body.is_empty() {
+        return None;
+    }
+
+    Some(MailMessage {
+        message_id: header(&headers, "message-id")
+            .map(|id| id.trim_matches(['<', '>']).to_string()),
+        from: header(&headers, "from"),
+        subject: header(&headers, "subject"),
+        date: header(&headers, "date").and_then(|date| {
+            DateTime::parse_from_rfc2822(&date)
+                .ok()
+                .map(|date| date.with_timezone(&Utc))
+        }),
+        body,
+    })
+}
+
+/// Remove quoted replies, reply attributions and signatures from a body
+///
+/// # Arguments
+///
+/// * `body` - The plain text body
+///
+/// # Returns
+///
+/// The cleaned body
+pub fn clean_body(body: &str) -> String {
+    let mut lines = Vec::new();
+    for line in body.lines() {
+        // Everything after the signature delimiter is the signature
+        if line == "-- " || line == "--" {
+            break;
+        }
+        // Outlook style forwarded history
+        if line.trim_start().starts_with("-----Original Message-----") {
+            break;
+        }
+        if line.trim_start().starts_with('>') {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    // Drop the "On <date>, <someone> wrote:" attribution left before a quote
+    while lines
+        .last()
+        .is_some_and(|line| line.trim().is_empty() || is_attribution(line))
+    {
+        lines.pop();
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+/// Whether a line introduces a quoted reply
+fn is_attribution(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("On ") && line.ends_with("wrote:")
+}
+
+/// Parse headers into lowercase name and value pairs, unfolding continuation lines
+fn parse_headers(block: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+/// Get the first value of a header
+fn header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.clone())
+        .filter(|value| !value.is_empty())
+}
+
+/// Extract the plain text of a body, descending into multipart messages
+fn extract_text(content_type: &str, encoding: &str, body: &str) -> Option<String> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    if mime.starts_with("multipart/") {
+        let boundary = content_type.split(';').find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            (name.trim().eq_ignore_ascii_case("boundary"))
+                .then(|| value.trim().trim_matches('"').to_string())
+        })?;
+
+        let delimiter = format!("--{}", boundary);
+        for part in body.split(&delimiter).skip(1) {
+            if part.starts_with("--") {
+                break;
+            }
+            let part = part.trim_start_matches('\n');
+            let (part_headers, part_body) = part.split_once("\n\n").unwrap_or((part, ""));
+            let part_headers = parse_headers(part_headers);
+            let text = extract_text(
+                &header(&part_headers, "content-type").unwrap_or_default(),
+                &header(&part_headers, "content-transfer-encoding").unwrap_or_default(),
+                part_body,
+            );
+            if text.is_some() {
+                return text;
+            }
+        }
+        return None;
+    }
+
+    if !mime.is_empty() && mime != "text/plain" {
+        return None;
+    }
+
+    match encoding.to_lowercase().as_str() {
+        "quoted-printable" => Some(decode_quoted_printable(body)),
+        // Binary transfer encodings are not decoded
+        "base64" => None,
+        _ => Some(body.to_string()),
+    }
+}
+
+/// Decode a quoted-printable body
+fn decode_quoted_printable(body: &str) -> String {
+    let mut bytes = Vec::with_capacity(body.len());
+    let mut lines = body.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let (line, soft_break) = match line.strip_suffix('=') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let raw = line.as_bytes();
+        let mut i = 0;
+        while i < raw.len() {
+            let escaped = if raw[i] == b'=' && i + 2 < raw.len() {
+                std::str::from_utf8(&raw[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            } else {
+                None
+            };
+            if let Some(byte) = escaped {
+                bytes.push(byte);
+                i += 3;
+                continue;
+            }
+            bytes.push(raw[i]);
+            i += 1;
+        }
+
+        if !soft_break && lines.peek().is_some() {
+            bytes.push(b'\n');
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MBOX: &str = "From alice@example.com Mon Jan  1 00:00:00 2024
+From: Alice <alice@example.com>
+To: Bob <bob@example.com>
+Subject: Deploy plan
+ for Friday
+Date: Mon, 1 Jan 2024 10:00:00 +0000
+Message-ID: <abc123@example.com>
+
+We deploy on Friday after the freeze.
+
+On Sun, Dec 31, 2023 at 9:00 AM Bob <bob@example.com> wrote:
+> When do we deploy?
+> Thanks
+
+From bob@example.com Tue Jan  2 00:00:00 2024
+From: Bob <bob@example.com>
+Subject: Re: Deploy plan
+Date: Tue, 2 Jan 2024 09:30:00 +0000
+Content-Type: multipart/alternative; boundary=\"b1\"
+
+--b1
+Content-Type: text/plain; charset=utf-8
+Content-Transfer-Encoding: quoted-printable
+
+Sounds good, caf=C3=A9 after=
+wards.
+>From now on we freeze earlier.
+
+--=20
+Bob
+--b1
+Content-Type: text/html
+
+<p>Sounds good</p>
+--b1--
+";
+
+    #[test]
+    fn test_parse_mbox() {
+        let messages = parse_mbox(MBOX);
+        assert_eq!(messages.len(), 2);
+
+        let first = &messages[0];
+        assert_eq!(first.subject.as_deref(), Some("Deploy plan for Friday"));
+        assert_eq!(first.from.as_deref(), Some("Alice <alice@example.com>"));
+        assert_eq!(first.message_id.as_deref(), Some("abc123@example.com"));
+        assert_eq!(
+            first.date.unwrap().to_rfc3339(),
+            "2024-01-01T10:00:00+00:00"
+        );
+        assert_eq!(first.body, "We deploy on Friday after the freeze.");
+
+        let second = &messages[1];
+        assert_eq!(
+            second.body,
+            "Sounds good, café afterwards.\nFrom now on we freeze earlier."
+        );
+    }
+
+    #[test]
+    fn test_clean_body() {
+        let body = "Thanks!\n\n-----Original Message-----\nFrom: someone\nOld text";
+        assert_eq!(clean_body(body), "Thanks!");
+
+        let body = "Short answer.\n\n> quoted\nMore after quote.\n-- \nSignature";
+        assert_eq!(clean_body(body), "Short answer.\n\nMore after quote.");
+    }
+
+    #[test]
+    fn test_into_page() {
+        let message = parse_mbox(MBOX).remove(0);
+        let page = message.into_page("inbox", 0);
+
+        assert_eq!(page.url, "mail://inbox/abc123@example.com");
+        assert_eq!(
+            page.metadata.author.as_deref(),
+            Some("Alice <alice@example.com>")
+        );
+        assert_eq!(page.metadata.domain, "inbox");
+        assert!(page.metadata.publication_date.is_some());
+        assert!(
+            page.content
+                .starts_with("# Deploy plan for Friday\n\nFrom: Alice")
+        );
+    }
+}
@@ -0,0 +1,421 @@
+//! # Crawl Report Module
+//!
+//! This module records what happened to every URL the crawler encountered, so a missing
+//! page can be diagnosed from the report instead of re-running the crawl with tracing.
+//!
+//! ## Key Components
+//!
+//! - `CrawlReport`: The outcome of every page of a crawl, serializable to JSON
+//! - `PageReport`: The outcome of a single URL
+//! - `PageOutcome`: Fetched, not modified, skipped by robots.txt, skipped for another
+//!   reason, failed, redirected, or empty
+//! - `CrawlSummary`: Counts per outcome
+//! - `RobotsRules`: The robots.txt rules used to explain skipped links
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::error::CrawlError;
+use crate::crawler::http_cache::HttpValidators;
+use crate::crawler::traps::CrawlTrap;
+
+/// What happened to a URL during a crawl
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageOutcome {
+    /// The page was fetched and its content kept
+    Fetched,
+
+    /// The page is disallowed by robots.txt and was not requested
+    SkippedByRobots,
+
+    /// The server answered with an error status or the request failed
+    Failed,
+
+    /// The page redirected elsewhere; the content of the destination was kept
+    Redirected,
+
+    /// The page was fetched but had too little content to keep
+    EmptyContent,
+
+    /// The URL was not kept for the reason in the report's detail, such as its size
+    Skipped,
+
+    /// A conditional request found the page unchanged since the last crawl
+    NotModified,
+}
+
+impl PageOutcome {
+    /// Label of the outcome in reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PageOutcome::Fetched => "fetched",
+            PageOutcome::SkippedByRobots => "skipped_by_robots",
+            PageOutcome::Failed => "failed",
+            PageOutcome::Redirected => "redirected",
+            PageOutcome::EmptyContent => "empty_content",
+            PageOutcome::Skipped => "skipped",
+            PageOutcome::NotModified => "not_modified",
+        }
+    }
+}
+
+impl fmt::Display for PageOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// The outcome of a single URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageReport {
+    /// The URL as it was requested or linked
+    pub url: String,
+
+    /// What happened to the URL
+    pub outcome: PageOutcome,
+
+    /// HTTP status code, if the URL was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+
+    /// Final URL after redirects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirected_to: Option<String>,
+
+    /// Additional detail, such as the matching robots.txt rule or the content length
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// Cache validators of the response, for conditional requests on the next crawl
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validators: Option<HttpValidators>,
+}
+
+impl PageReport {
+    /// Create a report for a URL with no status, redirect or detail
+    pub fn new(url: impl Into<String>, outcome: PageOutcome) -> Self {
+        Self {
+            url: url.into(),
+            outcome,
+            status_code: None,
+            redirected_to: None,
+            detail: None,
+            validators: None,
+        }
+    }
+}
+
+/// Counts of URLs per outcome
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlSummary {
+    /// Pages fetched and kept
+    pub fetched: usize,
+
+    /// Links disallowed by robots.txt
+    pub skipped_by_robots: usize,
+
+    /// Pages that failed
+    pub failed: usize,
+
+    /// Pages that redirected
+    pub redirected: usize,
+
+    /// Pages with too little content
+    pub empty_content: usize,
+
+    /// URLs skipped for another reason
+    #[serde(default)]
+    pub skipped: usize,
+
+    /// Pages unchanged since the last crawl
+    #[serde(default)]
+    pub not_modified: usize,
+}
+
+impl CrawlSummary {
+    /// Total number of URLs in the report
+    pub fn total(&self) -> usize {
+        self.fetched
+            + self.skipped_by_robots
+            + self.failed
+            + self.redirected
+            + self.empty_content
+            + self.skipped
+            + self.not_modified
+    }
+}
+
+/// The outcome of every URL encountered during a crawl
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlReport {
+    /// The URL the crawl started from
+    pub start_url: String,
+
+    /// When the crawl started
+    pub started_at: DateTime<Utc>,
+
+    /// When the crawl finished
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Counts per outcome
+    pub summary: CrawlSummary,
+
+    /// One entry per URL, in the order they were encountered
+    pub pages: Vec<PageReport>,
+
+    /// URL patterns the crawler stopped following
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traps: Vec<CrawlTrap>,
+
+    /// Whether the crawl was cancelled before it finished
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+impl CrawlReport {
+    /// Start a report for a crawl
+    pub fn new(start_url: impl Into<String>) -> Self {
+        Self {
+            start_url: start_url.into(),
+            started_at: Utc::now(),
+            finished_at: None,
+            summary: CrawlSummary::default(),
+            pages: Vec::new(),
+            traps: Vec::new(),
+            cancelled: false,
+        }
+    }
+
+    /// Record the outcome of a URL
+    pub fn record(&mut self, page: PageReport) {
+        match page.outcome {
+            PageOutcome::Fetched => self.summary.fetched += 1,
+            PageOutcome::SkippedByRobots => self.summary.skipped_by_robots += 1,
+            PageOutcome::Failed => self.summary.failed += 1,
+            PageOutcome::Redirected => self.summary.redirected += 1,
+            PageOutcome::EmptyContent => self.summary.empty_content += 1,
+            PageOutcome::Skipped => self.summary.skipped += 1,
+            PageOutcome::NotModified => self.summary.not_modified += 1,
+        }
+        self.pages.push(page);
+    }
+
+    /// Mark the crawl as finished
+    pub fn finish(&mut self) {
+        self.finished_at = Some(Utc::now());
+    }
+
+    /// Find the entry for a URL, ignoring a trailing slash
+    pub fn find(&self, url: &str) -> Option<&PageReport> {
+        let url = url.trim_end_matches('/');
+        self.pages.iter().find(|page| {
+            page.url.trim_end_matches('/') == url
+                || page
+                    .redirected_to
+                    .as_deref()
+                    .is_some_and(|to| to.trim_end_matches('/') == url)
+        })
+    }
+
+    /// URLs that still exist as of this crawl
+    ///
+    /// Includes fetched and unchanged pages, both ends of redirects, and pages that failed
+    /// with an error other than `404 Not Found` or `410 Gone`, since those failures may be
+    /// transient.
+    pub fn live_urls(&self) -> Vec<String> {
+        let mut urls = Vec::new();
+        for page in &self.pages {
+            match page.outcome {
+                PageOutcome::Fetched | PageOutcome::NotModified => urls.push(page.url.clone()),
+                PageOutcome::Redirected => {
+                    urls.push(page.url.clone());
+                    urls.extend(page.redirected_to.clone());
+                }
+                PageOutcome::Failed if !matches!(page.status_code, Some(404 | 410)) => {
+                    urls.push(page.url.clone())
+                }
+                _ => {}
+            }
+        }
+        urls
+    }
+
+    /// Number of pages the crawler requested
+    pub fn requested_pages(&self) -> usize {
+        self.summary.total() - self.summary.skipped_by_robots
+    }
+
+    /// Cache validators of the pages fetched or revalidated, by URL
+    pub fn validators(&self) -> impl Iterator<Item = (&str, &HttpValidators)> {
+        self.pages.iter().filter_map(|page| {
+            let url = page.redirected_to.as_deref().unwrap_or(&page.url);
+            page.validators.as_ref().map(|validators| (url, validators))
+        })
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub async fn write_json(&self, path: &Path) -> Result<(), CrawlError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CrawlError::Other(format!("Failed to serialize crawl report: {}", e)))?;
+        tokio::fs::write(path, json)
+            .await
+            .map_err(|e| CrawlError::Other(format!("Failed to write crawl report: {}", e)))
+    }
+}
+
+/// The robots.txt rules that apply to the crawler
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    /// Path prefixes and whether they are allowed
+    rules: Vec<(String, bool)>,
+}
+
+impl RobotsRules {
+    /// Parse the rules of a robots.txt file for a user agent
+    ///
+    /// Rules of groups naming the user agent take precedence over the `*` group.
+    pub fn parse(body: &str, user_agent: &str) -> Self {
+        let agent = user_agent
+            .split('/')
+            .next()
+            .unwrap_or(user_agent)
+            .to_lowercase();
+        let mut groups: HashMap<bool, Vec<(String, bool)>> = HashMap::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (field, value) = (field.trim().to_lowercase(), value.trim());
+
+            match field.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        current.clear();
+                        in_rules = false;
+                    }
+                    current.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (value.to_string(), field == "allow");
+                    for name in &current {
+                        if name == "*" {
+                            groups.entry(false).or_default().push(rule.clone());
+                        } else if agent.contains(name.as_str()) {
+                            groups.entry(true).or_default().push(rule.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let rules = groups
+            .remove(&true)
+            .or_else(|| groups.remove(&false))
+            .unwrap_or_default();
+        Self { rules }
+    }
+
+    /// The rule disallowing a path, or `None` if the path may be crawled
+    ///
+    /// The longest matching prefix wins and `Allow` wins ties, as crawlers commonly do.
+    pub fn disallowed_by(&self, path: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.trim_end_matches('*')))
+            .max_by_key(|(prefix, allow)| (prefix.len(), *allow))
+            .filter(|(_, allow)| !allow)
+            .map(|(prefix, _)| prefix.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_summary_and_find() {
+        let mut report = CrawlReport::new("https://example.com");
+        report.record(PageReport::new(
+            "https://example.com/",
+            PageOutcome::Fetched,
+        ));
+        report.record(PageReport {
+            status_code: Some(404),
+            ..PageReport::new("https://example.com/missing", PageOutcome::Failed)
+        });
+        report.record(PageReport {
+            redirected_to: Some("https://example.com/new".to_string()),
+            ..PageReport::new("https://example.com/old", PageOutcome::Redirected)
+        });
+        report.record(PageReport::new(
+            "https://example.com/private",
+            PageOutcome::SkippedByRobots,
+        ));
+        report.finish();
+
+        assert_eq!(report.summary.fetched, 1);
+        assert_eq!(report.summary.failed, 1);
+        assert_eq!(report.summary.total(), 4);
+        assert_eq!(
+            report
+                .find("https://example.com/missing/")
+                .unwrap()
+                .status_code,
+            Some(404)
+        );
+        assert_eq!(
+            report.find("https://example.com/new").unwrap().outcome,
+            PageOutcome::Redirected
+        );
+        assert!(report.find("https://example.com/other").is_none());
+
+        assert_eq!(
+            report.live_urls(),
+            vec![
+                "https://example.com/",
+                "https://example.com/old",
+                "https://example.com/new"
+            ]
+        );
+        assert_eq!(report.requested_pages(), 3);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["pages"][3]["outcome"], "skipped_by_robots");
+    }
+
+    #[test]
+    fn test_robots_rules() {
+        let robots = "
+            User-agent: *
+            Disallow: /private
+            Allow: /private/public
+            Disallow: /tmp/ # scratch
+
+            User-agent: otherbot
+            Disallow: /
+        ";
+        let rules = RobotsRules::parse(robots, "hal-rag/0.1");
+
+        assert_eq!(rules.disallowed_by("/private/notes"), Some("/private"));
+        assert_eq!(rules.disallowed_by("/private/public/page"), None);
+        assert_eq!(rules.disallowed_by("/tmp/file"), Some("/tmp/"));
+        assert_eq!(rules.disallowed_by("/docs"), None);
+
+        let specific = RobotsRules::parse(robots, "otherbot/2.0");
+        assert_eq!(specific.disallowed_by("/docs"), Some("/"));
+    }
+}
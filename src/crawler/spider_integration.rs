@@ -7,6 +7,8 @@
 //! ## Key Components
 //!
 //! - `crawl_website`: Main function to crawl a website with given configuration
+//! - `crawl_website_with_report`: Crawls and reports the outcome of every URL encountered
+//! - `page_from_html`: Applies the same extraction to stored raw HTML
 //! - Integration with Spider library's async crawling capabilities
 //! - Content transformation pipeline for HTML to Markdown conversion
 //!
@@ -16,7 +18,14 @@
 //! - URL filtering with regex patterns
 //! - Markdown conversion for cleaner text processing
 //! - Readability-focused content extraction
+//! - Extractor plugins converting the pages they match instead of the built-in extraction
 //! - Quality filtering to skip low-value pages
+//! - Size limits and guards against binary files, assets and minified scripts
+//! - Linked PDF and CSV documents downloaded after the crawl when enabled
+//! - Conditional requests for cached pages, which are not fetched again when unchanged
+//! - Proxy and TLS settings applied to every request of a crawl
+//! - Crawl trap detection, recrawling without the traps if they used up the page budget
+//! - Cancellation through `CrawlerConfig::cancel`, keeping the pages fetched so far
 //! - Structured logging and instrumentation
 //! - Proper error propagation
 //!
@@ -24,7 +33,10 @@
 //! RAG pipeline, gathering the raw material that will be processed, embedded,
 //! and indexed for retrieval.
 
+use std::collections::HashSet;
+
 use regex::Regex;
+use scraper::{Html, Selector};
 use spider::compact_str::CompactString;
 use spider::tokio;
 use spider::website::Website;
@@ -35,9 +47,29 @@ use tracing::{debug, error, info, info_span, instrument};
 use url::Url;
 
 use crate::crawler::content_extraction::extract_metadata;
+use crate::crawler::documents::{DocumentKind, fetch_documents};
 use crate::crawler::error::CrawlError;
+use crate::crawler::http_cache::{HttpValidators, revalidate_pages};
+use crate::crawler::report::{CrawlReport, PageOutcome, PageReport, RobotsRules};
+use crate::crawler::traps::TrapDetector;
 use crate::crawler::{CrawledPage, CrawlerConfig, PageMetadata};
 
+/// Minimum length of the Markdown content of a page worth keeping
+const MIN_CONTENT_LENGTH: usize = 100;
+
+/// Bytes at the start of a response inspected for binary or minified content
+const SNIFF_BYTES: usize = 8192;
+
+/// Line length beyond which content without markup is taken for a minified bundle
+const MINIFIED_LINE_LENGTH: usize = 5000;
+
+/// Extensions of static assets that never hold page content
+const ASSET_EXTENSIONS: &[&str] = &[
+    "js", "mjs", "css", "map", "wasm", "json", "png", "jpg", "jpeg", "gif", "webp", "svg", "ico",
+    "woff", "woff2", "ttf", "otf", "zip", "gz", "tgz", "bz2", "xz", "tar", "exe", "dmg", "iso",
+    "mp3", "mp4", "webm", "avi", "mov",
+];
+
 /// Crawl a website and extract content
 ///
 /// # Arguments
@@ -48,11 +80,35 @@ use crate::crawler::{CrawledPage, CrawlerConfig, PageMetadata};
 /// # Returns
 ///
 /// A vector of crawled pages
-#[instrument]
 pub async fn crawl_website(
     url: &str,
     config: CrawlerConfig,
 ) -> Result<Vec<CrawledPage>, CrawlError> {
+    let (pages, _report) = crawl_website_with_report(url, config).await?;
+    Ok(pages)
+}
+
+/// Crawl a website and report what happened to every URL encountered
+///
+/// # Arguments
+///
+/// * `url` - The URL to crawl
+/// * `config` - The crawler configuration
+///
+/// # Returns
+///
+/// The crawled pages and the crawl report. Links to pages on the same host that
+/// robots.txt disallows are reported as skipped. With `follow_documents`, linked
+/// documents on the same host are downloaded within the remaining page budget.
+/// Pages in `http_cache` are first revalidated with conditional requests; unchanged
+/// pages are reported as not modified and left out of the crawl. A cancelled crawl
+/// returns the pages fetched so far, without recrawling or fetching documents, and its
+/// report is marked as cancelled.
+#[instrument]
+pub async fn crawl_website_with_report(
+    url: &str,
+    config: CrawlerConfig,
+) -> Result<(Vec<CrawledPage>, CrawlReport), CrawlError> {
     info!("Starting crawl for {}", url);
     debug!("Crawler config: {:?}", config);
 
@@ -72,6 +128,128 @@ pub async fn crawl_website(
         None
     };
 
+    // The start page is always fetched so the crawl can discover new pages
+    let prefix = format!(
+        "{}://{}{}",
+        scheme,
+        base_url.host_str().unwrap_or_default(),
+        base_url.path()
+    );
+    let mut cache = config.http_cache.clone();
+    cache.retain(|cached, _| {
+        cached.trim_end_matches('/') != url.trim_end_matches('/')
+            && (!config.child_links_only || cached.starts_with(&prefix))
+    });
+    let revalidation = if cache.is_empty() {
+        None
+    } else {
+        info!("Revalidating {} cached pages", cache.len());
+        Some(revalidate_pages(&cache, &config).await?)
+    };
+    let unchanged: Option<Vec<CompactString>> = revalidation.as_ref().map(|revalidation| {
+        revalidation
+            .not_modified()
+            .map(|unchanged| CompactString::from(format!("^{}/?$", regex::escape(unchanged))))
+            .collect()
+    });
+
+    let robots = if config.respect_robots_txt {
+        fetch_robots_rules(&base_url, &config.http_client()?, &config.user_agent).await
+    } else {
+        RobotsRules::default()
+    };
+
+    let blacklist = unchanged.unwrap_or_default();
+    let (mut pages, mut report, mut links) =
+        run_spider(url, &config, allowed.clone(), blacklist.clone()).await?;
+
+    // Crawl again without the traps if they used up the page budget
+    if !report.cancelled
+        && !report.traps.is_empty()
+        && report.requested_pages() >= config.max_pages as usize
+    {
+        info!(
+            "Recrawling without {} crawl traps that used up the page budget",
+            report.traps.len()
+        );
+        let traps = report.traps.clone();
+        let mut blacklist = blacklist;
+        blacklist.extend(traps.iter().map(|trap| CompactString::from(&trap.regex)));
+        (pages, report, links) = run_spider(url, &config, allowed, blacklist).await?;
+        for trap in traps {
+            if !report.traps.iter().any(|known| known.regex == trap.regex) {
+                report.traps.push(trap);
+            }
+        }
+    }
+
+    // Keep revalidated pages the crawl did not reach again
+    if let Some(revalidation) = revalidation {
+        let crawled: HashSet<String> = report.pages.iter().map(|page| page.url.clone()).collect();
+        for page_report in revalidation.reports {
+            if !crawled.contains(&page_report.url) {
+                report.record(page_report);
+            }
+        }
+        for page in revalidation.pages {
+            if !pages.iter().any(|crawled| crawled.url == page.url) {
+                pages.push(page);
+            }
+        }
+    }
+
+    if config.follow_documents && !report.cancelled {
+        let mut documents: Vec<Url> = links
+            .iter()
+            .filter(|link| DocumentKind::from_url(link).is_some())
+            .filter(|link| report.find(link.as_str()).is_none())
+            .filter(|link| robots.disallowed_by(link.path()).is_none())
+            .cloned()
+            .collect();
+        documents.sort();
+        let limit = (config.max_pages as usize).saturating_sub(report.requested_pages());
+        info!("Fetching {} linked documents", documents.len().min(limit));
+        pages.extend(fetch_documents(documents, &config, limit, &mut report).await?);
+    }
+
+    let mut skipped: Vec<Url> = links
+        .into_iter()
+        .filter(|link| report.find(link.as_str()).is_none())
+        .collect();
+    skipped.sort();
+    for link in skipped {
+        if let Some(rule) = robots.disallowed_by(link.path()) {
+            report.record(PageReport {
+                detail: Some(format!("Disallow: {}", rule)),
+                ..PageReport::new(link.as_str(), PageOutcome::SkippedByRobots)
+            });
+        }
+    }
+    report.finish();
+
+    info!("Processed {} pages", pages.len());
+    Ok((pages, report))
+}
+
+/// Run the spider crawl and convert the pages it fetches
+///
+/// # Arguments
+///
+/// * `url` - The URL to crawl
+/// * `config` - The crawler configuration
+/// * `allowed` - Regexes of the URLs to crawl, or `None` for any URL on the host
+/// * `blacklist` - Regexes of URLs not to fetch, such as unchanged pages and crawl traps
+///
+/// # Returns
+///
+/// The pages, the report of the fetched URLs with the traps detected, and the same-host
+/// links found
+async fn run_spider(
+    url: &str,
+    config: &CrawlerConfig,
+    allowed: Option<Vec<CompactString>>,
+    blacklist: Vec<CompactString>,
+) -> Result<(Vec<CrawledPage>, CrawlReport, HashSet<Url>), CrawlError> {
     let mut website = Website::new(url);
     website
         .configuration
@@ -80,65 +258,469 @@ pub async fn crawl_website(
         .with_delay(config.rate_limit_ms)
         .with_depth(config.max_depth.try_into().unwrap_or(0))
         .with_limit(config.max_pages)
-        .with_whitelist_url(allowed);
+        .with_whitelist_url(allowed)
+        .with_blacklist_url((!blacklist.is_empty()).then_some(blacklist))
+        .with_proxies(config.proxy.clone().map(|proxy| vec![proxy]))
+        .with_danger_accept_invalid_certs(config.accept_invalid_certs);
+
+    let host = Url::parse(url)?.host_str().unwrap_or_default().to_string();
 
     let mut rx = website
         .subscribe(10)
         .ok_or_else(|| CrawlError::Other("Failed to subscribe to website".to_string()))?;
+    let mut report = CrawlReport::new(url);
+    let keep_raw_html = config.keep_raw_html;
+    let follow_documents = config.follow_documents;
+    let max_page_bytes = config.max_page_bytes;
+    let skip_binary_content = config.skip_binary_content;
+    let mut traps = config
+        .detect_traps
+        .then(|| TrapDetector::new(config.max_urls_per_pattern, config.max_segment_repeats));
+    // Extractor plugins take over the conversion of the pages they match
+    let plugins = crate::plugin::registry();
     let handle = tokio::spawn(async move {
         let mut pages = Vec::new();
+        let mut links = HashSet::new();
         while let Ok(page) = rx.recv().await {
             let _page_span = info_span!("process_page", url = %page.get_url());
             debug!("Received page: {}", page.get_url());
 
-            let transform_config = TransformConfig {
-                return_format: ReturnFormat::Markdown,
-                readability: true,
-                main_content: true,
-                ..Default::default()
+            // Documents are downloaded and extracted after the crawl, not converted as HTML
+            if follow_documents
+                && Url::parse(page.get_url())
+                    .ok()
+                    .as_ref()
+                    .and_then(DocumentKind::from_url)
+                    .is_some()
+            {
+                continue;
+            }
+
+            if let Some(reason) = traps.as_mut().and_then(|traps| traps.check(page.get_url())) {
+                debug!("Skipping {}: {}", page.get_url(), reason);
+                report.record(PageReport {
+                    detail: Some(reason),
+                    ..PageReport::new(page.get_url(), PageOutcome::Skipped)
+                });
+                continue;
+            }
+
+            let status = page.status_code.as_u16();
+            let mut page_report = PageReport {
+                status_code: Some(status),
+                validators: page
+                    .headers
+                    .as_ref()
+                    .and_then(HttpValidators::from_spider_headers),
+                ..PageReport::new(page.get_url(), PageOutcome::Fetched)
             };
+            if !page.status_code.is_success() {
+                debug!("Failed page: {} ({})", page.get_url(), status);
+                page_report.outcome = PageOutcome::Failed;
+                report.record(page_report);
+                continue;
+            }
+            if let Some(destination) = page
+                .final_redirect_destination
+                .as_deref()
+                .filter(|destination| *destination != page.get_url())
+            {
+                page_report.outcome = PageOutcome::Redirected;
+                page_report.redirected_to = Some(destination.to_string());
+            }
+
+            let body = page.get_html_bytes_u8();
+            if let Some(reason) =
+                skip_reason(page.get_url(), body, max_page_bytes, skip_binary_content)
+            {
+                debug!("Skipping {}: {}", page.get_url(), reason);
+                page_report.outcome = PageOutcome::Skipped;
+                page_report.detail = Some(reason);
+                report.record(page_report);
+                continue;
+            }
 
-            let markdown = transform_content(&page, &transform_config, &None, &None, &None);
-            if markdown.len() < 100 {
+            let html = page.get_html();
+            links.extend(same_host_links(page.get_url(), &html, &host));
+
+            let markdown = plugins.extract(page.get_url(), &html).unwrap_or_else(|| {
+                transform_content(&page, &transform_config(), &None, &None, &None)
+            });
+            if markdown.len() < MIN_CONTENT_LENGTH {
                 debug!("Skipping page: {}", page.get_url());
+                page_report.outcome = PageOutcome::EmptyContent;
+                page_report.detail = Some(format!("{} characters of content", markdown.len()));
+                report.record(page_report);
                 continue;
             }
-            let metadata_result = extract_metadata(page.get_url(), &page.get_html());
-
-            match metadata_result {
-                Ok(metadata) => {
-                    let crawled_page = CrawledPage {
-                        url: page.get_url().to_string(),
-                        content: markdown,
-                        metadata,
-                    };
-                    pages.push(crawled_page);
-                }
-                Err(e) => {
-                    error!("Error extracting metadata: {:?}", e);
-                    pages.push(CrawledPage {
-                        url: page.get_url().to_string(),
-                        content: markdown,
-                        metadata: PageMetadata {
-                            title: None,
-                            description: None,
-                            author: None,
-                            publication_date: None,
-                            domain: page.get_url().to_string(),
-                        },
-                    });
-                }
+            report.record(page_report);
+
+            let mut crawled_page = build_page(page.get_url(), markdown, &html);
+            if keep_raw_html {
+                crawled_page.raw_html = Some(html);
             }
+            pages.push(crawled_page);
         }
-        pages
+        if let Some(traps) = traps {
+            report.traps = traps.traps();
+        }
+        (pages, report, links)
     });
 
-    website.crawl().await;
-    info!("Crawl finished");
+    // Dropping the crawl stops it; the pages received so far are kept
+    let cancelled = tokio::select! {
+        _ = website.crawl() => false,
+        _ = config.cancel.cancelled() => true,
+    };
+    if cancelled {
+        info!("Crawl cancelled");
+    } else {
+        info!("Crawl finished");
+    }
     website.unsubscribe();
-    let pages = handle
+    let (pages, mut report, links) = handle
         .await
         .map_err(|e| CrawlError::Other(format!("Task join error: {}", e)))?;
-    info!("Processed {} pages", pages.len());
-    Ok(pages)
+    report.cancelled = cancelled;
+    Ok((pages, report, links))
+}
+
+/// Extract a page from stored raw HTML, as the crawler would have
+///
+/// # Arguments
+///
+/// * `url` - The URL the HTML was fetched from
+/// * `html` - The raw HTML
+///
+/// # Returns
+///
+/// The page, or `None` if it has too little content to keep
+pub fn page_from_html(url: &str, html: &str) -> Option<CrawledPage> {
+    let markdown = crate::plugin::registry()
+        .extract(url, html)
+        .unwrap_or_else(|| {
+            // The same transformation as a crawled page, so reprocessing matches crawling
+            let mut page = spider::page::build(
+                url,
+                spider::utils::PageResponse {
+                    content: Some(Box::new(html.as_bytes().to_vec())),
+                    ..Default::default()
+                },
+            );
+            page.set_url_parsed_direct();
+            transform_content(&page, &transform_config(), &None, &None, &None)
+        });
+    if markdown.len() < MIN_CONTENT_LENGTH {
+        debug!("Skipping page: {}", url);
+        return None;
+    }
+    Some(build_page(url, markdown, html))
+}
+
+/// Configuration of the HTML to Markdown transformation
+fn transform_config() -> TransformConfig {
+    TransformConfig {
+        return_format: ReturnFormat::Markdown,
+        readability: true,
+        main_content: true,
+        ..Default::default()
+    }
+}
+
+/// Build a crawled page from its Markdown content and the HTML metadata is read from
+fn build_page(url: &str, markdown: String, html: &str) -> CrawledPage {
+    match extract_metadata(url, html) {
+        Ok(metadata) => CrawledPage {
+            url: url.to_string(),
+            content: markdown,
+            metadata,
+            raw_html: None,
+        },
+        Err(e) => {
+            error!("Error extracting metadata: {:?}", e);
+            CrawledPage {
+                url: url.to_string(),
+                content: markdown,
+                metadata: PageMetadata {
+                    title: None,
+                    description: None,
+                    author: None,
+                    publication_date: None,
+                    domain: url.to_string(),
+                    tags: Vec::new(),
+                    content_type: None,
+                },
+                raw_html: None,
+            }
+        }
+    }
+}
+
+/// Why a response should not be converted to Markdown, or `None` to convert it
+///
+/// Responses over the size limit are always skipped. With `skip_binary_content`, static
+/// assets are skipped by extension, as is content that looks binary or like a minified
+/// script or stylesheet.
+fn skip_reason(
+    url: &str,
+    body: &[u8],
+    max_page_bytes: u64,
+    skip_binary_content: bool,
+) -> Option<String> {
+    if body.len() as u64 > max_page_bytes {
+        return Some(format!(
+            "{} bytes exceeds the {} byte page limit",
+            body.len(),
+            max_page_bytes
+        ));
+    }
+    if !skip_binary_content {
+        return None;
+    }
+
+    if let Some(extension) = Url::parse(url)
+        .ok()
+        .and_then(|url| Some(url.path().rsplit_once('.')?.1.to_lowercase()))
+        .filter(|extension| ASSET_EXTENSIONS.contains(&extension.as_str()))
+    {
+        return Some(format!("{} asset", extension));
+    }
+
+    // Text in any encoding has no NUL bytes and few other control characters
+    let sample = &body[..body.len().min(SNIFF_BYTES)];
+    let control = sample
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    if sample.contains(&0) || control * 10 > sample.len() {
+        return Some("binary content".to_string());
+    }
+
+    let text = String::from_utf8_lossy(sample);
+    if !text.contains('<') && text.lines().any(|line| line.len() >= MINIFIED_LINE_LENGTH) {
+        return Some("minified script or stylesheet".to_string());
+    }
+    None
+}
+
+/// Fetch and parse the robots.txt of a site, allowing everything if it is unavailable
+async fn fetch_robots_rules(
+    base_url: &Url,
+    client: &reqwest::Client,
+    user_agent: &str,
+) -> RobotsRules {
+    let Ok(robots_url) = base_url.join("/robots.txt") else {
+        return RobotsRules::default();
+    };
+
+    let response = match client.get(robots_url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(_) => return RobotsRules::default(),
+        Err(e) => {
+            debug!("Failed to fetch robots.txt: {}", e);
+            return RobotsRules::default();
+        }
+    };
+
+    match response.text().await {
+        Ok(body) => RobotsRules::parse(&body, user_agent),
+        Err(_) => RobotsRules::default(),
+    }
+}
+
+/// Links of a page that point to the given host, without fragments
+fn same_host_links(page_url: &str, html: &str, host: &str) -> Vec<Url> {
+    let Ok(base) = Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let selector = Selector::parse("a[href]").expect("valid selector");
+
+    Html::parse_document(html)
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter_map(|href| base.join(href).ok())
+        .filter(|link| link.host_str() == Some(host))
+        .map(|mut link| {
+            link.set_fragment(None);
+            link
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_reason() {
+        let html = b"<html><body><p>Welcome</p></body></html>";
+        assert_eq!(skip_reason("https://example.com/", html, 1024, true), None);
+        assert!(
+            skip_reason("https://example.com/", html, 10, false)
+                .unwrap()
+                .contains("page limit")
+        );
+        assert_eq!(
+            skip_reason("https://example.com/app.min.js", b"var a=1;", 1024, true).as_deref(),
+            Some("js asset")
+        );
+        assert_eq!(
+            skip_reason(
+                "https://example.com/logo",
+                b"\x89PNG\r\n\x1a\n\0\0",
+                1024,
+                true
+            )
+            .as_deref(),
+            Some("binary content")
+        );
+        let bundle = "!function(e){".repeat(500);
+        assert_eq!(
+            skip_reason(
+                "https://example.com/bundle",
+                bundle.as_bytes(),
+                1 << 20,
+                true
+            )
+            .as_deref(),
+            Some("minified script or stylesheet")
+        );
+    }
+
+    #[test]
+    fn test_same_host_links() {
+        let html = r#"<a href="/docs#intro">Docs</a>
+            <a href="https://other.com/page">Other</a>
+            <a href="guide">Guide</a>"#;
+
+        let links = same_host_links("https://example.com/en/", html, "example.com");
+        let links: Vec<&str> = links.iter().map(|link| link.as_str()).collect();
+        assert_eq!(
+            links,
+            vec!["https://example.com/docs", "https://example.com/en/guide"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_crawl_records_validators() {
+        let mut server = mockito::Server::new_async().await;
+        let text = "Install the command line tool, then write a configuration file. ".repeat(4);
+        let _home = server
+            .mock("GET", "/")
+            .with_header("content-type", "text/html")
+            .with_header("etag", "\"v1\"")
+            .with_body(format!(
+                r#"<html><body><main><p>{}</p><a href="/guide">Guide</a></main></body></html>"#,
+                text
+            ))
+            .create_async()
+            .await;
+        let _guide = server
+            .mock("GET", "/guide")
+            .with_header("content-type", "text/html")
+            .with_header("last-modified", "Wed, 01 Oct 2025 08:00:00 GMT")
+            .with_body(format!(
+                "<html><body><main><p>{}</p></main></body></html>",
+                text
+            ))
+            .create_async()
+            .await;
+
+        // The whitelist of child links has no port, which the mock server needs
+        let mut config = CrawlerConfig::builder()
+            .respect_robots_txt(false)
+            .rate_limit_ms(0)
+            .build();
+        config.child_links_only = false;
+        let (pages, report) = crawl_website_with_report(&format!("{}/", server.url()), config)
+            .await
+            .unwrap();
+
+        assert_eq!(pages.len(), 2);
+        let home = report.find(&server.url()).unwrap();
+        assert_eq!(
+            home.validators.as_ref().and_then(|v| v.etag.as_deref()),
+            Some("\"v1\"")
+        );
+        let guide = report.find(&format!("{}/guide", server.url())).unwrap();
+        assert_eq!(
+            guide
+                .validators
+                .as_ref()
+                .and_then(|v| v.last_modified.as_deref()),
+            Some("Wed, 01 Oct 2025 08:00:00 GMT")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recrawl_without_traps() {
+        let mut server = mockito::Server::new_async().await;
+        let traps: String = (1..=10)
+            .map(|n| format!(r#"<a href="/a/a/a/{}">Archive {}</a>"#, n, n))
+            .collect();
+        let _home = server
+            .mock("GET", "/")
+            .with_header("content-type", "text/html")
+            .with_body(format!(
+                r#"<html><body>{}<a href="/guide">Guide</a></body></html>"#,
+                traps
+            ))
+            .create_async()
+            .await;
+        let _trap = server
+            .mock("GET", mockito::Matcher::Regex("^/a/".to_string()))
+            .with_header("content-type", "text/html")
+            .with_body("<html><body><p>Archive</p></body></html>")
+            .create_async()
+            .await;
+        let _guide = server
+            .mock("GET", "/guide")
+            .with_header("content-type", "text/html")
+            .with_body(format!(
+                "<html><body><main><p>{}</p></main></body></html>",
+                "Install the command line tool, then write a configuration file. ".repeat(4)
+            ))
+            .create_async()
+            .await;
+
+        let mut config = CrawlerConfig::builder()
+            .respect_robots_txt(false)
+            .rate_limit_ms(0)
+            .max_depth(5)
+            .max_pages(5)
+            .build();
+        config.child_links_only = false;
+        let (pages, report) = crawl_website_with_report(&format!("{}/", server.url()), config)
+            .await
+            .unwrap();
+
+        // The trap is remembered from the first crawl, whose skipped pages were discarded
+        assert_eq!(report.traps.len(), 1);
+        assert_eq!(report.summary.skipped, 0);
+        assert!(
+            pages
+                .iter()
+                .any(|page| page.url == format!("{}/guide", server.url()))
+        );
+    }
+
+    #[test]
+    fn test_page_from_html() {
+        let html = r#"<html><head><title>Setup guide</title></head><body>
+            <nav><a href="/">Home</a></nav>
+            <main><h1>Setup</h1>
+            <p>Install the command line tool with cargo, then run the init command to write
+            a configuration file with your provider, API keys and default database.</p>
+            <p>See the <a href="/docs/config">configuration reference</a> for every option.</p>
+            </main></body></html>"#;
+
+        let page = page_from_html("https://example.com/docs/setup", html).unwrap();
+        assert_eq!(page.url, "https://example.com/docs/setup");
+        assert!(page.content.contains("Install the command line tool"));
+        assert!(!page.content.contains("<p>"));
+
+        assert!(
+            page_from_html("https://example.com/empty", "<html><body></body></html>").is_none()
+        );
+    }
 }
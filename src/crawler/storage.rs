@@ -60,6 +60,7 @@ impl From<PageEntry> for CrawledPage {
             url: entry.url,
             content: entry.content,
             metadata: entry.metadata,
+            raw_html: None,
         }
     }
 }
@@ -392,7 +393,10 @@ mod tests {
                 domain: "https://example.com".to_string(),
                 publication_date: None,
                 author: None,
+                tags: Vec::new(),
+                content_type: None,
             },
+            raw_html: None,
         };
 
         let entry: PageEntry = crawled.clone().into();
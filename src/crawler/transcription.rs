@@ -0,0 +1,497 @@
+//! # Audio Transcription Module
+//!
+//! This module converts audio files and podcast feeds into `CrawledPage`s by
+//! transcribing them through an OpenAI-compatible transcription API, so spoken
+//! content flows through the normal chunking and indexing pipeline.
+//!
+//! ## Key Components
+//!
+//! - `TranscriptionClient`: Client for a `/audio/transcriptions` endpoint
+//! - `Transcript`: Timestamped segments, optionally attributed to speakers
+//! - `transcript_to_markdown`: Renders a transcript with `[hh:mm:ss]` markers
+//! - `parse_podcast_feed`: Reads episode titles, dates and audio URLs from an RSS feed
+//!
+//! ## Features
+//!
+//! - Works with OpenAI Whisper or any server exposing the same API, including
+//!   self-hosted Whisper servers for local transcription
+//! - Segment timestamps and speaker labels kept in the page text, so every chunk
+//!   can be traced back to a position in the recording
+//! - Episode publication dates carried into `PageMetadata`
+//!
+//! Enabled with the `transcription` feature.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{debug, info, instrument};
+use url::Url;
+
+use crate::crawler::error::CrawlError;
+use crate::crawler::{CrawledPage, PageMetadata};
+
+/// Default transcription API
+const DEFAULT_API_URL: &str = "https://api.openai.com/v1";
+
+/// Default transcription model
+const DEFAULT_MODEL: &str = "whisper-1";
+
+/// Segments are merged into a paragraph until it spans this many seconds
+const PARAGRAPH_SECONDS: f64 = 60.0;
+
+/// A timestamped piece of a transcript
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TranscriptSegment {
+    /// Start of the segment in seconds
+    pub start: f64,
+
+    /// End of the segment in seconds
+    pub end: f64,
+
+    /// Speaker label, when the API performs diarization
+    #[serde(default)]
+    pub speaker: Option<String>,
+
+    /// Transcribed text
+    pub text: String,
+}
+
+/// A transcript of a recording
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Transcript {
+    /// Detected language
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Full transcript text
+    #[serde(default)]
+    pub text: String,
+
+    /// Timestamped segments
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// An episode listed in a podcast feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEpisode {
+    /// Title of the episode
+    pub title: Option<String>,
+
+    /// URL of the audio enclosure
+    pub audio_url: String,
+
+    /// Publication date of the episode
+    pub published: Option<DateTime<Utc>>,
+}
+
+/// Client for an OpenAI-compatible transcription API
+#[derive(Debug, Clone)]
+pub struct TranscriptionClient {
+    /// Base URL of the API
+    base_url: String,
+
+    /// API key
+    api_key: String,
+
+    /// Transcription model
+    model: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl TranscriptionClient {
+    /// Create a new client for the OpenAI API
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - API key
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            base_url: DEFAULT_API_URL.to_string(),
+            api_key: api_key.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a client from `TRANSCRIPTION_API_KEY` (or `OPENAI_API_KEY`), with optional
+    /// `TRANSCRIPTION_API_URL` and `TRANSCRIPTION_MODEL` overrides
+    pub fn from_env() -> Result<Self, CrawlError> {
+        let api_key = std::env::var("TRANSCRIPTION_API_KEY")
+            .or_else(|_| std::env::var("OPENAI_API_KEY"))
+            .map_err(|_| {
+                CrawlError::Other(
+                    "TRANSCRIPTION_API_KEY or OPENAI_API_KEY environment variable must be set"
+                        .to_string(),
+                )
+            })?;
+
+        let mut client = Self::new(&api_key);
+        if let Ok(base_url) = std::env::var("TRANSCRIPTION_API_URL") {
+            client = client.with_base_url(&base_url);
+        }
+        if let Ok(model) = std::env::var("TRANSCRIPTION_MODEL") {
+            client = client.with_model(&model);
+        }
+        Ok(client)
+    }
+
+    /// Use a different API base URL, such as a self-hosted Whisper server
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Use a different transcription model
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Transcribe audio bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - File name sent with the upload, used by the API to detect the format
+    /// * `audio` - The audio data
+    #[instrument(skip(self, audio), fields(bytes = audio.len()))]
+    pub async fn transcribe(
+        &self,
+        file_name: &str,
+        audio: Vec<u8>,
+    ) -> Result<Transcript, CrawlError> {
+        let form = reqwest::multipart::Form::new()
+            .text("model", self.model.clone())
+            .text("response_format", "verbose_json")
+            .part(
+                "file",
+                reqwest::multipart::Part::bytes(audio).file_name(file_name.to_string()),
+            );
+
+        let transcript: Transcript = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        debug!(
+            "Transcribed {} into {} segments",
+            file_name,
+            transcript.segments.len()
+        );
+        Ok(transcript)
+    }
+
+    /// Transcribe a local audio file into a page
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the audio file
+    #[instrument(skip(self))]
+    pub async fn transcribe_file(&self, path: &Path) -> Result<CrawledPage, CrawlError> {
+        let audio = tokio::fs::read(path)
+            .await
+            .map_err(|e| CrawlError::Other(format!("Failed to read {}: {}", path.display(), e)))?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "audio".to_string());
+
+        let transcript = self.transcribe(&file_name, audio).await?;
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.clone());
+
+        Ok(CrawledPage {
+            url: format!("audio://local/{}", file_name),
+            content: transcript_to_markdown(&title, &transcript),
+            metadata: PageMetadata {
+                title: Some(title),
+                description: None,
+                publication_date: None,
+                author: None,
+                domain: "local".to_string(),
+                tags: transcript.language.into_iter().collect(),
+                content_type: None,
+            },
+            raw_html: None,
+        })
+    }
+
+    /// Download and transcribe the latest episodes of a podcast feed
+    ///
+    /// # Arguments
+    ///
+    /// * `feed_url` - URL of the RSS feed
+    /// * `max_episodes` - Maximum number of episodes to transcribe
+    #[instrument(skip(self))]
+    pub async fn transcribe_feed(
+        &self,
+        feed_url: &str,
+        max_episodes: usize,
+    ) -> Result<Vec<CrawledPage>, CrawlError> {
+        let feed = self
+            .client
+            .get(feed_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let (podcast, episodes) = parse_podcast_feed(&feed)?;
+
+        let mut pages = Vec::new();
+        for episode in episodes.into_iter().take(max_episodes) {
+            info!("Transcribing {}", episode.audio_url);
+            let audio_url = Url::parse(&episode.audio_url)?;
+            let file_name = audio_url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("episode.mp3")
+                .to_string();
+
+            let audio = self
+                .client
+                .get(audio_url.clone())
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            let transcript = self.transcribe(&file_name, audio.to_vec()).await?;
+
+            let title = episode.title.clone().unwrap_or_else(|| file_name.clone());
+            pages.push(CrawledPage {
+                url: episode.audio_url,
+                content: transcript_to_markdown(&title, &transcript),
+                metadata: PageMetadata {
+                    title: Some(title),
+                    description: None,
+                    publication_date: episode.published,
+                    author: podcast.clone(),
+                    domain: audio_url.host_str().unwrap_or_default().to_string(),
+                    tags: transcript.language.into_iter().collect(),
+                    content_type: None,
+                },
+                raw_html: None,
+            });
+        }
+
+        Ok(pages)
+    }
+}
+
+/// Render a transcript as Markdown with timestamp and speaker markers
+///
+/// Consecutive segments from the same speaker are merged into paragraphs of about
+/// a minute, each prefixed with the time it starts at.
+///
+/// # Arguments
+///
+/// * `title` - Title used as the top-level heading
+/// * `transcript` - The transcript to render
+pub fn transcript_to_markdown(title: &str, transcript: &Transcript) -> String {
+    let mut markdown = format!("# {}\n\n", title);
+
+    if transcript.segments.is_empty() {
+        markdown.push_str(transcript.text.trim());
+        return markdown.trim_end().to_string();
+    }
+
+    let mut paragraph: Option<(f64, Option<&str>, String)> = None;
+    for segment in &transcript.segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some((start, speaker, body)) = &mut paragraph {
+            if *speaker == segment.speaker.as_deref() && segment.start - *start < PARAGRAPH_SECONDS
+            {
+                body.push(' ');
+                body.push_str(text);
+                continue;
+            }
+            push_paragraph(&mut markdown, *start, *speaker, body);
+        }
+        paragraph = Some((segment.start, segment.speaker.as_deref(), text.to_string()));
+    }
+    if let Some((start, speaker, body)) = &paragraph {
+        push_paragraph(&mut markdown, *start, *speaker, body);
+    }
+
+    markdown.trim_end().to_string()
+}
+
+/// Append a timestamped paragraph
+fn push_paragraph(markdown: &mut String, start: f64, speaker: Option<&str>, body: &str) {
+    markdown.push_str(&format!("[{}] ", format_timestamp(start)));
+    if let Some(speaker) = speaker {
+        markdown.push_str(&format!("**{}:** ", speaker));
+    }
+    markdown.push_str(body);
+    markdown.push_str("\n\n");
+}
+
+/// Format seconds as `hh:mm:ss`
+fn format_timestamp(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct Rss {
+    channel: Channel,
+}
+
+#[derive(Debug, Deserialize)]
+struct Channel {
+    title: Option<String>,
+    #[serde(rename = "item", default)]
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    title: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub_date: Option<String>,
+    enclosure: Option<Enclosure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Enclosure {
+    #[serde(rename = "@url")]
+    url: String,
+}
+
+/// Parse a podcast RSS feed
+///
+/// # Arguments
+///
+/// * `xml` - The feed document
+///
+/// # Returns
+///
+/// The podcast title and the episodes that have an audio enclosure, in feed order
+pub fn parse_podcast_feed(xml: &str) -> Result<(Option<String>, Vec<FeedEpisode>), CrawlError> {
+    let rss: Rss = quick_xml::de::from_str(xml)
+        .map_err(|e| CrawlError::ContentExtraction(format!("Failed to parse feed: {}", e)))?;
+
+    let episodes = rss
+        .channel
+        .items
+        .into_iter()
+        .filter_map(|item| {
+            Some(FeedEpisode {
+                title: item.title,
+                audio_url: item.enclosure?.url,
+                published: item.pub_date.and_then(|date| {
+                    DateTime::parse_from_rfc2822(&date)
+                        .ok()
+                        .map(|date| date.with_timezone(&Utc))
+                }),
+            })
+        })
+        .collect();
+
+    Ok((rss.channel.title, episodes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, speaker: Option<&str>, text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            start,
+            end: start + 5.0,
+            speaker: speaker.map(String::from),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_transcript_to_markdown() {
+        let transcript = Transcript {
+            language: Some("en".to_string()),
+            text: String::new(),
+            segments: vec![
+                segment(0.0, Some("Host"), "Welcome to the show."),
+                segment(5.0, Some("Host"), "Today we talk about search."),
+                segment(12.0, Some("Guest"), "Thanks for having me."),
+                segment(3725.0, Some("Guest"), "That wraps it up."),
+            ],
+        };
+
+        assert_eq!(
+            transcript_to_markdown("Episode 1", &transcript),
+            "# Episode 1\n\n\
+             [00:00:00] **Host:** Welcome to the show. Today we talk about search.\n\n\
+             [00:00:12] **Guest:** Thanks for having me.\n\n\
+             [01:02:05] **Guest:** That wraps it up."
+        );
+    }
+
+    #[test]
+    fn test_parse_podcast_feed() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Search Talk</title>
+    <itunes:author>Someone</itunes:author>
+    <item>
+      <title>Vectors</title>
+      <pubDate>Mon, 01 Jan 2024 10:00:00 +0000</pubDate>
+      <enclosure url="https://cdn.example.com/ep2.mp3" type="audio/mpeg" length="1"/>
+    </item>
+    <item>
+      <title>Announcement without audio</title>
+    </item>
+  </channel>
+</rss>"#;
+
+        let (title, episodes) = parse_podcast_feed(xml).unwrap();
+
+        assert_eq!(title.as_deref(), Some("Search Talk"));
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].title.as_deref(), Some("Vectors"));
+        assert_eq!(episodes[0].audio_url, "https://cdn.example.com/ep2.mp3");
+        assert!(episodes[0].published.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_transcribe() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/audio/transcriptions")
+            .match_header("authorization", "Bearer key")
+            .with_body(
+                r#"{"language":"en","text":"Hello there.","segments":[{"start":0.0,"end":1.5,"text":" Hello there."}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let client = TranscriptionClient::new("key").with_base_url(&server.url());
+        let transcript = client.transcribe("clip.mp3", vec![0, 1, 2]).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(transcript.language.as_deref(), Some("en"));
+        assert_eq!(transcript.segments[0].text, " Hello there.");
+    }
+}
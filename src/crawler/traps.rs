@@ -0,0 +1,244 @@
+//! # Crawl Trap Module
+//!
+//! This module recognizes URL spaces that grow without bound, such as calendars that
+//! link to the next month forever, faceted filters whose query parameters combine
+//! endlessly, and relative links that nest the same path segments again and again, so
+//! the crawler can stop following them instead of spending its page budget there.
+//!
+//! ## Key Components
+//!
+//! - `TrapDetector`: Tracks the URLs of a crawl and flags those inside a trap
+//! - `CrawlTrap`: A detected trap with the pattern it matches and its URL regex
+//!
+//! ## Heuristics
+//!
+//! - A path segment occurring more than `max_segment_repeats` times, e.g. `/a/b/a/b/a`
+//! - More than `max_urls_per_pattern` URLs differing only in their numbers, where the path
+//!   has at least two numbers, e.g. `/calendar/2031/07/14`
+//! - More than `max_urls_per_pattern` query strings on the same path, e.g. `?color=red&size=m`
+//!
+//! Paths with a single number, such as `/posts/123` or `/page/4`, are usually legitimate
+//! and are not limited.
+
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// A URL space the crawler stopped following
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlTrap {
+    /// The URL pattern, with `{n}` for numbers
+    pub pattern: String,
+
+    /// Why the pattern was considered a trap
+    pub reason: String,
+
+    /// Regex matching the URLs of the trap, for excluding them from a crawl
+    pub regex: String,
+
+    /// Number of URLs skipped because they matched
+    pub skipped: usize,
+}
+
+/// Flags URLs inside crawl traps as a crawl encounters them
+#[derive(Debug)]
+pub struct TrapDetector {
+    max_urls_per_pattern: usize,
+    max_segment_repeats: usize,
+    patterns: HashMap<String, HashSet<String>>,
+    queries: HashMap<String, HashSet<String>>,
+    traps: Vec<(CrawlTrap, Regex)>,
+    numbers: Regex,
+}
+
+impl TrapDetector {
+    /// Create a detector
+    ///
+    /// # Arguments
+    ///
+    /// * `max_urls_per_pattern` - URLs allowed per numeric pattern or per path with queries
+    /// * `max_segment_repeats` - Times a path segment may occur in one path
+    pub fn new(max_urls_per_pattern: usize, max_segment_repeats: usize) -> Self {
+        Self {
+            max_urls_per_pattern,
+            max_segment_repeats,
+            patterns: HashMap::new(),
+            queries: HashMap::new(),
+            traps: Vec::new(),
+            numbers: Regex::new(r"\d+").expect("valid regex"),
+        }
+    }
+
+    /// Record a URL and check whether it is inside a trap
+    ///
+    /// # Returns
+    ///
+    /// The reason the URL is a trap, or `None` if it may be crawled
+    pub fn check(&mut self, url: &str) -> Option<String> {
+        if let Some((trap, _)) = self.traps.iter_mut().find(|(_, regex)| regex.is_match(url)) {
+            trap.skipped += 1;
+            return Some(format!("crawl trap: {}", trap.reason));
+        }
+
+        let parsed = Url::parse(url).ok()?;
+        let host = parsed.host_str()?;
+        let origin = match parsed.port() {
+            Some(port) => format!("{}://{}:{}", parsed.scheme(), host, port),
+            None => format!("{}://{}", parsed.scheme(), host),
+        };
+        let escaped_origin = regex::escape(&origin);
+        let path = parsed.path();
+
+        // The same segment nested again and again
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for segment in &segments {
+            *counts.entry(segment).or_default() += 1;
+        }
+        if let Some(segment) = segments
+            .iter()
+            .find(|segment| counts[*segment] > self.max_segment_repeats)
+        {
+            let nested = vec![format!("/{}", regex::escape(segment)); self.max_segment_repeats + 1]
+                .join("(/[^?#]*)?");
+            return self.add_trap(
+                format!("{}/**/{}", origin, segment),
+                format!("path segment '{}' repeats", segment),
+                format!("^{}(/[^?#]*)?{}([/?#]|$)", escaped_origin, nested),
+            );
+        }
+
+        // Calendars and other paths generated from several numbers
+        let numbers = &self.numbers;
+        if numbers.find_iter(path).count() >= 2 {
+            let pattern = format!("{}{}", origin, numbers.replace_all(path, "{n}"));
+            let urls = self.patterns.entry(pattern.clone()).or_default();
+            urls.insert(format!("{}{}", origin, path));
+            if urls.len() > self.max_urls_per_pattern {
+                let path_regex = regex::escape(path);
+                let path_regex = numbers.replace_all(&path_regex, r"\d+");
+                return self.add_trap(
+                    pattern,
+                    format!("more than {} numbered pages", self.max_urls_per_pattern),
+                    format!("^{}{}/?([?#]|$)", escaped_origin, path_regex),
+                );
+            }
+        }
+
+        // Faceted filters combining query parameters
+        if let Some(query) = parsed.query().filter(|query| !query.is_empty()) {
+            let page = format!("{}{}", origin, path);
+            let queries = self.queries.entry(page.clone()).or_default();
+            queries.insert(query.to_string());
+            if queries.len() > self.max_urls_per_pattern {
+                return self.add_trap(
+                    format!("{}?*", page),
+                    format!("more than {} query variants", self.max_urls_per_pattern),
+                    format!("^{}\\?", regex::escape(&page)),
+                );
+            }
+        }
+
+        None
+    }
+
+    /// Record a new trap containing the URL just checked
+    fn add_trap(&mut self, pattern: String, reason: String, regex: String) -> Option<String> {
+        let compiled = Regex::new(&regex).ok()?;
+        let trap = CrawlTrap {
+            pattern,
+            reason: reason.clone(),
+            regex,
+            skipped: 1,
+        };
+        self.traps.push((trap, compiled));
+        Some(format!("crawl trap: {}", reason))
+    }
+
+    /// The traps detected so far
+    pub fn traps(&self) -> Vec<CrawlTrap> {
+        self.traps.iter().map(|(trap, _)| trap.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_segments() {
+        let mut detector = TrapDetector::new(50, 2);
+        assert_eq!(
+            detector.check("https://example.com/docs/api/docs/api"),
+            None
+        );
+
+        let reason = detector.check("https://example.com/docs/api/docs/api/docs/api");
+        assert_eq!(
+            reason.as_deref(),
+            Some("crawl trap: path segment 'docs' repeats")
+        );
+        assert!(
+            detector
+                .check("https://example.com/x/docs/y/docs/z/docs/guide")
+                .is_some()
+        );
+        assert_eq!(detector.check("https://example.com/docs/api/guide"), None);
+        assert_eq!(detector.traps()[0].skipped, 2);
+    }
+
+    #[test]
+    fn test_numbered_pages() {
+        let mut detector = TrapDetector::new(3, 2);
+        for day in 1..=3 {
+            let url = format!("https://example.com/calendar/2031/07/{}", day);
+            assert_eq!(detector.check(&url), None);
+        }
+        assert!(
+            detector
+                .check("https://example.com/calendar/2031/07/4")
+                .is_some()
+        );
+        assert!(
+            detector
+                .check("https://example.com/calendar/2040/12/31")
+                .is_some()
+        );
+
+        // A single number is not limited
+        for post in 1..=10 {
+            let url = format!("https://example.com/posts/{}", post);
+            assert_eq!(detector.check(&url), None);
+        }
+
+        let traps = detector.traps();
+        assert_eq!(traps.len(), 1);
+        assert_eq!(traps[0].pattern, "https://example.com/calendar/{n}/{n}/{n}");
+    }
+
+    #[test]
+    fn test_query_variants() {
+        let mut detector = TrapDetector::new(2, 2);
+        assert_eq!(detector.check("https://example.com/shop?color=red"), None);
+        assert_eq!(detector.check("https://example.com/shop?color=blue"), None);
+        assert!(
+            detector
+                .check("https://example.com/shop?color=red&size=m")
+                .is_some()
+        );
+        assert!(detector.check("https://example.com/shop?size=l").is_some());
+        assert_eq!(detector.check("https://example.com/shop"), None);
+    }
+
+    #[test]
+    fn test_port() {
+        let mut detector = TrapDetector::new(50, 2);
+        assert!(detector.check("http://localhost:8080/a/a/a/1").is_some());
+        assert!(detector.check("http://localhost:8080/a/a/a/2").is_some());
+        assert_eq!(detector.traps().len(), 1);
+        assert_eq!(detector.traps()[0].skipped, 2);
+        assert_eq!(detector.check("http://localhost:8080/guide"), None);
+    }
+}
@@ -0,0 +1,201 @@
+//! # Exit Code Module
+//!
+//! This module maps the errors a command fails with to distinct process exit codes, so
+//! scripts wrapping `hal` can branch on the kind of failure instead of parsing messages.
+//!
+//! ## Key Components
+//!
+//! - Exit code constants: The codes the CLI exits with
+//! - `ConfigError`: Missing or invalid configuration, such as an unset API key
+//! - `PartialFailure`: A command that finished but failed for some of its inputs
+//! - `Interrupted`: A command stopped by Ctrl+C after keeping the work it completed
+//! - `exit_code`: The exit code for an error
+//!
+//! ## Exit Codes
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | Success |
+//! | 1 | Any other error |
+//! | 2 | Invalid command line arguments |
+//! | 3 | Configuration error |
+//! | 4 | Network error |
+//! | 5 | Partial failure |
+//! | 6 | Rate limited |
+//! | 130 | Interrupted by Ctrl+C |
+
+use std::process::ExitCode;
+
+/// Any error without a more specific code
+pub const GENERAL_ERROR: u8 = 1;
+
+/// Missing or invalid configuration, such as an unset API key
+pub const CONFIG_ERROR: u8 = 3;
+
+/// A request failed to reach a website or API
+pub const NETWORK_ERROR: u8 = 4;
+
+/// The command finished, but some of its inputs failed
+pub const PARTIAL_FAILURE: u8 = 5;
+
+/// A website or API rejected requests for exceeding its rate limit
+pub const RATE_LIMITED: u8 = 6;
+
+/// The command was stopped by Ctrl+C, as shells report for SIGINT
+pub const INTERRUPTED: u8 = 130;
+
+/// Missing or invalid configuration
+#[derive(Debug, thiserror::Error)]
+#[error("Configuration error: {0}")]
+pub struct ConfigError(pub String);
+
+/// A command that finished but failed for some of its inputs
+#[derive(Debug, thiserror::Error)]
+#[error("Partial failure: {0}")]
+pub struct PartialFailure(pub String);
+
+/// A command stopped by Ctrl+C after keeping the work it completed
+#[derive(Debug, thiserror::Error)]
+#[error("Interrupted: {0}")]
+pub struct Interrupted(pub String);
+
+/// Fail with a `ConfigError` unless an API key is set in the environment or `hal.toml`
+///
+/// Checked before creating clients that would otherwise panic on a missing key. Gemini
+/// keys are optional with the `vertex` provider, which uses Application Default
+/// Credentials, and the `azure` provider, which needs its own key instead.
+pub fn require_api_key(name: &str) -> Result<String, ConfigError> {
+    let provider = hal::config::current().provider;
+    if provider != hal::config::Provider::Gemini && name.starts_with("GEMINI_") {
+        for (var, _) in provider.api_key_vars() {
+            require_api_key(var)?;
+        }
+        return Ok(hal::config::api_key(name).unwrap_or_default());
+    }
+    hal::config::api_key(name).ok_or_else(|| {
+        ConfigError(format!(
+            "{} environment variable must be set, or run `hal init`",
+            name
+        ))
+    })
+}
+
+/// The exit code for an error that ended a command
+///
+/// # Arguments
+///
+/// * `err` - The error; every error in its chain is considered
+///
+/// # Returns
+///
+/// The most specific code found, rate limiting taking precedence over network errors
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    let mut code = GENERAL_ERROR;
+    for cause in err.chain() {
+        let cause_code = cause_code(cause);
+        if cause_code == RATE_LIMITED {
+            return RATE_LIMITED;
+        }
+        if code == GENERAL_ERROR {
+            code = cause_code;
+        }
+    }
+    code
+}
+
+/// The exit code for a single error of a chain
+fn cause_code(cause: &(dyn std::error::Error + 'static)) -> u8 {
+    if cause.is::<ConfigError>()
+        || cause.is::<hal::config::ConfigError>()
+        || cause.is::<std::env::VarError>()
+    {
+        return CONFIG_ERROR;
+    }
+    if cause.is::<PartialFailure>() {
+        return PARTIAL_FAILURE;
+    }
+    if cause.is::<Interrupted>() {
+        return INTERRUPTED;
+    }
+    if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+        return if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+            RATE_LIMITED
+        } else {
+            NETWORK_ERROR
+        };
+    }
+    if let Some(e) = cause.downcast_ref::<hal::Error>() {
+        match e {
+            hal::Error::RateLimit { .. } => return RATE_LIMITED,
+            hal::Error::Http(_) => return NETWORK_ERROR,
+            _ => {}
+        }
+    }
+    if matches!(
+        cause.downcast_ref::<hal::search::SearchError>(),
+        Some(hal::search::SearchError::RateLimited(_))
+    ) || matches!(
+        cause.downcast_ref::<hal::crawler::CrawlError>(),
+        Some(hal::crawler::CrawlError::RateLimit(_))
+    ) {
+        return RATE_LIMITED;
+    }
+
+    // Provider and connector errors only carry messages
+    let message = cause.to_string();
+    if message.contains("environment variable must be set") {
+        CONFIG_ERROR
+    } else if message.contains("RESOURCE_EXHAUSTED") || message.contains("429 Too Many Requests") {
+        RATE_LIMITED
+    } else {
+        GENERAL_ERROR
+    }
+}
+
+/// Print an error and turn it into the process exit code
+pub fn report(err: anyhow::Error) -> ExitCode {
+    eprintln!("Error: {:?}", err);
+    ExitCode::from(exit_code(&err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code() {
+        let err = anyhow::Error::new(ConfigError("no model".to_string()));
+        assert_eq!(exit_code(&err), CONFIG_ERROR);
+
+        let err = Err::<(), _>(std::env::VarError::NotPresent)
+            .context("GEMINI_API_KEY is required")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), CONFIG_ERROR);
+
+        let err = anyhow::Error::new(PartialFailure("2 of 10 pages failed".to_string()));
+        assert_eq!(exit_code(&err), PARTIAL_FAILURE);
+
+        let err = anyhow::Error::new(Interrupted("indexed 3 of 10 pages".to_string()));
+        assert_eq!(exit_code(&err), INTERRUPTED);
+
+        let err = anyhow::Error::new(hal::search::SearchError::RateLimited(
+            "30 searches per minute".to_string(),
+        ))
+        .context("Search failed");
+        assert_eq!(exit_code(&err), RATE_LIMITED);
+
+        let err = anyhow::Error::new(hal::crawler::CrawlError::Other(
+            "NOTION_API_TOKEN environment variable must be set".to_string(),
+        ));
+        assert_eq!(exit_code(&err), CONFIG_ERROR);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("index is empty")), GENERAL_ERROR);
+    }
+
+    #[test]
+    fn test_require_api_key() {
+        assert!(require_api_key("PATH").is_ok());
+        assert!(require_api_key("HAL_TEST_UNSET_VARIABLE").is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! # C API Module
+//!
+//! This module exposes a small `extern "C"` API for querying an index from other
+//! languages and services without running an HTTP server. It is compiled with the `ffi`
+//! feature; `include/hal.h` declares the functions for C callers.
+//!
+//! ## Key Components
+//!
+//! - `hal_open` / `hal_close`: Open and close an index database
+//! - `hal_search`: Search an index, returning the results as a JSON string
+//! - `hal_string_free`: Free a string returned by `hal_search`
+//! - `hal_last_error`: The message of the last error on the calling thread
+//!
+//! ## Conventions
+//!
+//! Functions return null on failure and record a message retrievable with
+//! `hal_last_error`. Strings are UTF-8 and NUL-terminated. Each handle runs its own
+//! tokio runtime, so calls block the calling thread until they finish; a handle may be
+//! shared between threads. Queries are embedded with the embedding backend of `hal.toml`
+//! like the CLI; `GEMINI_FREE_API_KEY` or the key stored in `hal.toml` must be set.
+//!
+//! ## Building
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+
+use crate::index::Database;
+use crate::search::{SearchOptions, search_index};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An open index, returned by `hal_open` and released by `hal_close`
+pub struct HalIndex {
+    db: Database,
+    runtime: tokio::runtime::Runtime,
+}
+
+/// Record the error message returned by `hal_last_error`
+fn set_last_error(message: impl std::fmt::Display) {
+    let message =
+        CString::new(message.to_string().replace('\0', " ")).expect("NUL bytes were replaced");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `f`, turning errors and panics into a null pointer and a recorded message
+fn guard<T>(f: impl FnOnce() -> Result<*mut T, String>) -> *mut T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(pointer)) => pointer,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("HAL panicked");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Borrow a C string argument as UTF-8
+///
+/// # Safety
+///
+/// `value` must be null or point to a NUL-terminated string valid for `'a`.
+unsafe fn str_arg<'a>(value: *const c_char, name: &str) -> Result<&'a str, String> {
+    if value.is_null() {
+        return Err(format!("{} must not be null", name));
+    }
+    // SAFETY: the caller guarantees a valid NUL-terminated string
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+/// Open the index database at a path, creating it if needed
+///
+/// Returns null on failure. The handle must be released with `hal_close`.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hal_open(path: *const c_char) -> *mut HalIndex {
+    guard(|| {
+        // SAFETY: forwarded from the caller
+        let path = unsafe { str_arg(path, "path") }?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start runtime: {}", e))?;
+        let db = runtime
+            .block_on(Database::new_from_path(path))
+            .map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(HalIndex { db, runtime })))
+    })
+}
+
+/// Search an index
+///
+/// Returns a JSON array of results, best first, with the fields of the CLI's
+/// `search --vector-search-only --format json` output, or null on failure. The string
+/// must be released with `hal_string_free`.
+///
+/// # Safety
+///
+/// `index` must be a handle returned by `hal_open` and not yet closed. `query` and
+/// `source` must be null or NUL-terminated strings; a null `source` searches every
+/// website.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hal_search(
+    index: *const HalIndex,
+    query: *const c_char,
+    source: *const c_char,
+    limit: usize,
+) -> *mut c_char {
+    guard(|| {
+        // SAFETY: the caller passes a live handle from `hal_open` or null
+        let index = unsafe { index.as_ref() }.ok_or("index must not be null")?;
+        // SAFETY: forwarded from the caller
+        let query = unsafe { str_arg(query, "query") }?;
+        let source = if source.is_null() {
+            None
+        } else {
+            // SAFETY: forwarded from the caller
+            Some(unsafe { str_arg(source, "source") }?.to_string())
+        };
+        if crate::config::api_key("GEMINI_FREE_API_KEY").is_none() {
+            return Err("GEMINI_FREE_API_KEY environment variable must be set".to_string());
+        }
+
+        let options = SearchOptions {
+            limit: if limit == 0 { 10 } else { limit },
+            source_filter: source,
+            ..SearchOptions::default()
+        };
+        let client = crate::model::Client::new_gemini_free_from_env()
+            .with_embedding_config(&crate::config::current().embedding)
+            .map_err(|e| e.to_string())?;
+        let results = index
+            .runtime
+            .block_on(search_index(&index.db, &client, query, options))
+            .map_err(|e| e.to_string())?;
+
+        let json = serde_json::to_string(&results).map_err(|e| e.to_string())?;
+        Ok(CString::new(json).map_err(|e| e.to_string())?.into_raw())
+    })
+}
+
+/// Free a string returned by `hal_search`; null is ignored
+///
+/// # Safety
+///
+/// `value` must be null or a string returned by `hal_search` that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hal_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        // SAFETY: the string was created by `CString::into_raw`
+        drop(unsafe { CString::from_raw(value) });
+    }
+}
+
+/// Close an index handle; null is ignored
+///
+/// # Safety
+///
+/// `index` must be null or a handle returned by `hal_open` that was not closed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hal_close(index: *mut HalIndex) {
+    if !index.is_null() {
+        // SAFETY: the handle was created by `Box::into_raw`
+        drop(unsafe { Box::from_raw(index) });
+    }
+}
+
+/// The message of the last error on the calling thread, or null if there was none
+///
+/// The pointer stays valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn hal_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        let error = hal_last_error();
+        assert!(!error.is_null());
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_open_and_close() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().join("index.db").to_string_lossy().as_ref()).unwrap();
+
+        unsafe {
+            let index = hal_open(path.as_ptr());
+            assert!(!index.is_null());
+
+            let results = hal_search(index, ptr::null(), ptr::null(), 5);
+            assert!(results.is_null());
+            assert_eq!(last_error(), "query must not be null");
+
+            hal_string_free(ptr::null_mut());
+            hal_close(index);
+            hal_close(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_open_rejects_null_path() {
+        let index = unsafe { hal_open(ptr::null()) };
+        assert!(index.is_null());
+        assert_eq!(last_error(), "path must not be null");
+    }
+}
@@ -0,0 +1,534 @@
+//! # Health Module
+//!
+//! Checks that HAL is set up to work: API keys and credentials of the configured
+//! providers, the database, the dimensions of the stored embeddings and the reachability
+//! of the provider endpoints. Each failed check carries the fix to apply.
+//!
+//! `hal doctor` prints every check. Servers started with `--health-addr` answer over HTTP
+//! from the same checks:
+//!
+//! - `GET /healthz`: Liveness, checking API keys and the database
+//! - `GET /readyz`: Readiness, running every check
+//!
+//! Both respond `200 OK` when no check fails and `503 Service Unavailable` otherwise, with
+//! the [`HealthReport`] as JSON.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
+use libsql::params;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+use crate::config::{HalConfig, Provider};
+use crate::index::Database;
+use crate::model::azure;
+use crate::model::embedding_backend::{EMBEDDING_DIMENSIONS, EmbeddingProvider};
+use crate::model::huggingface;
+use crate::model::vertex::{Credentials, VertexClient};
+
+/// Endpoint of the Gemini API
+const GEMINI_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// How long an endpoint may take to respond before it counts as unreachable
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest HTTP request head the health server reads
+const MAX_REQUEST_HEAD: usize = 8192;
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Nothing to fix
+    Pass,
+
+    /// Working, but likely to cause problems
+    Warn,
+
+    /// Commands depending on this will fail
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pass => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+        })
+    }
+}
+
+/// Result of one check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    /// What was checked, e.g. `api key GEMINI_API_KEY`
+    pub name: String,
+
+    /// Outcome
+    pub status: Status,
+
+    /// What was found
+    pub detail: String,
+
+    /// How to fix a warning or failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Results of a set of checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    /// Worst outcome of the checks
+    pub status: Status,
+
+    /// Every check, in the order run
+    pub checks: Vec<Check>,
+}
+
+impl HealthReport {
+    /// A report of checks
+    pub fn new(checks: Vec<Check>) -> Self {
+        let status = checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(Status::Pass);
+        Self { status, checks }
+    }
+
+    /// Whether no check failed; warnings don't make HAL unhealthy
+    pub fn healthy(&self) -> bool {
+        self.status != Status::Fail
+    }
+}
+
+/// Which checks to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// API keys and the database, cheap enough for frequent liveness probes
+    Liveness,
+
+    /// Every check, including embedding dimensions and network reachability
+    Readiness,
+}
+
+/// Run the checks of a scope
+///
+/// # Arguments
+///
+/// * `scope` - Which checks to run
+/// * `config` - The configuration to check
+/// * `db` - The database, or why it couldn't be opened
+pub async fn run(scope: Scope, config: &HalConfig, db: Result<&Database, &str>) -> HealthReport {
+    let mut checks = check_api_keys(config);
+    checks.push(match db {
+        Ok(db) => check_database(db).await,
+        Err(error) => Check::fail(
+            "database",
+            error,
+            "start the libsql server on 127.0.0.1:8080, or set `database` in hal.toml to a file \
+             path or server URL",
+        ),
+    });
+    if let (Scope::Readiness, Ok(db)) = (scope, db) {
+        checks.push(check_embedding_dimensions(db, config).await);
+    }
+    if scope == Scope::Readiness {
+        checks.extend(check_network(config).await);
+    }
+    HealthReport::new(checks)
+}
+
+/// Providers serving completions or embeddings, each once
+fn providers(config: &HalConfig) -> Vec<Provider> {
+    let embedding = match config.embedding.provider {
+        EmbeddingProvider::Gemini => Some(config.provider),
+        EmbeddingProvider::Vertex => Some(Provider::Vertex),
+        EmbeddingProvider::Azure => Some(Provider::Azure),
+        EmbeddingProvider::Local | EmbeddingProvider::HuggingFace => None,
+    };
+    let mut providers = Vec::new();
+    for provider in std::iter::once(config.provider)
+        .chain(config.model_chain.models.iter().map(|model| model.provider))
+        .chain(embedding)
+    {
+        if !providers.contains(&provider) {
+            providers.push(provider);
+        }
+    }
+    providers
+}
+
+/// Check an API key set in the environment, the OS keyring or `hal.toml`
+fn check_api_key(var: &str, purpose: &str, fix: String) -> Check {
+    let name = format!("api key {}", var);
+    match crate::config::api_key(var) {
+        Some(_) => Check::pass(name, format!("set, for {}", purpose)),
+        None => Check::fail(name, format!("not set, needed for {}", purpose), fix),
+    }
+}
+
+/// Check the API keys and credentials of every configured provider
+pub fn check_api_keys(config: &HalConfig) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for provider in providers(config) {
+        for (var, purpose) in provider.api_key_vars() {
+            let fix = format!(
+                "run `hal auth set {}`, or set {} in the environment",
+                provider, var
+            );
+            checks.push(check_api_key(var, purpose, fix));
+        }
+        match provider {
+            Provider::Gemini => {}
+            Provider::Vertex => checks.push(check_vertex_credentials(config)),
+            Provider::Azure => checks.push(match config.azure.endpoint_url() {
+                Some(endpoint) => Check::pass("azure endpoint", endpoint),
+                None => Check::fail(
+                    "azure endpoint",
+                    "no Azure OpenAI resource or endpoint configured",
+                    format!(
+                        "set `resource` or `endpoint` in the [azure] table of hal.toml, or {}",
+                        azure::ENDPOINT_VAR
+                    ),
+                ),
+            }),
+        }
+    }
+    for model in &config.model_chain.models {
+        if let Some(var) = &model.api_key {
+            let purpose = format!("model {} of the model chain", model.model);
+            let fix = format!("set {}, or remove `api_key` from the model chain", var);
+            checks.push(check_api_key(var, &purpose, fix));
+        }
+    }
+    if config.embedding.provider == EmbeddingProvider::HuggingFace {
+        let fix = format!(
+            "create a token at https://huggingface.co/settings/tokens and set {}",
+            huggingface::TOKEN_VAR
+        );
+        checks.push(check_api_key(
+            huggingface::TOKEN_VAR,
+            "Hugging Face embeddings",
+            fix,
+        ));
+    }
+    checks
+}
+
+/// Check that Vertex AI credentials and a project are found
+fn check_vertex_credentials(config: &HalConfig) -> Check {
+    let name = "vertex credentials";
+    match VertexClient::from_config(&config.vertex) {
+        Ok(client) => match client.credentials() {
+            Credentials::Metadata => Check::warn(
+                name,
+                "no credentials file found, relying on the metadata server of a Google Cloud \
+                 instance",
+                "outside Google Cloud, run `gcloud auth application-default login` or set \
+                 GOOGLE_APPLICATION_CREDENTIALS",
+            ),
+            credentials => Check::pass(name, format!("{:?}", credentials)),
+        },
+        Err(e) => Check::fail(
+            name,
+            e.to_string(),
+            "run `gcloud auth application-default login`, and set `project` in the [vertex] \
+             table of hal.toml or GOOGLE_CLOUD_PROJECT",
+        ),
+    }
+}
+
+/// Check that the database answers queries
+pub async fn check_database(db: &Database) -> Check {
+    let count = async {
+        let mut rows = db
+            .execute_query("SELECT COUNT(*) FROM chunks", params![])
+            .await
+            .map_err(|e| e.to_string())?;
+        let row = rows
+            .next()
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "no rows returned".to_string())?;
+        row.get::<i64>(0).map_err(|e| e.to_string())
+    };
+    match count.await {
+        Ok(chunks) => Check::pass("database", format!("{} chunks indexed", chunks)),
+        Err(e) => Check::fail(
+            "database",
+            e,
+            "check that the database in hal.toml is reachable and readable",
+        ),
+    }
+}
+
+/// Check that every stored embedding has the dimensions of the configured model
+pub async fn check_embedding_dimensions(db: &Database, config: &HalConfig) -> Check {
+    let name = "embedding dimensions";
+    let model = config.embedding.model_name();
+    let mut dimensions = Vec::new();
+    let query = async {
+        let mut rows = db
+            .execute_query(
+                "SELECT length(embedding) / 4, COUNT(*) FROM chunks GROUP BY 1 ORDER BY 2 DESC",
+                params![],
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+            let ndims = row.get::<i64>(0).map_err(|e| e.to_string())?;
+            let chunks = row.get::<i64>(1).map_err(|e| e.to_string())?;
+            dimensions.push((ndims, chunks));
+        }
+        Ok::<_, String>(())
+    };
+    if let Err(e) = query.await {
+        return Check::fail(name, e, "check that the database in hal.toml is readable");
+    }
+
+    let mismatched: Vec<String> = dimensions
+        .iter()
+        .filter(|(ndims, _)| *ndims != EMBEDDING_DIMENSIONS as i64)
+        .map(|(ndims, chunks)| format!("{} chunks with {} dimensions", chunks, ndims))
+        .collect();
+    if !mismatched.is_empty() {
+        return Check::fail(
+            name,
+            format!(
+                "{}, but {} produces {}",
+                mismatched.join(", "),
+                model,
+                EMBEDDING_DIMENSIONS
+            ),
+            format!(
+                "run `hal reembed` to embed every chunk again with {}",
+                model
+            ),
+        );
+    }
+    let chunks: i64 = dimensions.iter().map(|(_, chunks)| chunks).sum();
+    Check::pass(
+        name,
+        format!(
+            "{} chunks with {} dimensions, as {} produces",
+            chunks, EMBEDDING_DIMENSIONS, model
+        ),
+    )
+}
+
+/// Endpoints of the configured providers
+fn endpoints(config: &HalConfig) -> BTreeSet<String> {
+    let mut endpoints = BTreeSet::new();
+    for provider in providers(config) {
+        let endpoint = match provider {
+            Provider::Gemini => Some(GEMINI_URL.to_string()),
+            Provider::Vertex => VertexClient::from_config(&config.vertex)
+                .ok()
+                .map(|client| client.base_url().to_string()),
+            Provider::Azure => config.azure.endpoint_url(),
+        };
+        // Providers without an endpoint already fail the credentials checks
+        endpoints.extend(endpoint);
+    }
+    if config.embedding.provider == EmbeddingProvider::HuggingFace {
+        endpoints.insert(
+            config
+                .embedding
+                .url
+                .clone()
+                .unwrap_or_else(|| huggingface::DEFAULT_API_URL.to_string()),
+        );
+    }
+    endpoints
+}
+
+/// Check that the endpoint of every configured provider responds
+///
+/// Any HTTP response counts, since requests are sent without credentials.
+pub async fn check_network(config: &HalConfig) -> Vec<Check> {
+    let client = match reqwest::Client::builder().timeout(NETWORK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            return vec![Check::fail(
+                "network",
+                e.to_string(),
+                "check the TLS and proxy settings of the environment",
+            )];
+        }
+    };
+    let mut checks = Vec::new();
+    for endpoint in endpoints(config) {
+        let name = format!("network {}", endpoint);
+        checks.push(match client.get(&endpoint).send().await {
+            Ok(response) => Check::pass(name, format!("reachable ({})", response.status())),
+            Err(e) => Check::fail(
+                name,
+                format!("unreachable: {}", e),
+                "check the network connection, DNS, and HTTPS_PROXY if a proxy is required",
+            ),
+        });
+    }
+    checks
+}
+
+/// Serve `/healthz` and `/readyz` until the listener fails
+///
+/// # Arguments
+///
+/// * `listener` - Socket accepting probe connections
+/// * `db` - The database of the server, or why it couldn't be opened
+pub async fn serve(listener: TcpListener, db: Result<Database, String>) -> std::io::Result<()> {
+    let db = std::sync::Arc::new(db);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let db = db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, db.as_ref().as_ref().map_err(String::as_str)).await {
+                debug!("Health probe from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Answer one HTTP request
+async fn respond(mut stream: TcpStream, db: Result<&Database, &str>) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+    let scope = match path {
+        "/healthz" => Some(Scope::Liveness),
+        "/readyz" => Some(Scope::Readiness),
+        _ => None,
+    };
+
+    let (status, body) = match (method, scope) {
+        ("GET", Some(scope)) => {
+            let report = run(scope, crate::config::current(), db).await;
+            if !report.healthy() {
+                warn!("Health check {} failed", path);
+            }
+            let status = if report.healthy() {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            let body = serde_json::to_string(&report).map_err(std::io::Error::other)?;
+            (status, body)
+        }
+        (_, Some(_)) => (
+            "405 Method Not Allowed",
+            r#"{"error":"method not allowed"}"#.to_string(),
+        ),
+        _ => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_report_status() {
+        let report = HealthReport::new(vec![
+            Check::pass("database", "3 chunks indexed"),
+            Check::warn("vertex credentials", "metadata server", "log in"),
+        ]);
+        assert_eq!(report.status, Status::Warn);
+        assert!(report.healthy());
+
+        let mut checks = report.checks;
+        checks.push(Check::fail("api key GEMINI_API_KEY", "not set", "set it"));
+        let report = HealthReport::new(checks);
+        assert_eq!(report.status, Status::Fail);
+        assert!(!report.healthy());
+        assert!(HealthReport::new(Vec::new()).healthy());
+    }
+
+    #[tokio::test]
+    async fn test_serve_probes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Err("connection refused".to_string())));
+
+        let response = get(addr, "/healthz").await;
+        assert!(response.starts_with("HTTP/1.1 503"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let report: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(report["status"], "fail");
+        assert!(
+            report["checks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|check| check["name"] == "database" && check["detail"] == "connection refused")
+        );
+
+        assert!(get(addr, "/metrics").await.starts_with("HTTP/1.1 404"));
+    }
+}
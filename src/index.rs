@@ -9,6 +9,15 @@
 //! - `Database`: Main interface for interacting with the LibSQL vector database
 //! - `Website`: Represents metadata about an indexed website
 //! - `IndexedChunk`: Represents a processed and indexed content chunk with its embedding
+//! - `GarbageCollection`: Pages removed because the latest crawl no longer found them
+//! - `StagedSwap`: Pages of an atomic re-index swapped into the index in one transaction
+//! - `ChunkProvenance`: Chunks of a website grouped by the processor settings that built them
+//! - `compression`: zstd compression of large stored values such as raw HTML
+//! - `encryption`: Keys for encrypting local databases at rest (`encryption` feature)
+//! - `language`: Detects the language of chunk texts
+//! - `quantization`: Compact int8 and binary copies of embeddings for faster scans
+//! - `retention`: Policies dropping the least recently used chunks so indexes stay bounded
+//! - `versions`: Detects documentation versions such as `/v2/` or `/latest/` in URLs
 //!
 //! ## Features
 //!
@@ -29,13 +38,21 @@
 //! This design enables efficient vector search across content while maintaining
 //! source attribution and metadata for retrieved results.
 
+pub mod compression;
 mod database;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod error;
+pub mod language;
+pub mod quantization;
+pub mod retention;
 mod schema;
+pub mod versions;
 
 pub use database::Database;
 pub use error::DbError;
 use rig::embeddings::Embedding;
+use serde::Serialize;
 
 /// Represents a website in the index
 #[derive(Debug, Clone)]
@@ -55,7 +72,7 @@ pub struct Website {
     /// Date of last indexing
     pub last_index_date: i64,
 
-    /// Number of pages indexed
+    /// Number of pages with indexed chunks, kept up to date by triggers on the chunks table
     pub page_count: i64,
 
     /// Status of the website
@@ -88,6 +105,73 @@ pub struct IndexedChunk {
 
     /// Heading of the chunk
     pub heading: Option<String>,
+
+    /// Breadcrumb of the headings enclosing the chunk, e.g. `Guide > Setup`
+    pub heading_path: Option<String>,
+
+    /// Author of the source page
+    pub author: Option<String>,
+
+    /// Publication date of the source page as a Unix timestamp
+    pub published_at: Option<i64>,
+
+    /// schema.org or OpenGraph type of the source page, e.g. `BlogPosting`
+    pub content_type: Option<String>,
+
+    /// Documentation version detected from the page URL, e.g. `v2` or `latest`
+    pub doc_version: Option<String>,
+}
+
+/// Result of removing the chunks of pages absent from the latest crawl of a website
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GarbageCollection {
+    /// ID of the crawl the pages were compared against
+    pub crawl_id: Option<i64>,
+
+    /// URLs of the indexed pages the crawl did not find
+    pub stale_pages: Vec<String>,
+
+    /// Number of chunks deleted; zero for a dry run
+    pub deleted_chunks: usize,
+}
+
+/// Result of swapping the staged pages of an atomic re-index into the index
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StagedSwap {
+    /// Number of re-indexed pages
+    pub pages: usize,
+
+    /// Number of chunks swapped in
+    pub chunks: usize,
+
+    /// Number of chunks of the pages they replaced
+    pub replaced_chunks: usize,
+}
+
+/// Chunks of a website built with the same processor settings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChunkProvenance {
+    /// Domain of the website
+    pub domain: String,
+
+    /// Fingerprint of the processor settings; `None` for chunks indexed before settings
+    /// were recorded or built without the processor, such as source code
+    pub config_hash: Option<String>,
+
+    /// Number of pages with such chunks
+    pub pages: i64,
+
+    /// Number of chunks
+    pub chunks: i64,
+
+    /// ID of the earliest crawl that built the chunks, `None` if no crawl was recorded
+    pub first_crawl_id: Option<i64>,
+
+    /// ID of the latest crawl that built the chunks
+    pub last_crawl_id: Option<i64>,
+
+    /// When the latest of the chunks was indexed as a Unix timestamp
+    pub last_indexed_at: Option<i64>,
 }
 
 #[cfg(test)]
@@ -129,6 +213,11 @@ mod tests {
             },
             position: 1,
             heading: Some("Test Heading".to_string()),
+            heading_path: Some("Guide > Test Heading".to_string()),
+            author: Some("Test Author".to_string()),
+            published_at: Some(1625097600),
+            content_type: None,
+            doc_version: Some("latest".to_string()),
         };
 
         assert_eq!(chunk.id, 1);
@@ -140,6 +229,9 @@ mod tests {
         assert_eq!(chunk.embedding.vec, vec![0.1, 0.2, 0.3, 0.4]);
         assert_eq!(chunk.position, 1);
         assert_eq!(chunk.heading, Some("Test Heading".to_string()));
+        assert_eq!(chunk.heading_path, Some("Guide > Test Heading".to_string()));
+        assert_eq!(chunk.author, Some("Test Author".to_string()));
+        assert_eq!(chunk.published_at, Some(1625097600));
     }
 
     #[test]
@@ -156,6 +248,11 @@ mod tests {
             },
             position: 2,
             heading: None,
+            heading_path: None,
+            author: None,
+            published_at: None,
+            content_type: None,
+            doc_version: None,
         };
 
         assert_eq!(chunk.id, 2);
@@ -167,5 +264,6 @@ mod tests {
         assert_eq!(chunk.embedding.vec, vec![0.5, 0.6, 0.7, 0.8]);
         assert_eq!(chunk.position, 2);
         assert_eq!(chunk.heading, None);
+        assert_eq!(chunk.heading_path, None);
     }
 }
@@ -0,0 +1,148 @@
+//! # Compression Module
+//!
+//! This module compresses large values stored in the index with zstd.
+//!
+//! ## Key Components
+//!
+//! - `compress` / `decompress`: Plain zstd for standalone values such as raw HTML
+//! - `train_dictionary`: Trains a zstd dictionary on the chunks of a collection
+//! - `compress_text` / `decompress_text`: Dictionary compression of chunk text and context
+//! - `DictionaryCache`: Dictionaries loaded from the database, keyed by dictionary ID
+//!
+//! ## Chunk Text
+//!
+//! Chunks are short and a collection repeats the same boilerplate, navigation and
+//! vocabulary across pages, so plain zstd gains little per chunk. A dictionary trained on
+//! a collection's own chunks captures that redundancy. Compressed values are stored as
+//! BLOBs in the `text` and `context` columns; plain values stay TEXT, so compressed and
+//! uncompressed collections share the schema. Every zstd frame records the ID of its
+//! dictionary, which is all a reader needs to decode it.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+
+use crate::index::error::DbError;
+
+/// zstd level used for stored values, favoring speed over ratio
+pub const COMPRESSION_LEVEL: i32 = 3;
+
+/// Maximum size of a trained dictionary in bytes (the zstd default of 110 KiB)
+pub const DICTIONARY_SIZE: usize = 112_640;
+
+/// Compress bytes with zstd
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, DbError> {
+    zstd::encode_all(data, COMPRESSION_LEVEL)
+        .map_err(|e| DbError::Data(format!("Failed to compress: {}", e)))
+}
+
+/// Decompress bytes compressed with `compress`
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DbError> {
+    zstd::decode_all(data).map_err(|e| DbError::Data(format!("Failed to decompress: {}", e)))
+}
+
+/// Train a dictionary on sample texts of a collection
+///
+/// zstd needs a reasonable number of samples; collections of only a handful of chunks
+/// fail to train and are better left uncompressed.
+pub fn train_dictionary(samples: &[String]) -> Result<Vec<u8>, DbError> {
+    let samples: Vec<&[u8]> = samples.iter().map(|sample| sample.as_bytes()).collect();
+    zstd::dict::from_samples(&samples, DICTIONARY_SIZE)
+        .map_err(|e| DbError::Data(format!("Failed to train dictionary: {}", e)))
+}
+
+/// ID of a trained dictionary
+pub fn dictionary_id(dictionary: &[u8]) -> Option<u32> {
+    zstd::zstd_safe::get_dict_id_from_dict(dictionary).map(|id| id.get())
+}
+
+/// ID of the dictionary a compressed value was written with
+pub fn frame_dictionary_id(frame: &[u8]) -> Option<u32> {
+    zstd::zstd_safe::get_dict_id_from_frame(frame).map(|id| id.get())
+}
+
+/// Compress a text with a dictionary
+pub fn compress_text(text: &str, dictionary: &[u8]) -> Result<Vec<u8>, DbError> {
+    zstd::bulk::Compressor::with_dictionary(COMPRESSION_LEVEL, dictionary)
+        .and_then(|mut compressor| compressor.compress(text.as_bytes()))
+        .map_err(|e| DbError::Data(format!("Failed to compress text: {}", e)))
+}
+
+/// Decompress a text compressed with `compress_text`
+pub fn decompress_text(frame: &[u8], dictionary: &[u8]) -> Result<String, DbError> {
+    let mut text = String::new();
+    zstd::stream::read::Decoder::with_dictionary(frame, dictionary)
+        .and_then(|mut decoder| decoder.read_to_string(&mut text))
+        .map_err(|e| DbError::Data(format!("Failed to decompress text: {}", e)))?;
+    Ok(text)
+}
+
+/// Dictionaries loaded from the database, shared by clones of a `Database`
+#[derive(Debug, Clone, Default)]
+pub struct DictionaryCache {
+    dictionaries: Arc<RwLock<HashMap<u32, Arc<Vec<u8>>>>>,
+}
+
+impl DictionaryCache {
+    /// Get a loaded dictionary by ID
+    pub fn get(&self, id: u32) -> Option<Arc<Vec<u8>>> {
+        self.dictionaries
+            .read()
+            .expect("dictionary cache lock poisoned")
+            .get(&id)
+            .cloned()
+    }
+
+    /// Add a dictionary to the cache
+    pub fn insert(&self, id: u32, dictionary: Vec<u8>) -> Arc<Vec<u8>> {
+        let dictionary = Arc::new(dictionary);
+        self.dictionaries
+            .write()
+            .expect("dictionary cache lock poisoned")
+            .insert(id, dictionary.clone());
+        dictionary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let html = "<html><body><p>Hello</p></body></html>".repeat(50);
+        let compressed = compress(html.as_bytes()).unwrap();
+        assert!(compressed.len() < html.len());
+        assert_eq!(decompress(&compressed).unwrap(), html.as_bytes());
+        assert!(decompress(b"not zstd").is_err());
+    }
+
+    #[test]
+    fn test_dictionary_round_trip() {
+        let samples: Vec<String> = (0..500)
+            .map(|i| {
+                format!(
+                    "Section {} of the configuration guide. Set the `timeout_{}` option in \
+                     config.toml to change how long the server waits before retrying.",
+                    i,
+                    i % 17
+                )
+            })
+            .collect();
+        let dictionary = train_dictionary(&samples).unwrap();
+        let id = dictionary_id(&dictionary).unwrap();
+
+        let compressed = compress_text(&samples[3], &dictionary).unwrap();
+        assert!(compressed.len() < samples[3].len());
+        assert_eq!(frame_dictionary_id(&compressed), Some(id));
+        assert_eq!(
+            decompress_text(&compressed, &dictionary).unwrap(),
+            samples[3]
+        );
+
+        let cache = DictionaryCache::default();
+        assert!(cache.get(id).is_none());
+        cache.insert(id, dictionary);
+        assert!(cache.get(id).is_some());
+    }
+}
@@ -11,12 +11,23 @@
 //! ## Features
 //!
 //! - LibSQL connection management (local and remote)
+//! - Encryption at rest of local databases with a key from the environment or a keyfile
 //! - Website metadata CRUD operations
 //! - Chunk storage with vector embeddings
 //! - Transactional operations for data integrity
 //! - Efficient binary encoding of embeddings
 //! - Concurrent processing for batch operations
 //! - Reembedding functionality for updating vector representations
+//! - Compressed raw HTML of pages for reprocessing
+//! - Audit log of the values redacted from each page
+//! - Quarantine of pages and chunks failing safety checks
+//! - Per-collection zstd dictionary compression of chunk text and context
+//! - Per-crawl page sets and garbage collection of pages that disappeared
+//! - Atomic re-indexes of websites staged in separate tables and swapped in at once
+//! - Retention policies dropping the least recently used chunks
+//! - Provenance of chunks: the crawl and the processor settings that built them
+//! - The git commit each repository source was last indexed at
+//! - HTTP cache validators of crawled pages for conditional recrawls
 //! - URL and domain-based indexing and retrieval
 //!
 //! ## Implementation Details
@@ -26,12 +37,24 @@
 //! between websites and their associated chunks, while providing optimized access
 //! patterns for vector similarity search.
 
+use crate::crawler::http_cache::HttpValidators;
+use crate::index::compression::{self, DictionaryCache};
+#[cfg(feature = "encryption")]
+use crate::index::encryption::EncryptionKey;
 use crate::index::error::DbError;
+use crate::index::language::detect_language;
+use crate::index::quantization::{Quantization, QueryVector};
+use crate::index::retention::{ChunkUsage, Pruning, RetentionPolicy, select_pruned};
 use crate::index::schema;
-use crate::index::{IndexedChunk, Website};
+use crate::index::versions::detect_version;
+use crate::index::{ChunkProvenance, GarbageCollection, IndexedChunk, StagedSwap, Website};
 use crate::model::embedding::EmbeddingConversion;
+use crate::processor::redaction::{Redaction, RedactionCounts, RedactionLogEntry};
+use crate::processor::safety::{QuarantineEntry, SafetyAction};
 use libsql::{Connection, Row, Rows, params};
+use rayon::prelude::*;
 use rig::embeddings::Embedding;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, instrument};
 
@@ -39,6 +62,8 @@ use tracing::{debug, instrument};
 #[derive(Clone)]
 pub struct Database {
     conn: Connection,
+    read_only: bool,
+    dictionaries: DictionaryCache,
 }
 
 impl Database {
@@ -48,25 +73,112 @@ impl Database {
         // Initialize schema
         schema::initialize_schema(&conn).await?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            read_only: false,
+            dictionaries: DictionaryCache::default(),
+        })
     }
 
     /// Create a new database manager from a path
+    ///
+    /// With the `encryption` feature, the database is encrypted with the key configured in
+    /// the environment, if any (see `EncryptionKey::from_env`).
     pub async fn new_from_path(path: &str) -> Result<Self, DbError> {
-        let db = libsql::Builder::new_local(path)
-            .build()
-            .await
-            .map_err(|e| DbError::Connection(format!("Failed to open database: {}", e)))?;
-
-        let conn = db
-            .connect()
-            .map_err(|e| DbError::Connection(format!("Failed to connect to database: {}", e)))?;
+        let conn = connect_local(path, libsql::OpenFlags::default(), env_encryption()?).await?;
+        Self::new(conn).await
+    }
 
+    /// Create a new database manager from a path, encrypted with the given key
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the database file
+    /// * `key` - The encryption key, or `None` for an unencrypted database
+    #[cfg(feature = "encryption")]
+    pub async fn new_from_path_with_key(
+        path: &str,
+        key: Option<&EncryptionKey>,
+    ) -> Result<Self, DbError> {
+        let encryption = key.map(EncryptionKey::encryption_config);
+        let conn = connect_local(path, libsql::OpenFlags::default(), encryption).await?;
         Self::new(conn).await
     }
 
+    /// Open an existing database at the given path in read-only mode
+    ///
+    /// The connection is opened with `SQLITE_OPEN_READ_ONLY`, so any write attempted
+    /// through it is rejected by SQLite itself. The schema is not created or migrated;
+    /// instead it is verified to exist, which makes this suitable for search servers
+    /// running against a database that is being written by the indexer, or against a
+    /// database file mounted read-only. With the `encryption` feature, encrypted databases
+    /// are opened with the key configured in the environment.
+    #[instrument]
+    pub async fn open_read_only(path: &str) -> Result<Self, DbError> {
+        let conn = connect_local(
+            path,
+            libsql::OpenFlags::SQLITE_OPEN_READ_ONLY,
+            env_encryption()?,
+        )
+        .await?;
+
+        schema::verify_schema(&conn).await?;
+
+        Ok(Self {
+            conn,
+            read_only: true,
+            dictionaries: DictionaryCache::default(),
+        })
+    }
+
+    /// Whether this database was opened in read-only mode
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Change the encryption key of the database
+    ///
+    /// The database must have been opened with its current key. Passing `None`
+    /// decrypts it; an unencrypted database is encrypted by passing a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_key` - The new encryption key, or `None` to store the database unencrypted
+    #[cfg(feature = "encryption")]
+    #[instrument(skip(self))]
+    pub async fn rekey(&self, new_key: Option<&EncryptionKey>) -> Result<(), DbError> {
+        if self.read_only {
+            return Err(DbError::Connection(
+                "Cannot change the key of a database opened read-only".to_string(),
+            ));
+        }
+
+        let literal = new_key.map_or_else(|| "''".to_string(), EncryptionKey::sql_literal);
+        self.conn
+            .execute(&format!("PRAGMA rekey = {}", literal), params![])
+            .await
+            .map_err(|e| DbError::Connection(format!("Failed to change key: {}", e)))?;
+
+        Ok(())
+    }
+
     pub async fn new_local_libsql() -> Result<Self, DbError> {
-        let db = libsql::Builder::new_remote("http://127.0.0.1:8080".to_string(), "".to_string())
+        Self::new_remote("http://127.0.0.1:8080").await
+    }
+
+    /// Open the default database: the libsql server or file set as `database` in
+    /// `hal.toml`, or the local libsql server
+    pub async fn open_configured() -> Result<Self, DbError> {
+        match &crate::config::current().database {
+            Some(location) if location.contains("://") => Self::new_remote(location).await,
+            Some(path) => Self::new_from_path(path).await,
+            None => Self::new_local_libsql().await,
+        }
+    }
+
+    /// Connect to a libsql server
+    pub async fn new_remote(url: &str) -> Result<Self, DbError> {
+        let db = libsql::Builder::new_remote(url.to_string(), "".to_string())
             .build()
             .await
             .map_err(|e| DbError::Connection(format!("Failed to open database: {}", e)))?;
@@ -108,18 +220,16 @@ impl Database {
 
         self.conn.execute(
             "INSERT INTO websites (url, domain, first_index_date, last_index_date, page_count, status)
-             VALUES (?, ?, ?, ?, ?, ?)
+             VALUES (?, ?, ?, ?, 0, ?)
              ON CONFLICT(url) DO UPDATE SET
              domain = excluded.domain,
              last_index_date = ?,
-             page_count = excluded.page_count,
              status = excluded.status",
             params![
                 base_url,
                 website.domain.clone(),
                 website.first_index_date,
                 website.last_index_date,
-                website.page_count,
                 website.status.clone(),
                 now,
             ],
@@ -314,9 +424,11 @@ impl Database {
                     .as_secs() as i64;
 
                 tx.execute(
-                    "UPDATE websites SET last_index_date = ?, page_count = page_count + 1 WHERE id = ?",
+                    "UPDATE websites SET last_index_date = ? WHERE id = ?",
                     params![now, website.id],
-                ).await.map_err(|e| DbError::Query(format!("Failed to update website: {}", e)))?;
+                )
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to update website: {}", e)))?;
 
                 website.id
             }
@@ -338,7 +450,7 @@ impl Database {
                     domain,
                     first_index_date: now,
                     last_index_date: now,
-                    page_count: 1,
+                    page_count: 0,
                     status: "active".to_string(),
                 };
 
@@ -383,59 +495,302 @@ impl Database {
             .map_err(|e| DbError::Query(format!("Failed to delete chunks: {}", e)))?;
 
         // Add new chunks
-        for chunk in chunks {
-            let indexed_chunk = IndexedChunk {
-                id: 0, // Will be set by the database
-                website_id,
-                url: url.to_string(),
-                text: chunk.text,
-                context: chunk.context,
-                embedding: chunk.embedding,
-                position: chunk.metadata.position as i64,
-                heading: chunk.metadata.heading,
-            };
+        insert_chunks(&tx, "chunks", website_id, url, chunks).await?;
+
+        // Commit the transaction
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(website_id)
+    }
+
+    /// Start an atomic re-index of a website, clearing what an interrupted one staged
+    ///
+    /// Pages are then staged with `stage_page_index` and made searchable together with
+    /// `swap_staged`, so searches never see a partly re-indexed website.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Scheme and host of the website; it is added if not indexed yet
+    ///
+    /// # Returns
+    ///
+    /// The ID of the website
+    #[instrument(skip(self))]
+    pub async fn begin_staging(&self, base_url: &str) -> Result<i64, DbError> {
+        let website_id = match self.get_website_by_url(base_url).await? {
+            Some(website) => website.id,
+            None => {
+                let domain = base_url
+                    .parse::<url::Url>()
+                    .map_err(|e| DbError::Data(format!("Failed to parse URL: {}", e)))?
+                    .host_str()
+                    .ok_or_else(|| DbError::Data("URL has no host".to_string()))?
+                    .to_string();
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs() as i64;
+                self.add_website(&Website {
+                    id: 0,
+                    url: base_url.to_string(),
+                    domain,
+                    first_index_date: now,
+                    last_index_date: now,
+                    page_count: 0,
+                    status: "active".to_string(),
+                })
+                .await?
+            }
+        };
+        self.discard_staged(website_id).await?;
+        Ok(website_id)
+    }
+
+    /// Stage the chunks of a page for an atomic re-index
+    ///
+    /// The page's searchable chunks are unchanged until `swap_staged`. Staging a page
+    /// again replaces its staged chunks.
+    ///
+    /// # Arguments
+    ///
+    /// * `website_id` - ID returned by `begin_staging`
+    /// * `url` - URL of the page
+    /// * `chunks` - The processed chunks; none removes the page's chunks on swap
+    #[instrument(skip(self, chunks))]
+    pub async fn stage_page_index(
+        &self,
+        website_id: i64,
+        url: &str,
+        chunks: Vec<crate::processor::ProcessedChunk>,
+    ) -> Result<usize, DbError> {
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute(
+            "INSERT OR IGNORE INTO staged_pages (website_id, url) VALUES (?, ?)",
+            params![website_id, url],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to stage page: {}", e)))?;
+        tx.execute(
+            "DELETE FROM staged_chunks WHERE website_id = ? AND url = ?",
+            params![website_id, url],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to delete staged chunks: {}", e)))?;
+        let staged = insert_chunks(&tx, "staged_chunks", website_id, url, chunks).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to commit transaction: {}", e)))?;
+        Ok(staged)
+    }
+
+    /// Validate the staged pages of a website and swap them in with one transaction
+    ///
+    /// Staged chunks must have full embeddings and text. A re-index that would leave the
+    /// staged pages without any chunks while they have some now is refused, since that
+    /// points to broken extraction rather than emptied pages. A refused swap leaves the
+    /// index and the staged pages as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `website_id` - ID returned by `begin_staging`
+    ///
+    /// # Returns
+    ///
+    /// The number of swapped pages and chunks and of chunks they replaced
+    #[instrument(skip(self))]
+    pub async fn swap_staged(&self, website_id: i64) -> Result<StagedSwap, DbError> {
+        let count = |sql: &'static str| async move {
+            let mut rows = self
+                .conn
+                .query(sql, params![website_id])
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to check staged chunks: {}", e)))?;
+            match rows.next().await {
+                Ok(Some(row)) => row
+                    .get::<i64>(0)
+                    .map(|count| count as usize)
+                    .map_err(|e| DbError::Data(format!("Failed to get count: {}", e))),
+                Ok(None) => Ok(0),
+                Err(e) => Err(DbError::Data(format!(
+                    "Failed to check staged chunks: {}",
+                    e
+                ))),
+            }
+        };
 
-            // Insert the chunk with the embedding as a binary blob
+        let swap = StagedSwap {
+            pages: count("SELECT COUNT(*) FROM staged_pages WHERE website_id = ?").await?,
+            chunks: count("SELECT COUNT(*) FROM staged_chunks WHERE website_id = ?").await?,
+            replaced_chunks: count(
+                "SELECT COUNT(*) FROM chunks WHERE website_id = ?1
+                 AND url IN (SELECT url FROM staged_pages WHERE website_id = ?1)",
+            )
+            .await?,
+        };
+        let invalid = count(
+            "SELECT COUNT(*) FROM staged_chunks WHERE website_id = ?
+             AND (length(embedding) != 768 * 4 OR length(text) = 0)",
+        )
+        .await?;
+        if invalid > 0 {
+            return Err(DbError::Data(format!(
+                "{} staged chunks have an invalid embedding or no text",
+                invalid
+            )));
+        }
+        if swap.chunks == 0 && swap.replaced_chunks > 0 {
+            return Err(DbError::Data(format!(
+                "Re-index produced no chunks for {} pages that have {} chunks",
+                swap.pages, swap.replaced_chunks
+            )));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to start transaction: {}", e)))?;
+        tx.execute(
+            "DELETE FROM chunks WHERE website_id = ?1
+             AND url IN (SELECT url FROM staged_pages WHERE website_id = ?1)",
+            params![website_id],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to delete chunks: {}", e)))?;
+        tx.execute(
+            "INSERT INTO chunks (website_id, url, text, context, embedding, position, heading, heading_path, author, published_at, doc_version, embedding_q, content_type, language, indexed_at, config_hash)
+             SELECT website_id, url, text, context, embedding, position, heading, heading_path, author, published_at, doc_version, embedding_q, content_type, language, indexed_at, config_hash
+             FROM staged_chunks WHERE website_id = ? ORDER BY rowid",
+            params![website_id],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to swap in staged chunks: {}", e)))?;
+        tx.execute(
+            "UPDATE websites SET last_index_date = ?1 WHERE id = ?2",
+            params![now, website_id],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to update website: {}", e)))?;
+        for table in ["staged_chunks", "staged_pages"] {
             tx.execute(
-                "INSERT INTO chunks (website_id, url, text, context, embedding, position, heading)
-                 VALUES (?, ?, ?, ?, ?, ?, ?)",
-                params![
-                    indexed_chunk.website_id,
-                    indexed_chunk.url,
-                    indexed_chunk.text,
-                    indexed_chunk.context,
-                    libsql::Value::Blob(indexed_chunk.embedding.to_binary()),
-                    indexed_chunk.position,
-                    indexed_chunk.heading,
-                ],
+                &format!("DELETE FROM {} WHERE website_id = ?", table),
+                params![website_id],
             )
             .await
-            .map_err(|e| DbError::Query(format!("Failed to add chunk: {}", e)))?;
+            .map_err(|e| DbError::Query(format!("Failed to clear {}: {}", table, e)))?;
         }
-
-        // Commit the transaction
         tx.commit()
             .await
             .map_err(|e| DbError::Transaction(format!("Failed to commit transaction: {}", e)))?;
 
-        Ok(website_id)
+        Ok(swap)
+    }
+
+    /// Drop the staged pages of a website without changing its searchable chunks
+    ///
+    /// # Returns
+    ///
+    /// The number of discarded chunks
+    #[instrument(skip(self))]
+    pub async fn discard_staged(&self, website_id: i64) -> Result<usize, DbError> {
+        let discarded = self
+            .conn
+            .execute(
+                "DELETE FROM staged_chunks WHERE website_id = ?",
+                params![website_id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to discard staged chunks: {}", e)))?;
+        self.conn
+            .execute(
+                "DELETE FROM staged_pages WHERE website_id = ?",
+                params![website_id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to discard staged pages: {}", e)))?;
+        Ok(discarded as usize)
+    }
+
+    /// Count the pages of a website that have chunks
+    ///
+    /// Unlike `Website::page_count`, the count is computed from the chunks table when
+    /// called.
+    pub async fn count_pages(&self, website_id: i64) -> Result<i64, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT COUNT(DISTINCT url) FROM chunks WHERE website_id = ?",
+                params![website_id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to count pages: {}", e)))?;
+        match rows.next().await {
+            Ok(Some(row)) => row
+                .get::<i64>(0)
+                .map_err(|e| DbError::Data(format!("Failed to get page count: {}", e))),
+            _ => Ok(0),
+        }
+    }
+
+    /// Recompute the page counts of all websites from their chunks
+    ///
+    /// Counts are kept up to date by triggers, so this only repairs databases whose
+    /// counts were changed by hand.
+    ///
+    /// # Returns
+    ///
+    /// The number of websites whose count was wrong
+    pub async fn recount_pages(&self) -> Result<usize, DbError> {
+        let corrected = self
+            .conn
+            .execute(schema::RECOUNT_PAGES, params![])
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to recount pages: {}", e)))?;
+        Ok(corrected as usize)
     }
 
     /// Add a chunk to the index
     pub async fn add_chunk(&self, chunk: &IndexedChunk) -> Result<i64, DbError> {
+        let quantization = website_quantization(&self.conn, chunk.website_id).await?;
+        let dictionary = website_dictionary(&self.conn, chunk.website_id).await?;
+        let indexed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
         // Insert the chunk with the embedding as a binary blob
         self.conn
             .execute(
-                "INSERT INTO chunks (website_id, url, text, context, embedding, position, heading)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
+                "INSERT INTO chunks (website_id, url, text, context, embedding, position, heading, heading_path, author, published_at, doc_version, embedding_q, content_type, language, indexed_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 params![
                     chunk.website_id,
                     chunk.url.clone(),
-                    chunk.text.clone(),
-                    chunk.context.clone(),
+                    encode_text(&chunk.text, dictionary.as_deref())?,
+                    encode_text(&chunk.context, dictionary.as_deref())?,
                     libsql::Value::Blob(chunk.embedding.to_binary()),
                     chunk.position,
                     chunk.heading.clone(),
+                    chunk.heading_path.clone(),
+                    chunk.author.clone(),
+                    chunk.published_at,
+                    chunk.doc_version.clone(),
+                    quantized_blob(quantization, &chunk.embedding),
+                    chunk.content_type.clone(),
+                    detect_language(&chunk.text),
+                    indexed_at,
                 ],
             )
             .await
@@ -485,7 +840,7 @@ impl Database {
         let mut rows = self
             .conn
             .query(
-                "SELECT id, website_id, url, text, context, embedding, position, heading
+                "SELECT id, website_id, url, text, context, embedding, position, heading, heading_path, author, published_at, doc_version, content_type
              FROM chunks
              WHERE website_id = ?",
                 params![website_id],
@@ -496,7 +851,7 @@ impl Database {
         let mut chunks = Vec::new();
         // In libsql 0.6.0, next() is async and returns Result<Option<Row>>
         while let Ok(Some(row)) = rows.next().await {
-            chunks.push(self.row_to_chunk(&row)?);
+            chunks.push(self.row_to_chunk(&row).await?);
         }
 
         Ok(chunks)
@@ -544,338 +899,2556 @@ impl Database {
         self.delete_chunks_by_website(website_id).await
     }
 
-    /// Convert a database row to a Website
-    fn row_to_website(&self, row: &Row) -> Result<Website, DbError> {
-        Ok(Website {
-            id: row
-                .get(0)
-                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?,
-            url: row
-                .get(1)
-                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
-            domain: row
-                .get(2)
-                .map_err(|e| DbError::Data(format!("Failed to get domain: {}", e)))?,
-            first_index_date: row
-                .get(3)
-                .map_err(|e| DbError::Data(format!("Failed to get first_index_date: {}", e)))?,
-            last_index_date: row
-                .get(4)
-                .map_err(|e| DbError::Data(format!("Failed to get last_index_date: {}", e)))?,
-            page_count: row
-                .get(5)
-                .map_err(|e| DbError::Data(format!("Failed to get page_count: {}", e)))?,
-            status: row
-                .get(6)
-                .map_err(|e| DbError::Data(format!("Failed to get status: {}", e)))?,
-        })
+    /// Delete the chunks of a single page
+    ///
+    /// The stored raw HTML of the page is deleted too, so reprocessing does not bring the
+    /// page back.
+    ///
+    /// # Arguments
+    ///
+    /// * `page_url` - URL of the page whose chunks are deleted
+    ///
+    /// # Returns
+    ///
+    /// The number of chunks deleted
+    pub async fn delete_chunks_by_page_url(&self, page_url: &str) -> Result<usize, DbError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM chunks WHERE url = ?", params![page_url])
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to delete chunks: {}", e)))?;
+        self.conn
+            .execute("DELETE FROM raw_pages WHERE url = ?", params![page_url])
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to delete raw HTML: {}", e)))?;
+
+        Ok(deleted as usize)
     }
 
-    /// Convert a database row to an IndexedChunk
-    fn row_to_chunk(&self, row: &Row) -> Result<IndexedChunk, DbError> {
-        // Get the embedding as a binary blob and convert it to Vec<f32>
-        let embedding_blob: Vec<u8> = row
-            .get(5)
-            .map_err(|e| DbError::Data(format!("Failed to get embedding: {}", e)))?;
+    /// Store the raw HTML of an indexed page, compressed with zstd
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the page; its website must already be indexed
+    /// * `html` - The raw HTML
+    pub async fn store_raw_html(&self, url: &str, html: &str) -> Result<(), DbError> {
+        let website_id = match self.get_website_by_page_url(url).await? {
+            Some(id) => id,
+            None => return Err(DbError::Data(format!("Website not found for URL: {}", url))),
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-        // Convert the blob to Vec<f32> using EmbeddingConversion trait
-        let embedding: Embedding = EmbeddingConversion::from_binary(&embedding_blob);
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO raw_pages (url, website_id, html, fetched_at)
+                 VALUES (?, ?, ?, ?)",
+                params![
+                    url,
+                    website_id,
+                    libsql::Value::Blob(compression::compress(html.as_bytes())?),
+                    now
+                ],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to store raw HTML: {}", e)))?;
 
-        Ok(IndexedChunk {
-            id: row
-                .get(0)
-                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?,
-            website_id: row
-                .get(1)
-                .map_err(|e| DbError::Data(format!("Failed to get website_id: {}", e)))?,
-            url: row
-                .get(2)
-                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
-            text: row
-                .get(3)
-                .map_err(|e| DbError::Data(format!("Failed to get text: {}", e)))?,
-            context: row
-                .get(4)
-                .map_err(|e| DbError::Data(format!("Failed to get context: {}", e)))?,
-            embedding,
-            position: row
-                .get(6)
-                .map_err(|e| DbError::Data(format!("Failed to get position: {}", e)))?,
-            heading: row
-                .get(7)
-                .map_err(|e| DbError::Data(format!("Failed to get heading: {}", e)))?,
-        })
+        Ok(())
     }
 
-    /// Reembed all chunks in the index with new embeddings
-    ///
-    /// This method retrieves all chunks from the database, regenerates embeddings for each chunk,
-    /// and updates the database with the new embeddings. This is useful when changing the embedding model.
+    /// Record the redaction of a page in the audit log
     ///
     /// # Arguments
     ///
-    /// * `client` - The Gemini client to use for generating embeddings
-    /// * `concurrency` - Maximum number of concurrent embedding operations
-    /// * `source_filter` - Optional filter by source domain
-    /// * `progress_sender` - Optional channel sender for progress updates
-    ///
+    /// * `url` - URL of the page; its website must already be indexed
+    /// * `redaction` - The redaction step that ran
+    /// * `counts` - Number of values masked
+    pub async fn record_redactions(
+        &self,
+        url: &str,
+        redaction: Redaction,
+        counts: &RedactionCounts,
+    ) -> Result<(), DbError> {
+        let website_id = match self.get_website_by_page_url(url).await? {
+            Some(id) => id,
+            None => return Err(DbError::Data(format!("Website not found for URL: {}", url))),
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        self.conn
+            .execute(
+                "INSERT INTO redaction_log (website_id, url, redaction, emails, phone_numbers, secrets, llm, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    website_id,
+                    url,
+                    redaction.to_string(),
+                    counts.emails as i64,
+                    counts.phone_numbers as i64,
+                    counts.secrets as i64,
+                    counts.llm as i64,
+                    now
+                ],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to record redactions: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Get the redaction audit log of a website, most recent first
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website
+    pub async fn get_redaction_log(&self, domain: &str) -> Result<Vec<RedactionLogEntry>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT r.url, r.redaction, r.emails, r.phone_numbers, r.secrets, r.llm, r.created_at
+                 FROM redaction_log r
+                 JOIN websites w ON r.website_id = w.id
+                 WHERE w.domain = ?
+                 ORDER BY r.created_at DESC, r.id DESC",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get redaction log: {}", e)))?;
+
+        let mut entries = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let count = |index: i32| {
+                row.get::<i64>(index)
+                    .map(|count| count as usize)
+                    .map_err(|e| DbError::Data(format!("Failed to get redaction count: {}", e)))
+            };
+            let redaction: String = row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get redaction: {}", e)))?;
+            entries.push(RedactionLogEntry {
+                url: row
+                    .get(0)
+                    .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
+                redaction: redaction.parse().map_err(DbError::Data)?,
+                counts: RedactionCounts {
+                    emails: count(2)?,
+                    phone_numbers: count(3)?,
+                    secrets: count(4)?,
+                    llm: count(5)?,
+                },
+                created_at: row
+                    .get(6)
+                    .map_err(|e| DbError::Data(format!("Failed to get created_at: {}", e)))?,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Hold a page or chunk that failed a safety check for review
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the page
+    /// * `position` - Position of the chunk in the page, or `None` for the whole page
+    /// * `text` - The content that failed the check
+    /// * `reasons` - Why the content failed
+    /// * `action` - Whether the content was dropped or indexed with a flag
+    ///
     /// # Returns
     ///
-    /// The number of chunks that were reembedded
-    #[instrument(skip(self, client, progress_sender))]
-    pub async fn reembed_all_chunks<'a, C, E>(
-        &'a self,
-        client: &'a crate::model::Client<C, E>,
-        concurrency: usize,
-        source_filter: Option<String>,
-        progress_sender: Option<tokio::sync::mpsc::Sender<(i64, String)>>,
-    ) -> Result<usize, DbError>
-    where
-        C: rig::completion::CompletionModel + Send + Sync + 'static,
-        E: rig::embeddings::EmbeddingModel + Send + Sync + 'static,
-    {
-        use crate::processor::generate_combined_embedding;
-        use futures::future;
-        use std::sync::Arc;
-        use tokio::sync::Semaphore;
-        use tracing::{debug, info};
+    /// The ID of the quarantine entry
+    pub async fn quarantine(
+        &self,
+        url: &str,
+        position: Option<i64>,
+        text: &str,
+        reasons: &[String],
+        action: SafetyAction,
+    ) -> Result<i64, DbError> {
+        let domain = url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let reasons = serde_json::to_string(reasons)
+            .map_err(|e| DbError::Data(format!("Failed to serialize reasons: {}", e)))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-        // Get all chunks from the database
+        let mut rows = self
+            .conn
+            .query(
+                "INSERT INTO quarantine (domain, url, position, text, reasons, action, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)
+                 RETURNING id",
+                params![
+                    domain,
+                    url,
+                    position,
+                    text,
+                    reasons,
+                    action.to_string(),
+                    now
+                ],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to quarantine content: {}", e)))?;
+
+        match rows.next().await {
+            Ok(Some(row)) => row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get ID: {}", e))),
+            Ok(None) => Err(DbError::Data(
+                "No ID returned for quarantine entry".to_string(),
+            )),
+            Err(e) => Err(DbError::Data(format!("Failed to get ID: {}", e))),
+        }
+    }
+
+    /// Get the content held in quarantine, oldest first
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Only return entries of this domain
+    pub async fn get_quarantine(
+        &self,
+        domain: Option<&str>,
+    ) -> Result<Vec<QuarantineEntry>, DbError> {
         let mut sql = String::from(
-            "SELECT c.id, c.website_id, c.url, c.text, c.context, c.embedding, c.position, c.heading
-             FROM chunks c
-             JOIN websites w ON c.website_id = w.id",
+            "SELECT id, url, position, text, reasons, action, created_at FROM quarantine",
         );
-
-        // Add source filter if specified
         let mut params: Vec<libsql::Value> = Vec::new();
-        if let Some(source) = &source_filter {
-            sql.push_str(" WHERE w.domain LIKE ?");
-            params.push(format!("%{}%", source).into());
+        if let Some(domain) = domain {
+            sql.push_str(" WHERE domain = ?");
+            params.push(domain.to_string().into());
         }
+        sql.push_str(" ORDER BY id");
 
-        // Execute query
         let mut rows = self
             .conn
             .query(&sql, params)
             .await
-            .map_err(|e| DbError::Query(format!("Failed to query chunks: {}", e)))?;
+            .map_err(|e| DbError::Query(format!("Failed to get quarantine: {}", e)))?;
 
-        // Convert rows to chunks
-        let mut chunks = Vec::new();
+        let mut entries = Vec::new();
         while let Ok(Some(row)) = rows.next().await {
-            chunks.push(self.row_to_chunk(&row)?);
+            entries.push(row_to_quarantine_entry(&row)?);
         }
+        Ok(entries)
+    }
 
-        info!("Found {} chunks to reembed", chunks.len());
+    /// Remove an entry from quarantine once it has been reviewed
+    ///
+    /// # Returns
+    ///
+    /// The removed entry, or `None` if there is no entry with the ID
+    pub async fn remove_quarantine_entry(
+        &self,
+        id: i64,
+    ) -> Result<Option<QuarantineEntry>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "DELETE FROM quarantine WHERE id = ?
+                 RETURNING id, url, position, text, reasons, action, created_at",
+                params![id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to remove quarantine entry: {}", e)))?;
 
-        // Create semaphore for limiting concurrency
-        let semaphore = Arc::new(Semaphore::new(concurrency));
-        let mut tasks = Vec::new();
+        match rows.next().await {
+            Ok(Some(row)) => Ok(Some(row_to_quarantine_entry(&row)?)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(DbError::Data(format!(
+                "Failed to remove quarantine entry: {}",
+                e
+            ))),
+        }
+    }
 
-        // Process chunks in parallel with bounded concurrency
-        for chunk in chunks {
-            let permit = semaphore.clone().acquire_owned();
-            let client = client.clone();
-            let db = self.clone();
-            let progress_sender = progress_sender.clone();
-            let chunk_id = chunk.id;
-            let chunk_url = chunk.url.clone();
+    /// Delete the chunk at a position of a page
+    ///
+    /// # Returns
+    ///
+    /// The number of chunks deleted
+    pub async fn delete_chunk_at(&self, page_url: &str, position: i64) -> Result<usize, DbError> {
+        let deleted = self
+            .conn
+            .execute(
+                "DELETE FROM chunks WHERE url = ? AND position = ?",
+                params![page_url, position],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to delete chunk: {}", e)))?;
 
-            let task = tokio::spawn(async move {
-                let _permit = permit
-                    .await
-                    .map_err(|e| DbError::Other(format!("Failed to acquire semaphore: {}", e)))?;
+        Ok(deleted as usize)
+    }
 
-                debug!("Reembedding chunk {} from {}", chunk_id, chunk_url);
+    /// Get the stored raw HTML of the pages of a website
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website
+    ///
+    /// # Returns
+    ///
+    /// Pairs of page URL and decompressed HTML
+    pub async fn get_raw_html_by_domain(
+        &self,
+        domain: &str,
+    ) -> Result<Vec<(String, String)>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT r.url, r.html FROM raw_pages r
+                 JOIN websites w ON r.website_id = w.id
+                 WHERE w.domain = ?
+                 ORDER BY r.url",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get raw HTML: {}", e)))?;
+
+        let mut pages = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let url: String = row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?;
+            let blob: Vec<u8> = row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get html: {}", e)))?;
+            let html = String::from_utf8(compression::decompress(&blob)?)
+                .map_err(|e| DbError::Data(format!("Stored HTML is not UTF-8: {}", e)))?;
+            pages.push((url, html));
+        }
+
+        Ok(pages)
+    }
+
+    /// The chunks of each website grouped by the processor settings that built them
+    ///
+    /// A website with more than one group was indexed with different settings over time.
+    /// Groups are ordered by domain, the most recently indexed first.
+    pub async fn chunk_provenance(&self) -> Result<Vec<ChunkProvenance>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT w.domain, c.config_hash, COUNT(DISTINCT c.url), COUNT(*),
+                        MIN(c.crawl_id), MAX(c.crawl_id), MAX(c.indexed_at)
+                 FROM chunks c JOIN websites w ON c.website_id = w.id
+                 GROUP BY w.id, c.config_hash
+                 ORDER BY w.domain, 7 DESC",
+                params![],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get chunk provenance: {}", e)))?;
+
+        let mut provenance = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let data = |e: libsql::Error| DbError::Data(format!("Failed to get provenance: {}", e));
+            provenance.push(ChunkProvenance {
+                domain: row.get(0).map_err(data)?,
+                config_hash: row.get(1).map_err(data)?,
+                pages: row.get(2).map_err(data)?,
+                chunks: row.get(3).map_err(data)?,
+                first_crawl_id: row.get(4).map_err(data)?,
+                last_crawl_id: row.get(5).map_err(data)?,
+                last_indexed_at: row.get(6).map_err(data)?,
+            });
+        }
+
+        Ok(provenance)
+    }
+
+    /// URLs of the pages of a website with chunks built by other processor settings
+    ///
+    /// Chunks indexed before their settings were recorded count as outdated.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website
+    /// * `config_hash` - Fingerprint of the current settings, see
+    ///   `ProcessorConfig::fingerprint`
+    pub async fn outdated_pages(
+        &self,
+        domain: &str,
+        config_hash: &str,
+    ) -> Result<Vec<String>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT DISTINCT c.url FROM chunks c
+                 JOIN websites w ON c.website_id = w.id
+                 WHERE w.domain = ? AND c.config_hash IS NOT ?
+                 ORDER BY c.url",
+                params![domain, config_hash],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get outdated pages: {}", e)))?;
+
+        let mut urls = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            urls.push(
+                row.get(0)
+                    .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
+            );
+        }
+
+        Ok(urls)
+    }
+
+    /// Record the pages found by a crawl of a website
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the crawled website
+    /// * `urls` - URLs of the pages that exist as of this crawl
+    /// * `complete` - Whether the crawl covered the whole site, i.e. did not stop at a page limit
+    ///   or skip the links of unchanged pages
+    ///
+    /// # Returns
+    ///
+    /// The ID of the new crawl
+    #[instrument(skip(self, urls))]
+    pub async fn record_crawl(
+        &self,
+        domain: &str,
+        urls: &[String],
+        complete: bool,
+    ) -> Result<i64, DbError> {
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to start transaction: {}", e)))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let mut rows = tx
+            .query(
+                "INSERT INTO crawls (domain, complete, page_count, created_at)
+                 VALUES (?, ?, ?, ?) RETURNING id",
+                params![domain, complete as i64, urls.len() as i64, now],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to record crawl: {}", e)))?;
+        let crawl_id: i64 = match rows.next().await {
+            Ok(Some(row)) => row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get crawl ID: {}", e)))?,
+            Ok(None) => return Err(DbError::Data("Failed to get crawl ID".to_string())),
+            Err(e) => return Err(DbError::Data(format!("Failed to get crawl ID: {}", e))),
+        };
+        drop(rows);
+
+        for url in urls {
+            tx.execute(
+                "INSERT OR IGNORE INTO crawl_pages (crawl_id, url) VALUES (?, ?)",
+                params![crawl_id, url.as_str()],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to record crawl page: {}", e)))?;
+        }
+
+        // Chunks indexed since the previous crawl of the website were built by this one
+        tx.execute(
+            "UPDATE chunks SET crawl_id = ?1
+             WHERE crawl_id IS NULL
+             AND url IN (SELECT url FROM crawl_pages WHERE crawl_id = ?1)",
+            params![crawl_id],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to record chunk crawls: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(crawl_id)
+    }
+
+    /// Remove the chunks of pages the latest crawls of a website no longer found
+    ///
+    /// Pages are compared against the latest complete crawl together with any partial
+    /// crawls after it, so pages first found by a partial crawl are kept. Nothing is
+    /// removed for websites without a complete crawl.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website
+    /// * `dry_run` - Only list the stale pages without deleting their chunks
+    ///
+    /// # Returns
+    ///
+    /// The stale pages and the number of chunks deleted
+    #[instrument(skip(self))]
+    pub async fn collect_garbage(
+        &self,
+        domain: &str,
+        dry_run: bool,
+    ) -> Result<GarbageCollection, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id FROM crawls WHERE domain = ? AND complete = 1 ORDER BY id DESC LIMIT 1",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get latest crawl: {}", e)))?;
+        let crawl_id: i64 = match rows.next().await {
+            Ok(Some(row)) => row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get crawl ID: {}", e)))?,
+            Ok(None) => return Ok(GarbageCollection::default()),
+            Err(e) => return Err(DbError::Data(format!("Failed to get latest crawl: {}", e))),
+        };
+
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT DISTINCT c.url FROM chunks c
+                 JOIN websites w ON c.website_id = w.id
+                 WHERE w.domain = ?1 AND c.url NOT IN (
+                     SELECT p.url FROM crawl_pages p
+                     JOIN crawls r ON p.crawl_id = r.id
+                     WHERE r.domain = ?1 AND r.id >= ?2
+                 )
+                 ORDER BY c.url",
+                params![domain, crawl_id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get stale pages: {}", e)))?;
+
+        let mut stale_pages = Vec::<String>::new();
+        while let Ok(Some(row)) = rows.next().await {
+            stale_pages.push(
+                row.get(0)
+                    .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
+            );
+        }
+
+        let mut deleted_chunks = 0;
+        if !dry_run {
+            for url in &stale_pages {
+                deleted_chunks += self.delete_chunks_by_page_url(url).await?;
+            }
+        }
+
+        Ok(GarbageCollection {
+            crawl_id: Some(crawl_id),
+            stale_pages,
+            deleted_chunks,
+        })
+    }
+
+    /// Drop the least recently used chunks beyond the limits of a retention policy
+    ///
+    /// Chunks never retrieved count as used when they were indexed, or for chunks indexed
+    /// before that was recorded, when their website was last indexed. The stored HTML of
+    /// pages left without chunks is deleted with them, and the database is vacuumed when
+    /// chunks were dropped to fit its size limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The limits to enforce
+    /// * `dry_run` - Only select the chunks without deleting them
+    ///
+    /// # Returns
+    ///
+    /// The dropped chunks and why they were dropped
+    #[instrument(skip(self))]
+    pub async fn prune(&self, policy: &RetentionPolicy, dry_run: bool) -> Result<Pruning, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT c.id, c.website_id, c.url,
+                        COALESCE(c.last_retrieved_at, c.indexed_at, w.last_index_date)
+                 FROM chunks c JOIN websites w ON c.website_id = w.id",
+                params![],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get chunk usage: {}", e)))?;
+
+        let mut chunks = Vec::new();
+        let mut urls = HashMap::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let chunk = ChunkUsage {
+                id: row
+                    .get(0)
+                    .map_err(|e| DbError::Data(format!("Failed to get chunk ID: {}", e)))?,
+                website_id: row
+                    .get(1)
+                    .map_err(|e| DbError::Data(format!("Failed to get website ID: {}", e)))?,
+                last_used: row
+                    .get(3)
+                    .map_err(|e| DbError::Data(format!("Failed to get last use: {}", e)))?,
+            };
+            let url: String = row
+                .get(2)
+                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?;
+            urls.insert(chunk.id, url);
+            chunks.push(chunk);
+        }
+
+        let used_bytes = if policy.max_database_mb.is_some() {
+            self.used_bytes().await?
+        } else {
+            0
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let pruning = select_pruned(policy, &chunks, now, used_bytes);
+        debug!("Pruning {} chunks", pruning.chunk_ids.len());
+        if dry_run || pruning.chunk_ids.is_empty() {
+            return Ok(pruning);
+        }
+
+        for ids in pruning.chunk_ids.chunks(500) {
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            let params: Vec<libsql::Value> = ids.iter().map(|id| (*id).into()).collect();
+            self.conn
+                .execute(
+                    &format!("DELETE FROM chunks WHERE id IN ({})", placeholders),
+                    params,
+                )
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to delete chunks: {}", e)))?;
+        }
+
+        let mut pruned_urls: Vec<&String> = pruning
+            .chunk_ids
+            .iter()
+            .filter_map(|id| urls.get(id))
+            .collect();
+        pruned_urls.sort();
+        pruned_urls.dedup();
+        for url in pruned_urls {
+            self.conn
+                .execute(
+                    "DELETE FROM raw_pages WHERE url = ?1
+                     AND NOT EXISTS (SELECT 1 FROM chunks WHERE url = ?1)",
+                    params![url.as_str()],
+                )
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to delete raw HTML: {}", e)))?;
+        }
+
+        if pruning.oversize_chunks > 0 {
+            self.conn
+                .execute("VACUUM", params![])
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to vacuum database: {}", e)))?;
+        }
+
+        Ok(pruning)
+    }
+
+    /// Bytes of the database in use, without free pages
+    async fn used_bytes(&self) -> Result<u64, DbError> {
+        let mut used = Vec::new();
+        for pragma in ["page_count", "freelist_count", "page_size"] {
+            let mut rows = self
+                .conn
+                .query(&format!("PRAGMA {}", pragma), params![])
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to get {}: {}", pragma, e)))?;
+            let value: i64 = match rows.next().await {
+                Ok(Some(row)) => row
+                    .get(0)
+                    .map_err(|e| DbError::Data(format!("Failed to get {}: {}", pragma, e)))?,
+                _ => 0,
+            };
+            used.push(value.max(0) as u64);
+        }
+        Ok(used[0].saturating_sub(used[1]) * used[2])
+    }
+
+    /// Get the git commit a repository source was last indexed at
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website, the corpus name for local sources
+    ///
+    /// # Returns
+    ///
+    /// The commit SHA, or `None` if the website is unknown or was not indexed from git
+    pub async fn get_indexed_commit(&self, domain: &str) -> Result<Option<String>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT indexed_commit FROM websites
+                 WHERE domain = ? AND indexed_commit IS NOT NULL
+                 ORDER BY last_index_date DESC LIMIT 1",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get indexed commit: {}", e)))?;
+
+        match rows.next().await {
+            Ok(Some(row)) => Ok(Some(row.get(0).map_err(|e| {
+                DbError::Data(format!("Failed to get indexed_commit: {}", e))
+            })?)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(DbError::Data(format!(
+                "Failed to get indexed commit: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Record the git commit a repository source was indexed at
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website, the corpus name for local sources
+    /// * `commit` - SHA of the indexed commit
+    ///
+    /// # Returns
+    ///
+    /// Whether a website was updated; websites are created when their first page is indexed
+    #[instrument(skip(self))]
+    pub async fn set_indexed_commit(&self, domain: &str, commit: &str) -> Result<bool, DbError> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE websites SET indexed_commit = ? WHERE domain = ?",
+                params![commit, domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to set indexed commit: {}", e)))?;
+
+        Ok(updated > 0)
+    }
+
+    /// Store the cache validators of a crawled page
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the page
+    /// * `validators` - Its `ETag` and `Last-Modified` headers
+    pub async fn store_http_validators(
+        &self,
+        url: &str,
+        validators: &HttpValidators,
+    ) -> Result<(), DbError> {
+        let domain = url
+            .parse::<url::Url>()
+            .map_err(|e| DbError::Data(format!("Failed to parse URL: {}", e)))?
+            .host_str()
+            .ok_or_else(|| DbError::Data("URL has no host".to_string()))?
+            .to_string();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO http_cache (url, domain, etag, last_modified, checked_at)
+                 VALUES (?, ?, ?, ?, ?)",
+                params![
+                    url,
+                    domain,
+                    validators.etag.clone(),
+                    validators.last_modified.clone(),
+                    now
+                ],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to store HTTP validators: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Get the cache validators of the indexed pages of a website
+    ///
+    /// Pages whose chunks were removed are left out, so they are fetched again.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the website
+    ///
+    /// # Returns
+    ///
+    /// The validators by page URL
+    pub async fn get_http_cache(
+        &self,
+        domain: &str,
+    ) -> Result<HashMap<String, HttpValidators>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT h.url, h.etag, h.last_modified FROM http_cache h
+                 WHERE h.domain = ? AND EXISTS (SELECT 1 FROM chunks c WHERE c.url = h.url)",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get HTTP cache: {}", e)))?;
+
+        let mut cache = HashMap::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let url: String = row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?;
+            let validators = HttpValidators {
+                etag: row
+                    .get(1)
+                    .map_err(|e| DbError::Data(format!("Failed to get etag: {}", e)))?,
+                last_modified: row
+                    .get(2)
+                    .map_err(|e| DbError::Data(format!("Failed to get last_modified: {}", e)))?,
+            };
+            cache.insert(url, validators);
+        }
+
+        Ok(cache)
+    }
+
+    /// Convert a database row to a Website
+    fn row_to_website(&self, row: &Row) -> Result<Website, DbError> {
+        Ok(Website {
+            id: row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?,
+            url: row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
+            domain: row
+                .get(2)
+                .map_err(|e| DbError::Data(format!("Failed to get domain: {}", e)))?,
+            first_index_date: row
+                .get(3)
+                .map_err(|e| DbError::Data(format!("Failed to get first_index_date: {}", e)))?,
+            last_index_date: row
+                .get(4)
+                .map_err(|e| DbError::Data(format!("Failed to get last_index_date: {}", e)))?,
+            page_count: row
+                .get(5)
+                .map_err(|e| DbError::Data(format!("Failed to get page_count: {}", e)))?,
+            status: row
+                .get(6)
+                .map_err(|e| DbError::Data(format!("Failed to get status: {}", e)))?,
+        })
+    }
+
+    /// Convert a database row to an IndexedChunk
+    ///
+    /// Text and context are decompressed if the collection is compressed.
+    async fn row_to_chunk(&self, row: &Row) -> Result<IndexedChunk, DbError> {
+        // Get the embedding as a binary blob and convert it to Vec<f32>
+        let embedding_blob: Vec<u8> = row
+            .get(5)
+            .map_err(|e| DbError::Data(format!("Failed to get embedding: {}", e)))?;
+
+        // Convert the blob to Vec<f32> using EmbeddingConversion trait
+        let embedding: Embedding = EmbeddingConversion::from_binary(&embedding_blob);
+
+        Ok(IndexedChunk {
+            id: row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?,
+            website_id: row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get website_id: {}", e)))?,
+            url: row
+                .get(2)
+                .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
+            text: self
+                .decode_text(
+                    row.get_value(3)
+                        .map_err(|e| DbError::Data(format!("Failed to get text: {}", e)))?,
+                )
+                .await?,
+            context: self
+                .decode_text(
+                    row.get_value(4)
+                        .map_err(|e| DbError::Data(format!("Failed to get context: {}", e)))?,
+                )
+                .await?,
+            embedding,
+            position: row
+                .get(6)
+                .map_err(|e| DbError::Data(format!("Failed to get position: {}", e)))?,
+            heading: row
+                .get(7)
+                .map_err(|e| DbError::Data(format!("Failed to get heading: {}", e)))?,
+            heading_path: row
+                .get(8)
+                .map_err(|e| DbError::Data(format!("Failed to get heading_path: {}", e)))?,
+            author: row
+                .get(9)
+                .map_err(|e| DbError::Data(format!("Failed to get author: {}", e)))?,
+            published_at: row
+                .get(10)
+                .map_err(|e| DbError::Data(format!("Failed to get published_at: {}", e)))?,
+            doc_version: row
+                .get(11)
+                .map_err(|e| DbError::Data(format!("Failed to get doc_version: {}", e)))?,
+            content_type: row
+                .get(12)
+                .map_err(|e| DbError::Data(format!("Failed to get content_type: {}", e)))?,
+        })
+    }
+
+    /// Reembed all chunks in the index with new embeddings
+    ///
+    /// This method retrieves all chunks from the database, regenerates embeddings for each chunk,
+    /// and updates the database with the new embeddings. This is useful when changing the embedding model.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The Gemini client to use for generating embeddings
+    /// * `concurrency` - Maximum number of concurrent embedding operations
+    /// * `source_filter` - Optional filter by source domain
+    /// * `progress_sender` - Optional channel sender for progress updates
+    /// * `cancel` - Token skipping the chunks not started yet; updated chunks are kept
+    ///
+    /// # Returns
+    ///
+    /// The number of chunks that were reembedded
+    #[instrument(skip(self, client, progress_sender, cancel))]
+    pub async fn reembed_all_chunks<'a, C, E>(
+        &'a self,
+        client: &'a crate::model::Client<C, E>,
+        concurrency: usize,
+        source_filter: Option<String>,
+        progress_sender: Option<tokio::sync::mpsc::Sender<(i64, String)>>,
+        cancel: crate::cancel::CancellationToken,
+    ) -> Result<usize, DbError>
+    where
+        C: rig::completion::CompletionModel + Send + Sync + 'static,
+        E: rig::embeddings::EmbeddingModel + Send + Sync + 'static,
+    {
+        use crate::processor::generate_combined_embedding;
+        use futures::future;
+        use std::sync::Arc;
+        use tokio::sync::Semaphore;
+        use tracing::{debug, info};
+
+        // Get all chunks from the database
+        let mut sql = String::from(
+            "SELECT c.id, c.website_id, c.url, c.text, c.context, c.embedding, c.position, c.heading, c.heading_path, c.author, c.published_at, c.doc_version, c.content_type
+             FROM chunks c
+             JOIN websites w ON c.website_id = w.id",
+        );
+
+        // Add source filter if specified
+        let mut params: Vec<libsql::Value> = Vec::new();
+        if let Some(source) = &source_filter {
+            sql.push_str(" WHERE w.domain LIKE ?");
+            params.push(format!("%{}%", source).into());
+        }
+
+        // Execute query
+        let mut rows = self
+            .conn
+            .query(&sql, params)
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to query chunks: {}", e)))?;
+
+        // Convert rows to chunks
+        let mut chunks = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            chunks.push(self.row_to_chunk(&row).await?);
+        }
+
+        info!("Found {} chunks to reembed", chunks.len());
+
+        // Create semaphore for limiting concurrency
+        let semaphore = Arc::new(Semaphore::new(concurrency));
+        let mut tasks = Vec::new();
+
+        // Process chunks in parallel with bounded concurrency
+        for chunk in chunks {
+            let permit = semaphore.clone().acquire_owned();
+            let client = client.clone();
+            let db = self.clone();
+            let progress_sender = progress_sender.clone();
+            let chunk_id = chunk.id;
+            let chunk_url = chunk.url.clone();
+            let cancel = cancel.clone();
+
+            let task = tokio::spawn(async move {
+                let _permit = permit
+                    .await
+                    .map_err(|e| DbError::Other(format!("Failed to acquire semaphore: {}", e)))?;
+
+                // Each chunk is updated on its own, so skipping the rest leaves no partial work
+                if cancel.is_cancelled() {
+                    return Ok(None);
+                }
+
+                debug!("Reembedding chunk {} from {}", chunk_id, chunk_url);
+
+                // Generate new embedding using combined text and context
+                let new_embedding =
+                    generate_combined_embedding(&client, &chunk.text, &chunk.context)
+                        .await
+                        .map_err(|e| {
+                            DbError::Other(format!("Failed to generate embedding: {}", e))
+                        })?;
+
+                // Update chunk in database
+                db.update_chunk_embedding(chunk_id, &new_embedding.to_vec())
+                    .await?;
+
+                // Send progress update if sender is provided
+                if let Some(sender) = progress_sender {
+                    // Ignore errors from sending (e.g., if receiver is dropped)
+                    let _ = sender.send((chunk_id, chunk_url)).await;
+                }
+
+                Ok::<Option<i64>, DbError>(Some(chunk_id))
+            });
+
+            tasks.push(task);
+        }
+
+        // Store the number of tasks
+        let task_count = tasks.len();
+
+        // Wait for all tasks to complete
+        let results = future::join_all(tasks).await;
+
+        // Count successful updates
+        let mut success_count = 0;
+        let mut skipped_count = 0;
+        for result in results {
+            match result {
+                Ok(Ok(Some(_))) => success_count += 1,
+                Ok(Ok(None)) => skipped_count += 1,
+                Ok(Err(e)) => {
+                    debug!("Failed to reembed chunk: {}", e);
+                }
+                Err(e) => {
+                    debug!("Task failed: {}", e);
+                }
+            }
+        }
+
+        info!(
+            "Successfully reembedded {}/{} chunks",
+            success_count, task_count
+        );
+        if skipped_count > 0 {
+            info!("Skipped {} chunks after cancellation", skipped_count);
+        }
+
+        Ok(success_count)
+    }
+
+    /// Update the embedding for a chunk
+    async fn update_chunk_embedding(
+        &self,
+        chunk_id: i64,
+        embedding: &[f32],
+    ) -> Result<(), DbError> {
+        // Convert embedding to binary blob
+        let embedding_blob: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+
+        // Keep the quantized copy in sync for quantized collections
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT w.quantization FROM chunks c JOIN websites w ON c.website_id = w.id WHERE c.id = ?",
+                params![chunk_id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get quantization: {}", e)))?;
+        let quantization = match rows.next().await {
+            Ok(Some(row)) => parse_quantization(row.get::<String>(0).ok()),
+            _ => Quantization::Float32,
+        };
+        let quantized = match quantization.quantize(embedding) {
+            Some(encoded) => libsql::Value::Blob(encoded),
+            None => libsql::Value::Null,
+        };
+
+        // Update the chunk in the database
+        self.conn
+            .execute(
+                "UPDATE chunks SET embedding = ?, embedding_q = ? WHERE id = ?",
+                params![libsql::Value::Blob(embedding_blob), quantized, chunk_id,],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to update chunk embedding: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Change the quantization of the collections of a domain
+    ///
+    /// Stores a quantized copy of every chunk embedding, or removes the copies when
+    /// converting back to `Quantization::Float32`. Full precision embeddings are kept
+    /// for rescoring.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the websites to convert
+    /// * `quantization` - The new quantization mode
+    ///
+    /// # Returns
+    ///
+    /// The number of chunks converted
+    #[instrument(skip(self))]
+    pub async fn quantize_website(
+        &self,
+        domain: &str,
+        quantization: Quantization,
+    ) -> Result<usize, DbError> {
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute(
+            "UPDATE websites SET quantization = ? WHERE domain = ?",
+            params![quantization.as_str(), domain],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to update quantization: {}", e)))?;
+
+        let mut rows = tx
+            .query(
+                "SELECT c.id, c.embedding FROM chunks c
+                 JOIN websites w ON c.website_id = w.id
+                 WHERE w.domain = ?",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get chunks: {}", e)))?;
+
+        let mut converted = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let id: i64 = row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?;
+            let blob: Vec<u8> = row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get embedding: {}", e)))?;
+            let embedding: Embedding = EmbeddingConversion::from_binary(&blob);
+            converted.push((id, quantized_blob(quantization, &embedding)));
+        }
+
+        for (id, quantized) in &converted {
+            tx.execute(
+                "UPDATE chunks SET embedding_q = ? WHERE id = ?",
+                params![quantized.clone(), *id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to update chunk: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(converted.len())
+    }
+
+    /// Decode a stored chunk text or context
+    ///
+    /// Plain values are returned as they are. Compressed values are decompressed with the
+    /// dictionary their zstd frame refers to, which is loaded once and then cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value of a `text` or `context` column
+    ///
+    /// # Returns
+    ///
+    /// The text, or an empty string for NULL
+    pub async fn decode_text(&self, value: libsql::Value) -> Result<String, DbError> {
+        let frame = match value {
+            libsql::Value::Text(text) => return Ok(text),
+            libsql::Value::Null => return Ok(String::new()),
+            libsql::Value::Blob(frame) => frame,
+            other => {
+                return Err(DbError::Data(format!(
+                    "Unexpected value in text column: {:?}",
+                    other
+                )));
+            }
+        };
+
+        let id = compression::frame_dictionary_id(&frame).ok_or_else(|| {
+            DbError::Data("Compressed text does not name its dictionary".to_string())
+        })?;
+        let dictionary = match self.dictionaries.get(id) {
+            Some(dictionary) => dictionary,
+            None => {
+                let mut rows = self
+                    .conn
+                    .query(
+                        "SELECT dictionary FROM compression_dictionaries WHERE id = ?",
+                        params![id as i64],
+                    )
+                    .await
+                    .map_err(|e| DbError::Query(format!("Failed to get dictionary: {}", e)))?;
+                let row = rows
+                    .next()
+                    .await
+                    .map_err(|e| DbError::Data(format!("Failed to get dictionary: {}", e)))?
+                    .ok_or_else(|| DbError::Data(format!("Dictionary {} not found", id)))?;
+                let dictionary: Vec<u8> = row
+                    .get(0)
+                    .map_err(|e| DbError::Data(format!("Failed to get dictionary: {}", e)))?;
+                self.dictionaries.insert(id, dictionary)
+            }
+        };
+
+        compression::decompress_text(&frame, &dictionary)
+    }
+
+    /// Compress or decompress the chunk text and context of the collections of a domain
+    ///
+    /// Enabling compression trains a zstd dictionary on the collection's own chunks and
+    /// rewrites every text and context with it; chunks indexed later are compressed with
+    /// the same dictionary. Running it again retrains the dictionary on the current
+    /// content. Disabling compression restores plain text and drops the dictionaries.
+    /// Chunk summaries are stored in the context column and are compressed with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain of the websites to convert
+    /// * `enabled` - Whether to compress or to restore plain text
+    ///
+    /// # Returns
+    ///
+    /// The number of chunks converted
+    #[instrument(skip(self))]
+    pub async fn compress_website(&self, domain: &str, enabled: bool) -> Result<usize, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT c.id, c.website_id, c.text, c.context FROM chunks c
+                 JOIN websites w ON c.website_id = w.id
+                 WHERE w.domain = ?",
+                params![domain],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to get chunks: {}", e)))?;
+
+        let mut chunks: Vec<(i64, i64, String, String)> = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let value = |index: i32| {
+                row.get_value(index)
+                    .map_err(|e| DbError::Data(format!("Failed to get chunk: {}", e)))
+            };
+            let id: i64 = row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?;
+            let website_id: i64 = row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get website_id: {}", e)))?;
+            let text = self.decode_text(value(2)?).await?;
+            let context = self.decode_text(value(3)?).await?;
+            chunks.push((id, website_id, text, context));
+        }
+
+        let mut website_ids: Vec<i64> = chunks.iter().map(|chunk| chunk.1).collect();
+        website_ids.sort_unstable();
+        website_ids.dedup();
+
+        let mut dictionaries = std::collections::HashMap::new();
+        if enabled {
+            for website_id in &website_ids {
+                let samples: Vec<String> = chunks
+                    .iter()
+                    .filter(|chunk| chunk.1 == *website_id)
+                    .flat_map(|chunk| [chunk.2.clone(), chunk.3.clone()])
+                    .filter(|sample| !sample.is_empty())
+                    .collect();
+                dictionaries.insert(*website_id, compression::train_dictionary(&samples)?);
+            }
+        }
+
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to start transaction: {}", e)))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        for website_id in &website_ids {
+            tx.execute(
+                "DELETE FROM compression_dictionaries WHERE website_id = ?",
+                params![*website_id],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to delete dictionaries: {}", e)))?;
+
+            if let Some(dictionary) = dictionaries.get(website_id) {
+                let id = compression::dictionary_id(dictionary).ok_or_else(|| {
+                    DbError::Data("Trained dictionary has no dictionary ID".to_string())
+                })?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO compression_dictionaries (id, website_id, dictionary, created_at)
+                     VALUES (?, ?, ?, ?)",
+                    params![id as i64, *website_id, dictionary.clone(), now],
+                )
+                .await
+                .map_err(|e| DbError::Query(format!("Failed to store dictionary: {}", e)))?;
+            }
+        }
+
+        for (id, website_id, text, context) in &chunks {
+            let dictionary = dictionaries.get(website_id).map(|d| d.as_slice());
+            tx.execute(
+                "UPDATE chunks SET text = ?, context = ? WHERE id = ?",
+                params![
+                    encode_text(text, dictionary)?,
+                    encode_text(context, dictionary)?,
+                    *id
+                ],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to update chunk: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Transaction(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(chunks.len())
+    }
+
+    /// Whether the approximate nearest neighbor index over chunk embeddings exists
+    pub async fn has_vector_index(&self) -> Result<bool, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'chunks_idx'",
+                params![],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to inspect indexes: {}", e)))?;
+
+        Ok(matches!(rows.next().await, Ok(Some(_))))
+    }
+
+    /// Drop and rebuild the approximate nearest neighbor index over chunk embeddings
+    ///
+    /// This is libsql's DiskANN index `chunks_idx` from the schema, which libsql maintains
+    /// incrementally on insert and delete; a rebuild compacts it after large deletions or
+    /// recreates it if it was dropped or failed to build.
+    #[instrument(skip(self))]
+    pub async fn rebuild_vector_index(&self) -> Result<(), DbError> {
+        self.conn
+            .execute("DROP INDEX IF EXISTS chunks_idx", params![])
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to drop vector index: {}", e)))?;
+
+        schema::create_vector_index(&self.conn).await
+    }
+
+    /// Find the chunks of quantized collections most similar to a query
+    ///
+    /// Scores the quantized embeddings only; callers rescore the returned candidates
+    /// with the full precision embeddings.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The prepared query embedding
+    /// * `k` - Number of candidates to return
+    ///
+    /// # Returns
+    ///
+    /// Chunk IDs ordered from most to least similar
+    pub async fn quantized_candidates(
+        &self,
+        query: &QueryVector,
+        k: usize,
+    ) -> Result<Vec<i64>, DbError> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT c.id, c.embedding_q, w.quantization FROM chunks c
+                 JOIN websites w ON c.website_id = w.id
+                 WHERE w.quantization != 'f32' AND c.embedding_q IS NOT NULL",
+                params![],
+            )
+            .await
+            .map_err(|e| DbError::Query(format!("Failed to scan quantized chunks: {}", e)))?;
+
+        let mut vectors = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let id: i64 = row
+                .get(0)
+                .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?;
+            let encoded: Vec<u8> = row
+                .get(1)
+                .map_err(|e| DbError::Data(format!("Failed to get embedding_q: {}", e)))?;
+            let quantization = parse_quantization(row.get::<String>(2).ok());
+            vectors.push((id, quantization, encoded));
+        }
+
+        // Score in parallel, then only sort the top k
+        let mut scored: Vec<(i64, f32)> = vectors
+            .par_iter()
+            .map(|(id, quantization, encoded)| (*id, query.similarity(*quantization, encoded)))
+            .collect();
+        if scored.len() > k && k > 0 {
+            scored.select_nth_unstable_by(k - 1, |a, b| b.1.total_cmp(&a.1));
+        }
+        scored.truncate(k);
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scored.into_iter().map(|(id, _)| id).collect())
+    }
+}
+
+/// Connect to a local database file
+///
+/// # Arguments
+///
+/// * `path` - Path of the database file
+/// * `flags` - How the file is opened
+/// * `encryption` - How the file is encrypted, or `None` for an unencrypted file
+async fn connect_local(
+    path: &str,
+    flags: libsql::OpenFlags,
+    encryption: Option<libsql::EncryptionConfig>,
+) -> Result<Connection, DbError> {
+    let mut builder = libsql::Builder::new_local(path).flags(flags);
+    if let Some(encryption) = encryption {
+        builder = builder.encryption_config(encryption);
+    }
+    let db = builder
+        .build()
+        .await
+        .map_err(|e| DbError::Connection(format!("Failed to open database: {}", e)))?;
+
+    db.connect()
+        .map_err(|e| DbError::Connection(format!("Failed to connect to database: {}", e)))
+}
+
+/// The encryption of the key configured in the environment, if any
+#[cfg(feature = "encryption")]
+fn env_encryption() -> Result<Option<libsql::EncryptionConfig>, DbError> {
+    Ok(EncryptionKey::from_env()?.map(|key| key.encryption_config()))
+}
+
+/// Without the `encryption` feature databases are never encrypted
+#[cfg(not(feature = "encryption"))]
+fn env_encryption() -> Result<Option<libsql::EncryptionConfig>, DbError> {
+    Ok(None)
+}
+
+/// Get the quantization of a website's collection
+async fn website_quantization(conn: &Connection, website_id: i64) -> Result<Quantization, DbError> {
+    let mut rows = conn
+        .query(
+            "SELECT quantization FROM websites WHERE id = ?",
+            params![website_id],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to get quantization: {}", e)))?;
+
+    match rows.next().await {
+        Ok(Some(row)) => Ok(parse_quantization(row.get::<String>(0).ok())),
+        Ok(None) => Ok(Quantization::Float32),
+        Err(e) => Err(DbError::Data(format!("Failed to get quantization: {}", e))),
+    }
+}
+
+/// Convert a quarantine row to a QuarantineEntry
+fn row_to_quarantine_entry(row: &Row) -> Result<QuarantineEntry, DbError> {
+    let reasons: String = row
+        .get(4)
+        .map_err(|e| DbError::Data(format!("Failed to get reasons: {}", e)))?;
+    let action: String = row
+        .get(5)
+        .map_err(|e| DbError::Data(format!("Failed to get action: {}", e)))?;
+
+    Ok(QuarantineEntry {
+        id: row
+            .get(0)
+            .map_err(|e| DbError::Data(format!("Failed to get id: {}", e)))?,
+        url: row
+            .get(1)
+            .map_err(|e| DbError::Data(format!("Failed to get url: {}", e)))?,
+        position: row
+            .get(2)
+            .map_err(|e| DbError::Data(format!("Failed to get position: {}", e)))?,
+        text: row
+            .get(3)
+            .map_err(|e| DbError::Data(format!("Failed to get text: {}", e)))?,
+        reasons: serde_json::from_str(&reasons)
+            .map_err(|e| DbError::Data(format!("Failed to parse reasons: {}", e)))?,
+        action: action.parse().map_err(DbError::Data)?,
+        created_at: row
+            .get(6)
+            .map_err(|e| DbError::Data(format!("Failed to get created_at: {}", e)))?,
+    })
+}
+
+/// Get the compression dictionary of a website's collection, if it is compressed
+async fn website_dictionary(
+    conn: &Connection,
+    website_id: i64,
+) -> Result<Option<Vec<u8>>, DbError> {
+    let mut rows = conn
+        .query(
+            "SELECT dictionary FROM compression_dictionaries
+             WHERE website_id = ? ORDER BY created_at DESC LIMIT 1",
+            params![website_id],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to get dictionary: {}", e)))?;
+
+    match rows.next().await {
+        Ok(Some(row)) => {
+            Ok(Some(row.get(0).map_err(|e| {
+                DbError::Data(format!("Failed to get dictionary: {}", e))
+            })?))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => Err(DbError::Data(format!("Failed to get dictionary: {}", e))),
+    }
+}
+
+/// Insert the processed chunks of a page into the chunks table or the staging table
+///
+/// Text is compressed and embeddings quantized as the website is configured.
+async fn insert_chunks(
+    conn: &Connection,
+    table: &str,
+    website_id: i64,
+    url: &str,
+    chunks: Vec<crate::processor::ProcessedChunk>,
+) -> Result<usize, DbError> {
+    let count = chunks.len();
+    let quantization = website_quantization(conn, website_id).await?;
+    let dictionary = website_dictionary(conn, website_id).await?;
+    let indexed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let sql = format!(
+        "INSERT INTO {} (website_id, url, text, context, embedding, position, heading, heading_path, author, published_at, doc_version, embedding_q, content_type, language, indexed_at, config_hash)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        table
+    );
+
+    for chunk in chunks {
+        let heading_path = chunk.metadata.breadcrumb();
+        let config_hash = chunk.metadata.config_hash;
+        let indexed_chunk = IndexedChunk {
+            id: 0, // Will be set by the database
+            website_id,
+            url: url.to_string(),
+            text: chunk.text,
+            context: chunk.context,
+            embedding: chunk.embedding,
+            position: chunk.metadata.position as i64,
+            heading_path,
+            heading: chunk.metadata.heading,
+            author: chunk.metadata.author,
+            published_at: chunk.metadata.published_at,
+            content_type: chunk.metadata.content_type,
+            doc_version: detect_version(url).map(|version| version.label),
+        };
+
+        // Insert the chunk with the embedding as a binary blob
+        conn.execute(
+            &sql,
+            params![
+                indexed_chunk.website_id,
+                indexed_chunk.url,
+                encode_text(&indexed_chunk.text, dictionary.as_deref())?,
+                encode_text(&indexed_chunk.context, dictionary.as_deref())?,
+                libsql::Value::Blob(indexed_chunk.embedding.to_binary()),
+                indexed_chunk.position,
+                indexed_chunk.heading,
+                indexed_chunk.heading_path,
+                indexed_chunk.author,
+                indexed_chunk.published_at,
+                indexed_chunk.doc_version,
+                quantized_blob(quantization, &indexed_chunk.embedding),
+                indexed_chunk.content_type,
+                detect_language(&indexed_chunk.text),
+                indexed_at,
+                config_hash,
+            ],
+        )
+        .await
+        .map_err(|e| DbError::Query(format!("Failed to add chunk: {}", e)))?;
+    }
+
+    Ok(count)
+}
+
+/// The value to store for a chunk text or context, compressed if there is a dictionary
+fn encode_text(text: &str, dictionary: Option<&[u8]>) -> Result<libsql::Value, DbError> {
+    match dictionary {
+        Some(dictionary) => Ok(libsql::Value::Blob(compression::compress_text(
+            text, dictionary,
+        )?)),
+        None => Ok(libsql::Value::Text(text.to_string())),
+    }
+}
+
+/// Parse a stored quantization mode, treating unknown values as full precision
+fn parse_quantization(value: Option<String>) -> Quantization {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+/// The quantized copy of an embedding to store, or NULL for full precision collections
+fn quantized_blob(quantization: Quantization, embedding: &Embedding) -> libsql::Value {
+    match quantization.quantize(&embedding.to_vec()) {
+        Some(encoded) => libsql::Value::Blob(encoded),
+        None => libsql::Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    async fn setup_test_db() -> Result<(Database, tempfile::TempDir), DbError> {
+        // Create a temporary directory for the database
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Create a new database
+        let db = Database::new_from_path(&db_path).await?;
+
+        Ok((db, temp_dir))
+    }
+
+    #[tokio::test]
+    async fn test_database_initialization() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        // Verify that the tables were created
+        let mut result = db.execute_query(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name IN ('websites', 'chunks')",
+            params![],
+        ).await.unwrap();
+
+        let mut tables = Vec::new();
+        while let Ok(Some(row)) = result.next().await {
+            let table_name: String = row.get(0).unwrap();
+            tables.push(table_name);
+        }
+
+        assert_eq!(tables.len(), 2);
+        assert!(tables.contains(&"websites".to_string()));
+        assert!(tables.contains(&"chunks".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_website() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        // Create a test website
+        let website = Website {
+            id: 0, // Will be set by the database
+            url: "https://example.com".to_string(),
+            domain: "example.com".to_string(),
+            first_index_date: 1625097600,
+            last_index_date: 1625097600,
+            page_count: 10,
+            status: "active".to_string(),
+        };
+
+        // Add the website
+        let id = db.add_website(&website).await.unwrap();
+        assert!(id > 0);
+
+        // Get the website by URL
+        let retrieved = db
+            .get_website_by_url("https://example.com")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(retrieved.id, id);
+        assert_eq!(retrieved.url, "https://example.com");
+        assert_eq!(retrieved.domain, "example.com");
+        assert_eq!(retrieved.first_index_date, 1625097600);
+        assert_eq!(retrieved.last_index_date, 1625097600);
+        // The page count is derived from chunks, of which there are none yet
+        assert_eq!(retrieved.page_count, 0);
+        assert_eq!(retrieved.status, "active");
+    }
+
+    #[tokio::test]
+    async fn test_get_all_websites() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        // Create test websites
+        let websites = vec![
+            Website {
+                id: 0,
+                url: "https://example1.com".to_string(),
+                domain: "example1.com".to_string(),
+                first_index_date: 1625097600,
+                last_index_date: 1625097600,
+                page_count: 10,
+                status: "active".to_string(),
+            },
+            Website {
+                id: 0,
+                url: "https://example2.com".to_string(),
+                domain: "example2.com".to_string(),
+                first_index_date: 1625097700,
+                last_index_date: 1625097700,
+                page_count: 5,
+                status: "active".to_string(),
+            },
+        ];
+
+        // Add the websites
+        for website in &websites {
+            db.add_website(website).await.unwrap();
+        }
+
+        // Get all websites
+        let retrieved = db.get_all_websites().await.unwrap();
+
+        assert_eq!(retrieved.len(), 2);
+        assert_eq!(retrieved[0].url, "https://example1.com");
+        assert_eq!(retrieved[1].url, "https://example2.com");
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Create and populate the database with a writable connection
+        let db = Database::new_from_path(&db_path).await.unwrap();
+        let website = Website {
+            id: 0,
+            url: "https://example.com".to_string(),
+            domain: "example.com".to_string(),
+            first_index_date: 1625097600,
+            last_index_date: 1625097600,
+            page_count: 1,
+            status: "active".to_string(),
+        };
+        db.add_website(&website).await.unwrap();
+        assert!(!db.is_read_only());
+
+        // Reads work through the read-only connection
+        let ro = Database::open_read_only(&db_path).await.unwrap();
+        assert!(ro.is_read_only());
+        let websites = ro.get_all_websites().await.unwrap();
+        assert_eq!(websites.len(), 1);
+
+        // Writes are rejected
+        let mut other = website.clone();
+        other.url = "https://other.com".to_string();
+        other.domain = "other.com".to_string();
+        assert!(ro.add_website(&other).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only_missing_schema() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("missing.db")
+            .to_string_lossy()
+            .to_string();
+
+        assert!(Database::open_read_only(&db_path).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_website_index_stores_heading_path() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        let chunk = ProcessedChunk {
+            text: "Install with cargo".to_string(),
+            embedding: Embedding {
+                document: "Install with cargo".to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: "Section: Guide > Setup\nInstallation steps".to_string(),
+            metadata: ChunkMetadata {
+                source_url: "https://example.com/guide".to_string(),
+                position: 0,
+                heading: Some("Setup".to_string()),
+                heading_path: vec!["Guide".to_string(), "Setup".to_string()],
+                author: Some("Ada".to_string()),
+                published_at: Some(1704067200),
+                content_type: Some("BlogPosting".to_string()),
+                config_hash: None,
+            },
+        };
+
+        let website_id = db
+            .update_website_index("https://example.com/guide", vec![chunk])
+            .await
+            .unwrap();
+
+        let chunks = db.get_chunks_by_website(website_id).await.unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].heading.as_deref(), Some("Setup"));
+        assert_eq!(chunks[0].heading_path.as_deref(), Some("Guide > Setup"));
+        assert_eq!(chunks[0].author.as_deref(), Some("Ada"));
+        assert_eq!(chunks[0].published_at, Some(1704067200));
+        assert_eq!(chunks[0].content_type.as_deref(), Some("BlogPosting"));
+        assert_eq!(chunks[0].doc_version, None);
+    }
+
+    #[tokio::test]
+    async fn test_update_website_index_stores_doc_version() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        let chunk = ProcessedChunk {
+            text: "Install with cargo".to_string(),
+            embedding: Embedding {
+                document: "Install with cargo".to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: "Installation steps".to_string(),
+            metadata: ChunkMetadata {
+                source_url: "https://docs.example.com/v2/guide".to_string(),
+                position: 0,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+
+        let website_id = db
+            .update_website_index("https://docs.example.com/v2/guide", vec![chunk])
+            .await
+            .unwrap();
+
+        let chunks = db.get_chunks_by_website(website_id).await.unwrap();
+        assert_eq!(chunks[0].doc_version.as_deref(), Some("v2"));
+    }
+
+    #[tokio::test]
+    async fn test_quantize_website() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        let chunk = |text: &str, first: f64| ProcessedChunk {
+            text: text.to_string(),
+            embedding: Embedding {
+                document: text.to_string(),
+                vec: std::iter::once(first)
+                    .chain(std::iter::repeat_n(0.1, 767))
+                    .collect(),
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: "https://example.com/page".to_string(),
+                position: 0,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+
+        db.update_website_index(
+            "https://example.com/page",
+            vec![chunk("near", 1.0), chunk("far", -5.0)],
+        )
+        .await
+        .unwrap();
+
+        let query = QueryVector::new(
+            std::iter::once(1.0)
+                .chain(std::iter::repeat_n(0.1, 767))
+                .collect(),
+        );
+        assert!(
+            db.quantized_candidates(&query, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let converted = db
+            .quantize_website("example.com", Quantization::Int8)
+            .await
+            .unwrap();
+        assert_eq!(converted, 2);
+
+        let chunks = db
+            .get_chunks_by_website_url("https://example.com")
+            .await
+            .unwrap();
+        let near = chunks.iter().find(|c| c.text == "near").unwrap().id;
+        assert_eq!(
+            db.quantized_candidates(&query, 1).await.unwrap(),
+            vec![near]
+        );
+
+        // Chunks indexed later are quantized too
+        db.update_website_index("https://example.com/other", vec![chunk("new", 1.0)])
+            .await
+            .unwrap();
+        assert_eq!(db.quantized_candidates(&query, 10).await.unwrap().len(), 3);
+
+        db.quantize_website("example.com", Quantization::Float32)
+            .await
+            .unwrap();
+        assert!(
+            db.quantized_candidates(&query, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_raw_html() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+        let html = "<html><body><h1>Title</h1></body></html>";
+
+        assert!(
+            db.store_raw_html("https://example.com/page", html)
+                .await
+                .is_err()
+        );
+
+        let website = Website {
+            id: 0,
+            url: "https://example.com".to_string(),
+            domain: "example.com".to_string(),
+            first_index_date: 0,
+            last_index_date: 0,
+            page_count: 0,
+            status: "active".to_string(),
+        };
+        db.add_website(&website).await.unwrap();
+        db.store_raw_html("https://example.com/page", html)
+            .await
+            .unwrap();
+        db.store_raw_html("https://example.com/page", html)
+            .await
+            .unwrap();
+
+        let pages = db.get_raw_html_by_domain("example.com").await.unwrap();
+        assert_eq!(
+            pages,
+            vec![("https://example.com/page".to_string(), html.to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redaction_log() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+        let counts = RedactionCounts {
+            emails: 2,
+            phone_numbers: 1,
+            secrets: 0,
+            llm: 0,
+        };
+
+        assert!(
+            db.record_redactions("https://example.com/team", Redaction::Patterns, &counts)
+                .await
+                .is_err()
+        );
+
+        let website = Website {
+            id: 0,
+            url: "https://example.com".to_string(),
+            domain: "example.com".to_string(),
+            first_index_date: 0,
+            last_index_date: 0,
+            page_count: 0,
+            status: "active".to_string(),
+        };
+        db.add_website(&website).await.unwrap();
+        db.record_redactions("https://example.com/team", Redaction::Patterns, &counts)
+            .await
+            .unwrap();
 
-                // Generate new embedding using combined text and context
-                let new_embedding =
-                    generate_combined_embedding(&client, &chunk.text, &chunk.context)
-                        .await
-                        .map_err(|e| {
-                            DbError::Other(format!("Failed to generate embedding: {}", e))
-                        })?;
+        let log = db.get_redaction_log("example.com").await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].url, "https://example.com/team");
+        assert_eq!(log[0].redaction, Redaction::Patterns);
+        assert_eq!(log[0].counts, counts);
+        assert!(db.get_redaction_log("other.com").await.unwrap().is_empty());
+    }
 
-                // Update chunk in database
-                db.update_chunk_embedding(chunk_id, &new_embedding.to_vec())
-                    .await?;
+    #[tokio::test]
+    async fn test_quarantine() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+        let reasons = vec!["keyword: confidential".to_string()];
+
+        let page = db
+            .quarantine(
+                "https://example.com/plan",
+                None,
+                "Confidential plan",
+                &reasons,
+                SafetyAction::Drop,
+            )
+            .await
+            .unwrap();
+        db.quarantine(
+            "https://other.com/notes",
+            Some(3),
+            "Confidential notes",
+            &reasons,
+            SafetyAction::Flag,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.get_quarantine(None).await.unwrap().len(), 2);
+        let entries = db.get_quarantine(Some("example.com")).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].position, None);
+        assert_eq!(entries[0].reasons, reasons);
+        assert_eq!(entries[0].action, SafetyAction::Drop);
+
+        let removed = db.remove_quarantine_entry(page).await.unwrap().unwrap();
+        assert_eq!(removed.url, "https://example.com/plan");
+        assert!(db.remove_quarantine_entry(page).await.unwrap().is_none());
+        assert_eq!(db.get_quarantine(None).await.unwrap().len(), 1);
+    }
 
-                // Send progress update if sender is provided
-                if let Some(sender) = progress_sender {
-                    // Ignore errors from sending (e.g., if receiver is dropped)
-                    let _ = sender.send((chunk_id, chunk_url)).await;
-                }
+    #[tokio::test]
+    async fn test_collect_garbage() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
 
-                Ok::<i64, DbError>(chunk_id)
-            });
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
 
-            tasks.push(task);
+        let chunk = |url: &str| ProcessedChunk {
+            text: "text".to_string(),
+            embedding: Embedding {
+                document: "text".to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: url.to_string(),
+                position: 0,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+        for page in ["a", "b", "c"] {
+            let url = format!("https://example.com/{}", page);
+            db.update_website_index(&url, vec![chunk(&url)])
+                .await
+                .unwrap();
         }
 
-        // Store the number of tasks
-        let task_count = tasks.len();
+        // Without a complete crawl nothing is collected
+        let gc = db.collect_garbage("example.com", false).await.unwrap();
+        assert_eq!(gc, GarbageCollection::default());
 
-        // Wait for all tasks to complete
-        let results = future::join_all(tasks).await;
+        let urls = |pages: &[&str]| -> Vec<String> {
+            pages
+                .iter()
+                .map(|page| format!("https://example.com/{}", page))
+                .collect()
+        };
+        db.record_crawl("example.com", &urls(&["a"]), true)
+            .await
+            .unwrap();
+        // A later partial crawl still protects the pages it found
+        db.record_crawl("example.com", &urls(&["b"]), false)
+            .await
+            .unwrap();
 
-        // Count successful updates
-        let mut success_count = 0;
-        for result in results {
-            match result {
-                Ok(Ok(_)) => success_count += 1,
-                Ok(Err(e)) => {
-                    debug!("Failed to reembed chunk: {}", e);
-                }
-                Err(e) => {
-                    debug!("Task failed: {}", e);
-                }
+        let dry_run = db.collect_garbage("example.com", true).await.unwrap();
+        assert_eq!(dry_run.stale_pages, urls(&["c"]));
+        assert_eq!(dry_run.deleted_chunks, 0);
+
+        let gc = db.collect_garbage("example.com", false).await.unwrap();
+        assert_eq!(gc.deleted_chunks, 1);
+        assert!(
+            db.collect_garbage("example.com", true)
+                .await
+                .unwrap()
+                .stale_pages
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_staged_reindex() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        let chunk = |text: &str, position: usize| ProcessedChunk {
+            text: text.to_string(),
+            embedding: Embedding {
+                document: text.to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: "https://example.com/guide".to_string(),
+                position,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+        async fn texts(db: &Database) -> Vec<String> {
+            let mut rows = db
+                .execute_query("SELECT text FROM chunks ORDER BY url, position", params![])
+                .await
+                .unwrap();
+            let mut texts = Vec::new();
+            while let Some(row) = rows.next().await.unwrap() {
+                texts.push(row.get::<String>(0).unwrap());
             }
+            texts
         }
 
-        info!(
-            "Successfully reembedded {}/{} chunks",
-            success_count, task_count
+        db.update_website_index("https://example.com/guide", vec![chunk("old", 0)])
+            .await
+            .unwrap();
+        db.update_website_index("https://example.com/other", vec![chunk("other", 0)])
+            .await
+            .unwrap();
+
+        // Staged pages are not searchable until swapped
+        let website_id = db.begin_staging("https://example.com").await.unwrap();
+        db.stage_page_index(
+            website_id,
+            "https://example.com/guide",
+            vec![chunk("new", 0), chunk("newer", 1)],
+        )
+        .await
+        .unwrap();
+        assert_eq!(texts(&db).await, vec!["old", "other"]);
+
+        let swap = db.swap_staged(website_id).await.unwrap();
+        assert_eq!(
+            swap,
+            StagedSwap {
+                pages: 1,
+                chunks: 2,
+                replaced_chunks: 1
+            }
         );
+        assert_eq!(texts(&db).await, vec!["new", "newer", "other"]);
+        let website = db
+            .get_website_by_url("https://example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(website.page_count, 2);
 
-        Ok(success_count)
+        // A re-index emptying pages that have chunks is refused and can be discarded
+        let website_id = db.begin_staging("https://example.com").await.unwrap();
+        db.stage_page_index(website_id, "https://example.com/other", Vec::new())
+            .await
+            .unwrap();
+        assert!(db.swap_staged(website_id).await.is_err());
+        assert_eq!(db.discard_staged(website_id).await.unwrap(), 0);
+        assert_eq!(texts(&db).await, vec!["new", "newer", "other"]);
     }
 
-    /// Update the embedding for a chunk
-    async fn update_chunk_embedding(
-        &self,
-        chunk_id: i64,
-        embedding: &[f32],
-    ) -> Result<(), DbError> {
-        // Convert embedding to binary blob
-        let embedding_blob: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+    #[tokio::test]
+    async fn test_page_count() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
 
-        // Update the chunk in the database
-        self.conn
-            .execute(
-                "UPDATE chunks SET embedding = ? WHERE id = ?",
-                params![libsql::Value::Blob(embedding_blob), chunk_id,],
-            )
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+
+        let chunk = |position: usize| ProcessedChunk {
+            text: format!("chunk {}", position),
+            embedding: Embedding {
+                document: format!("chunk {}", position),
+                vec: vec![0.1; 768],
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: "https://example.com/guide".to_string(),
+                position,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+        async fn page_count(db: &Database) -> i64 {
+            db.get_website_by_url("https://example.com")
+                .await
+                .unwrap()
+                .unwrap()
+                .page_count
+        }
+
+        db.update_website_index("https://example.com/guide", vec![chunk(0), chunk(1)])
             .await
-            .map_err(|e| DbError::Query(format!("Failed to update chunk embedding: {}", e)))?;
+            .unwrap();
+        db.update_website_index("https://example.com/other", vec![chunk(0)])
+            .await
+            .unwrap();
+        assert_eq!(page_count(&db).await, 2);
 
-        Ok(())
+        // Re-indexing a page does not count it again
+        db.update_website_index("https://example.com/guide", vec![chunk(0)])
+            .await
+            .unwrap();
+        assert_eq!(page_count(&db).await, 2);
+
+        // Deleting a page's chunks uncounts it
+        db.delete_chunks_by_page_url("https://example.com/other")
+            .await
+            .unwrap();
+        assert_eq!(page_count(&db).await, 1);
+        let website_id = db
+            .get_website_by_page_url("https://example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(db.count_pages(website_id).await.unwrap(), 1);
+
+        // Counts changed by hand are repaired
+        db.execute_query("UPDATE websites SET page_count = 7", params![])
+            .await
+            .unwrap();
+        assert_eq!(db.recount_pages().await.unwrap(), 1);
+        assert_eq!(page_count(&db).await, 1);
+        assert_eq!(db.recount_pages().await.unwrap(), 0);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_chunk_provenance() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
 
-    use tempfile::tempdir;
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
 
-    async fn setup_test_db() -> Result<(Database, tempfile::TempDir), DbError> {
-        // Create a temporary directory for the database
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir
-            .path()
-            .join("test.db")
-            .to_string_lossy()
-            .to_string();
+        let chunk = |config_hash: &str| ProcessedChunk {
+            text: "text".to_string(),
+            embedding: Embedding {
+                document: "text".to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: "https://example.com/guide".to_string(),
+                position: 0,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: Some(config_hash.to_string()),
+            },
+        };
 
-        // Create a new database
-        let db = Database::new_from_path(&db_path).await?;
+        db.update_website_index(
+            "https://example.com/guide",
+            vec![chunk("new"), chunk("new")],
+        )
+        .await
+        .unwrap();
+        db.update_website_index("https://example.com/other", vec![chunk("old")])
+            .await
+            .unwrap();
+        let crawl_id = db
+            .record_crawl(
+                "example.com",
+                &["https://example.com/guide".to_string()],
+                true,
+            )
+            .await
+            .unwrap();
 
-        Ok((db, temp_dir))
+        let mut provenance = db.chunk_provenance().await.unwrap();
+        provenance.sort_by_key(|group| group.config_hash.clone());
+        assert_eq!(provenance.len(), 2);
+        assert_eq!(provenance[0].config_hash.as_deref(), Some("new"));
+        assert_eq!(provenance[0].pages, 1);
+        assert_eq!(provenance[0].chunks, 2);
+        assert_eq!(provenance[0].last_crawl_id, Some(crawl_id));
+        // Only pages the crawl found are attributed to it
+        assert_eq!(provenance[1].config_hash.as_deref(), Some("old"));
+        assert_eq!(provenance[1].last_crawl_id, None);
+
+        assert_eq!(
+            db.outdated_pages("example.com", "new").await.unwrap(),
+            vec!["https://example.com/other".to_string()]
+        );
+        assert!(
+            db.outdated_pages("other.com", "new")
+                .await
+                .unwrap()
+                .is_empty()
+        );
     }
 
     #[tokio::test]
-    async fn test_database_initialization() {
+    async fn test_indexed_commit() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
         let (db, _temp_dir) = setup_test_db().await.unwrap();
 
-        // Verify that the tables were created
-        let mut result = db.execute_query(
-            "SELECT name FROM sqlite_master WHERE type='table' AND name IN ('websites', 'chunks')",
-            params![],
-        ).await.unwrap();
+        // Nothing to record before the website exists
+        assert!(!db.set_indexed_commit("docs", "abc123").await.unwrap());
+        assert_eq!(db.get_indexed_commit("docs").await.unwrap(), None);
 
-        let mut tables = Vec::new();
-        while let Ok(Some(row)) = result.next().await {
-            let table_name: String = row.get(0).unwrap();
-            tables.push(table_name);
+        let url = "file://docs/guide.md";
+        let chunk = ProcessedChunk {
+            text: "text".to_string(),
+            embedding: Embedding {
+                document: "text".to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: url.to_string(),
+                position: 0,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+        db.update_website_index(url, vec![chunk]).await.unwrap();
+
+        assert!(db.set_indexed_commit("docs", "abc123").await.unwrap());
+        assert!(db.set_indexed_commit("docs", "def456").await.unwrap());
+        assert_eq!(
+            db.get_indexed_commit("docs").await.unwrap().as_deref(),
+            Some("def456")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_cache() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+        let validators = HttpValidators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+        };
+        for page in ["a", "b"] {
+            db.store_http_validators(&format!("https://example.com/{}", page), &validators)
+                .await
+                .unwrap();
         }
 
-        assert_eq!(tables.len(), 2);
-        assert!(tables.contains(&"websites".to_string()));
-        assert!(tables.contains(&"chunks".to_string()));
+        // Only pages with chunks are revalidated
+        let url = "https://example.com/a";
+        let chunk = ProcessedChunk {
+            text: "text".to_string(),
+            embedding: Embedding {
+                document: "text".to_string(),
+                vec: vec![0.1; 768],
+            },
+            context: String::new(),
+            metadata: ChunkMetadata {
+                source_url: url.to_string(),
+                position: 0,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
+        };
+        db.update_website_index(url, vec![chunk]).await.unwrap();
+
+        let cache = db.get_http_cache("example.com").await.unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(url), Some(&validators));
+        assert!(db.get_http_cache("other.com").await.unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_add_and_get_website() {
+    async fn test_compress_website() {
+        use crate::processor::{ChunkMetadata, ProcessedChunk};
+
         let (db, _temp_dir) = setup_test_db().await.unwrap();
 
-        // Create a test website
-        let website = Website {
-            id: 0, // Will be set by the database
-            url: "https://example.com".to_string(),
-            domain: "example.com".to_string(),
-            first_index_date: 1625097600,
-            last_index_date: 1625097600,
-            page_count: 10,
-            status: "active".to_string(),
+        let chunk = |i: usize| ProcessedChunk {
+            text: format!(
+                "Step {} of the deployment guide: run `hal index` with the --store-html flag \
+                 and check the logs in /var/log/hal for warning {}.",
+                i,
+                i % 7
+            ),
+            embedding: Embedding {
+                document: String::new(),
+                vec: vec![0.1; 768],
+            },
+            context: format!("This chunk describes deployment step {}.", i),
+            metadata: ChunkMetadata {
+                source_url: "https://example.com/guide".to_string(),
+                position: i,
+                heading: None,
+                heading_path: Vec::new(),
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
+            },
         };
+        let chunks: Vec<ProcessedChunk> = (0..300).map(chunk).collect();
+        let website_id = db
+            .update_website_index("https://example.com/guide", chunks)
+            .await
+            .unwrap();
 
-        // Add the website
-        let id = db.add_website(&website).await.unwrap();
-        assert!(id > 0);
+        assert_eq!(db.compress_website("example.com", true).await.unwrap(), 300);
+        let mut rows = db
+            .execute_query(
+                "SELECT typeof(text), typeof(context) FROM chunks LIMIT 1",
+                params![],
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "blob");
+        assert_eq!(row.get::<String>(1).unwrap(), "blob");
+
+        // A fresh handle has to load the dictionary from the database
+        let reopened = Database {
+            dictionaries: DictionaryCache::default(),
+            ..db.clone()
+        };
+        let mut stored = reopened.get_chunks_by_website(website_id).await.unwrap();
+        stored.sort_by_key(|chunk| chunk.position);
+        assert_eq!(stored[5].text, chunk(5).text);
+        assert_eq!(stored[5].context, chunk(5).context);
 
-        // Get the website by URL
-        let retrieved = db
-            .get_website_by_url("https://example.com")
+        // New chunks of the collection are compressed too
+        db.update_website_index("https://example.com/other", vec![chunk(300)])
             .await
-            .unwrap()
             .unwrap();
+        let stored = db.get_chunks_by_website(website_id).await.unwrap();
+        assert!(stored.iter().any(|c| c.text == chunk(300).text));
 
-        assert_eq!(retrieved.id, id);
-        assert_eq!(retrieved.url, "https://example.com");
-        assert_eq!(retrieved.domain, "example.com");
-        assert_eq!(retrieved.first_index_date, 1625097600);
-        assert_eq!(retrieved.last_index_date, 1625097600);
-        assert_eq!(retrieved.page_count, 10);
-        assert_eq!(retrieved.status, "active");
+        assert_eq!(
+            db.compress_website("example.com", false).await.unwrap(),
+            301
+        );
+        let mut rows = db
+            .execute_query("SELECT DISTINCT typeof(text) FROM chunks", params![])
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "text");
+        assert!(rows.next().await.unwrap().is_none());
     }
 
+    #[cfg(feature = "encryption")]
     #[tokio::test]
-    async fn test_get_all_websites() {
-        let (db, _temp_dir) = setup_test_db().await.unwrap();
+    async fn test_encrypted_database() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("secret.db");
+        let path = path.to_string_lossy();
+        let key = EncryptionKey::new("first key").unwrap();
+        let new_key = EncryptionKey::new("second key").unwrap();
 
-        // Create test websites
-        let websites = vec![
-            Website {
-                id: 0,
-                url: "https://example1.com".to_string(),
-                domain: "example1.com".to_string(),
-                first_index_date: 1625097600,
-                last_index_date: 1625097600,
-                page_count: 10,
-                status: "active".to_string(),
-            },
-            Website {
-                id: 0,
-                url: "https://example2.com".to_string(),
-                domain: "example2.com".to_string(),
-                first_index_date: 1625097700,
-                last_index_date: 1625097700,
-                page_count: 5,
-                status: "active".to_string(),
-            },
-        ];
+        let db = Database::new_from_path_with_key(&path, Some(&key))
+            .await
+            .unwrap();
+        let website = Website {
+            id: 0,
+            url: "https://internal.example.com".to_string(),
+            domain: "internal.example.com".to_string(),
+            first_index_date: 0,
+            last_index_date: 0,
+            page_count: 0,
+            status: "active".to_string(),
+        };
+        db.add_website(&website).await.unwrap();
+        drop(db);
+
+        assert!(Database::new_from_path_with_key(&path, None).await.is_err());
+        assert!(
+            Database::new_from_path_with_key(&path, Some(&new_key))
+                .await
+                .is_err()
+        );
 
-        // Add the websites
-        for website in &websites {
-            db.add_website(website).await.unwrap();
-        }
+        let db = Database::new_from_path_with_key(&path, Some(&key))
+            .await
+            .unwrap();
+        db.rekey(Some(&new_key)).await.unwrap();
+        drop(db);
 
-        // Get all websites
-        let retrieved = db.get_all_websites().await.unwrap();
+        assert!(
+            Database::new_from_path_with_key(&path, Some(&key))
+                .await
+                .is_err()
+        );
+        let db = Database::new_from_path_with_key(&path, Some(&new_key))
+            .await
+            .unwrap();
+        assert!(
+            db.get_website_by_url("https://internal.example.com")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
 
-        assert_eq!(retrieved.len(), 2);
-        assert_eq!(retrieved[0].url, "https://example1.com");
-        assert_eq!(retrieved[1].url, "https://example2.com");
+    #[tokio::test]
+    async fn test_rebuild_vector_index() {
+        let (db, _temp_dir) = setup_test_db().await.unwrap();
+        assert!(db.has_vector_index().await.unwrap());
+
+        db.conn
+            .execute("DROP INDEX chunks_idx", params![])
+            .await
+            .unwrap();
+        assert!(!db.has_vector_index().await.unwrap());
+
+        db.rebuild_vector_index().await.unwrap();
+        assert!(db.has_vector_index().await.unwrap());
     }
 }
@@ -0,0 +1,112 @@
+//! # Encryption Module
+//!
+//! This module provides encryption at rest for local index files holding internal
+//! documentation. Databases are encrypted by libsql's SQLite3 Multiple Ciphers
+//! integration when a key is configured at open time.
+//!
+//! ## Key Components
+//!
+//! - `EncryptionKey`: A database key, read from an environment variable or a keyfile
+//! - `ENCRYPTION_KEY_ENV` / `ENCRYPTION_KEYFILE_ENV`: Where `Database` looks for the key
+//!
+//! ## Key Sources
+//!
+//! `HAL_DB_KEY` holds the key itself; `HAL_DB_KEYFILE` names a file containing it, which
+//! keeps the key out of the process environment. When both are set the variable wins.
+//! Databases on a libsql server are encrypted by the server and take no key here.
+
+use std::fmt;
+use std::path::Path;
+
+use bytes::Bytes;
+use libsql::{Cipher, EncryptionConfig};
+
+use crate::index::error::DbError;
+
+/// Environment variable holding the database key
+pub const ENCRYPTION_KEY_ENV: &str = "HAL_DB_KEY";
+
+/// Environment variable naming a file that holds the database key
+pub const ENCRYPTION_KEYFILE_ENV: &str = "HAL_DB_KEYFILE";
+
+/// A database encryption key
+///
+/// The key is never printed; `Debug` shows it redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey(String);
+
+impl EncryptionKey {
+    /// Create a key from a passphrase
+    pub fn new(key: impl Into<String>) -> Result<Self, DbError> {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(DbError::Connection(
+                "Encryption key must not be empty".to_string(),
+            ));
+        }
+        Ok(Self(key))
+    }
+
+    /// Read a key from a keyfile, ignoring a trailing newline
+    pub fn from_keyfile(path: &Path) -> Result<Self, DbError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            DbError::Connection(format!("Failed to read keyfile {}: {}", path.display(), e))
+        })?;
+        Self::new(contents.trim_end_matches(['\r', '\n']))
+    }
+
+    /// Read the key configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// The key from `HAL_DB_KEY` or the keyfile named by `HAL_DB_KEYFILE`, or `None`
+    /// if neither is set
+    pub fn from_env() -> Result<Option<Self>, DbError> {
+        if let Some(key) = std::env::var(ENCRYPTION_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty())
+        {
+            return Self::new(key).map(Some);
+        }
+        match std::env::var_os(ENCRYPTION_KEYFILE_ENV).filter(|path| !path.is_empty()) {
+            Some(path) => Self::from_keyfile(Path::new(&path)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The libsql configuration encrypting a database with this key
+    pub fn encryption_config(&self) -> EncryptionConfig {
+        EncryptionConfig::new(Cipher::Aes256Cbc, Bytes::from(self.0.clone()))
+    }
+
+    /// The key as a quoted SQL string literal, for `PRAGMA rekey`
+    pub(crate) fn sql_literal(&self) -> String {
+        format!("'{}'", self.0.replace('\'', "''"))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encryption_key() {
+        assert!(EncryptionKey::new("").is_err());
+
+        let key = EncryptionKey::new("it's secret").unwrap();
+        assert_eq!(key.sql_literal(), "'it''s secret'");
+        assert!(!format!("{:?}", key).contains("secret"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hal.key");
+        std::fs::write(&path, "it's secret\n").unwrap();
+        assert_eq!(EncryptionKey::from_keyfile(&path).unwrap(), key);
+        assert!(EncryptionKey::from_keyfile(&dir.path().join("missing")).is_err());
+    }
+}
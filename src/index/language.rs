@@ -0,0 +1,157 @@
+//! # Language Detection
+//!
+//! Chunks are marked with the language of their text when indexed, so searches can be
+//! restricted to the languages of the documents and results show their language.
+//!
+//! Detection is a heuristic needing no model: non-Latin scripts identify their language,
+//! and Latin-script texts are told apart by their most frequent function words. It only
+//! returns a language when the evidence is clear, so short or mixed texts stay unmarked.
+
+use std::collections::HashMap;
+
+/// Function words of the Latin-script languages told apart, as ISO 639-1 codes
+const FUNCTION_WORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "of", "to", "with", "this", "that", "for", "you", "how",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "mit", "ein", "eine", "für", "wie", "sie",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "des", "une", "pour", "avec", "dans", "comment", "vous",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "y", "es", "una", "para", "con", "del", "cómo", "por", "que",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "gli", "e", "è", "della", "una", "per", "con", "come", "che", "sono", "non",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "e", "é", "uma", "para", "com", "não", "como", "do", "da", "você",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "en", "is", "een", "van", "met", "niet", "voor", "hoe", "zijn", "je",
+        ],
+    ),
+];
+
+/// Share of a text's letters a non-Latin script needs to decide its language
+const SCRIPT_SHARE: f64 = 0.3;
+
+/// Guess the language of a text
+///
+/// # Returns
+///
+/// The ISO 639-1 code of the language, e.g. `en` or `ja`, or `None` when the text is too
+/// short or ambiguous to tell
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut scripts: HashMap<&'static str, usize> = HashMap::new();
+    let mut letters = 0;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if let Some(language) = script_language(c) {
+            *scripts.entry(language).or_default() += 1;
+        }
+    }
+    if letters == 0 {
+        return None;
+    }
+
+    // Japanese mixes kana with Han characters, which alone suggest Chinese
+    let share = |language| *scripts.get(language).unwrap_or(&0) as f64 / letters as f64;
+    if share("ja") > 0.05 {
+        return Some("ja");
+    }
+    if let Some((&language, _)) = scripts
+        .iter()
+        .filter(|&(&language, _)| share(language) >= SCRIPT_SHARE)
+        .max_by_key(|&(_, count)| *count)
+    {
+        return Some(language);
+    }
+
+    // Latin script: count the function words of each language
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(&'static str, usize)> = FUNCTION_WORDS
+        .iter()
+        .map(|(language, function_words)| {
+            let hits = words
+                .iter()
+                .filter(|word| function_words.contains(&word.as_str()))
+                .count();
+            (*language, hits)
+        })
+        .collect();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+    match scores.as_slice() {
+        [(language, best), (_, second), ..] if *best >= 2 && best > second => Some(language),
+        _ => None,
+    }
+}
+
+/// The language a character's script identifies, with `ja` for kana and `zh` for Han
+fn script_language(c: char) -> Option<&'static str> {
+    let language = match c as u32 {
+        0x3040..=0x30FF => "ja",
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF => "zh",
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => "ko",
+        0x0400..=0x04FF => "ru",
+        0x0370..=0x03FF => "el",
+        0x0590..=0x05FF => "he",
+        0x0600..=0x06FF => "ar",
+        0x0900..=0x097F => "hi",
+        0x0E00..=0x0E7F => "th",
+        _ => return None,
+    };
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language("How do I configure the crawler for this site?"),
+            Some("en")
+        );
+        assert_eq!(
+            detect_language("Wie konfiguriere ich den Crawler für die Seite und das Projekt?"),
+            Some("de")
+        );
+        assert_eq!(
+            detect_language("Comment configurer le crawler pour les sites?"),
+            Some("fr")
+        );
+        assert_eq!(detect_language("クローラーの設定方法"), Some("ja"));
+        assert_eq!(detect_language("如何配置爬虫"), Some("zh"));
+        assert_eq!(detect_language("Как настроить краулер?"), Some("ru"));
+        assert_eq!(detect_language("tokio::spawn"), None);
+        assert_eq!(detect_language("123"), None);
+    }
+}
@@ -0,0 +1,199 @@
+//! # Retention Module
+//!
+//! Indexes that are re-crawled for months keep every page they ever found, and each
+//! search scans all of them. Retention policies cap how much is kept, dropping the least
+//! recently used chunks first:
+//!
+//! - `max_unused_days`: chunks not retrieved for this many days; chunks never retrieved
+//!   count from when they were indexed
+//! - `max_chunks_per_domain`: chunks beyond this number per website
+//! - `max_database_mb`: chunks beyond what fits the database in this many megabytes
+//!
+//! Policies are read from the `[retention]` table of `hal.toml` and applied by `hal gc`
+//! through `Database::prune`. Retrievals are counted by `search::usage`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Seconds per day
+const DAY: i64 = 24 * 60 * 60;
+
+/// Limits on what the index keeps; unset limits are not enforced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Most chunks kept per website
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_chunks_per_domain: Option<usize>,
+
+    /// Days after which chunks that were not retrieved are dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_unused_days: Option<u64>,
+
+    /// Largest size of the database in megabytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_database_mb: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Whether no limit is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// When a chunk was last used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkUsage {
+    /// ID of the chunk
+    pub id: i64,
+
+    /// ID of the chunk's website
+    pub website_id: i64,
+
+    /// When the chunk was last retrieved, or indexed if it never was, as a Unix timestamp
+    pub last_used: i64,
+}
+
+/// Chunks dropped by a retention policy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pruning {
+    /// Number of chunks not retrieved within `max_unused_days`
+    pub unused_chunks: usize,
+
+    /// Number of chunks beyond `max_chunks_per_domain`
+    pub excess_chunks: usize,
+
+    /// Number of chunks dropped to fit `max_database_mb`
+    pub oversize_chunks: usize,
+
+    /// IDs of all dropped chunks; deleted unless the pruning was a dry run
+    pub chunk_ids: Vec<i64>,
+}
+
+/// Select the chunks a policy drops, least recently used first
+///
+/// # Arguments
+///
+/// * `policy` - The limits to enforce
+/// * `chunks` - All chunks of the index
+/// * `now` - The current time as a Unix timestamp
+/// * `used_bytes` - Bytes the database currently uses
+///
+/// Each chunk is assumed to take an equal share of `used_bytes` when fitting the size
+/// limit, so the size after pruning is an estimate.
+pub fn select_pruned(
+    policy: &RetentionPolicy,
+    chunks: &[ChunkUsage],
+    now: i64,
+    used_bytes: u64,
+) -> Pruning {
+    let mut chunks = chunks.to_vec();
+    chunks.sort_by_key(|chunk| (chunk.last_used, chunk.id));
+    let mut pruned = vec![false; chunks.len()];
+    let mut pruning = Pruning::default();
+
+    if let Some(days) = policy.max_unused_days {
+        let cutoff = now.saturating_sub(days as i64 * DAY);
+        for (i, chunk) in chunks.iter().enumerate() {
+            if chunk.last_used < cutoff {
+                pruned[i] = true;
+                pruning.unused_chunks += 1;
+            }
+        }
+    }
+
+    if let Some(max) = policy.max_chunks_per_domain {
+        let mut kept: HashMap<i64, usize> = HashMap::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            if !pruned[i] {
+                *kept.entry(chunk.website_id).or_default() += 1;
+            }
+        }
+        for (i, chunk) in chunks.iter().enumerate() {
+            let count = kept.entry(chunk.website_id).or_default();
+            if !pruned[i] && *count > max {
+                pruned[i] = true;
+                *count -= 1;
+                pruning.excess_chunks += 1;
+            }
+        }
+    }
+
+    if let Some(max_mb) = policy.max_database_mb {
+        let bytes_per_chunk = (used_bytes / chunks.len().max(1) as u64).max(1);
+        let max_bytes = max_mb * 1024 * 1024;
+        let kept = pruned.iter().filter(|pruned| !**pruned).count() as u64;
+        let kept_bytes = used_bytes.saturating_sub((chunks.len() as u64 - kept) * bytes_per_chunk);
+        let mut excess = kept_bytes
+            .saturating_sub(max_bytes)
+            .div_ceil(bytes_per_chunk);
+        for flag in pruned.iter_mut() {
+            if excess == 0 {
+                break;
+            }
+            if !*flag {
+                *flag = true;
+                excess -= 1;
+                pruning.oversize_chunks += 1;
+            }
+        }
+    }
+
+    pruning.chunk_ids = chunks
+        .iter()
+        .zip(&pruned)
+        .filter(|(_, pruned)| **pruned)
+        .map(|(chunk, _)| chunk.id)
+        .collect();
+    pruning
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_pruned() {
+        let now = 100 * DAY;
+        let chunk = |id: i64, website_id: i64, days_ago: i64| ChunkUsage {
+            id,
+            website_id,
+            last_used: now - days_ago * DAY,
+        };
+        let chunks = vec![
+            chunk(1, 1, 1),
+            chunk(2, 1, 5),
+            chunk(3, 1, 40),
+            chunk(4, 1, 3),
+            chunk(5, 2, 2),
+            chunk(6, 2, 4),
+        ];
+
+        assert!(RetentionPolicy::default().is_empty());
+        let none = select_pruned(&RetentionPolicy::default(), &chunks, now, 6_000_000);
+        assert!(none.chunk_ids.is_empty());
+
+        // Chunk 3 is unused for too long, then chunk 2 is the least recently used of
+        // website 1's three remaining chunks
+        let policy = RetentionPolicy {
+            max_unused_days: Some(30),
+            max_chunks_per_domain: Some(2),
+            ..RetentionPolicy::default()
+        };
+        let pruning = select_pruned(&policy, &chunks, now, 6_000_000);
+        assert_eq!(pruning.unused_chunks, 1);
+        assert_eq!(pruning.excess_chunks, 1);
+        assert_eq!(pruning.chunk_ids, vec![3, 2]);
+
+        // 6 MB over 6 chunks leave 1 MB per chunk, so 4 MB keep 4 chunks
+        let policy = RetentionPolicy {
+            max_database_mb: Some(4),
+            ..RetentionPolicy::default()
+        };
+        let pruning = select_pruned(&policy, &chunks, now, 6 * 1024 * 1024);
+        assert_eq!(pruning.oversize_chunks, 2);
+        assert_eq!(pruning.chunk_ids, vec![3, 2]);
+    }
+}
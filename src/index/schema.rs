@@ -6,11 +6,23 @@
 //! ## Key Components
 //!
 //! - `initialize_schema`: Function to create and update the database schema
+//! - `verify_schema`: Function to check the schema of a database opened read-only
+//! - `add_column_if_missing`: Migration helper for columns added after a table was created
+//! - `create_vector_index`: Creates the approximate nearest neighbor index over embeddings
 //!
 //! ## Features
 //!
-//! - Websites table for source metadata
+//! - Websites table for source metadata, including the git commit a repository was indexed at
 //! - Chunks table for content segments with embeddings
+//! - Search history table recording past queries and their sources
+//! - Feedback table with relevance judgments of search results
+//! - Answer cache table reusing answers for semantically similar queries
+//! - Raw pages table with compressed HTML for reprocessing without recrawling
+//! - Compression dictionaries table with the zstd dictionary of each compressed collection
+//! - Redaction log table with the number of values masked in each page
+//! - Quarantine table holding pages and chunks that failed safety checks for review
+//! - HTTP cache table with the `ETag` and `Last-Modified` validators of crawled pages
+//! - Crawls and crawl pages tables recording the page set of every crawl for garbage collection
 //! - Foreign key relationships for referential integrity
 //! - Indexes for efficient lookups and vector search
 //! - Vector-specific storage optimizations
@@ -39,7 +51,9 @@ pub async fn initialize_schema(conn: &Connection) -> Result<(), DbError> {
             first_index_date INTEGER,
             last_index_date INTEGER,
             page_count INTEGER DEFAULT 0,
-            status TEXT NOT NULL
+            status TEXT NOT NULL,
+            quantization TEXT NOT NULL DEFAULT 'f32',
+            indexed_commit TEXT
         )",
         params![],
     )
@@ -57,6 +71,18 @@ pub async fn initialize_schema(conn: &Connection) -> Result<(), DbError> {
             embedding F32_BLOB(768) NOT NULL,
             position INTEGER NOT NULL,
             heading TEXT,
+            heading_path TEXT,
+            author TEXT,
+            published_at INTEGER,
+            doc_version TEXT,
+            embedding_q BLOB,
+            content_type TEXT,
+            language TEXT,
+            retrieval_count INTEGER NOT NULL DEFAULT 0,
+            last_retrieved_at INTEGER,
+            indexed_at INTEGER,
+            crawl_id INTEGER,
+            config_hash TEXT,
             FOREIGN KEY (website_id) REFERENCES websites(id) ON DELETE CASCADE
         )",
         params![],
@@ -64,6 +90,34 @@ pub async fn initialize_schema(conn: &Connection) -> Result<(), DbError> {
     .await
     .map_err(|e| DbError::Schema(format!("Failed to create chunks table: {}", e)))?;
 
+    // Migrate chunks tables created before later columns were added
+    add_column_if_missing(conn, "chunks", "heading_path", "TEXT").await?;
+    add_column_if_missing(conn, "chunks", "author", "TEXT").await?;
+    add_column_if_missing(conn, "chunks", "published_at", "INTEGER").await?;
+    add_column_if_missing(conn, "chunks", "doc_version", "TEXT").await?;
+    add_column_if_missing(conn, "chunks", "embedding_q", "BLOB").await?;
+    add_column_if_missing(conn, "chunks", "content_type", "TEXT").await?;
+    add_column_if_missing(conn, "chunks", "language", "TEXT").await?;
+    add_column_if_missing(
+        conn,
+        "chunks",
+        "retrieval_count",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+    .await?;
+    add_column_if_missing(conn, "chunks", "last_retrieved_at", "INTEGER").await?;
+    add_column_if_missing(conn, "chunks", "indexed_at", "INTEGER").await?;
+    add_column_if_missing(conn, "chunks", "crawl_id", "INTEGER").await?;
+    add_column_if_missing(conn, "chunks", "config_hash", "TEXT").await?;
+    add_column_if_missing(
+        conn,
+        "websites",
+        "quantization",
+        "TEXT NOT NULL DEFAULT 'f32'",
+    )
+    .await?;
+    add_column_if_missing(conn, "websites", "indexed_commit", "TEXT").await?;
+
     // Create index on website_id for faster lookups
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_chunks_website_id ON chunks(website_id)",
@@ -80,20 +134,370 @@ pub async fn initialize_schema(conn: &Connection) -> Result<(), DbError> {
     .await
     .map_err(|e| DbError::Schema(format!("Failed to create index on chunks url: {}", e)))?;
 
-    // Create vector index for embeddings
-    // This might fail if the vector extension is not available, but we'll continue anyway
-    let vector_index_result = conn
-        .execute(
-            "CREATE INDEX IF NOT EXISTS chunks_idx ON chunks (libsql_vector_idx(embedding))",
+    // Create index on published_at for date filtered search
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_chunks_published_at ON chunks(published_at)",
+        params![],
+    )
+    .await
+    .map_err(|e| {
+        DbError::Schema(format!(
+            "Failed to create index on chunks published_at: {}",
+            e
+        ))
+    })?;
+
+    // Keep each website's page_count equal to its number of pages with chunks; the
+    // triggers run in the transaction of every chunk write, so concurrent writers can't
+    // race, and deletes through any path are counted
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND name = 'chunks_page_insert'",
             params![],
         )
-        .await;
+        .await
+        .map_err(|e| DbError::Schema(format!("Failed to inspect triggers: {}", e)))?;
+    let counted = matches!(rows.next().await, Ok(Some(_)));
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS chunks_page_insert AFTER INSERT ON chunks
+         WHEN NOT EXISTS (
+             SELECT 1 FROM chunks
+             WHERE website_id = NEW.website_id AND url = NEW.url AND id != NEW.id
+         )
+         BEGIN
+             UPDATE websites SET page_count = page_count + 1 WHERE id = NEW.website_id;
+         END",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create page count trigger: {}", e)))?;
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS chunks_page_delete AFTER DELETE ON chunks
+         WHEN NOT EXISTS (
+             SELECT 1 FROM chunks WHERE website_id = OLD.website_id AND url = OLD.url
+         )
+         BEGIN
+             UPDATE websites SET page_count = page_count - 1 WHERE id = OLD.website_id;
+         END",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create page count trigger: {}", e)))?;
+    // Counts maintained before the triggers existed have drifted
+    if !counted {
+        conn.execute(RECOUNT_PAGES, params![])
+            .await
+            .map_err(|e| DbError::Schema(format!("Failed to recount pages: {}", e)))?;
+    }
+
+    // Create search history table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS search_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            query TEXT NOT NULL,
+            options TEXT NOT NULL,
+            sources TEXT NOT NULL,
+            answer TEXT,
+            created_at INTEGER NOT NULL
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create search_history table: {}", e)))?;
+
+    // Create feedback table for relevance judgments of search results
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feedback (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chunk_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            query TEXT,
+            relevant INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create feedback table: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_feedback_chunk_id ON feedback(chunk_id)",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create index on feedback: {}", e)))?;
+
+    // Create answer cache table keyed by query embedding
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS answer_cache (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            query TEXT NOT NULL,
+            embedding F32_BLOB(768) NOT NULL,
+            model TEXT NOT NULL,
+            options TEXT NOT NULL,
+            answer TEXT NOT NULL,
+            sources TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create answer_cache table: {}", e)))?;
+
+    // Create crawl tables recording which pages each crawl found
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS crawls (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            domain TEXT NOT NULL,
+            complete INTEGER NOT NULL,
+            page_count INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create crawls table: {}", e)))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS crawl_pages (
+            crawl_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            PRIMARY KEY (crawl_id, url),
+            FOREIGN KEY (crawl_id) REFERENCES crawls(id) ON DELETE CASCADE
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create crawl_pages table: {}", e)))?;
+
+    // Create staging tables, where atomic re-indexes build the chunks of a website's pages
+    // before swapping them into the chunks table; the chunk columns mirror those inserted
+    // into chunks
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS staged_pages (
+            website_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            PRIMARY KEY (website_id, url),
+            FOREIGN KEY (website_id) REFERENCES websites(id) ON DELETE CASCADE
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create staged_pages table: {}", e)))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS staged_chunks (
+            website_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            text TEXT NOT NULL,
+            context TEXT NOT NULL,
+            embedding F32_BLOB(768) NOT NULL,
+            position INTEGER NOT NULL,
+            heading TEXT,
+            heading_path TEXT,
+            author TEXT,
+            published_at INTEGER,
+            doc_version TEXT,
+            embedding_q BLOB,
+            content_type TEXT,
+            language TEXT,
+            indexed_at INTEGER,
+            config_hash TEXT,
+            FOREIGN KEY (website_id) REFERENCES websites(id) ON DELETE CASCADE
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create staged_chunks table: {}", e)))?;
+    add_column_if_missing(conn, "staged_chunks", "config_hash", "TEXT").await?;
 
-    if let Err(e) = vector_index_result {
-        eprintln!(
-            "Warning: Failed to create vector index: {}. Vector search will not be available.",
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_staged_chunks_website_id ON staged_chunks(website_id)",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create index on staged_chunks: {}", e)))?;
+
+    // Create raw pages table with the compressed HTML of pages for reprocessing
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS raw_pages (
+            url TEXT PRIMARY KEY,
+            website_id INTEGER NOT NULL,
+            html BLOB NOT NULL,
+            fetched_at INTEGER NOT NULL,
+            FOREIGN KEY (website_id) REFERENCES websites(id) ON DELETE CASCADE
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create raw_pages table: {}", e)))?;
+
+    // Create compression dictionaries table; the ID is the zstd dictionary ID that
+    // compressed chunk values refer to
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS compression_dictionaries (
+            id INTEGER PRIMARY KEY,
+            website_id INTEGER NOT NULL,
+            dictionary BLOB NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (website_id) REFERENCES websites(id) ON DELETE CASCADE
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| {
+        DbError::Schema(format!(
+            "Failed to create compression_dictionaries table: {}",
             e
-        );
+        ))
+    })?;
+
+    // Create redaction log table auditing what was masked before pages were stored
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS redaction_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            website_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            redaction TEXT NOT NULL,
+            emails INTEGER NOT NULL,
+            phone_numbers INTEGER NOT NULL,
+            secrets INTEGER NOT NULL,
+            llm INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (website_id) REFERENCES websites(id) ON DELETE CASCADE
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create redaction_log table: {}", e)))?;
+
+    // Create quarantine table holding content that failed safety checks
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quarantine (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            domain TEXT NOT NULL,
+            url TEXT NOT NULL,
+            position INTEGER,
+            text TEXT NOT NULL,
+            reasons TEXT NOT NULL,
+            action TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create quarantine table: {}", e)))?;
+
+    // Create HTTP cache table with the validators sent in conditional requests on recrawl
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS http_cache (
+            url TEXT PRIMARY KEY,
+            domain TEXT NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            checked_at INTEGER NOT NULL
+        )",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create http_cache table: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_http_cache_domain ON http_cache(domain)",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create index on http_cache: {}", e)))?;
+
+    // Create vector index for embeddings
+    // This might fail if the vector extension is not available, but we'll continue anyway
+    if let Err(e) = create_vector_index(conn).await {
+        eprintln!("Warning: {}. Search will fall back to an exact scan.", e);
+    }
+
+    Ok(())
+}
+
+/// Create the approximate nearest neighbor index over chunk embeddings
+///
+/// libsql maintains the index on disk and updates it incrementally as chunks are
+/// inserted and deleted.
+pub async fn create_vector_index(conn: &Connection) -> Result<(), DbError> {
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS chunks_idx ON chunks (libsql_vector_idx(embedding))",
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to create vector index: {}", e)))?;
+
+    Ok(())
+}
+
+/// Set the page_count of websites whose count is wrong to their number of pages with chunks
+pub(crate) const RECOUNT_PAGES: &str = "UPDATE websites SET page_count = (
+        SELECT COUNT(DISTINCT url) FROM chunks WHERE chunks.website_id = websites.id
+    )
+    WHERE page_count IS NOT (
+        SELECT COUNT(DISTINCT url) FROM chunks WHERE chunks.website_id = websites.id
+    )";
+
+/// Add a column to an existing table unless it is already present
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves older databases untouched, so new columns
+/// are added here to keep existing indexes usable.
+async fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), DbError> {
+    let mut rows = conn
+        .query(&format!("PRAGMA table_info({})", table), params![])
+        .await
+        .map_err(|e| DbError::Schema(format!("Failed to inspect table {}: {}", table, e)))?;
+
+    while let Ok(Some(row)) = rows.next().await {
+        let name: String = row
+            .get(1)
+            .map_err(|e| DbError::Schema(format!("Failed to read column name: {}", e)))?;
+        if name == column {
+            return Ok(());
+        }
+    }
+
+    conn.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+        params![],
+    )
+    .await
+    .map_err(|e| DbError::Schema(format!("Failed to add column {}.{}: {}", table, column, e)))?;
+
+    Ok(())
+}
+
+/// Verify that the schema exists without modifying the database
+///
+/// Used for read-only connections, where `initialize_schema` cannot run.
+pub async fn verify_schema(conn: &Connection) -> Result<(), DbError> {
+    for table in ["websites", "chunks"] {
+        let mut rows = conn
+            .query(
+                "SELECT name FROM sqlite_master WHERE type='table' AND name = ?",
+                params![table],
+            )
+            .await
+            .map_err(|e| DbError::Schema(format!("Failed to inspect schema: {}", e)))?;
+
+        match rows.next().await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Err(DbError::Schema(format!(
+                    "Missing table '{}'. The database must be initialized before opening it read-only",
+                    table
+                )));
+            }
+            Err(e) => return Err(DbError::Schema(format!("Failed to inspect schema: {}", e))),
+        }
     }
 
     Ok(())
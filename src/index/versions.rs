@@ -0,0 +1,144 @@
+//! # Documentation Version Detection
+//!
+//! Documentation sites often publish the same page under several version paths, such
+//! as `/v1/`, `/v2/` and `/latest/`. This module detects the version segment of a URL
+//! so copies of a page can be filtered by version or collapsed to a single version.
+//!
+//! ## Key Components
+//!
+//! - `DocVersion`: The detected version label and the URL without it
+//! - `detect_version`: Detects the version segment of a URL path
+//! - `compare_versions`: Orders version labels from oldest to newest
+//!
+//! ## Recognized Segments
+//!
+//! - Numeric versions with a `v` prefix or a dot, and an optional `.x` suffix: `v2`, `3.11`, `1.x`
+//! - Channel names: `latest`, `stable`, `current`, `next`, `dev`, `nightly`, `main`, `master`
+
+use std::cmp::Ordering;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use url::Url;
+
+/// Matches numeric version path segments
+static NUMERIC_VERSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^v?(\d+(?:\.\d+)*)(?:\.x)?$").expect("valid version regex"));
+
+/// Channel names that point at the newest released docs
+const RELEASED_CHANNELS: [&str; 3] = ["latest", "stable", "current"];
+
+/// Channel names that point at unreleased docs
+const PRERELEASE_CHANNELS: [&str; 5] = ["next", "dev", "nightly", "main", "master"];
+
+/// A documentation version detected in a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocVersion {
+    /// The version label as it appears in the URL, e.g. `v2` or `latest`
+    pub label: String,
+
+    /// The URL with the version segment removed, shared by all versions of a page
+    pub canonical_url: String,
+}
+
+/// Detect the documentation version of a URL
+///
+/// # Arguments
+///
+/// * `url` - The page URL
+///
+/// # Returns
+///
+/// The version of the first path segment that looks like one, or `None`
+pub fn detect_version(url: &str) -> Option<DocVersion> {
+    let mut parsed = Url::parse(url).ok()?;
+    let segments: Vec<String> = parsed.path_segments()?.map(String::from).collect();
+
+    let index = segments
+        .iter()
+        .position(|segment| is_version_segment(segment))?;
+    let label = segments[index].clone();
+
+    let remaining: Vec<&str> = segments
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, segment)| segment.as_str())
+        .collect();
+    parsed.set_path(&format!("/{}", remaining.join("/")));
+
+    Some(DocVersion {
+        label,
+        canonical_url: parsed.to_string(),
+    })
+}
+
+/// Whether a path segment names a documentation version
+fn is_version_segment(segment: &str) -> bool {
+    let segment = segment.to_lowercase();
+    // Bare integers are more often years or IDs than versions
+    (NUMERIC_VERSION.is_match(&segment) && (segment.starts_with('v') || segment.contains('.')))
+        || RELEASED_CHANNELS.contains(&segment.as_str())
+        || PRERELEASE_CHANNELS.contains(&segment.as_str())
+}
+
+/// Rank of a version label: prerelease channels, then numeric versions, then released channels
+fn version_rank(label: &str) -> (u8, Vec<u64>) {
+    let label = label.to_lowercase();
+    if RELEASED_CHANNELS.contains(&label.as_str()) {
+        return (2, Vec::new());
+    }
+    if let Some(captures) = NUMERIC_VERSION.captures(&label) {
+        let parts = captures[1]
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        return (1, parts);
+    }
+    (0, Vec::new())
+}
+
+/// Compare two version labels from oldest to newest
+///
+/// Released channels such as `latest` sort above numbered versions, which sort above
+/// prerelease channels such as `dev`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    version_rank(a).cmp(&version_rank(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_version() {
+        let version = detect_version("https://docs.example.com/v2/guide/install").unwrap();
+        assert_eq!(version.label, "v2");
+        assert_eq!(
+            version.canonical_url,
+            "https://docs.example.com/guide/install"
+        );
+
+        let version = detect_version("https://docs.example.com/en/3.11/library/os.html").unwrap();
+        assert_eq!(version.label, "3.11");
+        assert_eq!(
+            version.canonical_url,
+            "https://docs.example.com/en/library/os.html"
+        );
+
+        let version = detect_version("https://docs.example.com/latest/guide").unwrap();
+        assert_eq!(version.label, "latest");
+
+        assert!(detect_version("https://example.com/blog/2024-01-01-post").is_none());
+        assert!(detect_version("https://example.com/guide/install").is_none());
+        assert!(detect_version("https://example.com/blog/2024/01/post").is_none());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("v2", "v10"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.x", "1.10"), Ordering::Less);
+        assert_eq!(compare_versions("latest", "v10"), Ordering::Greater);
+        assert_eq!(compare_versions("dev", "v1"), Ordering::Less);
+    }
+}
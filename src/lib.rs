@@ -12,6 +12,15 @@
 //! - **Content Processor**: Smart text chunking and processing for RAG applications
 //! - **Vector Database**: LibSQL-based storage for embeddings and content
 //! - **Semantic Search**: Vector-based search with RAG integration
+//! - **Agent Tools**: HAL search packaged as a `rig` tool for custom agents
+//! - **RAG Agents**: `rig` agents that retrieve from an index and cite their sources
+//! - **Cancellation**: Tokens that stop crawls, indexing and re-embedding cleanly, with
+//!   checkpoints of the work completed
+//! - **Audit Log**: Append-only record of the tool calls of agents and the MCP server
+//! - **Editor JSON-RPC**: Search, answers and indexing over stdio for editor plugins
+//! - **Configuration**: `hal.toml` with API keys, the default database and rate-limit tier
+//! - **Health Checks**: Checks of keys, the database, embeddings and network reachability
+//! - **Plugins**: Custom sources, extractors and tools, compiled in or run as commands
 //!
 //! ## Features
 //!
@@ -55,17 +64,27 @@
 //! }
 //! ```
 
+pub mod agent;
+pub mod audit;
+pub mod cancel;
 pub mod coder;
+pub mod config;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod health;
 mod markdown;
 pub mod mcp;
 pub mod model;
+pub mod plugin;
 
 // RAG feature modules
 pub mod crawler;
 pub mod index;
 pub mod processor;
+pub mod rpc;
 pub mod search;
+pub mod tools;
 
 pub use coder::{CoderConfig, CoderError, CoderEvent, run_coder_session};
 pub use error::Error;
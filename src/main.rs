@@ -7,12 +7,25 @@
 //!
 //! - CLI argument parsing with clap
 //! - Subcommands for different RAG operations:
+//!   - `init`: First-run setup writing `hal.toml`
+//!   - `auth`: API keys in the OS keyring
 //!   - `chat`: Interactive TUI-based chat interface
 //!   - `crawl`: Website content acquisition
 //!   - `index`: Content processing and storage
 //!   - `search`: Semantic search with RAG capabilities
 //!   - `list`: Index management and inspection
 //!   - `reembed`: Vector regeneration for existing content
+//!   - `reprocess`: Extraction and chunking re-run on stored raw HTML
+//!   - `quantize`: Compact int8 or binary embeddings for a collection
+//!   - `ann`: Maintenance of the approximate nearest neighbor index
+//!   - `gc`: Removal of pages that disappeared since the latest crawl
+//!   - `audit links`: Vanished pages and dead links of an indexed site
+//!   - `bench-embeddings`: Comparison of embedding models before re-embedding
+//!   - `history` / `feedback`: Past searches and relevance judgments
+//!   - `chunk`: Chunking inspection for a local Markdown file
+//!   - `code`: Coding task run end-to-end by a tool-using agent
+//!   - `lsp-ish`: Search, answers and indexing over stdio JSON-RPC for editor plugins
+//!   - `completions` / `manpages`: Shell completions and man pages generated from the CLI
 //!
 //! ## Features
 //!
@@ -22,19 +35,26 @@
 //! - Progress tracking for long-running operations
 //! - Telemetry integration for monitoring
 //! - Both JSON and text output formats
+//! - JSON lines for progress and results with `--log-format json`, for orchestration systems
+//! - `-q` to suppress decorative output, `-v`/`-vv` for debug and trace logs
+//! - Distinct exit codes per failure type, see `exit_code`
 //!
 //! The CLI provides a unified interface to the various components of the HAL framework,
 //! enabling end-to-end RAG workflows from content acquisition to knowledge retrieval.
 
+mod exit_code;
+#[macro_use]
+mod output;
 mod telemetry;
 mod tui;
 
 use anyhow::{Context, anyhow};
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand};
 use hal::{crawler::CrawledPage, processor::chunk_markdown};
 use indicatif::{ProgressBar, ProgressStyle};
 // Removed mcpr transport import
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use telemetry::OtelGuard;
 use tokio::sync::mpsc;
 use tracing::{info, instrument};
@@ -42,12 +62,34 @@ use tracing::{info, instrument};
 #[derive(Parser)]
 #[command(author, version, about = "A Rust framework for LLM-powered Retrieval Augmented Generation", long_about = None)]
 struct Cli {
+    /// Output format for progress and results (text|json); json writes one JSON object per line
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    log_format: output::LogFormat,
+
+    /// Only print results, warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log debug messages to stderr; repeat (-vv) for trace messages
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    /// Set up the provider, API keys, default database and rate-limit tier in hal.toml
+    Init(InitArgs),
+
+    /// Store or remove provider API keys in the OS keyring
+    Auth(AuthArgs),
+
+    /// Check API keys, the database, embedding dimensions and network reachability, printing
+    /// how to fix each problem
+    Doctor(DoctorArgs),
+
     /// Start an interactive chat session with an LLM
     Chat(ChatArgs),
 
@@ -58,7 +100,16 @@ enum Commands {
     Index(IndexArgs),
 
     /// Search the indexed content
-    Search(SearchArgs),
+    Search(Box<SearchArgs>),
+
+    /// List past searches
+    History(HistoryArgs),
+
+    /// Show the size of each indexed website and, with --usage, which sources searches use
+    Stats(StatsArgs),
+
+    /// Record whether a search result was relevant
+    Feedback(FeedbackArgs),
 
     /// List indexed websites
     List(ListArgs),
@@ -66,8 +117,55 @@ enum Commands {
     /// Reembed all chunks in the index with new embeddings
     Reembed(ReembedArgs),
 
+    /// Re-run extraction and chunking on the stored HTML of a website
+    Reprocess(ReprocessArgs),
+
+    /// Convert a collection's embeddings to int8 or binary quantization
+    Quantize(QuantizeArgs),
+
+    /// Compress a collection's chunk text with a trained zstd dictionary
+    Compress(CompressArgs),
+
+    /// Manage the approximate nearest neighbor index
+    Ann(AnnArgs),
+
+    /// Compare embedding models on a sample of the indexed chunks
+    BenchEmbeddings(BenchEmbeddingsArgs),
+
+    /// Check the health of indexed content and review recorded tool calls
+    Audit(AuditArgs),
+
+    /// Remove chunks of pages absent from the latest crawl of a website, and chunks beyond
+    /// the retention limits
+    Gc(GcArgs),
+
+    /// Manage local database files
+    #[cfg(feature = "encryption")]
+    Db(DbArgs),
+
+    /// Review content that failed safety checks
+    Quarantine(QuarantineArgs),
+
     /// Start an MCP server
     Mcp(McpArgs),
+
+    /// Run a coding task with a tool-using agent in a project directory
+    Code(CodeArgs),
+
+    /// Index a source repository per function, struct and class for code search
+    IndexCode(IndexCodeArgs),
+
+    /// Serve search, answers and indexing over stdio JSON-RPC for editor plugins
+    LspIsh(LspIshArgs),
+
+    /// Chunk a Markdown file and print the resulting chunks
+    Chunk(ChunkArgs),
+
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+
+    /// Generate man pages for hal and its subcommands
+    Manpages(ManpagesArgs),
 }
 
 #[derive(Args, Debug)]
@@ -75,6 +173,30 @@ struct ChatArgs {
     /// LLM model to use (default: gemini-2.0-flash)
     #[arg(short, long, default_value = "gemini-2.0-flash")]
     model: String,
+
+    /// Answer from the index, showing the retrieved chunks in a sources panel
+    #[arg(long)]
+    rag: bool,
+
+    /// Filter retrieved chunks by source domain
+    #[arg(short, long, requires = "rag")]
+    source: Option<String>,
+
+    /// Number of chunks retrieved per prompt
+    #[arg(short, long, default_value = "5", requires = "rag")]
+    limit: usize,
+
+    /// Suggest follow-up questions after each answer; `/follow N` asks the Nth one
+    #[arg(long, requires = "rag")]
+    follow_ups: bool,
+
+    /// Template of conversation exports, with {{title}}, {{model}}, {{date}} and {{messages}}
+    #[arg(long, value_name = "FILE")]
+    export_template: Option<PathBuf>,
+
+    /// Syntax highlighting theme of code blocks
+    #[arg(long, default_value = tui::highlight::DEFAULT_THEME)]
+    theme: String,
 }
 
 #[derive(Args, Debug)]
@@ -114,11 +236,19 @@ struct CrawlArgs {
     /// Index a single page
     #[arg(short, long)]
     single: bool,
+
+    /// Write a JSON report of every URL's outcome to this file
+    #[arg(long, default_value = "crawl-report.json")]
+    report: PathBuf,
+
+    #[command(flatten)]
+    crawler: CrawlerArgs,
 }
 
 #[derive(Args, Debug)]
 struct IndexArgs {
-    /// Source to index (URL or file)
+    /// Source to index (URL, JSON file of crawled pages, Markdown file or directory, `.mbox` file or Maildir,
+    /// `confluence://<space key>`, `notion://<database id>`, audio file, or `podcast:<feed url>`)
     #[arg(required = true)]
     source: String,
 
@@ -126,11 +256,48 @@ struct IndexArgs {
     #[arg(short, long, default_value = "500")]
     chunk_size: usize,
 
+    /// Overlap between chunks in whole sentences instead of a tenth of the chunk size
+    #[arg(long)]
+    overlap_sentences: Option<usize>,
+
+    /// Chunk size (and overlap) for pages whose URL contains a pattern, as
+    /// PATTERN=SIZE[/OVERLAP], e.g. /changelog=1500/150; may be repeated. Front matter
+    /// `chunk_size` and `chunk_overlap` keys take precedence
+    #[arg(long = "chunk-override", value_name = "PATTERN=SIZE[/OVERLAP]")]
+    chunk_overrides: Vec<hal::processor::SourceChunkOverride>,
+
+    /// Split pages into sections per H1/H2 heading, each summarized as its own document
+    #[arg(long)]
+    segment_by_heading: bool,
+
+    /// How chunk contexts are generated: one LLM call per chunk (llm), one call per batch
+    /// of chunks (batched), or from the page summary without LLM calls (template)
+    #[arg(long, default_value = "llm")]
+    context_mode: hal::processor::ContextMode,
+
+    /// Chunks per LLM call with --context-mode batched
+    #[arg(long, default_value = "10")]
+    context_batch_size: usize,
+
+    /// Chunks of a page processed at once; [concurrency] in hal.toml bounds the requests
+    /// in flight to each model
+    #[arg(long, default_value = "5")]
+    concurrency: usize,
+
+    /// Skip page summaries and build contexts from the title and headings, so indexing
+    /// makes embedding calls only (implies --context-mode template)
+    #[arg(long)]
+    no_summary: bool,
+
     /// LLM model for summaries
     #[arg(short, long, default_value = "gemini-2.0-flash-lite")]
     model: String,
 
-    /// Force reindex
+    /// Generate every summary and context again instead of reusing cached completions
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Fetch every page again instead of revalidating pages cached by the last crawl
     #[arg(short, long)]
     force: bool,
 
@@ -149,13 +316,139 @@ struct IndexArgs {
     /// Index a single page
     #[arg(short, long)]
     single: bool,
+
+    /// Remove chunks of pages the crawl no longer found (needs --force when pages were
+    /// unchanged since the last crawl)
+    #[arg(long)]
+    gc: bool,
+
+    /// Build each website's new chunks in a staging table and swap them in with one
+    /// transaction once all its pages are processed, so searches never see it half
+    /// re-indexed
+    #[arg(long)]
+    atomic: bool,
+
+    /// Store the compressed raw HTML of crawled pages for `hal reprocess`
+    #[arg(long)]
+    store_html: bool,
+
+    #[command(flatten)]
+    crawler: CrawlerArgs,
+
+    /// For a Markdown directory in a git repository, only reprocess the files changed
+    /// since the last indexed commit
+    #[arg(long)]
+    git_diff: bool,
+
+    /// Mask emails, phone numbers and keys before storing (off|patterns|llm)
+    #[arg(long, default_value = "off")]
+    redact: hal::processor::Redaction,
+
+    /// File of keywords, one per line, that fail a chunk's safety check
+    #[arg(long)]
+    safety_keywords: Option<PathBuf>,
+
+    /// Check pages with the moderation API (MODERATION_API_KEY or OPENAI_API_KEY)
+    #[arg(long)]
+    moderate: bool,
+
+    /// What happens to content failing a safety check (drop|flag)
+    #[arg(long, default_value = "drop")]
+    safety_action: hal::processor::SafetyAction,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
+}
+
+#[derive(Args, Debug)]
+struct ReprocessArgs {
+    /// Domain of the website to reprocess from its stored HTML
+    #[arg(required = true)]
+    domain: String,
+
+    /// Chunk size in characters
+    #[arg(short, long, default_value = "500")]
+    chunk_size: usize,
+
+    /// Overlap between chunks in whole sentences instead of a tenth of the chunk size
+    #[arg(long)]
+    overlap_sentences: Option<usize>,
+
+    /// Chunk size (and overlap) for pages whose URL contains a pattern, as
+    /// PATTERN=SIZE[/OVERLAP], e.g. /changelog=1500/150; may be repeated. Front matter
+    /// `chunk_size` and `chunk_overlap` keys take precedence
+    #[arg(long = "chunk-override", value_name = "PATTERN=SIZE[/OVERLAP]")]
+    chunk_overrides: Vec<hal::processor::SourceChunkOverride>,
+
+    /// Split pages into sections per H1/H2 heading, each summarized as its own document
+    #[arg(long)]
+    segment_by_heading: bool,
+
+    /// How chunk contexts are generated: one LLM call per chunk (llm), one call per batch
+    /// of chunks (batched), or from the page summary without LLM calls (template)
+    #[arg(long, default_value = "llm")]
+    context_mode: hal::processor::ContextMode,
+
+    /// Chunks per LLM call with --context-mode batched
+    #[arg(long, default_value = "10")]
+    context_batch_size: usize,
+
+    /// Chunks of a page processed at once; [concurrency] in hal.toml bounds the requests
+    /// in flight to each model
+    #[arg(long, default_value = "5")]
+    concurrency: usize,
+
+    /// Skip page summaries and build contexts from the title and headings, so indexing
+    /// makes embedding calls only (implies --context-mode template)
+    #[arg(long)]
+    no_summary: bool,
+
+    /// LLM model for summaries
+    #[arg(short, long, default_value = "gemini-2.0-flash-lite")]
+    model: String,
+
+    /// Generate every summary and context again instead of reusing cached completions
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Mask emails, phone numbers and keys before storing (off|patterns|llm)
+    #[arg(long, default_value = "off")]
+    redact: hal::processor::Redaction,
+
+    /// Only reprocess pages with chunks built with settings other than these, e.g. after
+    /// changing the chunk size or model
+    #[arg(long)]
+    outdated: bool,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
 }
 
 #[derive(Args, Debug)]
 struct SearchArgs {
     /// Search query
-    #[arg(required = true)]
-    query: String,
+    #[arg(required_unless_present_any = ["replay", "batch"])]
+    query: Option<String>,
+
+    /// Re-run a search from the history by its ID, with its original options
+    #[arg(long, conflicts_with = "query")]
+    replay: Option<i64>,
+
+    /// File of questions, one per line, to answer as JSON lines
+    #[arg(long, conflicts_with_all = ["query", "replay"])]
+    batch: Option<PathBuf>,
+
+    /// File the batch appends its JSON lines to; questions it already answered are skipped
+    #[arg(long, requires = "batch")]
+    output: Option<PathBuf>,
+
+    /// Number of batch questions answered at once
+    #[arg(long, default_value = "4", requires = "batch")]
+    concurrency: usize,
+
+    /// Do not record this search in the history
+    #[arg(long, default_value = "false")]
+    no_history: bool,
 
     /// Filter by source domain
     #[arg(short, long)]
@@ -165,21 +458,365 @@ struct SearchArgs {
     #[arg(short, long, default_value = "15")]
     limit: usize,
 
-    /// Output format (text|json)
-    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    /// Output format (text|json|jsonl); batches always write JSON lines
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json", "jsonl"])]
     format: String,
 
-    /// Database path
+    /// Database path; repeat to search several indexes and merge their results
     #[arg(short, long, default_value = "index.db")]
-    database: PathBuf,
+    database: Vec<PathBuf>,
 
     /// Use vector search only (no LLM)
-    #[arg(short = 'v', long, default_value = "false")]
+    #[arg(long, default_value = "false")]
     vector_search_only: bool,
 
     /// LLM model to use for RAG
     #[arg(short = 'm', long, default_value = "gemini-2.0-flash")]
     model: String,
+
+    /// Open the database file read-only instead of connecting to the libsql server
+    #[arg(long, default_value = "false")]
+    read_only: bool,
+
+    /// Only include sources published on or after this date (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_date_arg)]
+    after: Option<chrono::NaiveDate>,
+
+    /// Only include sources published on or before this date (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_date_arg)]
+    before: Option<chrono::NaiveDate>,
+
+    /// Only include sources of this schema.org or OpenGraph type, e.g. BlogPosting or article
+    #[arg(long)]
+    content_type: Option<String>,
+
+    /// Decay scores of old sources with this half-life in days
+    #[arg(long)]
+    half_life_days: Option<f64>,
+
+    /// Half-life in days for a specific source domain (DOMAIN=DAYS, repeatable)
+    #[arg(long, value_parser = parse_domain_half_life)]
+    domain_half_life: Vec<(String, f64)>,
+
+    /// Only include documentation pages of this version (e.g. v2, latest)
+    #[arg(long)]
+    doc_version: Option<String>,
+
+    /// Keep only the latest version of pages published under several doc versions
+    #[arg(long, default_value = "false")]
+    collapse_versions: bool,
+
+    /// Version to keep when collapsing doc versions (implies --collapse-versions)
+    #[arg(long)]
+    prefer_version: Option<String>,
+
+    /// Exclude sources whose URL contains this domain or path (repeatable)
+    #[arg(long)]
+    exclude_source: Vec<String>,
+
+    /// Exclude results containing this string, e.g. "deprecated" (repeatable)
+    #[arg(long)]
+    exclude_term: Vec<String>,
+
+    /// Rank results without the boost or penalty from relevance feedback
+    #[arg(long, default_value = "false")]
+    ignore_feedback: bool,
+
+    /// Keep only results in this language, as an ISO 639-1 code such as "de" (repeatable)
+    #[arg(long)]
+    target_language: Vec<String>,
+
+    /// Also search with the query translated into each --target-language
+    #[arg(long, default_value = "false", requires = "target_language")]
+    translate_query: bool,
+
+    /// Always generate a new answer instead of reusing one for a similar query
+    #[arg(long, default_value = "false")]
+    no_cache: bool,
+
+    /// Maximum age in hours of a cached answer that may be reused
+    #[arg(long, default_value = "168")]
+    cache_ttl_hours: i64,
+
+    /// Minimum similarity (0-1) between queries to reuse a cached answer
+    #[arg(long, default_value = "0.95")]
+    cache_threshold: f64,
+
+    /// Ask the LLM to check retrieved sources for prompt injection
+    #[arg(long, default_value = "false")]
+    detect_injection: bool,
+
+    /// JSON schema file the answer must conform to; the answer is returned as JSON
+    #[arg(long, conflicts_with = "vector_search_only")]
+    schema: Option<PathBuf>,
+
+    /// Language to write the answer in, e.g. "French" or "fr", whatever the sources' language
+    #[arg(long, conflicts_with = "vector_search_only")]
+    answer_language: Option<String>,
+
+    /// Suggest follow-up questions the sources can answer, shown after the answer
+    #[arg(long, default_value = "false", conflicts_with = "vector_search_only")]
+    follow_ups: bool,
+
+    /// Answer from each source page separately, then combine the answers; for broad
+    /// questions with many relevant chunks, usually with a higher --limit
+    #[arg(long, default_value = "false", conflicts_with_all = ["vector_search_only", "schema"])]
+    map_reduce: bool,
+
+    /// What answers are generated from: chunks, the full text of the top pages, or auto to
+    /// send pages when the model's context window is large enough
+    #[arg(long, default_value = "chunks", conflicts_with = "vector_search_only")]
+    retrieval_mode: hal::search::long_context::RetrievalMode,
+
+    /// Answer comparison queries ("compare X and Y", "X vs Y") with a table comparing the
+    /// items across sources, searching for each item separately
+    #[arg(long, default_value = "false", conflicts_with_all = ["vector_search_only", "schema", "map_reduce"])]
+    compare: bool,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
+}
+
+/// Crawler behavior shared by `crawl` and `index`
+#[derive(Args, Debug, Clone)]
+struct CrawlerArgs {
+    /// Download linked PDF and CSV documents and index their text
+    #[arg(long)]
+    follow_documents: bool,
+
+    /// Largest linked document to download, in megabytes
+    #[arg(long, default_value = "10")]
+    max_document_size: u64,
+
+    /// Largest page to convert to Markdown, in megabytes
+    #[arg(long, default_value = "2")]
+    max_page_size: u64,
+
+    /// Convert binary files, static assets and minified scripts instead of skipping them
+    #[arg(long)]
+    allow_binary: bool,
+
+    /// Send every request through this proxy (http://, https://, socks5:// or socks5h://
+    /// for Tor); defaults to HTTPS_PROXY/ALL_PROXY
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Accept invalid TLS certificates, e.g. of an intercepting corporate proxy
+    #[arg(long)]
+    insecure: bool,
+
+    /// PEM file with an additional CA certificate to trust
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+
+    /// Keep following calendars, endless pagination and other crawl traps
+    #[arg(long)]
+    no_trap_detection: bool,
+
+    /// URLs allowed per numbered URL pattern or per path with query strings before it is
+    /// treated as a crawl trap
+    #[arg(long, default_value = "50")]
+    max_urls_per_pattern: usize,
+}
+
+impl CrawlerArgs {
+    /// Apply the flags to a crawler configuration
+    fn apply(
+        &self,
+        builder: hal::crawler::CrawlerConfigBuilder,
+    ) -> hal::crawler::CrawlerConfigBuilder {
+        let mut builder = builder;
+        let proxy = self.proxy.clone().or_else(|| {
+            ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+                .into_iter()
+                .find_map(|name| std::env::var(name).ok().filter(|proxy| !proxy.is_empty()))
+        });
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(ca_cert) = &self.ca_cert {
+            builder = builder.ca_certificate(ca_cert);
+        }
+        builder
+            .accept_invalid_certs(self.insecure)
+            .follow_documents(self.follow_documents)
+            .max_document_bytes(self.max_document_size * 1024 * 1024)
+            .max_page_bytes(self.max_page_size * 1024 * 1024)
+            .skip_binary_content(!self.allow_binary)
+            .detect_traps(!self.no_trap_detection)
+            .max_urls_per_pattern(self.max_urls_per_pattern)
+    }
+}
+
+/// Sampling parameters for LLM completions, overriding `[generation]` in hal.toml
+#[derive(Args, Debug, Clone)]
+struct GenerationArgs {
+    /// Sampling temperature
+    #[arg(long)]
+    temperature: Option<f64>,
+
+    /// Nucleus sampling probability mass (0-1)
+    #[arg(long)]
+    top_p: Option<f64>,
+
+    /// Maximum number of tokens to generate
+    #[arg(long)]
+    max_tokens: Option<u64>,
+
+    /// Sequence that ends generation; repeat for several
+    #[arg(long)]
+    stop: Vec<String>,
+}
+
+impl GenerationArgs {
+    /// The options from hal.toml with the flags given on the command line applied
+    fn options(&self) -> hal::model::GenerationOptions {
+        hal::config::current()
+            .generation
+            .merge(&hal::model::GenerationOptions {
+                temperature: self.temperature,
+                top_p: self.top_p,
+                max_tokens: self.max_tokens,
+                stop: self.stop.clone(),
+                json_output: false,
+            })
+    }
+}
+
+/// Build search options from the search command arguments
+fn search_options(args: &SearchArgs) -> hal::search::SearchOptions {
+    hal::search::SearchOptions {
+        limit: args.limit,
+        source_filter: args.source.clone(),
+        exact_source: false,
+        date_range: None,
+        published_range: match (args.after, args.before) {
+            (None, None) => None,
+            (after, before) => Some((
+                after
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map_or(i64::MIN, |date| date.and_utc().timestamp()),
+                before
+                    .and_then(|date| date.and_hms_opt(23, 59, 59))
+                    .map_or(i64::MAX, |date| date.and_utc().timestamp()),
+            )),
+        },
+        content_type: args.content_type.clone(),
+        freshness: match (args.half_life_days, args.domain_half_life.is_empty()) {
+            (None, true) => None,
+            (default_half_life, _) => Some(args.domain_half_life.iter().fold(
+                // Without a default, only the listed domains decay
+                hal::search::FreshnessWeighting::new(default_half_life.unwrap_or(0.0)),
+                |weighting, (domain, days)| weighting.with_domain_half_life(domain, *days),
+            )),
+        },
+        doc_version: args.doc_version.clone(),
+        collapse_versions: args.collapse_versions || args.prefer_version.is_some(),
+        preferred_version: args.prefer_version.clone(),
+        exclude_sources: args.exclude_source.clone(),
+        exclude_terms: args.exclude_term.clone(),
+        ignore_feedback: args.ignore_feedback,
+        target_languages: args.target_language.clone(),
+    }
+}
+
+/// Parse a `DOMAIN=DAYS` half-life argument
+fn parse_domain_half_life(value: &str) -> Result<(String, f64), String> {
+    let (domain, days) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected DOMAIN=DAYS, got '{}'", value))?;
+    let days = days
+        .parse::<f64>()
+        .map_err(|e| format!("invalid half-life '{}': {}", days, e))?;
+    Ok((domain.to_string(), days))
+}
+
+/// Parse a `MODEL=PRICE` pair for `--price`
+fn parse_model_price(value: &str) -> Result<(String, f64), String> {
+    let (model, price) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected MODEL=PRICE, got '{}'", value))?;
+    let price: f64 = price
+        .parse()
+        .map_err(|_| format!("invalid price '{}'", price))?;
+    Ok((model.to_string(), price))
+}
+
+/// Parse a `YYYY-MM-DD` command line date
+fn parse_date_arg(value: &str) -> Result<chrono::NaiveDate, String> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| format!("invalid date '{}': {}", value, e))
+}
+
+#[derive(Args, Debug)]
+struct HistoryArgs {
+    /// Number of searches to show
+    #[arg(short, long, default_value = "20")]
+    limit: usize,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+
+    /// Database path
+    #[arg(short, long, default_value = "index.db")]
+    database: PathBuf,
+
+    /// Open the database file read-only instead of connecting to the libsql server
+    #[arg(long, default_value = "false")]
+    read_only: bool,
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    /// Show how often each website's chunks were retrieved, the most retrieved pages and
+    /// pages that were never retrieved
+    #[arg(long, default_value = "false")]
+    usage: bool,
+
+    /// Show the processor settings and crawls that built each website's chunks, so
+    /// websites indexed with mixed settings stand out
+    #[arg(long, default_value = "false")]
+    provenance: bool,
+
+    /// Number of most retrieved and never retrieved pages to show
+    #[arg(short, long, default_value = "10")]
+    limit: usize,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+
+    /// Database path
+    #[arg(short, long, default_value = "index.db")]
+    database: PathBuf,
+
+    /// Open the database file read-only instead of connecting to the libsql server
+    #[arg(long, default_value = "false")]
+    read_only: bool,
+}
+
+#[derive(Args, Debug)]
+struct FeedbackArgs {
+    /// ID of the search result, as shown by `hal search`
+    #[arg(required = true)]
+    result_id: i64,
+
+    /// Mark the result as relevant
+    #[arg(
+        long,
+        conflicts_with = "irrelevant",
+        required_unless_present = "irrelevant"
+    )]
+    relevant: bool,
+
+    /// Mark the result as irrelevant
+    #[arg(long)]
+    irrelevant: bool,
+
+    /// The query the result was returned for, stored as an evaluation label
+    #[arg(long)]
+    query: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -209,73 +846,609 @@ struct ReembedArgs {
 }
 
 #[derive(Args, Debug)]
-struct McpArgs {
-    /// Server name
-    #[arg(short, long, default_value = "HAL MCP Server")]
-    name: String,
+struct QuantizeArgs {
+    /// Source domain of the collection to convert
+    #[arg(short, long, required = true)]
+    source: String,
 
-    /// Server version
-    #[arg(short, long, default_value = "1.0.0")]
-    version: String,
+    /// Quantization mode (f32|int8|binary); f32 removes the quantized copies
+    #[arg(short, long, default_value = "int8")]
+    mode: hal::index::quantization::Quantization,
+}
 
-    /// Disable file tools
-    #[arg(long, default_value = "false")]
-    no_file_tools: bool,
+#[derive(Args, Debug)]
+struct CompressArgs {
+    /// Source domain of the collection to compress
+    #[arg(short, long, required = true)]
+    source: String,
+
+    /// Restore plain text instead of compressing
+    #[arg(long)]
+    off: bool,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Parse command line arguments
-    let cli = Cli::parse();
+#[derive(Args, Debug)]
+struct AnnArgs {
+    #[command(subcommand)]
+    command: AnnCommand,
+}
 
-    let mut _otel: Option<OtelGuard> = None;
-    if !matches!(cli.command, Some(Commands::Chat(_))) {
-        _otel = Some(crate::telemetry::init_tracing_subscriber());
-    }
+#[derive(Subcommand, Debug)]
+enum AnnCommand {
+    /// Show whether libsql's `chunks_idx` vector index exists
+    Status,
 
-    // Execute the appropriate command
-    match cli.command {
-        Some(Commands::Chat(_args)) => {
-            // Get API key from environment variable
-            let api_key = std::env::var("GEMINI_FREE_API_KEY")
-                .expect("GEMINI_API_KEY environment variable must be set");
+    /// Drop and rebuild the index from the stored embeddings
+    Rebuild,
+}
 
-            // Setup file-based logging for TUI
-            tui::logging::setup_logging()?;
+#[derive(Args, Debug)]
+struct GcArgs {
+    /// Domain of the indexed website whose pages absent from the latest crawl are removed
+    domain: Option<String>,
 
-            // Run the TUI application
-            tui::run(api_key).await?;
-        }
-        Some(Commands::Crawl(args)) => {
-            crawl_command(args).await?;
-        }
-        Some(Commands::Index(args)) => {
-            index_command(args).await?;
-        }
-        Some(Commands::Search(args)) => {
-            search_command(args).await?;
-        }
-        Some(Commands::List(args)) => {
-            list_command(args).await?;
-        }
-        Some(Commands::Reembed(args)) => {
-            reembed_command(args).await?;
-        }
-        Some(Commands::Mcp(args)) => {
-            mcp_command(args).await?;
-        }
-        None => {
-            // If no command is provided, show help
-            let _ = Cli::parse_from(["--help"]);
-        }
-    }
+    /// List the stale pages and count the chunks retention would drop, without deleting
+    #[arg(long)]
+    dry_run: bool,
 
-    Ok(())
+    /// Most chunks kept per website (default from [retention] in hal.toml)
+    #[arg(long)]
+    max_chunks_per_domain: Option<usize>,
+
+    /// Drop chunks not retrieved for this many days (default from [retention] in hal.toml)
+    #[arg(long)]
+    max_unused_days: Option<u64>,
+
+    /// Drop chunks until the database fits this many megabytes (default from [retention]
+    /// in hal.toml)
+    #[arg(long)]
+    max_database_mb: Option<u64>,
 }
 
-#[instrument]
-async fn crawl_command(args: CrawlArgs) -> anyhow::Result<()> {
-    println!("Crawling {}...", args.url);
+#[derive(Args, Debug)]
+struct AuditArgs {
+    #[command(subcommand)]
+    command: AuditCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditCommand {
+    /// Report pages that vanished since they were indexed and dead links in their content
+    Links(AuditLinksArgs),
+
+    /// Show how many values were redacted from each page of a website
+    Redactions(AuditRedactionsArgs),
+
+    /// Print the latest tool calls of agents and the MCP server, oldest first
+    Tail(AuditTailArgs),
+}
+
+#[derive(Args, Debug)]
+struct AuditTailArgs {
+    /// Number of calls to print
+    #[arg(short = 'n', long, default_value = "20")]
+    lines: usize,
+
+    /// Only print calls of this tool
+    #[arg(long)]
+    tool: Option<String>,
+
+    /// Audit log to read, instead of .hal/audit.jsonl in the current directory
+    #[arg(long)]
+    file: Option<PathBuf>,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text")]
+    format: String,
+}
+
+#[derive(Args, Debug)]
+struct AuditRedactionsArgs {
+    /// Domain of the indexed website
+    #[arg(required = true)]
+    domain: String,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text")]
+    format: String,
+}
+
+#[derive(Args, Debug)]
+struct AuditLinksArgs {
+    /// Domain of the indexed website
+    #[arg(required = true)]
+    domain: String,
+
+    /// Delete the chunks of pages that vanished
+    #[arg(long)]
+    prune: bool,
+
+    /// Only check the indexed pages, not the links in their content
+    #[arg(long)]
+    pages_only: bool,
+
+    /// Number of concurrent requests
+    #[arg(short, long, default_value = "8")]
+    concurrency: usize,
+
+    /// Request timeout in seconds
+    #[arg(long, default_value = "10")]
+    timeout: u64,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text")]
+    format: String,
+}
+
+#[derive(Args, Debug)]
+struct QuarantineArgs {
+    #[command(subcommand)]
+    command: QuarantineCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum QuarantineCommand {
+    /// List the pages and chunks held for review
+    List {
+        /// Only list content of this domain
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Output format (text|json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Accept quarantined content; flagged content stays in the index
+    Approve {
+        /// ID of the quarantine entry
+        id: i64,
+    },
+
+    /// Reject quarantined content; flagged content is removed from the index
+    Reject {
+        /// ID of the quarantine entry
+        id: i64,
+    },
+}
+
+#[cfg(feature = "encryption")]
+#[derive(Args, Debug)]
+struct DbArgs {
+    #[command(subcommand)]
+    command: DbCommand,
+}
+
+#[cfg(feature = "encryption")]
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Change the encryption key of a local database
+    ///
+    /// The current key is read from HAL_DB_KEY or HAL_DB_KEYFILE unless --keyfile is given.
+    Rekey(RekeyArgs),
+}
+
+#[cfg(feature = "encryption")]
+#[derive(Args, Debug)]
+struct RekeyArgs {
+    /// Path of the database file
+    #[arg(required = true)]
+    database: PathBuf,
+
+    /// File holding the current key, for databases not configured in the environment
+    #[arg(long)]
+    keyfile: Option<PathBuf>,
+
+    /// File holding the new key
+    #[arg(long, conflicts_with = "decrypt")]
+    new_keyfile: Option<PathBuf>,
+
+    /// Environment variable holding the new key
+    #[arg(long, default_value = "HAL_DB_NEW_KEY", conflicts_with = "decrypt")]
+    new_key_env: String,
+
+    /// Remove the encryption instead of changing the key
+    #[arg(long)]
+    decrypt: bool,
+}
+
+#[derive(Args, Debug)]
+struct BenchEmbeddingsArgs {
+    /// Embedding models to compare (comma-separated); `mock` runs offline
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "text-embedding-004"
+    )]
+    models: Vec<String>,
+
+    /// Number of chunks to embed with each model
+    #[arg(short, long, default_value = "200")]
+    sample: usize,
+
+    /// Number of results considered for recall
+    #[arg(short, long, default_value = "10")]
+    k: usize,
+
+    /// Price of a model in USD per million tokens as MODEL=PRICE (repeatable)
+    #[arg(long = "price", value_parser = parse_model_price)]
+    prices: Vec<(String, f64)>,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text")]
+    format: String,
+}
+
+#[derive(Args, Debug)]
+struct ChunkArgs {
+    /// Markdown file to chunk
+    #[arg(required = true)]
+    file: PathBuf,
+
+    /// Target chunk size in words
+    #[arg(short, long, default_value = "500")]
+    target_chunk_size: usize,
+
+    /// Overlap between chunks in words
+    #[arg(short, long, default_value = "50")]
+    overlap_size: usize,
+
+    /// Overlap between chunks in whole sentences instead of words
+    #[arg(long)]
+    overlap_sentences: Option<usize>,
+
+    /// Merge chunks with fewer characters into a neighboring chunk
+    #[arg(long, default_value = "100")]
+    min_chunk_chars: usize,
+
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+}
+
+#[derive(Args, Debug)]
+struct InitArgs {
+    /// Configuration file to write; defaults to the file in effect or ~/.config/hal/hal.toml
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct AuthArgs {
+    #[command(subcommand)]
+    command: AuthCommand,
+}
+
+#[derive(Args, Debug)]
+struct DoctorArgs {
+    /// Output format (text|json)
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum AuthCommand {
+    /// Prompt for a provider's API keys and store them in the OS keyring
+    Set(AuthProviderArgs),
+
+    /// Remove a provider's API keys from the OS keyring
+    Remove(AuthProviderArgs),
+}
+
+#[derive(Args, Debug)]
+struct AuthProviderArgs {
+    /// Provider the keys belong to (gemini)
+    #[arg(required = true)]
+    provider: hal::config::Provider,
+}
+
+#[derive(Args, Debug)]
+struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    #[arg(value_enum)]
+    shell: clap_complete::Shell,
+}
+
+#[derive(Args, Debug)]
+struct ManpagesArgs {
+    /// Directory to write one page per command to; prints the `hal` page when omitted
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct McpArgs {
+    /// Server name
+    #[arg(short, long, default_value = "HAL MCP Server")]
+    name: String,
+
+    /// Server version
+    #[arg(long, default_value = "1.0.0")]
+    version: String,
+
+    /// Disable file tools
+    #[arg(long, default_value = "false")]
+    no_file_tools: bool,
+
+    /// Most shell commands a session may run (default from [mcp_quotas] in hal.toml)
+    #[arg(long)]
+    max_shell_commands: Option<u64>,
+
+    /// Most bytes a session may write to files (default from [mcp_quotas] in hal.toml)
+    #[arg(long)]
+    max_bytes_written: Option<u64>,
+
+    /// Most searches a session may run per minute (default from [mcp_quotas] in hal.toml)
+    #[arg(long)]
+    max_searches_per_minute: Option<u64>,
+
+    /// JSON file of API keys search tools require; the session's key is read from HAL_API_KEY
+    #[arg(long)]
+    access_config: Option<PathBuf>,
+
+    /// Serve /healthz and /readyz over HTTP on this address, e.g. 127.0.0.1:8081
+    #[arg(long)]
+    health_addr: Option<std::net::SocketAddr>,
+}
+
+#[derive(Args, Debug)]
+struct CodeArgs {
+    /// Task for the agent, e.g. "refactor X in ./src"
+    task: String,
+
+    /// Project directory the agent may read, edit and run commands in
+    #[arg(short, long, default_value = ".")]
+    project: PathBuf,
+
+    /// Most agent iterations before giving up
+    #[arg(long, default_value = "30")]
+    max_iterations: usize,
+
+    /// LLM model to use
+    #[arg(short, long, default_value = "gemini-2.0-flash")]
+    model: String,
+
+    /// Run file edits, writes and shell commands without asking for approval
+    #[arg(long)]
+    auto_approve: bool,
+
+    /// Most characters of a tool result kept in the conversation; larger results are stored
+    /// in .hal/artifacts for the agent to page through
+    #[arg(long, default_value_t = hal::coder::artifacts::DEFAULT_RESULT_BUDGET)]
+    result_budget: usize,
+
+    /// Tokens of files from the index relevant to the task preloaded into the agent's
+    /// context (0 to preload nothing)
+    #[arg(long, default_value_t = hal::coder::context::DEFAULT_CONTEXT_TOKENS)]
+    context_tokens: usize,
+
+    /// Only preload files of this indexed source, e.g. the repository's name
+    #[arg(long)]
+    context_source: Option<String>,
+
+    /// Send the preamble, preloaded files and tools with every request instead of caching
+    /// them with the provider's context caching
+    #[arg(long)]
+    no_context_cache: bool,
+
+    /// Only give the agent the tools most relevant to the task, selected by embedding
+    /// similarity (finish and request_permission are always kept)
+    #[arg(long)]
+    max_tools: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+struct IndexCodeArgs {
+    /// Repository directory to index; its directory name is the source to search
+    path: PathBuf,
+
+    /// Most characters of a code chunk; longer functions are split
+    #[arg(long, default_value_t = hal::processor::code::DEFAULT_MAX_CODE_CHUNK)]
+    max_chunk_chars: usize,
+}
+
+#[derive(Args, Debug)]
+struct LspIshArgs {
+    /// Only search and answer, without indexing documents (no GEMINI_API_KEY needed)
+    #[arg(long)]
+    no_index: bool,
+
+    /// Target size of chunks of indexed documents, in characters
+    #[arg(long, default_value = "500")]
+    chunk_size: usize,
+
+    /// Serve /healthz and /readyz over HTTP on this address, e.g. 127.0.0.1:8081
+    #[arg(long)]
+    health_addr: Option<std::net::SocketAddr>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Parse command line arguments
+    let cli = Cli::parse();
+    output::set_format(cli.log_format);
+    output::set_quiet(cli.quiet);
+
+    let mut _otel: Option<OtelGuard> = None;
+    if !matches!(
+        cli.command,
+        Some(
+            Commands::Init(_)
+                | Commands::Auth(_)
+                | Commands::Chat(_)
+                | Commands::Completions(_)
+                | Commands::Manpages(_)
+        )
+    ) {
+        _otel = Some(crate::telemetry::init_tracing_subscriber(
+            default_log_filter(cli.verbose),
+        ));
+    }
+
+    // Long-running commands stop cleanly on Ctrl+C; the others keep the default behavior
+    if matches!(
+        cli.command,
+        Some(Commands::Crawl(_) | Commands::Index(_) | Commands::Reembed(_))
+    ) {
+        install_shutdown_handler();
+    }
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => exit_code::report(err),
+    }
+}
+
+/// Log filter used when `RUST_LOG` is not set
+fn default_log_filter(verbose: u8) -> &'static str {
+    match verbose {
+        0 => "error",
+        1 => "warn,hal=debug",
+        _ => "info,hal=trace",
+    }
+}
+
+/// Open the default database: the libsql server or file set in `hal.toml`, or the local
+/// libsql server
+async fn open_database() -> anyhow::Result<hal::index::Database> {
+    Ok(hal::index::Database::open_configured().await?)
+}
+
+/// Run the command selected on the command line
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    hal::plugin::register_commands(&hal::config::current().plugins);
+
+    // Execute the appropriate command
+    match cli.command {
+        Some(Commands::Init(args)) => {
+            init_command(args)?;
+        }
+        Some(Commands::Auth(args)) => {
+            auth_command(args)?;
+        }
+        Some(Commands::Doctor(args)) => {
+            doctor_command(args).await?;
+        }
+        Some(Commands::Chat(args)) => {
+            // Get API key from environment variable
+            let api_key = exit_code::require_api_key("GEMINI_FREE_API_KEY")?;
+
+            // Open the index before the terminal is taken over, so errors are readable
+            let rag = if args.rag {
+                Some(tui::RagConfig {
+                    db: open_database().await?,
+                    options: hal::search::SearchOptions {
+                        limit: args.limit,
+                        source_filter: args.source,
+                        ..Default::default()
+                    },
+                    follow_ups: args.follow_ups,
+                })
+            } else {
+                None
+            };
+
+            let export_template = args
+                .export_template
+                .map(|path| {
+                    std::fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read {}", path.display()))
+                })
+                .transpose()?;
+            tui::highlight::set_theme(&args.theme)?;
+
+            // Setup file-based logging for TUI
+            tui::logging::setup_logging()?;
+
+            // Run the TUI application
+            tui::run(api_key, args.model, rag, export_template).await?;
+        }
+        Some(Commands::Crawl(args)) => {
+            crawl_command(args).await?;
+        }
+        Some(Commands::Index(args)) => {
+            index_command(args).await?;
+        }
+        Some(Commands::Search(args)) => {
+            search_command(*args).await?;
+        }
+        Some(Commands::History(args)) => {
+            history_command(args).await?;
+        }
+        Some(Commands::Stats(args)) => {
+            stats_command(args).await?;
+        }
+        Some(Commands::Feedback(args)) => {
+            feedback_command(args).await?;
+        }
+        Some(Commands::List(args)) => {
+            list_command(args).await?;
+        }
+        Some(Commands::Reembed(args)) => {
+            reembed_command(args).await?;
+        }
+        Some(Commands::Reprocess(args)) => {
+            reprocess_command(args).await?;
+        }
+        Some(Commands::Quantize(args)) => {
+            quantize_command(args).await?;
+        }
+        Some(Commands::Compress(args)) => {
+            compress_command(args).await?;
+        }
+        Some(Commands::Ann(args)) => {
+            ann_command(args).await?;
+        }
+        Some(Commands::BenchEmbeddings(args)) => {
+            bench_embeddings_command(args).await?;
+        }
+        Some(Commands::Audit(args)) => {
+            audit_command(args).await?;
+        }
+        Some(Commands::Gc(args)) => {
+            gc_command(args).await?;
+        }
+        #[cfg(feature = "encryption")]
+        Some(Commands::Db(args)) => {
+            db_command(args).await?;
+        }
+        Some(Commands::Quarantine(args)) => {
+            quarantine_command(args).await?;
+        }
+        Some(Commands::Mcp(args)) => {
+            mcp_command(args).await?;
+        }
+        Some(Commands::Code(args)) => {
+            code_command(args).await?;
+        }
+        Some(Commands::IndexCode(args)) => {
+            index_code_command(args).await?;
+        }
+        Some(Commands::LspIsh(args)) => {
+            lsp_ish_command(args).await?;
+        }
+        Some(Commands::Chunk(args)) => {
+            chunk_command(args)?;
+        }
+        Some(Commands::Completions(args)) => {
+            completions_command(args);
+        }
+        Some(Commands::Manpages(args)) => {
+            manpages_command(args)?;
+        }
+        None => {
+            // If no command is provided, show help
+            let _ = Cli::parse_from(["--help"]);
+        }
+    }
+
+    Ok(())
+}
+
+#[instrument]
+async fn crawl_command(args: CrawlArgs) -> anyhow::Result<()> {
+    note!("Crawling {}...", args.url);
 
     // Set max_depth and max_pages based on the single argument
     let (depth, max_pages) = if args.single {
@@ -284,48 +1457,1899 @@ async fn crawl_command(args: CrawlArgs) -> anyhow::Result<()> {
         (args.max_depth, args.max_pages) // Use provided values otherwise
     };
 
-    // Create crawler configuration
-    let config = hal::crawler::CrawlerConfig::builder()
-        .max_depth(depth)
-        .max_pages(max_pages)
-        .rate_limit_ms(args.rate)
-        .respect_robots_txt(true)
-        .user_agent("hal-rag/0.1".to_string())
-        .exclude_selectors(args.exclude.split(',').map(String::from).collect())
-        .content_selectors(
-            args.include
-                .map(|s| s.split(',').map(String::from).collect())
-                .unwrap_or_default(),
-        )
-        .build();
+    // Create crawler configuration
+    let config = args
+        .crawler
+        .apply(hal::crawler::CrawlerConfig::builder())
+        .max_depth(depth)
+        .max_pages(max_pages)
+        .rate_limit_ms(args.rate)
+        .respect_robots_txt(true)
+        .user_agent("hal-rag/0.1".to_string())
+        .exclude_selectors(args.exclude.split(',').map(String::from).collect())
+        .content_selectors(
+            args.include
+                .map(|s| s.split(',').map(String::from).collect())
+                .unwrap_or_default(),
+        )
+        .cancel(hal::cancel::shutdown_token())
+        .build();
+
+    // Crawl the website
+    let (pages, report) = hal::crawler::crawl_website_with_report(&args.url, config).await?;
+
+    print_crawl_summary(&report);
+    report.write_json(&args.report).await?;
+    note!("Wrote crawl report to {}", args.report.display());
+
+    let store = pages
+        .iter()
+        .cloned()
+        .map(hal::crawler::storage::PageEntry::from);
+    hal::crawler::storage::store_batch(store).await?;
+
+    emit!(
+        "crawl_completed",
+        { "url": args.url, "pages": pages.len() },
+        "Crawled {} pages",
+        pages.len()
+    );
+    // The pages fetched before a cancellation are stored; record which ones they were
+    if report.cancelled {
+        let completed = pages.iter().map(|page| page.url.clone()).collect();
+        let checkpoint = hal::cancel::Checkpoint::new("crawl", &args.url, completed, Vec::new());
+        return cancelled(checkpoint, None);
+    }
+
+    if args.chunk {
+        let processor_config = hal::processor::ProcessorConfig::builder()
+            .chunk_options(hal::processor::ChunkOptions::default())
+            .embedding_dimensions(768)
+            .build();
+        let chunks: Vec<hal::processor::TextChunk> = pages
+            .into_iter()
+            .filter_map(|p| chunk_markdown(&p.content, &processor_config.chunk_options).ok())
+            .flatten()
+            .collect();
+
+        // Save to file if output is specified
+        if let Some(output_file) = args.output {
+            // Serialize chunks to JSON
+            let json = serde_json::to_string_pretty(&chunks)?;
+            tokio::fs::write(output_file.clone(), json).await?;
+            out!("Saved crawled content to {}", output_file.display());
+        }
+    }
+
+    partial_crawl_failure(&report)
+}
+
+/// Fail with a partial failure when some, but not all, pages of a crawl failed
+fn partial_crawl_failure(report: &hal::crawler::report::CrawlReport) -> anyhow::Result<()> {
+    let summary = &report.summary;
+    if summary.failed > 0 && summary.fetched > 0 {
+        return Err(exit_code::PartialFailure(format!(
+            "{} of {} pages failed to crawl",
+            summary.failed,
+            summary.total()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Write the checkpoint of a command stopped by Ctrl+C and print what it completed
+///
+/// # Arguments
+///
+/// * `checkpoint` - What the command completed and skipped
+/// * `skipped` - Items skipped, when known
+///
+/// # Returns
+///
+/// The `Interrupted` error the command exits with
+fn cancelled(checkpoint: hal::cancel::Checkpoint, skipped: Option<usize>) -> anyhow::Result<()> {
+    let path = hal::cancel::Checkpoint::default_path(&checkpoint.operation);
+    checkpoint.save(&path)?;
+
+    let completed = checkpoint.completed.len();
+    let summary = match skipped {
+        Some(skipped) => format!("{} completed, {} skipped", completed, skipped),
+        None => format!("{} completed", completed),
+    };
+    emit!(
+        "cancelled",
+        {
+            "operation": checkpoint.operation,
+            "completed": completed,
+            "skipped": skipped,
+            "checkpoint": path,
+        },
+        "Stopped {}: {}; checkpoint written to {}",
+        checkpoint.operation,
+        summary,
+        path.display()
+    );
+    Err(exit_code::Interrupted(format!("{} stopped with {}", checkpoint.operation, summary)).into())
+}
+
+/// Time requests in flight get to finish after Ctrl+C
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Cancel the shutdown token on Ctrl+C, so crawls, indexing and re-embedding stop cleanly
+///
+/// Requests in flight get `SHUTDOWN_GRACE` to finish, so the command can store what it
+/// completed and print a summary. A second Ctrl+C, or the grace period running out, exits
+/// at once.
+fn install_shutdown_handler() {
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\nStopping: finishing requests in flight (Ctrl+C again to exit immediately)...");
+        hal::cancel::shutdown_token().cancel();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = tokio::time::sleep(SHUTDOWN_GRACE) => {}
+        }
+        eprintln!("Exiting before the work in flight finished");
+        std::process::exit(exit_code::INTERRUPTED.into());
+    });
+}
+
+/// Print the outcome counts of a crawl and the pages that were not kept
+fn print_crawl_summary(report: &hal::crawler::report::CrawlReport) {
+    use hal::crawler::report::PageOutcome;
+
+    let summary = &report.summary;
+    if output::is_json() {
+        output::emit(
+            "crawl_summary",
+            format!("Crawl finished with {} URLs", summary.total()),
+            serde_json::to_value(summary).unwrap_or_default(),
+        );
+        return;
+    }
+    if output::is_quiet() {
+        return;
+    }
+
+    out!("\n{:<20} {:>6}", "Outcome", "Pages");
+    for (outcome, count) in [
+        (PageOutcome::Fetched, summary.fetched),
+        (PageOutcome::Redirected, summary.redirected),
+        (PageOutcome::NotModified, summary.not_modified),
+        (PageOutcome::EmptyContent, summary.empty_content),
+        (PageOutcome::Failed, summary.failed),
+        (PageOutcome::SkippedByRobots, summary.skipped_by_robots),
+        (PageOutcome::Skipped, summary.skipped),
+    ] {
+        out!("{:<20} {:>6}", outcome, count);
+    }
+    out!("{:<20} {:>6}\n", "total", summary.total());
+
+    for page in report.pages.iter().filter(|page| {
+        matches!(
+            page.outcome,
+            PageOutcome::Failed
+                | PageOutcome::EmptyContent
+                | PageOutcome::SkippedByRobots
+                | PageOutcome::Skipped
+        )
+    }) {
+        let reason = match (page.status_code, &page.detail) {
+            (_, Some(detail)) => detail.clone(),
+            (Some(status), None) => format!("HTTP {}", status),
+            (None, None) => String::new(),
+        };
+        out!("  {:<18} {} {}", page.outcome, page.url, reason);
+    }
+
+    for trap in &report.traps {
+        out!(
+            "Stopped following {}: {} ({} URLs skipped)",
+            trap.pattern,
+            trap.reason,
+            trap.skipped
+        );
+    }
+}
+
+#[instrument]
+async fn crawl_url(
+    source: &str,
+    max_depth: u32,
+    max_pages: u32,
+    keep_raw_html: bool,
+    crawler: &CrawlerArgs,
+    http_cache: std::collections::HashMap<String, hal::crawler::http_cache::HttpValidators>,
+) -> anyhow::Result<(Vec<CrawledPage>, hal::crawler::report::CrawlReport)> {
+    // info!("Check if page is already crawled");
+    // if let Ok(pages) = hal::crawler::storage::load_domain(source).await {
+    //     return Ok(pages
+    //         .into_iter()
+    //         .map(CrawledPage::from)
+    //         .collect::<Vec<CrawledPage>>());
+    // };
+    info!("Fetching {}...", source);
+
+    // Create crawler configuration
+    let config = crawler
+        .apply(hal::crawler::CrawlerConfig::builder())
+        .max_depth(max_depth)
+        .max_pages(max_pages)
+        .rate_limit_ms(500)
+        .respect_robots_txt(true)
+        .user_agent("hal-rag/0.1".to_string())
+        .exclude_selectors(vec![
+            "nav".to_string(),
+            "footer".to_string(),
+            "header".to_string(),
+            ".ads".to_string(),
+            "#comments".to_string(),
+        ])
+        .keep_raw_html(keep_raw_html)
+        .http_cache(http_cache)
+        .cancel(hal::cancel::shutdown_token())
+        .build();
+
+    // Crawl the website
+    hal::crawler::crawl_website_with_report(source, config)
+        .await
+        .context("crawl error")
+}
+
+/// Fetch pages through a source plugin or an API connector for `confluence://<space key>`
+/// and `notion://<database id>` sources, or transcribe `podcast:<feed url>` sources
+/// and audio files
+///
+/// Returns `None` for sources that are not handled by a plugin or connector.
+async fn connector_pages(source: &str, max_pages: u32) -> anyhow::Result<Option<Vec<CrawledPage>>> {
+    if let Some(plugin) = hal::plugin::registry().source(source) {
+        note!("Fetching {} with plugin {}...", source, plugin.name());
+        return Ok(Some(plugin.fetch(source, max_pages).await?));
+    }
+
+    if let Some(space_key) = source.strip_prefix("confluence://") {
+        #[cfg(feature = "confluence")]
+        {
+            note!("Fetching Confluence space {}...", space_key);
+            let connector = hal::crawler::connectors::ConfluenceConnector::from_env()?;
+            return Ok(Some(connector.fetch_space(space_key, max_pages).await?));
+        }
+        #[cfg(not(feature = "confluence"))]
+        return Err(anyhow!(
+            "Indexing Confluence space {} requires the `confluence` feature",
+            space_key
+        ));
+    }
+
+    if let Some(feed_url) = source.strip_prefix("podcast:") {
+        #[cfg(feature = "transcription")]
+        {
+            note!("Transcribing podcast feed {}...", feed_url);
+            let client = hal::crawler::transcription::TranscriptionClient::from_env()?;
+            return Ok(Some(
+                client.transcribe_feed(feed_url, max_pages as usize).await?,
+            ));
+        }
+        #[cfg(not(feature = "transcription"))]
+        return Err(anyhow!(
+            "Transcribing podcast feed {} requires the `transcription` feature",
+            feed_url
+        ));
+    }
+
+    let is_audio = !source.starts_with("http")
+        && std::path::Path::new(source)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                ["mp3", "m4a", "wav", "ogg", "flac", "webm"].contains(&ext.to_lowercase().as_str())
+            });
+    if is_audio {
+        #[cfg(feature = "transcription")]
+        {
+            note!("Transcribing {}...", source);
+            let client = hal::crawler::transcription::TranscriptionClient::from_env()?;
+            let page = client.transcribe_file(std::path::Path::new(source)).await?;
+            return Ok(Some(vec![page]));
+        }
+        #[cfg(not(feature = "transcription"))]
+        return Err(anyhow!(
+            "Transcribing {} requires the `transcription` feature",
+            source
+        ));
+    }
+
+    if let Some(database_id) = source.strip_prefix("notion://") {
+        #[cfg(feature = "notion")]
+        {
+            note!("Fetching Notion database {}...", database_id);
+            let connector = hal::crawler::connectors::NotionConnector::from_env()?;
+            return Ok(Some(
+                connector.fetch_database(database_id, max_pages).await?,
+            ));
+        }
+        #[cfg(not(feature = "notion"))]
+        return Err(anyhow!(
+            "Indexing Notion database {} requires the `notion` feature",
+            database_id
+        ));
+    }
+
+    Ok(None)
+}
+
+/// Load messages from an `.mbox` file or a Maildir directory as pages
+///
+/// Returns `None` for sources that are not mail archives.
+fn mail_pages(source: &std::path::Path) -> anyhow::Result<Option<Vec<CrawledPage>>> {
+    use hal::crawler::mail;
+
+    let messages = if source.extension().is_some_and(|ext| ext == "mbox") {
+        let content = std::fs::read(source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        mail::parse_mbox(&String::from_utf8_lossy(&content))
+    } else if source.is_dir() && mail::is_maildir(source) {
+        mail::load_maildir(source)?
+    } else {
+        return Ok(None);
+    };
+
+    let mailbox: String = source
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let mailbox = if mailbox.trim_matches('-').is_empty() {
+        "mail".to_string()
+    } else {
+        mailbox
+    };
+
+    Ok(Some(
+        messages
+            .into_iter()
+            .enumerate()
+            .map(|(i, message)| message.into_page(&mailbox, i))
+            .collect(),
+    ))
+}
+
+/// Whether an index source is a Markdown file or a directory of Markdown files
+fn is_markdown_source(path: &std::path::Path) -> bool {
+    path.is_dir()
+        || path
+            .extension()
+            .is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+/// The root directory of a Markdown source and the corpus its pages are indexed under
+///
+/// The corpus is the lowercased name of the source directory, or of the directory
+/// containing a single file.
+fn markdown_corpus(source: &std::path::Path) -> anyhow::Result<(std::path::PathBuf, String)> {
+    let source = source
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", source.display()))?;
+    let root = if source.is_dir() {
+        source
+    } else {
+        source
+            .parent()
+            .map(std::path::Path::to_path_buf)
+            .unwrap_or_default()
+    };
+
+    let corpus: String = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let corpus = if corpus.trim_matches('-').is_empty() {
+        "local".to_string()
+    } else {
+        corpus
+    };
+
+    Ok((root, corpus))
+}
+
+/// The `file://<corpus>/<relative path>` URL of a Markdown file
+fn markdown_page_url(root: &std::path::Path, corpus: &str, file: &std::path::Path) -> String {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    let relative = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("file://{}/{}", corpus, relative)
+}
+
+/// Load Markdown files as pages, recursing into directories
+///
+/// Pages get `file://<corpus>/<relative path>` URLs, where the corpus is the name of
+/// the source directory, so each corpus is indexed as its own website. Front matter
+/// is left in the content for the processor to extract.
+fn load_markdown_pages(source: &std::path::Path) -> anyhow::Result<Vec<CrawledPage>> {
+    let (root, corpus) = markdown_corpus(source)?;
+
+    let mut files = Vec::new();
+    let mut dirs = vec![source.canonicalize()?];
+    while let Some(path) = dirs.pop() {
+        if path.is_dir() {
+            for entry in std::fs::read_dir(&path)? {
+                dirs.push(entry?.path());
+            }
+        } else if is_markdown_source(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    markdown_file_pages(&root, &corpus, &files)
+}
+
+/// Read Markdown files below a corpus root as pages
+fn markdown_file_pages(
+    root: &std::path::Path,
+    corpus: &str,
+    files: &[std::path::PathBuf],
+) -> anyhow::Result<Vec<CrawledPage>> {
+    let mut pages = Vec::new();
+    for file in files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+
+        pages.push(CrawledPage {
+            url: markdown_page_url(root, corpus, file),
+            content,
+            metadata: hal::crawler::PageMetadata {
+                title: file.file_stem().map(|s| s.to_string_lossy().to_string()),
+                description: None,
+                publication_date: None,
+                author: None,
+                domain: corpus.to_string(),
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        });
+    }
+
+    Ok(pages)
+}
+
+/// Load the Markdown files of a git repository changed since the commit it was last indexed at
+///
+/// Chunks of deleted files are removed right away. Without a recorded commit, or if the
+/// recorded commit is no longer in the repository, every file is loaded.
+///
+/// # Returns
+///
+/// The pages to index, the corpus and the `HEAD` commit to record once they are indexed
+async fn git_diff_markdown_pages(
+    db: &hal::index::Database,
+    source: &std::path::Path,
+) -> anyhow::Result<(Vec<CrawledPage>, String, String)> {
+    use hal::crawler::git;
+
+    if !source.is_dir() {
+        return Err(anyhow!(
+            "--git-diff requires a directory in a git repository, got {}",
+            source.display()
+        ));
+    }
+    let (root, corpus) = markdown_corpus(source)?;
+    let head = git::head_commit(&root)?;
+
+    let changes = match db.get_indexed_commit(&corpus).await? {
+        Some(commit) if commit == head => {
+            out!("{} is up to date at commit {}", corpus, head);
+            return Ok((Vec::new(), corpus, head));
+        }
+        Some(commit) => match git::changed_files(&root, &commit) {
+            Ok(changes) => {
+                note!("Comparing {} with the indexed commit {}...", head, commit);
+                changes
+            }
+            Err(e) => {
+                out!(
+                    "Reindexing all files: cannot compare with the indexed commit {}: {}",
+                    commit,
+                    e
+                );
+                return Ok((load_markdown_pages(source)?, corpus, head));
+            }
+        },
+        None => {
+            note!("No indexed commit for {}, indexing all files...", corpus);
+            return Ok((load_markdown_pages(source)?, corpus, head));
+        }
+    };
+
+    let mut removed_chunks = 0;
+    let deleted: Vec<_> = changes
+        .deleted
+        .iter()
+        .map(|path| root.join(path))
+        .filter(|path| is_markdown_source(path))
+        .collect();
+    for file in &deleted {
+        removed_chunks += db
+            .delete_chunks_by_page_url(&markdown_page_url(&root, &corpus, file))
+            .await?;
+    }
+    if !deleted.is_empty() {
+        out!(
+            "Removed {} chunks of {} deleted files",
+            removed_chunks,
+            deleted.len()
+        );
+    }
+
+    let mut changed: Vec<_> = changes
+        .changed
+        .iter()
+        .map(|path| root.join(path))
+        .filter(|path| path.is_file() && is_markdown_source(path))
+        .collect();
+    changed.sort();
+    Ok((markdown_file_pages(&root, &corpus, &changed)?, corpus, head))
+}
+
+#[instrument]
+async fn index_command(args: IndexArgs) -> anyhow::Result<()> {
+    exit_code::require_api_key("GEMINI_API_KEY")?;
+    let client = hal::model::Client::new_gemini_chain_from_env()?
+        .with_embedding_config(&hal::config::current().embedding)?;
+    let client = with_completion_cache(client, args.no_cache);
+
+    // Create database connection
+    let db = open_database().await?;
+
+    // Set max_depth and max_pages based on the single argument
+    let (max_depth, max_pages) = if args.single {
+        (0, 1) // Set to 0 and 1 if single is true
+    } else {
+        (args.max_depth, args.max_pages) // Use provided values otherwise
+    };
+
+    let mut crawl_report = None;
+    let mut indexed_commit = None;
+    let pages = if let Some(pages) = connector_pages(&args.source, max_pages).await? {
+        pages
+    } else if args.source.starts_with("http") {
+        // Revalidate the pages of the last crawl unless every page is to be fetched again
+        let http_cache = if args.force || args.single {
+            Default::default()
+        } else {
+            let domain = url::Url::parse(&args.source)?
+                .host_str()
+                .unwrap_or_default()
+                .to_string();
+            db.get_http_cache(&domain).await?
+        };
+        let (pages, report) = crawl_url(
+            &args.source,
+            max_depth,
+            max_pages,
+            args.store_html,
+            &args.crawler,
+            http_cache,
+        )
+        .await?;
+        if report.summary.not_modified > 0 {
+            out!(
+                "Skipping {} pages unchanged since the last crawl",
+                report.summary.not_modified
+            );
+        }
+        crawl_report = Some(report);
+        pages
+    } else if let Some(pages) = mail_pages(std::path::Path::new(&args.source))? {
+        out!("Loaded {} messages from {}", pages.len(), args.source);
+        pages
+    } else if is_markdown_source(std::path::Path::new(&args.source)) {
+        note!("Loading Markdown from {}...", args.source);
+        if args.git_diff {
+            let (pages, corpus, head) =
+                git_diff_markdown_pages(&db, std::path::Path::new(&args.source)).await?;
+            indexed_commit = Some((corpus, head));
+            pages
+        } else {
+            load_markdown_pages(std::path::Path::new(&args.source))?
+        }
+    } else {
+        note!("Loading from file {}...", args.source);
+
+        // Read file
+        let content = tokio::fs::read_to_string(&args.source).await?;
+
+        // Parse JSON
+        serde_json::from_str(&content)?
+    };
+
+    note!("Processing {} pages...", pages.len());
+
+    // Pages indexed so far are kept when the process is asked to stop
+    let cancel = hal::cancel::shutdown_token();
+
+    // Create processor options
+    let processor_config = hal::processor::ProcessorConfig::builder()
+        .chunk_options(hal::processor::ChunkOptions {
+            target_chunk_size: args.chunk_size,
+            overlap_size: args.chunk_size / 10,
+            overlap_sentences: args.overlap_sentences,
+            ..hal::processor::ChunkOptions::default()
+        })
+        .llm_model(args.model.clone())
+        .generation(args.generation.options())
+        .embedding_model(hal::config::current().embedding.model_name())
+        .embedding_dimensions(768)
+        .segment_by_heading(args.segment_by_heading)
+        .context_mode(args.context_mode)
+        .context_batch_size(args.context_batch_size)
+        .concurrency(args.concurrency)
+        .skip_summary(args.no_summary)
+        .source_chunk_overrides(args.chunk_overrides.clone())
+        .cancel(cancel.clone())
+        .build();
+
+    // Configure the safety checks run before content enters the index
+    let mut safety = hal::processor::SafetyFilter::new(args.safety_action);
+    if let Some(path) = &args.safety_keywords {
+        safety = safety.with_keyword_file(path)?;
+    }
+    if args.moderate {
+        safety = safety.with_moderation(hal::processor::safety::ModerationClient::from_env()?);
+    }
+
+    // Process and index pages
+    let mut total_chunks = 0;
+    let mut indexed_pages = 0;
+
+    // Track unique base URLs to count pages per website
+    use std::collections::HashMap;
+    use url::Url;
+
+    let mut website_pages = HashMap::new();
+
+    // Group pages by base URL
+    for page in &pages {
+        if let Ok(parsed_url) = Url::parse(&page.url) {
+            if let Some(host) = parsed_url.host_str() {
+                let base_url = format!("{}://{}", parsed_url.scheme(), host);
+                website_pages
+                    .entry(base_url)
+                    .or_insert_with(Vec::new)
+                    .push(page);
+            }
+        }
+    }
+
+    // Get the number of websites before consuming the HashMap
+    let website_count = website_pages.len();
+
+    // Process and index pages by website
+    let mut done = std::collections::HashSet::new();
+    'websites: for (base_url, site_pages) in website_pages {
+        note!(
+            "Processing website: {} ({} pages)",
+            base_url,
+            site_pages.len()
+        );
+
+        // With --atomic, pages count as done only once the website is swapped in
+        let staging = if args.atomic {
+            Some(db.begin_staging(&base_url).await?)
+        } else {
+            None
+        };
+        let mut staged_pages = Vec::new();
+
+        for page in site_pages {
+            if cancel.is_cancelled() {
+                if let Some(website_id) = staging {
+                    db.discard_staged(website_id).await?;
+                }
+                break 'websites;
+            }
+
+            // Mask personal data and keys before anything is summarized, embedded or stored
+            let mut page = page.clone();
+            let redactions = hal::processor::redact_page(&client, &mut page, args.redact).await?;
+            if redactions.total() > 0 {
+                emit!(
+                    "page_redacted",
+                    { "url": page.url, "counts": redactions },
+                    "Redacted {} values from {}",
+                    redactions.total(),
+                    page.url
+                );
+            }
+
+            let reasons = safety.check_page(&page.content).await?;
+            if !reasons.is_empty() {
+                db.quarantine(&page.url, None, &page.content, &reasons, safety.action)
+                    .await?;
+                emit!(
+                    "page_quarantined",
+                    { "url": page.url, "reasons": reasons, "action": safety.action },
+                    "Quarantined {}: {}",
+                    page.url,
+                    reasons.join(", ")
+                );
+                if safety.action == hal::processor::SafetyAction::Drop {
+                    done.insert(page.url.clone());
+                    continue;
+                }
+            }
+
+            // Process content; a cancelled page is left out as a whole
+            let chunks = match hal::processor::process_content(
+                &client,
+                page.clone(),
+                processor_config.clone(),
+            )
+            .await
+            {
+                Ok(chunks) => chunks,
+                Err(hal::processor::ProcessError::Cancelled) => {
+                    if let Some(website_id) = staging {
+                        db.discard_staged(website_id).await?;
+                    }
+                    break 'websites;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let chunks = filter_unsafe_chunks(&db, &safety, &page.url, chunks).await?;
+            total_chunks += chunks.len();
+            indexed_pages += 1;
+
+            emit!(
+                "page_indexed",
+                { "url": page.url, "chunks": chunks.len() },
+                "Indexing {} chunks from {}...",
+                chunks.len(),
+                page.url
+            );
+
+            // Update website index
+            match staging {
+                Some(website_id) => {
+                    db.stage_page_index(website_id, &page.url, chunks).await?;
+                    staged_pages.push(page.url.clone());
+                }
+                None => {
+                    db.update_website_index(&page.url, chunks).await?;
+                    done.insert(page.url.clone());
+                }
+            }
+            if let Some(html) = &page.raw_html {
+                db.store_raw_html(&page.url, html).await?;
+            }
+            if args.redact != hal::processor::Redaction::Off {
+                db.record_redactions(&page.url, args.redact, &redactions)
+                    .await?;
+            }
+        }
+
+        if let Some(website_id) = staging {
+            let swap = match db.swap_staged(website_id).await {
+                Ok(swap) => swap,
+                Err(e) => {
+                    db.discard_staged(website_id).await?;
+                    return Err(anyhow!("Re-index of {} was discarded: {}", base_url, e));
+                }
+            };
+            emit!(
+                "website_swapped",
+                {
+                    "website": base_url,
+                    "pages": swap.pages,
+                    "chunks": swap.chunks,
+                    "replaced_chunks": swap.replaced_chunks
+                },
+                "Swapped in {} chunks of {} pages from {}, replacing {} chunks",
+                swap.chunks,
+                swap.pages,
+                base_url,
+                swap.replaced_chunks
+            );
+            done.extend(staged_pages);
+        }
+    }
+
+    emit!(
+        "index_completed",
+        { "chunks": total_chunks, "pages": indexed_pages, "websites": website_count },
+        "Indexed {} chunks across {} pages from {} websites",
+        total_chunks,
+        indexed_pages,
+        website_count
+    );
+
+    // A cancelled run records what it completed and skips the bookkeeping of a full run
+    if cancel.is_cancelled() {
+        let (completed, remaining): (Vec<String>, Vec<String>) = pages
+            .iter()
+            .map(|page| page.url.clone())
+            .partition(|url| done.contains(url));
+        let skipped = remaining.len();
+        let checkpoint = hal::cancel::Checkpoint::new("index", &args.source, completed, remaining);
+        return cancelled(checkpoint, Some(skipped));
+    }
+
+    // Compare the next --git-diff run against the commit just indexed
+    if let Some((corpus, head)) = indexed_commit {
+        db.set_indexed_commit(&corpus, &head).await?;
+    }
+
+    // Remember which pages this crawl found so pages that disappear can be collected
+    if let Some(report) = crawl_report {
+        // Only now that the pages are indexed may the next crawl skip them
+        for (url, validators) in report.validators() {
+            db.store_http_validators(url, validators).await?;
+        }
+
+        let domain = url::Url::parse(&args.source)?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+        // Unchanged pages are not fetched, so pages linked only from them are missing
+        // from the crawl even though they still exist
+        let complete =
+            report.requested_pages() < max_pages as usize && report.summary.not_modified == 0;
+        db.record_crawl(&domain, &report.live_urls(), complete)
+            .await?;
+
+        if args.gc {
+            if complete {
+                let gc = db.collect_garbage(&domain, false).await?;
+                out!(
+                    "Removed {} chunks of {} pages no longer found on {}",
+                    gc.deleted_chunks,
+                    gc.stale_pages.len(),
+                    domain
+                );
+            } else if report.summary.not_modified > 0 {
+                out!(
+                    "Skipping garbage collection: links of unchanged pages were not followed; \
+                     pass --force to crawl every page"
+                );
+            } else {
+                out!("Skipping garbage collection: the crawl stopped at the page limit");
+            }
+        }
+        partial_crawl_failure(&report)?;
+    }
+
+    Ok(())
+}
+
+/// Quarantine the chunks failing the keyword checks, dropping them unless they are only flagged
+async fn filter_unsafe_chunks(
+    db: &hal::index::Database,
+    safety: &hal::processor::SafetyFilter,
+    url: &str,
+    chunks: Vec<hal::processor::ProcessedChunk>,
+) -> anyhow::Result<Vec<hal::processor::ProcessedChunk>> {
+    let mut kept = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let reasons = safety.check_chunk(&chunk.text);
+        if reasons.is_empty() {
+            kept.push(chunk);
+            continue;
+        }
+
+        let position = chunk.metadata.position as i64;
+        db.quarantine(url, Some(position), &chunk.text, &reasons, safety.action)
+            .await?;
+        out!(
+            "Quarantined chunk {} of {}: {}",
+            position,
+            url,
+            reasons.join(", ")
+        );
+        if safety.action == hal::processor::SafetyAction::Flag {
+            kept.push(chunk);
+        }
+    }
+    Ok(kept)
+}
+
+#[instrument]
+async fn search_command(args: SearchArgs) -> anyhow::Result<()> {
+    if let Some(batch) = &args.batch {
+        return batch_search_command(&args, batch).await;
+    }
+
+    // Create database connections; history and the answer cache use the first one
+    let databases = open_search_databases(&args).await?;
+    let db = &databases[0].1;
+
+    exit_code::require_api_key("GEMINI_FREE_API_KEY")?;
+    let client = hal::model::Client::new_gemini_free_chain_from_env()?
+        .with_embedding_config(&hal::config::current().embedding)?;
+
+    // Replay a recorded search or create search options from the arguments
+    let (query, options) = match args.replay {
+        Some(id) => {
+            let entry = hal::search::history::get_history_entry(db, id)
+                .await?
+                .ok_or_else(|| anyhow!("No search history entry with ID {}", id))?;
+            (entry.query, entry.options)
+        }
+        None => (
+            args.query.clone().unwrap_or_default(),
+            search_options(&args),
+        ),
+    };
+
+    // Read the schema before any model is called
+    let schema = args.schema.as_deref().map(read_schema).transpose()?;
+
+    note!("Searching for: {}", query);
+
+    let embedding_blob = hal::search::embed_query(&client, &query).await?;
+
+    // Reuse the answer of a similar query when possible; cached answers are free text in
+    // the language of their query, generated in one pass from chunks without per-source
+    // answers
+    let cacheable = schema.is_none()
+        && args.answer_language.is_none()
+        && !args.compare
+        && !args.map_reduce
+        && args.retrieval_mode.resolve(&args.model)
+            == hal::search::long_context::RetrievalMode::Chunks;
+    if !args.vector_search_only && !args.no_cache && cacheable {
+        let config = hal::search::answer_cache::AnswerCacheConfig {
+            similarity_threshold: args.cache_threshold,
+            ttl_seconds: args.cache_ttl_hours * 3600,
+        };
+        match hal::search::answer_cache::lookup_cached_answer(
+            db,
+            &embedding_blob,
+            hal::search::answer_cache::AnswerKey {
+                model: &args.model,
+                options: &options,
+                generation: &args.generation.options(),
+            },
+            &config,
+        )
+        .await
+        {
+            Ok(Some(cached)) => return print_cached_answer(&query, &cached, &args.format),
+            Ok(None) => {}
+            Err(e) => info!("Skipping answer cache: {}", e),
+        }
+    }
+
+    // Search the index, or all indexes when several were given
+    let mut results = search_databases(
+        &args,
+        &databases,
+        &client,
+        &query,
+        &embedding_blob,
+        &options,
+    )
+    .await?;
+
+    if args.detect_injection {
+        hal::search::sanitize::detect_injections_with_llm(&client, &mut results).await?;
+    }
+
+    // If vector search only, output results directly
+    if args.vector_search_only {
+        record_search_history(db, &args, &query, &options, &results, None).await;
+        record_usage(&databases, &results).await;
+
+        // Output results
+        match args.format.as_str() {
+            "json" | "jsonl" => {
+                output::print_json(&results)?;
+            }
+            _ => {
+                out!("Found {} results", results.len());
+                for (i, result) in results.iter().enumerate() {
+                    // Show the matching sentences rather than the whole chunk
+                    let text = if result.snippets.is_empty() {
+                        result.text.clone()
+                    } else {
+                        let snippets: Vec<String> = result
+                            .snippets
+                            .iter()
+                            .map(|snippet| snippet.marked("**", "**"))
+                            .collect();
+                        snippets.join(" … ")
+                    };
+                    out!("{}. {}", i + 1, text);
+                    out!("   ID: {}", result.chunk_id);
+                    if let Some(database) = &result.database {
+                        out!("   Index: {}", database);
+                    }
+                    out!("   URL: {}", result.url);
+                    if let Some(heading_path) = &result.heading_path {
+                        out!("   Section: {}", heading_path);
+                    }
+                    if let Some(doc_version) = &result.doc_version {
+                        out!("   Version: {}", doc_version);
+                    }
+                    if let Some(content_type) = &result.content_type {
+                        out!("   Type: {}", content_type);
+                    }
+                    if let Some(language) = &result.language {
+                        out!("   Language: {}", language);
+                    }
+                    if let Some(author) = &result.author {
+                        out!("   Author: {}", author);
+                    }
+                    if let Some(date) = result
+                        .published_at
+                        .and_then(|published_at| chrono::DateTime::from_timestamp(published_at, 0))
+                    {
+                        out!("   Published: {}", date.format("%Y-%m-%d"));
+                    }
+                    out!("   Context: {}", result.context);
+                    for finding in &result.injection_findings {
+                        out!("   Warning: {}", finding);
+                    }
+                    out!();
+                }
+            }
+        }
+    } else {
+        // Use RAG to generate an answer
+        note!("Generating answer using RAG...");
+
+        // Prepare context from search results, or from their pages in page mode
+        let context = rag_context(&args, &databases, &results).await?;
+
+        // Generate answer using LLM, as JSON conforming to the schema if one was given
+        let structured_answer = match &schema {
+            Some(schema) => Some(
+                hal::search::structured::generate_structured_answer(
+                    &client,
+                    &query,
+                    &context,
+                    schema,
+                    &args.generation.options(),
+                    args.answer_language.as_deref(),
+                )
+                .await?,
+            ),
+            None => None,
+        };
+        let (answer, source_answers) = match &structured_answer {
+            Some(value) => (serde_json::to_string_pretty(value)?, Vec::new()),
+            None => {
+                text_answer(
+                    &args,
+                    &client,
+                    &query,
+                    &context,
+                    &results,
+                    &args.generation.options(),
+                )
+                .await?
+            }
+        };
+        record_search_history(db, &args, &query, &options, &results, Some(&answer)).await;
+        record_usage(&databases, &results).await;
+        let follow_ups = suggest_follow_ups(&args, &client, &query, &answer, &context).await;
+
+        if !args.no_cache && !db.is_read_only() && cacheable {
+            let stored = hal::search::answer_cache::store_cached_answer(
+                db,
+                &query,
+                &embedding_blob,
+                hal::search::answer_cache::AnswerKey {
+                    model: &args.model,
+                    options: &options,
+                    generation: &args.generation.options(),
+                },
+                &answer,
+                &results,
+            )
+            .await;
+            if let Err(e) = stored {
+                tracing::warn!("Failed to cache answer: {}", e);
+            }
+        }
+
+        // Output results
+        match args.format.as_str() {
+            "json" | "jsonl" => {
+                let answer = structured_answer.unwrap_or_else(|| answer.clone().into());
+                let mut json = rag_json(&query, answer, &results, &follow_ups);
+                if !source_answers.is_empty() {
+                    json["source_answers"] = serde_json::json!(source_answers);
+                }
+                output::print_json(&json)?;
+            }
+            _ => {
+                out!("\nAnswer:");
+                out!("{}", answer);
+                out!("\nSources:");
+                for (i, result) in results.iter().enumerate() {
+                    match &result.heading_path {
+                        Some(heading_path) => out!(
+                            "{}. [{}] {} ({})",
+                            i + 1,
+                            result.chunk_id,
+                            result.url,
+                            heading_path
+                        ),
+                        None => out!("{}. [{}] {}", i + 1, result.chunk_id, result.url),
+                    }
+                    for snippet in &result.snippets {
+                        out!("   > {}", snippet.marked("**", "**"));
+                    }
+                    for finding in &result.injection_findings {
+                        out!("   Warning: {}", finding);
+                    }
+                }
+                if !follow_ups.is_empty() {
+                    out!("\nFollow-up questions:");
+                    for question in &follow_ups {
+                        out!("- {}", question);
+                    }
+                }
+                out!();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The JSON output of a RAG answer, its sources and any follow-up questions
+fn rag_json(
+    query: &str,
+    answer: serde_json::Value,
+    results: &[hal::search::SearchResult],
+    follow_ups: &[String],
+) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "query": query,
+        "answer": answer,
+        "sources": results.iter().map(|r| {
+            serde_json::json!({
+                "id": r.chunk_id,
+                "text": r.text,
+                "url": r.url,
+                "section": r.heading_path,
+                "language": r.language,
+                "context": r.context,
+                "snippets": r.snippets,
+                "injection_findings": r.injection_findings
+            })
+        }).collect::<Vec<_>>()
+    });
+    if !follow_ups.is_empty() {
+        json["follow_ups"] = serde_json::json!(follow_ups);
+    }
+    json
+}
+
+/// Answer a query in free text from all sources at once, or per source page with
+/// `--map-reduce`
+///
+/// # Returns
+///
+/// The answer, and with `--map-reduce` the answers of the pages it was combined from
+///
+/// With `--compare`, comparison queries are answered with a table instead.
+async fn text_answer<C, E>(
+    args: &SearchArgs,
+    client: &hal::model::Client<C, E>,
+    query: &str,
+    context: &str,
+    results: &[hal::search::SearchResult],
+    generation: &hal::model::GenerationOptions,
+) -> anyhow::Result<(String, Vec<hal::search::map_reduce::SourceAnswer>)>
+where
+    C: rig::completion::CompletionModel,
+    E: rig::embeddings::EmbeddingModel,
+{
+    if let Some(comparison) = comparison(args, query) {
+        let answer = hal::search::compare::generate_comparison_answer(
+            client,
+            query,
+            &comparison,
+            context,
+            generation,
+            args.answer_language.as_deref(),
+        )
+        .await?;
+        return Ok((answer, Vec::new()));
+    }
+    if args.map_reduce {
+        let answer = hal::search::map_reduce::generate_map_reduce_answer(
+            client,
+            query,
+            results,
+            generation,
+            args.answer_language.as_deref(),
+        )
+        .await?;
+        return Ok((answer.answer, answer.sources));
+    }
+    let answer = hal::search::generate_answer_with_rag(
+        client,
+        query,
+        context,
+        &args.model,
+        generation,
+        args.answer_language.as_deref(),
+    )
+    .await?;
+    Ok((answer, Vec::new()))
+}
+
+/// Suggest follow-up questions with `--follow-ups`
+///
+/// Failing to suggest questions only loses the suggestions, not the answer.
+async fn suggest_follow_ups<C, E>(
+    args: &SearchArgs,
+    client: &hal::model::Client<C, E>,
+    query: &str,
+    answer: &str,
+    context: &str,
+) -> Vec<String>
+where
+    C: rig::completion::CompletionModel,
+    E: rig::embeddings::EmbeddingModel,
+{
+    if !args.follow_ups {
+        return Vec::new();
+    }
+    hal::search::follow_up::suggest_follow_ups(
+        client,
+        query,
+        answer,
+        context,
+        hal::search::follow_up::DEFAULT_FOLLOW_UPS,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        tracing::warn!("Failed to suggest follow-up questions: {}", e);
+        Vec::new()
+    })
+}
+
+/// Read a JSON schema file for `search --schema`
+fn read_schema(path: &Path) -> anyhow::Result<serde_json::Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not valid JSON", path.display()))
+}
+
+/// Answer every question of a file, writing one JSON result per line
+///
+/// Questions share the databases, client and search options, and up to `--concurrency`
+/// are answered at once. With `--output`, lines are appended to the file and questions it
+/// already answered are skipped, so an interrupted batch resumes where it stopped. Failed
+/// questions get a line with an `error` field and are retried on the next run.
+async fn batch_search_command(args: &SearchArgs, batch: &Path) -> anyhow::Result<()> {
+    use futures::StreamExt;
+    use std::io::Write;
+
+    let content = std::fs::read_to_string(batch)
+        .with_context(|| format!("Failed to read {}", batch.display()))?;
+    let questions = batch_questions(&content);
+    let answered = match &args.output {
+        Some(path) if path.exists() => answered_questions(&std::fs::read_to_string(path)?),
+        _ => std::collections::HashSet::new(),
+    };
+    let pending: Vec<String> = questions
+        .into_iter()
+        .filter(|question| !answered.contains(question))
+        .collect();
+    if !answered.is_empty() {
+        note!("Skipping {} questions already answered", answered.len());
+    }
+
+    let databases = open_search_databases(args).await?;
+    exit_code::require_api_key("GEMINI_FREE_API_KEY")?;
+    let client = hal::model::Client::new_gemini_free_chain_from_env()?
+        .with_embedding_config(&hal::config::current().embedding)?;
+    let schema = args.schema.as_deref().map(read_schema).transpose()?;
+    let options = search_options(args);
+    let generation = args.generation.options();
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?,
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+
+    let total = pending.len();
+    let mut answers = futures::stream::iter(pending)
+        .map(|query| {
+            let (databases, client, options) = (&databases, &client, &options);
+            let (schema, generation) = (schema.as_ref(), &generation);
+            async move {
+                let answer =
+                    batch_answer(args, databases, client, &query, options, schema, generation)
+                        .await;
+                (query, answer)
+            }
+        })
+        .buffer_unordered(args.concurrency.max(1));
+
+    let mut done = 0;
+    let mut failed = 0;
+    while let Some((query, answer)) = answers.next().await {
+        done += 1;
+        let line = answer.unwrap_or_else(|e| {
+            failed += 1;
+            serde_json::json!({ "query": query, "error": format!("{:#}", e) })
+        });
+        writeln!(writer, "{}", line)?;
+        // Keep every answer written so far if the batch is interrupted
+        writer.flush()?;
+        if args.output.is_some() {
+            note!("[{}/{}] {}", done, total, query);
+        }
+    }
+
+    if failed > 0 {
+        return Err(
+            exit_code::PartialFailure(format!("{} of {} questions failed", failed, total)).into(),
+        );
+    }
+    Ok(())
+}
+
+/// The questions of a batch file: non-empty lines not starting with `#`
+fn batch_questions(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// The questions answered without error in existing batch output
+fn answered_questions(output: &str) -> std::collections::HashSet<String> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|line| line.get("error").is_none())
+        .filter_map(|line| line["query"].as_str().map(String::from))
+        .collect()
+}
+
+/// Search for and answer one batch question
+async fn batch_answer<C, E>(
+    args: &SearchArgs,
+    databases: &[(String, hal::index::Database)],
+    client: &hal::model::Client<C, E>,
+    query: &str,
+    options: &hal::search::SearchOptions,
+    schema: Option<&serde_json::Value>,
+    generation: &hal::model::GenerationOptions,
+) -> anyhow::Result<serde_json::Value>
+where
+    C: rig::completion::CompletionModel,
+    E: rig::embeddings::EmbeddingModel,
+{
+    let embedding_blob = hal::search::embed_query(client, query).await?;
+    let mut results =
+        search_databases(args, databases, client, query, &embedding_blob, options).await?;
+    if args.detect_injection {
+        hal::search::sanitize::detect_injections_with_llm(client, &mut results).await?;
+    }
+    record_usage(databases, &results).await;
+    if args.vector_search_only {
+        return Ok(serde_json::json!({ "query": query, "results": results }));
+    }
+
+    let context = rag_context(args, databases, &results).await?;
+    let answer = match schema {
+        Some(schema) => {
+            hal::search::structured::generate_structured_answer(
+                client,
+                query,
+                &context,
+                schema,
+                generation,
+                args.answer_language.as_deref(),
+            )
+            .await?
+        }
+        None => {
+            let (answer, source_answers) =
+                text_answer(args, client, query, &context, &results, generation).await?;
+            let follow_ups = suggest_follow_ups(args, client, query, &answer, &context).await;
+            let mut json = rag_json(query, answer.into(), &results, &follow_ups);
+            if !source_answers.is_empty() {
+                json["source_answers"] = serde_json::json!(source_answers);
+            }
+            return Ok(json);
+        }
+    };
+    let follow_ups = suggest_follow_ups(args, client, query, &answer.to_string(), &context).await;
+    Ok(rag_json(query, answer, &results, &follow_ups))
+}
+
+/// Prepare the RAG context of the results as `--retrieval-mode` selects
+///
+/// Page mode sends the full text of the top pages, filling half of the model's context
+/// window; chunks are sent when not even the best page fits.
+async fn rag_context(
+    args: &SearchArgs,
+    databases: &[(String, hal::index::Database)],
+    results: &[hal::search::SearchResult],
+) -> anyhow::Result<String> {
+    use hal::search::long_context::{RetrievalMode, context_window, prepare_page_context};
+
+    if args.retrieval_mode.resolve(&args.model) == RetrievalMode::Pages {
+        let budget = context_window(&args.model) / 2;
+        let (context, pages) = prepare_page_context(databases, results, budget).await?;
+        if pages > 0 {
+            info!("Answering from {} full pages", pages);
+            return Ok(context);
+        }
+        tracing::warn!("No page fits the context window, answering from chunks");
+    }
+    Ok(hal::search::prepare_rag_context(results))
+}
+
+/// Search the databases for an embedded query, highlighting snippets in the results
+///
+/// A single database is searched directly and several are searched as one federated
+/// index. With `--translate-query`, the query is also translated into each target language
+/// and the results of all versions are merged. With `--compare`, each item of a comparison
+/// query is searched as well and the results of all searches are interleaved.
+async fn search_databases<C, E>(
+    args: &SearchArgs,
+    databases: &[(String, hal::index::Database)],
+    client: &hal::model::Client<C, E>,
+    query: &str,
+    embedding_blob: &[u8],
+    options: &hal::search::SearchOptions,
+) -> anyhow::Result<Vec<hal::search::SearchResult>>
+where
+    C: rig::completion::CompletionModel,
+    E: rig::embeddings::EmbeddingModel,
+{
+    let mut searches = vec![search_embedding(databases, embedding_blob, options).await?];
+    let mut queries = vec![query.to_string()];
+    if args.translate_query {
+        let translations =
+            hal::search::language::translate_query(client, query, &options.target_languages)
+                .await?;
+        for (language, translation) in translations {
+            info!("Searching in {}: {}", language, translation);
+            let embedding_blob = hal::search::embed_query(client, &translation).await?;
+            searches.push(search_embedding(databases, &embedding_blob, options).await?);
+            queries.push(translation);
+        }
+    }
+
+    let mut results = match searches.len() {
+        1 => searches.remove(0),
+        _ => hal::search::language::merge_results(searches, options.limit),
+    };
+    if let Some(comparison) = comparison(args, query) {
+        let mut searches = Vec::new();
+        for item_query in comparison.item_queries() {
+            info!("Searching for compared item: {}", item_query);
+            let embedding_blob = hal::search::embed_query(client, &item_query).await?;
+            searches.push(search_embedding(databases, &embedding_blob, options).await?);
+            queries.push(item_query);
+        }
+        searches.push(results);
+        results = hal::search::compare::interleave_results(searches, options.limit);
+    }
+    // Terms of the translations highlight sentences in the target languages
+    hal::search::snippets::highlight_results(
+        &queries.join(" "),
+        &mut results,
+        hal::search::snippets::DEFAULT_SNIPPET_SENTENCES,
+    );
+    Ok(results)
+}
+
+/// The items a query compares, with `--compare`
+fn comparison(args: &SearchArgs, query: &str) -> Option<hal::search::compare::Comparison> {
+    if !args.compare {
+        return None;
+    }
+    hal::search::compare::detect_comparison(query)
+}
+
+/// Search one database, or several as a federated index
+async fn search_embedding(
+    databases: &[(String, hal::index::Database)],
+    embedding_blob: &[u8],
+    options: &hal::search::SearchOptions,
+) -> Result<Vec<hal::search::SearchResult>, hal::search::SearchError> {
+    if databases.len() > 1 {
+        hal::search::search_federated(databases, embedding_blob, options.clone()).await
+    } else {
+        hal::search::search_index_with_embedding(&databases[0].1, embedding_blob, options.clone())
+            .await
+    }
+}
+
+/// Open the databases to search
+///
+/// A single database keeps the default behaviour of connecting to the libsql server
+/// unless `--read-only` is set. Several databases are opened as local files, each
+/// labelled by its path.
+async fn open_search_databases(
+    args: &SearchArgs,
+) -> anyhow::Result<Vec<(String, hal::index::Database)>> {
+    if let [path] = args.database.as_slice() {
+        let db = if args.read_only {
+            hal::index::Database::open_read_only(&path.to_string_lossy()).await?
+        } else {
+            open_database().await?
+        };
+        return Ok(vec![(path.to_string_lossy().to_string(), db)]);
+    }
+
+    let mut databases = Vec::new();
+    for path in &args.database {
+        let path = path.to_string_lossy().to_string();
+        let db = if args.read_only {
+            hal::index::Database::open_read_only(&path).await
+        } else {
+            hal::index::Database::new_from_path(&path).await
+        }
+        .with_context(|| format!("Failed to open database {}", path))?;
+        databases.push((path, db));
+    }
+    Ok(databases)
+}
+
+/// Print an answer reused from the answer cache
+fn print_cached_answer(
+    query: &str,
+    cached: &hal::search::answer_cache::CachedAnswer,
+    format: &str,
+) -> anyhow::Result<()> {
+    match format {
+        "json" => {
+            let json_response = serde_json::json!({
+                "query": query,
+                "answer": cached.answer,
+                "cached": {
+                    "query": cached.query,
+                    "similarity": cached.similarity,
+                    "created_at": cached.created_at,
+                },
+                "sources": cached.sources.iter().map(|s| {
+                    serde_json::json!({
+                        "id": s.chunk_id,
+                        "url": s.url,
+                        "section": s.heading_path,
+                    })
+                }).collect::<Vec<_>>()
+            });
+            output::print_json(&json_response)?;
+        }
+        _ => {
+            out!(
+                "\nAnswer (cached from \"{}\", similarity {:.2}):",
+                cached.query,
+                cached.similarity
+            );
+            out!("{}", cached.answer);
+            out!("\nSources:");
+            for (i, source) in cached.sources.iter().enumerate() {
+                match &source.heading_path {
+                    Some(heading_path) => out!(
+                        "{}. [{}] {} ({})",
+                        i + 1,
+                        source.chunk_id,
+                        source.url,
+                        heading_path
+                    ),
+                    None => out!("{}. [{}] {}", i + 1, source.chunk_id, source.url),
+                }
+            }
+            out!();
+        }
+    }
+
+    Ok(())
+}
+
+/// Record a search in the history unless disabled or the database is read-only
+///
+/// Failures are logged rather than returned so they never fail the search itself.
+async fn record_search_history(
+    db: &hal::index::Database,
+    args: &SearchArgs,
+    query: &str,
+    options: &hal::search::SearchOptions,
+    results: &[hal::search::SearchResult],
+    answer: Option<&str>,
+) {
+    if args.no_history || db.is_read_only() {
+        return;
+    }
+    match hal::search::history::record_search(db, query, options, results, answer).await {
+        Ok(id) => info!("Recorded search as history entry {}", id),
+        Err(e) => tracing::warn!("Failed to record search history: {}", e),
+    }
+}
+
+/// Count a retrieval of each result in the database it came from, for `hal stats --usage`
+///
+/// Failing to record usage only loses the counts, not the results.
+async fn record_usage(
+    databases: &[(String, hal::index::Database)],
+    results: &[hal::search::SearchResult],
+) {
+    for (label, db) in databases {
+        if db.is_read_only() {
+            continue;
+        }
+        // A single database is searched directly, without labelling its results
+        let chunk_ids: Vec<i64> = results
+            .iter()
+            .filter(|result| {
+                result
+                    .database
+                    .as_ref()
+                    .is_none_or(|database| database == label)
+            })
+            .map(|result| result.chunk_id)
+            .collect();
+        if let Err(e) = hal::search::usage::record_retrievals(db, &chunk_ids).await {
+            tracing::warn!("Failed to record usage of {}: {}", label, e);
+        }
+    }
+}
+
+#[instrument]
+async fn history_command(args: HistoryArgs) -> anyhow::Result<()> {
+    // Create database connection
+    let db = if args.read_only {
+        hal::index::Database::open_read_only(&args.database.to_string_lossy()).await?
+    } else {
+        open_database().await?
+    };
+
+    let entries = hal::search::history::list_history(&db, args.limit).await?;
+
+    match args.format.as_str() {
+        "json" => {
+            output::print_json(&entries)?;
+        }
+        _ => {
+            out!("Recent searches: {}", entries.len());
+            for entry in entries {
+                let date = chrono::DateTime::from_timestamp(entry.created_at, 0)
+                    .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                let mode = if entry.answer.is_some() {
+                    "answer"
+                } else {
+                    "search"
+                };
+                out!("[{}] {} ({}) {}", entry.id, date, mode, entry.query);
+                for source in &entry.sources {
+                    out!("    {:.3} {}", source.score, source.url);
+                }
+            }
+            out!();
+            note!("Re-run a search with: hal search --replay <id>");
+        }
+    }
+
+    Ok(())
+}
+
+#[instrument]
+async fn stats_command(args: StatsArgs) -> anyhow::Result<()> {
+    use hal::search::usage::{page_usage, unused_pages, website_usage};
+
+    // Create database connection
+    let db = if args.read_only {
+        hal::index::Database::open_read_only(&args.database.to_string_lossy()).await?
+    } else {
+        open_database().await?
+    };
+
+    let websites = website_usage(&db).await?;
+    let (pages, unused) = if args.usage {
+        (
+            page_usage(&db, args.limit).await?,
+            unused_pages(&db, args.limit).await?,
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let provenance = if args.provenance {
+        db.chunk_provenance().await?
+    } else {
+        Vec::new()
+    };
+
+    let format_timestamp = |ts: Option<i64>| -> String {
+        ts.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "never".to_string())
+    };
+
+    match args.format.as_str() {
+        "json" if args.usage || args.provenance => {
+            let mut report = serde_json::json!({ "websites": websites });
+            if args.usage {
+                report["pages"] = serde_json::json!(pages);
+                report["unused_pages"] = serde_json::json!(unused);
+            }
+            if args.provenance {
+                report["provenance"] = serde_json::json!(provenance);
+            }
+            output::print_json(&report)?
+        }
+        "json" => {
+            let sizes: Vec<serde_json::Value> = websites
+                .iter()
+                .map(|website| {
+                    serde_json::json!({
+                        "domain": website.domain,
+                        "pages": website.pages,
+                        "chunks": website.chunks,
+                    })
+                })
+                .collect();
+            output::print_json(&sizes)?;
+        }
+        _ => {
+            for website in &websites {
+                out!(
+                    "{} - {} pages, {} chunks",
+                    website.domain,
+                    website.pages,
+                    website.chunks
+                );
+                if args.usage {
+                    out!(
+                        "    {} of {} chunks retrieved, {} retrievals (last: {})",
+                        website.retrieved_chunks,
+                        website.chunks,
+                        website.retrievals,
+                        format_timestamp(website.last_retrieved_at)
+                    );
+                }
+                if args.provenance {
+                    let groups: Vec<_> = provenance
+                        .iter()
+                        .filter(|group| group.domain == website.domain)
+                        .collect();
+                    if groups.len() > 1 {
+                        out!("    built with {} processor configs", groups.len());
+                    }
+                    for group in groups {
+                        let crawls = match (group.first_crawl_id, group.last_crawl_id) {
+                            (Some(first), Some(last)) if first != last => {
+                                format!("crawls {}-{}", first, last)
+                            }
+                            (_, Some(last)) => format!("crawl {}", last),
+                            _ => "no crawl".to_string(),
+                        };
+                        out!(
+                            "    config {}: {} chunks of {} pages, {} (indexed: {})",
+                            group.config_hash.as_deref().unwrap_or("unknown"),
+                            group.chunks,
+                            group.pages,
+                            crawls,
+                            format_timestamp(group.last_indexed_at)
+                        );
+                    }
+                }
+            }
+            if args.usage {
+                out!();
+                out!("Most retrieved pages:");
+                for page in &pages {
+                    out!(
+                        "  {:>6} {} (last: {})",
+                        page.retrievals,
+                        page.url,
+                        format_timestamp(page.last_retrieved_at)
+                    );
+                }
+                out!();
+                out!("Never retrieved pages:");
+                for page in &unused {
+                    out!("  {:>6} chunks {}", page.chunks, page.url);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[instrument]
+async fn feedback_command(args: FeedbackArgs) -> anyhow::Result<()> {
+    // Create database connection
+    let db = open_database().await?;
+
+    hal::search::feedback::record_feedback(
+        &db,
+        args.result_id,
+        args.relevant,
+        args.query.as_deref(),
+    )
+    .await?;
+
+    out!(
+        "Marked result {} as {}",
+        args.result_id,
+        if args.relevant {
+            "relevant"
+        } else {
+            "irrelevant"
+        }
+    );
+
+    Ok(())
+}
 
-    // Crawl the website
-    let pages = hal::crawler::crawl_website(&args.url, config).await?;
+#[instrument]
+async fn list_command(args: ListArgs) -> anyhow::Result<()> {
+    // Create database connection
+    let db = open_database().await?;
 
-    let store = pages
-        .iter()
-        .cloned()
-        .map(hal::crawler::storage::PageEntry::from);
-    hal::crawler::storage::store_batch(store).await?;
+    // List websites
+    let websites = db.list_websites().await?;
 
-    println!("Crawled {} pages", pages.len());
-    if args.chunk {
-        let processor_config = hal::processor::ProcessorConfig::builder()
-            .chunk_options(hal::processor::ChunkOptions::default())
-            .embedding_dimensions(768)
-            .build();
-        let chunks: Vec<hal::processor::TextChunk> = pages
-            .into_iter()
-            .filter_map(|p| chunk_markdown(&p.content, &processor_config.chunk_options).ok())
-            .flatten()
-            .collect();
+    out!("Indexed websites: {}", websites.len());
 
-        // Save to file if output is specified
-        if let Some(output_file) = args.output {
-            // Serialize chunks to JSON
-            let json = serde_json::to_string_pretty(&chunks)?;
-            tokio::fs::write(output_file.clone(), json).await?;
-            println!("Saved crawled content to {}", output_file.display());
+    // Format timestamp function
+    let format_timestamp = |ts: i64| -> String {
+        use chrono::{DateTime, TimeZone, Utc};
+        let dt: DateTime<Utc> = Utc.timestamp_opt(ts, 0).unwrap();
+        dt.format("%Y-%m-%d %H:%M:%S").to_string()
+    };
+
+    // Display websites
+    for website in websites {
+        if args.details {
+            out!("URL: {}", website.url);
+            out!("Domain: {}", website.domain);
+            out!(
+                "First indexed: {}",
+                format_timestamp(website.first_index_date)
+            );
+            out!(
+                "Last indexed: {}",
+                format_timestamp(website.last_index_date)
+            );
+            out!("Pages: {}", website.page_count);
+            out!("Status: {}", website.status);
+            out!();
+        } else {
+            out!(
+                "{} - {} pages (Last indexed: {})",
+                website.domain,
+                website.page_count,
+                format_timestamp(website.last_index_date)
+            );
         }
     }
 
@@ -333,286 +3357,607 @@ async fn crawl_command(args: CrawlArgs) -> anyhow::Result<()> {
 }
 
 #[instrument]
-async fn crawl_url(
-    source: &str,
-    max_depth: u32,
-    max_pages: u32,
-) -> anyhow::Result<Vec<CrawledPage>> {
-    // info!("Check if page is already crawled");
-    // if let Ok(pages) = hal::crawler::storage::load_domain(source).await {
-    //     return Ok(pages
-    //         .into_iter()
-    //         .map(CrawledPage::from)
-    //         .collect::<Vec<CrawledPage>>());
-    // };
-    info!("Fetching {}...", source);
+async fn quantize_command(args: QuantizeArgs) -> anyhow::Result<()> {
+    // Create database connection
+    let db = open_database().await?;
 
-    // Create crawler configuration
-    let config = hal::crawler::CrawlerConfig::builder()
-        .max_depth(max_depth)
-        .max_pages(max_pages)
-        .rate_limit_ms(500)
-        .respect_robots_txt(true)
-        .user_agent("hal-rag/0.1".to_string())
-        .exclude_selectors(vec![
-            "nav".to_string(),
-            "footer".to_string(),
-            "header".to_string(),
-            ".ads".to_string(),
-            "#comments".to_string(),
-        ])
-        .build();
+    out!(
+        "Converting embeddings of {} to {}...",
+        args.source,
+        args.mode
+    );
+    let converted = db.quantize_website(&args.source, args.mode).await?;
+    out!("Converted {} chunks", converted);
 
-    // Crawl the website
-    hal::crawler::crawl_website(source, config)
-        .await
-        .context("crawl error")
+    Ok(())
 }
 
 #[instrument]
-async fn index_command(args: IndexArgs) -> anyhow::Result<()> {
-    let client = hal::model::Client::new_gemini_from_env();
+async fn compress_command(args: CompressArgs) -> anyhow::Result<()> {
+    // Create database connection
+    let db = open_database().await?;
+
+    if args.off {
+        note!("Decompressing chunks of {}...", args.source);
+    } else {
+        out!(
+            "Training dictionary and compressing chunks of {}...",
+            args.source
+        );
+    }
+    let converted = db.compress_website(&args.source, !args.off).await?;
+    out!("Converted {} chunks", converted);
 
+    Ok(())
+}
+
+#[instrument]
+async fn ann_command(args: AnnArgs) -> anyhow::Result<()> {
     // Create database connection
-    let db = hal::index::Database::new_local_libsql().await?;
+    let db = open_database().await?;
 
-    // Set max_depth and max_pages based on the single argument
-    let (max_depth, max_pages) = if args.single {
-        (0, 1) // Set to 0 and 1 if single is true
+    match args.command {
+        AnnCommand::Status => {
+            if db.has_vector_index().await? {
+                out!("Vector index: present");
+            } else {
+                out!("Vector index: missing (search uses an exact scan)");
+                note!("Run `hal ann rebuild` to create it");
+            }
+        }
+        AnnCommand::Rebuild => {
+            note!("Rebuilding vector index...");
+            db.rebuild_vector_index().await?;
+            out!("Vector index rebuilt");
+        }
+    }
+
+    Ok(())
+}
+
+async fn quarantine_command(args: QuarantineArgs) -> anyhow::Result<()> {
+    use hal::processor::SafetyAction;
+
+    // Create database connection
+    let db = open_database().await?;
+
+    match args.command {
+        QuarantineCommand::List { domain, format } => {
+            let entries = db.get_quarantine(domain.as_deref()).await?;
+            if format == "json" {
+                output::print_json(&entries)?;
+                return Ok(());
+            }
+            if entries.is_empty() {
+                out!("Nothing in quarantine");
+                return Ok(());
+            }
+            for entry in &entries {
+                let target = match entry.position {
+                    Some(position) => format!("chunk {} of {}", position, entry.url),
+                    None => entry.url.clone(),
+                };
+                out!("[{}] {} ({})", entry.id, target, entry.action);
+                out!("    {}", entry.reasons.join(", "));
+                let preview: String = entry.text.chars().take(160).collect();
+                out!("    {}", preview.replace('\n', " "));
+            }
+        }
+        QuarantineCommand::Approve { id } => {
+            let entry = db
+                .remove_quarantine_entry(id)
+                .await?
+                .ok_or_else(|| anyhow!("No quarantine entry with ID {}", id))?;
+            match entry.action {
+                SafetyAction::Flag => out!("Approved {}; it stays in the index", entry.url),
+                SafetyAction::Drop => out!(
+                    "Approved {}; index it again without the safety check to add it",
+                    entry.url
+                ),
+            }
+        }
+        QuarantineCommand::Reject { id } => {
+            let entry = db
+                .remove_quarantine_entry(id)
+                .await?
+                .ok_or_else(|| anyhow!("No quarantine entry with ID {}", id))?;
+            let deleted = match (entry.action, entry.position) {
+                (SafetyAction::Drop, _) => 0,
+                (SafetyAction::Flag, Some(position)) => {
+                    db.delete_chunk_at(&entry.url, position).await?
+                }
+                (SafetyAction::Flag, None) => db.delete_chunks_by_page_url(&entry.url).await?,
+            };
+            out!("Rejected {}; removed {} chunks", entry.url, deleted);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+async fn db_command(args: DbArgs) -> anyhow::Result<()> {
+    match args.command {
+        DbCommand::Rekey(args) => rekey_command(args).await,
+    }
+}
+
+#[cfg(feature = "encryption")]
+async fn rekey_command(args: RekeyArgs) -> anyhow::Result<()> {
+    use hal::index::encryption::EncryptionKey;
+
+    let current_key = match &args.keyfile {
+        Some(path) => Some(EncryptionKey::from_keyfile(path)?),
+        None => EncryptionKey::from_env()?,
+    };
+    let new_key = if args.decrypt {
+        None
+    } else if let Some(path) = &args.new_keyfile {
+        Some(EncryptionKey::from_keyfile(path)?)
     } else {
-        (args.max_depth, args.max_pages) // Use provided values otherwise
+        let key = std::env::var(&args.new_key_env).with_context(|| {
+            format!(
+                "Set {} or pass --new-keyfile with the new key, or --decrypt",
+                args.new_key_env
+            )
+        })?;
+        Some(EncryptionKey::new(key)?)
     };
 
-    let pages = if args.source.starts_with("http") {
-        crawl_url(&args.source, max_depth, max_pages).await?
+    let path = args.database.to_string_lossy();
+    let db = hal::index::Database::new_from_path_with_key(&path, current_key.as_ref())
+        .await
+        .with_context(|| format!("Failed to open database {} with the current key", path))?;
+    db.rekey(new_key.as_ref()).await?;
+
+    if new_key.is_some() {
+        out!("Changed the encryption key of {}", path);
+        out!("Update HAL_DB_KEY or HAL_DB_KEYFILE before opening it again");
     } else {
-        println!("Loading from file {}...", args.source);
+        out!("Decrypted {}", path);
+    }
 
-        // Read file
-        let content = tokio::fs::read_to_string(&args.source).await?;
+    Ok(())
+}
 
-        // Parse JSON
-        serde_json::from_str(&content)?
+#[instrument]
+async fn gc_command(args: GcArgs) -> anyhow::Result<()> {
+    let configured = hal::config::current().retention;
+    let policy = hal::index::retention::RetentionPolicy {
+        max_chunks_per_domain: args
+            .max_chunks_per_domain
+            .or(configured.max_chunks_per_domain),
+        max_unused_days: args.max_unused_days.or(configured.max_unused_days),
+        max_database_mb: args.max_database_mb.or(configured.max_database_mb),
     };
+    if args.domain.is_none() && policy.is_empty() {
+        return Err(anyhow!(
+            "Nothing to collect: give a domain or set retention limits in [retention] of hal.toml"
+        ));
+    }
 
-    println!("Processing {} pages...", pages.len());
+    // Create database connection
+    let db = open_database().await?;
 
-    // Create processor options
-    let processor_config = hal::processor::ProcessorConfig::builder()
-        .chunk_options(hal::processor::ChunkOptions {
-            target_chunk_size: args.chunk_size,
-            overlap_size: args.chunk_size / 10,
-        })
-        .llm_model(args.model.clone())
-        .embedding_dimensions(768)
-        .build();
+    if let Some(domain) = &args.domain {
+        collect_stale_pages(&db, domain, args.dry_run).await?;
+    }
 
-    // Process and index pages
-    let mut total_chunks = 0;
-    let mut indexed_pages = 0;
+    if !policy.is_empty() {
+        let pruning = db.prune(&policy, args.dry_run).await?;
+        let verb = if args.dry_run {
+            "Would drop"
+        } else {
+            "Dropped"
+        };
+        out!(
+            "{} {} chunks: {} unused, {} over the per-website limit, {} over the size limit",
+            verb,
+            pruning.chunk_ids.len(),
+            pruning.unused_chunks,
+            pruning.excess_chunks,
+            pruning.oversize_chunks
+        );
+    }
 
-    // Track unique base URLs to count pages per website
-    use std::collections::HashMap;
-    use url::Url;
+    Ok(())
+}
 
-    let mut website_pages = HashMap::new();
+/// Remove the chunks of a website's pages absent from its latest crawl
+async fn collect_stale_pages(
+    db: &hal::index::Database,
+    domain: &str,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let gc = db.collect_garbage(domain, dry_run).await?;
+    if gc.crawl_id.is_none() {
+        out!(
+            "No complete crawl of {} recorded; index it from its URL first",
+            domain
+        );
+        return Ok(());
+    }
 
-    // Group pages by base URL
-    for page in &pages {
-        if let Ok(parsed_url) = Url::parse(&page.url) {
-            if let Some(host) = parsed_url.host_str() {
-                let base_url = format!("{}://{}", parsed_url.scheme(), host);
-                website_pages
-                    .entry(base_url)
-                    .or_insert_with(Vec::new)
-                    .push(page);
-            }
-        }
+    for url in &gc.stale_pages {
+        out!("  {}", url);
+    }
+    if dry_run {
+        out!(
+            "{} pages would be removed; run without --dry-run to delete their chunks",
+            gc.stale_pages.len()
+        );
+    } else {
+        out!(
+            "Removed {} chunks of {} pages",
+            gc.deleted_chunks,
+            gc.stale_pages.len()
+        );
     }
 
-    // Get the number of websites before consuming the HashMap
-    let website_count = website_pages.len();
+    Ok(())
+}
 
-    // Process and index pages by website
-    for (base_url, site_pages) in website_pages {
-        println!(
-            "Processing website: {} ({} pages)",
-            base_url,
-            site_pages.len()
-        );
+#[instrument]
+async fn audit_command(args: AuditArgs) -> anyhow::Result<()> {
+    match args.command {
+        AuditCommand::Links(args) => audit_links_command(args).await,
+        AuditCommand::Redactions(args) => audit_redactions_command(args).await,
+        AuditCommand::Tail(args) => audit_tail_command(args),
+    }
+}
 
-        for page in site_pages {
-            // Process content
-            let chunks =
-                hal::processor::process_content(&client, page.clone(), processor_config.clone())
-                    .await?;
-            total_chunks += chunks.len();
-            indexed_pages += 1;
+fn audit_tail_command(args: AuditTailArgs) -> anyhow::Result<()> {
+    let log =
+        hal::audit::AuditLog::new(args.file.unwrap_or_else(hal::audit::AuditLog::default_path));
+    let entries = log
+        .tail(args.lines, args.tool.as_deref())
+        .with_context(|| format!("Failed to read {}", log.path().display()))?;
 
-            println!("Indexing {} chunks from {}...", chunks.len(), page.url);
+    if args.format == "json" {
+        output::print_json(&entries)?;
+        return Ok(());
+    }
 
-            // Update website index
-            db.update_website_index(&page.url, chunks).await?;
+    if entries.is_empty() {
+        out!("No tool calls recorded in {}", log.path().display());
+        return Ok(());
+    }
+    for entry in &entries {
+        out!(
+            "{} [{}] {} {} ({} bytes, {} ms, args {})",
+            entry.timestamp,
+            entry.source,
+            entry.tool,
+            entry.outcome,
+            entry.result_bytes,
+            entry.duration_ms,
+            entry.args_hash
+        );
+        if let Some(permission) = &entry.permission {
+            out!("    permission: {}", permission);
+        }
+        if let Some(error) = &entry.error {
+            out!("    error: {}", error);
         }
     }
 
-    println!(
-        "Indexed {} chunks across {} pages from {} websites",
-        total_chunks, indexed_pages, website_count
+    Ok(())
+}
+
+async fn audit_redactions_command(args: AuditRedactionsArgs) -> anyhow::Result<()> {
+    // Create database connection
+    let db = open_database().await?;
+
+    let log = db.get_redaction_log(&args.domain).await?;
+    if args.format == "json" {
+        output::print_json(&log)?;
+        return Ok(());
+    }
+    if log.is_empty() {
+        out!("No pages of {} were indexed with redaction", args.domain);
+        return Ok(());
+    }
+
+    out!(
+        "{:<20} {:<9} {:>6} {:>6} {:>7} {:>4}  URL",
+        "Redacted at",
+        "Mode",
+        "Emails",
+        "Phones",
+        "Secrets",
+        "LLM"
     );
+    for entry in &log {
+        let redacted_at = chrono::DateTime::from_timestamp(entry.created_at, 0)
+            .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        out!(
+            "{:<20} {:<9} {:>6} {:>6} {:>7} {:>4}  {}",
+            redacted_at,
+            entry.redaction.to_string(),
+            entry.counts.emails,
+            entry.counts.phone_numbers,
+            entry.counts.secrets,
+            entry.counts.llm,
+            entry.url
+        );
+    }
 
     Ok(())
 }
 
-#[instrument]
-async fn search_command(args: SearchArgs) -> anyhow::Result<()> {
-    use hal::search::{generate_answer_with_rag, prepare_rag_context};
+async fn audit_links_command(args: AuditLinksArgs) -> anyhow::Result<()> {
+    use hal::crawler::link_audit::{LinkAuditConfig, audit_links, prune_vanished_pages};
 
     // Create database connection
-    let db = hal::index::Database::new_local_libsql().await?;
-
-    println!("Searching for: {}", args.query);
+    let db = open_database().await?;
 
-    let client = hal::model::Client::new_gemini_free_from_env();
+    let config = LinkAuditConfig {
+        concurrency: args.concurrency,
+        timeout_secs: args.timeout,
+        check_links: !args.pages_only,
+        ..LinkAuditConfig::default()
+    };
+    if args.format != "json" {
+        note!("Auditing links of {}...", args.domain);
+    }
+    let audit = audit_links(&db, &args.domain, &config).await?;
 
-    // Create search options
-    let options = hal::search::SearchOptions {
-        limit: args.limit,
-        source_filter: args.source,
-        date_range: None,
+    let pruned = if args.prune {
+        Some(prune_vanished_pages(&db, &audit).await?)
+    } else {
+        None
     };
 
-    // Search the index
-    let results = hal::search::search_index_with_client(&db, &client, &args.query, options).await?;
+    if args.format == "json" {
+        let mut json = serde_json::to_value(&audit)?;
+        json["pruned_chunks"] = serde_json::json!(pruned);
+        output::print_json(&json)?;
+        return Ok(());
+    }
 
-    // If vector search only, output results directly
-    if args.vector_search_only {
-        // Output results
-        match args.format.as_str() {
-            "json" => {
-                println!("{}", serde_json::to_string_pretty(&results)?);
-            }
-            _ => {
-                println!("Found {} results", results.len());
-                for (i, result) in results.iter().enumerate() {
-                    println!("{}. {}", i + 1, result.text);
-                    println!("   URL: {}", result.url);
-                    println!("   Context: {}", result.context);
-                    println!();
-                }
-            }
-        }
-    } else {
-        // Use RAG to generate an answer
-        println!("Generating answer using RAG...");
+    let status =
+        |check: &hal::crawler::link_audit::LinkCheck| match (check.status_code, &check.error) {
+            (Some(code), _) => format!("HTTP {}", code),
+            (None, Some(error)) => error.clone(),
+            (None, None) => String::new(),
+        };
 
-        // Prepare context from search results
-        let context = prepare_rag_context(&results);
+    out!(
+        "Checked {} pages and {} links",
+        audit.pages_checked,
+        audit.links_checked
+    );
 
-        // Generate answer using LLM
-        let answer = generate_answer_with_rag(&client, &args.query, &context, &args.model).await?;
+    out!("\nVanished pages: {}", audit.vanished_pages.len());
+    for page in &audit.vanished_pages {
+        out!("  {} ({})", page.url, status(page));
+    }
 
-        // Output results
-        match args.format.as_str() {
-            "json" => {
-                let json_response = serde_json::json!({
-                    "query": args.query,
-                    "answer": answer,
-                    "sources": results.iter().map(|r| {
-                        serde_json::json!({
-                            "text": r.text,
-                            "url": r.url,
-                            "context": r.context
-                        })
-                    }).collect::<Vec<_>>()
-                });
-                println!("{}", serde_json::to_string_pretty(&json_response)?);
-            }
-            _ => {
-                println!("\nAnswer:");
-                println!("{}", answer);
-                println!("\nSources:");
-                for (i, result) in results.iter().enumerate() {
-                    println!("{}. {}", i + 1, result.url);
-                }
-                println!();
+    if !audit.unreachable_pages.is_empty() {
+        out!("\nUnreachable pages: {}", audit.unreachable_pages.len());
+        for page in &audit.unreachable_pages {
+            out!("  {} ({})", page.url, status(page));
+        }
+    }
+
+    if !args.pages_only {
+        out!("\nDead links: {}", audit.dead_links.len());
+        for link in &audit.dead_links {
+            out!("  {} ({})", link.check.url, status(&link.check));
+            for page in &link.found_on {
+                out!("    linked from {}", page);
             }
         }
     }
 
+    match pruned {
+        Some(count) => out!("\nPruned {} chunks of vanished pages", count),
+        None if !audit.vanished_pages.is_empty() => {
+            note!("\nRun with --prune to delete the chunks of vanished pages")
+        }
+        None => {}
+    }
+
     Ok(())
 }
 
 #[instrument]
-async fn list_command(args: ListArgs) -> anyhow::Result<()> {
-    // Create database connection
-    let db = hal::index::Database::new_local_libsql().await?;
+async fn bench_embeddings_command(args: BenchEmbeddingsArgs) -> anyhow::Result<()> {
+    use hal::search::bench::{benchmark_embedding_model, eval_questions, sample_chunks};
 
-    // List websites
-    let websites = db.list_websites().await?;
+    // Create database connection
+    let db = open_database().await?;
 
-    println!("Indexed websites: {}", websites.len());
+    let questions = eval_questions(&db).await?;
+    let chunks = sample_chunks(&db, args.sample, &questions).await?;
+    if chunks.is_empty() {
+        return Err(anyhow!("The index has no chunks to benchmark"));
+    }
+    if questions.is_empty() {
+        out!("No relevance feedback with queries found; recall is not measured");
+        note!("Mark relevant results with `hal feedback <id> --relevant --query <query>`");
+    }
 
-    // Format timestamp function
-    let format_timestamp = |ts: i64| -> String {
-        use chrono::{DateTime, TimeZone, Utc};
-        let dt: DateTime<Utc> = Utc.timestamp_opt(ts, 0).unwrap();
-        dt.format("%Y-%m-%d %H:%M:%S").to_string()
-    };
+    let mut reports = Vec::with_capacity(args.models.len());
+    for name in &args.models {
+        note!("Embedding {} chunks with {}...", chunks.len(), name);
+        let price = args
+            .prices
+            .iter()
+            .find(|(model, _)| model == name)
+            .map(|(_, price)| *price);
 
-    // Display websites
-    for website in websites {
-        if args.details {
-            println!("URL: {}", website.url);
-            println!("Domain: {}", website.domain);
-            println!(
-                "First indexed: {}",
-                format_timestamp(website.first_index_date)
-            );
-            println!(
-                "Last indexed: {}",
-                format_timestamp(website.last_index_date)
-            );
-            println!("Pages: {}", website.page_count);
-            println!("Status: {}", website.status);
-            println!();
+        let report = if name == "mock" {
+            let model = hal::model::mock_model::MockEmbeddingModel::default();
+            benchmark_embedding_model(name, &model, &chunks, &questions, args.k, price).await?
         } else {
-            println!(
-                "{} - {} pages (Last indexed: {})",
-                website.domain,
-                website.page_count,
-                format_timestamp(website.last_index_date)
+            let gemini_api_key = exit_code::require_api_key("GEMINI_API_KEY")?;
+            let client = rig::providers::gemini::Client::new(&gemini_api_key);
+            let model = client.embedding_model(name);
+            benchmark_embedding_model(name, &model, &chunks, &questions, args.k, price).await?
+        };
+        reports.push(report);
+    }
+
+    if args.format == "json" {
+        output::print_json(&reports)?;
+        return Ok(());
+    }
+
+    out!(
+        "\n{:<28} {:>6} {:>10} {:>12} {:>12} {:>10}",
+        "Model",
+        "Dims",
+        format!("Recall@{}", args.k),
+        "Chunk (ms)",
+        "Query (ms)",
+        "Cost"
+    );
+    for report in &reports {
+        out!(
+            "{:<28} {:>6} {:>10} {:>12.1} {:>12.1} {:>10}",
+            report.model,
+            report.dimensions,
+            report
+                .recall
+                .map_or("-".to_string(), |recall| format!("{:.3}", recall)),
+            report.chunk_latency_ms,
+            report.query_latency_ms,
+            report
+                .estimated_cost
+                .map_or("-".to_string(), |cost| format!("${:.4}", cost)),
+        );
+    }
+    if let Some(report) = reports.first() {
+        out!(
+            "\n{} chunks, {} questions, ~{} tokens per model",
+            report.chunks,
+            report.questions,
+            report.estimated_tokens
+        );
+    }
+
+    Ok(())
+}
+
+#[instrument]
+async fn reprocess_command(args: ReprocessArgs) -> anyhow::Result<()> {
+    exit_code::require_api_key("GEMINI_API_KEY")?;
+    let client = hal::model::Client::new_gemini_chain_from_env()?
+        .with_embedding_config(&hal::config::current().embedding)?;
+    let client = with_completion_cache(client, args.no_cache);
+
+    // Create database connection
+    let db = open_database().await?;
+
+    let mut raw_pages = db.get_raw_html_by_domain(&args.domain).await?;
+    if raw_pages.is_empty() {
+        return Err(anyhow!(
+            "No stored HTML for {}; index it with --store-html first",
+            args.domain
+        ));
+    }
+
+    let processor_config = hal::processor::ProcessorConfig::builder()
+        .chunk_options(hal::processor::ChunkOptions {
+            target_chunk_size: args.chunk_size,
+            overlap_size: args.chunk_size / 10,
+            overlap_sentences: args.overlap_sentences,
+            ..hal::processor::ChunkOptions::default()
+        })
+        .llm_model(args.model.clone())
+        .generation(args.generation.options())
+        .embedding_model(hal::config::current().embedding.model_name())
+        .embedding_dimensions(768)
+        .segment_by_heading(args.segment_by_heading)
+        .context_mode(args.context_mode)
+        .context_batch_size(args.context_batch_size)
+        .concurrency(args.concurrency)
+        .skip_summary(args.no_summary)
+        .source_chunk_overrides(args.chunk_overrides.clone())
+        .build();
+
+    if args.outdated {
+        let outdated = db
+            .outdated_pages(&args.domain, &processor_config.fingerprint())
+            .await?;
+        raw_pages.retain(|(url, _)| outdated.contains(url));
+        if raw_pages.is_empty() {
+            out!(
+                "All pages of {} were built with these settings",
+                args.domain
             );
+            return Ok(());
+        }
+    }
+    out!(
+        "Reprocessing {} stored pages of {}...",
+        raw_pages.len(),
+        args.domain
+    );
+
+    let mut total_chunks = 0;
+    let mut skipped = 0;
+    for (url, html) in raw_pages {
+        let Some(mut page) = hal::crawler::page_from_html(&url, &html) else {
+            out!("Skipping {}: too little content", url);
+            skipped += 1;
+            continue;
+        };
+        let redactions = hal::processor::redact_page(&client, &mut page, args.redact).await?;
+
+        let chunks =
+            hal::processor::process_content(&client, page, processor_config.clone()).await?;
+        total_chunks += chunks.len();
+        note!("Indexing {} chunks from {}...", chunks.len(), url);
+        db.update_website_index(&url, chunks).await?;
+        if args.redact != hal::processor::Redaction::Off {
+            db.record_redactions(&url, args.redact, &redactions).await?;
         }
     }
 
+    out!(
+        "Reprocessed into {} chunks ({} pages skipped)",
+        total_chunks,
+        skipped
+    );
+
     Ok(())
 }
 
 #[instrument]
 async fn reembed_command(args: ReembedArgs) -> anyhow::Result<()> {
     // Create database connection
-    let db = hal::index::Database::new_local_libsql().await?;
+    let db = open_database().await?;
 
-    println!("Reembedding all chunks in the index with new embeddings...");
+    note!("Reembedding all chunks in the index with new embeddings...");
 
     // Display source filter if specified
     if let Some(source) = &args.source {
-        println!("Filtering by source domain: {}", source);
+        note!("Filtering by source domain: {}", source);
     }
 
-    println!("Using concurrency level: {}", args.concurrency);
+    note!("Using concurrency level: {}", args.concurrency);
 
-    let client = hal::model::Client::new_gemini_from_env();
+    exit_code::require_api_key("GEMINI_API_KEY")?;
+    let embedding = &hal::config::current().embedding;
+    let client =
+        hal::model::Client::new_gemini_chain_from_env()?.with_embedding_config(embedding)?;
+    note!("Embedding with {}", embedding.model_name());
 
     // Create a channel for progress updates
-    let (progress_sender, mut progress_receiver) = mpsc::channel(100);
+    let (progress_sender, mut progress_receiver) = mpsc::channel::<(i64, String)>(100);
 
     // First, count the total number of chunks to process
     let total_chunks = count_chunks_to_reembed(&db, args.source.clone()).await?;
 
     // Create progress bar
-    let progress_bar = ProgressBar::new(total_chunks as u64);
+    let progress_bar = if !output::show_progress() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(total_chunks as u64)
+    };
     progress_bar.set_style(
         ProgressStyle::default_bar()
             .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({eta}) {msg}")
@@ -624,42 +3969,56 @@ async fn reembed_command(args: ReembedArgs) -> anyhow::Result<()> {
     // Start timer
     let start_time = std::time::Instant::now();
 
-    // Spawn a task to process progress updates
+    // Spawn a task to process progress updates, keeping the chunks done for a checkpoint
     let progress_handle = tokio::spawn({
         let progress_bar = progress_bar.clone();
         async move {
+            let mut done = Vec::new();
             while let Some((chunk_id, url)) = progress_receiver.recv().await {
                 progress_bar.inc(1);
                 // Only update the message, don't print a new line
                 progress_bar.set_message(format!("Processed chunk {} from {}", chunk_id, url));
+                done.push(chunk_id.to_string());
             }
             // Signal that we're done processing updates
             progress_bar.finish_with_message("Reembedding completed");
+            done
         }
     });
 
     // Reembed all chunks in the index with new embeddings
+    let cancel = hal::cancel::shutdown_token();
     let reembedded_count = db
         .reembed_all_chunks(
             &client,
             args.concurrency,
             args.source.clone(),
             Some(progress_sender),
+            cancel.clone(),
         )
         .await?;
 
     // Wait for progress task to complete (it will end when all senders are dropped)
-    let _ = progress_handle.await;
+    let done = progress_handle.await.unwrap_or_default();
+
+    if cancel.is_cancelled() {
+        let target = args.source.clone().unwrap_or_default();
+        let checkpoint = hal::cancel::Checkpoint::new("reembed", &target, done, Vec::new());
+        return cancelled(
+            checkpoint,
+            Some(total_chunks.saturating_sub(reembedded_count)),
+        );
+    }
 
     // Calculate elapsed time
     let elapsed = start_time.elapsed();
 
-    println!("Reembedding completed successfully");
-    println!("Reembedded {} chunks in {:.2?}", reembedded_count, elapsed);
+    out!("Reembedding completed successfully");
+    out!("Reembedded {} chunks in {:.2?}", reembedded_count, elapsed);
 
     if reembedded_count > 0 {
         let avg_time = elapsed.as_millis() as f64 / reembedded_count as f64;
-        println!("Average time per chunk: {:.2?}ms", avg_time);
+        note!("Average time per chunk: {:.2?}ms", avg_time);
     }
 
     Ok(())
@@ -704,7 +4063,699 @@ async fn count_chunks_to_reembed(
 async fn mcp_command(args: McpArgs) -> anyhow::Result<()> {
     info!("Starting HAL MCP server...");
 
-    hal::mcp::run(args.name, args.version, args.no_file_tools)
+    let configured = hal::config::current().mcp_quotas;
+    let quotas = hal::mcp::QuotaConfig {
+        max_shell_commands: args.max_shell_commands.or(configured.max_shell_commands),
+        max_bytes_written: args.max_bytes_written.or(configured.max_bytes_written),
+        max_searches_per_minute: args
+            .max_searches_per_minute
+            .or(configured.max_searches_per_minute),
+    };
+
+    let access = match &args.access_config {
+        Some(path) => {
+            let config = hal::search::access::AccessConfig::load(path)?;
+            let api_key = std::env::var(hal::search::access::API_KEY_VAR).ok();
+            Some((hal::search::access::AccessControl::new(config), api_key))
+        }
+        None => None,
+    };
+
+    if let Some(addr) = args.health_addr {
+        let db = open_database().await.map_err(|e| format!("{:#}", e));
+        spawn_health_server(addr, db).await?;
+    }
+
+    hal::mcp::run(args.name, args.version, args.no_file_tools, quotas, access)
         .await
         .context("error running MCP server")
 }
+
+/// Serve health probes in the background of a server running on stdio
+async fn spawn_health_server(
+    addr: std::net::SocketAddr,
+    db: Result<hal::index::Database, String>,
+) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen for health probes on {}", addr))?;
+    info!("Serving /healthz and /readyz on http://{}", addr);
+    tokio::spawn(async move {
+        if let Err(e) = hal::health::serve(listener, db).await {
+            tracing::warn!("Health server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Tools that change files or run commands, which need approval without `--auto-approve`
+const APPROVAL_TOOLS: &[&str] = &[
+    "edit_file",
+    "write_file",
+    "execute_shell_command",
+    "request_permission",
+    "run_linter",
+    "format_code",
+];
+
+/// Longest tool result printed, in characters
+const TOOL_RESULT_PREVIEW: usize = 500;
+
+async fn code_command(args: CodeArgs) -> anyhow::Result<()> {
+    let api_key = exit_code::require_api_key("GEMINI_FREE_API_KEY")?;
+    if !args.project.is_dir() {
+        return Err(exit_code::ConfigError(format!(
+            "Project directory {} does not exist",
+            args.project.display()
+        ))
+        .into());
+    }
+
+    // The agent's tools come from `hal mcp`, run as a child process
+    let transport = hal::mcp::config::McpServerTransportConfig::Stdio {
+        command: std::env::current_exe()?.to_string_lossy().to_string(),
+        args: vec!["mcp".to_string()],
+        envs: std::collections::HashMap::new(),
+    };
+    let service = transport
+        .start()
+        .await
+        .context("Failed to start the MCP tool server")?;
+    let manager = hal::mcp::adaptor::McpManager {
+        clients: std::collections::HashMap::from([("hal".to_string(), service)]),
+    };
+    let (tools, tool_defs) = manager.get_tool_set_and_defs().await?;
+
+    let init = tools
+        .call(
+            "init",
+            serde_json::json!({ "path": args.project.to_string_lossy() }).to_string(),
+        )
+        .await
+        .context("Failed to initialize the project directory")?;
+    let tree = serde_json::from_str::<serde_json::Value>(&init)
+        .ok()
+        .and_then(|init| init.get("directory_tree")?.get("tree").cloned())
+        .map(|tree| tree.to_string())
+        .unwrap_or_default();
+
+    // The preamble, preloaded files and tools repeat in every request of the agent
+    let gemini = rig::providers::gemini::Client::new(&api_key);
+    let mut model = hal::model::CachedGeminiModel::new(gemini.clone(), &args.model);
+    if args.no_context_cache {
+        model = model.with_min_tokens(usize::MAX);
+    }
+    let client = hal::model::Client::new_gemini_cached_model(model, gemini);
+    let mut builder = client
+        .completion()
+        .clone()
+        .agent()
+        .preamble(hal::coder::TASK_PREAMBLE)
+        .append_preamble(&format!(
+            "The project directory is {}. Its directory tree is:\n{}",
+            args.project.display(),
+            tree
+        ));
+    if args.context_tokens > 0 {
+        let options = hal::coder::context::ContextOptions {
+            max_tokens: args.context_tokens,
+            source_filter: args.context_source.clone(),
+            ..Default::default()
+        };
+        match relevant_files(&client, &args.task, &options).await {
+            Ok(context) if !context.is_empty() => {
+                note!(
+                    "Preloaded {} relevant files (~{} tokens)",
+                    context.files.len(),
+                    context.tokens
+                );
+                builder = builder.append_preamble(&context.to_prompt());
+            }
+            Ok(_) => {}
+            // The agent can still explore the project with its tools
+            Err(e) => tracing::warn!("Failed to load relevant files: {:#}", e),
+        }
+    }
+    let mut agent = builder.build();
+    agent.tools = tools;
+
+    // Unselected tools stay callable; only their definitions are left out of the requests
+    let tool_defs = match args.max_tools {
+        Some(limit) => {
+            let embedding = client.embedding();
+            let selected = async {
+                let index = hal::coder::ToolIndex::build(embedding, tool_defs.clone()).await?;
+                let essential = hal::coder::tool_selection::ESSENTIAL_TOOLS;
+                index.select(embedding, &args.task, limit, essential).await
+            }
+            .await;
+            match selected {
+                Ok(selected) => {
+                    note!(
+                        "Selected {} of {} tools for the task",
+                        selected.len(),
+                        tool_defs.len()
+                    );
+                    selected
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to select tools, using all of them: {}", e);
+                    tool_defs
+                }
+            }
+        }
+        None => tool_defs,
+    };
+
+    let mut executor = hal::coder::AgentExecutor::new(
+        std::sync::Arc::new(agent),
+        std::sync::Arc::new(tool_defs),
+        args.max_iterations,
+    )
+    .with_artifacts(hal::coder::ArtifactStore::new(
+        hal::coder::ArtifactStore::default_dir(),
+        args.result_budget,
+    ))
+    .with_audit(hal::audit::AuditLog::new(
+        hal::audit::AuditLog::default_path(),
+    ));
+    if !args.auto_approve {
+        executor = executor.with_approval(std::sync::Arc::new(approve_tool_call));
+    }
+
+    note!("Running task in {}...", args.project.display());
+    let (tx, mut rx) = mpsc::channel(32);
+    let task = args.task.clone();
+    let execution = tokio::spawn(async move { executor.execute(task, tx).await });
+
+    let mut failure = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            Ok(event) => print_executor_event(event),
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+    }
+    execution.await?;
+
+    match failure {
+        Some(e) => Err(anyhow!(e).context("Coding task failed")),
+        None => Ok(()),
+    }
+}
+
+/// Index the source files of a repository per symbol
+async fn index_code_command(args: IndexCodeArgs) -> anyhow::Result<()> {
+    exit_code::require_api_key("GEMINI_API_KEY")?;
+    if !args.path.is_dir() {
+        return Err(exit_code::ConfigError(format!(
+            "Repository directory {} does not exist",
+            args.path.display()
+        ))
+        .into());
+    }
+    let client = hal::model::Client::new_gemini_chain_from_env()?
+        .with_embedding_config(&hal::config::current().embedding)?;
+    let db = open_database().await?;
+
+    let (root, corpus) = markdown_corpus(&args.path)?;
+    let files = code_files(&root)?;
+    note!("Indexing {} source files of {}...", files.len(), corpus);
+
+    let progress_bar = if !output::show_progress() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(files.len() as u64)
+    };
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({eta}) {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+
+    let mut total_chunks = 0;
+    let mut skipped = 0;
+    for file in &files {
+        let url = markdown_page_url(&root, &corpus, file);
+        let relative = url.trim_start_matches(&format!("file://{}/", corpus));
+        progress_bar.set_message(relative.to_string());
+
+        // Generated or binary files are not worth indexing
+        let Ok(content) = std::fs::read_to_string(file) else {
+            skipped += 1;
+            progress_bar.inc(1);
+            continue;
+        };
+        let chunks = hal::processor::code::process_code_file(
+            &client,
+            &url,
+            relative,
+            &content,
+            args.max_chunk_chars,
+        )
+        .await?;
+        total_chunks += chunks.len();
+        db.update_website_index(&url, chunks).await?;
+        progress_bar.inc(1);
+    }
+    progress_bar.finish_and_clear();
+
+    emit!(
+        "index_completed",
+        { "chunks": total_chunks, "files": files.len() - skipped, "source": corpus },
+        "Indexed {} chunks from {} source files of {}",
+        total_chunks,
+        files.len() - skipped,
+        corpus
+    );
+    Ok(())
+}
+
+/// Source files below a repository root, skipping hidden, build and dependency directories
+fn code_files(root: &std::path::Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                if !matches!(name.as_ref(), "target" | "node_modules" | "vendor" | "dist") {
+                    dirs.push(path);
+                }
+            } else if hal::processor::code::language(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Files of the default index relevant to a coding task
+async fn relevant_files<C, E>(
+    client: &hal::model::Client<C, E>,
+    task: &str,
+    options: &hal::coder::context::ContextOptions,
+) -> anyhow::Result<hal::coder::context::RelevantContext>
+where
+    C: rig::completion::CompletionModel,
+    E: rig::embeddings::EmbeddingModel,
+{
+    let db = open_database().await?;
+    Ok(hal::coder::context::load_relevant_files(&db, client, task, options).await?)
+}
+
+/// Print an event of the coding agent
+fn print_executor_event(event: hal::coder::ExecutorEvent) {
+    use hal::coder::ExecutorEvent;
+
+    match event {
+        ExecutorEvent::Thinking { text } => {
+            emit!("agent_thinking", { "text": text }, "{}", text.trim());
+        }
+        ExecutorEvent::ToolCallAttempted { call } => {
+            emit!(
+                "tool_call",
+                { "id": call.id, "tool": call.function.name, "arguments": call.function.arguments },
+                "→ {} {}",
+                call.function.name,
+                call.function.arguments
+            );
+        }
+        ExecutorEvent::ToolCallCompleted {
+            id,
+            result,
+            tool_name,
+        } => {
+            let mut preview: String = result.chars().take(TOOL_RESULT_PREVIEW).collect();
+            if preview.len() < result.len() {
+                preview.push('…');
+            }
+            emit!(
+                "tool_result",
+                { "id": id, "tool": tool_name, "result": result },
+                "← {} {}",
+                tool_name,
+                preview
+            );
+        }
+        ExecutorEvent::ExecutionError { error } => {
+            emit!("agent_error", { "error": error }, "Warning: {}", error);
+        }
+        ExecutorEvent::Finished { summary } => {
+            emit!("agent_finished", { "summary": summary }, "Done: {}", summary);
+        }
+    }
+}
+
+/// Ask on the terminal whether a tool call that changes files or runs commands may run
+fn approve_tool_call(call: &rig::message::ToolCall) -> bool {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !APPROVAL_TOOLS.contains(&call.function.name.as_str()) {
+        return true;
+    }
+    // Without a terminal to ask, only --auto-approve allows these tools
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    // Permissions are granted per workspace, so ask about the workspace as a whole
+    let action = match call.function.name.as_str() {
+        "request_permission" if call.function.arguments["operation"] != "execute" => format!(
+            "{} access to workspace {}",
+            call.function.arguments["operation"]
+                .as_str()
+                .unwrap_or_default(),
+            call.function.arguments["path"].as_str().unwrap_or_default()
+        ),
+        name => format!("{} {}", name, call.function.arguments),
+    };
+    tokio::task::block_in_place(|| {
+        eprint!("Allow {}? [y/N] ", action);
+        let _ = std::io::stderr().flush();
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer).is_err() {
+            return false;
+        }
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    })
+}
+
+async fn lsp_ish_command(args: LspIshArgs) -> anyhow::Result<()> {
+    exit_code::require_api_key("GEMINI_FREE_API_KEY")?;
+    let db = open_database().await?;
+    let embedding = &hal::config::current().embedding;
+    let client = hal::model::Client::new_gemini_free_from_env().with_embedding_config(embedding)?;
+    if let Some(addr) = args.health_addr {
+        spawn_health_server(addr, Ok(db.clone())).await?;
+    }
+    let mut server = hal::rpc::Server::new(db, client);
+    if !args.no_index {
+        exit_code::require_api_key("GEMINI_API_KEY")?;
+        let config = hal::processor::ProcessorConfig::builder()
+            .chunk_options(hal::processor::ChunkOptions {
+                target_chunk_size: args.chunk_size,
+                overlap_size: args.chunk_size / 10,
+                ..hal::processor::ChunkOptions::default()
+            })
+            .embedding_model(embedding.model_name())
+            .embedding_dimensions(768)
+            .build();
+        let client = hal::model::Client::new_gemini_from_env().with_embedding_config(embedding)?;
+        server = server.with_indexing(client, config);
+    }
+
+    info!("Serving JSON-RPC on stdio...");
+    hal::rpc::run(server)
+        .await
+        .context("error running JSON-RPC server")
+}
+
+#[instrument]
+fn chunk_command(args: ChunkArgs) -> anyhow::Result<()> {
+    let markdown = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+
+    let options = hal::processor::ChunkOptions {
+        target_chunk_size: args.target_chunk_size,
+        overlap_size: args.overlap_size,
+        overlap_sentences: args.overlap_sentences,
+        min_chunk_chars: args.min_chunk_chars,
+    };
+    let chunks = chunk_markdown(&markdown, &options)?;
+
+    match args.format.as_str() {
+        "json" => {
+            let json_chunks = chunks
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    serde_json::json!({
+                        "index": i,
+                        "position": chunk.position,
+                        "heading": chunk.heading,
+                        "heading_path": chunk.heading_path,
+                        "word_count": hal::processor::count_words(&chunk.text),
+                        "char_count": chunk.text.chars().count(),
+                        "text": chunk.text,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let json_response = serde_json::json!({
+                "file": args.file,
+                "options": {
+                    "target_chunk_size": options.target_chunk_size,
+                    "overlap_size": options.overlap_size,
+                    "overlap_sentences": options.overlap_sentences,
+                    "min_chunk_chars": options.min_chunk_chars,
+                },
+                "chunks": json_chunks,
+            });
+            output::print_json(&json_response)?;
+        }
+        _ => {
+            out!("{}", hal::processor::render_chunks(&chunks).trim_end());
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a completion script for the CLI as currently defined
+fn completions_command(args: CompletionsArgs) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut std::io::stdout());
+}
+
+/// Print the `hal` man page, or write pages for every command to a directory
+fn manpages_command(args: ManpagesArgs) -> anyhow::Result<()> {
+    let mut command = Cli::command();
+    // Propagate global arguments to the subcommands
+    command.build();
+
+    match args.out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            let name = command.get_name().to_string();
+            let count = write_manpages(&command, &name, &dir)?;
+            out!("Wrote {} man pages to {}", count, dir.display());
+        }
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
+
+/// Write the man page of a command and its subcommands, returning the number of pages
+///
+/// Subcommand pages are named after their path, e.g. `hal-audit-links.1`.
+fn write_manpages(command: &clap::Command, name: &str, dir: &Path) -> anyhow::Result<usize> {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(command.clone().name(name.to_string())).render(&mut page)?;
+    let path = dir.join(format!("{}.1", name));
+    std::fs::write(&path, page).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let mut count = 1;
+    for subcommand in command.get_subcommands() {
+        if subcommand.get_name() == "help" || subcommand.is_hide_set() {
+            continue;
+        }
+        let name = format!("{}-{}", name, subcommand.get_name());
+        count += write_manpages(subcommand, &name, dir)?;
+    }
+    Ok(count)
+}
+
+/// Reuse the completions of earlier runs for summaries and contexts, unless `no_cache` is
+/// set or the cache is disabled in `hal.toml`
+fn with_completion_cache<C, E>(
+    client: hal::model::Client<C, E>,
+    no_cache: bool,
+) -> hal::model::Client<hal::model::CachedCompletionModel<C>, E>
+where
+    C: rig::completion::CompletionModel,
+    E: rig::embeddings::EmbeddingModel,
+{
+    let config = hal::config::current();
+    let cache = if no_cache {
+        None
+    } else {
+        config.completion_cache.cache()
+    };
+    client.with_completion_cache(cache, &config.model_chain.label(config.provider))
+}
+
+/// Prompt for the settings of `hal.toml`, offering the current values as defaults
+fn init_command(args: InitArgs) -> anyhow::Result<()> {
+    use hal::config::{HalConfig, RateLimitTier};
+
+    let path = match args.path.or_else(hal::config::config_path) {
+        Some(path) => path,
+        None => return Err(hal::config::ConfigError::NoLocation.into()),
+    };
+    let mut config = if path.exists() {
+        HalConfig::from_path(&path)?
+    } else {
+        HalConfig::default()
+    };
+
+    config.provider = prompt_parsed("Provider (gemini, vertex, azure)", config.provider)?;
+    for (name, key) in prompt_api_keys(config.provider)? {
+        // Keep keys out of the file unless there is no keyring to put them in
+        match hal::config::store_api_key(name, &key) {
+            Ok(()) => {
+                config.api_keys.remove(name);
+                out!("Stored {} in the OS keyring", name);
+            }
+            Err(e) => {
+                eprintln!("Warning: {}; storing {} in {}", e, name, path.display());
+                config.api_keys.insert(name.to_string(), key);
+            }
+        }
+    }
+
+    let database = prompt(
+        "Default database (libsql server URL or file path)",
+        config
+            .database
+            .as_deref()
+            .unwrap_or("http://127.0.0.1:8080"),
+    )?;
+    config.database = Some(database);
+    config.rate_limit_tier =
+        prompt_parsed::<RateLimitTier>("Rate-limit tier (free|paid)", config.rate_limit_tier)?;
+
+    config.save(&path)?;
+    out!("Wrote configuration to {}", path.display());
+    note!("Environment variables such as GEMINI_API_KEY still override the stored keys");
+    Ok(())
+}
+
+/// Run every health check and print the fix of each problem
+async fn doctor_command(args: DoctorArgs) -> anyhow::Result<()> {
+    let db = open_database().await.map_err(|e| format!("{:#}", e));
+    let report = hal::health::run(
+        hal::health::Scope::Readiness,
+        hal::config::current(),
+        db.as_ref().map_err(String::as_str),
+    )
+    .await;
+
+    if args.format == "json" {
+        output::print_json(&report)?;
+    } else {
+        for check in &report.checks {
+            out!("[{}] {}: {}", check.status, check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                out!("    fix: {}", fix);
+            }
+        }
+    }
+
+    let failed = report
+        .checks
+        .iter()
+        .filter(|check| check.status == hal::health::Status::Fail)
+        .count();
+    if failed > 0 {
+        return Err(exit_code::ConfigError(format!(
+            "{} of {} checks failed",
+            failed,
+            report.checks.len()
+        ))
+        .into());
+    }
+    if args.format != "json" {
+        match report.status {
+            hal::health::Status::Pass => out!("All {} checks passed", report.checks.len()),
+            _ => out!("No checks failed, but some have warnings"),
+        }
+    }
+    Ok(())
+}
+
+/// Store or remove a provider's API keys in the OS keyring
+fn auth_command(args: AuthArgs) -> anyhow::Result<()> {
+    hal::config::require_keyring()?;
+    match args.command {
+        AuthCommand::Set(args) => {
+            let keys = prompt_api_keys(args.provider)?;
+            if keys.is_empty() {
+                out!("No keys entered; nothing stored");
+            }
+            for (name, key) in keys {
+                hal::config::store_api_key(name, &key)?;
+                out!("Stored {} in the OS keyring", name);
+            }
+        }
+        AuthCommand::Remove(args) => {
+            for (name, _) in args.provider.api_key_vars() {
+                if hal::config::remove_api_key(name)? {
+                    out!("Removed {} from the OS keyring", name);
+                } else {
+                    note!("No {} in the OS keyring", name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prompt without echo for each API key of a provider, returning the keys entered
+fn prompt_api_keys(provider: hal::config::Provider) -> anyhow::Result<Vec<(&'static str, String)>> {
+    let mut keys = Vec::new();
+    for (name, purpose) in provider.api_key_vars() {
+        let empty = if hal::config::api_key(name).is_some() {
+            "keep the current key"
+        } else {
+            "skip"
+        };
+        let key = rpassword::prompt_password(format!(
+            "{} API key for {} ({}; leave empty to {}): ",
+            provider, purpose, name, empty
+        ))?;
+        if !key.trim().is_empty() {
+            keys.push((*name, key.trim().to_string()));
+        }
+    }
+    Ok(keys)
+}
+
+/// Ask a question on the terminal, returning the default for an empty answer
+fn prompt(question: &str, default: &str) -> anyhow::Result<String> {
+    use std::io::Write;
+
+    print!("{} [{}]: ", question, default);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        anyhow::bail!("No answer to \"{}\"", question);
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+/// Ask until the answer parses, returning the default for an empty answer
+fn prompt_parsed<T>(question: &str, default: T) -> anyhow::Result<T>
+where
+    T: std::str::FromStr<Err = String> + std::fmt::Display,
+{
+    loop {
+        match prompt(question, &default.to_string())?.parse() {
+            Ok(value) => return Ok(value),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
@@ -4,40 +4,59 @@
 //! It implements:
 //!
 //! - Session-based permission system to maintain permissions throughout user sessions
+//! - Scoped workspaces that file tools resolve paths through, with ignore rules and
+//!   read-only subpaths, so no path escapes the directories granted access
 //! - File operations: view, search, edit, and write files with proper permission checks
 //! - Shell operations: execute commands with validation and security checks
+//! - Lint and format: run the project's linter or formatter, returning structured diagnostics
 //! - Permission management: request and track permissions for directories and commands
+//! - Coding tasks: run an agent with these tools, streaming its events as notifications
+//! - Quotas: per-session limits on shell commands, bytes written and searches per minute,
+//!   enforced before any tool runs
+//! - API keys: with an access config, search tools need the session's `HAL_API_KEY`, are
+//!   rate limited per key and only search the collections granted to it
+//! - Audit log: every tool call is appended to `.hal/audit.jsonl` with its permission decision
+//! - Plugin tools: tools registered in `hal::plugin` are offered next to the built-in tools
 //!
 //! The implementation balances security with usability by requiring explicit user permission
 //! grants while maintaining those permissions throughout the session.
 
 // Core modules
-pub mod code;
 pub mod executor;
 pub mod file_utils;
+pub mod lint_utils;
 pub mod permissions;
+pub mod quota;
 pub mod shell_utils;
+pub mod workspace;
 
 // Tool implementation modules
 pub mod adaptor;
 pub mod config;
+pub mod tool_coder;
 pub mod tool_core;
 pub mod tool_file;
 pub mod tool_search;
 pub mod tool_shell;
 
+use crate::audit::{AuditEntry, AuditLog};
+use crate::search::SearchOptions;
+use crate::search::access::AccessControl;
 use executor::Executor;
 pub use permissions::{PermissionsRef, SessionPermissions, create_permissions};
+pub use quota::{QuotaConfig, QuotaExceeded, QuotasRef, SessionQuotas, create_quotas};
 use shell_utils::ShellExecutor;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
+pub use workspace::{Access, Workspace, WorkspaceConfig};
 
 use rmcp::{
     RoleServer,
     handler::server::tool::ToolCallContext,
     model::{
-        CallToolRequestParam, CallToolResult, ErrorCode, Implementation, ListToolsResult,
-        PaginatedRequestParam, ServerCapabilities, ServerInfo,
+        CallToolRequestParam, CallToolResult, Content, ErrorCode, Implementation, ListToolsResult,
+        PaginatedRequestParam, RawContent, ServerCapabilities, ServerInfo,
     },
     serve_server,
     service::RequestContext,
@@ -61,6 +80,9 @@ use tracing::{info, instrument, warn};
 /// * `name` - The name of the server (used for logging/identification).
 /// * `version` - The version string of the server (used for logging/identification).
 /// * `no_file_tools` - Flag to disable file tools.
+/// * `quotas` - Limits of each session, see [`QuotaConfig`].
+/// * `access` - API keys search tools require, with the key of the session, or `None` to
+///   allow every search.
 ///
 /// # Returns
 ///
@@ -74,19 +96,29 @@ use tracing::{info, instrument, warn};
 /// let name = "My MCP Server".to_string();
 /// let version = "0.1.0".to_string();
 /// let no_file_tools = false;
-/// hal::mcp::run(name, version, no_file_tools).await?; // Call the actual function
+/// let quotas = hal::mcp::QuotaConfig::default();
+/// hal::mcp::run(name, version, no_file_tools, quotas, None).await?; // Call the actual function
 /// # Ok(())
 /// # }
 /// ```
-#[instrument]
-pub async fn run(name: String, version: String, no_file_tools: bool) -> anyhow::Result<()> {
+#[instrument(skip(access))]
+pub async fn run(
+    name: String,
+    version: String,
+    no_file_tools: bool,
+    quotas: QuotaConfig,
+    access: Option<(AccessControl, Option<String>)>,
+) -> anyhow::Result<()> {
     info!("Starting HAL MCP server: {} v{}", name, version);
 
     let client = crate::model::Client::new_gemini_free_from_env();
     let model = client.completion().clone();
 
     // Create state containing permissions and executor
-    let state = State::new();
+    let mut state = State::new().with_quotas(quotas);
+    if let Some((access, api_key)) = access {
+        state = state.with_access(access, api_key);
+    }
 
     // Create the HAL server with state
     let hal_server = HalServer::new(state, model, no_file_tools);
@@ -153,8 +185,12 @@ impl HalServer {
         )
     }
 
-    fn search_tools(&self) -> tool_search::SearchTools {
-        tool_search::SearchTools::new()
+    fn search_tools(&self, exact_source: bool) -> tool_search::SearchTools {
+        tool_search::SearchTools::new(exact_source)
+    }
+
+    fn coder_tools(&self, context: &RequestContext<RoleServer>) -> tool_coder::CoderTools {
+        tool_coder::CoderTools::new(self.clone(), context.peer.clone(), context.id.clone())
     }
 }
 
@@ -177,16 +213,18 @@ Permissions persist throughout your session once granted. The system enforces se
 \n1. INITIALIZATION: Call the `init` tool first with a project directory path to establish context \
 and receive initial read/write permissions for that directory. \
 \n2. PERMISSIONS: Before accessing files or running commands, you must request explicit permissions: \
-   - Use `request_permission` with operation='read' and path=<directory> for file reading operations \
-   - Use `request_permission` with operation='write' and path=<directory> for file writing operations \
+   - Use `request_permission` with operation='read' and path=<directory> to grant read access to that directory as a workspace \
+   - Use `request_permission` with operation='write' and path=<directory> to grant write access to that directory as a workspace \
    - Use `request_permission` with operation='execute' and path=<command> for shell command execution \
 \n3. FILE OPERATIONS: After permissions are granted, you can use tools like `show_file`, `search_in_file`, and `write_file`. \
+Paths may be relative to the project directory; paths outside the granted workspaces, ignored paths and read-only paths are rejected. \
 \n4. SHELL OPERATIONS: After execution permission, you can use `execute_shell_command`. \
 \nPermissions persist throughout your session once granted. The system enforces security by limiting access to only explicitly permitted directories and commands.".to_string()
         };
         ServerInfo {
             protocol_version: Default::default(),
             capabilities: ServerCapabilities::builder()
+                .enable_logging()
                 .enable_tools()
                 .enable_tool_list_changed()
                 .build(),
@@ -211,6 +249,18 @@ and receive initial read/write permissions for that directory. \
             }
             all_tools.extend(tool_shell::ShellTools::get_tool_box().list());
             all_tools.extend(tool_search::SearchTools::get_tool_box().list());
+            all_tools.extend(tool_coder::CoderTools::get_tool_box().list());
+            all_tools.extend(crate::plugin::registry().tools().iter().map(|tool| {
+                let schema = match tool.parameters() {
+                    serde_json::Value::Object(schema) => schema,
+                    _ => serde_json::Map::new(),
+                };
+                rmcp::model::Tool::new(
+                    tool.name().to_string(),
+                    tool.description().to_string(),
+                    schema,
+                )
+            }));
             Ok(ListToolsResult {
                 tools: all_tools,
                 next_cursor: None,
@@ -225,11 +275,26 @@ and receive initial read/write permissions for that directory. \
         context: RequestContext<RoleServer>,
     ) -> impl Future<Output = Result<CallToolResult, rmcp::Error>> + Send + '_ {
         async move {
-            let tool_name = request_params.name.as_ref();
+            let tool_name = request_params.name.to_string();
+            let tool_name = tool_name.as_str();
             info!("Received tool call for: {}", tool_name);
-
-            // Use get_tool_box() for checking and calling
-            let result = if tool_core::CoreTools::get_tool_box()
+            let arguments = request_params.arguments.clone().unwrap_or_default();
+            let started = Instant::now();
+
+            // Quotas and API keys are checked before dispatching, so no tool runs over them
+            let admitted = self
+                .state
+                .quotas()
+                .lock()
+                .await
+                .admit(tool_name, &arguments);
+            let authorized = match admitted {
+                Ok(()) => self.state.authorize(tool_name, &arguments),
+                Err(exceeded) => Err(exceeded.into()),
+            };
+            let result = if let Err(denied) = &authorized {
+                Err(denied.clone())
+            } else if tool_core::CoreTools::get_tool_box()
                 .map
                 .contains_key(tool_name)
             {
@@ -268,12 +333,32 @@ and receive initial read/write permissions for that directory. \
                 .contains_key(tool_name)
             {
                 info!("Delegating to SearchTools...");
-                let search_tools_instance = self.search_tools();
+                let search_tools_instance = self.search_tools(authorized == Ok(true));
                 let search_context =
                     ToolCallContext::new(&search_tools_instance, request_params, context);
                 tool_search::SearchTools::get_tool_box()
                     .call(search_context)
                     .await // Use get_tool_box()
+            } else if tool_coder::CoderTools::get_tool_box()
+                .map
+                .contains_key(tool_name)
+            {
+                info!("Delegating to CoderTools...");
+                let coder_tools_instance = self.coder_tools(&context);
+                let coder_context =
+                    ToolCallContext::new(&coder_tools_instance, request_params, context);
+                tool_coder::CoderTools::get_tool_box()
+                    .call(coder_context)
+                    .await
+            } else if let Some(tool) = crate::plugin::registry().tool(tool_name) {
+                info!("Delegating to plugin tool...");
+                match tool
+                    .call(serde_json::Value::Object(arguments.clone()))
+                    .await
+                {
+                    Ok(output) => Ok(CallToolResult::success(vec![Content::text(output)])),
+                    Err(e) => Ok(CallToolResult::error(vec![Content::text(e.to_string())])),
+                }
             } else {
                 warn!("Tool not found: {}", tool_name);
                 // Using specific error recommended
@@ -284,18 +369,96 @@ and receive initial read/write permissions for that directory. \
                 ))
             };
 
-            // ... (logging success/failure) ...
+            let entry = audit_entry(tool_name, &arguments, started, &result);
+            self.state.audit().record_or_warn(&entry);
             result
         }
     }
 }
 
+/// Audit log entry of an MCP tool call
+///
+/// Failures caused by missing permissions, rejected paths or commands are recorded as
+/// denied, and granted permissions are recorded with the call that granted them.
+fn audit_entry(
+    tool_name: &str,
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    started: Instant,
+    result: &Result<CallToolResult, rmcp::Error>,
+) -> AuditEntry {
+    let args = serde_json::to_string(arguments).unwrap_or_default();
+    let entry = AuditEntry::new("mcp", tool_name, &args, started.elapsed());
+    let error = match result {
+        Ok(result) if result.is_error == Some(true) => result_text(result),
+        Ok(result) => {
+            let entry = entry.with_result(Ok::<_, String>(result_text(result).len()));
+            if tool_name != "request_permission" {
+                return entry;
+            }
+            let argument = |name: &str| {
+                arguments
+                    .get(name)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+            };
+            return entry.with_permission(
+                format!("granted {} {}", argument("operation"), argument("path")),
+                false,
+            );
+        }
+        Err(e) => e.message.to_string(),
+    };
+    let denied = is_denial(&error);
+    let entry = entry.with_result(Err::<usize, _>(error));
+    if denied {
+        entry.with_permission("denied", true)
+    } else {
+        entry
+    }
+}
+
+/// Text of a tool result, with non-text content serialized
+fn result_text(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .map(|content| match &content.raw {
+            RawContent::Text(text) => text.text.clone(),
+            raw => serde_json::to_string(raw).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Whether a tool error means the call was not permitted
+fn is_denial(error: &str) -> bool {
+    [
+        "No read access",
+        "No write access",
+        "permission not granted",
+        "not in allowlist",
+        "outside the workspace",
+        "ignored in the workspace",
+        "read-only in the workspace",
+        "needs a project workspace",
+        "Cannot access system directory",
+        "Quota exceeded",
+        "Access denied",
+        "Rate limit exceeded",
+    ]
+    .iter()
+    .any(|marker| error.contains(marker))
+}
+
 /// State for the MCP server
 #[derive(Clone)]
 pub struct State {
     permissions: PermissionsRef,
     executor: Arc<dyn Executor + Send + Sync>,
     project_path: Arc<Mutex<Option<String>>>,
+    quotas: QuotasRef,
+    audit: AuditLog,
+    access: Option<Arc<AccessControl>>,
+    api_key: Option<String>,
 }
 
 impl Default for State {
@@ -315,7 +478,64 @@ impl State {
             permissions,
             executor,
             project_path: Arc::new(Mutex::new(None)),
+            quotas: create_quotas(QuotaConfig::default()),
+            audit: AuditLog::new(AuditLog::default_path()),
+            access: None,
+            api_key: None,
+        }
+    }
+
+    /// Limit the session with the given quotas
+    pub fn with_quotas(mut self, config: QuotaConfig) -> Self {
+        self.quotas = create_quotas(config);
+        self
+    }
+
+    /// Require an API key for search tools
+    ///
+    /// # Arguments
+    ///
+    /// * `access` - The configured keys
+    /// * `api_key` - The key of the session, usually from `HAL_API_KEY`
+    pub fn with_access(mut self, access: AccessControl, api_key: Option<String>) -> Self {
+        self.access = Some(Arc::new(access));
+        self.api_key = api_key;
+        self
+    }
+
+    /// Authenticate a call to a search tool, count it against the key's rate limit and
+    /// check that the `source` it names is one of the key's collections
+    ///
+    /// # Returns
+    ///
+    /// Whether the tool must match `source` against whole domains, as for keys restricted
+    /// to some collections
+    pub fn authorize(
+        &self,
+        tool_name: &str,
+        arguments: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<bool, rmcp::Error> {
+        let Some(access) = &self.access else {
+            return Ok(false);
+        };
+        if !tool_search::SearchTools::get_tool_box()
+            .map
+            .contains_key(tool_name)
+        {
+            return Ok(false);
         }
+        let mut options = SearchOptions {
+            source_filter: arguments
+                .get("source")
+                .and_then(|source| source.as_str())
+                .map(str::to_string),
+            ..SearchOptions::default()
+        };
+        access
+            .authenticate(self.api_key.as_deref())
+            .and_then(|key| key.authorize_search(&mut options))
+            .map(|()| options.exact_source)
+            .map_err(|e| rmcp::Error::invalid_request(e.to_string(), None))
     }
 
     /// Get a reference to the permissions
@@ -331,4 +551,14 @@ impl State {
     pub fn project_path(&self) -> Arc<Mutex<Option<String>>> {
         self.project_path.clone()
     }
+
+    /// Get a reference to the usage of the session
+    pub fn quotas(&self) -> QuotasRef {
+        self.quotas.clone()
+    }
+
+    /// Get the log tool calls are recorded in
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
 }
@@ -0,0 +1,396 @@
+//! Linter and formatter utilities for the MCP server
+//!
+//! This module runs a project's own linter and formatter and turns their output into
+//! structured diagnostics, so an agent can iterate on warnings without scraping shell output:
+//! - Toolchain detection from the project's manifest: `Cargo.toml` for cargo clippy and
+//!   rustfmt, `package.json` for eslint and prettier
+//! - Commands run directly, without a shell, in the project directory
+//! - Parsers of cargo's JSON messages, eslint's JSON report and the formatters' check output
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tokio::process::Command;
+
+/// Toolchain of a project, deciding which linter and formatter run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Toolchain {
+    /// cargo clippy and cargo fmt
+    Cargo,
+    /// eslint and prettier through npx
+    Node,
+}
+
+impl Toolchain {
+    /// Detect the toolchain of a project from its manifest
+    pub fn detect(project: &Path) -> Option<Self> {
+        if project.join("Cargo.toml").is_file() {
+            Some(Self::Cargo)
+        } else if project.join("package.json").is_file() {
+            Some(Self::Node)
+        } else {
+            None
+        }
+    }
+
+    /// Program running the linter and the formatter, which needs an execute grant
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Node => "npx",
+        }
+    }
+
+    /// Program and arguments of the linter
+    fn lint_command(self) -> (&'static str, Vec<&'static str>) {
+        let args = match self {
+            Self::Cargo => vec!["clippy", "--all-targets", "--message-format=json"],
+            Self::Node => vec!["--no-install", "eslint", "--format", "json", "."],
+        };
+        (self.program(), args)
+    }
+
+    /// Program and arguments of the formatter, checking or rewriting files
+    fn format_command(self, check: bool) -> (&'static str, Vec<&'static str>) {
+        let args = match (self, check) {
+            (Self::Cargo, true) => vec!["fmt", "--", "--check"],
+            (Self::Cargo, false) => vec!["fmt"],
+            (Self::Node, true) => vec!["--no-install", "prettier", "--check", "."],
+            (Self::Node, false) => vec!["--no-install", "prettier", "--write", "."],
+        };
+        (self.program(), args)
+    }
+}
+
+/// Severity of a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A problem reported by a linter or formatter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// File the problem is in, as reported by the tool
+    pub file: String,
+    /// Line of the problem, starting at 1
+    pub line: usize,
+    /// Column of the problem, starting at 1, if reported
+    pub column: Option<usize>,
+    /// Severity of the problem
+    pub severity: Severity,
+    /// Description of the problem
+    pub message: String,
+    /// Lint or rule that reported the problem, e.g. `clippy::needless_return`
+    pub code: Option<String>,
+}
+
+/// Result of running a linter or formatter
+#[derive(Debug, Clone, Serialize)]
+pub struct LintReport {
+    /// Toolchain that ran
+    pub toolchain: Toolchain,
+    /// The command that ran
+    pub command: String,
+    /// Exit code of the command
+    pub exit_code: i32,
+    /// Problems found
+    pub diagnostics: Vec<Diagnostic>,
+    /// Output of the command when no diagnostics could be parsed from a failure, e.g. a
+    /// missing tool
+    pub output: Option<String>,
+}
+
+impl LintReport {
+    /// Number of diagnostics of a severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+/// Run the linter of a project
+pub async fn run_linter(project: &Path, toolchain: Toolchain) -> std::io::Result<LintReport> {
+    let (program, args) = toolchain.lint_command();
+    let (exit_code, stdout, stderr) = run(project, program, &args).await?;
+    let diagnostics = match toolchain {
+        Toolchain::Cargo => parse_cargo_messages(&stdout, project),
+        Toolchain::Node => parse_eslint_report(&stdout),
+    };
+    Ok(report(
+        toolchain,
+        program,
+        &args,
+        exit_code,
+        diagnostics,
+        stderr,
+    ))
+}
+
+/// Run the formatter of a project
+///
+/// # Arguments
+///
+/// * `project` - Project directory
+/// * `toolchain` - Toolchain of the project
+/// * `check` - Only report unformatted files instead of rewriting them
+pub async fn run_formatter(
+    project: &Path,
+    toolchain: Toolchain,
+    check: bool,
+) -> std::io::Result<LintReport> {
+    let (program, args) = toolchain.format_command(check);
+    let (exit_code, stdout, stderr) = run(project, program, &args).await?;
+    let diagnostics = if check {
+        parse_format_check(&format!("{}\n{}", stdout, stderr))
+    } else {
+        Vec::new()
+    };
+    Ok(report(
+        toolchain,
+        program,
+        &args,
+        exit_code,
+        diagnostics,
+        stderr,
+    ))
+}
+
+/// Run a command in the project directory, returning its exit code, stdout and stderr
+async fn run(
+    project: &Path,
+    program: &str,
+    args: &[&str],
+) -> std::io::Result<(i32, String, String)> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(project)
+        .output()
+        .await?;
+    Ok((
+        output.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    ))
+}
+
+/// Assemble a report, keeping the output of failures without diagnostics
+fn report(
+    toolchain: Toolchain,
+    program: &str,
+    args: &[&str],
+    exit_code: i32,
+    diagnostics: Vec<Diagnostic>,
+    stderr: String,
+) -> LintReport {
+    let output = (exit_code != 0 && diagnostics.is_empty()).then(|| {
+        // The end of the output has the reason of the failure
+        let chars: Vec<char> = stderr.chars().collect();
+        chars[chars.len().saturating_sub(4_000)..].iter().collect()
+    });
+    LintReport {
+        toolchain,
+        command: format!("{} {}", program, args.join(" ")),
+        exit_code,
+        diagnostics,
+        output,
+    }
+}
+
+/// Parse the `compiler-message` lines of `cargo --message-format=json`
+///
+/// Paths are made relative to the project where the message names a file inside it.
+pub fn parse_cargo_messages(stdout: &str, project: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for line in stdout.lines() {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if message["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &message["message"];
+        let severity = match message["level"].as_str() {
+            Some("error") => Severity::Error,
+            Some("warning") => Severity::Warning,
+            Some("note") | Some("help") => Severity::Note,
+            _ => continue,
+        };
+        let Some(span) = message["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true))
+        else {
+            // Summaries such as "3 warnings emitted" have no location
+            continue;
+        };
+
+        let file = span["file_name"].as_str().unwrap_or_default();
+        let file = Path::new(file)
+            .strip_prefix(project)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| PathBuf::from(file));
+        let diagnostic = Diagnostic {
+            file: file.to_string_lossy().to_string(),
+            line: span["line_start"].as_u64().unwrap_or(1) as usize,
+            column: span["column_start"].as_u64().map(|c| c as usize),
+            severity,
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+            code: message["code"]["code"].as_str().map(str::to_string),
+        };
+        // Cargo reports a message again for each target that includes the file
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
+/// Parse the report of `eslint --format json`
+pub fn parse_eslint_report(stdout: &str) -> Vec<Diagnostic> {
+    let Ok(files) = serde_json::from_str::<Vec<serde_json::Value>>(stdout) else {
+        return Vec::new();
+    };
+    files
+        .iter()
+        .flat_map(|file| {
+            let path = file["filePath"].as_str().unwrap_or_default().to_string();
+            file["messages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(move |message| Diagnostic {
+                    file: path.clone(),
+                    line: message["line"].as_u64().unwrap_or(1) as usize,
+                    column: message["column"].as_u64().map(|c| c as usize),
+                    severity: if message["severity"] == 2 {
+                        Severity::Error
+                    } else {
+                        Severity::Warning
+                    },
+                    message: message["message"].as_str().unwrap_or_default().to_string(),
+                    code: message["ruleId"].as_str().map(str::to_string),
+                })
+        })
+        .collect()
+}
+
+/// Parse the unformatted files listed by `cargo fmt -- --check` or `prettier --check`
+pub fn parse_format_check(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for line in output.lines() {
+        // rustfmt: "Diff in /src/main.rs at line 12:" or "Diff in /src/main.rs:12:"
+        let location = if let Some(diff) = line.strip_prefix("Diff in ") {
+            let diff = diff.trim_end_matches(':');
+            match diff.split_once(" at line ") {
+                Some((file, line)) => Some((file.to_string(), line.parse().unwrap_or(1))),
+                None => Some(match diff.rsplit_once(':') {
+                    Some((file, line)) if line.parse::<usize>().is_ok() => {
+                        (file.to_string(), line.parse().unwrap_or(1))
+                    }
+                    _ => (diff.to_string(), 1),
+                }),
+            }
+        // prettier: "[warn] src/app.js"
+        } else {
+            line.strip_prefix("[warn] ")
+                .filter(|file| !file.contains(' '))
+                .map(|file| (file.to_string(), 1))
+        };
+
+        if let Some((file, line)) = location {
+            let diagnostic = Diagnostic {
+                file,
+                line,
+                column: None,
+                severity: Severity::Warning,
+                message: "Not formatted; run format_code to fix".to_string(),
+                code: None,
+            };
+            if !diagnostics.contains(&diagnostic) {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect_toolchain() {
+        let temp_dir = tempdir().unwrap();
+        assert_eq!(Toolchain::detect(temp_dir.path()), None);
+
+        std::fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+        assert_eq!(Toolchain::detect(temp_dir.path()), Some(Toolchain::Node));
+
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(Toolchain::detect(temp_dir.path()), Some(Toolchain::Cargo));
+    }
+
+    #[test]
+    fn test_parse_cargo_messages() {
+        let stdout = r#"{"reason":"compiler-artifact","target":{"name":"hal"}}
+{"reason":"compiler-message","message":{"level":"warning","message":"unneeded `return` statement","code":{"code":"clippy::needless_return"},"spans":[{"file_name":"/work/app/src/main.rs","line_start":12,"column_start":5,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"1 warning emitted","code":null,"spans":[]}}
+{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"src/lib.rs","line_start":3,"column_start":9,"is_primary":true}]}}"#;
+
+        let diagnostics = parse_cargo_messages(stdout, Path::new("/work/app"));
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "src/main.rs");
+        assert_eq!(diagnostics[0].line, 12);
+        assert_eq!(diagnostics[0].column, Some(5));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].code.as_deref(),
+            Some("clippy::needless_return")
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].file, "src/lib.rs");
+    }
+
+    #[test]
+    fn test_parse_eslint_report() {
+        let stdout = r#"[{"filePath":"/app/src/index.js","messages":[{"ruleId":"no-unused-vars","severity":2,"message":"'x' is defined but never used.","line":4,"column":7},{"ruleId":"semi","severity":1,"message":"Missing semicolon.","line":9,"column":20}]},{"filePath":"/app/src/ok.js","messages":[]}]"#;
+
+        let diagnostics = parse_eslint_report(stdout);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "/app/src/index.js");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("no-unused-vars"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].line, 9);
+    }
+
+    #[test]
+    fn test_parse_format_check() {
+        let output = "Diff in /app/src/main.rs at line 12:\n-fn main(){}\n+fn main() {}\nDiff in /app/src/main.rs at line 40:\nDiff in /app/src/lib.rs:3:\nChecking formatting...\n[warn] src/app.js\n[warn] Code style issues found in the above file. Run Prettier with --write to fix.\n";
+
+        let diagnostics = parse_format_check(output);
+
+        let locations: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.file.as_str(), d.line))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                ("/app/src/main.rs", 12),
+                ("/app/src/main.rs", 40),
+                ("/app/src/lib.rs", 3),
+                ("src/app.js", 1),
+            ]
+        );
+    }
+}
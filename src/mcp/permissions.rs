@@ -1,12 +1,12 @@
 //! Permission management for the MCP server
 //!
 //! This module implements the session-based permission system that tracks which
-//! directories have read/write permissions and which shell commands are allowed.
+//! workspaces may be read or written and which shell commands are allowed.
 //! It provides:
 //!
 //! - A thread-safe permission structure that persists throughout the session
-//! - Functions to check if operations are allowed
-//! - Methods to grant new permissions
+//! - Functions to check if operations are allowed and to resolve tool paths
+//! - Methods to grant access to workspaces and commands
 //! - Path validation to prevent access to sensitive system directories
 
 use std::collections::HashSet;
@@ -15,22 +15,24 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::info;
 
-/// Session permissions structure to track allowed directories and commands
+use super::workspace::{Access, Workspace};
+
+/// Session permissions structure to track allowed workspaces and commands
 ///
-/// This structure maintains three sets of permissions:
-/// - Directories with read permission
-/// - Directories with write permission
+/// This structure maintains:
+/// - Workspaces with read or write access, see [`Workspace`]
+/// - The project workspace that relative paths are resolved against
 /// - Allowed shell commands (simple allowlist)
 ///
-/// The permission state is maintained throughout the session, so permissions
-/// only need to be granted once for each directory or command.
+/// The permission state is maintained throughout the session, so access
+/// only needs to be granted once for each workspace or command.
 #[derive(Debug, Clone)]
 pub struct SessionPermissions {
-    /// Directories with read permission
-    read_allowed_dirs: HashSet<PathBuf>,
+    /// Workspaces with the access granted to them
+    workspaces: Vec<(Workspace, Access)>,
 
-    /// Directories with write permission
-    write_allowed_dirs: HashSet<PathBuf>,
+    /// Root of the workspace relative paths are resolved against
+    project: Option<PathBuf>,
 
     /// Allowed shell commands (simple allowlist)
     allowed_commands: HashSet<String>,
@@ -58,80 +60,127 @@ impl SessionPermissions {
         allowed_commands.insert("which".to_string());
 
         Self {
-            read_allowed_dirs: HashSet::new(),
-            write_allowed_dirs: HashSet::new(),
+            workspaces: Vec::new(),
+            project: None,
             allowed_commands,
         }
     }
 
     /// Check if read is allowed for a path
     pub fn can_read(&self, path: &Path) -> bool {
-        self.has_permission(path, &self.read_allowed_dirs)
+        self.resolve(path, Access::Read).is_ok()
     }
 
     /// Check if write is allowed for a path
     pub fn can_write(&self, path: &Path) -> bool {
-        self.has_permission(path, &self.write_allowed_dirs)
+        self.resolve(path, Access::Write).is_ok()
     }
 
-    /// Check if a path is within any of the allowed directories
-    fn has_permission(&self, path: &Path, allowed_dirs: &HashSet<PathBuf>) -> bool {
-        // First try to canonicalize the path
-        let canonical_path = match path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => {
-                // If we can't canonicalize, check if the parent directory is allowed
-                // This is useful for operations like writing to a new file
-                if let Some(parent) = path.parent() {
-                    match parent.canonicalize() {
-                        Ok(parent_path) => {
-                            return allowed_dirs.iter().any(|dir| parent_path.starts_with(dir));
-                        }
-                        Err(_) => return false,
-                    }
-                }
-                return false;
-            }
+    /// Resolve a path given to a tool through the workspaces of the session
+    ///
+    /// Relative paths are resolved against the project workspace; absolute paths must be
+    /// inside a workspace granted the access.
+    ///
+    /// # Returns
+    ///
+    /// The absolute path, or an error naming why access is denied
+    pub fn resolve(&self, path: &Path, access: Access) -> Result<PathBuf, String> {
+        let resolve = |workspace: &Workspace| match access {
+            Access::Read => workspace.resolve(path),
+            Access::Write => workspace.resolve_writable(path),
         };
+        let operation = match access {
+            Access::Read => "read",
+            Access::Write => "write",
+        };
+
+        if path.is_relative() {
+            let Some((workspace, granted)) = self.project_workspace() else {
+                return Err(format!(
+                    "Relative path {} needs a project workspace. Call init first",
+                    path.display()
+                ));
+            };
+            if *granted < access {
+                return Err(format!(
+                    "No {} access to the workspace {}. Use request_permission with operation='{}' first",
+                    operation,
+                    workspace.root().display(),
+                    operation
+                ));
+            }
+            return resolve(workspace);
+        }
+
+        let mut denial = None;
+        for (workspace, granted) in &self.workspaces {
+            if *granted < access {
+                continue;
+            }
+            match resolve(workspace) {
+                Ok(resolved) => return Ok(resolved),
+                // Keep the reason of a workspace the path is in, e.g. an ignore rule
+                Err(e) if !e.contains("outside the workspace") => denial = Some(e),
+                Err(_) => {}
+            }
+        }
+        Err(denial.unwrap_or_else(|| {
+            format!(
+                "No {} access to {}. Use request_permission with operation='{}' to grant access to its workspace first",
+                operation,
+                path.display(),
+                operation
+            )
+        }))
+    }
 
-        // Check if path is within any allowed directory
-        allowed_dirs
+    /// The workspace relative paths are resolved against, with its access
+    pub fn project_workspace(&self) -> Option<&(Workspace, Access)> {
+        let project = self.project.as_ref()?;
+        self.workspaces
             .iter()
-            .any(|dir| canonical_path.starts_with(dir))
+            .find(|(workspace, _)| workspace.root() == project)
     }
 
-    /// Check if a shell command is allowed
-    pub fn can_execute_command(&self, command: &str) -> bool {
-        // Extract the program name (first word)
-        let program = command.split_whitespace().next().unwrap_or("");
-        self.allowed_commands.contains(program)
+    /// Workspaces of the session with the access granted to them
+    pub fn workspaces(&self) -> &[(Workspace, Access)] {
+        &self.workspaces
     }
 
-    /// Grant read permission for a directory
-    pub fn allow_read(&mut self, dir: PathBuf) {
-        info!("Granting read permission for directory: {}", dir.display());
-        // Try to canonicalize the path
-        if let Ok(canonical_path) = dir.canonicalize() {
-            self.read_allowed_dirs.insert(canonical_path);
-        } else {
-            // If canonicalization fails, use the path as is
-            self.read_allowed_dirs.insert(dir);
+    /// Grant access to a workspace, replacing its rules if it was granted before
+    ///
+    /// Access is never lowered: granting read access to a writable workspace keeps it
+    /// writable.
+    pub fn grant(&mut self, workspace: Workspace, access: Access) {
+        info!(
+            "Granting {:?} access to workspace: {}",
+            access,
+            workspace.root().display()
+        );
+        match self
+            .workspaces
+            .iter_mut()
+            .find(|(existing, _)| existing.root() == workspace.root())
+        {
+            Some((existing, granted)) => {
+                *existing = workspace;
+                *granted = access.max(*granted);
+            }
+            None => self.workspaces.push((workspace, access)),
         }
     }
 
-    /// Grant write permission for a directory
-    pub fn allow_write(&mut self, dir: PathBuf) {
-        info!("Granting write permission for directory: {}", dir.display());
-        // Try to canonicalize the path
-        if let Ok(canonical_path) = dir.canonicalize() {
-            self.write_allowed_dirs.insert(canonical_path.clone());
-            // Write permission implies read permission
-            self.read_allowed_dirs.insert(canonical_path);
-        } else {
-            // If canonicalization fails, use the path as is
-            self.write_allowed_dirs.insert(dir.clone());
-            self.read_allowed_dirs.insert(dir);
-        }
+    /// Grant write access to a workspace and resolve relative paths against it
+    pub fn set_project(&mut self, workspace: Workspace) {
+        self.project = Some(workspace.root().to_path_buf());
+        self.grant(workspace, Access::Write);
+    }
+
+    /// Check if a shell command is allowed
+    pub fn can_execute_command(&self, command: &str) -> bool {
+        // Extract the program name (first word)
+        let program = command.split_whitespace().next().unwrap_or("");
+        self.allowed_commands.contains(program)
     }
 
     /// Allow a new shell command
@@ -181,3 +230,29 @@ pub fn basic_path_validation(path: &Path) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_access_through_workspaces() {
+        let project = tempdir().unwrap();
+        let docs = tempdir().unwrap();
+        let mut perms = SessionPermissions::new();
+        assert!(!perms.can_read(Path::new("src/main.rs")));
+
+        perms.set_project(Workspace::new(project.path()).unwrap());
+        perms.grant(Workspace::new(docs.path()).unwrap(), Access::Read);
+
+        assert!(perms.can_write(Path::new("src/main.rs")));
+        assert!(!perms.can_read(Path::new("../escape")));
+        assert!(perms.can_read(&docs.path().join("guide.md")));
+        assert!(!perms.can_write(&docs.path().join("guide.md")));
+
+        // Granting read access again does not take write access away
+        perms.grant(Workspace::new(project.path()).unwrap(), Access::Read);
+        assert!(perms.can_write(&project.path().join("new.rs")));
+    }
+}
@@ -0,0 +1,284 @@
+//! Per-session quotas for the MCP server
+//!
+//! Permissions decide what a session may touch; quotas bound how much it may do, so a
+//! runaway agent cannot hammer the host. The server checks every tool call against the
+//! session's quotas before dispatching it:
+//!
+//! - Shell commands: `execute_shell_command`, `run_linter` and `format_code` runs
+//! - Bytes written: the content of `write_file` and the replacements of `edit_file`
+//! - Searches per minute: `search`, `code_search` and `load_relevant_files` calls
+//!
+//! A call over a quota fails with [`QUOTA_EXCEEDED`] and a [`QuotaExceeded`] as error data,
+//! telling the client which quota ran out and, for rate limits, when to retry.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rmcp::model::ErrorCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// JSON-RPC error code of calls over a quota
+pub const QUOTA_EXCEEDED: ErrorCode = ErrorCode(-32029);
+
+/// Tools counted as shell commands
+const SHELL_TOOLS: &[&str] = &["execute_shell_command", "run_linter", "format_code"];
+
+/// Tools counted as searches
+const SEARCH_TOOLS: &[&str] = &["search", "code_search", "load_relevant_files"];
+
+/// Window of the search rate limit
+const SEARCH_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits of a session; unset limits are not enforced
+///
+/// Read from the `[mcp_quotas]` table of `hal.toml` and overridden by `hal mcp` flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Most shell commands a session may run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_shell_commands: Option<u64>,
+
+    /// Most bytes a session may write to files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes_written: Option<u64>,
+
+    /// Most searches a session may run in any minute
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_searches_per_minute: Option<u64>,
+}
+
+/// A quota a call ran into, sent to the client as error data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuotaExceeded {
+    /// Name of the quota, as in [`QuotaConfig`]
+    pub quota: &'static str,
+    /// Limit of the quota
+    pub limit: u64,
+    /// Amount used so far
+    pub used: u64,
+    /// Amount the call asked for
+    pub requested: u64,
+    /// Seconds until the call may be retried, for rate limits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Quota exceeded: {} is {} and {} of it is used",
+            self.quota, self.limit, self.used
+        )?;
+        match self.retry_after_secs {
+            Some(secs) => write!(f, ". Retry in {} seconds", secs),
+            None => write!(f, " for this session"),
+        }
+    }
+}
+
+impl From<QuotaExceeded> for rmcp::Error {
+    fn from(exceeded: QuotaExceeded) -> Self {
+        let data = serde_json::to_value(&exceeded).ok();
+        rmcp::Error::new(QUOTA_EXCEEDED, exceeded.to_string(), data)
+    }
+}
+
+/// Usage of a session, checked against its quotas
+#[derive(Debug, Clone)]
+pub struct SessionQuotas {
+    /// Limits of the session
+    config: QuotaConfig,
+    /// Shell commands run
+    shell_commands: u64,
+    /// Bytes written to files
+    bytes_written: u64,
+    /// Start times of the searches of the last minute
+    searches: VecDeque<Instant>,
+}
+
+impl SessionQuotas {
+    /// Create the usage of a new session with the given limits
+    pub fn new(config: QuotaConfig) -> Self {
+        Self {
+            config,
+            shell_commands: 0,
+            bytes_written: 0,
+            searches: VecDeque::new(),
+        }
+    }
+
+    /// Limits of the session
+    pub fn config(&self) -> &QuotaConfig {
+        &self.config
+    }
+
+    /// Check a tool call against the quotas and count it if it is allowed
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Name of the tool
+    /// * `arguments` - Arguments of the call, to count the bytes it writes
+    pub fn admit(
+        &mut self,
+        tool: &str,
+        arguments: &Map<String, Value>,
+    ) -> Result<(), QuotaExceeded> {
+        self.admit_at(tool, arguments, Instant::now())
+    }
+
+    fn admit_at(
+        &mut self,
+        tool: &str,
+        arguments: &Map<String, Value>,
+        now: Instant,
+    ) -> Result<(), QuotaExceeded> {
+        if SHELL_TOOLS.contains(&tool) {
+            check(
+                "max_shell_commands",
+                self.config.max_shell_commands,
+                self.shell_commands,
+                1,
+            )?;
+            self.shell_commands += 1;
+        } else if let Some(bytes) = bytes_to_write(tool, arguments) {
+            check(
+                "max_bytes_written",
+                self.config.max_bytes_written,
+                self.bytes_written,
+                bytes,
+            )?;
+            self.bytes_written += bytes;
+        } else if SEARCH_TOOLS.contains(&tool) {
+            while let Some(&oldest) = self.searches.front() {
+                if now.duration_since(oldest) < SEARCH_WINDOW {
+                    break;
+                }
+                self.searches.pop_front();
+            }
+            check(
+                "max_searches_per_minute",
+                self.config.max_searches_per_minute,
+                self.searches.len() as u64,
+                1,
+            )
+            .map_err(|mut exceeded| {
+                // The oldest search leaving the window frees a slot
+                let oldest = self.searches.front().copied().unwrap_or(now);
+                let wait = SEARCH_WINDOW.saturating_sub(now.duration_since(oldest));
+                exceeded.retry_after_secs = Some(wait.as_secs().max(1));
+                exceeded
+            })?;
+            self.searches.push_back(now);
+        }
+        Ok(())
+    }
+}
+
+/// Check a usage against a limit, if there is one
+fn check(
+    quota: &'static str,
+    limit: Option<u64>,
+    used: u64,
+    requested: u64,
+) -> Result<(), QuotaExceeded> {
+    match limit {
+        Some(limit) if used + requested > limit => {
+            warn!(quota, limit, used, requested, "Tool call over quota");
+            Err(QuotaExceeded {
+                quota,
+                limit,
+                used,
+                requested,
+                retry_after_secs: None,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Bytes a file tool call writes, if it writes any
+fn bytes_to_write(tool: &str, arguments: &Map<String, Value>) -> Option<u64> {
+    let argument = match tool {
+        "write_file" => "content",
+        "edit_file" => "new_string",
+        _ => return None,
+    };
+    let text = arguments.get(argument).and_then(Value::as_str)?;
+    Some(text.len() as u64)
+}
+
+/// Thread-safe reference to the usage of a session
+pub type QuotasRef = Arc<Mutex<SessionQuotas>>;
+
+/// Create the usage of a new session with the given limits
+pub fn create_quotas(config: QuotaConfig) -> QuotasRef {
+    Arc::new(Mutex::new(SessionQuotas::new(config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn arguments(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap_or_default()
+    }
+
+    #[test]
+    fn test_session_quotas() {
+        let mut quotas = SessionQuotas::new(QuotaConfig {
+            max_shell_commands: Some(2),
+            max_bytes_written: Some(10),
+            max_searches_per_minute: None,
+        });
+        let none = Map::new();
+
+        assert!(quotas.admit("execute_shell_command", &none).is_ok());
+        assert!(quotas.admit("run_linter", &none).is_ok());
+        let exceeded = quotas.admit("execute_shell_command", &none).unwrap_err();
+        assert_eq!(exceeded.quota, "max_shell_commands");
+        assert_eq!(exceeded.used, 2);
+        assert_eq!(exceeded.retry_after_secs, None);
+
+        let write = arguments(json!({ "path": "a.txt", "content": "12345678" }));
+        assert!(quotas.admit("write_file", &write).is_ok());
+        let edit = arguments(json!({ "path": "a.txt", "old_string": "1", "new_string": "abc" }));
+        let exceeded = quotas.admit("edit_file", &edit).unwrap_err();
+        assert_eq!((exceeded.used, exceeded.requested), (8, 3));
+
+        for _ in 0..100 {
+            assert!(quotas.admit("search", &none).is_ok());
+        }
+        assert!(quotas.admit("show_file", &none).is_ok());
+    }
+
+    #[test]
+    fn test_search_rate_limit() {
+        let mut quotas = SessionQuotas::new(QuotaConfig {
+            max_searches_per_minute: Some(2),
+            ..QuotaConfig::default()
+        });
+        let none = Map::new();
+        let start = Instant::now();
+
+        assert!(quotas.admit_at("search", &none, start).is_ok());
+        let later = start + Duration::from_secs(20);
+        assert!(quotas.admit_at("code_search", &none, later).is_ok());
+        let exceeded = quotas.admit_at("search", &none, later).unwrap_err();
+        assert_eq!(exceeded.retry_after_secs, Some(40));
+
+        let error = rmcp::Error::from(exceeded);
+        assert_eq!(error.code, QUOTA_EXCEEDED);
+        assert_eq!(error.data.unwrap()["quota"], "max_searches_per_minute");
+
+        // The first search has left the window
+        let next_minute = start + Duration::from_secs(61);
+        assert!(quotas.admit_at("search", &none, next_minute).is_ok());
+    }
+}
@@ -0,0 +1,261 @@
+//! Coder tools for RMCP server using attribute macros
+//!
+//! This module runs coding tasks with the `AgentExecutor` inside the MCP server. The agent
+//! uses the server's own tools through an in-process client, sharing the session's
+//! permissions, and its events are forwarded to the MCP client as they happen:
+//!
+//! - Each `ExecutorEvent` is sent as a `notifications/message` logging notification from
+//!   the `hal-coder` logger, with the event as structured data
+//! - A `notifications/progress` notification counts the events so far, with the tool call's
+//!   request ID as progress token
+//!
+//! This lets clients render live agent activity instead of waiting for the final result.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::json;
+
+use rmcp::{
+    Error, Peer, RoleServer, ServiceExt,
+    handler::server::tool::ToolBox,
+    model::{
+        CallToolResult, Content, LoggingLevel, LoggingMessageNotificationParam,
+        ProgressNotificationParam, RequestId,
+    },
+    serve_server, tool,
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::coder::artifacts::DEFAULT_RESULT_BUDGET;
+use crate::coder::{AgentExecutor, ArtifactStore, ExecutorEvent, TASK_PREAMBLE};
+use crate::mcp::HalServer;
+use crate::mcp::adaptor::McpManager;
+
+/// Name of the tool running coding tasks, hidden from the agent itself
+pub const CODER_TOOL: &str = "run_coding_task";
+
+/// Logger name of the forwarded executor events
+const LOGGER: &str = "hal-coder";
+
+/// Coder tools handler running agent tasks with live notifications
+#[derive(Clone)]
+pub struct CoderTools {
+    /// Server whose tools the agent uses
+    server: HalServer,
+    /// Client the tool call came from
+    peer: Peer<RoleServer>,
+    /// ID of the tool call request, used as progress token
+    request_id: RequestId,
+}
+
+#[tool(tool_box)]
+impl CoderTools {
+    /// Create a new CoderTools instance for a tool call
+    pub fn new(server: HalServer, peer: Peer<RoleServer>, request_id: RequestId) -> Self {
+        Self {
+            server,
+            peer,
+            request_id,
+        }
+    }
+
+    pub fn get_tool_box() -> &'static ToolBox<Self> {
+        // Calls the associated function generated by #[tool(tool_box)]
+        Self::tool_box()
+    }
+
+    /// Run a coding task with an agent using this server's tools
+    #[tool(
+        description = "Run a coding task with an agent that uses this server's file and shell tools in a project directory. The agent's thoughts, tool calls and results are sent as logging and progress notifications while it works. Returns the agent's summary when it finishes."
+    )]
+    async fn run_coding_task(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The coding task, e.g. 'refactor the config loading in src'")]
+        task: String,
+
+        #[tool(param)]
+        #[schemars(description = "Path to the project directory to work in")]
+        path: String,
+
+        #[tool(param)]
+        #[schemars(description = "Most agent iterations before giving up (default 30)")]
+        max_iterations: Option<usize>,
+    ) -> Result<CallToolResult, Error> {
+        if task.trim().is_empty() {
+            return Err(Error::invalid_request("Task cannot be empty", None));
+        }
+        info!(task = %task, path = %path, "Running coding task");
+
+        // The agent calls this server's tools through an in-process client
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        let server = serve_server(self.server.clone(), server_io)
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to start tools: {}", e), None))?;
+        let client = ().serve(client_io).await.map_err(|e| {
+            Error::internal_error(format!("Failed to connect to tools: {}", e), None)
+        })?;
+        let manager = McpManager {
+            clients: HashMap::from([("hal".to_string(), client)]),
+        };
+        let (tools, tool_defs) = manager
+            .get_tool_set_and_defs()
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to list tools: {}", e), None))?;
+        let tool_defs: Vec<_> = tool_defs
+            .into_iter()
+            .filter(|def| def.name != CODER_TOOL)
+            .collect();
+
+        let init = tools
+            .call("init", json!({ "path": path }).to_string())
+            .await
+            .map_err(|e| {
+                Error::invalid_request(format!("Failed to initialize {}: {}", path, e), None)
+            })?;
+
+        let mut agent = self
+            .server
+            .model
+            .as_ref()
+            .clone()
+            .agent()
+            .preamble(TASK_PREAMBLE)
+            .append_preamble(&format!(
+                "The project directory is {}. Its initialization returned:\n{}",
+                path, init
+            ))
+            .build();
+        agent.tools = tools;
+
+        let mut executor = AgentExecutor::new(
+            Arc::new(agent),
+            Arc::new(tool_defs),
+            max_iterations.unwrap_or(30),
+        )
+        .with_artifacts(ArtifactStore::new(
+            ArtifactStore::default_dir(),
+            DEFAULT_RESULT_BUDGET,
+        ));
+        let (tx, mut rx) = mpsc::channel(32);
+        let execution = tokio::spawn(async move { executor.execute(task, tx).await });
+
+        let mut summary = None;
+        let mut failure = None;
+        let mut progress = 0;
+        while let Some(event) = rx.recv().await {
+            match event {
+                Ok(event) => {
+                    progress += 1;
+                    if let ExecutorEvent::Finished { summary: text } = &event {
+                        summary = Some(text.clone());
+                    }
+                    self.forward(&event, progress).await;
+                }
+                Err(e) => {
+                    failure = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        let _ = execution.await;
+        let _ = server.cancel().await;
+
+        let result = json!({
+            "success": failure.is_none(),
+            "summary": summary,
+            "error": failure,
+            "events": progress,
+        });
+        let content = vec![Content::text(serde_json::to_string(&result).unwrap())];
+        Ok(match failure {
+            Some(_) => CallToolResult::error(content),
+            None => CallToolResult::success(content),
+        })
+    }
+}
+
+impl CoderTools {
+    /// Send an executor event to the client as logging and progress notifications
+    async fn forward(&self, event: &ExecutorEvent, progress: u32) {
+        if let Err(e) = self
+            .peer
+            .notify_logging_message(event_notification(event))
+            .await
+        {
+            warn!("Failed to send coder event notification: {}", e);
+        }
+        let _ = self
+            .peer
+            .notify_progress(ProgressNotificationParam {
+                progress_token: self.request_id.clone(),
+                progress,
+                total: None,
+            })
+            .await;
+    }
+}
+
+/// The logging notification of an executor event
+pub fn event_notification(event: &ExecutorEvent) -> LoggingMessageNotificationParam {
+    let (level, data) = match event {
+        ExecutorEvent::Thinking { text } => (
+            LoggingLevel::Info,
+            json!({ "event": "thinking", "text": text }),
+        ),
+        ExecutorEvent::ToolCallAttempted { call } => (
+            LoggingLevel::Info,
+            json!({
+                "event": "tool_call",
+                "id": call.id,
+                "tool": call.function.name,
+                "arguments": call.function.arguments,
+            }),
+        ),
+        ExecutorEvent::ToolCallCompleted {
+            id,
+            result,
+            tool_name,
+        } => (
+            LoggingLevel::Info,
+            json!({ "event": "tool_result", "id": id, "tool": tool_name, "result": result }),
+        ),
+        ExecutorEvent::ExecutionError { error } => (
+            LoggingLevel::Warning,
+            json!({ "event": "error", "error": error }),
+        ),
+        ExecutorEvent::Finished { summary } => (
+            LoggingLevel::Info,
+            json!({ "event": "finished", "summary": summary }),
+        ),
+    };
+    LoggingMessageNotificationParam {
+        level,
+        logger: Some(LOGGER.to_string()),
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_notification() {
+        let notification = event_notification(&ExecutorEvent::Thinking {
+            text: "Reading main.rs".to_string(),
+        });
+        assert_eq!(notification.level, LoggingLevel::Info);
+        assert_eq!(notification.logger.as_deref(), Some(LOGGER));
+        assert_eq!(notification.data["event"], "thinking");
+        assert_eq!(notification.data["text"], "Reading main.rs");
+
+        let notification = event_notification(&ExecutorEvent::ExecutionError {
+            error: "Tool call 'write_file' failed".to_string(),
+        });
+        assert_eq!(notification.level, LoggingLevel::Warning);
+        assert_eq!(notification.data["event"], "error");
+    }
+}
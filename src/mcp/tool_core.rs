@@ -17,6 +17,7 @@ use rmcp::{
 
 use crate::mcp::file_utils;
 use crate::mcp::permissions::PermissionsRef;
+use crate::mcp::workspace::{Access, Workspace};
 use tokio::sync::Mutex;
 
 /// Core tools handler implementing basic utility tools
@@ -301,50 +302,35 @@ impl CoreTools {
         // Update permissions
         let mut perms = self.permissions.lock().await;
         let (result, details) = match operation.as_str() {
-            "read" => {
-                // Check if the directory exists before granting permission
+            "read" | "write" => {
+                // Access is granted to the directory as a whole workspace
                 let exists = dir_path.exists();
-                let status = if exists { "existing" } else { "specified" };
-
-                perms.allow_read(dir_path.clone());
-                (
-                    format!(
-                        "Read permission granted for {} directory: {}",
-                        status,
-                        dir_path.display()
-                    ),
-                    json!({
-                        "operation": "read",
-                        "path": dir_path.to_string_lossy(),
-                        "exists": exists,
-                        "absolute_path": dir_path.canonicalize()
-                            .map(|p| p.to_string_lossy().to_string())
-                            .unwrap_or_else(|_| dir_path.to_string_lossy().to_string())
-                    }),
-                )
-            }
-            "write" => {
-                // Check if the directory exists before granting permission
-                let exists = dir_path.exists();
-                let status = if exists { "existing" } else { "specified" };
-
-                perms.allow_write(dir_path.clone());
-                (
-                    format!(
-                        "Write permission granted for {} directory: {}",
-                        status,
-                        dir_path.display()
-                    ),
-                    json!({
-                        "operation": "write",
-                        "path": dir_path.to_string_lossy(),
-                        "exists": exists,
-                        "absolute_path": dir_path.canonicalize()
-                            .map(|p| p.to_string_lossy().to_string())
-                            .unwrap_or_else(|_| dir_path.to_string_lossy().to_string()),
-                        "includes_read": true
-                    }),
-                )
+                let workspace =
+                    Workspace::open(&dir_path).map_err(|e| Error::invalid_request(e, None))?;
+                let access = if operation == "write" {
+                    Access::Write
+                } else {
+                    Access::Read
+                };
+                let details = json!({
+                    "operation": operation,
+                    "path": dir_path.to_string_lossy(),
+                    "exists": exists,
+                    "absolute_path": workspace.root().to_string_lossy(),
+                    "workspace": workspace_json(&workspace, access),
+                    "includes_read": access == Access::Write
+                });
+                let message = format!(
+                    "{} access granted to workspace: {}",
+                    if access == Access::Write {
+                        "Write"
+                    } else {
+                        "Read"
+                    },
+                    workspace.root().display()
+                );
+                perms.grant(workspace, access);
+                (message, details)
             }
             "execute" => {
                 let program = path
@@ -421,15 +407,11 @@ impl CoreTools {
                 .to_path_buf()
         };
 
-        // Get canonicalized path if possible for better error messages
-        let canonical_path = dir_path.canonicalize().unwrap_or_else(|_| dir_path.clone());
-
-        // Grant permissions
-        {
-            let perms = &mut *self.permissions.lock().await;
-            perms.allow_read(dir_path.clone());
-            perms.allow_write(dir_path.clone());
-        }
+        // The project directory becomes the workspace relative paths are resolved against
+        let workspace = Workspace::open(&dir_path).map_err(|e| Error::invalid_request(e, None))?;
+        let canonical_path = workspace.root().to_path_buf();
+        let workspace_details = workspace_json(&workspace, Access::Write);
+        self.permissions.lock().await.set_project(workspace);
 
         // Store project path
         {
@@ -437,7 +419,7 @@ impl CoreTools {
         }
 
         // Get directory tree
-        match file_utils::directory_tree(&path_buf, &self.permissions).await {
+        match file_utils::directory_tree(&canonical_path, &self.permissions).await {
             Ok(tree) => {
                 // Count directories and files separately for better statistics
                 let dirs_count = tree
@@ -470,6 +452,7 @@ impl CoreTools {
                         "read": true,
                         "write": true
                     },
+                    "workspace": workspace_details,
                     "directory_tree": directory_tree
                 });
 
@@ -487,6 +470,7 @@ impl CoreTools {
                         "read": true,
                         "write": true
                     },
+                    "workspace": workspace_details,
                     "directory_tree_error": format!("Failed to generate directory tree: {}", e)
                 });
 
@@ -497,3 +481,13 @@ impl CoreTools {
         }
     }
 }
+
+/// Describe a workspace and its access in a tool result
+fn workspace_json(workspace: &Workspace, access: Access) -> serde_json::Value {
+    json!({
+        "root": workspace.root().to_string_lossy(),
+        "access": access,
+        "ignore": workspace.ignore(),
+        "read_only": workspace.read_only(),
+    })
+}
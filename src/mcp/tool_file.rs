@@ -1,9 +1,11 @@
 //! File operation tools for RMCP server using attribute macros
 //!
 //! This module contains tools for file operations using the new RMCP attribute macro pattern.
+//! Paths are resolved through the session's workspaces, so they may be relative to the
+//! project and cannot escape the workspaces granted access.
 
 use serde_json::json;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use rmcp::{
     Error,
@@ -14,6 +16,7 @@ use rmcp::{
 
 use crate::mcp::file_utils;
 use crate::mcp::permissions::PermissionsRef;
+use crate::mcp::workspace::Access;
 
 /// File tools handler implementing operations on files and directories
 #[derive(Clone)]
@@ -42,7 +45,7 @@ impl FileTools {
     async fn show_file(
         &self,
         #[tool(param)]
-        #[schemars(description = "Path to the file")]
+        #[schemars(description = "Path to the file, relative to the project or absolute")]
         path: String,
 
         #[tool(param)]
@@ -53,17 +56,8 @@ impl FileTools {
         #[schemars(description = "Ending line number (inclusive, optional)")]
         end_line: Option<i32>,
     ) -> Result<CallToolResult, Error> {
-        let path_buf = PathBuf::from(&path);
-
-        // Check read permission for the file's directory
-        let perms = self.permissions.lock().await;
-        if !perms.can_read(&path_buf) {
-            return Err(Error::invalid_request(
-                format!("No read permission for file: {}", path_buf.display()),
-                None,
-            ));
-        }
-        drop(perms);
+        // Resolve the path through the session's workspaces
+        let path_buf = self.resolve(&path, Access::Read).await?;
 
         // Validate line range parameters if provided
         if let Some(start) = start_line {
@@ -126,7 +120,7 @@ impl FileTools {
     async fn search_in_file(
         &self,
         #[tool(param)]
-        #[schemars(description = "Path to the file")]
+        #[schemars(description = "Path to the file, relative to the project or absolute")]
         path: String,
 
         #[tool(param)]
@@ -137,19 +131,10 @@ impl FileTools {
         #[schemars(description = "Whether to treat pattern as regex (default: false)")]
         is_regex: Option<bool>,
     ) -> Result<CallToolResult, Error> {
-        let path_buf = PathBuf::from(&path);
+        // Resolve the path through the session's workspaces
+        let path_buf = self.resolve(&path, Access::Read).await?;
         let use_regex = is_regex.unwrap_or(false);
 
-        // Check read permission for the file's directory
-        let perms = self.permissions.lock().await;
-        if !perms.can_read(&path_buf) {
-            return Err(Error::invalid_request(
-                format!("No read permission for file: {}", path_buf.display()),
-                None,
-            ));
-        }
-        drop(perms);
-
         // Validate regex pattern if regex mode is enabled
         if use_regex {
             match regex::Regex::new(&pattern) {
@@ -205,7 +190,7 @@ impl FileTools {
     async fn edit_file(
         &self,
         #[tool(param)]
-        #[schemars(description = "Path to the file")]
+        #[schemars(description = "Path to the file, relative to the project or absolute")]
         path: String,
 
         #[tool(param)]
@@ -216,17 +201,8 @@ impl FileTools {
         #[schemars(description = "Text to replace with")]
         new_string: String,
     ) -> Result<CallToolResult, Error> {
-        let path_buf = PathBuf::from(&path);
-
-        // Check write permission for the file's directory
-        let perms = self.permissions.lock().await;
-        if !perms.can_write(&path_buf) {
-            return Err(Error::invalid_request(
-                format!("No write permission for file: {}", path_buf.display()),
-                None,
-            ));
-        }
-        drop(perms);
+        // Resolve the path through the session's workspaces
+        let path_buf = self.resolve(&path, Access::Write).await?;
 
         // Parameter validation
         if old_string.is_empty() {
@@ -289,7 +265,7 @@ impl FileTools {
     async fn write_file(
         &self,
         #[tool(param)]
-        #[schemars(description = "Path to the file")]
+        #[schemars(description = "Path to the file, relative to the project or absolute")]
         path: String,
 
         #[tool(param)]
@@ -302,19 +278,10 @@ impl FileTools {
         )]
         append: Option<bool>,
     ) -> Result<CallToolResult, Error> {
-        let path_buf = PathBuf::from(&path);
+        // Resolve the path through the session's workspaces
+        let path_buf = self.resolve(&path, Access::Write).await?;
         let should_append = append.unwrap_or(false);
 
-        // Check write permission for the file's directory
-        let perms = self.permissions.lock().await;
-        if !perms.can_write(&path_buf) {
-            return Err(Error::invalid_request(
-                format!("No write permission for file: {}", path_buf.display()),
-                None,
-            ));
-        }
-        drop(perms);
-
         // Additional parameter validation
         let file_exists = path_buf.exists();
 
@@ -372,20 +339,11 @@ impl FileTools {
     async fn directory_tree(
         &self,
         #[tool(param)]
-        #[schemars(description = "Path to the directory")]
+        #[schemars(description = "Path to the directory, relative to the project or absolute")]
         path: String,
     ) -> Result<CallToolResult, Error> {
-        let path_buf = PathBuf::from(&path);
-
-        // Check read permission for the directory
-        let perms = self.permissions.lock().await;
-        if !perms.can_read(&path_buf) {
-            return Err(Error::invalid_request(
-                format!("No read permission for directory: {}", path_buf.display()),
-                None,
-            ));
-        }
-        drop(perms);
+        // Resolve the path through the session's workspaces
+        let path_buf = self.resolve(&path, Access::Read).await?;
 
         // Validate if path exists
         if !path_buf.exists() {
@@ -447,3 +405,14 @@ impl FileTools {
         }
     }
 }
+
+impl FileTools {
+    /// Resolve a tool's path through the session's workspaces
+    async fn resolve(&self, path: &str, access: Access) -> Result<PathBuf, Error> {
+        self.permissions
+            .lock()
+            .await
+            .resolve(Path::new(path), access)
+            .map_err(|e| Error::invalid_request(e, None))
+    }
+}
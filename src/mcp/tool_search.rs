@@ -1,9 +1,14 @@
 //! Search tools for RMCP server using attribute macros
 //!
-//! This module contains search functionality using the new RMCP attribute macro pattern.
+//! This module contains search, code search, relevance feedback and task context loading
+//! functionality
+//! using the new RMCP attribute macro pattern.
 
 use serde_json::json;
 
+use crate::coder::context::{ContextOptions, DEFAULT_CONTEXT_TOKENS, load_relevant_files};
+use crate::search::code::code_search;
+
 use rmcp::{
     Error,
     handler::server::tool::ToolBox,
@@ -14,14 +19,19 @@ use rmcp::{
 /// Search tools handler implementing semantic search functionality
 #[derive(Clone)]
 pub struct SearchTools {
-    // Add any necessary state for search here
+    /// Match `source` against whole domains, as for API keys restricted to collections
+    exact_source: bool,
 }
 
 #[tool(tool_box)]
 impl SearchTools {
     /// Create a new SearchTools instance
-    pub fn new() -> Self {
-        Self {}
+    ///
+    /// # Arguments
+    ///
+    /// * `exact_source` - Match the `source` of searches against whole domains
+    pub fn new(exact_source: bool) -> Self {
+        Self { exact_source }
     }
 
     pub fn get_tool_box() -> &'static ToolBox<Self> {
@@ -67,4 +77,168 @@ impl SearchTools {
             serde_json::to_string(&result).unwrap(),
         )]))
     }
+
+    /// Record whether a search result was relevant
+    #[tool(
+        description = "Record whether a search result was relevant. Judgments boost or penalize the result in future searches and are kept as relevance labels for evaluation."
+    )]
+    async fn feedback(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "ID of the search result")]
+        result_id: i64,
+
+        #[tool(param)]
+        #[schemars(description = "Whether the result was relevant to the query")]
+        relevant: bool,
+
+        #[tool(param)]
+        #[schemars(description = "The query the result was returned for")]
+        query: Option<String>,
+    ) -> Result<CallToolResult, Error> {
+        tracing::info!(result_id, relevant, "Recording search feedback");
+
+        let db = crate::index::Database::open_configured()
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to open index: {}", e), None))?;
+
+        let id =
+            crate::search::feedback::record_feedback(&db, result_id, relevant, query.as_deref())
+                .await
+                .map_err(|e| Error::invalid_request(e.to_string(), None))?;
+
+        let result = json!({
+            "success": true,
+            "feedback_id": id,
+            "result_id": result_id,
+            "relevant": relevant,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string(&result).unwrap(),
+        )]))
+    }
+
+    /// Preload the files relevant to a task within a token budget
+    #[tool(
+        description = "Find the files relevant to a task in the repository index and return their best matching excerpts, grouped by file, within a token budget. Use this at the start of a coding task instead of reading whole directories, then read the returned files for the full code. Requires the repository to be indexed."
+    )]
+    async fn load_relevant_files(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The task to find relevant files for")]
+        task: String,
+
+        #[tool(param)]
+        #[schemars(description = "Most tokens of excerpts to return (default 8000)")]
+        max_tokens: Option<usize>,
+
+        #[tool(param)]
+        #[schemars(description = "Only search this indexed source, e.g. the repository's name")]
+        source: Option<String>,
+    ) -> Result<CallToolResult, Error> {
+        if task.trim().is_empty() {
+            return Err(Error::invalid_request("Task cannot be empty", None));
+        }
+        tracing::info!(task = %task, "Loading relevant files");
+
+        let db = crate::index::Database::open_configured()
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to open index: {}", e), None))?;
+        let client = search_client()?;
+        let options = ContextOptions {
+            max_tokens: max_tokens.unwrap_or(DEFAULT_CONTEXT_TOKENS),
+            source_filter: source,
+            exact_source: self.exact_source,
+            ..Default::default()
+        };
+
+        let context = load_relevant_files(&db, &client, &task, &options)
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to search index: {}", e), None))?;
+
+        let result = json!({
+            "success": true,
+            "files": context.files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(),
+            "tokens": context.tokens,
+            "context": context.to_prompt(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string(&result).unwrap(),
+        )]))
+    }
+
+    /// Search indexed code for the symbols matching a query
+    #[tool(
+        description = "Search code indexed with `hal index-code` and return the matching functions, structs and classes as file:line hits with their source. Use this to find where something is implemented before reading or editing files."
+    )]
+    async fn code_search(
+        &self,
+        #[tool(param)]
+        #[schemars(
+            description = "What the code does or contains, e.g. 'where the config file is loaded'"
+        )]
+        query: String,
+
+        #[tool(param)]
+        #[schemars(description = "Most hits to return (default 10)")]
+        limit: Option<usize>,
+
+        #[tool(param)]
+        #[schemars(description = "Only search this indexed repository, by its directory name")]
+        source: Option<String>,
+    ) -> Result<CallToolResult, Error> {
+        if query.trim().is_empty() {
+            return Err(Error::invalid_request("Query cannot be empty", None));
+        }
+        tracing::info!(query = %query, "Searching code");
+
+        let db = crate::index::Database::open_configured()
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to open index: {}", e), None))?;
+        let client = search_client()?;
+
+        let hits = code_search(
+            &db,
+            &client,
+            &query,
+            limit.unwrap_or(10),
+            source,
+            self.exact_source,
+        )
+        .await
+        .map_err(|e| Error::internal_error(format!("Failed to search code: {}", e), None))?;
+
+        let result = json!({
+            "success": true,
+            "hits": hits.iter().map(|hit| json!({
+                "location": hit.location(),
+                "end_line": hit.end_line,
+                "symbol": hit.symbol,
+                "language": hit.language,
+                "score": hit.score,
+                "code": hit.text,
+            })).collect::<Vec<_>>(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string(&result).unwrap(),
+        )]))
+    }
+}
+
+/// Client searching with the embedding backend of `hal.toml`, like the index was built
+fn search_client() -> Result<
+    crate::model::Client<
+        impl rig::completion::CompletionModel,
+        impl rig::embeddings::EmbeddingModel,
+    >,
+    Error,
+> {
+    crate::model::Client::new_gemini_free_from_env()
+        .with_embedding_config(&crate::config::current().embedding)
+        .map_err(|e| {
+            Error::internal_error(format!("Failed to create the embedding model: {}", e), None)
+        })
 }
@@ -1,12 +1,12 @@
 //! Shell and execution tools for RMCP server
 //!
-//! This module contains tools for shell command execution and code repository
-//! overview functionality using the new RMCP attribute macro pattern.
+//! This module contains tools for shell command execution, and for running a project's
+//! linter and formatter with structured diagnostics, using the new RMCP attribute macro
+//! pattern.
 
 use serde_json::json;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::task;
 
 use rmcp::{
     Error,
@@ -15,13 +15,13 @@ use rmcp::{
     schemars, tool,
 };
 
-use crate::mcp::code;
 use crate::mcp::executor::Executor;
+use crate::mcp::lint_utils::{self, LintReport, Severity, Toolchain};
 use crate::mcp::permissions::PermissionsRef;
+use crate::mcp::workspace::Access;
 use tokio::sync::Mutex;
 
 /// Shell tool handler implementing tools for executing shell commands
-/// and accessing code repository information.
 #[derive(Clone)]
 #[allow(dead_code)] // Fields are used indirectly by RMCP macros
 pub struct ShellTools {
@@ -98,8 +98,22 @@ impl ShellTools {
             }
         }
 
-        // Convert working directory to PathBuf if provided and validate
-        let working_dir = working_directory.as_ref().map(|wd| PathBuf::from(wd));
+        // Resolve the working directory through the session's workspaces
+        let working_dir = match &working_directory {
+            Some(wd) => Some(
+                self.permissions
+                    .lock()
+                    .await
+                    .resolve(Path::new(wd), Access::Read)
+                    .map_err(|e| {
+                        Error::invalid_request(
+                            format!("No read permission for working directory: {}", e),
+                            None,
+                        )
+                    })?,
+            ),
+            None => None,
+        };
 
         // Validate working directory if provided
         if let Some(ref wd) = working_dir {
@@ -121,18 +135,6 @@ impl ShellTools {
                     None,
                 ));
             }
-
-            // Check read permission for working directory
-            let perms = self.permissions.lock().await;
-            if !perms.can_read(wd) {
-                return Err(Error::invalid_request(
-                    format!(
-                        "No read permission for working directory: {}. Use request_permission first",
-                        wd.display()
-                    ),
-                    None,
-                ));
-            }
         }
 
         // Execute the command using the executor
@@ -179,74 +181,139 @@ impl ShellTools {
         }
     }
 
-    /// Get an overview of a code repository
+    /// Run the project's linter and return structured diagnostics
     #[tool(
-        description = "Scans and displays the content of multiple files in a directory, providing a comprehensive overview of code and other text files. Returns a JSON object with an 'overview' field containing the formatted content of each file and a 'files' count.
-        This tool is ideal for:
-
-        Initial exploration of unfamiliar codebases
-        Understanding related files as a group
-        Analyzing patterns across multiple files
-        Getting a complete view of smaller projects
+        description = "Run the project's linter (cargo clippy for Cargo.toml projects, eslint for package.json projects) and return its diagnostics as a list of file, line, column, severity, message and lint code. Use this to find and fix warnings instead of parsing shell output. Requires execute permission for cargo or npx (request_permission with operation='execute') and read permission for the project directory."
+    )]
+    async fn run_linter(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Project directory containing Cargo.toml or package.json")]
+        path: String,
+    ) -> Result<CallToolResult, Error> {
+        let (project, toolchain) = self.lint_project(&path, false).await?;
+        let report = lint_utils::run_linter(&project, toolchain)
+            .await
+            .map_err(|e| Error::internal_error(format!("Failed to run the linter: {}", e), None))?;
+        Ok(lint_result(report))
+    }
 
-        Note: Automatically filters out binary files, large files (>10KB), and common exclusions (like node_modules). Response size is capped at 10,000 tokens. For larger directories, use directory_tree first to understand structure, then show_file for specific files of interest. Can significantly impact context window usage when querying large directories. Requires read permission for the directory."
+    /// Format the project's code, or check which files are unformatted
+    #[tool(
+        description = "Format the project's code with its formatter (cargo fmt for Cargo.toml projects, prettier for package.json projects). With check=true, files are left unchanged and the unformatted ones are returned as diagnostics. Requires execute permission for cargo or npx (request_permission with operation='execute'), and write permission for the project directory, or read permission when checking."
     )]
-    async fn directory_explorer(
+    async fn format_code(
         &self,
         #[tool(param)]
-        #[schemars(description = "Path to the directory")]
+        #[schemars(description = "Project directory containing Cargo.toml or package.json")]
         path: String,
+
+        #[tool(param)]
+        #[schemars(
+            description = "Only report unformatted files instead of rewriting them (default false)"
+        )]
+        check: Option<bool>,
     ) -> Result<CallToolResult, Error> {
-        let path_buf = PathBuf::from(&path);
+        let check = check.unwrap_or(false);
+        let (project, toolchain) = self.lint_project(&path, !check).await?;
+        let report = lint_utils::run_formatter(&project, toolchain, check)
+            .await
+            .map_err(|e| {
+                Error::internal_error(format!("Failed to run the formatter: {}", e), None)
+            })?;
+        Ok(lint_result(report))
+    }
+}
 
-        // Check read permission for the directory
-        let perms = self.permissions.lock().await;
-        if !perms.can_read(&path_buf) {
+impl ShellTools {
+    /// Resolve a project directory for linting, checking permissions and its toolchain
+    async fn lint_project(&self, path: &str, write: bool) -> Result<(PathBuf, Toolchain), Error> {
+        let access = if write { Access::Write } else { Access::Read };
+        let project = self
+            .permissions
+            .lock()
+            .await
+            .resolve(Path::new(path), access)
+            .map_err(|e| Error::invalid_request(e, None))?;
+        if !project.is_dir() {
             return Err(Error::invalid_request(
-                format!("No read permission for directory: {}", path_buf.display()),
+                format!("Project directory does not exist: {}", project.display()),
                 None,
             ));
         }
-        drop(perms);
-
-        // Create YekConfig with tokens mode
-        let mut config = yek::config::YekConfig::default();
-        let ignore = yek::defaults::DEFAULT_IGNORE_PATTERNS
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
-        config.ignore_patterns = ignore;
-        config.input_paths = vec![path.clone()];
-        config.token_mode = true;
-        config.max_size = "10K".to_string();
-        config.tokens = "10000".to_string();
-
-        config
-            .validate()
-            .map_err(|e| Error::invalid_request(format!("Failed to validate config: {e}"), None))?;
-
-        // Use spawn_blocking since overview is synchronous
-        let config_clone = config.clone();
-        let result = task::spawn_blocking(move || code::overview(&config_clone))
-            .await
-            .map_err(|e| Error::invalid_request(format!("Failed to run overview: {}", e), None))?;
-
-        match result {
-            Ok((overview, files)) => {
-                let result = json!({
-                    "overview": overview,
-                    "files": files.len(),
-                    "path": path
-                });
 
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string(&result).unwrap(),
-                )]))
-            }
-            Err(e) => Err(Error::invalid_request(
-                format!("Failed to generate overview: {}", e),
+        let toolchain = Toolchain::detect(&project).ok_or_else(|| {
+            Error::invalid_request(
+                format!(
+                    "No Cargo.toml or package.json in {}; cannot pick a linter",
+                    project.display()
+                ),
+                None,
+            )
+        })?;
+
+        // The toolchain runs arbitrary build scripts and plugins, like any other command
+        let program = toolchain.program();
+        if !self.permissions.lock().await.can_execute_command(program) {
+            return Err(Error::invalid_request(
+                format!(
+                    "No execute permission for command '{}'. Use request_permission with operation='execute' and path='{}' first",
+                    program, program
+                ),
                 None,
-            )),
+            ));
         }
+        Ok((project, toolchain))
+    }
+}
+
+/// The tool result of a lint or format report
+fn lint_result(report: LintReport) -> CallToolResult {
+    let output = json!({
+        "success": report.exit_code == 0,
+        "toolchain": report.toolchain,
+        "command": report.command,
+        "exit_code": report.exit_code,
+        "errors": report.count(Severity::Error),
+        "warnings": report.count(Severity::Warning),
+        "diagnostics": report.diagnostics,
+        "output": report.output,
+    });
+    CallToolResult::success(vec![Content::text(serde_json::to_string(&output).unwrap())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::permissions::create_permissions;
+    use crate::mcp::shell_utils::ShellExecutor;
+    use crate::mcp::workspace::Workspace;
+
+    #[tokio::test]
+    async fn test_lint_requires_execute_permission() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join("Cargo.toml"), "[package]\n").unwrap();
+        let permissions = create_permissions();
+        permissions
+            .lock()
+            .await
+            .grant(Workspace::new(project.path()).unwrap(), Access::Write);
+        let tools = ShellTools::new(
+            Arc::new(ShellExecutor::new(permissions.clone())),
+            permissions.clone(),
+            Arc::new(Mutex::new(None)),
+        );
+        let path = project.path().to_string_lossy();
+
+        let denied = tools.lint_project(&path, false).await.unwrap_err();
+        assert!(
+            denied
+                .message
+                .contains("No execute permission for command 'cargo'")
+        );
+
+        permissions.lock().await.allow_command("cargo".to_string());
+        let (_, toolchain) = tools.lint_project(&path, true).await.unwrap();
+        assert_eq!(toolchain, Toolchain::Cargo);
     }
 }
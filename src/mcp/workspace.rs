@@ -0,0 +1,295 @@
+//! Scoped workspaces for the MCP server
+//!
+//! A `Workspace` is a directory the file tools operate in. Instead of checking raw paths
+//! against allowed directories, every path a tool receives is resolved through a workspace:
+//!
+//! - Relative paths are resolved against the workspace root
+//! - `..` components are normalized away before anything touches the filesystem, and the
+//!   result is checked again after resolving symlinks, so no path escapes the root
+//! - Ignore rules hide files such as `.git` or `.env` from the tools
+//! - Read-only subpaths can be read but not changed
+//!
+//! Ignore rules and read-only subpaths are configured in `.hal/workspace.json` in the
+//! workspace root. Access is granted per workspace, so a session asks once for
+//! "read access to workspace X" rather than for every directory it touches.
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::permissions::basic_path_validation;
+
+/// Paths hidden from the tools in every workspace
+const DEFAULT_IGNORE: &[&str] = &[".git", ".env"];
+
+/// Access granted to a workspace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// Read files and list directories
+    Read,
+    /// Also create, edit and format files, except in read-only subpaths
+    Write,
+}
+
+/// Rules of a workspace, read from `.hal/workspace.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    /// Ignored paths: file or directory names such as `target`, extensions such as `*.pem`,
+    /// or paths relative to the root such as `config/secrets`
+    pub ignore: Vec<String>,
+    /// Paths relative to the root that may be read but not changed
+    pub read_only: Vec<PathBuf>,
+}
+
+/// A directory the file tools operate in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    /// Canonical root directory
+    root: PathBuf,
+    /// Ignore patterns
+    ignore: Vec<String>,
+    /// Read-only paths relative to the root
+    read_only: Vec<PathBuf>,
+}
+
+impl Workspace {
+    /// Create a workspace with the default ignore rules
+    ///
+    /// The root is canonicalized if it exists; system directories are rejected.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, String> {
+        let root = root.as_ref();
+        basic_path_validation(root)?;
+        let root = if root.is_absolute() {
+            real_path(&normalize(root))
+        } else {
+            let cwd = std::env::current_dir()
+                .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+            real_path(&normalize(&cwd.join(root)))
+        };
+        Ok(Self {
+            root,
+            ignore: DEFAULT_IGNORE.iter().map(|p| p.to_string()).collect(),
+            read_only: Vec::new(),
+        })
+    }
+
+    /// Create a workspace with the rules of its `.hal/workspace.json`, if any
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, String> {
+        let workspace = Self::new(root)?;
+        let path = Self::config_path(&workspace.root);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let config: WorkspaceConfig = serde_json::from_str(&contents)
+                    .map_err(|e| format!("Invalid workspace config {}: {}", path.display(), e))?;
+                Ok(workspace.with_config(config))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(workspace),
+            Err(e) => Err(format!(
+                "Failed to read workspace config {}: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    /// Location of the rules of a workspace
+    pub fn config_path(root: &Path) -> PathBuf {
+        root.join(".hal").join("workspace.json")
+    }
+
+    /// Add ignore rules and read-only paths
+    pub fn with_config(mut self, config: WorkspaceConfig) -> Self {
+        self.ignore.extend(config.ignore);
+        self.read_only.extend(config.read_only);
+        self
+    }
+
+    /// Canonical root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Ignore patterns
+    pub fn ignore(&self) -> &[String] {
+        &self.ignore
+    }
+
+    /// Read-only paths relative to the root
+    pub fn read_only(&self) -> &[PathBuf] {
+        &self.read_only
+    }
+
+    /// Resolve a path to read inside the workspace
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path relative to the root, or an absolute path inside it
+    ///
+    /// # Returns
+    ///
+    /// The absolute path, or an error if it is outside the workspace or ignored
+    pub fn resolve(&self, path: &Path) -> Result<PathBuf, String> {
+        let joined = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+        // Normalizing first keeps `..` from climbing out; resolving symlinks afterwards
+        // catches links pointing outside
+        let resolved = real_path(&normalize(&joined));
+        let Ok(relative) = resolved.strip_prefix(&self.root) else {
+            return Err(format!(
+                "Path {} is outside the workspace {}",
+                path.display(),
+                self.root.display()
+            ));
+        };
+        if self.is_ignored(relative) {
+            return Err(format!(
+                "Path {} is ignored in the workspace {}",
+                path.display(),
+                self.root.display()
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve a path to change inside the workspace, rejecting read-only paths
+    pub fn resolve_writable(&self, path: &Path) -> Result<PathBuf, String> {
+        let resolved = self.resolve(path)?;
+        let relative = resolved.strip_prefix(&self.root).unwrap_or(&resolved);
+        if self
+            .read_only
+            .iter()
+            .any(|read_only| relative.starts_with(read_only))
+        {
+            return Err(format!(
+                "Path {} is read-only in the workspace {}",
+                path.display(),
+                self.root.display()
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Whether a path relative to the root matches an ignore rule
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        self.ignore.iter().any(|pattern| {
+            let pattern = pattern.trim_end_matches('/');
+            if pattern.contains('/') {
+                relative.starts_with(pattern)
+            } else if let Some(extension) = pattern.strip_prefix("*.") {
+                relative.extension().is_some_and(|ext| ext == extension)
+            } else {
+                relative
+                    .components()
+                    .any(|component| component.as_os_str() == pattern)
+            }
+        })
+    }
+}
+
+/// Normalize `.` and `..` components without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Resolve symlinks of the longest existing ancestor of a path, which may not exist yet
+fn real_path(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest
+                .iter()
+                .rev()
+                .fold(canonical, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resolve_stays_inside_workspace() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        std::fs::write(temp_dir.path().join("src").join("main.rs"), "").unwrap();
+        let workspace = Workspace::new(temp_dir.path()).unwrap();
+        let root = workspace.root().to_path_buf();
+
+        assert_eq!(
+            workspace.resolve(Path::new("src/main.rs")).unwrap(),
+            root.join("src").join("main.rs")
+        );
+        assert_eq!(
+            workspace.resolve(Path::new("src/../new.rs")).unwrap(),
+            root.join("new.rs")
+        );
+        assert!(workspace.resolve(Path::new("../outside.rs")).is_err());
+        assert!(workspace.resolve(Path::new("src/../../outside")).is_err());
+        assert!(workspace.resolve(Path::new("/tmp/elsewhere")).is_err());
+        assert!(workspace.resolve(&root.join("src")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_symlinks_out() {
+        let temp_dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+        let workspace = Workspace::new(temp_dir.path()).unwrap();
+
+        assert!(workspace.resolve(Path::new("link/secret")).is_err());
+    }
+
+    #[test]
+    fn test_ignore_and_read_only_rules() {
+        let temp_dir = tempdir().unwrap();
+        let config = WorkspaceConfig {
+            ignore: vec!["target".to_string(), "*.pem".to_string()],
+            read_only: vec![PathBuf::from("vendor"), PathBuf::from("Cargo.lock")],
+        };
+        std::fs::create_dir(temp_dir.path().join(".hal")).unwrap();
+        std::fs::write(
+            Workspace::config_path(temp_dir.path()),
+            serde_json::to_string(&config).unwrap(),
+        )
+        .unwrap();
+        let workspace = Workspace::open(temp_dir.path()).unwrap();
+
+        assert!(workspace.resolve(Path::new(".git/config")).is_err());
+        assert!(workspace.resolve(Path::new("target/debug/hal")).is_err());
+        assert!(workspace.resolve(Path::new("certs/key.pem")).is_err());
+        assert!(workspace.resolve(Path::new("vendor/lib.rs")).is_ok());
+        assert!(
+            workspace
+                .resolve_writable(Path::new("vendor/lib.rs"))
+                .is_err()
+        );
+        assert!(workspace.resolve_writable(Path::new("Cargo.lock")).is_err());
+        assert!(workspace.resolve_writable(Path::new("src/lib.rs")).is_ok());
+    }
+}
@@ -9,10 +9,32 @@
 //! - `RateLimitedCompletionModel`: A wrapper that adds rate limiting to any completion model
 //! - `RateLimitedEmbeddingModel`: A wrapper that adds rate limiting to any embedding model
 //! - `EmbeddingConversion`: Utilities for converting between embedding formats
+//! - `GenerationOptions`: Sampling parameters for completion requests
+//! - `ModelConcurrency`: Bounds on the requests in flight to each model, applied on top of
+//!   the rate limits
+//! - `FallbackCompletionModel`: A chain of completion models falling back on rate-limit
+//!   and unavailable errors, configured by `ModelChain`
+//! - `VertexClient`: Gemini completion and embedding models hosted on Vertex AI,
+//!   authenticated with Application Default Credentials
+//! - `AzureClient`: OpenAI completion and embedding models deployed to Azure OpenAI
+//! - `CachedGeminiModel`: A Gemini model caching large, repeated request prefixes with the
+//!   provider's context caching
+//! - `CachedCompletionModel`: A completion model answering repeated requests from an
+//!   on-disk cache
+//! - `Client::with_vcr`: Records responses to a cassette, or replays them in tests
+//! - `EmbeddingBackend`: The embedding model chosen by the `[embedding]` table of
+//!   `hal.toml`, the provider's, a Hugging Face or a local one
+//! - `HuggingFaceEmbeddingModel`: An embedding model hosted by the Hugging Face Inference
+//!   API
+//! - `LocalEmbeddingModel`: An ONNX embedding model run in-process (available with the
+//!   `local-embeddings` feature)
+//! - `Client::new_mock`: An offline client with canned completions and deterministic
+//!   embeddings (available in tests and with the `mock` feature)
 //!
 //! ## Features
 //!
 //! - Configurable rate limiting with different quotas (standard and free tiers)
+//! - Per-model concurrency from the `[concurrency]` table of `hal.toml`
 //! - Environment variable configuration for API keys
 //! - Instrumentation with tracing spans for monitoring
 //! - Type-safe model integration with the `rig` framework
@@ -22,15 +44,39 @@ use std::num::NonZeroU32;
 
 use governor::{Quota, RateLimiter};
 pub use ratelimited_completion::RateLimitedCompletionModel;
-use ratelimited_embedding::RateLimitedEmbeddingModel;
+pub use ratelimited_embedding::RateLimitedEmbeddingModel;
 use rig::{completion::CompletionModel, embeddings::EmbeddingModel, providers::gemini};
+use serde::{Deserialize, Serialize};
 
+use crate::config::{ConfigError, Provider, RateLimitTier};
+pub use generation::GenerationOptions;
+
+pub mod azure;
+pub mod completion_cache;
+pub mod context_cache;
 pub mod embedding;
+pub mod embedding_backend;
+pub mod fallback;
+pub mod generation;
+pub mod huggingface;
+#[cfg(feature = "local-embeddings")]
+pub mod local_embedding;
 pub mod mock_model;
 pub mod ratelimited_completion;
 pub mod ratelimited_embedding;
+pub mod vcr;
+pub mod vertex;
 
+pub use completion_cache::{CachedCompletionModel, CompletionCache, CompletionCacheConfig};
+pub use context_cache::CachedGeminiModel;
 pub use embedding::EmbeddingConversion;
+pub use embedding_backend::{EmbeddingBackend, EmbeddingConfig, EmbeddingProvider};
+pub use fallback::{
+    ChainModel, FallbackCompletionModel, ModelChain, ProviderCompletionModel, ProviderResponse,
+};
+pub use huggingface::HuggingFaceEmbeddingModel;
+#[cfg(feature = "local-embeddings")]
+pub use local_embedding::LocalEmbeddingModel;
 
 #[derive(Debug, Clone)]
 pub struct Client<C, E>
@@ -42,6 +88,23 @@ where
     embedding_model: E,
 }
 
+/// Most requests in flight to each model; unset limits leave a model bounded by its rate
+/// limit only
+///
+/// The bounds are shared by every clone of a client, so the chunks of all pages being
+/// indexed draw from the same permits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelConcurrency {
+    /// Most completion requests in flight per model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion: Option<usize>,
+
+    /// Most embedding requests in flight
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<usize>,
+}
+
 pub struct RateLimitResponse<T> {
     #[allow(dead_code)]
     response: T,
@@ -53,25 +116,34 @@ impl
         RateLimitedEmbeddingModel<gemini::embedding::EmbeddingModel>,
     >
 {
+    /// Create a client from `GEMINI_API_KEY`, falling back to `hal.toml`
+    ///
+    /// Uses free tier quotas when the configured rate-limit tier is `free`.
     pub fn new_gemini_from_env() -> Self {
-        let gemini_api_key = std::env::var("GEMINI_API_KEY")
+        let gemini_api_key = crate::config::api_key("GEMINI_API_KEY")
             .expect("GEMINI_API_KEY environment variable must be set");
         let gemini_client = gemini::Client::new(&gemini_api_key);
-        Self::new_gemini(gemini_client)
+        let config = crate::config::current();
+        let client = match config.rate_limit_tier {
+            RateLimitTier::Free => Self::new_gemini_free(gemini_client),
+            RateLimitTier::Paid => Self::new_gemini(gemini_client),
+        };
+        client.with_concurrency(&config.concurrency)
     }
 
     pub fn new_gemini_free_from_env() -> Self {
-        let gemini_api_key = std::env::var("GEMINI_FREE_API_KEY")
+        let gemini_api_key = crate::config::api_key("GEMINI_FREE_API_KEY")
             .expect("GEMINI_FREE_API_KEY environment variable must be set");
         let gemini_client = gemini::Client::new(&gemini_api_key);
-        Self::new_gemini_free(gemini_client)
+        Self::new_gemini_free(gemini_client).with_concurrency(&crate::config::current().concurrency)
     }
 
     pub fn new_gemini_free_model_from_env(completion_model: &str) -> Self {
-        let gemini_api_key = std::env::var("GEMINI_FREE_API_KEY")
+        let gemini_api_key = crate::config::api_key("GEMINI_FREE_API_KEY")
             .expect("GEMINI_FREE_API_KEY environment variable must be set");
         let gemini_client = gemini::Client::new(&gemini_api_key);
         Self::new_gemini_free_model(gemini_client, completion_model)
+            .with_concurrency(&crate::config::current().concurrency)
     }
 
     pub fn new_gemini(gemini_client: gemini::Client) -> Self {
@@ -138,6 +210,227 @@ impl
     }
 }
 
+impl
+    Client<
+        RateLimitedCompletionModel<CachedGeminiModel>,
+        RateLimitedEmbeddingModel<gemini::embedding::EmbeddingModel>,
+    >
+{
+    /// Create a client with free tier quotas whose completions cache large prompt prefixes,
+    /// see [`CachedGeminiModel`]
+    pub fn new_gemini_cached_model(
+        completion_model: CachedGeminiModel,
+        gemini_client: gemini::Client,
+    ) -> Self {
+        let completion_limiter = RateLimiter::direct(Quota::per_minute(
+            NonZeroU32::new(30).expect("must create rate limit"),
+        ));
+        let embedding_limiter = RateLimiter::direct(Quota::per_minute(
+            NonZeroU32::new(1000).expect("must create rate limit"),
+        ));
+        let completion_model =
+            RateLimitedCompletionModel::new(completion_model, completion_limiter);
+        let embedding_model = RateLimitedEmbeddingModel::new(
+            gemini_client.embedding_model(gemini::embedding::EMBEDDING_004),
+            embedding_limiter,
+        );
+        Self {
+            completion_model,
+            embedding_model,
+        }
+    }
+}
+
+/// The Gemini API key of a chain client, which Vertex AI and Azure OpenAI users may leave
+/// unset
+fn chain_api_key(var: &str) -> String {
+    match crate::config::api_key(var) {
+        Some(key) => key,
+        None if crate::config::current().provider != Provider::Gemini => String::new(),
+        None => panic!("{} environment variable must be set", var),
+    }
+}
+
+/// An API key a chain model names
+fn required_api_key(var: &str) -> String {
+    crate::config::api_key(var)
+        .unwrap_or_else(|| panic!("{} environment variable must be set", var))
+}
+
+impl
+    Client<
+        FallbackCompletionModel<RateLimitedCompletionModel<ProviderCompletionModel>>,
+        RateLimitedEmbeddingModel<gemini::embedding::EmbeddingModel>,
+    >
+{
+    /// Create a client from `GEMINI_API_KEY` whose completions fall back along the
+    /// `model_chain` of `hal.toml`
+    ///
+    /// Uses free tier quotas when the configured rate-limit tier is `free`. See
+    /// [`Client::new_gemini_chain`] for the errors.
+    pub fn new_gemini_chain_from_env() -> Result<Self, ConfigError> {
+        let gemini_client = gemini::Client::new(&chain_api_key("GEMINI_API_KEY"));
+        let config = crate::config::current();
+        Self::new_gemini_chain(
+            gemini_client,
+            &config.model_chain,
+            config.rate_limit_tier,
+            &config.concurrency,
+        )
+    }
+
+    /// Create a client from `GEMINI_FREE_API_KEY` with free tier quotas whose completions
+    /// fall back along the `model_chain` of `hal.toml`
+    ///
+    /// See [`Client::new_gemini_chain`] for the errors.
+    pub fn new_gemini_free_chain_from_env() -> Result<Self, ConfigError> {
+        let gemini_client = gemini::Client::new(&chain_api_key("GEMINI_FREE_API_KEY"));
+        let config = crate::config::current();
+        Self::new_gemini_chain(
+            gemini_client,
+            &config.model_chain,
+            RateLimitTier::Free,
+            &config.concurrency,
+        )
+    }
+
+    /// Create a client whose completions fall back along a chain of models
+    ///
+    /// Each model has its own rate limiter and concurrency bound, its `concurrency` or else
+    /// the completion bound of `concurrency`. Models naming an `api_key` read it like the
+    /// other keys, from the environment, the keyring or `hal.toml`; the others and the
+    /// embedding model use `gemini_client`. Vertex AI models use the `[vertex]` table of
+    /// `hal.toml` and Application Default Credentials, and Azure OpenAI models name a
+    /// deployment of the resource of the `[azure]` table, with `AZURE_OPENAI_API_KEY` unless
+    /// they name another key. An empty chain uses `gemini-2.0-flash` alone, or the `[azure]`
+    /// deployment, from the configured provider.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ConfigError::Provider`] if the credentials or project of a Vertex AI
+    /// model can't be found, or the endpoint of an Azure OpenAI model isn't set.
+    pub fn new_gemini_chain(
+        gemini_client: gemini::Client,
+        chain: &ModelChain,
+        tier: RateLimitTier,
+        concurrency: &ModelConcurrency,
+    ) -> Result<Self, ConfigError> {
+        let per_minute = match tier {
+            RateLimitTier::Free => 30,
+            RateLimitTier::Paid => 2000,
+        };
+        let config = crate::config::current();
+        let default_chain = [ChainModel {
+            provider: config.provider,
+            model: match config.provider {
+                Provider::Azure => config.azure.deployment().to_string(),
+                Provider::Gemini | Provider::Vertex => "gemini-2.0-flash".to_string(),
+            },
+            api_key: None,
+            concurrency: None,
+        }];
+        let models = if chain.is_empty() {
+            &default_chain[..]
+        } else {
+            &chain.models[..]
+        };
+
+        let mut vertex: Option<vertex::VertexClient> = None;
+        let mut completion_model: Option<FallbackCompletionModel<_>> = None;
+        for entry in models {
+            let model = match entry.provider {
+                Provider::Gemini => {
+                    let client = match &entry.api_key {
+                        Some(var) => gemini::Client::new(&required_api_key(var)),
+                        None => gemini_client.clone(),
+                    };
+                    ProviderCompletionModel::Gemini(client.completion_model(&entry.model))
+                }
+                Provider::Vertex => {
+                    let vertex = match &mut vertex {
+                        Some(vertex) => vertex,
+                        None => vertex.insert(
+                            vertex::VertexClient::from_config(&config.vertex)
+                                .map_err(|e| ConfigError::Provider(e.to_string()))?,
+                        ),
+                    };
+                    ProviderCompletionModel::Vertex(vertex.completion_model(&entry.model))
+                }
+                Provider::Azure => {
+                    let var = entry.api_key.as_deref().unwrap_or(azure::API_KEY_VAR);
+                    let client =
+                        azure::AzureClient::from_config(&config.azure, &required_api_key(var))
+                            .map_err(ConfigError::Provider)?;
+                    ProviderCompletionModel::Azure(client.completion_model(&entry.model))
+                }
+            };
+            let limiter = RateLimiter::direct(Quota::per_minute(
+                NonZeroU32::new(per_minute).expect("must create rate limit"),
+            ));
+            let mut model = RateLimitedCompletionModel::new(model, limiter);
+            if let Some(max) = entry.concurrency.or(concurrency.completion) {
+                model = model.with_concurrency(max);
+            }
+            let name = match &entry.api_key {
+                Some(var) => format!("{} ({})", entry.model, var),
+                None => entry.model.clone(),
+            };
+            completion_model = Some(match completion_model {
+                Some(chain) => chain.with_fallback(name, model),
+                None => FallbackCompletionModel::new(name, model),
+            });
+        }
+
+        let embedding_limiter = RateLimiter::direct(Quota::per_minute(
+            NonZeroU32::new(1000).expect("must create rate limit"),
+        ));
+        let mut embedding_model = RateLimitedEmbeddingModel::new(
+            gemini_client.embedding_model(gemini::embedding::EMBEDDING_004),
+            embedding_limiter,
+        );
+        if let Some(max) = concurrency.embedding {
+            embedding_model = embedding_model.with_concurrency(max);
+        }
+        Ok(Self {
+            completion_model: completion_model.expect("a chain has at least one model"),
+            embedding_model,
+        })
+    }
+}
+
+impl<M, E> Client<RateLimitedCompletionModel<M>, RateLimitedEmbeddingModel<E>>
+where
+    M: CompletionModel,
+    E: EmbeddingModel,
+{
+    /// Bound the requests in flight to the completion and embedding models
+    pub fn with_concurrency(mut self, concurrency: &ModelConcurrency) -> Self {
+        if let Some(max) = concurrency.completion {
+            self.completion_model = self.completion_model.with_concurrency(max);
+        }
+        if let Some(max) = concurrency.embedding {
+            self.embedding_model = self.embedding_model.with_concurrency(max);
+        }
+        self
+    }
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl Client<mock_model::MockCompletionModel, mock_model::MockEmbeddingModel> {
+    /// Create a client that makes no API calls
+    ///
+    /// Completions always return `mock_model::MOCK_RESPONSE` and embeddings are deterministic,
+    /// so the processing, search, and coder pipelines can be exercised offline.
+    pub fn new_mock() -> Self {
+        Self {
+            completion_model: mock_model::MockCompletionModel::with_text_response(
+                mock_model::MOCK_RESPONSE,
+            ),
+            embedding_model: mock_model::MockEmbeddingModel::new(),
+        }
+    }
+}
+
 impl<C, E> Client<C, E>
 where
     C: CompletionModel,
@@ -150,4 +443,45 @@ where
     pub fn embedding(&self) -> &E {
         &self.embedding_model
     }
+
+    /// Embed with the backend of an embedding configuration, keeping this client's
+    /// embedding model when the provider's is configured
+    ///
+    /// Completions are unaffected, so answers still come from the provider when embeddings
+    /// are computed locally. See [`EmbeddingBackend::from_config`] for the errors.
+    pub fn with_embedding_config(
+        self,
+        config: &EmbeddingConfig,
+    ) -> Result<Client<C, EmbeddingBackend<E>>, rig::embeddings::EmbeddingError> {
+        Ok(Client {
+            completion_model: self.completion_model,
+            embedding_model: EmbeddingBackend::from_config(config, self.embedding_model)?,
+        })
+    }
+
+    /// Answer completions from a cache, keyed by `model` and the request, when `cache` is
+    /// set; embeddings are unaffected
+    pub fn with_completion_cache(
+        self,
+        cache: Option<CompletionCache>,
+        model: &str,
+    ) -> Client<CachedCompletionModel<C>, E> {
+        Client {
+            completion_model: CachedCompletionModel::new(self.completion_model, cache, model),
+            embedding_model: self.embedding_model,
+        }
+    }
+
+    /// Record completions and embeddings to a cassette, or replay them from it without
+    /// calling this client's models
+    pub fn with_vcr(
+        self,
+        cassette: vcr::Cassette,
+    ) -> Client<vcr::VcrCompletionModel<C>, vcr::VcrEmbeddingModel<E>> {
+        let cassette = std::sync::Arc::new(cassette);
+        Client {
+            completion_model: vcr::VcrCompletionModel::new(self.completion_model, cassette.clone()),
+            embedding_model: vcr::VcrEmbeddingModel::new(self.embedding_model, cassette),
+        }
+    }
 }
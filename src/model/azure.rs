@@ -0,0 +1,444 @@
+//! # Azure OpenAI Module
+//!
+//! This module calls OpenAI models deployed to an Azure OpenAI resource, so organizations
+//! that can only use Azure-hosted models can index and search with HAL.
+//!
+//! Azure routes requests by deployment rather than model: each model of a resource is
+//! deployed under a name chosen by its owner, and that name is what requests address.
+//!
+//! ## Key Components
+//!
+//! - `AzureConfig`: The `[azure]` table of `hal.toml`
+//! - `AzureClient`: Sends requests to the deployments of a resource
+//! - `AzureCompletionModel`: A chat completion deployment
+//! - `AzureEmbeddingModel`: An embedding deployment
+//!
+//! ## Example
+//!
+//! ```toml
+//! provider = "azure"
+//!
+//! [azure]
+//! resource = "docs-openai"
+//! deployment = "gpt-4o-mini"
+//! api_version = "2024-10-21"
+//!
+//! [embedding]
+//! provider = "azure"
+//! model = "text-embedding-3-small"
+//! ```
+//!
+//! The API key is read from `AZURE_OPENAI_API_KEY`, the keyring or `hal.toml`, and the
+//! endpoint falls back to `AZURE_OPENAI_ENDPOINT` when neither `resource` nor `endpoint`
+//! is set.
+
+use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest};
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use rig::providers::openai;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::debug;
+
+/// Environment variable holding the API key of the resource
+pub const API_KEY_VAR: &str = "AZURE_OPENAI_API_KEY";
+
+/// Environment variable holding the endpoint of the resource
+pub const ENDPOINT_VAR: &str = "AZURE_OPENAI_ENDPOINT";
+
+/// REST API version used when none is configured
+pub const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Deployment used when no model chain is configured
+pub const DEFAULT_DEPLOYMENT: &str = "gpt-4o-mini";
+
+/// Embedding deployment used when the `[embedding]` table names none
+pub const DEFAULT_EMBEDDING_DEPLOYMENT: &str = "text-embedding-3-small";
+
+/// Azure OpenAI resource, API version and default deployment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AzureConfig {
+    /// Name of the resource, served at `https://{resource}.openai.azure.com`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+
+    /// Endpoint URL instead of the resource's own, e.g. a custom domain or a gateway
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    /// REST API version, `2024-10-21` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+
+    /// Chat deployment used when no model chain is configured, `gpt-4o-mini` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+}
+
+impl AzureConfig {
+    /// Endpoint of the resource, from `endpoint`, `resource` or `AZURE_OPENAI_ENDPOINT`
+    pub fn endpoint_url(&self) -> Option<String> {
+        self.endpoint
+            .clone()
+            .or_else(|| {
+                self.resource
+                    .as_ref()
+                    .map(|resource| format!("https://{}.openai.azure.com", resource))
+            })
+            .or_else(|| {
+                std::env::var(ENDPOINT_VAR)
+                    .ok()
+                    .filter(|url| !url.is_empty())
+            })
+    }
+
+    /// REST API version
+    pub fn api_version(&self) -> &str {
+        self.api_version.as_deref().unwrap_or(DEFAULT_API_VERSION)
+    }
+
+    /// Chat deployment used when no model chain is configured
+    pub fn deployment(&self) -> &str {
+        self.deployment.as_deref().unwrap_or(DEFAULT_DEPLOYMENT)
+    }
+}
+
+/// Error for a failed request, keeping the status and body of the response
+async fn response_error(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    format!("Azure OpenAI returned {}: {}", status, body.trim())
+}
+
+/// Client of the deployments of an Azure OpenAI resource
+#[derive(Debug, Clone)]
+pub struct AzureClient {
+    endpoint: String,
+    api_version: String,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl AzureClient {
+    /// Create a client for a resource
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - Endpoint URL, e.g. `https://docs-openai.openai.azure.com`
+    /// * `api_version` - REST API version, e.g. `2024-10-21`
+    /// * `api_key` - API key of the resource
+    pub fn new(endpoint: &str, api_version: &str, api_key: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_version: api_version.to_string(),
+            api_key: api_key.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a client from a configuration and an API key
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoint is configured.
+    pub fn from_config(config: &AzureConfig, api_key: &str) -> Result<Self, String> {
+        let endpoint = config.endpoint_url().ok_or_else(|| {
+            format!(
+                "set [azure] resource or endpoint, or {}, to use Azure OpenAI",
+                ENDPOINT_VAR
+            )
+        })?;
+        Ok(Self::new(&endpoint, config.api_version(), api_key))
+    }
+
+    /// A chat completion deployment
+    pub fn completion_model(&self, deployment: &str) -> AzureCompletionModel {
+        AzureCompletionModel {
+            client: self.clone(),
+            deployment: deployment.to_string(),
+        }
+    }
+
+    /// An embedding deployment producing `ndims` dimensions
+    pub fn embedding_model(&self, deployment: &str, ndims: usize) -> AzureEmbeddingModel {
+        AzureEmbeddingModel {
+            client: self.clone(),
+            deployment: deployment.to_string(),
+            ndims,
+        }
+    }
+
+    /// URL of an operation of a deployment
+    fn deployment_url(&self, deployment: &str, operation: &str) -> String {
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.endpoint, deployment, operation, self.api_version
+        )
+    }
+
+    /// POST a JSON body with the API key
+    async fn post(&self, url: &str, body: &Value) -> Result<reqwest::Response, String> {
+        debug!("POST {}", url);
+        self.client
+            .post(url)
+            .header("api-key", &self.api_key)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A chat completion deployment of an Azure OpenAI resource
+#[derive(Debug, Clone)]
+pub struct AzureCompletionModel {
+    client: AzureClient,
+    deployment: String,
+}
+
+impl AzureCompletionModel {
+    /// Name of the deployment
+    pub fn deployment(&self) -> &str {
+        &self.deployment
+    }
+}
+
+impl CompletionModel for AzureCompletionModel {
+    type Response = openai::CompletionResponse;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let body = request_body(request)?;
+        let url = self
+            .client
+            .deployment_url(&self.deployment, "chat/completions");
+        let response = self
+            .client
+            .post(&url, &body)
+            .await
+            .map_err(CompletionError::ProviderError)?;
+        if !response.status().is_success() {
+            return Err(CompletionError::ProviderError(
+                response_error(response).await,
+            ));
+        }
+        let response = response
+            .json::<openai::CompletionResponse>()
+            .await
+            .map_err(|e| CompletionError::ResponseError(e.to_string()))?;
+        completion::CompletionResponse::try_from(response)
+    }
+}
+
+/// Body of a chat completions request; the deployment, not the body, names the model
+fn request_body(request: CompletionRequest) -> Result<Value, CompletionError> {
+    let prompt = request.prompt_with_context();
+    let mut messages: Vec<openai::Message> = request
+        .preamble
+        .iter()
+        .map(|preamble| openai::Message::system(preamble))
+        .collect();
+    for message in request
+        .chat_history
+        .into_iter()
+        .chain(std::iter::once(prompt))
+    {
+        let converted: Vec<openai::Message> = message.try_into()?;
+        messages.extend(converted);
+    }
+
+    let mut body = Map::new();
+    body.insert("messages".to_string(), serde_json::to_value(messages)?);
+    if let Some(temperature) = request.temperature {
+        body.insert("temperature".to_string(), temperature.into());
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        body.insert("max_tokens".to_string(), max_tokens.into());
+    }
+    if !request.tools.is_empty() {
+        let tools: Vec<openai::ToolDefinition> = request
+            .tools
+            .into_iter()
+            .map(openai::ToolDefinition::from)
+            .collect();
+        body.insert("tools".to_string(), serde_json::to_value(tools)?);
+        body.insert("tool_choice".to_string(), "auto".into());
+    }
+    if let Some(Value::Object(params)) = request.additional_params {
+        body.extend(params);
+    }
+    Ok(Value::Object(body))
+}
+
+/// An embedding deployment of an Azure OpenAI resource
+#[derive(Debug, Clone)]
+pub struct AzureEmbeddingModel {
+    client: AzureClient,
+    deployment: String,
+    ndims: usize,
+}
+
+/// Response of an embeddings request
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f64>,
+}
+
+impl EmbeddingModel for AzureEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts: Vec<String> = texts.into_iter().collect();
+        // `text-embedding-3` models shorten their vectors to the index's dimensions
+        let body = serde_json::json!({
+            "input": texts,
+            "dimensions": self.ndims,
+        });
+        let url = self.client.deployment_url(&self.deployment, "embeddings");
+        let response = self
+            .client
+            .post(&url, &body)
+            .await
+            .map_err(EmbeddingError::ProviderError)?;
+        if !response.status().is_success() {
+            return Err(EmbeddingError::ProviderError(
+                response_error(response).await,
+            ));
+        }
+        let mut response = response
+            .json::<EmbeddingsResponse>()
+            .await
+            .map_err(|e| EmbeddingError::ResponseError(e.to_string()))?;
+        if response.data.len() != texts.len() {
+            return Err(EmbeddingError::ResponseError(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                response.data.len()
+            )));
+        }
+        response.data.sort_by_key(|data| data.index);
+        Ok(texts
+            .into_iter()
+            .zip(response.data)
+            .map(|(document, data)| Embedding {
+                document,
+                vec: data.embedding,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::message::Message;
+
+    #[tokio::test]
+    async fn test_azure_deployments() {
+        let mut server = mockito::Server::new_async().await;
+        let completion = server
+            .mock("POST", "/openai/deployments/chat-prod/chat/completions")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "api-version".to_string(),
+                "2024-10-21".to_string(),
+            ))
+            .match_header("api-key", "key")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "messages": [
+                    { "role": "system", "content": [{ "text": "Be brief." }] },
+                    { "role": "user" },
+                ],
+                "temperature": 0.2,
+            })))
+            .with_body(
+                r#"{"id": "1", "object": "chat.completion", "created": 0, "model": "gpt-4o-mini",
+                    "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi"},
+                    "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/openai/deployments/embed/embeddings")
+            .match_query(mockito::Matcher::Any)
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "dimensions": 2 }),
+            ))
+            .with_body(
+                r#"{"data": [{"index": 1, "embedding": [0.0, 1.0]},
+                             {"index": 0, "embedding": [1.0, 0.0]}]}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/openai/deployments/busy/chat/completions")
+            .match_query(mockito::Matcher::Any)
+            .with_status(429)
+            .with_body(r#"{"error": {"code": "429", "message": "Rate limit exceeded"}}"#)
+            .create_async()
+            .await;
+
+        let config = AzureConfig {
+            endpoint: Some(format!("{}/", server.url())),
+            ..AzureConfig::default()
+        };
+        let client = AzureClient::from_config(&config, "key").unwrap();
+
+        let request = |temperature| CompletionRequest {
+            prompt: Message::user("Hello"),
+            preamble: Some("Be brief.".to_string()),
+            chat_history: Vec::new(),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature,
+            max_tokens: None,
+            additional_params: None,
+        };
+        let response = client
+            .completion_model("chat-prod")
+            .completion(request(Some(0.2)))
+            .await
+            .unwrap();
+        completion.assert_async().await;
+        assert_eq!(response.raw_response.choices.len(), 1);
+
+        let embeddings = client
+            .embedding_model("embed", 2)
+            .embed_texts(vec!["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings[0].vec, vec![1.0, 0.0]);
+        assert_eq!(embeddings[1].document, "b");
+
+        let busy = client
+            .completion_model("busy")
+            .completion(request(None))
+            .await
+            .unwrap_err();
+        assert!(crate::model::fallback::should_fall_back(&busy));
+
+        let resource = AzureConfig {
+            resource: Some("docs-openai".to_string()),
+            ..AzureConfig::default()
+        };
+        assert_eq!(
+            resource.endpoint_url().as_deref(),
+            Some("https://docs-openai.openai.azure.com")
+        );
+        assert_eq!(resource.deployment(), DEFAULT_DEPLOYMENT);
+    }
+}
@@ -0,0 +1,340 @@
+//! # Completion Cache Module
+//!
+//! This module keeps completion responses on disk, keyed by a hash of the model and the
+//! whole request, so re-running indexing after a crash or a settings tweak reuses the
+//! summaries and contexts already generated for identical prompts instead of paying for
+//! them again.
+//!
+//! ## Key Components
+//!
+//! - `CompletionCacheConfig`: The `[completion_cache]` table of `hal.toml`
+//! - `CompletionCache`: The cache directory and how long entries stay valid
+//! - `CachedCompletionModel`: A completion model answering from the cache when it can
+//!
+//! ## Example
+//!
+//! ```toml
+//! [completion_cache]
+//! ttl_hours = 72
+//! dir = "/var/cache/hal/completions"
+//! ```
+//!
+//! `hal index --no-cache` and `hal reprocess --no-cache` neither read nor write the cache.
+//! Entries older than the TTL are ignored and removed when next looked up.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rig::OneOrMany;
+use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest};
+use rig::message::AssistantContent;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// How long entries stay valid when no TTL is configured
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Completion cache settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompletionCacheConfig {
+    /// Whether indexing caches completions
+    pub enabled: bool,
+
+    /// Hours an entry stays valid, a week if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_hours: Option<u64>,
+
+    /// Directory of the cache, `~/.cache/hal/completions` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for CompletionCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_hours: None,
+            dir: None,
+        }
+    }
+}
+
+impl CompletionCacheConfig {
+    /// The cache these settings describe, if enabled
+    pub fn cache(&self) -> Option<CompletionCache> {
+        if !self.enabled {
+            return None;
+        }
+        let dir = self.dir.clone().unwrap_or_else(default_dir);
+        let ttl = self
+            .ttl_hours
+            .map_or(DEFAULT_TTL, |hours| Duration::from_secs(hours * 60 * 60));
+        Some(CompletionCache::new(dir).with_ttl(ttl))
+    }
+}
+
+/// `$XDG_CACHE_HOME/hal/completions` or `~/.cache/hal/completions`
+fn default_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("hal").join("completions"))
+        .unwrap_or_else(|| PathBuf::from(".hal_completions"))
+}
+
+/// SHA-256 of a text, as 64 hex digits
+pub(crate) fn sha256_hex(text: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, text.as_bytes());
+    digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A cached response
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Seconds since the Unix epoch when the response was stored
+    created: u64,
+
+    /// Model that answered
+    model: String,
+
+    /// The response
+    choice: OneOrMany<AssistantContent>,
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Completion responses stored as one JSON file per request
+#[derive(Debug, Clone)]
+pub struct CompletionCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl CompletionCache {
+    /// Create a cache in a directory, created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Keep entries valid for `ttl` instead of a week
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Key of a request to a model: a SHA-256 of the model, prompt, history, documents,
+    /// tools and sampling parameters
+    pub fn key(model: &str, request: &CompletionRequest) -> String {
+        let material = serde_json::json!({
+            "model": model,
+            "preamble": request.preamble,
+            "chat_history": request.chat_history,
+            "prompt": request.prompt,
+            "documents": request.documents,
+            "tools": request.tools,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "additional_params": request.additional_params,
+        });
+        sha256_hex(&material.to_string())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The cached response of a key, unless missing, unreadable or expired
+    pub fn get(&self, key: &str) -> Option<OneOrMany<AssistantContent>> {
+        let path = self.path(key);
+        let content = std::fs::read_to_string(&path).ok()?;
+        let entry = match serde_json::from_str::<CacheEntry>(&content) {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Removing unreadable cache entry {}: {}", path.display(), e);
+                let _ = std::fs::remove_file(&path);
+                return None;
+            }
+        };
+        if now().saturating_sub(entry.created) > self.ttl.as_secs() {
+            debug!("Removing expired cache entry {}", path.display());
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        Some(entry.choice)
+    }
+
+    /// Store the response of a key, replacing the file atomically so concurrent readers
+    /// never see half an entry
+    pub fn put(
+        &self,
+        key: &str,
+        model: &str,
+        choice: &OneOrMany<AssistantContent>,
+    ) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let entry = CacheEntry {
+            created: now(),
+            model: model.to_string(),
+            choice: choice.clone(),
+        };
+        // Writers of the same key in this or another process each use their own file
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let path = self.path(key);
+        let partial = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&partial, serde_json::to_vec(&entry)?)?;
+        std::fs::rename(&partial, &path)
+    }
+}
+
+/// Raw response of a cached model
+#[derive(Debug)]
+pub enum CachedResponse<T> {
+    /// The wrapped model answered
+    Fresh(T),
+
+    /// The answer came from the cache, without a raw response
+    Cached,
+}
+
+/// A completion model answering repeated requests from a [`CompletionCache`]
+#[derive(Clone)]
+pub struct CachedCompletionModel<M: CompletionModel> {
+    model: M,
+    cache: Option<Arc<CompletionCache>>,
+    name: String,
+}
+
+impl<M: CompletionModel> CachedCompletionModel<M> {
+    /// Wrap a model, passing every request through when `cache` is `None`
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - The model answering requests missing from the cache
+    /// * `cache` - The cache, or `None` to disable it
+    /// * `name` - Name of the model in cache keys, so other models don't share its entries
+    pub fn new(model: M, cache: Option<CompletionCache>, name: &str) -> Self {
+        Self {
+            model,
+            cache: cache.map(Arc::new),
+            name: name.to_string(),
+        }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for CachedCompletionModel<M> {
+    type Response = CachedResponse<M::Response>;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let Some(cache) = &self.cache else {
+            let response = self.model.completion(request).await?;
+            return Ok(completion::CompletionResponse {
+                choice: response.choice,
+                raw_response: CachedResponse::Fresh(response.raw_response),
+            });
+        };
+
+        let key = CompletionCache::key(&self.name, &request);
+        if let Some(choice) = cache.get(&key) {
+            debug!("Completion cache hit {}", key);
+            return Ok(completion::CompletionResponse {
+                choice,
+                raw_response: CachedResponse::Cached,
+            });
+        }
+
+        let response = self.model.completion(request).await?;
+        if let Err(e) = cache.put(&key, &self.name, &response.choice) {
+            warn!("Failed to cache completion {}: {}", key, e);
+        }
+        Ok(completion::CompletionResponse {
+            choice: response.choice,
+            raw_response: CachedResponse::Fresh(response.raw_response),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock_model::MockCompletionModel;
+    use rig::completion::Prompt;
+
+    #[tokio::test]
+    async fn test_cached_completions() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompletionCache::new(dir.path());
+        let mock = MockCompletionModel::with_text_response("first");
+        let model = CachedCompletionModel::new(
+            mock.clone(),
+            Some(cache.clone()),
+            "gemini/gemini-2.0-flash",
+        );
+        let agent = rig::agent::AgentBuilder::new(model)
+            .preamble("Summarize:")
+            .build();
+        assert_eq!(agent.prompt("some page").await.unwrap(), "first");
+
+        // Identical requests are answered from the cache, new ones by the model
+        mock.set_text_response("second").await;
+        assert_eq!(agent.prompt("some page").await.unwrap(), "first");
+        assert_eq!(agent.prompt("another page").await.unwrap(), "second");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let uncached = CachedCompletionModel::new(mock.clone(), None, "gemini/gemini-2.0-flash");
+        let agent = rig::agent::AgentBuilder::new(uncached)
+            .preamble("Summarize:")
+            .build();
+        assert_eq!(agent.prompt("some page").await.unwrap(), "second");
+
+        let request = |temperature| CompletionRequest {
+            prompt: rig::message::Message::user("some page"),
+            preamble: Some("Summarize:".to_string()),
+            chat_history: Vec::new(),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature,
+            max_tokens: None,
+            additional_params: None,
+        };
+        let key = CompletionCache::key("gemini/gemini-2.0-flash", &request(None));
+        assert!(cache.get(&key).is_some());
+        assert_ne!(key, CompletionCache::key("other", &request(None)));
+        assert_ne!(
+            key,
+            CompletionCache::key("gemini/gemini-2.0-flash", &request(Some(0.2)))
+        );
+
+        let expired = CompletionCache::new(dir.path()).with_ttl(Duration::ZERO);
+        std::fs::write(
+            dir.path().join(format!("{}.json", key)),
+            serde_json::json!({ "created": 0, "model": "m", "choice": [{ "text": "old" }] })
+                .to_string(),
+        )
+        .unwrap();
+        assert!(expired.get(&key).is_none());
+        assert!(!dir.path().join(format!("{}.json", key)).exists());
+    }
+}
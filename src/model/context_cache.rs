@@ -0,0 +1,419 @@
+//! # Gemini Context Cache Module
+//!
+//! This module provides a Gemini completion model that uses the provider's context caching
+//! (the `cachedContents` API) for large, repeated request prefixes, so they are billed at
+//! the cached rate and not processed again on every request.
+//!
+//! ## What is cached
+//!
+//! The prefix of a request is its preamble, its attached documents and its tool
+//! definitions. These stay the same across the requests of a coding agent, whose preamble
+//! holds the task and preloaded repository files, and across the turns of a chat answering
+//! from the same sources. Chat history and the prompt are sent with each request as usual.
+//!
+//! ## Behavior
+//!
+//! - Prefixes shorter than the minimum the API caches are sent uncached
+//! - A cache is created on the first request with a prefix and reused until it expires
+//! - Prefixes the API refuses to cache are remembered and sent uncached from then on
+//! - A request whose cache vanished is retried once without it
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rig::completion::{
+    self, CompletionError, CompletionModel, CompletionRequest, CompletionResponse, ToolDefinition,
+};
+use rig::message::Message;
+use rig::providers::gemini::{
+    self,
+    completion::gemini_api_types::{Content, GenerateContentResponse, GenerationConfig, Tool},
+};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use tracing::{debug, info, warn};
+
+use crate::search::bench::estimate_tokens;
+
+/// Fewest estimated tokens of a prefix worth caching; the API refuses smaller caches
+pub const DEFAULT_MIN_CACHE_TOKENS: usize = 4096;
+
+/// How long a cache is kept by the provider
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// Caches about to expire are replaced rather than used
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// The cacheable part of a completion request
+#[derive(Debug, Clone)]
+struct CachePrefix {
+    /// Preamble followed by the attached documents
+    system_instruction: String,
+    /// Tools the model may call
+    tools: Vec<ToolDefinition>,
+}
+
+impl CachePrefix {
+    /// The prefix of a request, if it has any
+    fn of(request: &CompletionRequest) -> Option<Self> {
+        let mut system_instruction = request.preamble.clone().unwrap_or_default();
+        if !request.documents.is_empty() {
+            let attachments: String = request.documents.iter().map(|d| d.to_string()).collect();
+            system_instruction
+                .push_str(&format!("\n\n<attachments>\n{}</attachments>", attachments));
+        }
+        if system_instruction.is_empty() && request.tools.is_empty() {
+            return None;
+        }
+        Some(Self {
+            system_instruction,
+            tools: request.tools.clone(),
+        })
+    }
+
+    /// Estimated tokens of the prefix
+    fn tokens(&self) -> usize {
+        estimate_tokens(&self.system_instruction)
+            + self
+                .tools
+                .iter()
+                .map(|tool| {
+                    estimate_tokens(&tool.description)
+                        + estimate_tokens(&tool.parameters.to_string())
+                })
+                .sum::<usize>()
+    }
+
+    /// Key of the prefix for a model
+    fn key(&self, model: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        model.hash(&mut hasher);
+        self.system_instruction.hash(&mut hasher);
+        for tool in &self.tools {
+            tool.name.hash(&mut hasher);
+            tool.description.hash(&mut hasher);
+            tool.parameters.to_string().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Body of the request creating a cache of the prefix
+    fn cache_body(&self, model: &str, ttl: Duration) -> Result<Value, CompletionError> {
+        let mut body = json!({
+            "model": format!("models/{}", model),
+            "ttl": format!("{}s", ttl.as_secs()),
+        });
+        if !self.system_instruction.is_empty() {
+            let text = json!({ "text": self.system_instruction });
+            body["systemInstruction"] = json!({ "parts": [text] });
+        }
+        if !self.tools.is_empty() {
+            let tools = self
+                .tools
+                .iter()
+                .cloned()
+                .map(Tool::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            body["tools"] = serde_json::to_value(tools)?;
+        }
+        Ok(body)
+    }
+}
+
+/// A cache created by the provider
+#[derive(Debug, Clone)]
+struct CachedContent {
+    /// Resource name, `cachedContents/...`
+    name: String,
+    /// When the provider deletes the cache
+    expires_at: Instant,
+}
+
+/// Response of the `cachedContents` API
+#[derive(Debug, Deserialize)]
+struct CreatedCache {
+    name: String,
+}
+
+/// Caches of the prefixes seen by a model
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Caches by prefix key
+    caches: HashMap<u64, CachedContent>,
+    /// Prefixes the provider refused to cache
+    refused: HashSet<u64>,
+}
+
+/// A Gemini completion model caching the prefixes of its requests
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use hal::model::context_cache::CachedGeminiModel;
+/// use rig::{completion::Prompt, providers::gemini};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = gemini::Client::new("your-api-key");
+/// let model = CachedGeminiModel::new(client, "gemini-2.0-flash");
+/// let agent = rig::agent::AgentBuilder::new(model)
+///     .preamble("A long preamble, e.g. the files of a repository...")
+///     .build();
+/// // Later prompts reuse the cached preamble
+/// let answer = agent.prompt("Where is the config loaded?").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CachedGeminiModel {
+    client: gemini::Client,
+    model: gemini::completion::CompletionModel,
+    name: String,
+    min_tokens: usize,
+    ttl: Duration,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl CachedGeminiModel {
+    /// Create a model caching prefixes of at least `DEFAULT_MIN_CACHE_TOKENS` tokens
+    pub fn new(client: gemini::Client, model: &str) -> Self {
+        Self {
+            model: client.completion_model(model),
+            client,
+            name: model.to_string(),
+            min_tokens: DEFAULT_MIN_CACHE_TOKENS,
+            ttl: DEFAULT_CACHE_TTL,
+            state: Arc::new(Mutex::new(CacheState::default())),
+        }
+    }
+
+    /// Only cache prefixes of at least this many estimated tokens; `usize::MAX` disables
+    /// caching
+    pub fn with_min_tokens(mut self, min_tokens: usize) -> Self {
+        self.min_tokens = min_tokens;
+        self
+    }
+
+    /// Keep caches for this long
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The cache of a prefix, created if there is none yet
+    async fn cache(&self, prefix: &CachePrefix) -> Option<String> {
+        let key = prefix.key(&self.name);
+        {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.refused.contains(&key) {
+                return None;
+            }
+            let fresh = state
+                .caches
+                .get(&key)
+                .filter(|cache| cache.expires_at > Instant::now() + EXPIRY_MARGIN);
+            if let Some(cache) = fresh {
+                return Some(cache.name.clone());
+            }
+        }
+
+        match self.create_cache(prefix).await {
+            Ok(name) => {
+                info!(cache = %name, tokens = prefix.tokens(), "Created context cache");
+                let cache = CachedContent {
+                    name: name.clone(),
+                    expires_at: Instant::now() + self.ttl,
+                };
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                state.caches.insert(key, cache);
+                Some(name)
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to create context cache; sending the prefix uncached");
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                state.refused.insert(key);
+                None
+            }
+        }
+    }
+
+    /// Create a cache of a prefix with the provider
+    async fn create_cache(&self, prefix: &CachePrefix) -> Result<String, CompletionError> {
+        let body = prefix.cache_body(&self.name, self.ttl)?;
+        let response = self
+            .client
+            .post("/v1beta/cachedContents")
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(CompletionError::ProviderError(response.text().await?));
+        }
+        Ok(response.json::<CreatedCache>().await?.name)
+    }
+
+    /// Send a request whose prefix is in a cache
+    async fn cached_completion(
+        &self,
+        cache: &str,
+        request: &CompletionRequest,
+    ) -> Result<CompletionResponse<GenerateContentResponse>, CompletionError> {
+        let body = cached_request_body(cache, request)?;
+        let response = self
+            .client
+            .post(&format!("/v1beta/models/{}:generateContent", self.name))
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(CompletionError::ProviderError(response.text().await?));
+        }
+
+        let response = response.json::<GenerateContentResponse>().await?;
+        if let Some(usage) = &response.usage_metadata {
+            debug!(
+                cache,
+                cached_tokens = usage.cached_content_token_count,
+                prompt_tokens = usage.prompt_token_count,
+                "Completion used context cache"
+            );
+        }
+        completion::CompletionResponse::try_from(response)
+    }
+
+    /// Forget the cache of a prefix, e.g. after the provider deleted it early
+    fn evict(&self, prefix: &CachePrefix) {
+        let key = prefix.key(&self.name);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.caches.remove(&key);
+    }
+}
+
+impl CompletionModel for CachedGeminiModel {
+    type Response = GenerateContentResponse;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let prefix = CachePrefix::of(&request).filter(|prefix| prefix.tokens() >= self.min_tokens);
+        let Some(prefix) = prefix else {
+            return self.model.completion(request).await;
+        };
+        let Some(cache) = self.cache(&prefix).await else {
+            return self.model.completion(request).await;
+        };
+
+        match self.cached_completion(&cache, &request).await {
+            Ok(response) => Ok(response),
+            Err(CompletionError::ProviderError(e)) => {
+                warn!(cache, error = %e, "Cached completion failed; retrying without the cache");
+                self.evict(&prefix);
+                self.model.completion(request).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Body of a `generateContent` request with its prefix replaced by a cache
+///
+/// The API rejects requests setting a system instruction or tools next to a cache, so only
+/// the history, the prompt and the generation config are sent.
+fn cached_request_body(cache: &str, request: &CompletionRequest) -> Result<Value, CompletionError> {
+    let contents = request
+        .chat_history
+        .iter()
+        .cloned()
+        .chain(std::iter::once(request.prompt.clone()))
+        .map(|message: Message| {
+            Content::try_from(message).map_err(|e| CompletionError::RequestError(Box::new(e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let params = request
+        .additional_params
+        .clone()
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    let mut generation_config = serde_json::from_value::<GenerationConfig>(params)?;
+    if let Some(temperature) = request.temperature {
+        generation_config.temperature = Some(temperature);
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        generation_config.max_output_tokens = Some(max_tokens);
+    }
+
+    Ok(json!({
+        "cachedContent": cache,
+        "contents": contents,
+        "generationConfig": generation_config,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::completion::Document;
+
+    fn request(preamble: &str, documents: Vec<Document>) -> CompletionRequest {
+        CompletionRequest {
+            prompt: Message::user("Where is the config loaded?"),
+            preamble: Some(preamble.to_string()),
+            chat_history: vec![Message::user("Hi"), Message::assistant("Hello")],
+            documents,
+            tools: vec![ToolDefinition {
+                name: "show_file".to_string(),
+                description: "Show a file".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": { "path": { "type": "string" } }
+                }),
+            }],
+            temperature: Some(0.2),
+            max_tokens: None,
+            additional_params: Some(json!({ "topP": 0.9 })),
+        }
+    }
+
+    #[test]
+    fn test_prefix_key() {
+        let document = Document {
+            id: "src/config.rs".to_string(),
+            text: "fn load() {}".to_string(),
+            additional_props: HashMap::new(),
+        };
+        let prefix = CachePrefix::of(&request("Preamble", vec![document])).unwrap();
+        assert!(
+            prefix
+                .system_instruction
+                .starts_with("Preamble\n\n<attachments>")
+        );
+        assert!(prefix.system_instruction.contains("fn load() {}"));
+
+        let other = CachePrefix::of(&request("Other preamble", Vec::new())).unwrap();
+        assert_eq!(prefix.key("model"), prefix.clone().key("model"));
+        assert_ne!(prefix.key("model"), other.key("model"));
+        assert_ne!(prefix.key("model"), prefix.key("other-model"));
+        assert!(prefix.tokens() > other.tokens());
+
+        let body = prefix
+            .cache_body("gemini-2.0-flash", DEFAULT_CACHE_TTL)
+            .unwrap();
+        assert_eq!(body["model"], "models/gemini-2.0-flash");
+        assert_eq!(body["ttl"], "600s");
+        assert_eq!(body["tools"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cached_request_body() {
+        let body =
+            cached_request_body("cachedContents/abc", &request("Preamble", Vec::new())).unwrap();
+        assert_eq!(body["cachedContent"], "cachedContents/abc");
+        assert_eq!(body["contents"].as_array().unwrap().len(), 3);
+        assert_eq!(body["generationConfig"]["temperature"], 0.2);
+        assert_eq!(body["generationConfig"]["topP"], 0.9);
+        assert!(body.get("systemInstruction").is_none());
+        assert!(body.get("tools").is_none());
+    }
+}
@@ -0,0 +1,301 @@
+//! # Embedding Backend Module
+//!
+//! This module selects the model embedding chunks and queries, so indexing and search can
+//! embed offline with a local model, or with an open-source model hosted by Hugging Face,
+//! while answers still come from the cloud LLM.
+//!
+//! ## Key Components
+//!
+//! - `EmbeddingConfig`: The `[embedding]` table of `hal.toml`
+//! - `EmbeddingProvider`: Whether the Gemini API, Vertex AI, Azure OpenAI, a local model
+//!   or a Hugging Face model embeds texts
+//! - `EmbeddingBackend`: An embedding model dispatching to the configured backend
+//!
+//! ## Example
+//!
+//! ```toml
+//! [embedding]
+//! provider = "local"
+//! model = "BAAI/bge-base-en-v1.5"
+//! ```
+//!
+//! Hugging Face models read an access token from `HF_TOKEN`, the keyring or `hal.toml`:
+//!
+//! ```toml
+//! [embedding]
+//! provider = "huggingface"
+//! model = "sentence-transformers/all-mpnet-base-v2"
+//! # Optional dedicated Inference Endpoint serving the model
+//! url = "https://xyz.us-east-1.aws.endpoints.huggingface.cloud"
+//! ```
+//!
+//! Azure OpenAI embeds with the deployment named by `model`, `text-embedding-3-small` if
+//! unset, of the `[azure]` resource.
+//!
+//! Local models need the `local-embeddings` feature. Indexes store 768-dimensional
+//! embeddings, so only models of that size can be used, and an index must be re-embedded
+//! with `hal reembed` after switching providers or models.
+
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use governor::{Quota, RateLimiter};
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use rig::providers::gemini;
+use serde::{Deserialize, Serialize};
+
+use super::azure::{self, AzureClient, AzureEmbeddingModel};
+use super::huggingface::{self, HuggingFaceEmbeddingModel};
+use super::ratelimited_embedding::RateLimitedEmbeddingModel;
+use super::vertex::{VertexClient, VertexEmbeddingModel};
+
+#[cfg(feature = "local-embeddings")]
+use super::local_embedding::LocalEmbeddingModel;
+
+/// Dimensions of the embeddings an index stores, the `F32_BLOB(768)` column of the chunks
+/// table
+pub const EMBEDDING_DIMENSIONS: usize = 768;
+
+/// Local or Hugging Face model used when none is configured
+pub const DEFAULT_MODEL: &str = "BAAI/bge-base-en-v1.5";
+
+/// Backend embedding chunks and queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProvider {
+    /// The embedding model of the LLM provider
+    #[default]
+    Gemini,
+
+    /// The embedding model of Vertex AI, configured by the `[vertex]` table
+    Vertex,
+
+    /// An embedding deployment of the Azure OpenAI resource of the `[azure]` table
+    Azure,
+
+    /// An ONNX model run in-process, downloaded once and then used offline
+    Local,
+
+    /// A model hosted by the Hugging Face Inference API or an Inference Endpoint
+    HuggingFace,
+}
+
+/// Model embedding chunks and queries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    /// Backend embedding texts
+    pub provider: EmbeddingProvider,
+
+    /// Model of the local or Hugging Face provider, `BAAI/bge-base-en-v1.5` if unset, or
+    /// deployment of the Azure provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// URL of a dedicated Hugging Face Inference Endpoint serving the model, instead of
+    /// the serverless Inference API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Directory local models are downloaded to, `~/.cache/hal/models` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl EmbeddingConfig {
+    /// Name of the model embedding texts, recorded in the processor fingerprint
+    pub fn model_name(&self) -> &str {
+        match self.provider {
+            EmbeddingProvider::Gemini | EmbeddingProvider::Vertex => {
+                gemini::embedding::EMBEDDING_004
+            }
+            EmbeddingProvider::Azure => self
+                .model
+                .as_deref()
+                .unwrap_or(azure::DEFAULT_EMBEDDING_DEPLOYMENT),
+            EmbeddingProvider::Local | EmbeddingProvider::HuggingFace => {
+                self.model.as_deref().unwrap_or(DEFAULT_MODEL)
+            }
+        }
+    }
+
+    /// Directory local models are downloaded to
+    pub fn model_cache_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.cache_dir {
+            return dir.clone();
+        }
+        std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|dir| dir.join("hal").join("models"))
+            .unwrap_or_else(|| PathBuf::from(".fastembed_cache"))
+    }
+}
+
+/// Embedding model of a client: the provider's model, a Hugging Face model or a local one
+#[derive(Clone)]
+pub enum EmbeddingBackend<E> {
+    /// The provider's embedding model
+    Remote(E),
+
+    /// The embedding model of Vertex AI
+    Vertex(RateLimitedEmbeddingModel<VertexEmbeddingModel>),
+
+    /// An embedding deployment of Azure OpenAI
+    Azure(RateLimitedEmbeddingModel<AzureEmbeddingModel>),
+
+    /// A model hosted by Hugging Face
+    HuggingFace(RateLimitedEmbeddingModel<HuggingFaceEmbeddingModel>),
+
+    /// A local ONNX model
+    #[cfg(feature = "local-embeddings")]
+    Local(LocalEmbeddingModel),
+}
+
+impl<E: EmbeddingModel> EmbeddingBackend<E> {
+    /// The backend of a configuration, `remote` for the provider's model
+    ///
+    /// # Errors
+    ///
+    /// Fails if Vertex AI credentials or project can't be found for the Vertex provider, if
+    /// no Azure OpenAI endpoint or key is set for the Azure provider, if no Hugging Face
+    /// token is set for the Hugging Face provider, if the local
+    /// provider is configured without the `local-embeddings` feature, if the local model
+    /// can't be loaded, or if it doesn't produce [`EMBEDDING_DIMENSIONS`] dimensions.
+    pub fn from_config(config: &EmbeddingConfig, remote: E) -> Result<Self, EmbeddingError> {
+        match config.provider {
+            EmbeddingProvider::Gemini => Ok(Self::Remote(remote)),
+            EmbeddingProvider::Vertex => {
+                let client = VertexClient::from_config(&crate::config::current().vertex)
+                    .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+                let model = client.embedding_model(config.model_name(), EMBEDDING_DIMENSIONS);
+                Ok(Self::Vertex(rate_limited(model)))
+            }
+            EmbeddingProvider::Azure => {
+                let api_key = crate::config::api_key(azure::API_KEY_VAR).ok_or_else(|| {
+                    EmbeddingError::ProviderError(format!(
+                        "{} must be set for Azure OpenAI embeddings",
+                        azure::API_KEY_VAR
+                    ))
+                })?;
+                let client = AzureClient::from_config(&crate::config::current().azure, &api_key)
+                    .map_err(EmbeddingError::ProviderError)?;
+                let model = client.embedding_model(config.model_name(), EMBEDDING_DIMENSIONS);
+                Ok(Self::Azure(rate_limited(model)))
+            }
+            EmbeddingProvider::HuggingFace => {
+                let token = crate::config::api_key(huggingface::TOKEN_VAR).ok_or_else(|| {
+                    EmbeddingError::ProviderError(format!(
+                        "{} must be set for Hugging Face embeddings",
+                        huggingface::TOKEN_VAR
+                    ))
+                })?;
+                let mut model = HuggingFaceEmbeddingModel::new(
+                    &token,
+                    config.model_name(),
+                    EMBEDDING_DIMENSIONS,
+                );
+                if let Some(url) = &config.url {
+                    model = model.with_url(url);
+                }
+                Ok(Self::HuggingFace(rate_limited(model)))
+            }
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingProvider::Local => {
+                let model =
+                    LocalEmbeddingModel::new(config.model_name(), config.model_cache_dir())?;
+                if model.ndims() != EMBEDDING_DIMENSIONS {
+                    return Err(EmbeddingError::ProviderError(format!(
+                        "{} produces {} dimensions but indexes store {}",
+                        config.model_name(),
+                        model.ndims(),
+                        EMBEDDING_DIMENSIONS
+                    )));
+                }
+                Ok(Self::Local(model))
+            }
+            #[cfg(not(feature = "local-embeddings"))]
+            EmbeddingProvider::Local => Err(EmbeddingError::ProviderError(
+                "local embeddings need hal built with the local-embeddings feature".to_string(),
+            )),
+        }
+    }
+}
+
+/// A remote model with the embedding quota and concurrency of the other clients
+fn rate_limited<M: EmbeddingModel>(model: M) -> RateLimitedEmbeddingModel<M> {
+    let limiter = RateLimiter::direct(Quota::per_minute(
+        NonZeroU32::new(1000).expect("must create rate limit"),
+    ));
+    let model = RateLimitedEmbeddingModel::new(model, limiter);
+    match crate::config::current().concurrency.embedding {
+        Some(max) => model.with_concurrency(max),
+        None => model,
+    }
+}
+
+impl<E: EmbeddingModel> EmbeddingModel for EmbeddingBackend<E> {
+    // The smallest batch of the remote backends; local models batch any number of texts
+    // themselves
+    const MAX_DOCUMENTS: usize = if E::MAX_DOCUMENTS < HuggingFaceEmbeddingModel::MAX_DOCUMENTS {
+        E::MAX_DOCUMENTS
+    } else {
+        HuggingFaceEmbeddingModel::MAX_DOCUMENTS
+    };
+
+    fn ndims(&self) -> usize {
+        match self {
+            Self::Remote(model) => model.ndims(),
+            Self::Vertex(model) => model.ndims(),
+            Self::Azure(model) => model.ndims(),
+            Self::HuggingFace(model) => model.ndims(),
+            #[cfg(feature = "local-embeddings")]
+            Self::Local(model) => model.ndims(),
+        }
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        match self {
+            Self::Remote(model) => model.embed_texts(texts).await,
+            Self::Vertex(model) => model.embed_texts(texts).await,
+            Self::Azure(model) => model.embed_texts(texts).await,
+            Self::HuggingFace(model) => model.embed_texts(texts).await,
+            #[cfg(feature = "local-embeddings")]
+            Self::Local(model) => model.embed_texts(texts).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock_model::MockEmbeddingModel;
+
+    #[tokio::test]
+    async fn test_embedding_backend() {
+        let config: EmbeddingConfig = toml::from_str("").unwrap();
+        assert_eq!(config.model_name(), gemini::embedding::EMBEDDING_004);
+        let backend = EmbeddingBackend::from_config(&config, MockEmbeddingModel::new()).unwrap();
+        let embedding = backend.embed_text("offline search").await.unwrap();
+        assert_eq!(embedding.vec.len(), backend.ndims());
+
+        let config: EmbeddingConfig =
+            toml::from_str("provider = \"local\"\ncache_dir = \"/tmp/models\"").unwrap();
+        assert_eq!(config.model_name(), DEFAULT_MODEL);
+        assert_eq!(config.model_cache_dir(), PathBuf::from("/tmp/models"));
+        #[cfg(not(feature = "local-embeddings"))]
+        assert!(EmbeddingBackend::from_config(&config, MockEmbeddingModel::new()).is_err());
+
+        let config: EmbeddingConfig =
+            toml::from_str("provider = \"huggingface\"\nmodel = \"org/model\"").unwrap();
+        assert_eq!(config.provider, EmbeddingProvider::HuggingFace);
+        assert_eq!(config.model_name(), "org/model");
+
+        let config: EmbeddingConfig = toml::from_str("provider = \"azure\"").unwrap();
+        assert_eq!(config.model_name(), azure::DEFAULT_EMBEDDING_DEPLOYMENT);
+    }
+}
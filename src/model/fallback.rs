@@ -0,0 +1,331 @@
+//! # Model Fallback Module
+//!
+//! This module chains completion models so a request that hits a rate limit or an
+//! unavailable model is retried on the next model of the chain, e.g. `gemini-2.0-flash`,
+//! then `gemini-2.0-flash-lite`, then the same model with another API key. Indexing jobs
+//! issue thousands of requests, and without a fallback they fail as soon as one model's
+//! quota runs out.
+//!
+//! ## Key Components
+//!
+//! - `ModelChain`: The models to try in order, read from the `[[model_chain.models]]` tables
+//!   of `hal.toml`
+//! - `FallbackCompletionModel`: A completion model trying each model of a chain in turn
+//! - `ProviderCompletionModel`: A model of a chain, served by the Gemini API, Vertex AI or
+//!   Azure OpenAI
+//! - `should_fall_back`: Which errors move a request to the next model
+//!
+//! The fallback is decided per request: the next request starts again with the first model,
+//! so the chain returns to it once its quota recovers.
+
+use rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+use rig::providers::gemini::{self, completion::gemini_api_types::GenerateContentResponse};
+use rig::providers::openai;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::azure::AzureCompletionModel;
+use super::vertex::VertexCompletionModel;
+use crate::config::Provider;
+
+/// Markers of provider errors caused by quotas or unavailable models
+const FALLBACK_MARKERS: &[&str] = &[
+    "RESOURCE_EXHAUSTED",
+    "UNAVAILABLE",
+    "DEADLINE_EXCEEDED",
+    "429 Too Many Requests",
+    "503 Service Unavailable",
+];
+
+/// Completion models to try in order; an empty chain uses the default model
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelChain {
+    /// Models, first preferred
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<ChainModel>,
+}
+
+/// A model of a chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainModel {
+    /// Provider serving the model
+    #[serde(default)]
+    pub provider: Provider,
+
+    /// Name of the model, e.g. `gemini-2.0-flash-lite`
+    pub model: String,
+
+    /// Environment variable of the API key to use instead of the command's default key,
+    /// so a model can fall back to another account's quota
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// Most requests in flight to this model, instead of the `[concurrency]` default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+}
+
+impl ModelChain {
+    /// Whether no model is configured
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Provider and name of each model, first preferred, or the provider alone for an empty
+    /// chain, identifying the chain's answers in the completion cache
+    pub fn label(&self, provider: Provider) -> String {
+        if self.is_empty() {
+            return format!("{}/default", provider);
+        }
+        self.models
+            .iter()
+            .map(|entry| format!("{}/{}", entry.provider, entry.model))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Whether an error of one model should move the request to the next model of a chain
+///
+/// Rate limits, exhausted quotas, overloaded or unreachable models fall back; errors of
+/// the request itself, such as invalid arguments, would fail on every model and don't.
+pub fn should_fall_back(error: &CompletionError) -> bool {
+    match error {
+        CompletionError::HttpError(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status()
+                    .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        CompletionError::ProviderError(message) => FALLBACK_MARKERS
+            .iter()
+            .any(|marker| message.contains(marker)),
+        _ => false,
+    }
+}
+
+/// Completion model of a chain, from the provider serving it
+///
+/// Models of every provider can be mixed in one chain; their raw responses are kept as a
+/// `ProviderResponse`.
+#[derive(Clone)]
+pub enum ProviderCompletionModel {
+    /// A model of the Gemini API
+    Gemini(gemini::completion::CompletionModel),
+
+    /// A model hosted on Vertex AI
+    Vertex(VertexCompletionModel),
+
+    /// A deployment of an Azure OpenAI resource
+    Azure(AzureCompletionModel),
+}
+
+/// Raw response of a chain model
+#[derive(Debug)]
+pub enum ProviderResponse {
+    /// Response of a Gemini model, from the Gemini API or Vertex AI
+    Gemini(GenerateContentResponse),
+
+    /// Response of an OpenAI model deployed to Azure
+    OpenAI(openai::CompletionResponse),
+}
+
+impl CompletionModel for ProviderCompletionModel {
+    type Response = ProviderResponse;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let (choice, raw_response) = match self {
+            ProviderCompletionModel::Gemini(model) => {
+                let response = model.completion(request).await?;
+                (
+                    response.choice,
+                    ProviderResponse::Gemini(response.raw_response),
+                )
+            }
+            ProviderCompletionModel::Vertex(model) => {
+                let response = model.completion(request).await?;
+                (
+                    response.choice,
+                    ProviderResponse::Gemini(response.raw_response),
+                )
+            }
+            ProviderCompletionModel::Azure(model) => {
+                let response = model.completion(request).await?;
+                (
+                    response.choice,
+                    ProviderResponse::OpenAI(response.raw_response),
+                )
+            }
+        };
+        Ok(CompletionResponse {
+            choice,
+            raw_response,
+        })
+    }
+}
+
+/// A completion model trying the models of a chain in turn
+#[derive(Clone)]
+pub struct FallbackCompletionModel<M: CompletionModel> {
+    /// Models with their names, first preferred
+    models: Vec<(String, M)>,
+}
+
+impl<M: CompletionModel> FallbackCompletionModel<M> {
+    /// Create a chain starting with a model
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the model, for logs
+    /// * `model` - The preferred model
+    pub fn new(name: impl Into<String>, model: M) -> Self {
+        Self {
+            models: vec![(name.into(), model)],
+        }
+    }
+
+    /// Add a model tried when the previous ones fail
+    pub fn with_fallback(mut self, name: impl Into<String>, model: M) -> Self {
+        self.models.push((name.into(), model));
+        self
+    }
+
+    /// Names of the models, first preferred
+    pub fn names(&self) -> Vec<&str> {
+        self.models.iter().map(|(name, _)| name.as_str()).collect()
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for FallbackCompletionModel<M> {
+    type Response = M::Response;
+
+    async fn completion(
+        &self,
+        completion_request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let (last, fallbacks) = self
+            .models
+            .split_last()
+            .expect("a chain has at least one model");
+        for (i, (name, model)) in fallbacks.iter().enumerate() {
+            match model.completion(copy_request(&completion_request)).await {
+                Err(e) if should_fall_back(&e) => {
+                    let next = &self.models[i + 1].0;
+                    warn!(model = %name, next = %next, error = %e, "Falling back to the next model");
+                }
+                result => return result,
+            }
+        }
+        last.1.completion(completion_request).await
+    }
+}
+
+/// A copy of a request, to send it to another model; rig's request is not `Clone`
+fn copy_request(request: &CompletionRequest) -> CompletionRequest {
+    CompletionRequest {
+        prompt: request.prompt.clone(),
+        preamble: request.preamble.clone(),
+        chat_history: request.chat_history.clone(),
+        documents: request.documents.clone(),
+        tools: request.tools.clone(),
+        temperature: request.temperature,
+        max_tokens: request.max_tokens,
+        additional_params: request.additional_params.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::completion::AssistantContent;
+    use rig::message::Message;
+    use rig::one_or_many::OneOrMany;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A model failing with a provider error, or answering with its name
+    #[derive(Clone)]
+    struct TestModel {
+        name: &'static str,
+        error: Option<&'static str>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl TestModel {
+        fn new(name: &'static str, error: Option<&'static str>) -> Self {
+            Self {
+                name,
+                error,
+                calls: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    impl CompletionModel for TestModel {
+        type Response = String;
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<String>, CompletionError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.error {
+                Some(error) => Err(CompletionError::ProviderError(error.to_string())),
+                None => Ok(CompletionResponse {
+                    choice: OneOrMany::one(AssistantContent::text(self.name)),
+                    raw_response: self.name.to_string(),
+                }),
+            }
+        }
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            prompt: Message::user("Summarize the page"),
+            preamble: None,
+            chat_history: Vec::new(),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            additional_params: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_quota_errors() {
+        let exhausted = r#"{"error": {"code": 429, "status": "RESOURCE_EXHAUSTED"}}"#;
+        let invalid = r#"{"error": {"code": 400, "status": "INVALID_ARGUMENT"}}"#;
+
+        let flash = TestModel::new("flash", Some(exhausted));
+        let lite = TestModel::new("flash-lite", None);
+        let chain = FallbackCompletionModel::new("flash", flash.clone())
+            .with_fallback("flash-lite", lite.clone());
+        let response = chain.completion(request()).await.unwrap();
+        assert_eq!(response.raw_response, "flash-lite");
+        assert_eq!(chain.names(), vec!["flash", "flash-lite"]);
+
+        // Each request starts again with the first model
+        chain.completion(request()).await.unwrap();
+        assert_eq!(flash.calls.load(Ordering::SeqCst), 2);
+
+        // Invalid requests fail without trying other models
+        let bad = TestModel::new("flash", Some(invalid));
+        let chain =
+            FallbackCompletionModel::new("flash", bad).with_fallback("flash-lite", lite.clone());
+        assert!(chain.completion(request()).await.is_err());
+        assert_eq!(lite.calls.load(Ordering::SeqCst), 2);
+
+        // The error of the last model is returned when every model fails
+        let chain = FallbackCompletionModel::new("flash", flash.clone()).with_fallback(
+            "flash-lite",
+            TestModel::new("flash-lite", Some("UNAVAILABLE")),
+        );
+        let error = chain.completion(request()).await.unwrap_err();
+        assert!(error.to_string().contains("UNAVAILABLE"));
+    }
+}
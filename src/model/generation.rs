@@ -0,0 +1,119 @@
+//! # Generation Options Module
+//!
+//! This module holds the sampling parameters passed with completion requests, so
+//! summaries, context strings and answers can be tuned from the CLI or `hal.toml` instead
+//! of using the provider defaults.
+//!
+//! ## Key Components
+//!
+//! - `GenerationOptions`: Temperature, nucleus sampling, output length, stop sequences and
+//!   JSON output
+//!
+//! `temperature` and `max_tokens` map to rig's request fields. `top_p`, `stop` and
+//! `json_output` have no rig field and are sent as Gemini generation config through
+//! `additional_params`.
+
+use rig::agent::AgentBuilder;
+use rig::completion::CompletionModel;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+/// Sampling parameters for completion requests; unset fields keep the provider defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationOptions {
+    /// Sampling temperature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+
+    /// Nucleus sampling probability mass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+
+    /// Maximum number of tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+
+    /// Sequences that end generation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+
+    /// Ask the provider for a JSON response
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub json_output: bool,
+}
+
+impl GenerationOptions {
+    /// These options with every field set in `overrides` replaced
+    pub fn merge(&self, overrides: &GenerationOptions) -> GenerationOptions {
+        GenerationOptions {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            stop: if overrides.stop.is_empty() {
+                self.stop.clone()
+            } else {
+                overrides.stop.clone()
+            },
+            json_output: self.json_output || overrides.json_output,
+        }
+    }
+
+    /// Provider parameters for the options rig has no request field for
+    fn additional_params(&self) -> Option<Value> {
+        let mut params = Map::new();
+        if let Some(top_p) = self.top_p {
+            params.insert("topP".to_string(), json!(top_p));
+        }
+        if !self.stop.is_empty() {
+            params.insert("stopSequences".to_string(), json!(self.stop));
+        }
+        if self.json_output {
+            params.insert("responseMimeType".to_string(), json!("application/json"));
+        }
+        (!params.is_empty()).then_some(Value::Object(params))
+    }
+
+    /// Apply the options to an agent before it is built
+    pub fn apply<M: CompletionModel>(&self, mut builder: AgentBuilder<M>) -> AgentBuilder<M> {
+        if let Some(temperature) = self.temperature {
+            builder = builder.temperature(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            builder = builder.max_tokens(max_tokens);
+        }
+        if let Some(params) = self.additional_params() {
+            builder = builder.additional_params(params);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_and_params() {
+        let config = GenerationOptions {
+            temperature: Some(0.2),
+            stop: vec!["END".to_string()],
+            ..GenerationOptions::default()
+        };
+        let flags = GenerationOptions {
+            temperature: Some(0.7),
+            top_p: Some(0.9),
+            ..GenerationOptions::default()
+        };
+
+        let options = config.merge(&flags);
+        assert_eq!(options.temperature, Some(0.7));
+        assert_eq!(options.top_p, Some(0.9));
+        assert_eq!(options.stop, vec!["END".to_string()]);
+        assert_eq!(
+            options.additional_params(),
+            Some(json!({ "topP": 0.9, "stopSequences": ["END"] }))
+        );
+        assert_eq!(GenerationOptions::default().additional_params(), None);
+    }
+}
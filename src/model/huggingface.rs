@@ -0,0 +1,218 @@
+//! # Hugging Face Embedding Model Module
+//!
+//! This module embeds texts with open-source models hosted by the Hugging Face Inference
+//! API, or by a dedicated Inference Endpoint, through the feature-extraction pipeline.
+//!
+//! Models with a pooling layer, such as sentence-transformers and BGE models, return one
+//! vector per text. Token vectors returned by other models are mean-pooled.
+
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use serde::Deserialize;
+use tracing::instrument;
+
+/// Base URL of the serverless Inference API models
+pub const DEFAULT_API_URL: &str = "https://router.huggingface.co/hf-inference/models";
+
+/// Environment variable holding the Hugging Face access token
+pub const TOKEN_VAR: &str = "HF_TOKEN";
+
+/// Vectors of the texts of a request
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FeatureExtraction {
+    /// A vector per text
+    Pooled(Vec<Vec<f64>>),
+
+    /// A vector per token of each text
+    Tokens(Vec<Vec<Vec<f64>>>),
+}
+
+impl FeatureExtraction {
+    /// A vector per text, averaging token vectors
+    fn into_vectors(self) -> Vec<Vec<f64>> {
+        match self {
+            FeatureExtraction::Pooled(vectors) => vectors,
+            FeatureExtraction::Tokens(texts) => texts.into_iter().map(mean_pool).collect(),
+        }
+    }
+}
+
+/// Average of token vectors
+fn mean_pool(tokens: Vec<Vec<f64>>) -> Vec<f64> {
+    let count = tokens.len().max(1) as f64;
+    let mut sum = vec![0.0; tokens.first().map_or(0, Vec::len)];
+    for token in &tokens {
+        for (total, value) in sum.iter_mut().zip(token) {
+            *total += value;
+        }
+    }
+    sum.into_iter().map(|total| total / count).collect()
+}
+
+/// Embedding model hosted by Hugging Face
+#[derive(Debug, Clone)]
+pub struct HuggingFaceEmbeddingModel {
+    /// URL of the feature-extraction pipeline of the model
+    url: String,
+
+    /// Access token
+    token: String,
+
+    /// Model ID, e.g. `BAAI/bge-base-en-v1.5`
+    model: String,
+
+    /// Dimensions of the model's vectors
+    ndims: usize,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl HuggingFaceEmbeddingModel {
+    /// Create a model served by the serverless Inference API
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Hugging Face access token
+    /// * `model` - Model ID, e.g. `BAAI/bge-base-en-v1.5`
+    /// * `ndims` - Dimensions of the model's vectors
+    pub fn new(token: &str, model: &str, ndims: usize) -> Self {
+        Self {
+            url: format!("{}/{}/pipeline/feature-extraction", DEFAULT_API_URL, model),
+            token: token.to_string(),
+            model: model.to_string(),
+            ndims,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send requests to a dedicated Inference Endpoint serving the model instead
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Model ID
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+impl EmbeddingModel for HuggingFaceEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 32;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    #[instrument(skip(self, texts), fields(model = %self.model))]
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts: Vec<String> = texts.into_iter().collect();
+        let response = self
+            .client
+            .post(&self.url)
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({
+                "inputs": texts,
+                "options": { "wait_for_model": true },
+            }))
+            .send()
+            .await
+            .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(EmbeddingError::ProviderError(format!(
+                "Hugging Face returned {}: {}",
+                status,
+                body.trim()
+            )));
+        }
+
+        let vectors = serde_json::from_str::<FeatureExtraction>(&body)?.into_vectors();
+        if vectors.len() != texts.len() {
+            return Err(EmbeddingError::ResponseError(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                vectors.len()
+            )));
+        }
+        if let Some(vec) = vectors.iter().find(|vec| vec.len() != self.ndims) {
+            return Err(EmbeddingError::ResponseError(format!(
+                "{} returned {} dimensions, expected {}",
+                self.model,
+                vec.len(),
+                self.ndims
+            )));
+        }
+        Ok(texts
+            .into_iter()
+            .zip(vectors)
+            .map(|(document, vec)| Embedding { document, vec })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_embed_texts() {
+        let mut server = mockito::Server::new_async().await;
+        let pooled = server
+            .mock("POST", "/pooled")
+            .match_header("authorization", "Bearer token")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "inputs": ["a", "b"] }),
+            ))
+            .with_body("[[1.0, 0.0], [0.0, 1.0]]")
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/tokens")
+            .with_body("[[[1.0, 2.0], [3.0, 4.0]]]")
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/loading")
+            .with_status(503)
+            .with_body(r#"{"error": "Model is currently loading"}"#)
+            .create_async()
+            .await;
+
+        let model = |path: &str| {
+            HuggingFaceEmbeddingModel::new("token", "org/model", 2).with_url(&format!(
+                "{}{}",
+                server.url(),
+                path
+            ))
+        };
+        let embeddings = model("/pooled")
+            .embed_texts(vec!["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        pooled.assert_async().await;
+        assert_eq!(embeddings[1].document, "b");
+        assert_eq!(embeddings[1].vec, vec![0.0, 1.0]);
+
+        let embedding = model("/tokens").embed_text("a").await.unwrap();
+        assert_eq!(embedding.vec, vec![2.0, 3.0]);
+
+        let error = model("/loading").embed_text("a").await.unwrap_err();
+        assert!(error.to_string().contains("currently loading"));
+        let wrong_size = HuggingFaceEmbeddingModel::new("token", "org/model", 768)
+            .with_url(&format!("{}/tokens", server.url()));
+        assert!(matches!(
+            wrong_size.embed_texts(vec!["a".to_string()]).await,
+            Err(EmbeddingError::ResponseError(_))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! # Local Embedding Model Module
+//!
+//! This module embeds texts in-process with ONNX models through fastembed, so indexing
+//! and search need no API calls or quota for embeddings.
+//!
+//! Models are downloaded from Hugging Face on first use and cached, after which they run
+//! offline. Inference is CPU-bound, so it runs on the blocking thread pool.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fastembed::{InitOptions, TextEmbedding};
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use tracing::{info, info_span};
+
+/// Texts embedded per ONNX inference run
+const BATCH_SIZE: usize = 64;
+
+/// Embedding model run locally with fastembed
+#[derive(Clone)]
+pub struct LocalEmbeddingModel {
+    name: String,
+    ndims: usize,
+    model: Arc<TextEmbedding>,
+}
+
+impl fmt::Debug for LocalEmbeddingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalEmbeddingModel")
+            .field("name", &self.name)
+            .field("ndims", &self.ndims)
+            .finish()
+    }
+}
+
+impl LocalEmbeddingModel {
+    /// Load a model, downloading it first if it isn't cached
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The Hugging Face name of a model fastembed supports, e.g.
+    ///   `BAAI/bge-base-en-v1.5`; the part after the `/` is enough
+    /// * `cache_dir` - The directory models are downloaded to
+    pub fn new(name: &str, cache_dir: PathBuf) -> Result<Self, EmbeddingError> {
+        let info = TextEmbedding::list_supported_models()
+            .into_iter()
+            .find(|info| {
+                info.model_code.eq_ignore_ascii_case(name)
+                    || info
+                        .model_code
+                        .rsplit('/')
+                        .next()
+                        .is_some_and(|short| short.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| {
+                EmbeddingError::ProviderError(format!("unknown local embedding model {}", name))
+            })?;
+
+        info!("Loading local embedding model {}", info.model_code);
+        let options = InitOptions::new(info.model.clone())
+            .with_cache_dir(cache_dir)
+            .with_show_download_progress(false);
+        let model = TextEmbedding::try_new(options)
+            .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+        Ok(Self {
+            name: info.model_code,
+            ndims: info.dim,
+            model: Arc::new(model),
+        })
+    }
+
+    /// Hugging Face name of the model
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl EmbeddingModel for LocalEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts: Vec<String> = texts.into_iter().collect();
+        let model = self.model.clone();
+        let span = info_span!("local_embedding", model = %self.name, texts = texts.len());
+        tokio::task::spawn_blocking(move || {
+            let _enter = span.enter();
+            let vectors = model
+                .embed(texts.clone(), Some(BATCH_SIZE))
+                .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+            Ok(texts
+                .into_iter()
+                .zip(vectors)
+                .map(|(document, vec)| Embedding {
+                    document,
+                    vec: vec.into_iter().map(f64::from).collect(),
+                })
+                .collect())
+        })
+        .await
+        .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?
+    }
+}
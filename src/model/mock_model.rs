@@ -1,13 +1,18 @@
-//! # Mock Completion Model for Testing
+//! # Mock Models for Testing
 //!
 //! Provides a `MockCompletionModel` that implements the `CompletionModel` trait
 //! for use in tests. It allows setting a predefined response or error to simulate
 //! different model behaviors without making actual API calls.
+//!
+//! `MockEmbeddingModel` implements the `EmbeddingModel` trait with deterministic,
+//! bag-of-words style embeddings, so texts sharing words produce similar vectors
+//! and the same text always produces the same vector.
 
 use rig::{
     completion::{
         AssistantContent, CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
     },
+    embeddings::{Embedding, EmbeddingError, EmbeddingModel},
     one_or_many::OneOrMany,
 };
 use std::sync::Arc;
@@ -29,6 +34,15 @@ impl MockCompletionModel {
         }
     }
 
+    /// Creates a new mock model that returns the given text for every request.
+    pub fn with_text_response(text: &str) -> Self {
+        Self {
+            response: Arc::new(Mutex::new(Some(OneOrMany::one(AssistantContent::text(
+                text,
+            ))))),
+        }
+    }
+
     /// Sets the response that the mock model should return.
     pub async fn set_response(&self, response: OneOrMany<AssistantContent>) {
         let mut guard = self.response.lock().await;
@@ -71,3 +85,127 @@ impl CompletionModel for MockCompletionModel {
         }
     }
 }
+
+/// Canned text returned by the completion model of `Client::new_mock`.
+pub const MOCK_RESPONSE: &str = "This is a mock response.";
+
+/// Number of dimensions produced by the mock embedding model, matching the
+/// `F32_BLOB(768)` column of the chunks table.
+pub const MOCK_EMBEDDING_DIMENSIONS: usize = 768;
+
+/// A mock embedding model for testing purposes.
+///
+/// Each word of the input is hashed into one of the dimensions, and the resulting
+/// vector is normalized. No network calls are made.
+#[derive(Debug, Clone)]
+pub struct MockEmbeddingModel {
+    ndims: usize,
+}
+
+impl MockEmbeddingModel {
+    /// Creates a new mock embedding model with `MOCK_EMBEDDING_DIMENSIONS` dimensions.
+    pub fn new() -> Self {
+        Self::with_dimensions(MOCK_EMBEDDING_DIMENSIONS)
+    }
+
+    /// Creates a new mock embedding model with the given number of dimensions.
+    pub fn with_dimensions(ndims: usize) -> Self {
+        Self { ndims }
+    }
+
+    /// Generates the deterministic embedding vector for a text.
+    fn embed(&self, text: &str) -> Vec<f64> {
+        let mut vec = vec![0.0; self.ndims];
+        for word in text.split_whitespace() {
+            let word = word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            if word.is_empty() {
+                continue;
+            }
+            // FNV-1a keeps the mapping stable across runs and platforms
+            let hash = word.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+            vec[(hash % self.ndims as u64) as usize] += 1.0;
+        }
+
+        let norm = vec.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            vec.iter_mut().for_each(|v| *v /= norm);
+        }
+        vec
+    }
+}
+
+impl Default for MockEmbeddingModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmbeddingModel for MockEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(texts
+            .into_iter()
+            .map(|document| Embedding {
+                vec: self.embed(&document),
+                document,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_embeddings_are_deterministic() {
+        let model = MockEmbeddingModel::new();
+        let first = model.embed_text("The quick brown fox").await.unwrap();
+        let second = model.embed_text("The quick brown fox").await.unwrap();
+
+        assert_eq!(first.vec.len(), MOCK_EMBEDDING_DIMENSIONS);
+        assert_eq!(first.vec, second.vec);
+    }
+
+    #[tokio::test]
+    async fn test_mock_embeddings_reflect_word_overlap() {
+        let model = MockEmbeddingModel::new();
+        let embeddings = model
+            .embed_texts(vec![
+                "rust async runtime".to_string(),
+                "rust async tasks".to_string(),
+                "banana bread recipe".to_string(),
+            ])
+            .await
+            .unwrap();
+
+        let dot = |a: &Embedding, b: &Embedding| -> f64 {
+            a.vec.iter().zip(b.vec.iter()).map(|(x, y)| x * y).sum()
+        };
+
+        assert!(dot(&embeddings[0], &embeddings[1]) > dot(&embeddings[0], &embeddings[2]));
+    }
+
+    #[tokio::test]
+    async fn test_mock_completion_with_text_response() {
+        let model = MockCompletionModel::with_text_response("canned");
+        let response = model.completion_request("hello").send().await.unwrap();
+
+        match response.choice.first() {
+            AssistantContent::Text(text) => assert_eq!(text.text, "canned"),
+            _ => panic!("Expected text response"),
+        }
+    }
+}
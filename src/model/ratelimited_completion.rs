@@ -7,6 +7,7 @@
 //!
 //! - Transparent rate limiting for any model implementing the `CompletionModel` trait
 //! - Configurable rate limits with governor crate integration
+//! - Optional bound on the requests in flight, shared by all clones of the model
 //! - Instrumentation with tracing spans for monitoring and debugging
 //! - Direct integration with the agent framework for conversation management
 //!
@@ -14,7 +15,9 @@
 //!
 //! The rate-limited model can be configured with different quota settings for
 //! different usage tiers (standard vs free). When the rate limit is reached,
-//! the model will transparently wait until requests are allowed again.
+//! the model will transparently wait until requests are allowed again. A request first
+//! waits for a concurrency permit and only then for the rate limiter, so requests queued
+//! behind the concurrency bound don't use up the rate limit while they wait.
 
 use std::sync::Arc;
 
@@ -23,6 +26,7 @@ use rig::{
     agent::AgentBuilder,
     completion::{self, CompletionError, CompletionModel, CompletionRequest, CompletionResponse},
 };
+use tokio::sync::Semaphore;
 use tracing::{Instrument, debug_span, info_span};
 
 use super::RateLimitResponse;
@@ -31,6 +35,7 @@ use super::RateLimitResponse;
 pub struct RateLimitedCompletionModel<M: CompletionModel> {
     model: M,
     limiter: Arc<DefaultDirectRateLimiter>,
+    concurrency: Option<Arc<Semaphore>>,
 }
 
 impl<M> RateLimitedCompletionModel<M>
@@ -41,9 +46,16 @@ where
         Self {
             model,
             limiter: Arc::new(limiter),
+            concurrency: None,
         }
     }
 
+    /// Allow at most `max` requests in flight at once
+    pub fn with_concurrency(mut self, max: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
+
     pub fn agent(self) -> AgentBuilder<Self> {
         AgentBuilder::new(self)
     }
@@ -56,6 +68,16 @@ impl<M: CompletionModel> CompletionModel for RateLimitedCompletionModel<M> {
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let _permit = match &self.concurrency {
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire()
+                    .instrument(debug_span!("concurrency"))
+                    .await
+                    .expect("the semaphore is never closed"),
+            ),
+            None => None,
+        };
         self.limiter
             .until_ready()
             .instrument(debug_span!("limiter"))
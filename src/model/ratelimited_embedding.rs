@@ -8,6 +8,7 @@
 //!
 //! - Transparent rate limiting for any model implementing the `EmbeddingModel` trait
 //! - Configurable rate limits with governor crate integration
+//! - Optional bound on the requests in flight, shared by all clones of the model
 //! - Instrumentation with tracing spans for monitoring and debugging
 //! - Maintains compatibility with the original model's dimensionality and constraints
 //!
@@ -15,18 +16,21 @@
 //!
 //! The rate-limited model transparently handles waiting when the rate limit is
 //! reached, making it suitable for batch processing of documents where a large
-//! number of embeddings need to be generated without exceeding API limits.
+//! number of embeddings need to be generated without exceeding API limits. As with
+//! completions, a request takes a concurrency permit before waiting for the limiter.
 
 use std::sync::Arc;
 
 use governor::DefaultDirectRateLimiter;
 use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use tokio::sync::Semaphore;
 use tracing::{Instrument, debug_span, info_span};
 
 #[derive(Clone)]
 pub struct RateLimitedEmbeddingModel<M: EmbeddingModel> {
     model: M,
     limiter: Arc<DefaultDirectRateLimiter>,
+    concurrency: Option<Arc<Semaphore>>,
 }
 
 impl<M> RateLimitedEmbeddingModel<M>
@@ -37,8 +41,15 @@ where
         Self {
             model,
             limiter: Arc::new(limiter),
+            concurrency: None,
         }
     }
+
+    /// Allow at most `max` requests in flight at once
+    pub fn with_concurrency(mut self, max: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
 }
 
 impl<M: EmbeddingModel> EmbeddingModel for RateLimitedEmbeddingModel<M> {
@@ -52,6 +63,16 @@ impl<M: EmbeddingModel> EmbeddingModel for RateLimitedEmbeddingModel<M> {
         &self,
         texts: impl IntoIterator<Item = String> + Send,
     ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let _permit = match &self.concurrency {
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire()
+                    .instrument(debug_span!("concurrency"))
+                    .await
+                    .expect("the semaphore is never closed"),
+            ),
+            None => None,
+        };
         self.limiter
             .until_ready()
             .instrument(debug_span!("limiter"))
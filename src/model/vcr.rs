@@ -0,0 +1,384 @@
+//! # Record/Replay Module
+//!
+//! This module records the completions and embeddings a client receives to a cassette file
+//! and replays them later without calling the provider, so integration tests of the
+//! processor, search and the coder executor run deterministically and offline in CI.
+//!
+//! ## Key Components
+//!
+//! - `VcrMode`: Whether a cassette is being recorded or replayed
+//! - `Cassette`: The recorded responses, keyed by a hash of the request
+//! - `VcrCompletionModel` / `VcrEmbeddingModel`: Models recording to or replaying from a
+//!   cassette
+//!
+//! ## Usage
+//!
+//! A test wraps a client with [`Client::with_vcr`](super::Client::with_vcr). Run it once
+//! with `HAL_VCR=record` and a real client to capture the responses, commit the cassette,
+//! and later runs replay it with `Client::new_mock`, failing on any request that wasn't
+//! recorded:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "mock")]
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use hal::model::Client;
+//! use hal::model::vcr::{Cassette, VcrMode};
+//!
+//! let cassette = Cassette::open("fixtures/guide.json", VcrMode::from_env()?)?;
+//! let client = Client::new_mock().with_vcr(cassette);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Completions are keyed like the completion cache, by the prompt, history, documents,
+//! tools and sampling parameters; embeddings by the text embedded, so replays don't
+//! depend on how texts were batched.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use rig::OneOrMany;
+use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest};
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use rig::message::{AssistantContent, Message};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::debug;
+
+use super::completion_cache::{CachedResponse, CompletionCache, sha256_hex};
+
+/// Environment variable choosing the mode, `record` or `replay`
+pub const MODE_VAR: &str = "HAL_VCR";
+
+/// Errors opening or saving a cassette
+#[derive(Debug, Error)]
+pub enum VcrError {
+    /// The cassette could not be read or written
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The cassette is not valid JSON
+    #[error("{path}: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    /// `HAL_VCR` names an unknown mode
+    #[error("{0}")]
+    Mode(String),
+}
+
+/// Whether a cassette is being recorded or replayed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VcrMode {
+    /// Call the wrapped models and store their responses
+    Record,
+
+    /// Answer from the cassette only, failing on requests it doesn't hold
+    #[default]
+    Replay,
+}
+
+impl VcrMode {
+    /// The mode named by `HAL_VCR`, replaying if it is unset
+    pub fn from_env() -> Result<Self, VcrError> {
+        match std::env::var(MODE_VAR) {
+            Ok(value) if !value.is_empty() => value.parse().map_err(VcrError::Mode),
+            _ => Ok(Self::default()),
+        }
+    }
+}
+
+impl FromStr for VcrMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            other => Err(format!(
+                "unknown {} mode '{}', expected record or replay",
+                MODE_VAR, other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for VcrMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Record => "record",
+            Self::Replay => "replay",
+        })
+    }
+}
+
+/// A recorded completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedCompletion {
+    /// Prompt of the request, to make the cassette readable in reviews
+    prompt: Message,
+
+    /// The response
+    choice: OneOrMany<AssistantContent>,
+}
+
+/// Contents of a cassette file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Recording {
+    /// Completions by request key
+    completions: BTreeMap<String, RecordedCompletion>,
+
+    /// Embedding vectors by text key
+    embeddings: BTreeMap<String, Vec<f64>>,
+}
+
+/// Responses recorded to, or replayed from, a JSON file
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: VcrMode,
+    recording: Mutex<Recording>,
+}
+
+impl Cassette {
+    /// Open a cassette
+    ///
+    /// Recording starts from an empty cassette, replacing the file as responses arrive;
+    /// replaying reads the file, which must exist.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a cassette to replay can't be read or parsed.
+    pub fn open(path: impl AsRef<Path>, mode: VcrMode) -> Result<Self, VcrError> {
+        let path = path.as_ref().to_path_buf();
+        let recording = match mode {
+            VcrMode::Record => Recording::default(),
+            VcrMode::Replay => {
+                let content = std::fs::read_to_string(&path).map_err(|source| VcrError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                serde_json::from_str(&content).map_err(|e| VcrError::Parse {
+                    path: path.clone(),
+                    message: e.to_string(),
+                })?
+            }
+        };
+        Ok(Self {
+            path,
+            mode,
+            recording: Mutex::new(recording),
+        })
+    }
+
+    /// Mode of the cassette
+    pub fn mode(&self) -> VcrMode {
+        self.mode
+    }
+
+    /// Write the recording, pretty-printed with sorted keys so fixture diffs stay small
+    fn save(&self, recording: &Recording) -> Result<(), VcrError> {
+        let io = |source| VcrError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        let content = serde_json::to_string_pretty(recording).map_err(|e| VcrError::Parse {
+            path: self.path.clone(),
+            message: e.to_string(),
+        })?;
+        std::fs::write(&self.path, content + "\n").map_err(io)
+    }
+
+    /// Error for a request the cassette doesn't hold
+    fn missing(&self, what: &str, key: &str) -> String {
+        format!(
+            "no recorded {} {} in {}; record it again with {}=record",
+            what,
+            key,
+            self.path.display(),
+            MODE_VAR
+        )
+    }
+}
+
+/// A completion model recording to or replaying from a [`Cassette`]
+#[derive(Clone)]
+pub struct VcrCompletionModel<M: CompletionModel> {
+    model: M,
+    cassette: Arc<Cassette>,
+}
+
+impl<M: CompletionModel> VcrCompletionModel<M> {
+    /// Wrap a model; it is only called while recording
+    pub fn new(model: M, cassette: Arc<Cassette>) -> Self {
+        Self { model, cassette }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for VcrCompletionModel<M> {
+    type Response = CachedResponse<M::Response>;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let key = CompletionCache::key("", &request);
+        if self.cassette.mode == VcrMode::Replay {
+            let recording = self
+                .cassette
+                .recording
+                .lock()
+                .expect("cassette lock poisoned");
+            let recorded = recording.completions.get(&key).ok_or_else(|| {
+                CompletionError::ProviderError(self.cassette.missing("completion", &key))
+            })?;
+            debug!("Replaying completion {}", key);
+            return Ok(completion::CompletionResponse {
+                choice: recorded.choice.clone(),
+                raw_response: CachedResponse::Cached,
+            });
+        }
+
+        let prompt = request.prompt.clone();
+        let response = self.model.completion(request).await?;
+        let mut recording = self
+            .cassette
+            .recording
+            .lock()
+            .expect("cassette lock poisoned");
+        recording.completions.insert(
+            key,
+            RecordedCompletion {
+                prompt,
+                choice: response.choice.clone(),
+            },
+        );
+        self.cassette
+            .save(&recording)
+            .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+        Ok(completion::CompletionResponse {
+            choice: response.choice,
+            raw_response: CachedResponse::Fresh(response.raw_response),
+        })
+    }
+}
+
+/// An embedding model recording to or replaying from a [`Cassette`]
+#[derive(Clone)]
+pub struct VcrEmbeddingModel<E: EmbeddingModel> {
+    model: E,
+    cassette: Arc<Cassette>,
+}
+
+impl<E: EmbeddingModel> VcrEmbeddingModel<E> {
+    /// Wrap a model; it is only called while recording, and gives the dimensions
+    pub fn new(model: E, cassette: Arc<Cassette>) -> Self {
+        Self { model, cassette }
+    }
+}
+
+impl<E: EmbeddingModel> EmbeddingModel for VcrEmbeddingModel<E> {
+    const MAX_DOCUMENTS: usize = E::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        self.model.ndims()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts: Vec<String> = texts.into_iter().collect();
+        if self.cassette.mode == VcrMode::Replay {
+            let recording = self
+                .cassette
+                .recording
+                .lock()
+                .expect("cassette lock poisoned");
+            return texts
+                .into_iter()
+                .map(|document| {
+                    let key = sha256_hex(&document);
+                    let vec = recording.embeddings.get(&key).cloned().ok_or_else(|| {
+                        EmbeddingError::ProviderError(self.cassette.missing("embedding", &key))
+                    })?;
+                    Ok(Embedding { document, vec })
+                })
+                .collect();
+        }
+
+        let embeddings = self.model.embed_texts(texts).await?;
+        let mut recording = self
+            .cassette
+            .recording
+            .lock()
+            .expect("cassette lock poisoned");
+        for embedding in &embeddings {
+            recording
+                .embeddings
+                .insert(sha256_hex(&embedding.document), embedding.vec.clone());
+        }
+        self.cassette
+            .save(&recording)
+            .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+        Ok(embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock_model::{MockCompletionModel, MockEmbeddingModel};
+    use rig::completion::Prompt;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixtures").join("cassette.json");
+
+        let cassette = Arc::new(Cassette::open(&path, VcrMode::Record).unwrap());
+        let model = VcrCompletionModel::new(
+            MockCompletionModel::with_text_response("recorded"),
+            cassette.clone(),
+        );
+        let embedder = VcrEmbeddingModel::new(MockEmbeddingModel::new(), cassette);
+        let agent = rig::agent::AgentBuilder::new(model).build();
+        assert_eq!(agent.prompt("Summarize").await.unwrap(), "recorded");
+        let recorded = embedder
+            .embed_texts(vec!["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+
+        let cassette = Arc::new(Cassette::open(&path, VcrMode::Replay).unwrap());
+        let model = VcrCompletionModel::new(
+            MockCompletionModel::with_text_response("live"),
+            cassette.clone(),
+        );
+        let embedder = VcrEmbeddingModel::new(MockEmbeddingModel::new(), cassette);
+        let agent = rig::agent::AgentBuilder::new(model).build();
+        assert_eq!(agent.prompt("Summarize").await.unwrap(), "recorded");
+        // Texts replay one by one, however they are batched
+        let replayed = embedder.embed_text("b").await.unwrap();
+        assert_eq!(replayed.vec, recorded[1].vec);
+
+        let missing = agent.prompt("Something new").await.unwrap_err();
+        assert!(missing.to_string().contains("HAL_VCR=record"));
+        assert!(embedder.embed_text("c").await.is_err());
+        assert!(Cassette::open(dir.path().join("missing.json"), VcrMode::Replay).is_err());
+        assert!("rewind".parse::<VcrMode>().is_err());
+    }
+}
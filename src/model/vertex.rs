@@ -0,0 +1,425 @@
+//! # Vertex AI Module
+//!
+//! This module calls Gemini models hosted on Google Cloud's Vertex AI, authenticated with
+//! Application Default Credentials rather than an API key, so projects with Vertex quotas
+//! and data residency requirements can index and search with HAL.
+//!
+//! ## Key Components
+//!
+//! - `VertexConfig`: The `[vertex]` table of `hal.toml`
+//! - `VertexClient`: Sends requests to a project and location with fresh access tokens
+//! - `VertexCompletionModel`: A Gemini completion model on Vertex AI
+//! - `VertexEmbeddingModel`: A text embedding model on Vertex AI
+//! - `auth`: Application Default Credentials and access token refresh
+//!
+//! ## Example
+//!
+//! ```toml
+//! provider = "vertex"
+//!
+//! [vertex]
+//! project = "docs-project"
+//! location = "europe-west4"
+//!
+//! [embedding]
+//! provider = "vertex"
+//! ```
+//!
+//! The project and location fall back to `GOOGLE_CLOUD_PROJECT` and `GOOGLE_CLOUD_LOCATION`,
+//! then to the project of the credentials and `us-central1`.
+
+pub mod auth;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rig::OneOrMany;
+use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest};
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use rig::providers::gemini::completion::gemini_api_types::{
+    Content, GenerateContentRequest, GenerateContentResponse, GenerationConfig, Tool,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::debug;
+
+pub use auth::{AuthError, Credentials, TokenProvider};
+
+/// Location used when none is configured
+pub const DEFAULT_LOCATION: &str = "us-central1";
+
+/// Vertex AI project, location and credentials
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VertexConfig {
+    /// Google Cloud project ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+
+    /// Region of the models, e.g. `us-central1`, or `global`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+
+    /// Service account key or `authorized_user` credentials file, taking precedence over
+    /// `GOOGLE_APPLICATION_CREDENTIALS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<PathBuf>,
+}
+
+/// Error for a failed request, keeping the status and body of the response
+async fn response_error(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    format!("Vertex AI returned {}: {}", status, body.trim())
+}
+
+/// Client of the Vertex AI models of a project and location
+#[derive(Debug, Clone)]
+pub struct VertexClient {
+    project: String,
+    location: String,
+    base_url: String,
+    tokens: Arc<TokenProvider>,
+    client: reqwest::Client,
+}
+
+impl VertexClient {
+    /// Create a client for a project and location
+    pub fn new(project: &str, location: &str, credentials: Credentials) -> Self {
+        let base_url = match location {
+            "global" => "https://aiplatform.googleapis.com".to_string(),
+            region => format!("https://{}-aiplatform.googleapis.com", region),
+        };
+        Self {
+            project: project.to_string(),
+            location: location.to_string(),
+            base_url,
+            tokens: Arc::new(TokenProvider::new(credentials)),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a client from a configuration, the environment and Application Default
+    /// Credentials
+    ///
+    /// # Errors
+    ///
+    /// Fails if the credentials file can't be read or no project is configured.
+    pub fn from_config(config: &VertexConfig) -> Result<Self, AuthError> {
+        let credentials = Credentials::application_default(config.credentials.as_deref())?;
+        let project = config
+            .project
+            .clone()
+            .or_else(|| std::env::var("GOOGLE_CLOUD_PROJECT").ok())
+            .or_else(|| credentials.project_id().map(str::to_string))
+            .ok_or_else(|| {
+                AuthError::NotConfigured("set [vertex] project or GOOGLE_CLOUD_PROJECT".to_string())
+            })?;
+        let location = config
+            .location
+            .clone()
+            .or_else(|| std::env::var("GOOGLE_CLOUD_LOCATION").ok())
+            .unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+        Ok(Self::new(&project, &location, credentials))
+    }
+
+    /// Send requests to a different endpoint, e.g. a private service connect endpoint
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Use a different token provider
+    pub fn with_tokens(mut self, tokens: TokenProvider) -> Self {
+        self.tokens = Arc::new(tokens);
+        self
+    }
+
+    /// Endpoint requests are sent to
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Credentials requests are authenticated with
+    pub fn credentials(&self) -> &Credentials {
+        self.tokens.credentials()
+    }
+
+    /// A completion model, e.g. `gemini-2.0-flash`
+    pub fn completion_model(&self, model: &str) -> VertexCompletionModel {
+        VertexCompletionModel {
+            client: self.clone(),
+            model: model.to_string(),
+        }
+    }
+
+    /// An embedding model, e.g. `text-embedding-004`
+    pub fn embedding_model(&self, model: &str, ndims: usize) -> VertexEmbeddingModel {
+        VertexEmbeddingModel {
+            client: self.clone(),
+            model: model.to_string(),
+            ndims,
+        }
+    }
+
+    /// URL of a method of a Google model
+    fn model_url(&self, model: &str, method: &str) -> String {
+        format!(
+            "{}/v1/projects/{}/locations/{}/publishers/google/models/{}:{}",
+            self.base_url, self.project, self.location, model, method
+        )
+    }
+
+    /// POST a JSON body with a fresh access token
+    async fn post(&self, url: &str, body: &impl Serialize) -> Result<reqwest::Response, String> {
+        let token = self.tokens.token().await.map_err(|e| e.to_string())?;
+        debug!("POST {}", url);
+        self.client
+            .post(url)
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A Gemini completion model hosted on Vertex AI
+///
+/// Requests and responses have the same shape as those of the Gemini API, so responses
+/// are `GenerateContentResponse`s like those of `rig`'s Gemini models.
+#[derive(Debug, Clone)]
+pub struct VertexCompletionModel {
+    client: VertexClient,
+    model: String,
+}
+
+impl VertexCompletionModel {
+    /// Name of the model
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+impl CompletionModel for VertexCompletionModel {
+    type Response = GenerateContentResponse;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let body = request_body(request)?;
+        let url = self.client.model_url(&self.model, "generateContent");
+        let response = self
+            .client
+            .post(&url, &body)
+            .await
+            .map_err(CompletionError::ProviderError)?;
+        if !response.status().is_success() {
+            return Err(CompletionError::ProviderError(
+                response_error(response).await,
+            ));
+        }
+        let response = response
+            .json::<GenerateContentResponse>()
+            .await
+            .map_err(|e| CompletionError::ResponseError(e.to_string()))?;
+        completion::CompletionResponse::try_from(response)
+    }
+}
+
+/// Body of a `generateContent` request, as the Gemini API builds it
+fn request_body(request: CompletionRequest) -> Result<GenerateContentRequest, CompletionError> {
+    let prompt = request.prompt_with_context();
+    let contents = request
+        .chat_history
+        .into_iter()
+        .chain(std::iter::once(prompt))
+        .map(|message| {
+            Content::try_from(message).map_err(|e| CompletionError::RequestError(Box::new(e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let params = request
+        .additional_params
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    let mut generation_config = serde_json::from_value::<GenerationConfig>(params)?;
+    if let Some(temperature) = request.temperature {
+        generation_config.temperature = Some(temperature);
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        generation_config.max_output_tokens = Some(max_tokens);
+    }
+
+    let tools = request
+        .tools
+        .into_iter()
+        .map(Tool::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(GenerateContentRequest {
+        contents,
+        tools: (!tools.is_empty()).then_some(tools),
+        tool_config: None,
+        generation_config: Some(generation_config),
+        safety_settings: None,
+        system_instruction: request.preamble.map(|preamble| Content {
+            parts: OneOrMany::one(preamble.into()),
+            role: None,
+        }),
+    })
+}
+
+/// A text embedding model hosted on Vertex AI
+#[derive(Debug, Clone)]
+pub struct VertexEmbeddingModel {
+    client: VertexClient,
+    model: String,
+    ndims: usize,
+}
+
+/// Response of the `predict` method of an embedding model
+#[derive(Debug, Deserialize)]
+struct PredictResponse {
+    predictions: Vec<Prediction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Prediction {
+    embeddings: PredictionEmbedding,
+}
+
+#[derive(Debug, Deserialize)]
+struct PredictionEmbedding {
+    values: Vec<f64>,
+}
+
+impl EmbeddingModel for VertexEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 64;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts: Vec<String> = texts.into_iter().collect();
+        let instances: Vec<Value> = texts
+            .iter()
+            .map(|text| serde_json::json!({ "content": text }))
+            .collect();
+        let body = serde_json::json!({
+            "instances": instances,
+            "parameters": { "outputDimensionality": self.ndims },
+        });
+        let url = self.client.model_url(&self.model, "predict");
+        let response = self
+            .client
+            .post(&url, &body)
+            .await
+            .map_err(EmbeddingError::ProviderError)?;
+        if !response.status().is_success() {
+            return Err(EmbeddingError::ProviderError(
+                response_error(response).await,
+            ));
+        }
+        let response = response
+            .json::<PredictResponse>()
+            .await
+            .map_err(|e| EmbeddingError::ResponseError(e.to_string()))?;
+        if response.predictions.len() != texts.len() {
+            return Err(EmbeddingError::ResponseError(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                response.predictions.len()
+            )));
+        }
+        Ok(texts
+            .into_iter()
+            .zip(response.predictions)
+            .map(|(document, prediction)| Embedding {
+                document,
+                vec: prediction.embeddings.values,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::message::Message;
+
+    #[tokio::test]
+    async fn test_vertex_models() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/token")
+            .with_body(r#"{"access_token": "ya29.token", "expires_in": 3599}"#)
+            .create_async()
+            .await;
+        let path = "/v1/projects/docs/locations/europe-west4/publishers/google/models";
+        let completion = server
+            .mock(
+                "POST",
+                format!("{}/gemini-2.0-flash:generateContent", path).as_str(),
+            )
+            .match_header("authorization", "Bearer ya29.token")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "systemInstruction": { "parts": [{ "text": "Be brief." }] },
+                "generationConfig": { "temperature": 0.2 },
+            })))
+            .with_body(
+                r#"{"candidates": [{"content": {"role": "model", "parts": [{"text": "Hi"}]}, "index": 0}]}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock(
+                "POST",
+                format!("{}/text-embedding-004:predict", path).as_str(),
+            )
+            .match_header("authorization", "Bearer ya29.token")
+            .with_body(r#"{"predictions": [{"embeddings": {"values": [0.5, 0.25]}}]}"#)
+            .create_async()
+            .await;
+
+        let tokens = TokenProvider::new(Credentials::Metadata)
+            .with_metadata_url(&format!("{}/token", server.url()));
+        let client = VertexClient::new("docs", "europe-west4", Credentials::Metadata)
+            .with_base_url(&server.url())
+            .with_tokens(tokens);
+
+        let request = CompletionRequest {
+            prompt: Message::user("Hello"),
+            preamble: Some("Be brief.".to_string()),
+            chat_history: Vec::new(),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            additional_params: None,
+        };
+        let response = client
+            .completion_model("gemini-2.0-flash")
+            .completion(request)
+            .await
+            .unwrap();
+        completion.assert_async().await;
+        assert_eq!(response.raw_response.candidates.len(), 1);
+
+        let embedding = client
+            .embedding_model("text-embedding-004", 2)
+            .embed_text("Hello")
+            .await
+            .unwrap();
+        assert_eq!(embedding.vec, vec![0.5, 0.25]);
+
+        let missing = client
+            .embedding_model("missing", 2)
+            .embed_text("Hello")
+            .await;
+        assert!(matches!(missing, Err(EmbeddingError::ProviderError(_))));
+    }
+}
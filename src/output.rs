@@ -0,0 +1,144 @@
+//! # CLI Output Module
+//!
+//! This module routes the CLI's progress messages and results through one place, so
+//! `--log-format json` can turn them into JSON lines for orchestration systems.
+//!
+//! ## Key Components
+//!
+//! - `LogFormat`: Human-readable text or JSON lines
+//! - `out!`: Prints a progress message, like `println!`
+//! - `emit!`: Prints a progress message with an event name and structured fields
+//! - `note!`: Prints a decorative message that `--quiet` suppresses
+//! - `print_json`: Prints a command's JSON result
+//!
+//! ## JSON Lines
+//!
+//! Every line is an object with `event`, `timestamp` (RFC 3339), `message` and `fields`.
+//! Plain messages have the event `message` and empty fields; results printed with
+//! `print_json` have the event `result` and the result as `fields`. Empty messages,
+//! used as spacing in text output, are skipped.
+//!
+//! ## Quiet Mode
+//!
+//! With `--quiet`, decorative output such as "Crawling..." banners, summary tables, hints
+//! and progress bars is suppressed, so only results, warnings and errors remain.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+/// How the CLI writes progress and results to stdout
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+
+    /// One JSON object per line
+    Json,
+}
+
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+/// Set the output format for the rest of the process; only the first call has an effect
+pub fn set_format(format: LogFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Whether output is written as JSON lines
+pub fn is_json() -> bool {
+    FORMAT.get().copied().unwrap_or_default() == LogFormat::Json
+}
+
+/// Suppress decorative output for the rest of the process; only the first call has an effect
+pub fn set_quiet(quiet: bool) {
+    let _ = QUIET.set(quiet);
+}
+
+/// Whether decorative output is suppressed
+pub fn is_quiet() -> bool {
+    QUIET.get().copied().unwrap_or_default()
+}
+
+/// Whether progress bars may be drawn; they would interleave with JSON lines
+pub fn show_progress() -> bool {
+    !is_json() && !is_quiet()
+}
+
+/// The JSON line for an event
+fn json_line(event: &str, message: &str, fields: serde_json::Value) -> String {
+    serde_json::json!({
+        "event": event,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "message": message,
+        "fields": fields,
+    })
+    .to_string()
+}
+
+/// Print a message, as a JSON line with the event and fields in JSON mode
+pub fn emit(event: &str, message: String, fields: serde_json::Value) {
+    if !is_json() {
+        println!("{}", message);
+    } else if !message.is_empty() || event != "message" {
+        println!("{}", json_line(event, &message, fields));
+    }
+}
+
+/// Print a command's result, pretty-printed in text mode
+pub fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+    if is_json() {
+        println!("{}", json_line("result", "", serde_json::to_value(value)?));
+    } else {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    }
+    Ok(())
+}
+
+/// Print a progress message, like `println!`
+macro_rules! out {
+    () => {
+        $crate::output::emit("message", String::new(), serde_json::json!({}))
+    };
+    ($($arg:tt)+) => {
+        $crate::output::emit("message", format!($($arg)+), serde_json::json!({}))
+    };
+}
+
+/// Print a decorative message, like `out!`, unless `--quiet` is set
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            out!($($arg)*)
+        }
+    };
+}
+
+/// Print a progress message with an event name and fields for JSON output
+///
+/// `emit!("page_indexed", { "url": url, "chunks": n }, "Indexed {} chunks from {}", n, url)`
+macro_rules! emit {
+    ($event:literal, { $($fields:tt)* }, $($arg:tt)+) => {
+        $crate::output::emit($event, format!($($arg)+), serde_json::json!({ $($fields)* }))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_line() {
+        let line: serde_json::Value = serde_json::from_str(&json_line(
+            "page_indexed",
+            "Indexed 3 chunks",
+            serde_json::json!({ "chunks": 3 }),
+        ))
+        .unwrap();
+        assert_eq!(line["event"], "page_indexed");
+        assert_eq!(line["message"], "Indexed 3 chunks");
+        assert_eq!(line["fields"]["chunks"], 3);
+        assert!(chrono::DateTime::parse_from_rfc3339(line["timestamp"].as_str().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,340 @@
+//! # Plugin Module
+//!
+//! This module lets company-specific connectors, extractors and tools be added to HAL
+//! without forking the crate.
+//!
+//! ## Key Components
+//!
+//! - `SourcePlugin`: Fetches the pages of `scheme://location` index sources
+//! - `ExtractorPlugin`: Converts the HTML of matching pages to Markdown in place of the
+//!   built-in extraction
+//! - `ToolPlugin`: A tool offered by the MCP server next to the built-in tools
+//! - `PluginRegistry`: The registered plugins, looked up by the index, crawl and MCP paths
+//! - `CommandPlugin`: A plugin run as an external command, declared in `hal.toml`
+//!
+//! ## Registration
+//!
+//! Applications embedding HAL register plugins compiled into them, e.g. behind their own
+//! cargo features, in the process-wide registry:
+//!
+//! ```rust,no_run
+//! use hal::crawler::CrawledPage;
+//! use hal::plugin::{self, BoxFuture, PluginError, SourcePlugin};
+//!
+//! struct Jira;
+//!
+//! impl SourcePlugin for Jira {
+//!     fn name(&self) -> &str {
+//!         "jira"
+//!     }
+//!
+//!     fn handles(&self, scheme: &str) -> bool {
+//!         scheme == "jira"
+//!     }
+//!
+//!     fn fetch<'a>(
+//!         &'a self,
+//!         source: &'a str,
+//!         max_pages: u32,
+//!     ) -> BoxFuture<'a, Result<Vec<CrawledPage>, PluginError>> {
+//!         // Fetch the issues of the project in `jira://PROJ`
+//!         Box::pin(async move { Ok(Vec::new()) })
+//!     }
+//! }
+//!
+//! plugin::register_source(Jira);
+//! ```
+//!
+//! The `hal` binary registers the command plugins listed under `[[plugins]]` in `hal.toml`
+//! at startup, so users of the released binary can add plugins too:
+//!
+//! ```toml
+//! [[plugins]]
+//! name = "corp"
+//! command = "hal-corp-plugin"
+//! # Index sources such as jira://PROJ
+//! schemes = ["jira"]
+//! # Pages whose URL starts with a prefix are extracted by the plugin
+//! extract_urls = ["https://wiki.corp.example.com/"]
+//!
+//! [[plugins.tools]]
+//! name = "lookup_employee"
+//! description = "Look up an employee by name"
+//! parameters = { type = "object", properties = { name = { type = "string" } } }
+//! ```
+//!
+//! Plugins registered later take precedence over earlier ones for the same source or URL.
+
+mod command;
+pub mod error;
+
+pub use command::{CommandPlugin, PluginConfig, ToolSpec};
+pub use error::PluginError;
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use crate::crawler::CrawledPage;
+
+/// Future returned by plugins, boxed so plugin traits can be used as trait objects
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Source of pages for index sources like `jira://PROJ`
+pub trait SourcePlugin: Send + Sync {
+    /// Name of the plugin, used in logs and errors
+    fn name(&self) -> &str;
+
+    /// Whether the plugin fetches sources with this scheme, e.g. `jira`
+    fn handles(&self, scheme: &str) -> bool;
+
+    /// Fetch the pages of a source
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The index source, e.g. `jira://PROJ`
+    /// * `max_pages` - Most pages to return
+    fn fetch<'a>(
+        &'a self,
+        source: &'a str,
+        max_pages: u32,
+    ) -> BoxFuture<'a, Result<Vec<CrawledPage>, PluginError>>;
+}
+
+/// Converter of the HTML of particular pages to Markdown
+///
+/// Extraction runs while pages are crawled, so it is synchronous.
+pub trait ExtractorPlugin: Send + Sync {
+    /// Name of the plugin, used in logs and errors
+    fn name(&self) -> &str;
+
+    /// Whether the plugin extracts the page at this URL
+    fn matches(&self, url: &str) -> bool;
+
+    /// The Markdown content of a page, or `None` to leave it to the built-in extraction
+    fn extract(&self, url: &str, html: &str) -> Result<Option<String>, PluginError>;
+}
+
+/// Tool offered to MCP clients
+pub trait ToolPlugin: Send + Sync {
+    /// Name of the tool
+    fn name(&self) -> &str;
+
+    /// Description of the tool shown to models
+    fn description(&self) -> &str;
+
+    /// JSON schema of the tool's arguments
+    fn parameters(&self) -> serde_json::Value;
+
+    /// Call the tool, returning its output as text
+    fn call(&self, arguments: serde_json::Value) -> BoxFuture<'_, Result<String, PluginError>>;
+}
+
+/// Registered plugins
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    sources: Vec<Arc<dyn SourcePlugin>>,
+    extractors: Vec<Arc<dyn ExtractorPlugin>>,
+    tools: Vec<Arc<dyn ToolPlugin>>,
+}
+
+impl fmt::Debug for PluginRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginRegistry")
+            .field(
+                "sources",
+                &self.sources.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .field(
+                "extractors",
+                &self.extractors.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .field(
+                "tools",
+                &self.tools.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl PluginRegistry {
+    /// Add a source plugin
+    pub fn register_source(&mut self, plugin: Arc<dyn SourcePlugin>) {
+        self.sources.push(plugin);
+    }
+
+    /// Add an extractor plugin
+    pub fn register_extractor(&mut self, plugin: Arc<dyn ExtractorPlugin>) {
+        self.extractors.push(plugin);
+    }
+
+    /// Add a tool plugin
+    pub fn register_tool(&mut self, plugin: Arc<dyn ToolPlugin>) {
+        self.tools.push(plugin);
+    }
+
+    /// Add the sources, extractors and tools of a command plugin
+    pub fn register_command(&mut self, config: PluginConfig) {
+        let plugin = Arc::new(CommandPlugin::new(config));
+        if !plugin.config().schemes.is_empty() {
+            self.register_source(plugin.clone());
+        }
+        if !plugin.config().extract_urls.is_empty() {
+            self.register_extractor(plugin.clone());
+        }
+        for tool in plugin.tools() {
+            self.register_tool(Arc::new(tool));
+        }
+    }
+
+    /// The plugin fetching an index source
+    ///
+    /// Returns `None` for sources without a scheme or whose scheme no plugin handles.
+    pub fn source(&self, source: &str) -> Option<Arc<dyn SourcePlugin>> {
+        let (scheme, _) = source.split_once("://")?;
+        self.sources
+            .iter()
+            .rev()
+            .find(|plugin| plugin.handles(scheme))
+            .cloned()
+    }
+
+    /// The Markdown content of a page from the latest matching extractor that returns one
+    ///
+    /// Extractor errors are logged and the next extractor is tried, so a broken plugin
+    /// leaves the page to the built-in extraction.
+    pub fn extract(&self, url: &str, html: &str) -> Option<String> {
+        self.extractors
+            .iter()
+            .rev()
+            .filter(|plugin| plugin.matches(url))
+            .find_map(|plugin| match plugin.extract(url, html) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("Extractor {} failed on {}: {}", plugin.name(), url, e);
+                    None
+                }
+            })
+    }
+
+    /// The registered tools
+    pub fn tools(&self) -> &[Arc<dyn ToolPlugin>] {
+        &self.tools
+    }
+
+    /// The tool with a name
+    pub fn tool(&self, name: &str) -> Option<Arc<dyn ToolPlugin>> {
+        self.tools
+            .iter()
+            .rev()
+            .find(|tool| tool.name() == name)
+            .cloned()
+    }
+
+    /// Whether no plugin is registered
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty() && self.extractors.is_empty() && self.tools.is_empty()
+    }
+}
+
+/// The process-wide registry
+fn global() -> &'static RwLock<PluginRegistry> {
+    static REGISTRY: OnceLock<RwLock<PluginRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(RwLock::default)
+}
+
+/// Change the process-wide registry
+fn update(change: impl FnOnce(&mut PluginRegistry)) {
+    change(&mut global().write().unwrap_or_else(PoisonError::into_inner));
+}
+
+/// Add a source plugin to the process-wide registry
+pub fn register_source(plugin: impl SourcePlugin + 'static) {
+    update(|registry| registry.register_source(Arc::new(plugin)));
+}
+
+/// Add an extractor plugin to the process-wide registry
+pub fn register_extractor(plugin: impl ExtractorPlugin + 'static) {
+    update(|registry| registry.register_extractor(Arc::new(plugin)));
+}
+
+/// Add a tool plugin to the process-wide registry
+pub fn register_tool(plugin: impl ToolPlugin + 'static) {
+    update(|registry| registry.register_tool(Arc::new(plugin)));
+}
+
+/// Add command plugins to the process-wide registry
+pub fn register_commands(configs: &[PluginConfig]) {
+    update(|registry| {
+        for config in configs {
+            registry.register_command(config.clone());
+        }
+    });
+}
+
+/// A snapshot of the process-wide registry
+pub fn registry() -> PluginRegistry {
+    global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Prefix(&'static str, Option<&'static str>);
+
+    impl ExtractorPlugin for Prefix {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn matches(&self, url: &str) -> bool {
+            url.starts_with(self.0)
+        }
+
+        fn extract(&self, _url: &str, _html: &str) -> Result<Option<String>, PluginError> {
+            Ok(self.1.map(str::to_string))
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = PluginRegistry::default();
+        assert!(registry.is_empty());
+        registry.register_command(PluginConfig {
+            name: "corp".to_string(),
+            command: "hal-corp-plugin".to_string(),
+            schemes: vec!["jira".to_string()],
+            tools: vec![ToolSpec {
+                name: "lookup".to_string(),
+                description: "Look up".to_string(),
+                parameters: serde_json::json!({ "type": "object" }),
+            }],
+            ..PluginConfig::default()
+        });
+
+        assert_eq!(registry.source("jira://PROJ").unwrap().name(), "corp");
+        assert!(registry.source("https://example.com").is_none());
+        assert!(registry.source("jira").is_none());
+        assert_eq!(registry.tool("lookup").unwrap().description(), "Look up");
+        assert!(registry.tool("other").is_none());
+
+        // The latest matching extractor with content wins
+        registry.register_extractor(Arc::new(Prefix("https://wiki", Some("old"))));
+        registry.register_extractor(Arc::new(Prefix("https://wiki/team", None)));
+        registry.register_extractor(Arc::new(Prefix("https://wiki/docs", Some("new"))));
+        assert_eq!(
+            registry.extract("https://wiki/docs/a", "<p>").as_deref(),
+            Some("new")
+        );
+        assert_eq!(
+            registry.extract("https://wiki/team/a", "<p>").as_deref(),
+            Some("old")
+        );
+        assert_eq!(registry.extract("https://example.com", "<p>"), None);
+    }
+}
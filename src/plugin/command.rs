@@ -0,0 +1,341 @@
+//! Plugins run as external commands.
+//!
+//! Each operation runs the plugin's command once, writes a JSON request to its stdin and
+//! reads a JSON response from its stdout. Requests carry an `operation`:
+//!
+//! - `fetch` with `source` and `max_pages`, answered with `{"pages": [...]}`, pages in the
+//!   shape of `CrawledPage`
+//! - `extract` with `url` and `html`, answered with `{"content": "..."}` or
+//!   `{"content": null}` to leave the page to the built-in extraction
+//! - `call` with `tool` and `arguments`, answered with `{"output": "..."}`
+//!
+//! A response with an `error` string, or a non-zero exit status, fails the operation.
+
+use std::io::Write;
+use std::process::{Output, Stdio};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use super::{BoxFuture, ExtractorPlugin, PluginError, SourcePlugin, ToolPlugin};
+use crate::crawler::CrawledPage;
+
+/// A plugin run as an external command, as declared under `[[plugins]]` in `hal.toml`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Name of the plugin
+    pub name: String,
+
+    /// Command to run
+    pub command: String,
+
+    /// Arguments of the command
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Schemes of the index sources the plugin fetches, e.g. `jira` for `jira://PROJ`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schemes: Vec<String>,
+
+    /// URL prefixes of the pages the plugin extracts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extract_urls: Vec<String>,
+
+    /// Tools the plugin offers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+}
+
+/// A tool of a command plugin
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    /// Name of the tool
+    pub name: String,
+
+    /// Description of the tool shown to models
+    pub description: String,
+
+    /// JSON schema of the tool's arguments
+    #[serde(default = "empty_schema")]
+    pub parameters: serde_json::Value,
+}
+
+/// Schema of a tool without arguments
+fn empty_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Request written to a plugin command
+#[derive(Debug, Serialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+enum Request<'a> {
+    Fetch {
+        source: &'a str,
+        max_pages: u32,
+    },
+    Extract {
+        url: &'a str,
+        html: &'a str,
+    },
+    Call {
+        tool: &'a str,
+        arguments: &'a serde_json::Value,
+    },
+}
+
+/// Response read from a plugin command; only the field of the operation is set
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Response {
+    pages: Vec<CrawledPage>,
+    content: Option<String>,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+/// A plugin run as an external command
+#[derive(Debug, Clone)]
+pub struct CommandPlugin {
+    config: PluginConfig,
+}
+
+impl CommandPlugin {
+    /// Create a plugin from its configuration
+    pub fn new(config: PluginConfig) -> Self {
+        Self { config }
+    }
+
+    /// The configuration of the plugin
+    pub fn config(&self) -> &PluginConfig {
+        &self.config
+    }
+
+    /// The tools of the plugin
+    pub fn tools(&self) -> Vec<CommandTool> {
+        self.config
+            .tools
+            .iter()
+            .map(|spec| CommandTool {
+                plugin: self.clone(),
+                spec: spec.clone(),
+            })
+            .collect()
+    }
+
+    /// Run the command with a request
+    async fn run(&self, request: &Request<'_>) -> Result<Response, PluginError> {
+        let input = self.encode(request)?;
+        let mut child = tokio::process::Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| self.io_error(e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(&input)
+                .await
+                .map_err(|e| self.io_error(e))?;
+        }
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| self.io_error(e))?;
+        self.decode(output)
+    }
+
+    /// Run the command with a request, blocking the thread
+    fn run_blocking(&self, request: &Request<'_>) -> Result<Response, PluginError> {
+        let input = self.encode(request)?;
+        let mut child = std::process::Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.io_error(e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&input).map_err(|e| self.io_error(e))?;
+        }
+        let output = child.wait_with_output().map_err(|e| self.io_error(e))?;
+        self.decode(output)
+    }
+
+    fn encode(&self, request: &Request<'_>) -> Result<Vec<u8>, PluginError> {
+        serde_json::to_vec(request).map_err(|e| PluginError::Protocol {
+            plugin: self.config.name.clone(),
+            message: e.to_string(),
+        })
+    }
+
+    fn decode(&self, output: Output) -> Result<Response, PluginError> {
+        if !output.status.success() {
+            return Err(PluginError::Failed {
+                plugin: self.config.name.clone(),
+                message: format!(
+                    "{}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+        let response: Response =
+            serde_json::from_slice(&output.stdout).map_err(|e| PluginError::Protocol {
+                plugin: self.config.name.clone(),
+                message: e.to_string(),
+            })?;
+        match response.error {
+            Some(message) => Err(PluginError::Failed {
+                plugin: self.config.name.clone(),
+                message,
+            }),
+            None => Ok(response),
+        }
+    }
+
+    fn io_error(&self, source: std::io::Error) -> PluginError {
+        PluginError::Io {
+            plugin: self.config.name.clone(),
+            source,
+        }
+    }
+}
+
+impl SourcePlugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn handles(&self, scheme: &str) -> bool {
+        self.config.schemes.iter().any(|handled| handled == scheme)
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        source: &'a str,
+        max_pages: u32,
+    ) -> BoxFuture<'a, Result<Vec<CrawledPage>, PluginError>> {
+        Box::pin(async move {
+            let mut pages = self.run(&Request::Fetch { source, max_pages }).await?.pages;
+            pages.truncate(max_pages as usize);
+            Ok(pages)
+        })
+    }
+}
+
+impl ExtractorPlugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        self.config
+            .extract_urls
+            .iter()
+            .any(|prefix| url.starts_with(prefix.as_str()))
+    }
+
+    fn extract(&self, url: &str, html: &str) -> Result<Option<String>, PluginError> {
+        Ok(self.run_blocking(&Request::Extract { url, html })?.content)
+    }
+}
+
+/// A tool of a command plugin
+#[derive(Debug, Clone)]
+pub struct CommandTool {
+    plugin: CommandPlugin,
+    spec: ToolSpec,
+}
+
+impl ToolPlugin for CommandTool {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        self.spec.parameters.clone()
+    }
+
+    fn call(&self, arguments: serde_json::Value) -> BoxFuture<'_, Result<String, PluginError>> {
+        Box::pin(async move {
+            let response = self
+                .plugin
+                .run(&Request::Call {
+                    tool: &self.spec.name,
+                    arguments: &arguments,
+                })
+                .await?;
+            Ok(response.output.unwrap_or_default())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plugin answering every request with a fixed response
+    fn plugin(response: &str) -> CommandPlugin {
+        CommandPlugin::new(PluginConfig {
+            name: "test".to_string(),
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("cat > /dev/null; printf '%s' '{}'", response),
+            ],
+            schemes: vec!["jira".to_string()],
+            extract_urls: vec!["https://wiki.example.com/".to_string()],
+            tools: vec![ToolSpec {
+                name: "lookup".to_string(),
+                description: "Look up".to_string(),
+                parameters: empty_schema(),
+            }],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_command_plugin() {
+        let page = serde_json::json!({
+            "url": "jira://PROJ/1",
+            "content": "Issue",
+            "metadata": { "domain": "jira" }
+        });
+        let pages = plugin(&serde_json::json!({ "pages": [page, page] }).to_string())
+            .fetch("jira://PROJ", 1)
+            .await
+            .unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].content, "Issue");
+
+        let extractor = plugin(r##"{"content": "# Title"}"##);
+        assert!(extractor.matches("https://wiki.example.com/page"));
+        assert!(!extractor.matches("https://example.com/page"));
+        assert_eq!(
+            extractor
+                .extract("https://wiki.example.com/page", "<h1>")
+                .unwrap(),
+            Some("# Title".to_string())
+        );
+
+        let tools = plugin(r#"{"output": "Ada"}"#).tools();
+        assert_eq!(tools[0].call(serde_json::json!({})).await.unwrap(), "Ada");
+
+        let failing = plugin(r#"{"error": "no access"}"#);
+        assert!(matches!(
+            failing.fetch("jira://PROJ", 10).await,
+            Err(PluginError::Failed { .. })
+        ));
+        let garbled = plugin("not json");
+        assert!(matches!(
+            garbled.extract("https://wiki.example.com/page", ""),
+            Err(PluginError::Protocol { .. })
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+//! # Plugin Error Types Module
+//!
+//! This module defines the errors of plugins and of running command plugins.
+
+use thiserror::Error;
+
+/// Error type for plugin operations
+#[derive(Debug, Error)]
+pub enum PluginError {
+    /// The plugin command could not be run
+    #[error("Failed to run plugin {plugin}: {source}")]
+    Io {
+        /// Name of the plugin
+        plugin: String,
+        /// The underlying error
+        source: std::io::Error,
+    },
+
+    /// The plugin ran but reported an error
+    #[error("Plugin {plugin} failed: {message}")]
+    Failed {
+        /// Name of the plugin
+        plugin: String,
+        /// The reported error
+        message: String,
+    },
+
+    /// The plugin's response could not be understood
+    #[error("Plugin {plugin} sent an invalid response: {message}")]
+    Protocol {
+        /// Name of the plugin
+        plugin: String,
+        /// What was wrong with the response
+        message: String,
+    },
+}
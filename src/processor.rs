@@ -10,19 +10,30 @@
 //! - `ProcessedChunk`: A fully processed chunk with embedding and context
 //! - `ChunkOptions`: Configuration for text chunking behavior
 //! - `ProcessorConfig`: Complete configuration for the processor pipeline
+//! - `ChunkOverrides`: Per-page or per-source changes to the chunk options
+//! - `FrontMatter`: Metadata parsed from YAML front matter of Markdown sources
+//! - `code`: Splits source files into chunks per function, struct or class, with their
+//!   line ranges
+//! - `redaction`: Masks emails, phone numbers and keys before content is stored
+//! - `safety`: Drops or flags pages and chunks failing safety checks
+//! - `segmentation`: Splits long pages into sections per H1/H2 heading, processed as
+//!   separate documents
 //!
 //! ## Features
 //!
 //! - Smart text chunking that respects document structure (paragraphs, code blocks, headings)
-//! - LLM-powered context generation for improved semantic understanding
+//! - LLM-powered context generation for improved semantic understanding, per chunk, in
+//!   batches, or from templates without LLM calls
 //! - Parallel processing with rate limiting and concurrency controls
 //! - Flexible configuration for different content types and embedding strategies
 //! - Support for document metadata preservation throughout the processing pipeline
 //!
 //! ## Processing Pipeline
 //!
-//! 1. Chunk raw content into semantically coherent segments
-//! 2. Generate summaries and context for each chunk using LLMs
+//! 1. Extract front matter and chunk raw content into semantically coherent segments,
+//!    optionally per section of the page
+//! 2. Generate summaries of each page or section and context for each chunk using LLMs,
+//!    unless summaries are skipped to index with embedding calls only
 //! 3. Create embeddings that combine both content and context
 //! 4. Preserve source information and metadata
 //!
@@ -30,14 +41,29 @@
 //! and contextually enriched before being stored in the vector database.
 
 mod chunking;
+pub mod code;
 mod config;
 mod error;
+mod front_matter;
 mod llm_integration;
+pub mod redaction;
+pub mod safety;
+pub mod segmentation;
 
-pub use chunking::{TextChunk, chunk_markdown};
-pub use config::{ChunkOptions, ProcessorConfig};
+pub use chunking::{
+    TextChunk, chunk_markdown, chunk_markdown_iter, chunk_markdown_stream, count_words,
+    format_breadcrumb, render_chunks,
+};
+pub use config::{ChunkOptions, ChunkOverrides, ProcessorConfig, SourceChunkOverride};
 pub use error::ProcessError;
-pub use llm_integration::{generate_context_string, generate_summary};
+pub use front_matter::{FrontMatter, apply_front_matter, parse_front_matter};
+pub use llm_integration::{
+    ContextMode, generate_context_string, generate_context_strings, generate_summary,
+    template_context,
+};
+pub use redaction::{Redaction, RedactionCounts, redact_page};
+pub use safety::{SafetyAction, SafetyFilter};
+pub use segmentation::{Section, segment_by_heading};
 
 use crate::crawler::CrawledPage;
 use crate::model::Client;
@@ -77,6 +103,29 @@ pub struct ChunkMetadata {
 
     /// The heading of the chunk
     pub heading: Option<String>,
+
+    /// The H1–H3 headings enclosing the chunk, outermost first
+    pub heading_path: Vec<String>,
+
+    /// The author of the source page, such as the sender of an email
+    pub author: Option<String>,
+
+    /// The publication date of the source page as a Unix timestamp
+    pub published_at: Option<i64>,
+
+    /// The schema.org or OpenGraph type of the source page, e.g. `BlogPosting`
+    pub content_type: Option<String>,
+
+    /// Fingerprint of the processor settings the chunk was built with, see
+    /// `ProcessorConfig::fingerprint`
+    pub config_hash: Option<String>,
+}
+
+impl ChunkMetadata {
+    /// The heading path formatted as a breadcrumb, e.g. `Guide > Setup > Install`
+    pub fn breadcrumb(&self) -> Option<String> {
+        format_breadcrumb(&self.heading_path)
+    }
 }
 
 /// Generate an embedding from combined text and context
@@ -126,11 +175,12 @@ where
 ///
 /// # Returns
 ///
-/// A vector of processed chunks
+/// A vector of processed chunks, or `ProcessError::Cancelled` once `config.cancel` is
+/// cancelled; requests already in flight finish first
 #[instrument(skip(client, page), fields(url = page.url))]
 pub async fn process_content<C, E>(
     client: &Client<C, E>,
-    page: CrawledPage,
+    mut page: CrawledPage,
     config: ProcessorConfig,
 ) -> Result<Vec<ProcessedChunk>, ProcessError>
 where
@@ -139,47 +189,143 @@ where
 {
     debug!("Processing content from {}", page.url);
 
-    // Chunk the markdown content
-    let chunks = chunk_markdown(&page.content, &config.chunk_options)?;
-    let mut processed_chunks = Vec::new();
+    // Move any Markdown front matter into the page metadata, which may also change how
+    // the page is chunked
+    let chunk_overrides = apply_front_matter(&mut page);
+    let chunk_options = chunk_overrides.apply(&config.chunk_options_for(&page.url));
+    if !chunk_overrides.is_empty() {
+        debug!("Chunking {} with {:?}", page.url, chunk_options);
+    }
 
-    // Generate summary of page to use for context
-    let summary = generate_summary(client, &page.content, &config.llm_model).await?;
+    // Treat each section of the page as its own document when segmenting by heading
+    let sections = if config.segment_by_heading {
+        segment_by_heading(&page.content)
+    } else {
+        vec![Section {
+            parent_heading: None,
+            content: page.content.clone(),
+        }]
+    };
+    if sections.len() > 1 {
+        debug!("Split {} into {} sections", page.url, sections.len());
+    }
+
+    // Chunk each section and summarize it to use for context
+    let mut chunks = Vec::new();
+    for section in sections {
+        if config.cancel.is_cancelled() {
+            return Err(ProcessError::Cancelled);
+        }
+        let summary = if config.skip_summary {
+            String::new()
+        } else {
+            generate_summary(
+                client,
+                &section.content,
+                &config.llm_model,
+                &config.generation,
+            )
+            .await?
+        };
+        let offset = chunks.len();
+        for mut chunk in chunk_markdown(&section.content, &chunk_options)? {
+            chunk.position += offset;
+            if let Some(parent) = &section.parent_heading {
+                chunk.heading_path.insert(0, parent.clone());
+            }
+            chunks.push((chunk, summary.clone()));
+        }
+    }
+    let mut processed_chunks = Vec::new();
 
     info!("Created {} chunks from {}", chunks.len(), page.url);
 
+    // Generate contexts up front unless each chunk gets its own LLM call
+    let context_mode = if config.skip_summary {
+        ContextMode::Template
+    } else {
+        config.context_mode
+    };
+    let contexts: Vec<Option<String>> = match context_mode {
+        ContextMode::Llm => vec![None; chunks.len()],
+        ContextMode::Template => chunks
+            .iter()
+            .map(|(_, summary)| Some(template_context(&page.url, summary, &page.metadata)))
+            .collect(),
+        ContextMode::Batched => {
+            let mut contexts = Vec::with_capacity(chunks.len());
+            // Batches never mix sections, which have different summaries
+            for batch in chunks
+                .chunk_by(|(_, a), (_, b)| a == b)
+                .flat_map(|section| section.chunks(config.context_batch_size.max(1)))
+            {
+                let texts: Vec<&str> = batch.iter().map(|(chunk, _)| chunk.text.as_str()).collect();
+                let batch_contexts = generate_context_strings(
+                    client,
+                    &texts,
+                    &page.url,
+                    &batch[0].1,
+                    &page.metadata,
+                    &config.llm_model,
+                    &config.generation,
+                )
+                .await?;
+                contexts.extend(batch_contexts.into_iter().map(Some));
+            }
+            contexts
+        }
+    };
+
     // Process chunks in parallel with bounded concurrency
-    let semaphore = Arc::new(Semaphore::new(5)); // Limit concurrent API calls
+    let config_hash = config.fingerprint();
+    let semaphore = Arc::new(Semaphore::new(config.concurrency.max(1)));
 
     let tasks = chunks
         .into_iter()
-        .filter_map(|chunk| {
+        .zip(contexts)
+        .map(|((chunk, summary), context)| {
             let permit = semaphore.clone().acquire_owned();
             let llm_model = config.llm_model.clone();
+            let generation = config.generation.clone();
             let metadata = page.metadata.clone();
             let url = page.url.clone();
-            let summary = summary.clone();
             let client = client.clone();
+            let cancel = config.cancel.clone();
+            let config_hash = config_hash.clone();
 
-            if chunk.text.len() <= 100 {
-                debug!("Skipping small chunk");
-                return None;
-            }
-            Some(tokio::spawn(async move {
+            tokio::spawn(async move {
                 let _permit = permit
                     .await
                     .map_err(|e| ProcessError::Semaphore(e.to_string()));
 
-                // Generate context string using LLM
-                let context = generate_context_string(
-                    &client,
-                    &chunk.text,
-                    &url,
-                    &summary,
-                    &metadata,
-                    &llm_model,
-                )
-                .await?;
+                // Chunks waiting for a permit are skipped once cancelled; the page is
+                // dropped as a whole
+                if cancel.is_cancelled() {
+                    return Err(ProcessError::Cancelled);
+                }
+
+                // Generate context string using LLM unless it was generated up front
+                let context = match context {
+                    Some(context) => context,
+                    None => {
+                        generate_context_string(
+                            &client,
+                            &chunk.text,
+                            &url,
+                            &summary,
+                            &metadata,
+                            &llm_model,
+                            &generation,
+                        )
+                        .await?
+                    }
+                };
+
+                // Prefix the context with the section breadcrumb so it is embedded and stored
+                let context = match format_breadcrumb(&chunk.heading_path) {
+                    Some(breadcrumb) => format!("Section: {}\n{}", breadcrumb, context),
+                    None => context,
+                };
 
                 // Generate embedding from combined text and context
                 let embedding = generate_combined_embedding(&client, &chunk.text, &context).await?;
@@ -189,6 +335,11 @@ where
                     source_url: url,
                     position: chunk.position,
                     heading: chunk.heading,
+                    heading_path: chunk.heading_path,
+                    author: metadata.author.clone(),
+                    published_at: metadata.publication_date.map(|date| date.timestamp()),
+                    content_type: metadata.content_type.clone(),
+                    config_hash: Some(config_hash),
                 };
 
                 // Create processed chunk
@@ -200,7 +351,7 @@ where
                 };
 
                 Ok::<ProcessedChunk, ProcessError>(processed_chunk)
-            }))
+            })
         })
         .collect::<Vec<_>>();
 
@@ -228,6 +379,7 @@ mod tests {
         let options = ChunkOptions {
             target_chunk_size: 1000,
             overlap_size: 100,
+            ..ChunkOptions::default()
         };
 
         assert_eq!(options.target_chunk_size, 1000);
@@ -249,17 +401,51 @@ mod tests {
         assert_eq!(config.embedding_dimensions, 768);
     }
 
+    #[test]
+    fn test_processor_config_fingerprint() {
+        let config = ProcessorConfig::builder().target_chunk_size(1000).build();
+        let fingerprint = config.fingerprint();
+        assert_eq!(fingerprint.len(), 16);
+
+        // Settings that don't shape the chunks leave the fingerprint unchanged
+        let mut same = config.clone();
+        same.concurrency = 20;
+        assert_eq!(same.fingerprint(), fingerprint);
+
+        let mut resized = config.clone();
+        resized.chunk_options.target_chunk_size = 800;
+        assert_ne!(resized.fingerprint(), fingerprint);
+        let mut remodeled = config;
+        remodeled.llm_model = "gemini-2.0-flash".to_string();
+        assert_ne!(remodeled.fingerprint(), fingerprint);
+        let mut reembedded = ProcessorConfig::builder()
+            .target_chunk_size(1000)
+            .embedding_model("BAAI/bge-base-en-v1.5")
+            .build();
+        assert_ne!(reembedded.fingerprint(), fingerprint);
+        reembedded.embedding_model = rig::providers::gemini::embedding::EMBEDDING_004.to_string();
+        assert_eq!(reembedded.fingerprint(), fingerprint);
+    }
+
     #[test]
     fn test_chunk_metadata() {
         let metadata = ChunkMetadata {
             source_url: "https://example.com".to_string(),
             position: 1,
             heading: Some("Test Heading".to_string()),
+            heading_path: vec!["Guide".to_string(), "Test Heading".to_string()],
+            author: Some("Test Author".to_string()),
+            published_at: Some(1625097600),
+            content_type: None,
+            config_hash: None,
         };
 
         assert_eq!(metadata.source_url, "https://example.com");
         assert_eq!(metadata.position, 1);
         assert_eq!(metadata.heading.as_deref().unwrap(), "Test Heading");
+        assert_eq!(metadata.breadcrumb().unwrap(), "Guide > Test Heading");
+        assert_eq!(metadata.author.as_deref().unwrap(), "Test Author");
+        assert_eq!(metadata.published_at, Some(1625097600));
     }
 
     #[test]
@@ -276,6 +462,11 @@ mod tests {
                 source_url: "https://example.com".to_string(),
                 position: 1,
                 heading: Some("Test Heading".to_string()),
+                heading_path: vec!["Test Heading".to_string()],
+                author: None,
+                published_at: None,
+                content_type: None,
+                config_hash: None,
             },
         };
 
@@ -286,4 +477,110 @@ mod tests {
         assert_eq!(chunk.metadata.position, 1);
         assert_eq!(chunk.metadata.heading.as_deref().unwrap(), "Test Heading");
     }
+
+    #[tokio::test]
+    async fn test_process_content_with_mock_client() {
+        use crate::crawler::PageMetadata;
+
+        let client = Client::new_mock();
+        let content = "# Guide\n\n".to_string()
+            + &"This paragraph explains how the mock client produces embeddings offline. "
+                .repeat(10);
+        let page = CrawledPage {
+            url: "https://example.com/guide".to_string(),
+            content,
+            metadata: PageMetadata {
+                title: Some("Guide".to_string()),
+                description: None,
+                publication_date: None,
+                author: None,
+                domain: "example.com".to_string(),
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        };
+
+        let chunks = process_content(&client, page, ProcessorConfig::default())
+            .await
+            .unwrap();
+
+        assert!(!chunks.is_empty());
+        for chunk in chunks {
+            assert_eq!(
+                chunk.context,
+                format!(
+                    "Section: Guide\n{}",
+                    crate::model::mock_model::MOCK_RESPONSE
+                )
+            );
+            assert_eq!(chunk.embedding.vec.len(), 768);
+            assert_eq!(chunk.metadata.source_url, "https://example.com/guide");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_content_replays_recording() {
+        use crate::model::vcr::{Cassette, VcrMode};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("guide.json");
+        let page = CrawledPage {
+            url: "https://example.com/guide".to_string(),
+            content: "# Guide\n\n".to_string() + &"Recorded responses replay offline. ".repeat(20),
+            metadata: crate::crawler::PageMetadata {
+                title: Some("Guide".to_string()),
+                description: None,
+                publication_date: None,
+                author: None,
+                domain: "example.com".to_string(),
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        };
+
+        let recorder = Client::new_mock().with_vcr(Cassette::open(&path, VcrMode::Record).unwrap());
+        let recorded = process_content(&recorder, page.clone(), ProcessorConfig::default())
+            .await
+            .unwrap();
+
+        // The replaying client's own models would answer differently
+        let live = Client::new_mock();
+        live.completion().set_text_response("live").await;
+        let player = live.with_vcr(Cassette::open(&path, VcrMode::Replay).unwrap());
+        let replayed = process_content(&player, page, ProcessorConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(replayed.len(), recorded.len());
+        for (replayed, recorded) in replayed.iter().zip(&recorded) {
+            assert_eq!(replayed.context, recorded.context);
+            assert_eq!(replayed.embedding.vec, recorded.embedding.vec);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_content_cancelled() {
+        let client = Client::new_mock();
+        let page = CrawledPage {
+            url: "https://example.com/guide".to_string(),
+            content: "Some content to chunk. ".repeat(50),
+            metadata: crate::crawler::PageMetadata {
+                title: None,
+                description: None,
+                publication_date: None,
+                author: None,
+                domain: "example.com".to_string(),
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        };
+        let cancel = crate::cancel::CancellationToken::new();
+        cancel.cancel();
+        let config = ProcessorConfig::builder().cancel(cancel).build();
+
+        let result = process_content(&client, page, config).await;
+        assert!(matches!(result, Err(ProcessError::Cancelled)));
+    }
 }
@@ -0,0 +1,494 @@
+//! # Code Chunking
+//!
+//! Splits source files into chunks per item (function, struct, class, impl block, ...)
+//! instead of by paragraph, so each chunk is a symbol with its doc comments and attributes.
+//! Chunks keep the line range they came from; the range is stored as the first entry of the
+//! chunk's heading path, e.g. `src/config.rs:10-42 > Config::load`, which is what lets
+//! code search return `file:line` hits.
+//!
+//! Item starts are recognized per language from their keywords, at the top level or one
+//! level deep for methods of impl blocks, traits and classes. Files in languages without
+//! keywords, such as C, are split by size only.
+
+use rig::{completion::CompletionModel, embeddings::EmbeddingModel};
+
+use crate::model::Client;
+use crate::processor::{ChunkMetadata, ProcessError, ProcessedChunk, generate_combined_embedding};
+
+/// Default most characters of a code chunk
+pub const DEFAULT_MAX_CODE_CHUNK: usize = 2_000;
+
+/// Chunks shorter than this are merged into the next chunk, e.g. an `impl` line
+const MIN_CODE_CHUNK: usize = 80;
+
+/// A chunk of a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeChunk {
+    /// The source of the chunk
+    pub text: String,
+    /// First line of the chunk, starting at 1
+    pub start_line: usize,
+    /// Last line of the chunk
+    pub end_line: usize,
+    /// Symbol defined by the chunk, e.g. `Config::load`, none for file preambles
+    pub symbol: Option<String>,
+}
+
+/// The language of a source file from its extension, if it is code
+pub fn language(path: &std::path::Path) -> Option<&'static str> {
+    let language = match path.extension()?.to_str()? {
+        "rs" => "rust",
+        "py" => "python",
+        "go" => "go",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "cs" => "csharp",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "hpp" => "cpp",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// The `path:start-end` location of a chunk
+pub fn location(path: &str, start_line: usize, end_line: usize) -> String {
+    format!("{}:{}-{}", path, start_line, end_line)
+}
+
+/// Parse a `path:start-end` location, as stored at the start of a code chunk's heading path
+pub fn parse_location(location: &str) -> Option<(String, usize, usize)> {
+    let location = location.split(" > ").next()?;
+    let (path, lines) = location.rsplit_once(':')?;
+    let (start, end) = lines.split_once('-')?;
+    Some((path.to_string(), start.parse().ok()?, end.parse().ok()?))
+}
+
+/// Split source code into chunks per item
+///
+/// # Arguments
+///
+/// * `content` - The source code
+/// * `language` - Language of the code, as returned by [`language`]
+/// * `max_chars` - Most characters of a chunk; longer items are split at line boundaries
+pub fn chunk_code(content: &str, language: &str, max_chars: usize) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let separator = if language == "rust" { "::" } else { "." };
+
+    // Lines starting an item, with its symbol
+    let mut starts: Vec<(usize, String)> = Vec::new();
+    let mut container: Option<String> = None;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if indent > 4 || (indent > 0 && container.is_none()) {
+            continue;
+        }
+        let Some((kind, name)) = item(trimmed, language) else {
+            continue;
+        };
+        let symbol = match (&container, indent) {
+            (Some(container), 1..) => format!("{}{}{}", container, separator, name),
+            _ => name.clone(),
+        };
+        if indent == 0 {
+            container = CONTAINERS.contains(&kind).then_some(name);
+        }
+
+        // Doc comments, attributes and decorators belong to the item below them
+        let floor = starts.last().map_or(0, |(start, _)| start + 1);
+        let mut start = index;
+        while start > floor && is_annotation(lines[start - 1].trim_start()) {
+            start -= 1;
+        }
+        starts.push((start, symbol));
+    }
+
+    // Ranges of lines between item starts, the first being the file's preamble
+    let mut ranges = Vec::new();
+    let mut previous = (0, None);
+    for (start, symbol) in starts {
+        ranges.push((previous.0, start, previous.1));
+        previous = (start, Some(symbol));
+    }
+    ranges.push((previous.0, lines.len(), previous.1));
+
+    let mut chunks: Vec<CodeChunk> = Vec::new();
+    let mut pending: Option<usize> = None;
+    for (start, end, symbol) in ranges {
+        let start = pending.take().unwrap_or(start);
+        let mut end = end;
+        while end > start && lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+        let text = lines[start..end].join("\n");
+        if text.trim().is_empty() {
+            continue;
+        }
+        if text.trim().len() < MIN_CODE_CHUNK && end < lines.len() {
+            pending = Some(start);
+            continue;
+        }
+        chunks.extend(split_lines(&lines, start, end, symbol, max_chars));
+    }
+    if let Some(start) = pending {
+        chunks.extend(split_lines(&lines, start, lines.len(), None, max_chars));
+    }
+    chunks
+}
+
+/// Kinds of items whose indented items are members, e.g. the methods of an `impl` block
+const CONTAINERS: &[&str] = &[
+    "impl",
+    "trait",
+    "mod",
+    "class",
+    "interface",
+    "object",
+    "module",
+];
+
+/// Keywords preceding an item's keyword, e.g. `pub async`
+const MODIFIERS: &[&str] = &[
+    "pub",
+    "pub(crate)",
+    "pub(super)",
+    "async",
+    "unsafe",
+    "extern",
+    "\"C\"",
+    "default",
+    "export",
+    "public",
+    "private",
+    "protected",
+    "internal",
+    "abstract",
+    "final",
+    "sealed",
+    "open",
+    "override",
+    "data",
+    "static",
+];
+
+/// Item keywords of each language
+fn keywords(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" => &[
+            "fn",
+            "struct",
+            "enum",
+            "trait",
+            "impl",
+            "mod",
+            "type",
+            "union",
+            "const",
+            "static",
+            "macro_rules!",
+        ],
+        "python" => &["def", "class"],
+        "go" => &["func", "type"],
+        "javascript" => &["function", "class"],
+        "typescript" => &["function", "class", "interface", "type", "enum"],
+        "java" | "csharp" => &["class", "interface", "enum", "record"],
+        "kotlin" => &["fun", "class", "interface", "object"],
+        "swift" => &["func", "class", "struct", "enum", "protocol", "extension"],
+        "ruby" => &["def", "class", "module"],
+        _ => &[],
+    }
+}
+
+/// The kind and name of the item a line starts, if any
+fn item(line: &str, language: &str) -> Option<(&'static str, String)> {
+    let keywords = keywords(language);
+    let mut rest = line;
+    loop {
+        let (token, after) = next_token(rest)?;
+        // Generics may follow the keyword directly, e.g. `impl<T>`
+        let word = token.split('<').next().unwrap_or(token);
+        if let Some(keyword) = keywords.iter().copied().find(|k| *k == word) {
+            // `const fn` and `static async` are modifiers of the item that follows
+            let is_modifier = matches!(keyword, "const" | "static")
+                && next_token(after)
+                    .is_some_and(|(next, _)| keywords.contains(&next) || MODIFIERS.contains(&next));
+            if !is_modifier {
+                let body = rest.trim_start()[word.len()..].trim_start();
+                let name = item_name(keyword, body)?;
+                return (!name.is_empty()).then_some((keyword, name));
+            }
+        } else if !MODIFIERS.contains(&token) && token != "const" {
+            return None;
+        }
+        rest = after;
+    }
+}
+
+/// The next whitespace-separated token of a line and the text after it
+fn next_token(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.split_at(text.find(char::is_whitespace).unwrap_or(text.len())))
+}
+
+/// The name of an item from the text following its keyword
+fn item_name(keyword: &str, body: &str) -> Option<String> {
+    let name = match keyword {
+        "impl" => impl_name(body),
+        // Go methods name their receiver first, e.g. `func (s *Server) Handle(`
+        "func" if body.starts_with('(') => {
+            let (receiver, method) = body[1..].split_once(')')?;
+            let receiver = receiver.split_whitespace().last()?.trim_start_matches('*');
+            format!("{}.{}", ident(receiver), ident(method.trim_start()))
+        }
+        _ => ident(body),
+    };
+    Some(name)
+}
+
+/// The type an `impl` block is for, e.g. `Config` for `impl<T> Display for Config<T> {`
+fn impl_name(rest: &str) -> String {
+    let rest = match rest.strip_prefix('<') {
+        Some(generics) => {
+            let mut depth = 1;
+            let end = generics
+                .char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map_or(generics.len(), |(i, _)| i + 1);
+            generics[end..].trim_start()
+        }
+        None => rest,
+    };
+    let target = rest.split_once(" for ").map_or(rest, |(_, target)| target);
+    ident(target.trim_start_matches('&'))
+}
+
+/// The identifier a string starts with
+fn ident(text: &str) -> String {
+    text.chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect()
+}
+
+/// Whether a line is a comment, attribute or decorator attached to the next item
+fn is_annotation(line: &str) -> bool {
+    ["///", "//", "#[", "@", "/**", "/*", "* ", "*/", "#"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        || line == "*"
+}
+
+/// Split a range of lines into chunks of at most `max_chars`
+fn split_lines(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    symbol: Option<String>,
+    max_chars: usize,
+) -> Vec<CodeChunk> {
+    let mut chunks = Vec::new();
+    let mut chunk_start = start;
+    let mut size = 0;
+    for (index, line) in lines.iter().enumerate().take(end).skip(start) {
+        let line_size = line.len() + 1;
+        if size > 0 && size + line_size > max_chars {
+            chunks.push((chunk_start, index));
+            chunk_start = index;
+            size = 0;
+        }
+        size += line_size;
+    }
+    chunks.push((chunk_start, end));
+
+    let parts = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(part, (start, end))| CodeChunk {
+            text: lines[start..end].join("\n"),
+            start_line: start + 1,
+            end_line: end,
+            symbol: match (&symbol, parts) {
+                (Some(symbol), 2..) => Some(format!("{} (part {})", symbol, part + 1)),
+                _ => symbol.clone(),
+            },
+        })
+        .collect()
+}
+
+/// Chunk a source file and embed its chunks for the index
+///
+/// The chunks get a template context naming the file, lines, language and symbol instead of
+/// an LLM-generated one, so indexing a repository only costs embedding calls.
+///
+/// # Arguments
+///
+/// * `client` - Client generating the embeddings
+/// * `url` - URL the file is indexed under
+/// * `path` - Path of the file relative to the repository, used in locations
+/// * `content` - The source code
+/// * `max_chars` - Most characters of a chunk
+pub async fn process_code_file<C, E>(
+    client: &Client<C, E>,
+    url: &str,
+    path: &str,
+    content: &str,
+    max_chars: usize,
+) -> Result<Vec<ProcessedChunk>, ProcessError>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    let Some(language) = language(std::path::Path::new(path)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut processed = Vec::new();
+    for (position, chunk) in chunk_code(content, language, max_chars)
+        .into_iter()
+        .enumerate()
+    {
+        let location = location(path, chunk.start_line, chunk.end_line);
+        let context = format!(
+            "File: {}\nLines: {}-{}\nLanguage: {}\nSymbol: {}",
+            path,
+            chunk.start_line,
+            chunk.end_line,
+            language,
+            chunk.symbol.as_deref().unwrap_or("(file preamble)")
+        );
+        let embedding = generate_combined_embedding(client, &chunk.text, &context).await?;
+
+        let mut heading_path = vec![location];
+        heading_path.extend(chunk.symbol.clone());
+        processed.push(ProcessedChunk {
+            text: chunk.text,
+            embedding,
+            context,
+            metadata: ChunkMetadata {
+                source_url: url.to_string(),
+                position,
+                heading: chunk.symbol,
+                heading_path,
+                author: None,
+                published_at: None,
+                content_type: Some(format!("code/{}", language)),
+                config_hash: None,
+            },
+        });
+    }
+    Ok(processed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"//! Configuration
+use std::path::PathBuf;
+
+/// Settings of the server
+#[derive(Debug)]
+pub struct Config {
+    pub path: PathBuf,
+    pub verbose: bool,
+}
+
+impl Config {
+    /// Load the configuration from a file, falling back to the defaults
+    pub fn load(path: PathBuf) -> Self {
+        Self { path, verbose: false }
+    }
+
+    pub(crate) const fn verbose(&self) -> bool {
+        self.verbose
+    }
+}
+"#;
+
+    #[test]
+    fn test_chunk_rust_items() {
+        let chunks = chunk_code(RUST, "rust", 2_000);
+
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        assert_eq!(
+            symbols,
+            vec![
+                Some("Config"),
+                Some("Config::load"),
+                Some("Config::verbose")
+            ]
+        );
+
+        // The preamble is too short for its own chunk and merged into the struct
+        assert_eq!(chunks[0].start_line, 1);
+        assert!(
+            chunks[0]
+                .text
+                .contains("/// Settings of the server\n#[derive(Debug)]")
+        );
+        assert_eq!(chunks[0].end_line, 9);
+
+        // The impl line and the method's doc comment belong to the method
+        assert_eq!(chunks[1].start_line, 11);
+        assert!(chunks[1].text.starts_with("impl Config {\n    /// Load"));
+        assert_eq!(chunks[2].end_line, 20);
+        assert!(chunks[2].text.ends_with("}\n}"));
+    }
+
+    #[test]
+    fn test_chunk_python_methods() {
+        let source = "import os\n\n\nclass Store:\n    \"\"\"Key value store backed by files in a directory on disk.\"\"\"\n\n    @staticmethod\n    def open(path):\n        return Store(os.path.abspath(path), create=True, readonly=False)\n\n    def get(self, key):\n        with open(os.path.join(self.path, key)) as f:\n            return f.read()\n";
+        let chunks = chunk_code(source, "python", 2_000);
+
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        assert_eq!(
+            symbols,
+            vec![Some("Store"), Some("Store.open"), Some("Store.get")]
+        );
+        assert!(chunks[1].text.trim_start().starts_with("@staticmethod"));
+        assert_eq!(chunks[1].start_line, 7);
+    }
+
+    #[test]
+    fn test_large_items_are_split() {
+        let body: String = (0..50)
+            .map(|i| format!("    let x{} = {};\n", i, i))
+            .collect();
+        let source = format!("fn big() {{\n{}}}\n", body);
+        let chunks = chunk_code(&source, "rust", 400);
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.text.len() <= 400));
+        assert_eq!(chunks[0].symbol.as_deref(), Some("big (part 1)"));
+        assert_eq!(chunks.last().unwrap().end_line, 52);
+    }
+
+    #[test]
+    fn test_location_round_trip() {
+        assert_eq!(location("src/main.rs", 3, 10), "src/main.rs:3-10");
+        assert_eq!(
+            parse_location("src/main.rs:3-10 > main"),
+            Some(("src/main.rs".to_string(), 3, 10))
+        );
+        assert_eq!(parse_location("Guide > Setup"), None);
+        assert_eq!(
+            language(std::path::Path::new("lib/app.tsx")),
+            Some("typescript")
+        );
+        assert_eq!(language(std::path::Path::new("README.md")), None);
+    }
+}
@@ -9,37 +9,42 @@
 //! - `ChunkOptions`: Controls the chunking behavior (size and overlap)
 //! - `ProcessorConfig`: Complete configuration for the processor pipeline
 //! - `ProcessorConfigBuilder`: Builder pattern implementation for easier configuration
+//! - `ChunkOverrides`: Per-page changes to the chunk options, from front matter or a source
+//! - `SourceChunkOverride`: Chunk overrides for the pages whose URL contains a pattern
 //!
 //! ## Features
 //!
 //! - Default configurations suitable for general RAG use cases
 //! - Builder pattern for flexible and fluent configuration
 //! - Independent control of chunk size and overlap parameters
-//! - Model selection for LLM-powered summarization and context generation
+//! - Model selection and sampling parameters for LLM-powered summarization and context
+//!   generation
 //! - Embedding dimension configuration to match the chosen embedding model
+//! - Optional segmentation of pages into sections per heading before chunking
+//! - Context generation per chunk, in batches or from templates, and a summary-free mode
+//!   without any completion calls
+//! - Configurable number of chunks processed at once
+//! - Chunk options varied per source or per page, so heterogeneous corpora such as docs
+//!   with changelogs fit in one index
+//! - A fingerprint of the settings recorded with each chunk, so chunks built with outdated
+//!   settings can be found
 //!
 //! The configuration parameters in this module significantly impact RAG performance,
 //! affecting the granularity of chunks, the quality of context generation, and the
 //! dimensions of the vector space used for similarity search.
 
-/// Configuration for chunking text
-#[derive(Debug, Clone)]
-pub struct ChunkOptions {
-    /// Target size of each chunk in words
-    pub target_chunk_size: usize,
+pub use hal_core::chunking::ChunkOptions;
 
-    /// Size of overlap between chunks in words
-    pub overlap_size: usize,
-}
+use std::fmt;
+use std::str::FromStr;
 
-impl Default for ChunkOptions {
-    fn default() -> Self {
-        Self {
-            target_chunk_size: 500,
-            overlap_size: 50,
-        }
-    }
-}
+use crate::audit::args_hash;
+use crate::cancel::CancellationToken;
+use crate::model::GenerationOptions;
+use crate::processor::ContextMode;
+
+/// Model embedding chunks unless another is configured
+const EMBEDDING_MODEL: &str = rig::providers::gemini::embedding::EMBEDDING_004;
 
 /// Configuration for the processor
 #[derive(Debug, Clone)]
@@ -50,8 +55,38 @@ pub struct ProcessorConfig {
     /// LLM model to use for summaries and context
     pub llm_model: String,
 
+    /// Sampling parameters for summaries and context
+    pub generation: GenerationOptions,
+
+    /// Model embedding the chunks, part of the fingerprint since embeddings of different
+    /// models can't be compared
+    pub embedding_model: String,
+
     /// Dimensions of the embedding vectors
     pub embedding_dimensions: usize,
+
+    /// Whether to split pages into sections per H1/H2 heading, each with its own summary
+    pub segment_by_heading: bool,
+
+    /// How the context strings of chunks are generated
+    pub context_mode: ContextMode,
+
+    /// Chunks per LLM call in the batched context mode
+    pub context_batch_size: usize,
+
+    /// Chunks of a page processed at once; the client's per-model concurrency and rate
+    /// limits still apply to the requests they make
+    pub concurrency: usize,
+
+    /// Whether to skip page summaries; contexts are then built from the title and heading
+    /// breadcrumb, so only embeddings are requested
+    pub skip_summary: bool,
+
+    /// Chunk overrides for the pages of particular sources, the last matching one applied
+    pub source_chunk_overrides: Vec<SourceChunkOverride>,
+
+    /// Token stopping the processing of a page before it is complete
+    pub cancel: CancellationToken,
 }
 
 impl Default for ProcessorConfig {
@@ -59,7 +94,16 @@ impl Default for ProcessorConfig {
         Self {
             chunk_options: ChunkOptions::default(),
             llm_model: "gemini-1.5-flash".to_string(),
+            generation: GenerationOptions::default(),
+            embedding_model: EMBEDDING_MODEL.to_string(),
             embedding_dimensions: 384,
+            segment_by_heading: false,
+            context_mode: ContextMode::Llm,
+            context_batch_size: 10,
+            concurrency: 5,
+            skip_summary: false,
+            source_chunk_overrides: Vec::new(),
+            cancel: CancellationToken::new(),
         }
     }
 }
@@ -102,12 +146,69 @@ impl ProcessorConfigBuilder {
         self
     }
 
+    /// Set the sampling parameters for summaries and context
+    pub fn generation(mut self, generation: GenerationOptions) -> Self {
+        self.config.generation = generation;
+        self
+    }
+
+    /// Set the model embedding the chunks
+    pub fn embedding_model(mut self, embedding_model: impl Into<String>) -> Self {
+        self.config.embedding_model = embedding_model.into();
+        self
+    }
+
     /// Set the embedding dimensions
     pub fn embedding_dimensions(mut self, embedding_dimensions: usize) -> Self {
         self.config.embedding_dimensions = embedding_dimensions;
         self
     }
 
+    /// Set whether to split pages into sections per H1/H2 heading
+    pub fn segment_by_heading(mut self, segment_by_heading: bool) -> Self {
+        self.config.segment_by_heading = segment_by_heading;
+        self
+    }
+
+    /// Set how the context strings of chunks are generated
+    pub fn context_mode(mut self, context_mode: ContextMode) -> Self {
+        self.config.context_mode = context_mode;
+        self
+    }
+
+    /// Set the chunks per LLM call in the batched context mode
+    pub fn context_batch_size(mut self, context_batch_size: usize) -> Self {
+        self.config.context_batch_size = context_batch_size;
+        self
+    }
+
+    /// Set the chunks of a page processed at once
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config.concurrency = concurrency;
+        self
+    }
+
+    /// Set whether to skip page summaries and build contexts without completion calls
+    pub fn skip_summary(mut self, skip_summary: bool) -> Self {
+        self.config.skip_summary = skip_summary;
+        self
+    }
+
+    /// Set the chunk overrides for the pages of particular sources
+    pub fn source_chunk_overrides(
+        mut self,
+        source_chunk_overrides: Vec<SourceChunkOverride>,
+    ) -> Self {
+        self.config.source_chunk_overrides = source_chunk_overrides;
+        self
+    }
+
+    /// Set the token stopping the processing of a page
+    pub fn cancel(mut self, cancel: CancellationToken) -> Self {
+        self.config.cancel = cancel;
+        self
+    }
+
     /// Build the configuration
     pub fn build(self) -> ProcessorConfig {
         self.config
@@ -119,4 +220,152 @@ impl ProcessorConfig {
     pub fn builder() -> ProcessorConfigBuilder {
         ProcessorConfigBuilder::new()
     }
+
+    /// Chunk options for a page, with the overrides of the last matching source applied
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page
+    ///
+    /// # Returns
+    ///
+    /// The chunk options to use for the page
+    pub fn chunk_options_for(&self, url: &str) -> ChunkOptions {
+        self.source_chunk_overrides
+            .iter()
+            .rev()
+            .find(|source| url.contains(&source.pattern))
+            .map(|source| source.overrides.apply(&self.chunk_options))
+            .unwrap_or_else(|| self.chunk_options.clone())
+    }
+
+    /// Stable hash of the settings that shape the chunks built with this configuration
+    ///
+    /// Chunks record the fingerprint of their configuration, so chunks built with other
+    /// settings can be found and reprocessed. Concurrency and cancellation do not change
+    /// what is built and are left out.
+    pub fn fingerprint(&self) -> String {
+        let options = &self.chunk_options;
+        let settings = format!(
+            "chunks={}/{}/{:?}/{};overrides={:?};llm={};generation={};embedding={}/{};segment={};context={}/{};summary={}",
+            options.target_chunk_size,
+            options.overlap_size,
+            options.overlap_sentences,
+            options.min_chunk_chars,
+            self.source_chunk_overrides,
+            self.llm_model,
+            serde_json::to_string(&self.generation).unwrap_or_default(),
+            self.embedding_model,
+            self.embedding_dimensions,
+            self.segment_by_heading,
+            self.context_mode,
+            self.context_batch_size,
+            !self.skip_summary,
+        );
+        args_hash(&settings)
+    }
+}
+
+/// Changes to the chunk options for a single page or source
+///
+/// Unset fields keep the configured value, so a changelog can declare only a larger
+/// `chunk_size` and still use the configured overlap.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkOverrides {
+    /// Target chunk size in words
+    pub target_chunk_size: Option<usize>,
+
+    /// Overlap between chunks in words
+    pub overlap_size: Option<usize>,
+
+    /// Overlap between chunks in whole sentences
+    pub overlap_sentences: Option<usize>,
+
+    /// Fewest characters a chunk may have before it is merged into a neighbor
+    pub min_chunk_chars: Option<usize>,
+}
+
+impl ChunkOverrides {
+    /// Whether no chunk option is overridden
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply the overrides to chunk options
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The configured chunk options
+    ///
+    /// # Returns
+    ///
+    /// The chunk options with every set override replacing the configured value
+    pub fn apply(&self, options: &ChunkOptions) -> ChunkOptions {
+        ChunkOptions {
+            target_chunk_size: self.target_chunk_size.unwrap_or(options.target_chunk_size),
+            overlap_size: self.overlap_size.unwrap_or(options.overlap_size),
+            overlap_sentences: self.overlap_sentences.or(options.overlap_sentences),
+            min_chunk_chars: self.min_chunk_chars.unwrap_or(options.min_chunk_chars),
+        }
+    }
+}
+
+/// Chunk overrides for the pages whose URL contains a pattern
+///
+/// Parsed from `PATTERN=SIZE[/OVERLAP]`, e.g. `/changelog=1500/150`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceChunkOverride {
+    /// Substring of the URLs of the pages to override
+    pub pattern: String,
+
+    /// The chunk overrides for those pages
+    pub overrides: ChunkOverrides,
+}
+
+impl FromStr for SourceChunkOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, sizes) = s
+            .rsplit_once('=')
+            .filter(|(pattern, _)| !pattern.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "invalid chunk override '{}', expected PATTERN=SIZE[/OVERLAP]",
+                    s
+                )
+            })?;
+        let (size, overlap) = match sizes.split_once('/') {
+            Some((size, overlap)) => (size, Some(overlap)),
+            None => (sizes, None),
+        };
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid chunk size '{}' in chunk override '{}'", value, s))
+        };
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            overrides: ChunkOverrides {
+                target_chunk_size: Some(parse(size)?),
+                overlap_size: overlap.map(parse).transpose()?,
+                ..ChunkOverrides::default()
+            },
+        })
+    }
+}
+
+impl fmt::Display for SourceChunkOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=", self.pattern)?;
+        if let Some(size) = self.overrides.target_chunk_size {
+            write!(f, "{}", size)?;
+        }
+        if let Some(overlap) = self.overrides.overlap_size {
+            write!(f, "/{}", overlap)?;
+        }
+        Ok(())
+    }
 }
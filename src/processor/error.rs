@@ -14,6 +14,7 @@
 //! - LLM-related errors for summarization and context generation failures
 //! - Chunking errors for text segmentation problems
 //! - Task and concurrency management errors
+//! - Cancellation of a page before it was complete
 //! - Detailed error messages for easier debugging
 //!
 //! The error handling in this module ensures that failures in the content processing
@@ -64,6 +65,10 @@ pub enum ProcessError {
     #[error("Task execution error: {0}")]
     Task(String),
 
+    /// Processing was cancelled before the page was complete
+    #[error("Processing cancelled")]
+    Cancelled,
+
     /// Other errors
     #[error("{0}")]
     Other(String),
@@ -0,0 +1,373 @@
+//! # Markdown Front Matter Module
+//!
+//! This module extracts YAML front matter from Markdown sources and maps it into
+//! `PageMetadata`, so file-based corpora carry the same metadata as crawled HTML.
+//!
+//! ## Key Components
+//!
+//! - `FrontMatter`: The recognized front matter fields
+//! - `parse_front_matter`: Splits a document into its front matter and body
+//! - `apply_front_matter`: Strips front matter from a page and merges it into the page metadata
+//!
+//! ## Supported Syntax
+//!
+//! Front matter is a block delimited by `---` lines at the very start of the document.
+//! Only the flat subset of YAML used by static site generators is understood:
+//!
+//! - `key: value` scalars, optionally single or double quoted
+//! - Flow lists such as `tags: [rust, rag]`
+//! - Block lists with one `- item` per line
+//!
+//! Unrecognized keys and nested structures are ignored rather than rejected.
+//!
+//! ## Chunking Overrides
+//!
+//! A page can change how it is chunked with the `chunk_size`, `chunk_overlap`,
+//! `overlap_sentences` and `min_chunk_chars` keys, e.g. larger chunks for a changelog:
+//!
+//! ```yaml
+//! ---
+//! title: Changelog
+//! chunk_size: 1500
+//! ---
+//! ```
+
+use crate::crawler::CrawledPage;
+use crate::processor::config::ChunkOverrides;
+use chrono::{DateTime, NaiveDate, Utc};
+use tracing::debug;
+
+/// Metadata fields recognized in Markdown front matter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    /// Title of the document
+    pub title: Option<String>,
+
+    /// Description or summary of the document
+    pub description: Option<String>,
+
+    /// Author of the document
+    pub author: Option<String>,
+
+    /// Publication date of the document
+    pub date: Option<DateTime<Utc>>,
+
+    /// Tags or keywords of the document
+    pub tags: Vec<String>,
+
+    /// Changes to how the document is chunked
+    pub chunking: ChunkOverrides,
+}
+
+/// Split a Markdown document into its front matter and body
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown document
+///
+/// # Returns
+///
+/// The parsed front matter, if present, and the remaining body
+pub fn parse_front_matter(markdown: &str) -> (Option<FrontMatter>, &str) {
+    let content = markdown.strip_prefix('\u{feff}').unwrap_or(markdown);
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, markdown);
+    };
+
+    // Find the closing delimiter on its own line
+    let mut offset = 0;
+    let mut block_end = None;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            block_end = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+
+    let Some((yaml_end, body_start)) = block_end else {
+        return (None, markdown);
+    };
+
+    let front_matter = parse_yaml_fields(&rest[..yaml_end]);
+    (Some(front_matter), &rest[body_start..])
+}
+
+/// Strip front matter from a page and merge it into the page metadata
+///
+/// Front matter values take precedence over metadata already on the page, since
+/// they are authored explicitly for the document.
+///
+/// # Arguments
+///
+/// * `page` - The page to update
+///
+/// # Returns
+///
+/// The chunking overrides declared by the page, empty without front matter
+pub fn apply_front_matter(page: &mut CrawledPage) -> ChunkOverrides {
+    let (front_matter, body) = parse_front_matter(&page.content);
+    let Some(front_matter) = front_matter else {
+        return ChunkOverrides::default();
+    };
+    debug!("Found front matter in {}", page.url);
+
+    page.content = body.trim_start().to_string();
+
+    let metadata = &mut page.metadata;
+    if front_matter.title.is_some() {
+        metadata.title = front_matter.title;
+    }
+    if front_matter.description.is_some() {
+        metadata.description = front_matter.description;
+    }
+    if front_matter.author.is_some() {
+        metadata.author = front_matter.author;
+    }
+    if front_matter.date.is_some() {
+        metadata.publication_date = front_matter.date;
+    }
+    for tag in front_matter.tags {
+        if !metadata.tags.contains(&tag) {
+            metadata.tags.push(tag);
+        }
+    }
+    front_matter.chunking
+}
+
+/// Parse the flat YAML subset between the front matter delimiters
+fn parse_yaml_fields(yaml: &str) -> FrontMatter {
+    let mut front_matter = FrontMatter::default();
+    let mut list_key: Option<String> = None;
+
+    for line in yaml.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        // Block list item belonging to the previous key
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some(key) = &list_key {
+                set_list_item(&mut front_matter, key, unquote(item));
+            }
+            continue;
+        }
+
+        // Nested values are not supported
+        if line.starts_with(' ') || line.starts_with('\t') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            list_key = None;
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        if value.is_empty() {
+            list_key = Some(key);
+            continue;
+        }
+        list_key = None;
+
+        if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            for item in items.split(',') {
+                set_list_item(&mut front_matter, &key, unquote(item));
+            }
+            continue;
+        }
+
+        let value = unquote(value);
+        match key.as_str() {
+            "title" => front_matter.title = Some(value),
+            "description" | "summary" => front_matter.description = Some(value),
+            "author" => front_matter.author = Some(value),
+            "date" | "published" | "publication_date" => front_matter.date = parse_date(&value),
+            "tags" | "keywords" | "categories" => {
+                for item in value.split(',') {
+                    set_list_item(&mut front_matter, &key, unquote(item));
+                }
+            }
+            "chunk_size" | "chunk_overlap" | "overlap_sentences" | "min_chunk_chars" => {
+                let Ok(number) = value.parse::<usize>() else {
+                    debug!("Ignoring non-numeric front matter {}: {}", key, value);
+                    continue;
+                };
+                let chunking = &mut front_matter.chunking;
+                match key.as_str() {
+                    "chunk_size" => chunking.target_chunk_size = Some(number),
+                    "chunk_overlap" => chunking.overlap_size = Some(number),
+                    "overlap_sentences" => chunking.overlap_sentences = Some(number),
+                    _ => chunking.min_chunk_chars = Some(number),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    front_matter
+}
+
+/// Add a list item to the front matter field named by `key`
+fn set_list_item(front_matter: &mut FrontMatter, key: &str, item: String) {
+    if item.is_empty() {
+        return;
+    }
+    match key {
+        "tags" | "keywords" | "categories" if !front_matter.tags.contains(&item) => {
+            front_matter.tags.push(item)
+        }
+        "author" | "authors" if front_matter.author.is_none() => front_matter.author = Some(item),
+        _ => {}
+    }
+}
+
+/// Remove surrounding whitespace and matching quotes from a scalar
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Parse an RFC 3339 timestamp or a plain `YYYY-MM-DD` date
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::PageMetadata;
+    use crate::processor::{ChunkOptions, ProcessorConfig, SourceChunkOverride};
+
+    #[test]
+    fn test_parse_front_matter() {
+        let markdown = "---\ntitle: \"Getting Started\"\nauthor: Jane Doe\ndate: 2024-03-01\ntags: [rust, rag]\n---\n# Intro\n\nBody text.";
+
+        let (front_matter, body) = parse_front_matter(markdown);
+        let front_matter = front_matter.unwrap();
+
+        assert_eq!(front_matter.title.as_deref(), Some("Getting Started"));
+        assert_eq!(front_matter.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            front_matter.date.unwrap().to_rfc3339(),
+            "2024-03-01T00:00:00+00:00"
+        );
+        assert_eq!(front_matter.tags, vec!["rust", "rag"]);
+        assert_eq!(body, "# Intro\n\nBody text.");
+    }
+
+    #[test]
+    fn test_parse_front_matter_block_list() {
+        let markdown = "---\ntitle: Notes\ntags:\n  - search\n  - 'vectors'\ndescription: Some notes\n---\nBody";
+
+        let (front_matter, body) = parse_front_matter(markdown);
+        let front_matter = front_matter.unwrap();
+
+        assert_eq!(front_matter.tags, vec!["search", "vectors"]);
+        assert_eq!(front_matter.description.as_deref(), Some("Some notes"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn test_parse_front_matter_absent_or_unterminated() {
+        let markdown = "# Title\n\n---\ntitle: not front matter\n---";
+        assert_eq!(parse_front_matter(markdown), (None, markdown));
+
+        let unterminated = "---\ntitle: Open\n\nNo closing delimiter";
+        assert_eq!(parse_front_matter(unterminated), (None, unterminated));
+    }
+
+    #[test]
+    fn test_apply_front_matter() {
+        let mut page = CrawledPage {
+            url: "file://docs/guide.md".to_string(),
+            content: "---\ntitle: Guide\ntags: setup\n---\n\n# Guide\n\nText".to_string(),
+            metadata: PageMetadata {
+                title: Some("guide.md".to_string()),
+                description: None,
+                publication_date: None,
+                author: None,
+                domain: "docs".to_string(),
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        };
+
+        apply_front_matter(&mut page);
+
+        assert_eq!(page.content, "# Guide\n\nText");
+        assert_eq!(page.metadata.title.as_deref(), Some("Guide"));
+        assert_eq!(page.metadata.tags, vec!["setup"]);
+        assert_eq!(page.metadata.domain, "docs");
+    }
+
+    #[test]
+    fn test_front_matter_chunk_overrides() {
+        let markdown = "---\ntitle: Changelog\nchunk_size: 1500\nchunk_overlap: '150'\nmin_chunk_chars: many\n---\nBody";
+
+        let (front_matter, _) = parse_front_matter(markdown);
+        let chunking = front_matter.unwrap().chunking;
+        assert_eq!(chunking.target_chunk_size, Some(1500));
+        assert_eq!(chunking.overlap_size, Some(150));
+        assert_eq!(chunking.min_chunk_chars, None);
+
+        let options = chunking.apply(&ChunkOptions::default());
+        assert_eq!(options.target_chunk_size, 1500);
+        assert_eq!(options.overlap_size, 150);
+        assert_eq!(
+            options.min_chunk_chars,
+            ChunkOptions::default().min_chunk_chars
+        );
+    }
+
+    #[test]
+    fn test_source_chunk_overrides() {
+        let config = ProcessorConfig::builder()
+            .target_chunk_size(500)
+            .source_chunk_overrides(vec![
+                "/changelog=1500/150".parse().unwrap(),
+                "/blog/=800".parse().unwrap(),
+            ])
+            .build();
+
+        let changelog = config.chunk_options_for("https://example.com/changelog/v2");
+        assert_eq!(changelog.target_chunk_size, 1500);
+        assert_eq!(changelog.overlap_size, 150);
+        let blog = config.chunk_options_for("https://example.com/blog/post");
+        assert_eq!(blog.target_chunk_size, 800);
+        assert_eq!(blog.overlap_size, config.chunk_options.overlap_size);
+        assert_eq!(
+            config
+                .chunk_options_for("https://example.com/docs")
+                .target_chunk_size,
+            500
+        );
+
+        assert!("=1500".parse::<SourceChunkOverride>().is_err());
+        assert!("/changelog=large".parse::<SourceChunkOverride>().is_err());
+        assert_eq!(
+            "/changelog=1500/150"
+                .parse::<SourceChunkOverride>()
+                .unwrap()
+                .to_string(),
+            "/changelog=1500/150"
+        );
+    }
+}
@@ -8,11 +8,15 @@
 //!
 //! - `generate_summary`: Creates concise summaries of document content
 //! - `generate_context_string`: Produces rich context information for document chunks
+//! - `generate_context_strings`: Produces the contexts of several chunks in one LLM call
+//! - `template_context`: Builds a context from the page metadata and summary without an LLM
+//! - `ContextMode`: Chooses between one call per chunk, batched calls and templates
 //!
 //! ## Features
 //!
 //! - LLM-powered document summarization for high-level understanding
 //! - Context generation that combines metadata and content for improved relevance
+//! - Batched and template-only context modes to cut the number of LLM calls when indexing
 //! - Integration with the agent framework for conversation-like interactions
 //! - Structured prompting for consistent, high-quality outputs
 //! - Instrumentation with tracing for monitoring and performance analysis
@@ -22,12 +26,58 @@
 //! and context strings help bridge the gap between raw text and the nuanced understanding
 //! needed for effective retrieval augmentation.
 
-use crate::model::Client;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::crawler::PageMetadata;
+use crate::model::{Client, GenerationOptions};
 use crate::processor::error::ProcessError;
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use rig::embeddings::EmbeddingModel;
-use tracing::{debug, instrument, trace};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, trace, warn};
+
+/// How the context strings of chunks are generated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextMode {
+    /// One LLM call per chunk
+    #[default]
+    Llm,
+
+    /// One LLM call per batch of chunks, answered as a JSON array
+    Batched,
+
+    /// Built from the page metadata and summary without an LLM call
+    Template,
+}
+
+impl FromStr for ContextMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "llm" => Ok(Self::Llm),
+            "batched" => Ok(Self::Batched),
+            "template" => Ok(Self::Template),
+            other => Err(format!(
+                "unknown context mode '{}', expected llm, batched or template",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ContextMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Llm => "llm",
+            Self::Batched => "batched",
+            Self::Template => "template",
+        })
+    }
+}
 
 /// Generate a summary for a text
 ///
@@ -36,6 +86,7 @@ use tracing::{debug, instrument, trace};
 /// * `client` - The client to use
 /// * `text` - The text to generate a summary for
 /// * `model` - The LLM model to use
+/// * `options` - Sampling parameters for the completion
 ///
 /// # Returns
 ///
@@ -45,6 +96,7 @@ pub async fn generate_summary<C, E>(
     client: &Client<C, E>,
     text: &str,
     _model: &str,
+    options: &GenerationOptions,
 ) -> Result<String, ProcessError>
 where
     C: CompletionModel,
@@ -53,8 +105,11 @@ where
     debug!("Generating summary for text of length {}", text.len());
 
     let completion = client.completion().clone();
-    let agent = AgentBuilder::new(completion)
-        .preamble("Summarize the following text in a concise paragraph:\n")
+    let agent = options
+        .apply(
+            AgentBuilder::new(completion)
+                .preamble("Summarize the following text in a concise paragraph:\n"),
+        )
         .build();
 
     let summary = agent
@@ -75,6 +130,7 @@ where
 /// * `url` - The URL of the source
 /// * `metadata` - Metadata about the source
 /// * `model` - The LLM model to use
+/// * `options` - Sampling parameters for the completion
 ///
 /// # Returns
 ///
@@ -87,6 +143,7 @@ pub async fn generate_context_string<C, E>(
     summary: &str,
     metadata: &crate::crawler::PageMetadata,
     _model: &str,
+    options: &GenerationOptions,
 ) -> Result<String, ProcessError>
 where
     C: CompletionModel,
@@ -99,12 +156,135 @@ where
 
     let prompt = format!(
         "Generate a concise context string for the following text. The context string should help a user understand where this information comes from and its relevance.\n\n\
-            Source URL: {}\n\
+            {}\n\
+            Text:\n",
+        source_details(url, summary, metadata)
+    );
+    let completion = client.completion().clone();
+    let context = options
+        .apply(AgentBuilder::new(completion))
+        .build()
+        .prompt(prompt)
+        .await
+        .map_err(|e| ProcessError::Llm(format!("Failed to generate context string: {}", e)))?;
+
+    trace!("Generated context string of length {}", context.len());
+    Ok(context)
+}
+
+/// Generate the context strings of several chunks of a page in one call
+///
+/// The model answers with a JSON array holding one context per chunk. If the response
+/// cannot be parsed or has the wrong length, the batch falls back to `template_context`.
+///
+/// # Arguments
+///
+/// * `client` - The client to use
+/// * `texts` - The texts of the chunks, from the same page or section
+/// * `url` - The URL of the source
+/// * `summary` - The summary of the page or section
+/// * `metadata` - Metadata about the source
+/// * `model` - The LLM model to use
+/// * `options` - Sampling parameters for the completion
+///
+/// # Returns
+///
+/// One context string per text, in the same order
+#[instrument(skip(client, texts, summary), fields(chunks = texts.len()))]
+pub async fn generate_context_strings<C, E>(
+    client: &Client<C, E>,
+    texts: &[&str],
+    url: &str,
+    summary: &str,
+    metadata: &PageMetadata,
+    _model: &str,
+    options: &GenerationOptions,
+) -> Result<Vec<String>, ProcessError>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    debug!("Generating context strings for {} chunks", texts.len());
+
+    let chunks: String = texts
+        .iter()
+        .enumerate()
+        .map(|(i, text)| format!("<chunk {}>\n{}\n</chunk {}>\n", i + 1, text, i + 1))
+        .collect();
+    let prompt = format!(
+        "Generate a concise context string for each of the {} numbered text chunks below. Each context string should help a user understand where the chunk comes from and its relevance.\n\n\
+            {}\n\
+            Reply with only a JSON array of {} strings, the context of each chunk in order.\n\n\
+            {}",
+        texts.len(),
+        source_details(url, summary, metadata),
+        texts.len(),
+        chunks
+    );
+    let completion = client.completion().clone();
+    let options = options.merge(&GenerationOptions {
+        json_output: true,
+        ..GenerationOptions::default()
+    });
+    let response = options
+        .apply(AgentBuilder::new(completion))
+        .build()
+        .prompt(prompt)
+        .await
+        .map_err(|e| ProcessError::Llm(format!("Failed to generate context strings: {}", e)))?;
+
+    match parse_contexts(&response, texts.len()) {
+        Some(contexts) => Ok(contexts),
+        None => {
+            warn!(
+                "Expected {} context strings from the batch response, using templates",
+                texts.len()
+            );
+            let context = template_context(url, summary, metadata);
+            Ok(vec![context; texts.len()])
+        }
+    }
+}
+
+/// Build a context string from the page metadata and summary without an LLM
+///
+/// # Arguments
+///
+/// * `url` - The URL of the source
+/// * `summary` - The summary of the page or section
+/// * `metadata` - Metadata about the source
+///
+/// # Returns
+///
+/// The title, description and summary of the source
+pub fn template_context(url: &str, summary: &str, metadata: &PageMetadata) -> String {
+    let mut context = match &metadata.title {
+        Some(title) => format!("From \"{}\" ({})", title.trim(), url),
+        None => format!("From {}", url),
+    };
+    if let Some(description) = metadata
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty())
+    {
+        context.push_str(&format!("\n{}", description));
+    }
+    if !summary.trim().is_empty() {
+        context.push_str(&format!("\nSummary: {}", summary.trim()));
+    }
+    context
+}
+
+/// The source of a chunk, as described to the model when generating its context
+fn source_details(url: &str, summary: &str, metadata: &PageMetadata) -> String {
+    format!(
+        "Source URL: {}\n\
             Title: {}\n\
             Description: {}\n\
             Page Summary: {}\n\
-            Domain: {}\n\n\
-            Text:\n",
+            Domain: {}\n\
+            Tags: {}\n",
         url,
         metadata.title.as_deref().unwrap_or("Unknown"),
         metadata
@@ -112,15 +292,70 @@ where
             .as_deref()
             .unwrap_or("No description available"),
         summary,
-        metadata.domain
-    );
-    let completion = client.completion().clone();
-    let context = AgentBuilder::new(completion)
-        .build()
-        .prompt(prompt)
-        .await
-        .map_err(|e| ProcessError::Llm(format!("Failed to generate context string: {}", e)))?;
+        metadata.domain,
+        if metadata.tags.is_empty() {
+            "None".to_string()
+        } else {
+            metadata.tags.join(", ")
+        }
+    )
+}
 
-    trace!("Generated context string of length {}", context.len());
-    Ok(context)
+/// The context strings of a batch response, or `None` unless it is a JSON array of
+/// `expected` strings
+fn parse_contexts(response: &str, expected: usize) -> Option<Vec<String>> {
+    let start = response.find('[')?;
+    let end = response.rfind(']')?;
+    if start >= end {
+        return None;
+    }
+    let contexts: Vec<String> = serde_json::from_str(&response[start..=end]).ok()?;
+    (contexts.len() == expected).then_some(contexts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> PageMetadata {
+        PageMetadata {
+            title: Some("Install Guide".to_string()),
+            description: None,
+            publication_date: None,
+            author: None,
+            domain: "docs.example.com".to_string(),
+            tags: Vec::new(),
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_contexts() {
+        let response = "```json\n[\"About setup\", \"About [flags]\"]\n```";
+        assert_eq!(
+            parse_contexts(response, 2),
+            Some(vec!["About setup".to_string(), "About [flags]".to_string()])
+        );
+        assert_eq!(parse_contexts(response, 3), None);
+        assert_eq!(parse_contexts("No JSON here", 1), None);
+    }
+
+    #[test]
+    fn test_template_context() {
+        let context = template_context(
+            "https://docs.example.com/install",
+            "How to install the CLI.",
+            &metadata(),
+        );
+        assert_eq!(
+            context,
+            "From \"Install Guide\" (https://docs.example.com/install)\nSummary: How to install the CLI."
+        );
+        assert_eq!(
+            template_context("https://docs.example.com/install", "", &metadata()),
+            "From \"Install Guide\" (https://docs.example.com/install)"
+        );
+        assert_eq!("llm".parse::<ContextMode>(), Ok(ContextMode::Llm));
+        assert!("fast".parse::<ContextMode>().is_err());
+    }
 }
@@ -0,0 +1,361 @@
+//! # Content Safety Module
+//!
+//! This module keeps unsafe content out of the index. Pages and chunks that fail a check
+//! are either dropped or indexed with a flag, and in both cases recorded in a quarantine
+//! table so someone can review them.
+//!
+//! ## Key Components
+//!
+//! - `SafetyFilter`: The configured checks and what happens to content failing them
+//! - `SafetyAction`: Drop failing content, or index it and flag it for review
+//! - `ModerationClient`: Client for an OpenAI-compatible moderation API
+//! - `QuarantineEntry`: A page or chunk held for review
+//!
+//! ## Checks
+//!
+//! Keyword lists are checked per chunk, so only the offending chunks of a page are
+//! affected. The moderation API is called once per page to keep the number of requests
+//! down; a page it flags is handled as a whole.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::processor::error::ProcessError;
+
+/// Default base URL of the moderation API
+const DEFAULT_API_URL: &str = "https://api.openai.com/v1";
+
+/// Default moderation model
+const DEFAULT_MODEL: &str = "omni-moderation-latest";
+
+/// What happens to content failing a safety check
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SafetyAction {
+    /// The content is not indexed and waits in quarantine
+    #[default]
+    Drop,
+
+    /// The content is indexed and also listed in quarantine for review
+    Flag,
+}
+
+impl std::str::FromStr for SafetyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Self::Drop),
+            "flag" => Ok(Self::Flag),
+            other => Err(format!(
+                "unknown safety action '{}', expected drop or flag",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for SafetyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Drop => "drop",
+            Self::Flag => "flag",
+        })
+    }
+}
+
+/// A page or chunk held for review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    /// ID of the entry
+    pub id: i64,
+
+    /// URL of the page
+    pub url: String,
+
+    /// Position of the chunk in the page, or `None` for the whole page
+    pub position: Option<i64>,
+
+    /// The content that failed the check
+    pub text: String,
+
+    /// Why the content failed, e.g. `keyword: …` or `moderation: violence`
+    pub reasons: Vec<String>,
+
+    /// Whether the content was dropped or indexed with a flag
+    pub action: SafetyAction,
+
+    /// When the content was quarantined, as a Unix timestamp
+    pub created_at: i64,
+}
+
+/// Client for an OpenAI-compatible moderation API
+#[derive(Debug, Clone)]
+pub struct ModerationClient {
+    /// Base URL of the API
+    base_url: String,
+
+    /// API key
+    api_key: String,
+
+    /// Moderation model
+    model: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModerationResponse {
+    results: Vec<ModerationResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModerationResult {
+    flagged: bool,
+    #[serde(default)]
+    categories: std::collections::BTreeMap<String, bool>,
+}
+
+impl ModerationClient {
+    /// Create a new client for the OpenAI API
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - API key
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            base_url: DEFAULT_API_URL.to_string(),
+            api_key: api_key.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a client from `MODERATION_API_KEY` (or `OPENAI_API_KEY`), with optional
+    /// `MODERATION_API_URL` and `MODERATION_MODEL` overrides
+    pub fn from_env() -> Result<Self, ProcessError> {
+        let api_key = std::env::var("MODERATION_API_KEY")
+            .or_else(|_| std::env::var("OPENAI_API_KEY"))
+            .map_err(|_| {
+                ProcessError::Other(
+                    "MODERATION_API_KEY or OPENAI_API_KEY environment variable must be set"
+                        .to_string(),
+                )
+            })?;
+
+        let mut client = Self::new(&api_key);
+        if let Ok(base_url) = std::env::var("MODERATION_API_URL") {
+            client = client.with_base_url(&base_url);
+        }
+        if let Ok(model) = std::env::var("MODERATION_MODEL") {
+            client.model = model;
+        }
+        Ok(client)
+    }
+
+    /// Use a different API base URL
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Check a text with the moderation API
+    ///
+    /// # Returns
+    ///
+    /// The categories the text was flagged for, empty if it passed
+    #[instrument(skip(self, text), fields(length = text.len()))]
+    pub async fn moderate(&self, text: &str) -> Result<Vec<String>, ProcessError> {
+        let response: ModerationResponse = self
+            .client
+            .post(format!("{}/moderations", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({ "model": self.model, "input": text }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut categories = Vec::new();
+        for result in response.results.into_iter().filter(|result| result.flagged) {
+            let flagged: Vec<String> = result
+                .categories
+                .into_iter()
+                .filter(|(_, flagged)| *flagged)
+                .map(|(category, _)| category)
+                .collect();
+            if flagged.is_empty() {
+                categories.push("flagged".to_string());
+            }
+            categories.extend(flagged);
+        }
+        categories.dedup();
+        Ok(categories)
+    }
+}
+
+/// The safety checks run before content enters the index
+#[derive(Debug, Clone, Default)]
+pub struct SafetyFilter {
+    /// Lowercased keywords that fail a chunk
+    keywords: Vec<String>,
+
+    /// Moderation API checking whole pages, if enabled
+    moderation: Option<ModerationClient>,
+
+    /// What happens to failing content
+    pub action: SafetyAction,
+}
+
+impl SafetyFilter {
+    /// Create a filter with no checks
+    pub fn new(action: SafetyAction) -> Self {
+        Self {
+            action,
+            ..Self::default()
+        }
+    }
+
+    /// Fail chunks containing any of the keywords, ignoring case
+    pub fn with_keywords(mut self, keywords: impl IntoIterator<Item = String>) -> Self {
+        self.keywords.extend(
+            keywords
+                .into_iter()
+                .map(|keyword| keyword.trim().to_lowercase())
+                .filter(|keyword| !keyword.is_empty()),
+        );
+        self
+    }
+
+    /// Read keywords from a file with one keyword per line; `#` starts a comment line
+    pub fn with_keyword_file(self, path: &Path) -> Result<Self, ProcessError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ProcessError::Other(format!(
+                "Failed to read keyword list {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let keywords: Vec<String> = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        Ok(self.with_keywords(keywords))
+    }
+
+    /// Check whole pages with a moderation API
+    pub fn with_moderation(mut self, moderation: ModerationClient) -> Self {
+        self.moderation = Some(moderation);
+        self
+    }
+
+    /// Whether any check is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.keywords.is_empty() || self.moderation.is_some()
+    }
+
+    /// Check a page with the moderation API
+    ///
+    /// # Returns
+    ///
+    /// Why the page failed, empty if it passed or no moderation API is configured
+    pub async fn check_page(&self, content: &str) -> Result<Vec<String>, ProcessError> {
+        let Some(moderation) = &self.moderation else {
+            return Ok(Vec::new());
+        };
+        let reasons: Vec<String> = moderation
+            .moderate(content)
+            .await?
+            .into_iter()
+            .map(|category| format!("moderation: {}", category))
+            .collect();
+        if !reasons.is_empty() {
+            debug!("Page failed moderation: {:?}", reasons);
+        }
+        Ok(reasons)
+    }
+
+    /// Check a chunk against the keyword lists
+    ///
+    /// # Returns
+    ///
+    /// Why the chunk failed, empty if it passed
+    pub fn check_chunk(&self, text: &str) -> Vec<String> {
+        if self.keywords.is_empty() {
+            return Vec::new();
+        }
+        let text = text.to_lowercase();
+        self.keywords
+            .iter()
+            .filter(|keyword| text.contains(keyword.as_str()))
+            .map(|keyword| format!("keyword: {}", keyword))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_chunk_keywords() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keywords.txt");
+        std::fs::write(
+            &path,
+            "# blocked terms\nConfidential\n\n  do not distribute \n",
+        )
+        .unwrap();
+
+        let filter = SafetyFilter::new(SafetyAction::Flag)
+            .with_keyword_file(&path)
+            .unwrap();
+        assert!(filter.is_enabled());
+        assert_eq!(
+            filter.check_chunk("CONFIDENTIAL: do not distribute this plan"),
+            vec!["keyword: confidential", "keyword: do not distribute"]
+        );
+        assert!(filter.check_chunk("Public release notes").is_empty());
+        assert!(!SafetyFilter::default().is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_check_page_moderation() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/moderations")
+            .match_header("authorization", "Bearer test-key")
+            .with_status(200)
+            .with_body(
+                r#"{"results": [{"flagged": true, "categories": {"violence": true, "hate": false}}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let filter = SafetyFilter::new(SafetyAction::Drop)
+            .with_moderation(ModerationClient::new("test-key").with_base_url(&server.url()));
+        assert_eq!(
+            filter.check_page("some text").await.unwrap(),
+            vec!["moderation: violence"]
+        );
+        assert!(
+            SafetyFilter::default()
+                .check_page("some text")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_parse_safety_action() {
+        assert_eq!("flag".parse::<SafetyAction>(), Ok(SafetyAction::Flag));
+        assert_eq!(SafetyAction::Drop.to_string(), "drop");
+        assert!("quarantine".parse::<SafetyAction>().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! # Page Segmentation Module
+//!
+//! This module splits a page into sections at its H1 and H2 headings, so long reference
+//! pages covering many unrelated topics can be processed as separate logical documents,
+//! each with its own summary and chunk context.
+//!
+//! ## Key Components
+//!
+//! - `Section`: A part of a page under one H1 or H2 heading
+//! - `segment_by_heading`: Splits Markdown into sections
+//!
+//! Sections with fewer than `MIN_SECTION_WORDS` words, such as a page title directly
+//! followed by its first subheading, are merged into the next section instead of being
+//! summarized on their own. Headings inside fenced code blocks are ignored.
+
+use super::count_words;
+
+/// Fewest words a section needs to be processed on its own
+const MIN_SECTION_WORDS: usize = 50;
+
+/// A part of a page under one H1 or H2 heading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// The H1 heading enclosing an H2 section, which its chunks are not aware of
+    pub parent_heading: Option<String>,
+
+    /// The Markdown of the section, starting with its heading
+    pub content: String,
+}
+
+impl Section {
+    /// Number of whitespace-separated words in the section
+    fn word_count(&self) -> usize {
+        count_words(&self.content)
+    }
+}
+
+/// Split Markdown into sections at its H1 and H2 headings
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown of the page
+///
+/// # Returns
+///
+/// The sections in page order; a page without H1 or H2 headings is a single section
+pub fn segment_by_heading(markdown: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut current = Section {
+        parent_heading: None,
+        content: String::new(),
+    };
+    let mut title: Option<String> = None;
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some((level, heading)) = section_heading(line) {
+            if !current.content.trim().is_empty() {
+                sections.push(current);
+            }
+            if level == 1 {
+                title = Some(heading.to_string());
+            }
+            current = Section {
+                parent_heading: if level == 2 { title.clone() } else { None },
+                content: String::new(),
+            };
+        }
+        current.content.push_str(line);
+        current.content.push('\n');
+    }
+    if !current.content.trim().is_empty() {
+        sections.push(current);
+    }
+
+    merge_short_sections(sections)
+}
+
+/// The level and text of an H1 or H2 ATX heading line
+fn section_heading(line: &str) -> Option<(usize, &str)> {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[hashes..];
+    if !(1..=2).contains(&hashes) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((hashes, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Carry sections too short to stand alone into the next section, or the previous one
+/// at the end of the page
+fn merge_short_sections(sections: Vec<Section>) -> Vec<Section> {
+    let mut merged: Vec<Section> = Vec::new();
+    let mut pending: Option<Section> = None;
+    for section in sections {
+        let section = match pending.take() {
+            Some(mut short) => {
+                short.content.push_str(&section.content);
+                // A carried page title already encloses the chunks of the section
+                if short.parent_heading.is_some() {
+                    short.parent_heading = section.parent_heading;
+                }
+                short
+            }
+            None => section,
+        };
+        if section.word_count() < MIN_SECTION_WORDS {
+            pending = Some(section);
+        } else {
+            merged.push(section);
+        }
+    }
+    if let Some(short) = pending {
+        match merged.last_mut() {
+            Some(last) => last.content.push_str(&short.content),
+            None => merged.push(short),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(count: usize) -> String {
+        vec!["word"; count].join(" ")
+    }
+
+    #[test]
+    fn test_segment_by_heading() {
+        let markdown = format!(
+            "# Reference\n\n## Install\n\n{}\n\n### Linux\n\n{}\n\n## Configure\n\n```sh\n# not a heading\n```\n\n{}\n",
+            words(60),
+            words(10),
+            words(60)
+        );
+
+        let sections = segment_by_heading(&markdown);
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].content.starts_with("# Reference\n\n## Install"));
+        assert!(sections[0].content.contains("### Linux"));
+        assert_eq!(sections[0].parent_heading, None);
+        assert!(sections[1].content.starts_with("## Configure"));
+        assert!(sections[1].content.contains("# not a heading"));
+        assert_eq!(sections[1].parent_heading.as_deref(), Some("Reference"));
+    }
+
+    #[test]
+    fn test_short_sections_are_merged() {
+        let markdown = format!("## Intro\n\n{}\n\n## Notes\n\nShort.\n", words(60));
+        let sections = segment_by_heading(&markdown);
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].content.ends_with("## Notes\n\nShort.\n"));
+
+        assert_eq!(segment_by_heading("Plain text only.").len(), 1);
+        assert!(segment_by_heading("").is_empty());
+    }
+}
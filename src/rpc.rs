@@ -0,0 +1,606 @@
+//! # Editor JSON-RPC Module
+//!
+//! This module serves search, answers and indexing over JSON-RPC 2.0 on stdin and stdout,
+//! for editor plugins that can't run an MCP client. `hal lsp-ish` starts it.
+//!
+//! Messages are framed like the Language Server Protocol, with a `Content-Length` header
+//! before each JSON body, so the JSON-RPC clients editors ship for language servers can
+//! talk to it.
+//!
+//! ## Methods
+//!
+//! - `initialize`: The server name, version and methods
+//! - `search` `{query, limit?, source?}`: The chunks found for a query
+//! - `answer` `{query, limit?, source?}`: An answer generated from the chunks found, with
+//!   its sources
+//! - `index` `{url, content, title?}`: Chunk, embed and store a Markdown document, such as
+//!   the buffer being edited
+//! - `shutdown`: Acknowledged; `exit` then stops the server
+//!
+//! ## Cancellation and Progress
+//!
+//! Requests run concurrently. `$/cancelRequest` `{id}` stops a running request, which is
+//! answered with the error code `-32800`. Long requests send `$/progress` notifications with
+//! `begin`, `report` and `end` values, using the `workDoneToken` of the request or else its
+//! id as the token.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use rig::completion::CompletionModel;
+use rig::embeddings::EmbeddingModel;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinSet};
+use tracing::{debug, warn};
+
+use crate::crawler::{CrawledPage, PageMetadata};
+use crate::index::Database;
+use crate::model::{Client, GenerationOptions};
+use crate::processor::{ProcessorConfig, process_content};
+use crate::search::{
+    SearchOptions, SearchResult, generate_answer_with_rag, prepare_rag_context,
+    search_index_with_client,
+};
+
+/// The message is not valid JSON
+pub const PARSE_ERROR: i64 = -32700;
+
+/// The method does not exist
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The parameters of the method are invalid
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The method failed
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// The request was cancelled with `$/cancelRequest`
+pub const REQUEST_CANCELLED: i64 = -32800;
+
+/// Methods answered by the server
+pub const METHODS: &[&str] = &["initialize", "search", "answer", "index", "shutdown"];
+
+/// A JSON-RPC error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    /// Error code, one of the constants of this module
+    pub code: i64,
+
+    /// Description of the error
+    pub message: String,
+}
+
+impl RpcError {
+    /// Create an error with a code and message
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// An error of a failed method
+    fn internal(error: impl std::fmt::Display) -> Self {
+        Self::new(INTERNAL_ERROR, error.to_string())
+    }
+}
+
+/// An incoming request or notification
+#[derive(Debug, Deserialize)]
+struct Message {
+    /// Id of a request; notifications have none
+    #[serde(default)]
+    id: Option<Value>,
+
+    /// Method called; responses to the server have none
+    #[serde(default)]
+    method: Option<String>,
+
+    /// Parameters of the method
+    #[serde(default)]
+    params: Value,
+}
+
+/// Parameters of `search` and `answer`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryParams {
+    query: String,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    answer_language: Option<String>,
+}
+
+impl QueryParams {
+    fn options(&self) -> SearchOptions {
+        let defaults = SearchOptions::default();
+        SearchOptions {
+            limit: self.limit.unwrap_or(defaults.limit),
+            source_filter: self.source.clone(),
+            ..defaults
+        }
+    }
+}
+
+/// Parameters of `index`
+#[derive(Debug, Deserialize)]
+struct IndexParams {
+    url: String,
+    content: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+/// Sends `$/progress` notifications for a request
+pub struct Progress {
+    sender: mpsc::UnboundedSender<Value>,
+    token: Value,
+}
+
+impl Progress {
+    fn notify(&self, value: Value) {
+        let _ = self.sender.send(json!({
+            "jsonrpc": "2.0",
+            "method": "$/progress",
+            "params": { "token": self.token, "value": value },
+        }));
+    }
+
+    /// Report that the work started
+    pub fn begin(&self, title: &str) {
+        self.notify(json!({ "kind": "begin", "title": title }));
+    }
+
+    /// Report a step of the work
+    pub fn report(&self, message: &str) {
+        self.notify(json!({ "kind": "report", "message": message }));
+    }
+
+    /// Report that the work finished
+    pub fn end(&self, message: &str) {
+        self.notify(json!({ "kind": "end", "message": message }));
+    }
+}
+
+/// The methods of the server, over an index and models
+pub struct Server<C, E>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    db: Database,
+    client: Client<C, E>,
+    index_client: Option<Client<C, E>>,
+    processor_config: ProcessorConfig,
+}
+
+impl<C, E> Server<C, E>
+where
+    C: CompletionModel + Clone + Send + Sync + 'static,
+    E: EmbeddingModel + Clone + Send + Sync + 'static,
+{
+    /// Create a server that searches and answers, without indexing
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The index
+    /// * `client` - Client embedding queries and generating answers
+    pub fn new(db: Database, client: Client<C, E>) -> Self {
+        Self {
+            db,
+            client,
+            index_client: None,
+            processor_config: ProcessorConfig::builder().embedding_dimensions(768).build(),
+        }
+    }
+
+    /// Enable `index`, processing documents with a client and configuration
+    pub fn with_indexing(mut self, client: Client<C, E>, config: ProcessorConfig) -> Self {
+        self.index_client = Some(client);
+        self.processor_config = config;
+        self
+    }
+
+    /// Call a method
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - Name of the method
+    /// * `params` - Parameters of the method
+    /// * `progress` - Notifications for the progress of the request
+    pub async fn handle(
+        &self,
+        method: &str,
+        params: Value,
+        progress: &Progress,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "serverInfo": { "name": "hal", "version": env!("CARGO_PKG_VERSION") },
+                "capabilities": { "methods": METHODS, "index": self.index_client.is_some() },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "search" => {
+                let params: QueryParams = parse_params(params)?;
+                let results = self.search(&params).await?;
+                serde_json::to_value(results).map_err(RpcError::internal)
+            }
+            "answer" => self.answer(parse_params(params)?, progress).await,
+            "index" => self.index(parse_params(params)?, progress).await,
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", method),
+            )),
+        }
+    }
+
+    async fn search(&self, params: &QueryParams) -> Result<Vec<SearchResult>, RpcError> {
+        search_index_with_client(&self.db, &self.client, &params.query, params.options())
+            .await
+            .map_err(RpcError::internal)
+    }
+
+    async fn answer(&self, params: QueryParams, progress: &Progress) -> Result<Value, RpcError> {
+        progress.begin("Answering");
+        progress.report("Searching the index");
+        let results = self.search(&params).await?;
+
+        progress.report(&format!(
+            "Generating an answer from {} sources",
+            results.len()
+        ));
+        let context = prepare_rag_context(&results);
+        let answer = generate_answer_with_rag(
+            &self.client,
+            &params.query,
+            &context,
+            "",
+            &GenerationOptions::default(),
+            params.answer_language.as_deref(),
+        )
+        .await
+        .map_err(RpcError::internal)?;
+        progress.end("Answered");
+
+        Ok(json!({ "query": params.query, "answer": answer, "sources": results }))
+    }
+
+    async fn index(&self, params: IndexParams, progress: &Progress) -> Result<Value, RpcError> {
+        let Some(client) = &self.index_client else {
+            return Err(RpcError::new(
+                INTERNAL_ERROR,
+                "Indexing is disabled on this server",
+            ));
+        };
+        if self.db.is_read_only() {
+            return Err(RpcError::new(INTERNAL_ERROR, "The index is read-only"));
+        }
+
+        let domain = url::Url::parse(&params.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "local".to_string());
+        let page = CrawledPage {
+            url: params.url.clone(),
+            content: params.content,
+            metadata: PageMetadata {
+                title: params.title,
+                description: None,
+                publication_date: None,
+                author: None,
+                domain,
+                tags: Vec::new(),
+                content_type: None,
+            },
+            raw_html: None,
+        };
+
+        progress.begin("Indexing");
+        progress.report("Chunking and embedding");
+        let chunks = process_content(client, page, self.processor_config.clone())
+            .await
+            .map_err(RpcError::internal)?;
+        let count = chunks.len();
+
+        progress.report(&format!("Storing {} chunks", count));
+        self.db
+            .update_website_index(&params.url, chunks)
+            .await
+            .map_err(RpcError::internal)?;
+        progress.end(&format!("Indexed {} chunks", count));
+
+        Ok(json!({ "url": params.url, "chunks": count }))
+    }
+}
+
+/// Deserialize the parameters of a method
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// A response to a request
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+/// Read the body of the next message
+///
+/// # Returns
+///
+/// The body, or `None` at the end of the input
+pub async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> std::io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((_, value)) = line
+            .split_once(':')
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Write a message with its `Content-Length` header
+pub async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    body: &str,
+) -> std::io::Result<()> {
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(body.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Serve requests read from `reader` until `exit` or the end of the input
+///
+/// Requests still running then are finished before returning.
+///
+/// # Arguments
+///
+/// * `server` - The methods to serve
+/// * `reader` - Input of framed requests
+/// * `writer` - Output for framed responses and notifications
+pub async fn serve<C, E, R, W>(server: Server<C, E>, mut reader: R, writer: W) -> anyhow::Result<()>
+where
+    C: CompletionModel + Clone + Send + Sync + 'static,
+    E: EmbeddingModel + Clone + Send + Sync + 'static,
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let server = Arc::new(server);
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Value>();
+    let writer_task = tokio::spawn(async move {
+        let mut writer = writer;
+        while let Some(message) = receiver.recv().await {
+            write_message(&mut writer, &message.to_string()).await?;
+        }
+        Ok::<_, std::io::Error>(())
+    });
+
+    // Requests being handled, by the JSON of their id
+    let running: Arc<Mutex<HashMap<String, AbortHandle>>> = Arc::default();
+    let mut tasks = JoinSet::new();
+
+    while let Some(body) = read_message(&mut reader).await? {
+        let message: Message = match serde_json::from_str(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, e.to_string());
+                let _ = sender.send(response(Value::Null, Err(error)));
+                continue;
+            }
+        };
+        let Some(method) = message.method else {
+            continue;
+        };
+        debug!("Received {}", method);
+
+        match method.as_str() {
+            "exit" => break,
+            "$/cancelRequest" => {
+                let Some(id) = message.params.get("id") else {
+                    continue;
+                };
+                let cancelled = running.lock().unwrap().remove(&id.to_string());
+                if let Some(task) = cancelled {
+                    task.abort();
+                    let error = RpcError::new(REQUEST_CANCELLED, "Request cancelled");
+                    let _ = sender.send(response(id.clone(), Err(error)));
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        // Other notifications need no answer
+        let Some(id) = message.id else {
+            continue;
+        };
+        let key = id.to_string();
+        let progress = Progress {
+            sender: sender.clone(),
+            token: message
+                .params
+                .get("workDoneToken")
+                .cloned()
+                .unwrap_or_else(|| id.clone()),
+        };
+
+        // Register the task before it can finish, so exactly one response is sent: by the
+        // task, or by the cancellation if it removed the task first
+        let mut running_tasks = running.lock().unwrap();
+        let task = tasks.spawn({
+            let server = server.clone();
+            let sender = sender.clone();
+            let running = running.clone();
+            let key = key.clone();
+            async move {
+                let result = server.handle(&method, message.params, &progress).await;
+                if let Err(error) = &result {
+                    warn!("{} failed: {}", method, error.message);
+                }
+                if running.lock().unwrap().remove(&key).is_some() {
+                    let _ = sender.send(response(id, result));
+                }
+            }
+        });
+        running_tasks.insert(key, task);
+    }
+
+    // Finish the requests still running before closing the output
+    while tasks.join_next().await.is_some() {}
+    drop(sender);
+    writer_task.await??;
+    Ok(())
+}
+
+/// Serve requests on stdin and stdout until `exit`
+pub async fn run<C, E>(server: Server<C, E>) -> anyhow::Result<()>
+where
+    C: CompletionModel + Clone + Send + Sync + 'static,
+    E: EmbeddingModel + Clone + Send + Sync + 'static,
+{
+    serve(
+        server,
+        tokio::io::BufReader::new(tokio::io::stdin()),
+        tokio::io::stdout(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock_model::{MockCompletionModel, MockEmbeddingModel};
+    use tokio::io::BufReader;
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    async fn mock_server() -> (
+        tempfile::TempDir,
+        Server<MockCompletionModel, MockEmbeddingModel>,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_from_path(&dir.path().join("index.db").to_string_lossy())
+            .await
+            .unwrap();
+        (dir, Server::new(db, Client::new_mock()))
+    }
+
+    /// Serve the framed input and return the bodies written, in order
+    async fn exchange(input: String) -> Vec<Value> {
+        let (_dir, server) = mock_server().await;
+        let (mut client, server_output) = tokio::io::duplex(64 * 1024);
+        serve(server, BufReader::new(input.as_bytes()), server_output)
+            .await
+            .unwrap();
+
+        let mut output = BufReader::new(&mut client);
+        let mut messages = Vec::new();
+        while let Some(body) = read_message(&mut output).await.unwrap() {
+            messages.push(serde_json::from_str(&body).unwrap());
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_read_and_write_message() {
+        let mut output = Vec::new();
+        write_message(&mut output, r#"{"a":"é"}"#).await.unwrap();
+        assert!(output.starts_with(b"Content-Length: 10\r\n\r\n"));
+
+        let mut reader = BufReader::new(output.as_slice());
+        assert_eq!(
+            read_message(&mut reader).await.unwrap().as_deref(),
+            Some(r#"{"a":"é"}"#)
+        );
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_and_unknown_method() {
+        let input = [
+            frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "id": 2, "method": "missing" })),
+            frame(json!({ "jsonrpc": "2.0", "id": 3, "method": "search", "params": {} })),
+        ]
+        .concat();
+
+        let mut messages = exchange(input).await;
+        messages.sort_by_key(|message| message["id"].as_i64());
+
+        assert_eq!(messages[0]["result"]["serverInfo"]["name"], "hal");
+        assert_eq!(messages[0]["result"]["capabilities"]["index"], false);
+        assert_eq!(messages[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(messages[2]["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_parse_error_and_exit() {
+        let input = [
+            "Content-Length: 8\r\n\r\nnot json".to_string(),
+            frame(json!({ "jsonrpc": "2.0", "method": "exit" })),
+            frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })),
+        ]
+        .concat();
+
+        let messages = exchange(input).await;
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["id"], Value::Null);
+        assert_eq!(messages[0]["error"]["code"], PARSE_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_index_is_disabled_without_client() {
+        let (_dir, server) = mock_server().await;
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let progress = Progress {
+            sender,
+            token: json!(1),
+        };
+
+        let error = server
+            .handle(
+                "index",
+                json!({ "url": "file:///notes.md", "content": "# Notes" }),
+                &progress,
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code, INTERNAL_ERROR);
+    }
+}
@@ -9,6 +9,24 @@
 //! - `SearchSystem`: Main interface for performing semantic searches
 //! - `SearchOptions`: Configuration for filtering and limiting search results
 //! - `SearchResult`: Represents a retrieved document with its metadata
+//! - `FreshnessWeighting`: Decays scores of old sources so recent content ranks higher
+//! - `access`: API keys with per-key rate limits and collection-level access control
+//! - `answer_cache`: Reuses answers for queries similar to ones answered before
+//! - `bench`: Compares embedding models on recall, latency and cost
+//! - `code`: Searches code indexed with `hal index-code`, returning `file:line` hits
+//! - `compare`: Answers comparison queries with a table, retrieving sources for each item
+//! - `condense`: Rewrites a chat message into a standalone query using the conversation
+//! - `feedback`: Stores relevance judgments used to boost or penalize results
+//! - `follow_up`: Suggests follow-up questions the sources of an answer can answer
+//! - `history`: Records past searches and their sources for auditing and replay
+//! - `language`: Translates queries into the languages of the documents for cross-lingual search
+//! - `long_context`: Sends whole pages instead of chunks to models with large context windows
+//! - `map_reduce`: Answers broad questions per source page, then combines the answers
+//! - `sanitize`: Guards the RAG prompt against instructions planted in retrieved content
+//! - `snippets`: Picks the sentences of each result matching the query and highlights its terms
+//! - `structured`: RAG answers conforming to a user-supplied JSON schema
+//! - `usage`: Counts how often chunks are retrieved, to find sources that are never used
+//! - `collapse_versions`: Keeps a single copy of pages published under several doc versions
 //!
 //! ## Features
 //!
@@ -31,13 +49,33 @@
 //! This module bridges the gap between the vector database and the LLM,
 //! enabling knowledge augmentation through efficient semantic retrieval.
 
+pub mod access;
+pub mod answer_cache;
+pub mod bench;
+pub mod code;
+pub mod compare;
+pub mod condense;
 mod error;
+pub mod feedback;
+pub mod follow_up;
+pub mod history;
+pub mod language;
+pub mod long_context;
+pub mod map_reduce;
+mod ranking;
+pub mod sanitize;
 mod search_impl;
+pub mod snippets;
+pub mod structured;
+pub mod usage;
 
+pub use condense::condense_query;
 pub use error::SearchError;
+pub use ranking::{FreshnessWeighting, apply_feedback, apply_freshness, collapse_versions};
 pub use search_impl::{
-    SearchOptions, SearchResult, generate_answer_with_rag, prepare_rag_context, search_index,
-    search_index_with_client,
+    SearchOptions, SearchResult, answer_prompt, embed_query, generate_answer_with_rag,
+    prepare_rag_context, search_federated, search_index, search_index_with_client,
+    search_index_with_embedding,
 };
 
 /// Re-export types needed for the search API
@@ -96,12 +134,29 @@ mod tests {
         let options = SearchOptions {
             limit: 10,
             source_filter: Some("example.com".to_string()),
+            exact_source: true,
             date_range: Some((1000, 2000)),
+            published_range: Some((1500, 1800)),
+            content_type: Some("BlogPosting".to_string()),
+            freshness: None,
+            doc_version: Some("v2".to_string()),
+            collapse_versions: false,
+            preferred_version: None,
+            exclude_sources: vec!["example.com/archive".to_string()],
+            exclude_terms: vec!["deprecated".to_string()],
+            ignore_feedback: true,
+            target_languages: vec!["de".to_string()],
         };
 
         assert_eq!(options.limit, 10);
         assert_eq!(options.source_filter.as_deref().unwrap(), "example.com");
+        assert!(options.exact_source);
         assert_eq!(options.date_range.unwrap(), (1000, 2000));
+        assert_eq!(options.published_range.unwrap(), (1500, 1800));
+        assert_eq!(options.doc_version.as_deref(), Some("v2"));
+        assert_eq!(options.exclude_sources, vec!["example.com/archive"]);
+        assert_eq!(options.exclude_terms, vec!["deprecated"]);
+        assert_eq!(options.target_languages, vec!["de"]);
     }
 
     #[test]
@@ -110,7 +165,26 @@ mod tests {
 
         assert_eq!(options.limit, 10);
         assert!(options.source_filter.is_none());
+        assert!(!options.exact_source);
         assert!(options.date_range.is_none());
+        assert!(options.published_range.is_none());
+        assert!(options.freshness.is_none());
+        assert!(options.doc_version.is_none());
+        assert!(!options.collapse_versions);
+        assert!(options.exclude_sources.is_empty());
+        assert!(options.exclude_terms.is_empty());
+        assert!(!options.ignore_feedback);
+        assert!(options.target_languages.is_empty());
+    }
+
+    #[test]
+    fn test_answer_prompt() {
+        let prompt = crate::search::answer_prompt("<source id=\"1\">…</source>", "Wie?", None);
+        assert!(prompt.ends_with("Question: Wie?\n\nAnswer:"));
+
+        let prompt = crate::search::answer_prompt("", "Wie?", Some("French"));
+        assert!(prompt.contains("Write the answer in French"));
+        assert!(prompt.ends_with("Answer:"));
     }
 
     // Note: We're skipping the SearchSystem test as it requires a real Database instance
@@ -0,0 +1,375 @@
+//! # Access Control Module
+//!
+//! This module lets one HAL instance serve several consumers with separate API keys.
+//! Each key has its own rate limit and may be restricted to a set of collections, so a
+//! consumer only ever sees results from the websites it was granted.
+//!
+//! ## Key Components
+//!
+//! - `ApiKeyConfig` / `AccessConfig`: Keys as stored in the JSON config file
+//! - `AccessControl`: Authenticates requests and enforces the per-key limits
+//! - `ApiKey`: An authenticated key, used to scope search options to its collections
+//!
+//! ## Config File
+//!
+//! ```json
+//! {
+//!   "keys": [
+//!     { "name": "docs-bot", "key": "…", "requests_per_minute": 60,
+//!       "collections": ["docs.example.com"] },
+//!     { "name": "admin", "key": "…" }
+//!   ]
+//! }
+//! ```
+//!
+//! A key without `collections` may search every collection, and a key without
+//! `requests_per_minute` is not rate limited. The MCP server (`hal mcp --access-config`)
+//! reads the key of a session from [`API_KEY_VAR`].
+
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::{Deserialize, Serialize};
+
+use super::error::SearchError;
+use super::search_impl::SearchOptions;
+
+/// Environment variable holding the API key of an MCP session
+pub const API_KEY_VAR: &str = "HAL_API_KEY";
+
+/// An API key as configured
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Name of the consumer, used in logs instead of the key
+    pub name: String,
+
+    /// The secret key sent by the consumer
+    pub key: String,
+
+    /// Maximum number of requests per minute, unlimited if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+
+    /// Domains of the collections the key may search, all if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
+}
+
+/// The API keys of a server
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccessConfig {
+    /// The configured keys
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+impl AccessConfig {
+    /// Load the keys from a JSON config file
+    pub fn load(path: &Path) -> Result<Self, SearchError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            SearchError::InvalidParameters(format!(
+                "Failed to read access config {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let config: Self = serde_json::from_str(&contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that keys are non-empty and unique
+    fn validate(&self) -> Result<(), SearchError> {
+        for (i, key) in self.keys.iter().enumerate() {
+            if key.key.is_empty() {
+                return Err(SearchError::InvalidParameters(format!(
+                    "API key '{}' is empty",
+                    key.name
+                )));
+            }
+            if self.keys[..i].iter().any(|other| other.key == key.key) {
+                return Err(SearchError::InvalidParameters(format!(
+                    "API key '{}' is configured twice",
+                    key.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An authenticated API key
+pub struct ApiKey {
+    config: ApiKeyConfig,
+    limiter: Option<DefaultDirectRateLimiter>,
+}
+
+impl ApiKey {
+    /// Name of the consumer
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Whether the key may search the collection of a domain
+    pub fn allows(&self, domain: &str) -> bool {
+        self.config.collections.is_empty()
+            || self
+                .config
+                .collections
+                .iter()
+                .any(|collection| collection.eq_ignore_ascii_case(domain))
+    }
+
+    /// Check the source filter of a search against the key's collections
+    ///
+    /// A restricted key must name one of its collections as the source filter, which is
+    /// then matched against whole domains, so a key for `a.com` does not also search
+    /// `xa.com` or `a.com.example.org`.
+    pub fn authorize_search(&self, options: &mut SearchOptions) -> Result<(), SearchError> {
+        if self.config.collections.is_empty() {
+            return Ok(());
+        }
+        match &options.source_filter {
+            Some(source) if self.allows(source) => {
+                options.exact_source = true;
+                Ok(())
+            }
+            Some(source) => Err(SearchError::AccessDenied(format!(
+                "key '{}' may not search {}",
+                self.name(),
+                source
+            ))),
+            None => Err(SearchError::AccessDenied(format!(
+                "key '{}' must name a collection, one of: {}",
+                self.name(),
+                self.config.collections.join(", ")
+            ))),
+        }
+    }
+}
+
+/// Authenticates requests and enforces per-key rate limits
+pub struct AccessControl {
+    keys: Vec<ApiKey>,
+}
+
+impl AccessControl {
+    /// Create the access control for the configured keys
+    pub fn new(config: AccessConfig) -> Self {
+        let keys = config
+            .keys
+            .into_iter()
+            .map(|config| ApiKey {
+                limiter: config
+                    .requests_per_minute
+                    .and_then(NonZeroU32::new)
+                    .map(|limit| RateLimiter::direct(Quota::per_minute(limit))),
+                config,
+            })
+            .collect();
+        Self { keys }
+    }
+
+    /// Authenticate a request and count it against the key's rate limit
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The API key sent with the request, if any
+    ///
+    /// # Returns
+    ///
+    /// The authenticated key, or `AccessDenied` for a missing or unknown key and
+    /// `RateLimited` when the key exceeded its limit
+    pub fn authenticate(&self, key: Option<&str>) -> Result<&ApiKey, SearchError> {
+        let key = key.ok_or_else(|| SearchError::AccessDenied("missing API key".to_string()))?;
+        let api_key = self
+            .keys
+            .iter()
+            .find(|candidate| constant_time_eq(candidate.config.key.as_bytes(), key.as_bytes()))
+            .ok_or_else(|| SearchError::AccessDenied("unknown API key".to_string()))?;
+
+        if api_key
+            .limiter
+            .as_ref()
+            .is_some_and(|limiter| limiter.check().is_err())
+        {
+            return Err(SearchError::RateLimited(format!(
+                "key '{}' allows {} requests per minute",
+                api_key.name(),
+                api_key.config.requests_per_minute.unwrap_or_default()
+            )));
+        }
+        Ok(api_key)
+    }
+}
+
+/// Compare two secrets without returning early on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access() -> AccessControl {
+        AccessControl::new(AccessConfig {
+            keys: vec![
+                ApiKeyConfig {
+                    name: "docs-bot".to_string(),
+                    key: "docs-secret".to_string(),
+                    requests_per_minute: Some(2),
+                    collections: vec!["docs.example.com".to_string()],
+                },
+                ApiKeyConfig {
+                    name: "admin".to_string(),
+                    key: "admin-secret".to_string(),
+                    requests_per_minute: None,
+                    collections: Vec::new(),
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_authenticate_and_rate_limit() {
+        let access = access();
+        assert!(matches!(
+            access.authenticate(None),
+            Err(SearchError::AccessDenied(_))
+        ));
+        assert!(matches!(
+            access.authenticate(Some("wrong")),
+            Err(SearchError::AccessDenied(_))
+        ));
+
+        assert_eq!(
+            access.authenticate(Some("docs-secret")).unwrap().name(),
+            "docs-bot"
+        );
+        access.authenticate(Some("docs-secret")).unwrap();
+        assert!(matches!(
+            access.authenticate(Some("docs-secret")),
+            Err(SearchError::RateLimited(_))
+        ));
+
+        for _ in 0..10 {
+            access.authenticate(Some("admin-secret")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_collection_access() {
+        let access = access();
+        let docs = access.authenticate(Some("docs-secret")).unwrap();
+        let admin = access.authenticate(Some("admin-secret")).unwrap();
+
+        let mut options = SearchOptions::default();
+        assert!(docs.authorize_search(&mut options).is_err());
+        assert!(admin.authorize_search(&mut options).is_ok());
+
+        options.source_filter = Some("docs.example.com".to_string());
+        assert!(admin.authorize_search(&mut options).is_ok());
+        assert!(!options.exact_source);
+        assert!(docs.authorize_search(&mut options).is_ok());
+        assert!(options.exact_source);
+        options.source_filter = Some("wiki.example.com".to_string());
+        assert!(docs.authorize_search(&mut options).is_err());
+
+        assert!(docs.allows("DOCS.example.com"));
+        assert!(!docs.allows("example.com"));
+        assert!(admin.allows("anything.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_scoped_key_searches_whole_domains() {
+        use crate::index::{Database, IndexedChunk, Website};
+        use crate::model::Client;
+        use crate::search::search_index_with_client;
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_from_path(&dir.path().join("index.db").to_string_lossy())
+            .await
+            .unwrap();
+        for domain in [
+            "docs.example.com",
+            "xdocs.example.com",
+            "docs.example.com.evil.org",
+        ] {
+            let website_id = db
+                .add_website(&Website {
+                    id: 0,
+                    url: format!("https://{}", domain),
+                    domain: domain.to_string(),
+                    first_index_date: 0,
+                    last_index_date: 0,
+                    page_count: 0,
+                    status: "active".to_string(),
+                })
+                .await
+                .unwrap();
+            db.add_chunk(&IndexedChunk {
+                id: 0,
+                website_id,
+                url: format!("https://{}/setup", domain),
+                text: "How to set up the crawler".to_string(),
+                context: String::new(),
+                embedding: rig::embeddings::Embedding {
+                    document: String::new(),
+                    vec: vec![0.1; 768],
+                },
+                position: 0,
+                heading: None,
+                heading_path: None,
+                author: None,
+                published_at: None,
+                content_type: None,
+                doc_version: None,
+            })
+            .await
+            .unwrap();
+        }
+
+        let access = access();
+        let docs = access.authenticate(Some("docs-secret")).unwrap();
+        let mut options = SearchOptions {
+            source_filter: Some("docs.example.com".to_string()),
+            ..Default::default()
+        };
+        let client = Client::new_mock();
+        let unscoped = search_index_with_client(&db, &client, "setup", options.clone())
+            .await
+            .unwrap();
+        assert_eq!(unscoped.len(), 3);
+
+        docs.authorize_search(&mut options).unwrap();
+        let scoped = search_index_with_client(&db, &client, "setup", options)
+            .await
+            .unwrap();
+        let domains: Vec<&str> = scoped
+            .iter()
+            .map(|result| result.website_domain.as_str())
+            .collect();
+        assert_eq!(domains, vec!["docs.example.com"]);
+    }
+
+    #[test]
+    fn test_duplicate_keys_rejected() {
+        let key = ApiKeyConfig {
+            name: "a".to_string(),
+            key: "same".to_string(),
+            requests_per_minute: None,
+            collections: Vec::new(),
+        };
+        let config = AccessConfig {
+            keys: vec![
+                key.clone(),
+                ApiKeyConfig {
+                    name: "b".to_string(),
+                    ..key
+                },
+            ],
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! # Semantic Answer Cache Module
+//!
+//! This module caches generated answers by the embedding of their query. A new query
+//! whose embedding is close enough to a cached one reuses the cached answer instead of
+//! running retrieval and generation again, which saves most of the cost of repeated
+//! FAQ-style questions.
+//!
+//! ## Key Components
+//!
+//! - `AnswerCacheConfig`: Similarity threshold and time-to-live of cached answers
+//! - `CachedAnswer`: A cached answer with the sources it was generated from
+//! - `AnswerKey`: Model, search options and sampling parameters an answer is cached under
+//! - `lookup_cached_answer`: Finds a cached answer for a query embedding
+//! - `store_cached_answer`: Caches a generated answer
+//!
+//! ## Invalidation
+//!
+//! A cached answer is only reused when it was generated with the same model, search
+//! options and sampling parameters, is younger than the TTL, and none of its sources were
+//! re-indexed since. Re-indexing a page replaces its chunks, so a source is stale once any
+//! of the cached chunk IDs no longer exists.
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::error::SearchError;
+use super::history::HistorySource;
+use super::search_impl::{SearchOptions, SearchResult};
+use crate::index::Database;
+use crate::model::GenerationOptions;
+
+/// Default minimum cosine similarity between queries to reuse an answer
+pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.95;
+
+/// Default time-to-live of cached answers in seconds (one week)
+pub const DEFAULT_TTL_SECONDS: i64 = 7 * 86_400;
+
+/// Configuration of the answer cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnswerCacheConfig {
+    /// Minimum cosine similarity between the new and cached query
+    pub similarity_threshold: f64,
+
+    /// Maximum age of a cached answer in seconds
+    pub ttl_seconds: i64,
+}
+
+impl Default for AnswerCacheConfig {
+    fn default() -> Self {
+        Self {
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
+            ttl_seconds: DEFAULT_TTL_SECONDS,
+        }
+    }
+}
+
+/// A cached answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedAnswer {
+    /// ID of the cache entry
+    pub id: i64,
+
+    /// The query the answer was generated for
+    pub query: String,
+
+    /// The cached answer
+    pub answer: String,
+
+    /// The results the answer was generated from
+    pub sources: Vec<HistorySource>,
+
+    /// Cosine similarity between the cached and the new query
+    pub similarity: f64,
+
+    /// When the answer was generated as a Unix timestamp
+    pub created_at: i64,
+}
+
+/// What an answer was generated with; only answers with the same key are reused
+#[derive(Debug, Clone, Copy)]
+pub struct AnswerKey<'a> {
+    /// The model that generated the answer
+    pub model: &'a str,
+
+    /// The search options the answer was generated with
+    pub options: &'a SearchOptions,
+
+    /// The sampling parameters the answer was generated with
+    pub generation: &'a GenerationOptions,
+}
+
+impl AnswerKey<'_> {
+    /// The search options and sampling parameters, as stored in the `options` column
+    fn options(&self) -> Result<String, SearchError> {
+        Ok(serde_json::to_string(&(self.options, self.generation))?)
+    }
+}
+
+/// Find a cached answer for a query embedding
+///
+/// # Arguments
+///
+/// * `db` - The database holding the cache
+/// * `embedding_blob` - The query embedding as a binary blob
+/// * `key` - What the answer would be generated with
+/// * `config` - Similarity threshold and TTL
+///
+/// # Returns
+///
+/// The most similar valid cached answer, or `None` on a cache miss
+pub async fn lookup_cached_answer(
+    db: &Database,
+    embedding_blob: &[u8],
+    key: AnswerKey<'_>,
+    config: &AnswerCacheConfig,
+) -> Result<Option<CachedAnswer>, SearchError> {
+    let now = chrono::Utc::now().timestamp();
+    let params: Vec<libsql::Value> = vec![
+        libsql::Value::Blob(embedding_blob.to_vec()),
+        (now - config.ttl_seconds).into(),
+        key.model.to_string().into(),
+        key.options()?.into(),
+    ];
+
+    let mut rows = db
+        .execute_query(
+            "SELECT id, query, answer, sources, created_at,
+                1 - vector_distance_cos(embedding, ?) AS similarity
+             FROM answer_cache
+             WHERE created_at >= ? AND model = ? AND options = ?
+             ORDER BY similarity DESC
+             LIMIT 1",
+            params,
+        )
+        .await?;
+
+    let Some(row) = rows.next().await? else {
+        return Ok(None);
+    };
+
+    let similarity: f64 = row.get(5)?;
+    if similarity < config.similarity_threshold {
+        debug!("Closest cached answer has similarity {:.3}", similarity);
+        return Ok(None);
+    }
+
+    let sources: String = row.get(3)?;
+    let cached = CachedAnswer {
+        id: row.get(0)?,
+        query: row.get(1)?,
+        answer: row.get(2)?,
+        sources: serde_json::from_str(&sources)?,
+        similarity,
+        created_at: row.get(4)?,
+    };
+
+    if !sources_unchanged(db, &cached.sources).await? {
+        debug!("Cached answer {} has re-indexed sources", cached.id);
+        db.execute_query(
+            "DELETE FROM answer_cache WHERE id = ?",
+            vec![libsql::Value::from(cached.id)],
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    Ok(Some(cached))
+}
+
+/// Cache a generated answer
+///
+/// # Arguments
+///
+/// * `db` - The database holding the cache
+/// * `query` - The query the answer was generated for
+/// * `embedding_blob` - The query embedding as a binary blob
+/// * `key` - What the answer was generated with
+/// * `answer` - The generated answer
+/// * `results` - The results the answer was generated from
+///
+/// # Returns
+///
+/// The ID of the new cache entry
+pub async fn store_cached_answer(
+    db: &Database,
+    query: &str,
+    embedding_blob: &[u8],
+    key: AnswerKey<'_>,
+    answer: &str,
+    results: &[SearchResult],
+) -> Result<i64, SearchError> {
+    let sources: Vec<HistorySource> = results.iter().map(HistorySource::from).collect();
+    let params: Vec<libsql::Value> = vec![
+        query.to_string().into(),
+        libsql::Value::Blob(embedding_blob.to_vec()),
+        key.model.to_string().into(),
+        key.options()?.into(),
+        answer.to_string().into(),
+        serde_json::to_string(&sources)?.into(),
+        chrono::Utc::now().timestamp().into(),
+    ];
+
+    let mut rows = db
+        .execute_query(
+            "INSERT INTO answer_cache (query, embedding, model, options, answer, sources, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id",
+            params,
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get(0)?),
+        None => Err(SearchError::ResultProcessing(
+            "Failed to get answer cache ID".to_string(),
+        )),
+    }
+}
+
+/// Whether all source chunks of a cached answer still exist
+async fn sources_unchanged(db: &Database, sources: &[HistorySource]) -> Result<bool, SearchError> {
+    if sources.is_empty() {
+        return Ok(true);
+    }
+
+    let placeholders = vec!["?"; sources.len()].join(", ");
+    let sql = format!("SELECT COUNT(*) FROM chunks WHERE id IN ({})", placeholders);
+    let params: Vec<libsql::Value> = sources.iter().map(|s| s.chunk_id.into()).collect();
+
+    let mut rows = db.execute_query(&sql, params).await?;
+    let count: i64 = match rows.next().await? {
+        Some(row) => row.get(0)?,
+        None => 0,
+    };
+    Ok(count as usize == sources.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::EmbeddingConversion;
+    use rig::embeddings::Embedding;
+    use tempfile::tempdir;
+
+    fn embedding_blob(first: f64) -> Vec<u8> {
+        let mut vec = vec![0.0; 768];
+        vec[0] = first;
+        vec[1] = 1.0;
+        Embedding {
+            document: String::new(),
+            vec,
+        }
+        .to_binary()
+    }
+
+    #[tokio::test]
+    async fn test_store_and_lookup() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("cache.db");
+        let db = Database::new_from_path(&db_path.to_string_lossy())
+            .await
+            .unwrap();
+        let options = SearchOptions::default();
+        let generation = GenerationOptions::default();
+        let key = AnswerKey {
+            model: "model",
+            options: &options,
+            generation: &generation,
+        };
+        let config = AnswerCacheConfig::default();
+
+        let id = store_cached_answer(
+            &db,
+            "how do I install?",
+            &embedding_blob(0.0),
+            key,
+            "Run the installer.",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let cached = lookup_cached_answer(&db, &embedding_blob(0.01), key, &config)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.id, id);
+        assert_eq!(cached.answer, "Run the installer.");
+        assert!(cached.similarity > 0.99);
+
+        // Dissimilar queries, other models, other options and other sampling parameters miss
+        assert!(
+            lookup_cached_answer(&db, &embedding_blob(5.0), key, &config)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            lookup_cached_answer(
+                &db,
+                &embedding_blob(0.0),
+                AnswerKey {
+                    model: "other",
+                    ..key
+                },
+                &config
+            )
+            .await
+            .unwrap()
+            .is_none()
+        );
+        let filtered = SearchOptions {
+            source_filter: Some("example.com".to_string()),
+            ..SearchOptions::default()
+        };
+        assert!(
+            lookup_cached_answer(
+                &db,
+                &embedding_blob(0.0),
+                AnswerKey {
+                    options: &filtered,
+                    ..key
+                },
+                &config
+            )
+            .await
+            .unwrap()
+            .is_none()
+        );
+        let bounded = GenerationOptions {
+            max_tokens: Some(50),
+            ..GenerationOptions::default()
+        };
+        assert!(
+            lookup_cached_answer(
+                &db,
+                &embedding_blob(0.0),
+                AnswerKey {
+                    generation: &bounded,
+                    ..key
+                },
+                &config
+            )
+            .await
+            .unwrap()
+            .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_skips_reindexed_sources() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("cache.db");
+        let db = Database::new_from_path(&db_path.to_string_lossy())
+            .await
+            .unwrap();
+        let options = SearchOptions::default();
+        let generation = GenerationOptions::default();
+        let key = AnswerKey {
+            model: "model",
+            options: &options,
+            generation: &generation,
+        };
+
+        let result = SearchResult {
+            chunk_id: 42,
+            text: "text".to_string(),
+            context: "context".to_string(),
+            url: "https://example.com/page".to_string(),
+            website_url: "https://example.com".to_string(),
+            website_domain: "example.com".to_string(),
+            score: 0.9,
+            ..Default::default()
+        };
+        store_cached_answer(&db, "query", &embedding_blob(0.0), key, "answer", &[result])
+            .await
+            .unwrap();
+
+        // Chunk 42 does not exist, as if its page had been re-indexed
+        let cached = lookup_cached_answer(
+            &db,
+            &embedding_blob(0.0),
+            key,
+            &AnswerCacheConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(cached.is_none());
+    }
+}
@@ -0,0 +1,330 @@
+//! # Embedding Benchmark Module
+//!
+//! This module compares embedding models on a sample of the indexed chunks before
+//! committing to a full re-embed. Each model embeds the same sample and answers the same
+//! evaluation questions, and the report shows retrieval quality next to latency and cost.
+//!
+//! ## Key Components
+//!
+//! - `EvalQuestion`: A query with a chunk known to answer it
+//! - `EmbeddingBenchmark`: Recall, latency and cost of one model
+//! - `eval_questions`: Builds the question set from relevance feedback
+//! - `sample_chunks`: Samples chunks, always including the ones the questions refer to
+//! - `benchmark_embedding_model`: Runs the benchmark for one model
+//!
+//! ## Evaluation Set
+//!
+//! The questions are the queries of results marked relevant with `hal feedback`. A
+//! question counts as recalled when its relevant chunk is among the `k` sampled chunks
+//! most similar to the query.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use rig::embeddings::EmbeddingModel;
+use serde::{Deserialize, Serialize};
+
+use super::error::SearchError;
+use crate::index::Database;
+
+/// A query with a chunk known to answer it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalQuestion {
+    /// The query
+    pub query: String,
+
+    /// ID of a chunk judged relevant for the query
+    pub chunk_id: i64,
+}
+
+/// Benchmark results of one embedding model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingBenchmark {
+    /// Name of the model
+    pub model: String,
+
+    /// Dimensions of the model's embeddings
+    pub dimensions: usize,
+
+    /// Number of chunks embedded
+    pub chunks: usize,
+
+    /// Number of evaluation questions answered
+    pub questions: usize,
+
+    /// Fraction of questions whose relevant chunk was in the top `k`, if there were any
+    pub recall: Option<f64>,
+
+    /// Number of results considered for recall
+    pub k: usize,
+
+    /// Average time to embed a chunk in milliseconds
+    pub chunk_latency_ms: f64,
+
+    /// Average time to embed a query in milliseconds
+    pub query_latency_ms: f64,
+
+    /// Estimated number of tokens embedded
+    pub estimated_tokens: usize,
+
+    /// Estimated cost in USD, if a price was given
+    pub estimated_cost: Option<f64>,
+}
+
+/// Build the evaluation question set from relevance feedback
+///
+/// Only judgments marked relevant that recorded their query are used.
+pub async fn eval_questions(db: &Database) -> Result<Vec<EvalQuestion>, SearchError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT DISTINCT f.query, f.chunk_id FROM feedback f
+             JOIN chunks c ON c.id = f.chunk_id
+             WHERE f.relevant = 1 AND f.query IS NOT NULL AND f.query != ''
+             ORDER BY f.chunk_id",
+            Vec::<libsql::Value>::new(),
+        )
+        .await?;
+
+    let mut questions = Vec::new();
+    while let Some(row) = rows.next().await? {
+        questions.push(EvalQuestion {
+            query: row.get(0)?,
+            chunk_id: row.get(1)?,
+        });
+    }
+    Ok(questions)
+}
+
+/// Sample chunks to embed
+///
+/// # Arguments
+///
+/// * `db` - The database to sample from
+/// * `size` - Number of chunks in the sample
+/// * `questions` - Evaluation questions whose chunks must be part of the sample
+///
+/// # Returns
+///
+/// Pairs of chunk ID and text. The chunks of the questions come first and the rest of
+/// the sample is drawn at random.
+pub async fn sample_chunks(
+    db: &Database,
+    size: usize,
+    questions: &[EvalQuestion],
+) -> Result<Vec<(i64, String)>, SearchError> {
+    let mut sample = Vec::new();
+    let mut seen = HashSet::new();
+
+    let required: Vec<i64> = questions
+        .iter()
+        .map(|q| q.chunk_id)
+        .filter(|id| seen.insert(*id))
+        .collect();
+    if !required.is_empty() {
+        let placeholders = vec!["?"; required.len()].join(", ");
+        let sql = format!("SELECT id, text FROM chunks WHERE id IN ({})", placeholders);
+        let params: Vec<libsql::Value> = required.iter().map(|id| (*id).into()).collect();
+
+        let mut rows = db.execute_query(&sql, params).await?;
+        while let Some(row) = rows.next().await? {
+            let text = db.decode_text(row.get_value(1)?).await?;
+            sample.push((row.get(0)?, text));
+        }
+    }
+
+    let mut rows = db
+        .execute_query(
+            "SELECT id, text FROM chunks ORDER BY RANDOM() LIMIT ?",
+            vec![libsql::Value::from((size + seen.len()) as i64)],
+        )
+        .await?;
+    while sample.len() < size {
+        let Some(row) = rows.next().await? else {
+            break;
+        };
+        let id: i64 = row.get(0)?;
+        if seen.insert(id) {
+            sample.push((id, db.decode_text(row.get_value(1)?).await?));
+        }
+    }
+
+    Ok(sample)
+}
+
+/// Benchmark an embedding model on a chunk sample
+///
+/// # Arguments
+///
+/// * `name` - Name of the model, used in the report
+/// * `model` - The embedding model
+/// * `chunks` - The sampled chunks as pairs of chunk ID and text
+/// * `questions` - The evaluation questions; questions whose chunk is not sampled are skipped
+/// * `k` - Number of results considered for recall
+/// * `price_per_million_tokens` - Price of the model in USD per million tokens, if known
+///
+/// # Returns
+///
+/// Recall, latency and cost of the model
+pub async fn benchmark_embedding_model<E: EmbeddingModel>(
+    name: &str,
+    model: &E,
+    chunks: &[(i64, String)],
+    questions: &[EvalQuestion],
+    k: usize,
+    price_per_million_tokens: Option<f64>,
+) -> Result<EmbeddingBenchmark, SearchError> {
+    let mut chunk_vectors = Vec::with_capacity(chunks.len());
+    let start = Instant::now();
+    for batch in chunks.chunks(E::MAX_DOCUMENTS.max(1)) {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let embeddings = model
+            .embed_texts(texts)
+            .await
+            .map_err(|e| SearchError::Embedding(format!("{}: {}", name, e)))?;
+        chunk_vectors.extend(embeddings.into_iter().map(|e| e.vec));
+    }
+    let chunk_time = start.elapsed();
+
+    let sampled: Vec<i64> = chunks.iter().map(|(id, _)| *id).collect();
+    let questions: Vec<&EvalQuestion> = questions
+        .iter()
+        .filter(|q| sampled.contains(&q.chunk_id))
+        .collect();
+
+    let mut ranks = Vec::with_capacity(questions.len());
+    let mut query_time = Duration::ZERO;
+    for question in &questions {
+        let start = Instant::now();
+        let embedding = model
+            .embed_text(&question.query)
+            .await
+            .map_err(|e| SearchError::Embedding(format!("{}: {}", name, e)))?;
+        query_time += start.elapsed();
+
+        ranks.push(rank_of(
+            &embedding.vec,
+            &chunk_vectors,
+            &sampled,
+            question.chunk_id,
+        ));
+    }
+
+    let estimated_tokens = chunks
+        .iter()
+        .map(|(_, text)| estimate_tokens(text))
+        .chain(questions.iter().map(|q| estimate_tokens(&q.query)))
+        .sum();
+
+    Ok(EmbeddingBenchmark {
+        model: name.to_string(),
+        dimensions: model.ndims(),
+        chunks: chunks.len(),
+        questions: questions.len(),
+        recall: recall_at_k(&ranks, k),
+        k,
+        chunk_latency_ms: average_ms(chunk_time, chunks.len()),
+        query_latency_ms: average_ms(query_time, questions.len()),
+        estimated_tokens,
+        estimated_cost: price_per_million_tokens
+            .map(|price| estimated_tokens as f64 / 1_000_000.0 * price),
+    })
+}
+
+/// Zero-based rank of the target chunk when chunks are ordered by similarity to the query
+fn rank_of(query: &[f64], vectors: &[Vec<f64>], ids: &[i64], target: i64) -> Option<usize> {
+    let index = ids.iter().position(|id| *id == target)?;
+    let target_similarity = cosine_similarity(query, &vectors[index]);
+    Some(
+        vectors
+            .iter()
+            .filter(|vector| cosine_similarity(query, vector) > target_similarity)
+            .count(),
+    )
+}
+
+/// Fraction of ranks within the top `k`, or `None` without questions
+fn recall_at_k(ranks: &[Option<usize>], k: usize) -> Option<f64> {
+    if ranks.is_empty() {
+        return None;
+    }
+    let hits = ranks
+        .iter()
+        .filter(|rank| matches!(rank, Some(r) if *r < k))
+        .count();
+    Some(hits as f64 / ranks.len() as f64)
+}
+
+/// Cosine similarity between two vectors, `0.0` when either is zero
+pub(crate) fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|v| v * v).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|v| v * v).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Rough token count, assuming four characters per token
+pub(crate) fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Average duration per item in milliseconds
+fn average_ms(total: Duration, count: usize) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total.as_secs_f64() * 1000.0 / count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_and_recall() {
+        let vectors = vec![vec![1.0, 0.0], vec![0.7, 0.7], vec![0.0, 1.0]];
+        let ids = vec![10, 11, 12];
+        let query = vec![0.9, 0.1];
+
+        assert_eq!(rank_of(&query, &vectors, &ids, 10), Some(0));
+        assert_eq!(rank_of(&query, &vectors, &ids, 12), Some(2));
+        assert_eq!(rank_of(&query, &vectors, &ids, 99), None);
+
+        let ranks = vec![Some(0), Some(2), None, Some(1)];
+        assert_eq!(recall_at_k(&ranks, 2), Some(0.5));
+        assert_eq!(recall_at_k(&ranks, 3), Some(0.75));
+        assert_eq!(recall_at_k(&[], 5), None);
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_mock_model() {
+        let model = crate::model::mock_model::MockEmbeddingModel::default();
+        let chunks = vec![
+            (1, "Install the package with cargo".to_string()),
+            (2, "Configure the server port".to_string()),
+        ];
+        let questions = vec![
+            EvalQuestion {
+                query: "Install the package with cargo".to_string(),
+                chunk_id: 1,
+            },
+            EvalQuestion {
+                query: "not sampled".to_string(),
+                chunk_id: 3,
+            },
+        ];
+
+        let report = benchmark_embedding_model("mock", &model, &chunks, &questions, 1, Some(0.1))
+            .await
+            .unwrap();
+        assert_eq!(report.chunks, 2);
+        assert_eq!(report.questions, 1);
+        assert_eq!(report.recall, Some(1.0));
+        assert!(report.estimated_tokens > 0);
+        assert!(report.estimated_cost.unwrap() > 0.0);
+    }
+}
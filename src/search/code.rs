@@ -0,0 +1,149 @@
+//! Code-aware search over repositories indexed with `hal index-code`.
+//!
+//! Code chunks are stored with a `code/<language>` content type and their location as the
+//! first entry of the heading path. Code search runs a normal semantic search, keeps only
+//! code chunks and turns them into `file:line` hits an agent can open directly.
+
+use rig::{completion::CompletionModel, embeddings::EmbeddingModel};
+use serde::Serialize;
+
+use crate::index::Database;
+use crate::model::Client;
+use crate::processor::code::parse_location;
+use crate::search::{SearchError, SearchOptions, SearchResult, search_index_with_client};
+
+/// Content type prefix of code chunks
+const CODE_CONTENT_TYPE: &str = "code/";
+
+/// A matching piece of code
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeHit {
+    /// Path of the file, relative to the indexed repository
+    pub path: String,
+    /// First line of the match, starting at 1
+    pub start_line: usize,
+    /// Last line of the match
+    pub end_line: usize,
+    /// Symbol defined by the match, e.g. `Config::load`
+    pub symbol: Option<String>,
+    /// Language of the file
+    pub language: String,
+    /// Similarity score
+    pub score: f64,
+    /// Source of the match
+    pub text: String,
+}
+
+impl CodeHit {
+    /// The code hit of a search result, if it is a code chunk
+    pub fn from_result(result: &SearchResult) -> Option<Self> {
+        let language = result
+            .content_type
+            .as_deref()?
+            .strip_prefix(CODE_CONTENT_TYPE)?;
+        let heading_path = result.heading_path.as_deref()?;
+        let (path, start_line, end_line) = parse_location(heading_path)?;
+        let symbol = heading_path
+            .split_once(" > ")
+            .map(|(_, symbol)| symbol.to_string());
+
+        Some(Self {
+            path,
+            start_line,
+            end_line,
+            symbol,
+            language: language.to_string(),
+            score: result.score,
+            text: result.text.clone(),
+        })
+    }
+
+    /// The `path:line` location of the hit
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.path, self.start_line)
+    }
+}
+
+/// Search indexed code
+///
+/// # Arguments
+///
+/// * `db` - Index with repositories indexed by `hal index-code`
+/// * `client` - Client embedding the query
+/// * `query` - What the code does or contains, e.g. "where the config file is loaded"
+/// * `limit` - Most hits to return
+/// * `source_filter` - Only search this repository
+/// * `exact_source` - Match `source_filter` against the whole domain instead of any part
+pub async fn code_search<C, E>(
+    db: &Database,
+    client: &Client<C, E>,
+    query: &str,
+    limit: usize,
+    source_filter: Option<String>,
+    exact_source: bool,
+) -> Result<Vec<CodeHit>, SearchError>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    // Documents indexed alongside the code take part of the results
+    let results = search_index_with_client(
+        db,
+        client,
+        query,
+        SearchOptions {
+            limit: limit * 4,
+            source_filter,
+            exact_source,
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(code_hits(&results, limit))
+}
+
+/// The code hits among search results, best first
+fn code_hits(results: &[SearchResult], limit: usize) -> Vec<CodeHit> {
+    let mut hits: Vec<CodeHit> = results.iter().filter_map(CodeHit::from_result).collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_hits() {
+        let results = vec![
+            SearchResult::builder("file://repo/src/config.rs")
+                .text("fn load() {}")
+                .heading_path("Guide > Setup")
+                .score(0.95)
+                .build(),
+            SearchResult::builder("file://repo/src/config.rs")
+                .text("fn load() {}")
+                .heading_path("src/main.rs:1-8")
+                .content_type("code/rust")
+                .score(0.7)
+                .build(),
+            SearchResult::builder("file://repo/src/config.rs")
+                .text("fn load() {}")
+                .heading_path("src/config.rs:10-42 > Config::load")
+                .content_type("code/rust")
+                .score(0.9)
+                .build(),
+        ];
+
+        let hits = code_hits(&results, 5);
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].location(), "src/config.rs:10");
+        assert_eq!(hits[0].end_line, 42);
+        assert_eq!(hits[0].symbol.as_deref(), Some("Config::load"));
+        assert_eq!(hits[0].language, "rust");
+        assert_eq!(hits[1].symbol, None);
+        assert_eq!(code_hits(&results, 1).len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+//! # Query Condensing Module
+//!
+//! In a conversation, follow-up messages lean on what was said before: "how do I disable
+//! it?" means nothing to a vector search on its own. This module asks the LLM to rewrite
+//! the latest message together with the conversation into a standalone query, which is
+//! then embedded instead of the raw message.
+//!
+//! ## Key Components
+//!
+//! - `condense_query`: Rewrites a message of a conversation as a standalone search query
+//! - `MAX_HISTORY_MESSAGES`: How much of the conversation the rewrite sees
+//!
+//! The first message of a conversation is returned unchanged without a request.
+
+use rig::agent::AgentBuilder;
+use rig::completion::{AssistantContent, CompletionModel, Message, Prompt};
+use rig::embeddings::EmbeddingModel;
+use rig::message::UserContent;
+use tracing::{debug, instrument};
+
+use super::error::SearchError;
+use crate::model::Client;
+
+/// Latest messages of the conversation shown to the model
+pub const MAX_HISTORY_MESSAGES: usize = 6;
+
+/// Characters kept of each message, so long answers don't dominate the request
+const MAX_MESSAGE_CHARS: usize = 1000;
+
+/// Rewrite the latest message of a conversation as a standalone search query
+///
+/// # Arguments
+///
+/// * `client` - The client whose completion model rewrites the message
+/// * `history` - Messages of the conversation before the latest one
+/// * `message` - The latest message
+///
+/// # Returns
+///
+/// A query resolving the pronouns and references of the message from the conversation, or
+/// the message itself when there is no conversation yet
+#[instrument(skip(client, history), fields(history = history.len()))]
+pub async fn condense_query<C, E>(
+    client: &Client<C, E>,
+    history: &[Message],
+    message: &str,
+) -> Result<String, SearchError>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    let conversation = transcript(history);
+    if conversation.is_empty() {
+        return Ok(message.to_string());
+    }
+
+    let agent = AgentBuilder::new(client.completion().clone())
+        .preamble(
+            "You rewrite the latest message of a conversation as a standalone search query. \
+            Replace pronouns and references with what they refer to in the conversation, keep \
+            the language of the message, and do not answer it. Reply with the query only.",
+        )
+        .temperature(0.0)
+        .build();
+    let prompt = format!(
+        "Conversation:\n{}\n\nLatest message: {}\n\nStandalone query:",
+        conversation, message
+    );
+    let query = agent
+        .prompt(prompt)
+        .await
+        .map_err(|e| SearchError::Query(format!("Failed to condense query: {}", e)))?;
+
+    let query = query.trim().trim_matches('"').trim();
+    if query.is_empty() {
+        return Ok(message.to_string());
+    }
+    debug!(message = %message, query = %query, "Condensed query");
+    Ok(query.to_string())
+}
+
+/// The latest text messages of a conversation, one `role: text` line each
+fn transcript(history: &[Message]) -> String {
+    let lines: Vec<String> = history
+        .iter()
+        .filter_map(|message| {
+            let (role, text) = match message {
+                Message::User { content } => (
+                    "user",
+                    content
+                        .iter()
+                        .filter_map(|content| match content {
+                            UserContent::Text(text) => Some(text.text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                Message::Assistant { content } => (
+                    "assistant",
+                    content
+                        .iter()
+                        .filter_map(|content| match content {
+                            AssistantContent::Text(text) => Some(text.text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+            };
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!text.is_empty()).then(|| {
+                let text: String = text.chars().take(MAX_MESSAGE_CHARS).collect();
+                format!("{}: {}", role, text)
+            })
+        })
+        .collect();
+    let start = lines.len().saturating_sub(MAX_HISTORY_MESSAGES);
+    lines[start..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_first_message_is_unchanged() {
+        let client = Client::new_mock();
+        let query = condense_query(&client, &[], "How do I crawl a site?")
+            .await
+            .unwrap();
+        assert_eq!(query, "How do I crawl a site?");
+    }
+
+    #[test]
+    fn test_transcript() {
+        let mut history = vec![
+            Message::user("How do I crawl a site?"),
+            Message::assistant("Run\n  `hal crawl <url>`."),
+        ];
+        assert_eq!(
+            transcript(&history),
+            "user: How do I crawl a site?\nassistant: Run `hal crawl <url>`."
+        );
+
+        history.extend((0..10).map(|i| Message::user(format!("message {}", i))));
+        let transcript = transcript(&history);
+        assert_eq!(transcript.lines().count(), MAX_HISTORY_MESSAGES);
+        assert!(transcript.ends_with("user: message 9"));
+    }
+}
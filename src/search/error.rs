@@ -47,6 +47,14 @@ pub enum SearchError {
     /// Invalid search parameters
     #[error("Invalid search parameters: {0}")]
     InvalidParameters(String),
+
+    /// The API key is missing, unknown or not allowed to search a collection
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+
+    /// The API key exceeded its rate limit
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
 }
 
 impl From<serde_json::Error> for SearchError {
@@ -0,0 +1,202 @@
+//! # Relevance Feedback Module
+//!
+//! This module stores user judgments of whether a search result was relevant. The
+//! judgments are used as a lightweight boost or penalty when ranking later searches,
+//! and together with the recorded query they serve as relevance labels for evaluation.
+//!
+//! ## Key Components
+//!
+//! - `Feedback`: A recorded relevance judgment
+//! - `record_feedback`: Stores a judgment for a result chunk
+//! - `list_feedback`: Lists all judgments, e.g. to export evaluation labels
+//! - `feedback_scores`: Net judgments per chunk, used by `ranking::apply_feedback`
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::SearchError;
+use crate::index::Database;
+
+/// A relevance judgment for a search result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feedback {
+    /// ID of the judgment
+    pub id: i64,
+
+    /// ID of the judged chunk
+    pub chunk_id: i64,
+
+    /// URL of the page the chunk belongs to
+    pub url: String,
+
+    /// The query the result was returned for, if known
+    pub query: Option<String>,
+
+    /// Whether the result was relevant
+    pub relevant: bool,
+
+    /// When the judgment was made as a Unix timestamp
+    pub created_at: i64,
+}
+
+/// Record a relevance judgment for a result chunk
+///
+/// # Arguments
+///
+/// * `db` - The database to write to
+/// * `chunk_id` - ID of the judged chunk, as shown in search results
+/// * `relevant` - Whether the result was relevant
+/// * `query` - The query the result was returned for, if known
+///
+/// # Returns
+///
+/// The ID of the new judgment, or an error if the chunk does not exist
+pub async fn record_feedback(
+    db: &Database,
+    chunk_id: i64,
+    relevant: bool,
+    query: Option<&str>,
+) -> Result<i64, SearchError> {
+    let params: Vec<libsql::Value> = vec![
+        query.map_or(libsql::Value::Null, |query| query.to_string().into()),
+        (relevant as i64).into(),
+        chrono::Utc::now().timestamp().into(),
+        chunk_id.into(),
+    ];
+
+    let mut rows = db
+        .execute_query(
+            "INSERT INTO feedback (chunk_id, url, query, relevant, created_at)
+             SELECT id, url, ?, ?, ? FROM chunks WHERE id = ?
+             RETURNING id",
+            params,
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get(0)?),
+        None => Err(SearchError::InvalidParameters(format!(
+            "No result with ID {}",
+            chunk_id
+        ))),
+    }
+}
+
+/// List all relevance judgments, oldest first
+pub async fn list_feedback(db: &Database) -> Result<Vec<Feedback>, SearchError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT id, chunk_id, url, query, relevant, created_at FROM feedback ORDER BY id",
+            libsql::params![],
+        )
+        .await?;
+
+    let mut feedback = Vec::new();
+    while let Some(row) = rows.next().await? {
+        feedback.push(Feedback {
+            id: row.get(0)?,
+            chunk_id: row.get(1)?,
+            url: row.get(2)?,
+            query: row.get(3)?,
+            relevant: row.get::<i64>(4)? != 0,
+            created_at: row.get(5)?,
+        });
+    }
+    Ok(feedback)
+}
+
+/// Net judgments per chunk: relevant judgments minus irrelevant ones
+///
+/// Chunks without feedback are not included in the returned map.
+pub async fn feedback_scores(
+    db: &Database,
+    chunk_ids: &[i64],
+) -> Result<HashMap<i64, i64>, SearchError> {
+    let mut scores = HashMap::new();
+    if chunk_ids.is_empty() {
+        return Ok(scores);
+    }
+
+    let placeholders = vec!["?"; chunk_ids.len()].join(", ");
+    let sql = format!(
+        "SELECT chunk_id, SUM(CASE WHEN relevant THEN 1 ELSE -1 END)
+         FROM feedback WHERE chunk_id IN ({}) GROUP BY chunk_id",
+        placeholders
+    );
+    let params: Vec<libsql::Value> = chunk_ids.iter().map(|id| (*id).into()).collect();
+
+    let mut rows = db.execute_query(&sql, params).await?;
+    while let Some(row) = rows.next().await? {
+        scores.insert(row.get(0)?, row.get(1)?);
+    }
+    Ok(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{IndexedChunk, Website};
+    use rig::embeddings::Embedding;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_record_feedback() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("feedback.db");
+        let db = Database::new_from_path(&db_path.to_string_lossy())
+            .await
+            .unwrap();
+
+        let website_id = db
+            .add_website(&Website {
+                id: 0,
+                url: "https://example.com".to_string(),
+                domain: "example.com".to_string(),
+                first_index_date: 0,
+                last_index_date: 0,
+                page_count: 0,
+                status: "active".to_string(),
+            })
+            .await
+            .unwrap();
+        let chunk_id = db
+            .add_chunk(&IndexedChunk {
+                id: 0,
+                website_id,
+                url: "https://example.com/page".to_string(),
+                text: "text".to_string(),
+                context: "context".to_string(),
+                embedding: Embedding {
+                    document: "text".to_string(),
+                    vec: vec![0.1; 768],
+                },
+                position: 0,
+                heading: None,
+                heading_path: None,
+                author: None,
+                published_at: None,
+                content_type: None,
+                doc_version: None,
+            })
+            .await
+            .unwrap();
+
+        record_feedback(&db, chunk_id, true, Some("query"))
+            .await
+            .unwrap();
+        record_feedback(&db, chunk_id, true, None).await.unwrap();
+        record_feedback(&db, chunk_id, false, None).await.unwrap();
+        assert!(record_feedback(&db, 999, true, None).await.is_err());
+
+        let scores = feedback_scores(&db, &[chunk_id, 999]).await.unwrap();
+        assert_eq!(scores.get(&chunk_id), Some(&1));
+        assert!(!scores.contains_key(&999));
+
+        let feedback = list_feedback(&db).await.unwrap();
+        assert_eq!(feedback.len(), 3);
+        assert_eq!(feedback[0].url, "https://example.com/page");
+        assert_eq!(feedback[0].query.as_deref(), Some("query"));
+        assert!(!feedback[2].relevant);
+    }
+}
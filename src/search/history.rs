@@ -0,0 +1,223 @@
+//! # Search History Module
+//!
+//! This module persists executed searches so past answers can be audited and re-run.
+//! Each entry records the query, the options it ran with, the top results that were
+//! retrieved, and the generated answer when there was one.
+//!
+//! ## Key Components
+//!
+//! - `HistoryEntry`: A recorded search
+//! - `HistorySource`: A result that was returned for a recorded search
+//! - `record_search`: Stores a search in the `search_history` table
+//! - `list_history`: Lists the most recent searches
+//! - `get_history_entry`: Loads a single search, e.g. to replay it
+
+use serde::{Deserialize, Serialize};
+
+use super::error::SearchError;
+use super::search_impl::{SearchOptions, SearchResult};
+use crate::index::Database;
+
+/// A result returned for a recorded search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistorySource {
+    /// ID of the chunk
+    pub chunk_id: i64,
+
+    /// URL of the source page
+    pub url: String,
+
+    /// Breadcrumb of the headings enclosing the chunk
+    #[serde(default)]
+    pub heading_path: Option<String>,
+
+    /// Relevance score at the time of the search
+    pub score: f64,
+}
+
+impl From<&SearchResult> for HistorySource {
+    fn from(result: &SearchResult) -> Self {
+        Self {
+            chunk_id: result.chunk_id,
+            url: result.url.clone(),
+            heading_path: result.heading_path.clone(),
+            score: result.score,
+        }
+    }
+}
+
+/// A recorded search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// ID of the entry
+    pub id: i64,
+
+    /// The search query
+    pub query: String,
+
+    /// The options the search ran with
+    pub options: SearchOptions,
+
+    /// The results returned, in rank order
+    pub sources: Vec<HistorySource>,
+
+    /// The generated answer, if the search used RAG
+    pub answer: Option<String>,
+
+    /// When the search ran as a Unix timestamp
+    pub created_at: i64,
+}
+
+/// Record a search in the history
+///
+/// # Arguments
+///
+/// * `db` - The database to write to
+/// * `query` - The search query
+/// * `options` - The options the search ran with
+/// * `results` - The results that were returned
+/// * `answer` - The generated answer, if any
+///
+/// # Returns
+///
+/// The ID of the new history entry
+pub async fn record_search(
+    db: &Database,
+    query: &str,
+    options: &SearchOptions,
+    results: &[SearchResult],
+    answer: Option<&str>,
+) -> Result<i64, SearchError> {
+    let sources: Vec<HistorySource> = results.iter().map(HistorySource::from).collect();
+    let params: Vec<libsql::Value> = vec![
+        query.to_string().into(),
+        serde_json::to_string(options)?.into(),
+        serde_json::to_string(&sources)?.into(),
+        answer.map_or(libsql::Value::Null, |answer| answer.to_string().into()),
+        chrono::Utc::now().timestamp().into(),
+    ];
+
+    let mut rows = db
+        .execute_query(
+            "INSERT INTO search_history (query, options, sources, answer, created_at)
+             VALUES (?, ?, ?, ?, ?) RETURNING id",
+            params,
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get(0)?),
+        None => Err(SearchError::ResultProcessing(
+            "Failed to get search history ID".to_string(),
+        )),
+    }
+}
+
+/// List the most recent searches, newest first
+pub async fn list_history(db: &Database, limit: usize) -> Result<Vec<HistoryEntry>, SearchError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT id, query, options, sources, answer, created_at
+             FROM search_history ORDER BY id DESC LIMIT ?",
+            vec![libsql::Value::from(limit as i64)],
+        )
+        .await?;
+
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next().await? {
+        entries.push(row_to_entry(&row)?);
+    }
+    Ok(entries)
+}
+
+/// Get a recorded search by ID
+pub async fn get_history_entry(
+    db: &Database,
+    id: i64,
+) -> Result<Option<HistoryEntry>, SearchError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT id, query, options, sources, answer, created_at
+             FROM search_history WHERE id = ?",
+            vec![libsql::Value::from(id)],
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(Some(row_to_entry(&row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Convert a database row to a history entry
+fn row_to_entry(row: &libsql::Row) -> Result<HistoryEntry, SearchError> {
+    let options: String = row.get(2)?;
+    let sources: String = row.get(3)?;
+
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        query: row.get(1)?,
+        options: serde_json::from_str(&options)?,
+        sources: serde_json::from_str(&sources)?,
+        answer: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_record_and_replay_search() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("history.db");
+        let db = Database::new_from_path(&db_path.to_string_lossy())
+            .await
+            .unwrap();
+
+        let options = SearchOptions {
+            limit: 5,
+            source_filter: Some("example.com".to_string()),
+            ..SearchOptions::default()
+        };
+        let results = vec![
+            SearchResult::builder("https://example.com/a")
+                .chunk_id(1)
+                .text("text")
+                .context("context")
+                .heading_path("Guide")
+                .score(0.8)
+                .build(),
+            SearchResult::builder("https://example.com/b")
+                .chunk_id(2)
+                .text("text")
+                .context("context")
+                .heading_path("Guide")
+                .score(0.8)
+                .build(),
+        ];
+
+        let first = record_search(&db, "first", &options, &results, Some("An answer"))
+            .await
+            .unwrap();
+        let second = record_search(&db, "second", &SearchOptions::default(), &[], None)
+            .await
+            .unwrap();
+
+        let entries = list_history(&db, 10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, second);
+        assert_eq!(entries[1].query, "first");
+
+        let entry = get_history_entry(&db, first).await.unwrap().unwrap();
+        assert_eq!(entry.options.limit, 5);
+        assert_eq!(entry.options.source_filter.as_deref(), Some("example.com"));
+        assert_eq!(entry.sources.len(), 2);
+        assert_eq!(entry.sources[1].url, "https://example.com/b");
+        assert_eq!(entry.answer.as_deref(), Some("An answer"));
+
+        assert!(get_history_entry(&db, 999).await.unwrap().is_none());
+    }
+}
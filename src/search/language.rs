@@ -0,0 +1,121 @@
+//! # Cross-Lingual Retrieval Module
+//!
+//! Documentation is often written in one language and searched in another. Multilingual
+//! embedding models place a question and its answer close together across languages, but
+//! recall is noticeably better when the query is in the language of the documents.
+//!
+//! ## Key Components
+//!
+//! - `translate_query`: Asks the LLM to translate a query into each target language
+//! - `merge_results`: Merges the results of the original and translated queries
+//!
+//! Chunks are marked with the language `index::language::detect_language` detects when
+//! they are indexed, so results can be filtered with `SearchOptions::target_languages`.
+
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel, Prompt};
+use rig::embeddings::EmbeddingModel;
+use tracing::{debug, instrument};
+
+use super::error::SearchError;
+use super::search_impl::SearchResult;
+use crate::index::language::detect_language;
+use crate::model::Client;
+
+/// Translate a query into the target languages it is not written in
+///
+/// # Arguments
+///
+/// * `client` - The client whose completion model translates
+/// * `query` - The query
+/// * `target_languages` - ISO 639-1 codes of the languages of the documents
+///
+/// # Returns
+///
+/// The translations with their language, without the query's own language
+#[instrument(skip(client))]
+pub async fn translate_query<C, E>(
+    client: &Client<C, E>,
+    query: &str,
+    target_languages: &[String],
+) -> Result<Vec<(String, String)>, SearchError>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    let source = detect_language(query);
+    let agent = AgentBuilder::new(client.completion().clone())
+        .preamble(
+            "You translate search queries. Reply with the translation only, keeping product \
+            names, identifiers and code unchanged.",
+        )
+        .temperature(0.0)
+        .build();
+
+    let mut translations = Vec::new();
+    for target in target_languages {
+        if source == Some(target.as_str()) {
+            continue;
+        }
+        let translation = agent
+            .prompt(format!(
+                "Translate this query into the language with ISO 639-1 code '{}':\n{}",
+                target, query
+            ))
+            .await
+            .map_err(|e| SearchError::Query(format!("Failed to translate query: {}", e)))?;
+        let translation = translation.trim().to_string();
+        debug!(language = %target, translation = %translation, "Translated query");
+        translations.push((target.clone(), translation));
+    }
+    Ok(translations)
+}
+
+/// Merge the results of several queries, keeping the best score of each chunk
+///
+/// # Returns
+///
+/// Up to `limit` results, highest score first
+pub fn merge_results(searches: Vec<Vec<SearchResult>>, limit: usize) -> Vec<SearchResult> {
+    let mut merged: Vec<SearchResult> = Vec::new();
+    for result in searches.into_iter().flatten() {
+        match merged
+            .iter_mut()
+            .find(|r| r.chunk_id == result.chunk_id && r.database == result.database)
+        {
+            Some(existing) if existing.score >= result.score => {}
+            Some(existing) => *existing = result,
+            None => merged.push(result),
+        }
+    }
+    merged.sort_by(|a, b| b.score.total_cmp(&a.score));
+    merged.truncate(limit);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_results() {
+        let result = |chunk_id: i64, score: f64| SearchResult {
+            chunk_id,
+            url: format!("https://example.com/{}", chunk_id),
+            website_url: "https://example.com".to_string(),
+            website_domain: "example.com".to_string(),
+            score,
+            ..Default::default()
+        };
+
+        let merged = merge_results(
+            vec![
+                vec![result(1, 0.9), result(2, 0.5)],
+                vec![result(2, 0.8), result(3, 0.4)],
+            ],
+            2,
+        );
+        let scores: Vec<(i64, f64)> = merged.iter().map(|r| (r.chunk_id, r.score)).collect();
+        assert_eq!(scores, vec![(1, 0.9), (2, 0.8)]);
+    }
+}
@@ -0,0 +1,217 @@
+//! # Long-Context Module
+//!
+//! Chunks are retrieved because small prompts were all early models could take. Models
+//! with context windows of a million tokens can read whole pages, which keeps the text
+//! around a matching chunk, such as the prerequisites of a step or the rest of a table,
+//! that chunk retrieval drops.
+//!
+//! ## Key Components
+//!
+//! - `RetrievalMode`: Whether answers are generated from chunks, whole pages, or whichever
+//!   the model's context window allows
+//! - `context_window`: Known context windows of models, in tokens
+//! - `prepare_page_context`: Formats the full text of the top pages as RAG context
+//!
+//! Pages are ranked by their best chunk and rebuilt from their stored chunks, in order.
+//! `hal search` fills at most half of the context window with pages, leaving room for the
+//! answer and the conversation.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use super::bench::estimate_tokens;
+use super::error::SearchError;
+use super::map_reduce::group_by_source;
+use super::sanitize::{escape_delimiters, sanitize_text};
+use super::search_impl::SearchResult;
+use crate::index::Database;
+
+/// Context window of models missing from `CONTEXT_WINDOWS`, in tokens
+pub const DEFAULT_CONTEXT_WINDOW: usize = 32_768;
+
+/// Smallest context window, in tokens, for which `auto` sends whole pages
+pub const LONG_CONTEXT_THRESHOLD: usize = 128_000;
+
+/// Context windows of model families in tokens, most specific prefix first
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-1.5-flash", 1_048_576),
+    ("gemini-2.0", 1_048_576),
+    ("gemini-2.5", 1_048_576),
+    ("gemini-1.0", 32_768),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("claude", 200_000),
+];
+
+/// What answers are generated from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetrievalMode {
+    /// The retrieved chunks
+    #[default]
+    Chunks,
+
+    /// The full text of the pages the retrieved chunks come from
+    Pages,
+
+    /// Pages when the model's context window is at least `LONG_CONTEXT_THRESHOLD`, chunks
+    /// otherwise
+    Auto,
+}
+
+impl RetrievalMode {
+    /// The mode to use with a model, resolving `Auto` from its context window
+    pub fn resolve(self, model: &str) -> RetrievalMode {
+        match self {
+            RetrievalMode::Auto if context_window(model) >= LONG_CONTEXT_THRESHOLD => {
+                RetrievalMode::Pages
+            }
+            RetrievalMode::Auto => RetrievalMode::Chunks,
+            mode => mode,
+        }
+    }
+}
+
+impl FromStr for RetrievalMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chunks" => Ok(Self::Chunks),
+            "pages" => Ok(Self::Pages),
+            "auto" => Ok(Self::Auto),
+            other => Err(format!(
+                "unknown retrieval mode '{}', expected chunks, pages or auto",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RetrievalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Chunks => "chunks",
+            Self::Pages => "pages",
+            Self::Auto => "auto",
+        })
+    }
+}
+
+/// The context window of a model in tokens, `DEFAULT_CONTEXT_WINDOW` if unknown
+pub fn context_window(model: &str) -> usize {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, tokens)| *tokens)
+}
+
+/// Prepare the full text of the top pages of the results as RAG context
+///
+/// Each page is wrapped in `<source>` delimiters like `prepare_rag_context` does for
+/// chunks. Pages are taken in the order of their best result until the next one would
+/// exceed the budget.
+///
+/// # Arguments
+///
+/// * `databases` - The searched indexes with their labels, to read the pages from
+/// * `results` - The search results, best first
+/// * `max_tokens` - Most estimated tokens of page text
+///
+/// # Returns
+///
+/// The context and the number of pages in it
+#[instrument(skip(databases, results), fields(results = results.len()))]
+pub async fn prepare_page_context(
+    databases: &[(String, Database)],
+    results: &[SearchResult],
+    max_tokens: usize,
+) -> Result<(String, usize), SearchError> {
+    let mut context = String::new();
+    let mut tokens = 0;
+    let mut pages = 0;
+
+    for (url, chunks) in group_by_source(results) {
+        let best = &chunks[0];
+        let db = match &best.database {
+            Some(label) => databases.iter().find(|(l, _)| l == label),
+            None => databases.first(),
+        };
+        let Some((_, db)) = db else {
+            continue;
+        };
+        let text = page_text(db, &url).await?;
+        let page_tokens = estimate_tokens(&text);
+        if tokens + page_tokens > max_tokens {
+            debug!(url = %url, tokens = page_tokens, "Page exceeds the context budget");
+            continue;
+        }
+        tokens += page_tokens;
+        pages += 1;
+
+        context.push_str(&format!("<source id=\"{}\">\n", pages));
+        context.push_str(&format!("URL: {}\n", escape_delimiters(&url)));
+        if let Some(author) = &best.author {
+            context.push_str(&format!("Author: {}\n", escape_delimiters(author)));
+        }
+        if let Some(date) = best
+            .published_at
+            .and_then(|published_at| chrono::DateTime::from_timestamp(published_at, 0))
+        {
+            context.push_str(&format!("Published: {}\n", date.format("%Y-%m-%d")));
+        }
+        let (text, _) = sanitize_text(&text);
+        context.push_str(&format!("Content:\n{}\n", escape_delimiters(&text)));
+        context.push_str("</source>\n\n");
+    }
+
+    debug!("Prepared {} pages of about {} tokens", pages, tokens);
+    Ok((context, pages))
+}
+
+/// The text of a page, rebuilt from its chunks in order
+async fn page_text(db: &Database, url: &str) -> Result<String, SearchError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT text FROM chunks WHERE url = ? ORDER BY position",
+            vec![libsql::Value::from(url.to_string())],
+        )
+        .await?;
+    let mut texts = Vec::new();
+    while let Some(row) = rows.next().await? {
+        texts.push(db.decode_text(row.get_value(0)?).await?);
+    }
+    Ok(texts.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retrieval_mode() {
+        assert_eq!(context_window("gemini-2.0-flash"), 1_048_576);
+        assert_eq!(context_window("models/gemini-1.5-pro-002"), 2_097_152);
+        assert_eq!(context_window("local-model"), DEFAULT_CONTEXT_WINDOW);
+
+        assert_eq!(
+            RetrievalMode::Auto.resolve("gemini-2.0-flash"),
+            RetrievalMode::Pages
+        );
+        assert_eq!(
+            RetrievalMode::Auto.resolve("gemini-1.0-pro"),
+            RetrievalMode::Chunks
+        );
+        assert_eq!(
+            RetrievalMode::Pages.resolve("gemini-1.0-pro"),
+            RetrievalMode::Pages
+        );
+        assert_eq!("auto".parse::<RetrievalMode>(), Ok(RetrievalMode::Auto));
+        assert!("page".parse::<RetrievalMode>().is_err());
+    }
+}
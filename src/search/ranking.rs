@@ -0,0 +1,323 @@
+//! # Result Ranking Module
+//!
+//! This module adjusts the order of vector search results after retrieval. Vector
+//! similarity alone cannot tell a current page from a stale copy of the same content,
+//! so additional signals are applied here.
+//!
+//! ## Key Components
+//!
+//! - `FreshnessWeighting`: Exponential decay of scores by source age, with a half-life
+//!   configurable per collection (source domain)
+//! - `apply_freshness`: Re-scores and re-sorts results using a `FreshnessWeighting`
+//! - `collapse_versions`: Drops copies of a page from other documentation versions
+//! - `apply_feedback`: Boosts or penalizes results using recorded relevance judgments
+//!
+//! ## Scoring
+//!
+//! A result's age is taken from its publication date when known, and otherwise from
+//! the last time its website was indexed. The similarity score is multiplied by
+//! `0.5^(age / half_life)`, so a page one half-life old counts half as much as a new one.
+//!
+//! ## Version Collapsing
+//!
+//! Pages whose URLs differ only by a version segment (`/v1/`, `/v2/`, `/latest/`) are
+//! treated as copies of the same page. Only the preferred version is kept, or the
+//! latest one by `compare_versions` when the preferred version has no copy.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::SearchResult;
+use crate::index::versions::{compare_versions, detect_version};
+
+/// Seconds in a day
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Configuration for decaying scores of old sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FreshnessWeighting {
+    /// Half-life in days applied to sources without a specific setting
+    pub default_half_life_days: f64,
+
+    /// Half-life in days per source domain, sorted so serialized options compare equal
+    #[serde(default)]
+    pub half_life_days_by_domain: BTreeMap<String, f64>,
+}
+
+impl FreshnessWeighting {
+    /// Create a weighting with the same half-life for every source
+    pub fn new(default_half_life_days: f64) -> Self {
+        Self {
+            default_half_life_days,
+            half_life_days_by_domain: BTreeMap::new(),
+        }
+    }
+
+    /// Set the half-life for a source domain
+    pub fn with_domain_half_life(mut self, domain: &str, half_life_days: f64) -> Self {
+        self.half_life_days_by_domain
+            .insert(domain.to_string(), half_life_days);
+        self
+    }
+
+    /// The half-life in days for a source domain
+    pub fn half_life_days(&self, domain: &str) -> f64 {
+        self.half_life_days_by_domain
+            .get(domain)
+            .copied()
+            .unwrap_or(self.default_half_life_days)
+    }
+
+    /// The decay factor for a result at the given time
+    ///
+    /// Returns `1.0` when the result has no known date or the half-life is not positive.
+    pub fn decay(&self, result: &SearchResult, now: i64) -> f64 {
+        let half_life = self.half_life_days(&result.website_domain);
+        let Some(timestamp) = result.published_at.or(result.indexed_at) else {
+            return 1.0;
+        };
+        if half_life <= 0.0 {
+            return 1.0;
+        }
+
+        let age_days = ((now - timestamp).max(0) as f64) / SECONDS_PER_DAY;
+        0.5_f64.powf(age_days / half_life)
+    }
+}
+
+/// Apply freshness weighting to results and sort them by the weighted score
+///
+/// # Arguments
+///
+/// * `results` - The results to re-rank
+/// * `weighting` - The freshness configuration
+/// * `now` - The current time as a Unix timestamp
+///
+/// # Returns
+///
+/// The results ordered from highest to lowest weighted score
+pub fn apply_freshness(
+    mut results: Vec<SearchResult>,
+    weighting: &FreshnessWeighting,
+    now: i64,
+) -> Vec<SearchResult> {
+    for result in &mut results {
+        result.score = result.score.max(0.0) * weighting.decay(result, now);
+    }
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+/// Weight of relevance feedback: a strongly judged result moves by up to this fraction
+pub const FEEDBACK_WEIGHT: f64 = 0.2;
+
+/// Adjust scores using relevance feedback and sort results by the adjusted score
+///
+/// # Arguments
+///
+/// * `results` - The results to re-rank
+/// * `scores` - Net judgments per chunk ID, as returned by `feedback::feedback_scores`
+///
+/// # Returns
+///
+/// The results ordered from highest to lowest adjusted score
+pub fn apply_feedback(
+    mut results: Vec<SearchResult>,
+    scores: &HashMap<i64, i64>,
+) -> Vec<SearchResult> {
+    for result in &mut results {
+        if let Some(net) = scores.get(&result.chunk_id) {
+            // tanh keeps repeated judgments from outweighing similarity entirely
+            result.score *= 1.0 + FEEDBACK_WEIGHT * (*net as f64).tanh();
+        }
+    }
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+/// Keep a single documentation version of each page
+///
+/// # Arguments
+///
+/// * `results` - The ranked results
+/// * `preferred` - Version to keep when a page has a copy in it, otherwise the latest is kept
+///
+/// # Returns
+///
+/// The results in their original order without copies from other versions
+pub fn collapse_versions(results: Vec<SearchResult>, preferred: Option<&str>) -> Vec<SearchResult> {
+    let versions: Vec<_> = results
+        .iter()
+        .map(|result| detect_version(&result.url))
+        .collect();
+
+    // Pick the version to keep for each canonical page
+    let mut chosen: HashMap<&str, &str> = HashMap::new();
+    for version in versions.iter().flatten() {
+        let label = version.label.as_str();
+        chosen
+            .entry(version.canonical_url.as_str())
+            .and_modify(|current| {
+                let is_preferred = |l: &str| preferred.is_some_and(|p| p.eq_ignore_ascii_case(l));
+                if !is_preferred(current)
+                    && (is_preferred(label) || compare_versions(label, current).is_gt())
+                {
+                    *current = label;
+                }
+            })
+            .or_insert(label);
+    }
+
+    let keep: Vec<bool> = versions
+        .iter()
+        .map(|version| match version {
+            Some(version) => chosen[version.canonical_url.as_str()] == version.label,
+            None => true,
+        })
+        .collect();
+
+    results
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(result, keep)| keep.then_some(result))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    #[test]
+    fn test_decay_half_life() {
+        let weighting = FreshnessWeighting::new(30.0).with_domain_half_life("news.com", 1.0);
+        let now = 100 * DAY;
+
+        assert_eq!(
+            weighting.decay(
+                &SearchResult::builder("https://docs.com/page")
+                    .score(1.0)
+                    .published_at(now)
+                    .build(),
+                now
+            ),
+            1.0
+        );
+        assert!(
+            (weighting.decay(
+                &SearchResult::builder("https://docs.com/page")
+                    .score(1.0)
+                    .published_at(now - 30 * DAY)
+                    .build(),
+                now
+            ) - 0.5)
+                .abs()
+                < 1e-9
+        );
+        assert!(
+            (weighting.decay(
+                &SearchResult::builder("https://news.com/page")
+                    .score(1.0)
+                    .published_at(now - 2 * DAY)
+                    .build(),
+                now
+            ) - 0.25)
+                .abs()
+                < 1e-9
+        );
+        assert_eq!(
+            weighting.decay(
+                &SearchResult::builder("https://docs.com/page")
+                    .score(1.0)
+                    .build(),
+                now
+            ),
+            1.0
+        );
+
+        // Answer cache keys compare serialized options, whatever order domains were given in
+        let a = FreshnessWeighting::new(30.0)
+            .with_domain_half_life("a.com", 1.0)
+            .with_domain_half_life("b.com", 2.0);
+        let b = FreshnessWeighting::new(30.0)
+            .with_domain_half_life("b.com", 2.0)
+            .with_domain_half_life("a.com", 1.0);
+        assert_eq!(
+            serde_json::to_string(&a).unwrap(),
+            serde_json::to_string(&b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_apply_freshness_prefers_recent() {
+        let now = 1000 * DAY;
+        let results = vec![
+            SearchResult::builder("https://docs.com/page")
+                .score(0.90)
+                .published_at(now - 365 * DAY)
+                .build(),
+            SearchResult::builder("https://docs.com/page")
+                .score(0.85)
+                .published_at(now - DAY)
+                .build(),
+        ];
+
+        let ranked = apply_freshness(results, &FreshnessWeighting::new(90.0), now);
+
+        assert_eq!(ranked[0].published_at, Some(now - DAY));
+        assert!(ranked[0].score > ranked[1].score);
+    }
+
+    #[test]
+    fn test_apply_feedback() {
+        let penalized = SearchResult::builder("https://docs.com/page")
+            .chunk_id(1)
+            .score(0.9)
+            .build();
+        let boosted = SearchResult::builder("https://docs.com/page")
+            .chunk_id(2)
+            .score(0.8)
+            .build();
+        let scores = HashMap::from([(1, -2), (2, 3)]);
+
+        let ranked = apply_feedback(vec![penalized, boosted], &scores);
+
+        assert_eq!(ranked[0].chunk_id, 2);
+        assert!(ranked[0].score > 0.8 && ranked[0].score < 0.8 * (1.0 + FEEDBACK_WEIGHT));
+        assert!(ranked[1].score < 0.9);
+    }
+
+    #[test]
+    fn test_collapse_versions() {
+        let page = |path: &str, score: f64| {
+            SearchResult::builder(&format!("https://docs.com{}", path))
+                .score(score)
+                .build()
+        };
+        let results = vec![
+            page("/v1/guide", 0.9),
+            page("/latest/guide", 0.8),
+            page("/v2/guide", 0.7),
+            page("/blog/post", 0.6),
+        ];
+
+        let collapsed = collapse_versions(results.clone(), None);
+        let urls: Vec<&str> = collapsed.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://docs.com/latest/guide",
+                "https://docs.com/blog/post"
+            ]
+        );
+
+        let collapsed = collapse_versions(results, Some("v1"));
+        let urls: Vec<&str> = collapsed.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://docs.com/v1/guide", "https://docs.com/blog/post"]
+        );
+    }
+}
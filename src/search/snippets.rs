@@ -0,0 +1,203 @@
+//! # Snippet Highlighting Module
+//!
+//! Chunks are often several paragraphs long, and only a sentence or two of them answers
+//! the query. This module picks the sentences of each result sharing the most terms with
+//! the query and records where the terms occur, so the CLI can print short highlighted
+//! snippets instead of whole chunks.
+//!
+//! Sentences are scored by keyword overlap: the share of the query's distinct terms they
+//! contain, ignoring case, common stopwords and plural endings. It needs no model request,
+//! so every search gets snippets.
+
+use serde::{Deserialize, Serialize};
+
+use super::search_impl::SearchResult;
+
+/// Sentences kept per result by the search commands
+pub const DEFAULT_SNIPPET_SENTENCES: usize = 2;
+
+/// Query words too common to signal relevance
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how",
+    "i", "in", "is", "it", "of", "on", "or", "that", "the", "this", "to", "what", "when", "where",
+    "which", "who", "why", "with", "you",
+];
+
+/// A sentence of a result matching the query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    /// The sentence
+    pub text: String,
+
+    /// Share of the query terms the sentence contains, from 0 to 1
+    pub score: f64,
+
+    /// Byte ranges of the query terms in the sentence, as `(start, end)`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<(usize, usize)>,
+}
+
+impl Snippet {
+    /// The sentence with each highlight wrapped in the given markers, e.g. `**`
+    pub fn marked(&self, open: &str, close: &str) -> String {
+        let mut marked = String::with_capacity(self.text.len());
+        let mut last = 0;
+        for &(start, end) in &self.highlights {
+            marked.push_str(&self.text[last..start]);
+            marked.push_str(open);
+            marked.push_str(&self.text[start..end]);
+            marked.push_str(close);
+            last = end;
+        }
+        marked.push_str(&self.text[last..]);
+        marked
+    }
+}
+
+/// The sentences of a text sharing the most terms with a query
+///
+/// # Arguments
+///
+/// * `query` - The search query
+/// * `text` - Text of a result
+/// * `max_sentences` - Most sentences to return
+///
+/// # Returns
+///
+/// Up to `max_sentences` sentences containing at least one query term, in text order
+pub fn highlight(query: &str, text: &str, max_sentences: usize) -> Vec<Snippet> {
+    let mut terms: Vec<String> = words(query)
+        .map(|(_, word)| normalize(word))
+        .filter(|term| !STOPWORDS.contains(&term.as_str()))
+        .collect();
+    terms.sort();
+    terms.dedup();
+    if terms.is_empty() || max_sentences == 0 {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(usize, Snippet)> = sentences(text)
+        .enumerate()
+        .filter_map(|(i, sentence)| {
+            let mut matched: Vec<&str> = Vec::new();
+            let mut highlights = Vec::new();
+            for (start, word) in words(sentence) {
+                let term = normalize(word);
+                if let Some(term) = terms.iter().find(|t| **t == term) {
+                    highlights.push((start, start + word.len()));
+                    if !matched.contains(&term.as_str()) {
+                        matched.push(term);
+                    }
+                }
+            }
+            (!matched.is_empty()).then(|| {
+                let snippet = Snippet {
+                    text: sentence.to_string(),
+                    score: matched.len() as f64 / terms.len() as f64,
+                    highlights,
+                };
+                (i, snippet)
+            })
+        })
+        .collect();
+
+    // Keep the best sentences, earlier ones first on ties, then restore text order
+    scored.sort_by(|a, b| b.1.score.total_cmp(&a.1.score).then(a.0.cmp(&b.0)));
+    scored.truncate(max_sentences);
+    scored.sort_by_key(|(i, _)| *i);
+    scored.into_iter().map(|(_, snippet)| snippet).collect()
+}
+
+/// Set the snippets of each result
+///
+/// # Arguments
+///
+/// * `query` - The search query
+/// * `results` - The results to highlight in place
+/// * `max_sentences` - Most sentences per result
+pub fn highlight_results(query: &str, results: &mut [SearchResult], max_sentences: usize) {
+    for result in results.iter_mut() {
+        result.snippets = highlight(query, &result.text, max_sentences);
+    }
+}
+
+/// Sentences of a text, split after `.`, `!` or `?` followed by whitespace and at line
+/// breaks
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_space = chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+        let end = match c {
+            '\n' => Some(i),
+            '.' | '!' | '?' if next_is_space => Some(i + c.len_utf8()),
+            _ => None,
+        };
+        if let Some(end) = end {
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+    }
+    sentences.push(&text[start..]);
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|sentence| !sentence.is_empty())
+}
+
+/// Words of a text with their byte offsets; identifiers such as `spawn_blocking` are split
+/// into their words
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// A word lowercased, without a plural `s`, to compare query and sentence terms
+fn normalize(word: &str) -> String {
+    let word = word.to_lowercase();
+    match word.strip_suffix('s') {
+        Some(stem) if stem.chars().count() >= 3 && !stem.ends_with('s') => stem.to_string(),
+        _ => word,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_best_sentences() {
+        let text = "Tokio is an async runtime. It schedules tasks on worker threads.\n\
+            Spawned tasks must be Send. Blocking code should use spawn_blocking instead.";
+        let snippets = highlight("How do I spawn blocking tasks?", text, 2);
+
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].text, "It schedules tasks on worker threads.");
+        assert_eq!(
+            snippets[1].text,
+            "Blocking code should use spawn_blocking instead."
+        );
+        assert_eq!(
+            snippets[1].marked("**", "**"),
+            "**Blocking** code should use **spawn**_**blocking** instead."
+        );
+        assert!(snippets[1].score > snippets[0].score);
+
+        assert!(highlight("the of", text, 2).is_empty());
+        assert!(highlight("unrelated", text, 2).is_empty());
+    }
+
+    #[test]
+    fn test_sentences_and_words() {
+        let split: Vec<&str> = sentences("Version 1.2 is out! Really?\n\nYes.").collect();
+        assert_eq!(split, vec!["Version 1.2 is out!", "Really?", "Yes."]);
+
+        let text = "Café, crème";
+        let found: Vec<(usize, &str)> = words(text).collect();
+        assert_eq!(found, vec![(0, "Café"), (7, "crème")]);
+        assert_eq!(normalize("Threads"), "thread");
+        assert_eq!(normalize("class"), "class");
+    }
+}
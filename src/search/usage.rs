@@ -0,0 +1,225 @@
+//! # Usage Statistics Module
+//!
+//! This module records how often each chunk is returned by searches and when it was last
+//! returned. The counts show which websites and pages the index is actually used for, and
+//! which pages were never retrieved and only add cost to every search.
+//!
+//! ## Key Components
+//!
+//! - `record_retrievals`: Counts a retrieval of each returned chunk
+//! - `website_usage`: Retrievals and the latest retrieval per website
+//! - `page_usage`: The most retrieved pages
+//! - `unused_pages`: Pages none of whose chunks were ever retrieved
+//!
+//! Counts belong to chunks, so re-indexing a page starts its counts over.
+
+use serde::Serialize;
+
+use super::error::SearchError;
+use crate::index::Database;
+
+/// Retrievals of a website's chunks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WebsiteUsage {
+    /// Domain of the website
+    pub domain: String,
+
+    /// Number of indexed pages
+    pub pages: i64,
+
+    /// Number of indexed chunks
+    pub chunks: i64,
+
+    /// Number of chunks retrieved at least once
+    pub retrieved_chunks: i64,
+
+    /// Total retrievals of the website's chunks
+    pub retrievals: i64,
+
+    /// When a chunk of the website was last retrieved as a Unix timestamp
+    pub last_retrieved_at: Option<i64>,
+}
+
+/// Retrievals of a page's chunks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageUsage {
+    /// URL of the page
+    pub url: String,
+
+    /// Number of indexed chunks
+    pub chunks: i64,
+
+    /// Total retrievals of the page's chunks
+    pub retrievals: i64,
+
+    /// When a chunk of the page was last retrieved as a Unix timestamp
+    pub last_retrieved_at: Option<i64>,
+}
+
+/// Count a retrieval of each chunk, now
+///
+/// IDs of chunks that no longer exist are ignored.
+pub async fn record_retrievals(db: &Database, chunk_ids: &[i64]) -> Result<(), SearchError> {
+    if chunk_ids.is_empty() {
+        return Ok(());
+    }
+
+    let placeholders = vec!["?"; chunk_ids.len()].join(", ");
+    let sql = format!(
+        "UPDATE chunks SET retrieval_count = retrieval_count + 1, last_retrieved_at = ?
+         WHERE id IN ({})",
+        placeholders
+    );
+    let mut params: Vec<libsql::Value> = vec![chrono::Utc::now().timestamp().into()];
+    params.extend(chunk_ids.iter().map(|id| libsql::Value::from(*id)));
+
+    db.execute_query(&sql, params).await?;
+    Ok(())
+}
+
+/// Retrievals per website, most retrieved first
+pub async fn website_usage(db: &Database) -> Result<Vec<WebsiteUsage>, SearchError> {
+    let mut rows = db
+        .execute_query(
+            "SELECT w.domain, COUNT(DISTINCT c.url), COUNT(c.id),
+                    COUNT(CASE WHEN c.retrieval_count > 0 THEN 1 END),
+                    COALESCE(SUM(c.retrieval_count), 0), MAX(c.last_retrieved_at)
+             FROM websites w LEFT JOIN chunks c ON c.website_id = w.id
+             GROUP BY w.id
+             ORDER BY 5 DESC, w.domain",
+            libsql::params![],
+        )
+        .await?;
+
+    let mut usage = Vec::new();
+    while let Some(row) = rows.next().await? {
+        usage.push(WebsiteUsage {
+            domain: row.get(0)?,
+            pages: row.get(1)?,
+            chunks: row.get(2)?,
+            retrieved_chunks: row.get(3)?,
+            retrievals: row.get(4)?,
+            last_retrieved_at: row.get(5)?,
+        });
+    }
+    Ok(usage)
+}
+
+/// The most retrieved pages, most retrieved first
+pub async fn page_usage(db: &Database, limit: usize) -> Result<Vec<PageUsage>, SearchError> {
+    pages(
+        db,
+        "HAVING SUM(retrieval_count) > 0 ORDER BY 3 DESC, url",
+        limit,
+    )
+    .await
+}
+
+/// Pages none of whose chunks were ever retrieved, largest first
+pub async fn unused_pages(db: &Database, limit: usize) -> Result<Vec<PageUsage>, SearchError> {
+    pages(
+        db,
+        "HAVING SUM(retrieval_count) = 0 ORDER BY 2 DESC, url",
+        limit,
+    )
+    .await
+}
+
+/// Pages with their retrievals, filtered and ordered by an SQL clause
+async fn pages(db: &Database, clause: &str, limit: usize) -> Result<Vec<PageUsage>, SearchError> {
+    let sql = format!(
+        "SELECT url, COUNT(*), SUM(retrieval_count), MAX(last_retrieved_at)
+         FROM chunks GROUP BY url {} LIMIT ?",
+        clause
+    );
+    let mut rows = db
+        .execute_query(&sql, vec![libsql::Value::from(limit as i64)])
+        .await?;
+
+    let mut pages = Vec::new();
+    while let Some(row) = rows.next().await? {
+        pages.push(PageUsage {
+            url: row.get(0)?,
+            chunks: row.get(1)?,
+            retrievals: row.get(2)?,
+            last_retrieved_at: row.get(3)?,
+        });
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{IndexedChunk, Website};
+    use rig::embeddings::Embedding;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_usage() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("usage.db");
+        let db = Database::new_from_path(&db_path.to_string_lossy())
+            .await
+            .unwrap();
+
+        let website_id = db
+            .add_website(&Website {
+                id: 0,
+                url: "https://example.com".to_string(),
+                domain: "example.com".to_string(),
+                first_index_date: 0,
+                last_index_date: 0,
+                page_count: 0,
+                status: "active".to_string(),
+            })
+            .await
+            .unwrap();
+        let mut chunk_ids = Vec::new();
+        for (position, page) in ["used", "used", "unused"].iter().enumerate() {
+            let chunk_id = db
+                .add_chunk(&IndexedChunk {
+                    id: 0,
+                    website_id,
+                    url: format!("https://example.com/{}", page),
+                    text: "text".to_string(),
+                    context: "context".to_string(),
+                    embedding: Embedding {
+                        document: "text".to_string(),
+                        vec: vec![0.1; 768],
+                    },
+                    position: position as i64,
+                    heading: None,
+                    heading_path: None,
+                    author: None,
+                    published_at: None,
+                    content_type: None,
+                    doc_version: None,
+                })
+                .await
+                .unwrap();
+            chunk_ids.push(chunk_id);
+        }
+
+        record_retrievals(&db, &chunk_ids[..2]).await.unwrap();
+        record_retrievals(&db, &[chunk_ids[0], 999]).await.unwrap();
+
+        let websites = website_usage(&db).await.unwrap();
+        assert_eq!(websites.len(), 1);
+        assert_eq!(websites[0].pages, 2);
+        assert_eq!(websites[0].chunks, 3);
+        assert_eq!(websites[0].retrieved_chunks, 2);
+        assert_eq!(websites[0].retrievals, 3);
+        assert!(websites[0].last_retrieved_at.is_some());
+
+        let pages = page_usage(&db, 10).await.unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].url, "https://example.com/used");
+        assert_eq!(pages[0].retrievals, 3);
+
+        let unused = unused_pages(&db, 10).await.unwrap();
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].url, "https://example.com/unused");
+        assert_eq!(unused[0].last_retrieved_at, None);
+    }
+}
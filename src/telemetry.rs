@@ -54,7 +54,7 @@ fn init_metrics() -> SdkMeterProvider {
 }
 
 // Initialize tracing-subscriber and return OtelGuard for opentelemetry-related termination processing
-pub fn init_tracing_subscriber() -> OtelGuard {
+pub fn init_tracing_subscriber(default_filter: &str) -> OtelGuard {
     let tracer_provider = init_traces();
     let meter_provider = init_metrics();
     // let logger_provider = init_logs();
@@ -82,7 +82,9 @@ pub fn init_tracing_subscriber() -> OtelGuard {
 
     let console_layer = tracing_subscriber::fmt::layer()
         .with_writer(std::io::stderr)
-        .with_filter(EnvFilter::from_default_env());
+        .with_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter)),
+        );
 
     tracing_subscriber::registry()
         .with(console_layer)
@@ -0,0 +1,32 @@
+//! # Agent Tools Module
+//!
+//! This module packages HAL functionality as `rig` tools, so applications building their
+//! own rig agents can give them access to an index without reimplementing the query path.
+//!
+//! ## Key Components
+//!
+//! - `SemanticSearch`: Searches an index and returns the matching chunks with their sources
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use hal::model::Client;
+//! use hal::tools::SemanticSearch;
+//! use rig::{agent::AgentBuilder, completion::Prompt};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new_gemini_free_from_env();
+//! let search = SemanticSearch::from_path("index.db", client.clone()).await?;
+//!
+//! let agent = AgentBuilder::new(client.completion().clone())
+//!     .preamble("Answer questions using the semantic_search tool.")
+//!     .tool(search)
+//!     .build();
+//! let answer = agent.prompt("How do I configure the crawler?").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod semantic_search;
+
+pub use semantic_search::{SemanticSearch, SemanticSearchArgs};
@@ -0,0 +1,254 @@
+//! Semantic search over a HAL index as a rig tool
+
+use rig::completion::{CompletionModel, ToolDefinition};
+use rig::embeddings::EmbeddingModel;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Instant;
+use tracing::debug;
+
+use crate::audit::{AuditEntry, AuditLog};
+use crate::index::{Database, DbError};
+use crate::model::Client;
+use crate::search::sanitize::sanitize_text;
+use crate::search::{SearchError, SearchOptions, SearchResult, search_index_with_client};
+
+/// Upper bound on the number of results a model may request
+const MAX_LIMIT: usize = 50;
+
+/// Arguments the model passes to the tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticSearchArgs {
+    /// The search query
+    pub query: String,
+
+    /// Maximum number of results, defaults to the tool's configured limit
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Only search pages of this domain
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Semantic search over a HAL index, usable by any rig agent
+///
+/// Results are returned as JSON with their source URL, section and context. Instruction
+/// patterns are stripped from the returned text, since it goes straight to the model.
+#[derive(Clone)]
+pub struct SemanticSearch<C, E>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    db: Database,
+    client: Client<C, E>,
+    options: SearchOptions,
+    audit: Option<AuditLog>,
+}
+
+impl<C, E> SemanticSearch<C, E>
+where
+    C: CompletionModel,
+    E: EmbeddingModel,
+{
+    /// Create the tool for an open database
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The index to search
+    /// * `client` - The client whose embedding model embeds queries; it must be the
+    ///   model the index was built with
+    pub fn new(db: Database, client: Client<C, E>) -> Self {
+        Self {
+            db,
+            client,
+            options: SearchOptions::default(),
+            audit: None,
+        }
+    }
+
+    /// Create the tool for the database at a path
+    pub async fn from_path(path: &str, client: Client<C, E>) -> Result<Self, DbError> {
+        let db = Database::new_from_path(path).await?;
+        Ok(Self::new(db, client))
+    }
+
+    /// Use these options for every search; the model may still lower the limit or
+    /// narrow the source
+    pub fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Record each call in an audit log
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// The search options for a call
+    fn options_for(&self, args: &SemanticSearchArgs) -> SearchOptions {
+        let mut options = self.options.clone();
+        if let Some(limit) = args.limit {
+            options.limit = limit.clamp(1, MAX_LIMIT);
+        }
+        if args.source.is_some() && options.source_filter.is_none() {
+            options.source_filter = args.source.clone();
+        }
+        options
+    }
+}
+
+impl<C, E> Tool for SemanticSearch<C, E>
+where
+    C: CompletionModel + 'static,
+    E: EmbeddingModel + 'static,
+{
+    const NAME: &'static str = "semantic_search";
+
+    type Error = SearchError;
+    type Args = SemanticSearchArgs;
+    type Output = Vec<SearchResult>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Search the indexed documentation using semantic search. Returns \
+                          relevant text chunks with their source URL, section and context."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "The search query"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of results"
+                    },
+                    "source": {
+                        "type": "string",
+                        "description": "Only search pages of this domain"
+                    }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync {
+        let db = self.db.clone();
+        let client = self.client.clone();
+        let options = self.options_for(&args);
+        let audit = self.audit.clone();
+        async move {
+            let started = Instant::now();
+            let args_json = serde_json::to_string(&args).unwrap_or_default();
+            let result = search(db, client, args, options).await;
+
+            if let Some(audit) = audit {
+                let bytes = result
+                    .as_ref()
+                    .map(|results| serde_json::to_string(results).map_or(0, |json| json.len()));
+                let entry = AuditEntry::new("rig", Self::NAME, &args_json, started.elapsed())
+                    .with_result(bytes);
+                audit.record_or_warn(&entry);
+            }
+            result
+        }
+    }
+}
+
+/// Run a search for the tool, sanitizing the results
+async fn search<C, E>(
+    db: Database,
+    client: Client<C, E>,
+    args: SemanticSearchArgs,
+    options: SearchOptions,
+) -> Result<Vec<SearchResult>, SearchError>
+where
+    C: CompletionModel + 'static,
+    E: EmbeddingModel + 'static,
+{
+    if args.query.trim().is_empty() {
+        return Err(SearchError::InvalidParameters(
+            "Search query cannot be empty".to_string(),
+        ));
+    }
+
+    // Run on a task so the returned future only holds the join handle
+    let mut results =
+        tokio::spawn(
+            async move { search_index_with_client(&db, &client, &args.query, options).await },
+        )
+        .await
+        .map_err(|e| SearchError::Query(format!("Search task failed: {}", e)))??;
+
+    for result in &mut results {
+        result.text = sanitize_text(&result.text).0;
+        result.context = sanitize_text(&result.context).0;
+    }
+    debug!("Semantic search tool returned {} results", results.len());
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock_model::{MockCompletionModel, MockEmbeddingModel};
+
+    async fn tool() -> (
+        tempfile::TempDir,
+        SemanticSearch<MockCompletionModel, MockEmbeddingModel>,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.db");
+        let tool = SemanticSearch::from_path(&path.to_string_lossy(), Client::new_mock())
+            .await
+            .unwrap();
+        (dir, tool)
+    }
+
+    #[tokio::test]
+    async fn test_definition() {
+        let (_dir, tool) = tool().await;
+        let definition = tool.definition(String::new()).await;
+        assert_eq!(definition.name, "semantic_search");
+        assert_eq!(definition.parameters["required"], json!(["query"]));
+    }
+
+    #[tokio::test]
+    async fn test_options_for_call() {
+        let (_dir, tool) = tool().await;
+        let tool = tool.with_options(SearchOptions {
+            limit: 5,
+            ..SearchOptions::default()
+        });
+
+        let args: SemanticSearchArgs =
+            serde_json::from_str(r#"{"query": "setup", "limit": 500, "source": "docs.rs"}"#)
+                .unwrap();
+        let options = tool.options_for(&args);
+        assert_eq!(options.limit, MAX_LIMIT);
+        assert_eq!(options.source_filter.as_deref(), Some("docs.rs"));
+
+        let args: SemanticSearchArgs = serde_json::from_str(r#"{"query": "setup"}"#).unwrap();
+        assert_eq!(tool.options_for(&args).limit, 5);
+
+        let empty = SemanticSearchArgs {
+            query: " ".to_string(),
+            limit: None,
+            source: None,
+        };
+        assert!(matches!(
+            tool.call(empty).await,
+            Err(SearchError::InvalidParameters(_))
+        ));
+    }
+}
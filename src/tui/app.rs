@@ -6,9 +6,20 @@
 //! ## Key Components
 //!
 //! - `App`: Main application state structure that tracks all UI state
+//! - `Session`: A chat session in its own tab, with its own history, model and loading state
 //!
 //! ## Features
 //!
+//! - Multiple concurrent chat sessions in tabs (Ctrl+T new, Ctrl+Tab switch), each sending
+//!   prompts over its own LLM channel
+//! - Prompt history persisted across runs, recalled with Up/Down from an empty input field
+//!   and searched backwards with Ctrl+R
+//! - Export of the conversation to Markdown or HTML (`/export [file]`, Ctrl+E)
+//! - A sources panel listing the chunks retrieved for an answer, with a full-text viewer
+//!   and opening of their URLs in the browser
+//! - Follow-up questions suggested after answers from the index, asked with `/follow N`
+//! - Focus cycling between the chat, input and sources panels with Tab, and splits
+//!   resized with Alt+arrows, kept across runs
 //! - Text input handling with cursor management
 //! - Multi-line text editing with proper line wrapping
 //! - Mouse and keyboard event processing
@@ -22,15 +33,156 @@
 //! text operations like cursor positioning and scrolling.
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
+use hal::search::SearchResult;
 use ratatui::text::Text;
+use ratatui::widgets::ListState;
+use std::path::{Path, PathBuf};
 use tokio::sync::mpsc;
+use tracing::warn;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::tui::error::{Error, Result};
-use crate::tui::event::{AppEvent, Event, EventHandler};
+use crate::tui::event::{AppEvent, Event, EventHandler, LlmRequest};
+use crate::tui::export::{self, Conversation, ExportMessage};
+use crate::tui::history::{HistorySearch, PromptHistory};
+use crate::tui::layout::LayoutState;
 use crate::tui::markdown::markdown_to_ratatui_text;
 use crate::tui::scroll::ScrollState;
 
+/// Longest tab title taken from the first prompt of a session
+const MAX_TITLE_WIDTH: usize = 20;
+
+/// Panel receiving the keys that aren't shortcuts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    /// Chat history, scrolled with the arrow keys
+    Chat,
+    /// Input field
+    Input,
+    /// Sources panel, browsed with the arrow keys
+    Sources,
+}
+
+/// A chat session shown in its own tab
+pub struct Session {
+    /// Title shown in the tab bar
+    pub title: String,
+    /// LLM model the session chats with
+    pub model: String,
+    /// Rendered messages for display
+    pub rendered_messages: Vec<(String, Text<'static>)>, // (role, rendered_text)
+    /// Messages as written, for exports
+    pub messages: Vec<ExportMessage>,
+    /// Flag to indicate if we're waiting for LLM response
+    pub is_loading: bool,
+    /// Enhanced scroll state for chat history
+    pub chat_scroll: ScrollState,
+    /// Chunks retrieved from the index for the latest prompt
+    pub sources: Vec<SearchResult>,
+    /// Selection in the sources panel
+    pub sources_state: ListState,
+    /// Follow-up questions suggested after the latest answer
+    pub follow_ups: Vec<String>,
+    /// Channel to the LLM task of the session, set once the task is spawned
+    pub llm_tx: Option<mpsc::UnboundedSender<LlmRequest>>,
+    /// Whether the title was taken from a prompt yet
+    titled: bool,
+}
+
+impl Session {
+    /// Create a new session
+    pub fn new(title: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            model: model.into(),
+            rendered_messages: Vec::new(),
+            messages: Vec::new(),
+            is_loading: false,
+            chat_scroll: ScrollState::new(),
+            sources: Vec::new(),
+            sources_state: ListState::default(),
+            follow_ups: Vec::new(),
+            llm_tx: None,
+            titled: false,
+        }
+    }
+
+    /// Add a message to the chat history of the session
+    pub fn add_message(&mut self, role: &str, text: &str) {
+        let rendered_text = markdown_to_ratatui_text(text);
+        self.rendered_messages
+            .push((role.to_string(), rendered_text));
+        self.messages.push(ExportMessage {
+            role: role.to_string(),
+            text: text.to_string(),
+            sources: Vec::new(),
+        });
+    }
+
+    /// Write the conversation of the session to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to write, Markdown unless it ends in `.html`
+    /// * `template` - Template replacing the built-in one of the format
+    pub fn export(&self, path: &Path, template: Option<&str>) -> Result<()> {
+        let conversation = Conversation {
+            title: &self.title,
+            model: &self.model,
+            messages: &self.messages,
+        };
+        export::export(path, &conversation, template)
+    }
+
+    /// Calculate total height of all messages
+    pub fn calculate_total_height(&self) -> usize {
+        self.rendered_messages
+            .iter()
+            .map(|(_, text)| text.height() + 2) // +2 for role line and separator
+            .sum()
+    }
+
+    /// The source selected in the sources panel
+    pub fn selected_source(&self) -> Option<&SearchResult> {
+        self.sources_state
+            .selected()
+            .and_then(|index| self.sources.get(index))
+    }
+
+    /// Send a request to the LLM task of the session
+    fn send(&self, request: LlmRequest) -> Result<()> {
+        let Some(llm_tx) = &self.llm_tx else {
+            return Err(Error::Event("session has no LLM channel".to_string()));
+        };
+        llm_tx
+            .send(request)
+            .map_err(|e| Error::Event(e.to_string()))
+    }
+
+    /// Name the session after its first prompt
+    fn title_from_prompt(&mut self, prompt: &str) {
+        if self.titled {
+            return;
+        }
+        self.titled = true;
+        let mut title = String::new();
+        for word in prompt.split_whitespace() {
+            if title.width() + word.width() + 1 > MAX_TITLE_WIDTH {
+                if title.is_empty() {
+                    title = word.chars().take(MAX_TITLE_WIDTH - 1).collect();
+                }
+                title.push('…');
+                break;
+            }
+            if !title.is_empty() {
+                title.push(' ');
+            }
+            title.push_str(word);
+        }
+        self.title = title;
+    }
+}
+
 /// Application state
 pub struct App {
     /// Current input text
@@ -39,14 +191,30 @@ pub struct App {
     pub cursor_position: usize,
     /// Flag to indicate if the application should quit
     pub should_quit: bool,
-    /// Rendered messages for display
-    pub rendered_messages: Vec<(String, Text<'static>)>, // (role, rendered_text)
-    /// Flag to indicate if we're waiting for LLM response
-    pub is_loading: bool,
+    /// Chat sessions, one per tab
+    pub sessions: Vec<Session>,
+    /// Index of the session shown
+    pub active_session: usize,
+    /// LLM model for new sessions
+    pub default_model: String,
+    /// Whether prompts are answered from the index, which shows the sources panel
+    pub rag_enabled: bool,
+    /// Panel receiving the keys
+    pub focus: Focus,
+    /// Sizes of the resizable splits
+    pub layout: LayoutState,
+    /// File the layout is saved to when it changes, if persisted
+    pub layout_path: Option<PathBuf>,
+    /// Scroll offset of the full-text viewer of the selected source, when it is open
+    pub source_viewer: Option<u16>,
+    /// Template of exports, replacing the built-in one of each format
+    pub export_template: Option<String>,
+    /// Prompts entered before, recalled with Up and Down
+    pub prompt_history: PromptHistory,
+    /// Reverse search of the prompt history, while it is active
+    pub history_search: Option<HistorySearch>,
     /// Counter for spinner animation frames
     pub spinner_frame: usize,
-    /// Enhanced scroll state for chat history
-    pub chat_scroll: ScrollState,
     /// Enhanced scroll state for input field
     pub input_scroll: ScrollState,
     /// Event handler
@@ -54,8 +222,8 @@ pub struct App {
 }
 
 impl App {
-    /// Create a new application state
-    pub fn new() -> Self {
+    /// Create a new application state with one session
+    pub fn new(model: &str) -> Self {
         // Only create debug log file if HAL_TUI_DEBUG environment variable is set
         if std::env::var("HAL_TUI_DEBUG").is_ok() {
             let _ = std::fs::write("hal-debug.log", "Debug log started\n");
@@ -65,15 +233,65 @@ impl App {
             input: String::new(),
             cursor_position: 0,
             should_quit: false,
-            rendered_messages: Vec::new(),
-            is_loading: false,
+            sessions: vec![Session::new("Chat 1", model)],
+            active_session: 0,
+            default_model: model.to_string(),
+            rag_enabled: false,
+            focus: Focus::Input,
+            layout: LayoutState::default(),
+            layout_path: None,
+            source_viewer: None,
+            export_template: None,
+            prompt_history: PromptHistory::new(),
+            history_search: None,
             spinner_frame: 0,
-            chat_scroll: ScrollState::new(),
             input_scroll: ScrollState::new(),
             event_handler: EventHandler::new(),
         }
     }
 
+    /// The session shown
+    pub fn session(&self) -> &Session {
+        &self.sessions[self.active_session]
+    }
+
+    /// The session shown, mutably
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.sessions[self.active_session]
+    }
+
+    /// Open a new session in a new tab and show it
+    pub fn new_session(&mut self) {
+        let title = format!("Chat {}", self.sessions.len() + 1);
+        let mut session = Session::new(title, self.default_model.clone());
+        session.add_message(
+            "ui",
+            &format!(
+                "New session with `{}`. Type `/model <name>` to switch models.",
+                self.default_model
+            ),
+        );
+        self.sessions.push(session);
+        self.active_session = self.sessions.len() - 1;
+    }
+
+    /// Show the next session, wrapping around to the first
+    pub fn next_session(&mut self) {
+        self.active_session = (self.active_session + 1) % self.sessions.len();
+    }
+
+    /// Show the previous session, wrapping around to the last
+    pub fn previous_session(&mut self) {
+        self.active_session = (self.active_session + self.sessions.len() - 1) % self.sessions.len();
+    }
+
+    /// Show the session at an index, if it exists
+    pub fn select_session(&mut self, index: usize) {
+        if index < self.sessions.len() {
+            self.active_session = index;
+        }
+    }
+
     /// Get the next event
     pub async fn next_event(&mut self) -> Option<Event> {
         if let Some(event) = self.event_handler.next().await {
@@ -151,7 +369,7 @@ impl App {
                                     "Chat scroll up at row {} (terminal height: {})",
                                     mouse.row, height
                                 ));
-                                self.chat_scroll.scroll_by(-1);
+                                self.session_mut().chat_scroll.scroll_by(-1);
                             }
                         }
                         MouseEventKind::ScrollDown => {
@@ -191,7 +409,7 @@ impl App {
                                     "Chat scroll down at row {} (terminal height: {})",
                                     mouse.row, height
                                 ));
-                                self.chat_scroll.scroll_by(1);
+                                self.session_mut().chat_scroll.scroll_by(1);
                             }
                         }
                         MouseEventKind::Down(_) => {
@@ -235,18 +453,80 @@ impl App {
     /// Handle application events
     fn handle_app_event(&mut self, event: &AppEvent) -> Result<()> {
         match event {
-            AppEvent::Submit(input) => {
-                self.add_message("user", input);
-                self.is_loading = true;
+            AppEvent::Submit { session, input } => {
+                let Some(session) = self.sessions.get_mut(*session) else {
+                    return Ok(());
+                };
+                session.add_message("user", input);
+                session.title_from_prompt(input);
+                session.follow_ups.clear();
+                session.is_loading = true;
+                session.send(LlmRequest::Chat(input.clone()))?;
                 self.reset_input();
             }
-            AppEvent::LLMResponse(response) => {
-                self.is_loading = false;
-                self.add_message("model", response);
+            AppEvent::SetModel { session, model } => {
+                let Some(session) = self.sessions.get_mut(*session) else {
+                    return Ok(());
+                };
+                session.model = model.clone();
+                session.add_message("ui", &format!("Switched to `{}`.", model));
+                session.send(LlmRequest::SetModel(model.clone()))?;
+                self.reset_input();
+            }
+            AppEvent::Sources { session, sources } => {
+                if let Some(session) = self.sessions.get_mut(*session) {
+                    session.sources = sources.clone();
+                    session
+                        .sources_state
+                        .select((!sources.is_empty()).then_some(0));
+                }
+            }
+            AppEvent::Export { session, path } => {
+                let Some(session) = self.sessions.get_mut(*session) else {
+                    return Ok(());
+                };
+                let path = path.clone().unwrap_or_else(export::default_path);
+                let message = match session.export(&path, self.export_template.as_deref()) {
+                    Ok(()) => format!("Exported to `{}`.", path.display()),
+                    Err(e) => format!("Could not export to `{}`: {}", path.display(), e),
+                };
+                session.add_message("ui", &message);
+            }
+            AppEvent::LLMResponse { session, response } => {
+                if let Some(session) = self.sessions.get_mut(*session) {
+                    session.is_loading = false;
+                    session.add_message("model", response);
+                    // Cite the sources retrieved for the prompt in exports
+                    if let Some(message) = session.messages.last_mut() {
+                        message.sources = session.sources.clone();
+                    }
+                }
             }
-            AppEvent::LLMError(error) => {
-                self.is_loading = false;
-                self.add_message("model", &format!("Error: {}", error));
+            AppEvent::FollowUps { session, questions } => {
+                if let Some(session) = self.sessions.get_mut(*session) {
+                    let list: Vec<String> = questions
+                        .iter()
+                        .enumerate()
+                        .map(|(i, question)| format!("{}. {}", i + 1, question))
+                        .collect();
+                    session.add_message(
+                        "ui",
+                        &format!(
+                            "Follow-up questions (`/follow N` to ask):\n\n{}",
+                            list.join("\n")
+                        ),
+                    );
+                    session.follow_ups = questions.clone();
+                }
+            }
+            AppEvent::LLMError { session, error } => {
+                if let Some(session) = self.sessions.get_mut(*session) {
+                    session.is_loading = false;
+                    session.add_message("model", &format!("Error: {}", error));
+                }
+            }
+            AppEvent::NewSession => {
+                self.new_session();
             }
             AppEvent::Quit => {
                 self.should_quit = true;
@@ -255,8 +535,211 @@ impl App {
         Ok(())
     }
 
+    /// Handle keys while the full-text viewer of a source is open
+    fn handle_viewer_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(scroll) = self.source_viewer else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.source_viewer = None,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.source_viewer = Some(scroll.saturating_sub(1));
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.source_viewer = Some(scroll.saturating_add(1));
+            }
+            KeyCode::PageUp => self.source_viewer = Some(scroll.saturating_sub(10)),
+            KeyCode::PageDown => self.source_viewer = Some(scroll.saturating_add(10)),
+            KeyCode::Char('o') => self.open_selected_source(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handle keys while the sources panel is focused
+    ///
+    /// # Returns
+    ///
+    /// Whether the key was handled; other keys go to the input field
+    fn handle_sources_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            return false;
+        }
+        let session = self.session_mut();
+        let count = session.sources.len();
+        let selected = session.sources_state.selected();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') if count > 0 => {
+                let index = selected.map_or(0, |index| index.saturating_sub(1));
+                session.sources_state.select(Some(index));
+            }
+            KeyCode::Down | KeyCode::Char('j') if count > 0 => {
+                let index = selected.map_or(0, |index| (index + 1).min(count - 1));
+                session.sources_state.select(Some(index));
+            }
+            KeyCode::Enter if selected.is_some() => self.source_viewer = Some(0),
+            KeyCode::Char('o') => self.open_selected_source(),
+            KeyCode::Esc => self.focus = Focus::Input,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handle keys while the chat history is focused
+    ///
+    /// # Returns
+    ///
+    /// Whether the key was handled; other keys go to the input field
+    fn handle_chat_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_down(),
+            KeyCode::PageUp => self.session_mut().chat_scroll.scroll_by(-10),
+            KeyCode::PageDown => self.session_mut().chat_scroll.scroll_by(10),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll_to_top(),
+            KeyCode::End | KeyCode::Char('G') => self.scroll_to_bottom(),
+            KeyCode::Esc => self.focus = Focus::Input,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Move the focus to the next or previous panel, skipping the sources panel without
+    /// retrieval
+    pub fn cycle_focus(&mut self, forward: bool) {
+        let panels: &[Focus] = if self.rag_enabled {
+            &[Focus::Chat, Focus::Input, Focus::Sources]
+        } else {
+            &[Focus::Chat, Focus::Input]
+        };
+        let current = panels
+            .iter()
+            .position(|panel| *panel == self.focus)
+            .unwrap_or(1);
+        let next = if forward {
+            (current + 1) % panels.len()
+        } else {
+            (current + panels.len() - 1) % panels.len()
+        };
+        self.focus = panels[next];
+    }
+
+    /// Grow or shrink the input field and save the layout
+    fn resize_input(&mut self, delta: i16) {
+        self.layout.resize_input(delta);
+        self.save_layout();
+    }
+
+    /// Grow or shrink the sources panel and save the layout
+    fn resize_sources(&mut self, delta: i16) {
+        if !self.rag_enabled {
+            return;
+        }
+        self.layout.resize_sources(delta);
+        self.save_layout();
+    }
+
+    /// Save the layout, if it is persisted
+    fn save_layout(&self) {
+        let Some(path) = &self.layout_path else {
+            return;
+        };
+        if let Err(e) = self.layout.save(path) {
+            warn!("Failed to save TUI layout {}: {}", path.display(), e);
+        }
+    }
+
+    /// Open the URL of the selected source in the browser
+    fn open_selected_source(&mut self) {
+        let Some(url) = self
+            .session()
+            .selected_source()
+            .map(|source| source.url.clone())
+        else {
+            return;
+        };
+        if let Err(e) = open_in_browser(&url) {
+            self.add_message("ui", &format!("Could not open {}: {}", url, e));
+        }
+    }
+
+    /// Handle keys during a reverse search of the prompt history
+    ///
+    /// # Returns
+    ///
+    /// Whether the key was handled; other keys end the search, keeping the prompt found,
+    /// and are handled as usual
+    fn handle_history_search_key(&mut self, key: KeyEvent) -> bool {
+        let Some(search) = &mut self.history_search else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Find the next older match, keeping the current one if there is none
+                if let Some(found) = self.prompt_history.search(&search.query, search.found) {
+                    search.found = Some(found);
+                }
+            }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cancel_history_search();
+                return true;
+            }
+            KeyCode::Esc => {
+                self.cancel_history_search();
+                return true;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                search.query.push(c);
+                search.found = self.prompt_history.search(&search.query, None);
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.found = self.prompt_history.search(&search.query, None);
+            }
+            KeyCode::Enter if !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.history_search = None;
+                return true;
+            }
+            _ => {
+                self.history_search = None;
+                return false;
+            }
+        }
+
+        let prompt = match search.found {
+            Some(found) => self.prompt_history.get(found).unwrap_or_default(),
+            None => search.original_input.as_str(),
+        }
+        .to_string();
+        self.set_input(&prompt);
+        true
+    }
+
+    /// Cancel the reverse search of the prompt history, restoring the input it started from
+    fn cancel_history_search(&mut self) {
+        if let Some(search) = self.history_search.take() {
+            self.set_input(&search.original_input);
+        }
+    }
+
     /// Handle key events
     fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
+        if self.source_viewer.is_some() {
+            return self.handle_viewer_key(key);
+        }
+        if self.focus == Focus::Sources && self.handle_sources_key(key) {
+            return Ok(());
+        }
+        if self.focus == Focus::Chat && self.handle_chat_key(key) {
+            return Ok(());
+        }
+        if self.handle_history_search_key(key) {
+            return Ok(());
+        }
+
         match key.code {
             KeyCode::Esc => {
                 self.event_handler
@@ -291,14 +774,89 @@ impl App {
                 } else {
                     self.debug_log("Regular Enter detected");
                     let input = self.input.trim().to_string();
-                    if !input.is_empty() {
+                    self.prompt_history.push(&input);
+                    let session = self.active_session;
+                    let event = if input == "/model" || input.starts_with("/model ") {
+                        let model = input["/model".len()..].trim();
+                        (!model.is_empty()).then(|| AppEvent::SetModel {
+                            session,
+                            model: model.to_string(),
+                        })
+                    } else if input == "/follow" || input.starts_with("/follow ") {
+                        // Ask a suggested follow-up question, the first one by default
+                        let number = input["/follow".len()..].trim().parse::<usize>();
+                        self.session()
+                            .follow_ups
+                            .get(number.unwrap_or(1).saturating_sub(1))
+                            .cloned()
+                            .map(|input| AppEvent::Submit { session, input })
+                    } else if input == "/export" || input.starts_with("/export ") {
+                        let path = input["/export".len()..].trim();
+                        self.reset_input();
+                        Some(AppEvent::Export {
+                            session,
+                            path: (!path.is_empty()).then(|| PathBuf::from(path)),
+                        })
+                    } else {
+                        (!input.is_empty()).then_some(AppEvent::Submit { session, input })
+                    };
+                    if let Some(event) = event {
                         self.event_handler
                             .sender()
-                            .send(Event::App(AppEvent::Submit(input)))
+                            .send(Event::App(event))
                             .map_err(|e| Error::Event(e.to_string()))?;
                     }
                 }
             }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.prompt_history.reset();
+                self.history_search = Some(HistorySearch {
+                    original_input: self.input.clone(),
+                    ..Default::default()
+                });
+            }
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let session = self.active_session;
+                self.event_handler
+                    .sender()
+                    .send(Event::App(AppEvent::Export {
+                        session,
+                        path: None,
+                    }))
+                    .map_err(|e| Error::Event(e.to_string()))?;
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.focus = if self.rag_enabled && self.focus != Focus::Sources {
+                    Focus::Sources
+                } else {
+                    Focus::Input
+                };
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.event_handler
+                    .sender()
+                    .send(Event::App(AppEvent::NewSession))
+                    .map_err(|e| Error::Event(e.to_string()))?;
+            }
+            KeyCode::Tab if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                    self.previous_session();
+                } else {
+                    self.next_session();
+                }
+            }
+            KeyCode::BackTab if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.previous_session();
+            }
+            KeyCode::Tab => self.cycle_focus(true),
+            KeyCode::BackTab => self.cycle_focus(false),
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => self.resize_input(1),
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => self.resize_input(-1),
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::ALT) => self.resize_sources(5),
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::ALT) => self.resize_sources(-5),
+            KeyCode::Char(c @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.select_session(c as usize - '1' as usize);
+            }
             KeyCode::Char(c) => {
                 self.insert_char(c);
             }
@@ -317,11 +875,19 @@ impl App {
             KeyCode::Up => {
                 if key.modifiers.contains(KeyModifiers::CONTROL) {
                     self.scroll_up();
+                } else if self.input.is_empty() || self.prompt_history.is_browsing() {
+                    let older = self.prompt_history.older().map(str::to_string);
+                    if let Some(prompt) = older {
+                        self.set_input(&prompt);
+                    }
                 }
             }
             KeyCode::Down => {
                 if key.modifiers.contains(KeyModifiers::CONTROL) {
                     self.scroll_down();
+                } else if self.prompt_history.is_browsing() {
+                    let prompt = self.prompt_history.newer().unwrap_or_default().to_string();
+                    self.set_input(&prompt);
                 }
             }
             KeyCode::PageUp => {
@@ -329,10 +895,10 @@ impl App {
                     // Get a rough estimate of page size (half the terminal height)
                     if let Ok((_, height)) = crossterm::terminal::size() {
                         let page_size = (height / 2) as i32;
-                        self.chat_scroll.scroll_by(-page_size);
+                        self.session_mut().chat_scroll.scroll_by(-page_size);
                     } else {
                         // Fallback if terminal size can't be determined
-                        self.chat_scroll.scroll_by(-10);
+                        self.session_mut().chat_scroll.scroll_by(-10);
                     }
                 }
             }
@@ -341,10 +907,10 @@ impl App {
                     // Get a rough estimate of page size (half the terminal height)
                     if let Ok((_, height)) = crossterm::terminal::size() {
                         let page_size = (height / 2) as i32;
-                        self.chat_scroll.scroll_by(page_size);
+                        self.session_mut().chat_scroll.scroll_by(page_size);
                     } else {
                         // Fallback if terminal size can't be determined
-                        self.chat_scroll.scroll_by(10);
+                        self.session_mut().chat_scroll.scroll_by(10);
                     }
                 }
             }
@@ -363,32 +929,27 @@ impl App {
         Ok(())
     }
 
-    /// Add a message to the chat history
+    /// Add a message to the chat history of the session shown
     pub fn add_message(&mut self, role: &str, text: &str) {
-        // Add to rendered messages for display
-        let rendered_text = markdown_to_ratatui_text(text);
-        self.rendered_messages
-            .push((role.to_string(), rendered_text));
-
-        // Calculate total height
-        let _total_height = self.calculate_total_height();
-
         // Note: We'll update the scroll position in the UI rendering code
         // based on the viewport size and content height
-    }
-
-    /// Calculate total height of all messages
-    pub fn calculate_total_height(&self) -> usize {
-        self.rendered_messages
-            .iter()
-            .map(|(_, text)| text.height() + 2) // +2 for role line and separator
-            .sum()
+        self.session_mut().add_message(role, text);
     }
 
     /// Reset the input field
     pub fn reset_input(&mut self) {
         self.input.clear();
         self.cursor_position = 0;
+        self.prompt_history.reset();
+    }
+
+    /// Replace the input field, with the cursor at its end
+    pub fn set_input(&mut self, text: &str) {
+        self.input = text.to_string();
+        self.cursor_position = self.input.len();
+        if let Ok((width, _)) = crossterm::terminal::size() {
+            self.update_input_scroll_position(width);
+        }
     }
 
     /// Move cursor left in the input field
@@ -448,29 +1009,29 @@ impl App {
 
     /// Scroll chat history up
     pub fn scroll_up(&mut self) {
-        self.chat_scroll.scroll_by(-1);
+        self.session_mut().chat_scroll.scroll_by(-1);
     }
 
     /// Scroll chat history down
     pub fn scroll_down(&mut self) {
-        self.chat_scroll.scroll_by(1);
+        self.session_mut().chat_scroll.scroll_by(1);
     }
 
     /// Scroll to the top of chat history
     pub fn scroll_to_top(&mut self) {
-        self.chat_scroll.scroll_to_top();
+        self.session_mut().chat_scroll.scroll_to_top();
     }
 
     /// Scroll to the bottom of chat history
     pub fn scroll_to_bottom(&mut self) {
-        self.chat_scroll.scroll_to_bottom();
+        self.session_mut().chat_scroll.scroll_to_bottom();
     }
 
     // Remove the scroll_by method since we're now using chat_scroll.scroll_by directly
 
     /// Update spinner frame
     pub fn tick_spinner(&mut self) {
-        if self.is_loading {
+        if self.sessions.iter().any(|session| session.is_loading) {
             self.spinner_frame = (self.spinner_frame + 1) % 8;
         }
     }
@@ -704,3 +1265,22 @@ impl App {
         }
     }
 }
+
+/// Open a URL with the default browser of the platform
+fn open_in_browser(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
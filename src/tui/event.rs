@@ -1,5 +1,7 @@
 use crossterm::event::Event as CrosstermEvent;
 use futures::{FutureExt, StreamExt};
+use hal::search::SearchResult;
+use std::path::PathBuf;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
@@ -18,18 +20,49 @@ pub enum Event {
 }
 
 /// Application specific events
+///
+/// Events about a chat session carry the index of the session in `App::sessions`, so
+/// responses reach their own tab while another tab is shown.
 #[derive(Debug)]
 pub enum AppEvent {
-    /// Submit the current input
-    Submit(String),
+    /// Submit the current input to a session
+    Submit { session: usize, input: String },
+    /// Switch the LLM model of a session
+    SetModel { session: usize, model: String },
+    /// Export the conversation of a session, to a file named after the time if no path is given
+    Export {
+        session: usize,
+        path: Option<PathBuf>,
+    },
+    /// Chunks retrieved from the index for the latest prompt of a session
+    Sources {
+        session: usize,
+        sources: Vec<SearchResult>,
+    },
     /// Received response from LLM
-    LLMResponse(String),
+    LLMResponse { session: usize, response: String },
+    /// Follow-up questions suggested after the latest answer of a session
+    FollowUps {
+        session: usize,
+        questions: Vec<String>,
+    },
     /// Error from LLM
-    LLMError(String),
+    LLMError { session: usize, error: String },
+    /// Open a new session in a new tab
+    NewSession,
     /// Quit the application
     Quit,
 }
 
+/// Requests sent to the LLM task of a session
+#[derive(Debug)]
+pub enum LlmRequest {
+    /// Send a prompt with the session's message history
+    Chat(String),
+    /// Use a different model for the rest of the session, keeping its history
+    SetModel(String),
+}
+
 /// Event handler that manages the event stream
 pub struct EventHandler {
     /// Event sender
@@ -0,0 +1,326 @@
+//! # Conversation Export
+//!
+//! Writes the conversation of a chat session to a Markdown or HTML file, picked by the file
+//! extension. Code blocks are kept as fenced blocks, or `<pre><code>` in HTML, and answers
+//! list the sources they were grounded on.
+//!
+//! ## Templates
+//!
+//! The document is built from a template with these placeholders:
+//!
+//! - `{{title}}`: Title of the session
+//! - `{{model}}`: LLM model of the session
+//! - `{{date}}`: Time of the export
+//! - `{{messages}}`: The conversation, rendered in the format of the file
+//!
+//! `hal chat --export-template <FILE>` replaces the built-in template of each format.
+
+use std::path::{Path, PathBuf};
+
+use hal::search::SearchResult;
+use pulldown_cmark::{Options, Parser, html};
+
+use crate::tui::error::Result;
+
+/// Built-in template of Markdown exports
+const MARKDOWN_TEMPLATE: &str = "# {{title}}\n\n_Exported {{date}} · {{model}}_\n\n{{messages}}";
+
+/// Built-in template of HTML exports
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { max-width: 50rem; margin: 2rem auto; font-family: sans-serif; line-height: 1.5; }
+.message { margin-bottom: 1.5rem; }
+.role { font-weight: bold; }
+pre { background: #f4f4f4; padding: 0.75rem; overflow-x: auto; }
+.sources { font-size: 0.9rem; color: #555; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<p><em>Exported {{date}} · {{model}}</em></p>
+{{messages}}
+</body>
+</html>
+"#;
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    /// Format of a file by its extension, Markdown unless it is `.html` or `.htm`
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension)
+                if extension.eq_ignore_ascii_case("html")
+                    || extension.eq_ignore_ascii_case("htm") =>
+            {
+                ExportFormat::Html
+            }
+            _ => ExportFormat::Markdown,
+        }
+    }
+
+    /// Built-in template of the format
+    fn template(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => MARKDOWN_TEMPLATE,
+            ExportFormat::Html => HTML_TEMPLATE,
+        }
+    }
+}
+
+/// A message of the conversation, kept as the Markdown it was written in
+#[derive(Debug, Clone)]
+pub struct ExportMessage {
+    /// Role of the author: `user`, `model` or `ui`
+    pub role: String,
+    /// Markdown text of the message
+    pub text: String,
+    /// Chunks the message was answered from
+    pub sources: Vec<SearchResult>,
+}
+
+/// A conversation to export
+pub struct Conversation<'a> {
+    /// Title of the session
+    pub title: &'a str,
+    /// LLM model of the session
+    pub model: &'a str,
+    /// Messages of the session; `ui` messages are left out
+    pub messages: &'a [ExportMessage],
+}
+
+/// Default file name of an export, from the current time
+pub fn default_path() -> PathBuf {
+    PathBuf::from(format!(
+        "hal-chat-{}.md",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Render a conversation in a format
+///
+/// # Arguments
+///
+/// * `conversation` - The conversation to render
+/// * `format` - Format of the document
+/// * `template` - Template replacing the built-in one of the format
+pub fn render(conversation: &Conversation, format: ExportFormat, template: Option<&str>) -> String {
+    let messages = conversation
+        .messages
+        .iter()
+        .filter(|message| message.role != "ui")
+        .map(|message| match format {
+            ExportFormat::Markdown => message_to_markdown(message),
+            ExportFormat::Html => message_to_html(message),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let (title, model) = match format {
+        ExportFormat::Markdown => (
+            conversation.title.to_string(),
+            conversation.model.to_string(),
+        ),
+        ExportFormat::Html => (
+            escape_html(conversation.title),
+            escape_html(conversation.model),
+        ),
+    };
+
+    template
+        .unwrap_or(format.template())
+        .replace("{{title}}", &title)
+        .replace("{{model}}", &model)
+        .replace(
+            "{{date}}",
+            &chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        )
+        .replace("{{messages}}", &messages)
+}
+
+/// Write a conversation to a file, in the format of its extension
+///
+/// # Arguments
+///
+/// * `path` - File to write
+/// * `conversation` - The conversation to export
+/// * `template` - Template replacing the built-in one of the format
+pub fn export(path: &Path, conversation: &Conversation, template: Option<&str>) -> Result<()> {
+    let document = render(conversation, ExportFormat::from_path(path), template);
+    std::fs::write(path, document)?;
+    Ok(())
+}
+
+/// Display name of a role
+fn role_name(role: &str) -> &str {
+    match role {
+        "user" => "You",
+        "model" => "AI",
+        other => other,
+    }
+}
+
+/// Title of a source in citations
+fn source_title(source: &SearchResult) -> &str {
+    source.heading_path.as_deref().unwrap_or(&source.url)
+}
+
+/// Render a message as Markdown, followed by its sources
+fn message_to_markdown(message: &ExportMessage) -> String {
+    let mut markdown = format!(
+        "## {}\n\n{}\n",
+        role_name(&message.role),
+        message.text.trim_end()
+    );
+    if !message.sources.is_empty() {
+        markdown.push_str("\n**Sources**\n\n");
+        for (i, source) in message.sources.iter().enumerate() {
+            markdown.push_str(&format!(
+                "{}. [{}]({}) ({:.3})\n",
+                i + 1,
+                source_title(source),
+                source.url,
+                source.score
+            ));
+        }
+    }
+    markdown
+}
+
+/// Render a message as HTML, followed by its sources
+fn message_to_html(message: &ExportMessage) -> String {
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(&message.text, Options::all()));
+
+    let mut html = format!(
+        "<div class=\"message {}\">\n<div class=\"role\">{}</div>\n{}",
+        escape_html(&message.role),
+        escape_html(role_name(&message.role)),
+        body
+    );
+    if !message.sources.is_empty() {
+        html.push_str("<ol class=\"sources\">\n");
+        for source in &message.sources {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> ({:.3})</li>\n",
+                escape_html(&source.url),
+                escape_html(source_title(source)),
+                source.score
+            ));
+        }
+        html.push_str("</ol>\n");
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// Escape text for HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, text: &str) -> ExportMessage {
+        ExportMessage {
+            role: role.to_string(),
+            text: text.to_string(),
+            sources: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("chat.html")),
+            ExportFormat::Html
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("chat.HTM")),
+            ExportFormat::Html
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("chat.md")),
+            ExportFormat::Markdown
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("chat")),
+            ExportFormat::Markdown
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_skips_ui_messages() {
+        let messages = vec![
+            message("ui", "# Welcome"),
+            message("user", "How do I build?"),
+            message("model", "Run:\n\n```sh\ncargo build\n```"),
+        ];
+        let conversation = Conversation {
+            title: "Build",
+            model: "gemini-2.0-flash",
+            messages: &messages,
+        };
+
+        let document = render(
+            &conversation,
+            ExportFormat::Markdown,
+            Some("{{title}}|{{model}}\n{{messages}}"),
+        );
+
+        assert!(document.starts_with("Build|gemini-2.0-flash\n## You"));
+        assert!(document.contains("```sh\ncargo build\n```"));
+        assert!(!document.contains("Welcome"));
+    }
+
+    #[test]
+    fn test_render_html_escapes_and_cites_sources() {
+        let mut answer = message("model", "Use `<T>`:\n\n```rust\nfn f<T>() {}\n```");
+        answer.sources.push(SearchResult {
+            chunk_id: 1,
+            text: "text".to_string(),
+            context: "context".to_string(),
+            url: "https://example.com/?a=1&b=2".to_string(),
+            website_url: "https://example.com".to_string(),
+            website_domain: "example.com".to_string(),
+            heading_path: Some("Guide > Generics".to_string()),
+            score: 0.5,
+            ..Default::default()
+        });
+        let messages = vec![answer];
+        let conversation = Conversation {
+            title: "<script>",
+            model: "m",
+            messages: &messages,
+        };
+
+        let document = render(&conversation, ExportFormat::Html, None);
+
+        assert!(document.contains("<title>&lt;script&gt;</title>"));
+        assert!(document.contains("<pre><code class=\"language-rust\">fn f&lt;T&gt;() {}"));
+        assert!(document.contains("href=\"https://example.com/?a=1&amp;b=2\""));
+        assert!(document.contains("Guide &gt; Generics</a> (0.500)"));
+    }
+}
@@ -0,0 +1,179 @@
+//! # Code Highlighting
+//!
+//! Highlights fenced code blocks of responses with syntect, so long answers with code stay
+//! readable. Blocks in a language syntect doesn't know, or without a language, are shown in
+//! the plain code style instead.
+//!
+//! The theme is chosen once at startup with `hal chat --theme <name>`; syntect's built-in
+//! themes are available, `base16-ocean.dark` being the default.
+
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Theme used unless another one is chosen
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+/// Syntax definitions, loaded on first use
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+
+/// Built-in themes, loaded on first use
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+/// Name of the chosen theme
+static THEME: OnceLock<String> = OnceLock::new();
+
+fn syntaxes() -> &'static SyntaxSet {
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Names of the available themes
+pub fn theme_names() -> Vec<&'static str> {
+    themes().themes.keys().map(String::as_str).collect()
+}
+
+/// Choose the theme of code blocks
+///
+/// # Arguments
+///
+/// * `name` - Name of a built-in syntect theme
+///
+/// # Returns
+///
+/// An error listing the available themes if there is no theme of that name
+pub fn set_theme(name: &str) -> anyhow::Result<()> {
+    if !themes().themes.contains_key(name) {
+        return Err(anyhow!(
+            "Unknown theme '{}', expected one of: {}",
+            name,
+            theme_names().join(", ")
+        ));
+    }
+    // The theme is only chosen once, at startup
+    let _ = THEME.set(name.to_string());
+    Ok(())
+}
+
+/// The chosen theme
+fn theme() -> &'static Theme {
+    let name = THEME.get().map_or(DEFAULT_THEME, String::as_str);
+    &themes().themes[name]
+}
+
+/// Style of code blocks that aren't highlighted
+fn plain_style() -> Style {
+    Style::default().fg(Color::Green)
+}
+
+/// Render a code block as lines, highlighted if its language is known
+///
+/// # Arguments
+///
+/// * `code` - Text of the code block
+/// * `language` - Language of a fenced code block, possibly followed by attributes, or empty
+pub fn highlight_code(code: &str, language: &str) -> Vec<Line<'static>> {
+    let token = language.split([' ', ',', '{']).next().unwrap_or_default();
+    let syntax = (!token.is_empty())
+        .then(|| syntaxes().find_syntax_by_token(token))
+        .flatten();
+    let Some(syntax) = syntax else {
+        return plain_lines(code);
+    };
+
+    let mut highlighter = HighlightLines::new(syntax, theme());
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let Ok(ranges) = highlighter.highlight_line(line, syntaxes()) else {
+            return plain_lines(code);
+        };
+        let spans: Vec<Span<'static>> = ranges
+            .into_iter()
+            .map(|(style, text)| {
+                Span::styled(
+                    text.trim_end_matches(['\n', '\r']).to_string(),
+                    convert_style(style),
+                )
+            })
+            .filter(|span| !span.content.is_empty())
+            .collect();
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Render a code block without highlighting
+fn plain_lines(code: &str) -> Vec<Line<'static>> {
+    code.lines()
+        .map(|line| Line::from(Span::styled(line.to_string(), plain_style())))
+        .collect()
+}
+
+/// Convert a syntect style to a ratatui style, leaving the background to the terminal
+fn convert_style(style: syntect::highlighting::Style) -> Style {
+    let foreground = style.foreground;
+    let mut converted = Style::default().fg(Color::Rgb(foreground.r, foreground.g, foreground.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        converted = converted.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        converted = converted.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        converted = converted.add_modifier(Modifier::UNDERLINED);
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_highlights_known_language() {
+        let lines = highlight_code("fn main() {\n    let x = 1;\n}\n", "rust");
+
+        assert_eq!(text(&lines), vec!["fn main() {", "    let x = 1;", "}"]);
+        assert!(lines[0].spans.len() > 1);
+        assert!(
+            lines[0]
+                .spans
+                .iter()
+                .all(|span| matches!(span.style.fg, Some(Color::Rgb(..))))
+        );
+    }
+
+    #[test]
+    fn test_falls_back_for_unknown_language() {
+        let lines = highlight_code("some text\nmore\n", "not-a-language");
+
+        assert_eq!(text(&lines), vec!["some text", "more"]);
+        assert!(
+            lines
+                .iter()
+                .all(|line| line.spans[0].style == plain_style())
+        );
+        assert_eq!(text(&highlight_code("plain\n", "")), vec!["plain"]);
+    }
+
+    #[test]
+    fn test_set_theme_rejects_unknown_theme() {
+        assert!(theme_names().contains(&DEFAULT_THEME));
+        assert!(set_theme("no-such-theme").is_err());
+    }
+}
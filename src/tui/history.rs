@@ -0,0 +1,216 @@
+//! # Prompt History
+//!
+//! Keeps the prompts entered in the TUI so they can be recalled like in a shell: Up and Down
+//! browse them from an empty input field, and Ctrl+R searches them backwards.
+//!
+//! Prompts are appended to `.hal/prompt_history.jsonl` in the working directory, one JSON
+//! string per line so multi-line prompts survive, and are loaded again on the next start.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+/// Most prompts kept in the history
+const MAX_ENTRIES: usize = 1000;
+
+/// Prompts entered in earlier and current sessions, oldest first
+pub struct PromptHistory {
+    /// The prompts, oldest first
+    entries: Vec<String>,
+    /// File the prompts are appended to, if persisted
+    path: Option<PathBuf>,
+    /// Prompt shown while browsing with Up and Down
+    position: Option<usize>,
+}
+
+impl PromptHistory {
+    /// Create a history that is not persisted
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            path: None,
+            position: None,
+        }
+    }
+
+    /// Load the history from a file, starting empty if it does not exist or cannot be read
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut entries: Vec<String> = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                warn!("Failed to read prompt history {}: {}", path.display(), e);
+                Vec::new()
+            }
+        };
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+        Self {
+            entries,
+            path: Some(path),
+            position: None,
+        }
+    }
+
+    /// Default location of the history, in the `.hal` directory of the working directory
+    pub fn default_path() -> PathBuf {
+        Path::new(".hal").join("prompt_history.jsonl")
+    }
+
+    /// Add a prompt, unless it repeats the latest one, and stop browsing
+    pub fn push(&mut self, prompt: &str) {
+        self.position = None;
+        if prompt.is_empty() || self.entries.last().is_some_and(|last| last == prompt) {
+            return;
+        }
+        self.entries.push(prompt.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = append(path, prompt) {
+            warn!("Failed to save prompt history {}: {}", path.display(), e);
+        }
+    }
+
+    /// Whether a prompt is shown by browsing with Up and Down
+    pub fn is_browsing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Stop browsing
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// Browse to the prompt before the one shown, or the latest one when not browsing
+    pub fn older(&mut self) -> Option<&str> {
+        let position = match self.position {
+            Some(position) => position.saturating_sub(1),
+            None => self.entries.len().checked_sub(1)?,
+        };
+        self.position = Some(position);
+        self.entries.get(position).map(String::as_str)
+    }
+
+    /// Browse to the prompt after the one shown
+    ///
+    /// # Returns
+    ///
+    /// The prompt, or `None` past the latest prompt, which stops browsing
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position >= self.entries.len() {
+            self.position = None;
+            return None;
+        }
+        self.position = Some(position);
+        self.entries.get(position).map(String::as_str)
+    }
+
+    /// Find the latest prompt containing a query, before an index
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Text the prompt contains, ignoring case
+    /// * `before` - Only prompts before this index are searched, all if `None`
+    ///
+    /// # Returns
+    ///
+    /// The index of the prompt found
+    pub fn search(&self, query: &str, before: Option<usize>) -> Option<usize> {
+        let query = query.to_lowercase();
+        let end = before.unwrap_or(self.entries.len()).min(self.entries.len());
+        self.entries[..end]
+            .iter()
+            .rposition(|entry| entry.to_lowercase().contains(&query))
+    }
+
+    /// The prompt at an index
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+}
+
+impl Default for PromptHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append a prompt to the history file, creating it and its directory as needed
+fn append(path: &Path, prompt: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(prompt)?)
+}
+
+/// State of a reverse search of the prompt history (Ctrl+R)
+#[derive(Debug, Clone, Default)]
+pub struct HistorySearch {
+    /// Text searched for
+    pub query: String,
+    /// Index of the prompt found
+    pub found: Option<usize>,
+    /// Input before the search started, restored when it is cancelled
+    pub original_input: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_browse_older_and_newer() {
+        let mut history = PromptHistory::new();
+        history.push("first");
+        history.push("second");
+        history.push("second");
+
+        assert_eq!(history.older(), Some("second"));
+        assert_eq!(history.older(), Some("first"));
+        assert_eq!(history.older(), Some("first"));
+        assert_eq!(history.newer(), Some("second"));
+        assert_eq!(history.newer(), None);
+        assert!(!history.is_browsing());
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_search_backwards() {
+        let mut history = PromptHistory::new();
+        history.push("index the docs");
+        history.push("search rust");
+        history.push("Index again");
+
+        assert_eq!(history.search("index", None), Some(2));
+        assert_eq!(history.search("index", Some(2)), Some(0));
+        assert_eq!(history.search("index", Some(0)), None);
+        assert_eq!(history.search("missing", None), None);
+    }
+
+    #[test]
+    fn test_persists_multi_line_prompts() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(".hal").join("prompt_history.jsonl");
+
+        let mut history = PromptHistory::load(&path);
+        history.push("line one\nline two");
+        history.push("another");
+
+        let mut reloaded = PromptHistory::load(&path);
+        assert_eq!(reloaded.older(), Some("another"));
+        assert_eq!(reloaded.older(), Some("line one\nline two"));
+    }
+}
@@ -0,0 +1,142 @@
+//! # Layout State
+//!
+//! Sizes of the resizable splits of the TUI: the height of the input field and the width of
+//! the sources panel. Alt+Up/Down and Alt+Left/Right change them, and they are saved to
+//! `.hal/tui_layout.json` in the working directory so the next start keeps them.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Smallest height of the input field, in lines including its borders
+pub const MIN_INPUT_HEIGHT: u16 = 3;
+
+/// Largest height of the input field, in lines including its borders
+pub const MAX_INPUT_HEIGHT: u16 = 30;
+
+/// Smallest width of the sources panel, in percent of the screen
+pub const MIN_SOURCES_WIDTH: u16 = 15;
+
+/// Largest width of the sources panel, in percent of the screen
+pub const MAX_SOURCES_WIDTH: u16 = 70;
+
+/// Sizes of the resizable splits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutState {
+    /// Height of the input field, in lines including its borders; it grows with longer
+    /// prompts up to half the screen
+    pub input_height: u16,
+    /// Width of the sources panel, in percent of the screen
+    pub sources_width: u16,
+}
+
+impl Default for LayoutState {
+    fn default() -> Self {
+        Self {
+            input_height: 5,
+            sources_width: 35,
+        }
+    }
+}
+
+impl LayoutState {
+    /// Default location of the layout state, in the `.hal` directory of the working directory
+    pub fn default_path() -> PathBuf {
+        Path::new(".hal").join("tui_layout.json")
+    }
+
+    /// Load the layout state, falling back to the defaults if it is missing or invalid
+    pub fn load(path: &Path) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Failed to read TUI layout {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        match serde_json::from_str::<LayoutState>(&contents) {
+            Ok(layout) => layout.clamped(),
+            Err(e) => {
+                warn!("Invalid TUI layout {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Save the layout state, creating its directory as needed
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Grow or shrink the input field by a number of lines, within its limits
+    pub fn resize_input(&mut self, delta: i16) {
+        self.input_height = self.input_height.saturating_add_signed(delta);
+        *self = self.clamped();
+    }
+
+    /// Grow or shrink the sources panel by a number of percent, within its limits
+    pub fn resize_sources(&mut self, delta: i16) {
+        self.sources_width = self.sources_width.saturating_add_signed(delta);
+        *self = self.clamped();
+    }
+
+    /// The layout state with its sizes within their limits
+    fn clamped(self) -> Self {
+        Self {
+            input_height: self.input_height.clamp(MIN_INPUT_HEIGHT, MAX_INPUT_HEIGHT),
+            sources_width: self
+                .sources_width
+                .clamp(MIN_SOURCES_WIDTH, MAX_SOURCES_WIDTH),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resize_within_limits() {
+        let mut layout = LayoutState::default();
+
+        layout.resize_input(-10);
+        assert_eq!(layout.input_height, MIN_INPUT_HEIGHT);
+        layout.resize_input(100);
+        assert_eq!(layout.input_height, MAX_INPUT_HEIGHT);
+
+        layout.resize_sources(5);
+        assert_eq!(layout.sources_width, 40);
+        layout.resize_sources(-100);
+        assert_eq!(layout.sources_width, MIN_SOURCES_WIDTH);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(".hal").join("tui_layout.json");
+        assert_eq!(LayoutState::load(&path), LayoutState::default());
+
+        let layout = LayoutState {
+            input_height: 8,
+            sources_width: 50,
+        };
+        layout.save(&path).unwrap();
+        assert_eq!(LayoutState::load(&path), layout);
+
+        std::fs::write(&path, r#"{"input_height": 1000}"#).unwrap();
+        assert_eq!(
+            LayoutState::load(&path),
+            LayoutState {
+                input_height: MAX_INPUT_HEIGHT,
+                sources_width: 35,
+            }
+        );
+    }
+}
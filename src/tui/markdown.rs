@@ -11,7 +11,7 @@
 //! ## Features
 //!
 //! - Heading rendering with different colors based on level
-//! - Code block formatting with language indicators
+//! - Code block formatting with language indicators and syntax highlighting
 //! - Syntax highlighting for inline code
 //! - Support for lists (ordered and unordered)
 //! - Block quote styling
@@ -29,19 +29,26 @@ use ratatui::{
     text::{Line, Span, Text},
 };
 
+use crate::tui::highlight::highlight_code;
+
 /// Converts markdown text to ratatui Text for rendering in the terminal UI
 pub fn markdown_to_ratatui_text(markdown: &str) -> Text<'static> {
     let parser = Parser::new_ext(markdown, Options::all());
     let mut lines: Vec<Line> = Vec::new();
     let mut current_line: Vec<Span> = Vec::new();
     let mut current_style = Style::default();
-    let mut _in_code_block = false;
+    // Language and text of the code block being read, highlighted once it ends
+    let mut code_block: Option<(String, String)> = None;
     let mut list_level = 0;
 
     for event in parser {
         match event {
             Event::Text(text) => {
-                current_line.push(Span::styled(text.to_string(), current_style));
+                if let Some((_, code)) = &mut code_block {
+                    code.push_str(&text);
+                } else {
+                    current_line.push(Span::styled(text.to_string(), current_style));
+                }
             }
             Event::Code(code) => {
                 // Inline code styling
@@ -84,9 +91,6 @@ pub fn markdown_to_ratatui_text(markdown: &str) -> Text<'static> {
                         current_line.push(Span::raw("  │ "));
                     }
                     Tag::CodeBlock(kind) => {
-                        _in_code_block = true;
-                        current_style = Style::default().fg(Color::Green);
-
                         // Add a blank line before code blocks
                         if !current_line.is_empty() {
                             lines.push(Line::from(std::mem::take(&mut current_line)));
@@ -94,16 +98,19 @@ pub fn markdown_to_ratatui_text(markdown: &str) -> Text<'static> {
                         lines.push(Line::from(""));
 
                         // Add language indicator for fenced code blocks
-                        if let CodeBlockKind::Fenced(lang) = kind {
-                            if !lang.is_empty() {
-                                lines.push(Line::from(vec![Span::styled(
-                                    format!("[{}]", lang),
-                                    Style::default()
-                                        .fg(Color::Blue)
-                                        .add_modifier(Modifier::ITALIC),
-                                )]));
-                            }
+                        let lang = match kind {
+                            CodeBlockKind::Fenced(lang) => lang.to_string(),
+                            CodeBlockKind::Indented => String::new(),
+                        };
+                        if !lang.is_empty() {
+                            lines.push(Line::from(vec![Span::styled(
+                                format!("[{}]", lang),
+                                Style::default()
+                                    .fg(Color::Blue)
+                                    .add_modifier(Modifier::ITALIC),
+                            )]));
                         }
+                        code_block = Some((lang, String::new()));
                     }
                     Tag::List(start) => {
                         list_level += 1;
@@ -162,10 +169,8 @@ pub fn markdown_to_ratatui_text(markdown: &str) -> Text<'static> {
                     lines.push(Line::from(""));
                 }
                 TagEnd::CodeBlock => {
-                    _in_code_block = false;
-                    current_style = Style::default();
-                    if !current_line.is_empty() {
-                        lines.push(Line::from(std::mem::take(&mut current_line)));
+                    if let Some((lang, code)) = code_block.take() {
+                        lines.extend(highlight_code(&code, &lang));
                     }
                     lines.push(Line::from(""));
                 }
@@ -3,59 +3,133 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap},
 };
 use unicode_width::UnicodeWidthStr;
 
-use crate::tui::app::App;
+use crate::tui::app::{App, Focus};
 use crate::tui::scrollbar::render_enhanced_scrollbar;
 
 const SPINNER_FRAMES: [&str; 8] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧"];
 
 /// Draw the UI
 pub fn draw(f: &mut Frame, app: &mut App) {
-    // Create main layout
+    // Create main layout, growing the input field with long prompts up to half the screen
+    let area = f.area();
+    let input_height = input_height(app, area);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Min(1),    // Chat history
-            Constraint::Length(5), // Input field (increased height)
-            Constraint::Length(1), // Status bar
+            Constraint::Length(1),            // Session tabs
+            Constraint::Min(1),               // Chat history
+            Constraint::Length(input_height), // Input field
+            Constraint::Length(1),            // Status bar
         ])
-        .split(f.area());
-
-    // Render chat history
-    render_messages(f, app, chunks[0]);
+        .split(area);
+
+    // Render session tabs
+    render_tabs(f, app, chunks[0]);
+
+    // Render chat history, next to the sources panel when answering from the index
+    if app.rag_enabled {
+        let sources_width = app.layout.sources_width;
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(100 - sources_width),
+                Constraint::Percentage(sources_width),
+            ])
+            .split(chunks[1]);
+        render_messages(f, app, columns[0]);
+        render_sources(f, app, columns[1]);
+    } else {
+        render_messages(f, app, chunks[1]);
+    }
 
     // Render input field
-    render_input(f, app, chunks[1]);
+    render_input(f, app, chunks[2]);
 
     // Render status bar
-    render_command_help(f, chunks[2]);
+    render_command_help(f, app, chunks[3]);
+
+    // Render the full text of the selected source over everything else
+    if let Some(scroll) = app.source_viewer {
+        render_source_viewer(f, app, scroll);
+    }
+}
+
+/// Height of the input field: the height set in the layout, or more to fit the prompt
+fn input_height(app: &App, area: Rect) -> u16 {
+    // Two columns for the borders and two for the prompt
+    let width = area.width.saturating_sub(4).max(1) as usize;
+    let lines: usize = app
+        .input
+        .split('\n')
+        .map(|line| line.width().saturating_sub(1) / width + 1)
+        .sum();
+    let needed = (lines as u16).saturating_add(2);
+    let max = (area.height / 2).max(app.layout.input_height);
+    needed.clamp(app.layout.input_height, max)
+}
+
+/// Render the tabs of the chat sessions, with a spinner on sessions waiting for a response
+fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
+    let titles: Vec<Line> = app
+        .sessions
+        .iter()
+        .enumerate()
+        .map(|(i, session)| {
+            let status = if session.is_loading {
+                SPINNER_FRAMES[app.spinner_frame]
+            } else {
+                " "
+            };
+            Line::from(format!("{} {} {}", i + 1, session.title, status))
+        })
+        .collect();
+
+    let tabs = Tabs::new(titles)
+        .select(app.active_session)
+        .style(Style::default().fg(Color::Gray))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .divider(Span::styled("│", Style::default().fg(Color::DarkGray)));
+
+    f.render_widget(tabs, area);
 }
 
 /// Render chat messages
 fn render_messages(f: &mut Frame, app: &mut App, area: Rect) {
-    let messages_block = Block::default().borders(Borders::ALL).title(Span::styled(
-        "Chat History",
-        Style::default()
-            .fg(Color::Cyan)
-            .add_modifier(Modifier::BOLD),
-    ));
+    let spinner_frame = app.spinner_frame;
+    let border_style = focus_border_style(app, Focus::Chat);
+    let session = app.session_mut();
+    let messages_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(
+            format!("Chat History · {}", session.model),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
 
     f.render_widget(messages_block.clone(), area);
     let inner_area = messages_block.inner(area);
 
     // Determine visible content based on scroll position
-    let content_height = app.calculate_total_height();
+    let content_height = session.calculate_total_height();
     let viewport_height = inner_area.height as usize;
 
     // Update scroll state with current content
-    app.chat_scroll
+    session
+        .chat_scroll
         .update_content_size(content_height, viewport_height);
 
     // Get visible content range based on scroll position
-    let scroll_offset = app.chat_scroll.position;
+    let scroll_offset = session.chat_scroll.position;
 
     // Create a paragraph for each message
     let mut lines: Vec<Line> = Vec::new();
@@ -63,7 +137,7 @@ fn render_messages(f: &mut Frame, app: &mut App, area: Rect) {
     // Keep track of what is visible
     let mut _visible_message_count = 0;
 
-    for (i, (role, text)) in app.rendered_messages.iter().enumerate() {
+    for (i, (role, text)) in session.rendered_messages.iter().enumerate() {
         let message_height = text.height() + 2; // +2 for role line and separator
 
         // Check if this message is visible in the viewport
@@ -128,7 +202,7 @@ fn render_messages(f: &mut Frame, app: &mut App, area: Rect) {
             }
 
             // Add separator between messages
-            if i < app.rendered_messages.len() - 1 && lines.len() < viewport_height {
+            if i < session.rendered_messages.len() - 1 && lines.len() < viewport_height {
                 lines.push(Line::from(vec![Span::styled(
                     "────────────────────────────────────────────────────────────────────────────────",
                     Style::default().fg(Color::DarkGray),
@@ -145,9 +219,9 @@ fn render_messages(f: &mut Frame, app: &mut App, area: Rect) {
     }
 
     // Show spinner if loading
-    if app.is_loading && lines.len() < viewport_height {
+    if session.is_loading && lines.len() < viewport_height {
         lines.push(Line::from(vec![Span::styled(
-            format!("{} Thinking...", SPINNER_FRAMES[app.spinner_frame]),
+            format!("{} Thinking...", SPINNER_FRAMES[spinner_frame]),
             Style::default()
                 .fg(Color::Blue)
                 .add_modifier(Modifier::BOLD),
@@ -164,17 +238,17 @@ fn render_messages(f: &mut Frame, app: &mut App, area: Rect) {
     render_enhanced_scrollbar(
         f.buffer_mut(),
         inner_area,
-        app.chat_scroll.position,
-        app.chat_scroll.max_position,
+        session.chat_scroll.position,
+        session.chat_scroll.max_position,
         viewport_height,
     );
 
     // Optional position indicator
-    if app.chat_scroll.max_position > 0 {
+    if session.chat_scroll.max_position > 0 {
         let position_text = format!(
             "{}/{}",
-            app.chat_scroll.position.saturating_add(1),
-            app.chat_scroll.max_position.saturating_add(1)
+            session.chat_scroll.position.saturating_add(1),
+            session.chat_scroll.max_position.saturating_add(1)
         );
 
         let position_widget = Paragraph::new(Span::styled(
@@ -188,14 +262,154 @@ fn render_messages(f: &mut Frame, app: &mut App, area: Rect) {
     }
 }
 
-/// Render input field
-fn render_input(f: &mut Frame, app: &mut App, area: Rect) {
-    let input_block = Block::default().borders(Borders::ALL).title(Span::styled(
-        "Input",
+/// Border style of a panel, highlighted when it has the focus
+fn focus_border_style(app: &App, panel: Focus) -> Style {
+    if app.focus == panel {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    }
+}
+
+/// Render the sources panel, listing the chunks retrieved for the latest prompt with their scores
+fn render_sources(f: &mut Frame, app: &mut App, area: Rect) {
+    let border_style = focus_border_style(app, Focus::Sources);
+    let session = app.session_mut();
+    let sources_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(
+            format!("Sources ({})", session.sources.len()),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    if session.sources.is_empty() {
+        let placeholder = Paragraph::new(Span::styled(
+            "Sources of the next answer appear here",
+            Style::default().fg(Color::DarkGray),
+        ))
+        .block(sources_block)
+        .wrap(Wrap { trim: true });
+        f.render_widget(placeholder, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = session
+        .sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let title = source
+                .heading_path
+                .clone()
+                .unwrap_or_else(|| source.url.clone());
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(format!("[{}] ", i + 1), Style::default().fg(Color::Yellow)),
+                    Span::styled(
+                        format!("{:.3} ", source.score),
+                        Style::default().fg(Color::Green),
+                    ),
+                    Span::raw(title),
+                ]),
+                Line::from(Span::styled(
+                    format!("    {}", source.website_domain),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+        })
+        .collect();
+
+    let list = List::new(items).block(sources_block).highlight_style(
         Style::default()
-            .fg(Color::Cyan)
+            .bg(Color::DarkGray)
             .add_modifier(Modifier::BOLD),
-    ));
+    );
+
+    f.render_stateful_widget(list, area, &mut session.sources_state);
+}
+
+/// Render the full text of the selected source in a popup
+fn render_source_viewer(f: &mut Frame, app: &App, scroll: u16) {
+    let Some(source) = app.session().selected_source() else {
+        return;
+    };
+    let size = f.area();
+    let popup_area = Rect::new(
+        size.width / 10,
+        size.height / 10,
+        size.width - size.width / 5,
+        size.height - size.height / 5,
+    );
+
+    f.render_widget(Clear, popup_area);
+
+    let viewer_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black))
+        .title(Span::styled(
+            source.url.as_str(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Line::from(vec![
+            Span::styled(" ↑↓ ", Style::default().fg(Color::Yellow)),
+            Span::styled("scroll", Style::default().fg(Color::Gray)),
+            Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+            Span::styled("o ", Style::default().fg(Color::Yellow)),
+            Span::styled("open in browser", Style::default().fg(Color::Gray)),
+            Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+            Span::styled("Esc ", Style::default().fg(Color::Yellow)),
+            Span::styled("close ", Style::default().fg(Color::Gray)),
+        ]));
+
+    let mut lines = Vec::new();
+    if let Some(heading_path) = &source.heading_path {
+        lines.push(Line::from(Span::styled(
+            heading_path.as_str(),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+    lines.push(Line::from(Span::styled(
+        format!("Score: {:.3}", source.score),
+        Style::default().fg(Color::Green),
+    )));
+    lines.push(Line::default());
+    lines.extend(source.text.lines().map(Line::from));
+
+    let text = Paragraph::new(lines)
+        .block(viewer_block)
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+
+    f.render_widget(text, popup_area);
+}
+
+/// Render input field
+fn render_input(f: &mut Frame, app: &mut App, area: Rect) {
+    // Show the query while searching the prompt history backwards
+    let title = match &app.history_search {
+        Some(search) if search.found.is_none() && !search.query.is_empty() => {
+            format!("Reverse search (no match): {}", search.query)
+        }
+        Some(search) => format!("Reverse search: {}", search.query),
+        None => "Input".to_string(),
+    };
+    let input_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(focus_border_style(app, Focus::Input))
+        .title(Span::styled(
+            title,
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
 
     f.render_widget(input_block.clone(), area);
     let inner_area = input_block.inner(area);
@@ -365,26 +579,51 @@ pub fn render_popup(f: &mut Frame, title: &str, text: &str) {
 }
 
 /// Render command help bar
-fn render_command_help(f: &mut Frame, area: Rect) {
+fn render_command_help(f: &mut Frame, app: &App, area: Rect) {
     // Create a background block with subtle border
     let help_block = Block::default().style(Style::default().bg(Color::Black));
 
     let inner_area = help_block.inner(area);
 
     // Add status bar content with Claude Code-inspired styling
-    let help_text = Line::from(vec![
+    let mut help_spans = vec![
         Span::styled("Alt+Enter ", Style::default().fg(Color::Yellow)),
         Span::styled("for newline", Style::default().fg(Color::Gray)),
         Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+        Span::styled("Tab ", Style::default().fg(Color::Yellow)),
+        Span::styled("focus", Style::default().fg(Color::Gray)),
+        Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+        Span::styled("Alt+arrows ", Style::default().fg(Color::Yellow)),
+        Span::styled("resize", Style::default().fg(Color::Gray)),
+        Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+        Span::styled("Ctrl+T ", Style::default().fg(Color::Yellow)),
+        Span::styled("new tab", Style::default().fg(Color::Gray)),
+        Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+        Span::styled("Ctrl+Tab ", Style::default().fg(Color::Yellow)),
+        Span::styled("switch", Style::default().fg(Color::Gray)),
+        Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+        Span::styled("Ctrl+R ", Style::default().fg(Color::Yellow)),
+        Span::styled("history", Style::default().fg(Color::Gray)),
+        Span::styled(" · ", Style::default().fg(Color::DarkGray)),
         Span::styled("Ctrl+↑↓ ", Style::default().fg(Color::Yellow)),
         Span::styled("to scroll", Style::default().fg(Color::Gray)),
         Span::styled(" · ", Style::default().fg(Color::DarkGray)),
         Span::styled("Ctrl+Home/End ", Style::default().fg(Color::Yellow)),
         Span::styled("for top/bottom", Style::default().fg(Color::Gray)),
         Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+    ];
+    if app.rag_enabled {
+        help_spans.extend([
+            Span::styled("Ctrl+S ", Style::default().fg(Color::Yellow)),
+            Span::styled("sources", Style::default().fg(Color::Gray)),
+            Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+        ]);
+    }
+    help_spans.extend([
         Span::styled("Esc ", Style::default().fg(Color::Yellow)),
         Span::styled("to exit", Style::default().fg(Color::Gray)),
     ]);
+    let help_text = Line::from(help_spans);
 
     let help = Paragraph::new(help_text)
         .style(Style::default().bg(Color::Black))